@@ -0,0 +1,16 @@
+//! 构建脚本：启用 grpc feature 时由 proto/hub.proto 生成 tonic 服务端 / 客户端代码（使用内置 protoc，可被 PROTOC 环境变量覆盖）
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/hub.proto");
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc =
+                protoc_bin_vendored::protoc_bin_path().expect("vendored protoc is unavailable on this platform");
+            std::env::set_var("PROTOC", protoc);
+        }
+        tonic_build::compile_protos("proto/hub.proto").expect("failed to compile proto/hub.proto");
+    }
+}
@@ -8,15 +8,18 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, VectorBackend};
 use crate::core::{AgentBuilder, AgentComponents, AgentError};
 use crate::llm::create_embedder_from_config;
 use crate::memory::{
-    assistant_memory_root, ConsolidateResult, FileLongTerm, InMemoryLongTerm, InMemoryVectorLongTerm,
+    ConsolidateResult, FileLongTerm, InMemoryLongTerm, InMemoryVectorLongTerm,
     list_daily_logs_for_llm, lessons_path, long_term_path, memory_root, preferences_path,
-    procedural_path, vector_snapshot_path, LongTermMemory, Message,
+    procedural_path, vector_snapshot_path, episodes_path, graph_path, EpisodicMemory, GraphMemory,
+    LongTermMemory, MemoryScope, Message, SqliteVectorLongTerm,
+};
+use crate::react::{
+    react_loop_v2, resume_react_loop, ContextManager, Planner, ReactCheckpoint, ReactEvent, ReactLimits, ReactSession,
 };
-use crate::react::{react_loop, ContextManager, Planner, ReactEvent};
 use crate::skills::SkillSelector;
 use tokio::sync::mpsc;
 
@@ -40,20 +43,30 @@ pub fn create_agent_components_with_prompt(
         .build_components()
 }
 
-/// 创建可共享的向量长期记忆（带快照路径，启动时加载、可定期 save_snapshot）；未启用或无法创建 embedder 时返回 None
+/// 创建可共享的向量长期记忆（带快照路径，启动时加载、可定期 flush）；未启用或无法创建 embedder 时返回 None
 pub fn create_shared_vector_long_term(
     workspace: &Path,
     cfg: &AppConfig,
-) -> Option<Arc<InMemoryVectorLongTerm>> {
+) -> Option<Arc<dyn LongTermMemory>> {
     create_vector_long_term_for_assistant(workspace, cfg, None)
 }
 
-/// 为指定助手创建独立的向量长期记忆（memory/{assistant_id}/vector_snapshot.json）
+/// 为指定助手创建独立的向量长期记忆（等价于 MemoryScope::for_assistant）
 pub fn create_vector_long_term_for_assistant(
     workspace: &Path,
     cfg: &AppConfig,
     assistant_id: Option<&str>,
-) -> Option<Arc<InMemoryVectorLongTerm>> {
+) -> Option<Arc<dyn LongTermMemory>> {
+    create_vector_long_term_for_scope(workspace, cfg, &MemoryScope::for_assistant(assistant_id))
+}
+
+/// 按作用域创建向量长期记忆；后端由 [memory].vector_backend 决定：
+/// memory → {scope 根目录}/vector_snapshot.json；sqlite / pgvector → 以 scope.vector_namespace() 为 namespace 的共享表
+pub fn create_vector_long_term_for_scope(
+    workspace: &Path,
+    cfg: &AppConfig,
+    scope: &MemoryScope,
+) -> Option<Arc<dyn LongTermMemory>> {
     if !cfg.memory.vector_enabled {
         return None;
     }
@@ -67,17 +80,84 @@ pub fn create_vector_long_term_for_assistant(
         &cfg.memory.embedding_model,
         api_key.as_deref(),
     )?;
-    let root = match assistant_id.filter(|s| !s.is_empty()) {
-        Some(id) => crate::memory::assistant_memory_root(workspace, id),
-        None => memory_root(workspace),
-    };
+    // 旧版目录仍在使用时，共享表后端的 namespace 随之沿用旧名
+    let scope = scope.resolve(workspace);
+    let root = scope.root(workspace);
     std::fs::create_dir_all(&root).ok();
     let snapshot_path = vector_snapshot_path(&root);
-    Some(Arc::new(InMemoryVectorLongTerm::new_with_persistence(
-        embedder,
-        2000,
-        Some(snapshot_path),
-    )))
+    let namespace = scope.vector_namespace();
+    // 嵌入失败时降级为 long-term.md 关键词检索
+    let keyword_fallback: Arc<dyn LongTermMemory> = Arc::new(
+        FileLongTerm::new(long_term_path(&root), 2000).with_decay(cfg.memory.decay.clone().into()),
+    );
+    match cfg.memory.vector_backend {
+        VectorBackend::Memory => Some(Arc::new(
+            InMemoryVectorLongTerm::new_with_persistence(embedder, 2000, Some(snapshot_path))
+                .with_decay(cfg.memory.decay.clone().into())
+                .with_keyword_fallback(keyword_fallback),
+        )),
+        VectorBackend::Sqlite => {
+            let db_path = cfg
+                .memory
+                .vector_db_path
+                .clone()
+                .unwrap_or_else(|| workspace.join(".bee/conversations.db"));
+            match SqliteVectorLongTerm::new(&db_path, embedder, &namespace, 2000) {
+                Ok(lt) => {
+                    let lt = lt.with_keyword_fallback(keyword_fallback);
+                    // 首次启用时从旧 JSON 快照迁移
+                    if lt.is_empty() && snapshot_path.exists() {
+                        if let Err(e) = lt.import_snapshot(&snapshot_path) {
+                            tracing::warn!("vector snapshot migration failed: {}", e);
+                        }
+                    }
+                    Some(Arc::new(lt))
+                }
+                Err(e) => {
+                    tracing::warn!("sqlite vector long-term unavailable ({}), falling back to file", e);
+                    None
+                }
+            }
+        }
+        #[cfg(feature = "pgvector")]
+        VectorBackend::Pgvector => {
+            let Some(url) = cfg.memory.pgvector_url.clone() else {
+                tracing::warn!("vector_backend = \"pgvector\" requires memory.pgvector_url; falling back to file");
+                return None;
+            };
+            let connected = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(crate::memory::PgVectorLongTerm::connect(
+                    &url, embedder, &namespace, 2000,
+                ))
+            });
+            match connected {
+                Ok(lt) => {
+                    let lt = lt.with_keyword_fallback(keyword_fallback);
+                    if snapshot_path.exists() {
+                        let migrated = tokio::task::block_in_place(|| {
+                            tokio::runtime::Handle::current().block_on(lt.import_snapshot(&snapshot_path))
+                        });
+                        match migrated {
+                            Ok(_) => {
+                                let _ = std::fs::rename(&snapshot_path, snapshot_path.with_extension("json.migrated"));
+                            }
+                            Err(e) => tracing::warn!("vector snapshot migration failed: {}", e),
+                        }
+                    }
+                    Some(Arc::new(lt))
+                }
+                Err(e) => {
+                    tracing::warn!("pgvector long-term unavailable ({}), falling back to file", e);
+                    None
+                }
+            }
+        }
+        #[cfg(not(feature = "pgvector"))]
+        VectorBackend::Pgvector => {
+            tracing::warn!("vector_backend = \"pgvector\" requires the pgvector feature; falling back to file");
+            None
+        }
+    }
 }
 
 /// 创建带长期记忆的 ContextManager。
@@ -90,7 +170,7 @@ pub fn create_context_with_long_term(
     cfg: &AppConfig,
     max_turns: usize,
     workspace: Option<&Path>,
-    shared_vector_long_term: Option<Arc<InMemoryVectorLongTerm>>,
+    shared_vector_long_term: Option<Arc<dyn LongTermMemory>>,
 ) -> ContextManager {
     create_context_with_long_term_for_assistant(
         cfg,
@@ -106,9 +186,28 @@ pub fn create_context_with_long_term_for_assistant(
     cfg: &AppConfig,
     max_turns: usize,
     workspace: Option<&Path>,
-    shared_vector_long_term: Option<Arc<InMemoryVectorLongTerm>>,
+    shared_vector_long_term: Option<Arc<dyn LongTermMemory>>,
     assistant_id: Option<&str>,
 ) -> ContextManager {
+    create_context_for_scope(
+        cfg,
+        max_turns,
+        workspace,
+        shared_vector_long_term,
+        &MemoryScope::for_assistant(assistant_id),
+    )
+}
+
+/// 按记忆作用域（user / assistant / namespace）创建 ContextManager：
+/// lessons / procedural / preferences / long-term 均位于 scope.root(workspace) 下，向量后端使用 scope.vector_namespace()
+pub fn create_context_for_scope(
+    cfg: &AppConfig,
+    max_turns: usize,
+    workspace: Option<&Path>,
+    shared_vector_long_term: Option<Arc<dyn LongTermMemory>>,
+    scope: &MemoryScope,
+) -> ContextManager {
+    let scope_label = scope.vector_namespace();
     let (long_term, lessons_path_opt, procedural_path_opt, preferences_path_opt): (
         Arc<dyn crate::memory::LongTermMemory>,
         Option<std::path::PathBuf>,
@@ -116,51 +215,30 @@ pub fn create_context_with_long_term_for_assistant(
         Option<std::path::PathBuf>,
     ) = match workspace {
         Some(w) => {
-            let root = match assistant_id.filter(|s| !s.is_empty()) {
-                Some(id) => assistant_memory_root(w, id),
-                None => memory_root(w),
-            };
+            let root = scope.root(w);
             std::fs::create_dir_all(&root).ok();
             let lessons = Some(lessons_path(&root));
             let procedural = Some(procedural_path(&root));
             let preferences = Some(preferences_path(&root));
             let lt: Arc<dyn crate::memory::LongTermMemory> = if cfg.memory.vector_enabled {
                 if let Some(shared) = shared_vector_long_term {
+                    tracing::info!("long-term memory: vector (scope: {})", scope_label);
+                    shared
+                } else if let Some(vector) = create_vector_long_term_for_scope(w, cfg, scope) {
                     tracing::info!(
-                        "long-term memory: vector (assistant: {})",
-                        assistant_id.unwrap_or("default")
+                        "long-term memory: vector (scope: {}, model {}, backend {:?})",
+                        scope_label,
+                        cfg.memory.embedding_model,
+                        cfg.memory.vector_backend
                     );
-                    shared
+                    vector
                 } else {
-                    let api_key = cfg
-                        .memory
-                        .embedding_api_key
-                        .clone()
-                        .or_else(|| std::env::var("OPENAI_API_KEY").ok());
-                    if let Some(embedder) = create_embedder_from_config(
-                        cfg.memory.embedding_base_url.as_deref().or(cfg.llm.base_url.as_deref()),
-                        &cfg.memory.embedding_model,
-                        api_key.as_deref(),
-                    ) {
-                        tracing::info!(
-                            "long-term memory: vector (assistant: {}, model {})",
-                            assistant_id.unwrap_or("default"),
-                            cfg.memory.embedding_model
-                        );
-                        let snapshot = vector_snapshot_path(&root);
-                        Arc::new(InMemoryVectorLongTerm::new_with_persistence(
-                            embedder,
-                            2000,
-                            Some(snapshot),
-                        ))
-                    } else {
-                        let path = long_term_path(&root);
-                        Arc::new(FileLongTerm::new(path, 2000))
-                    }
+                    let path = long_term_path(&root);
+                    Arc::new(FileLongTerm::new(path, 2000).with_decay(cfg.memory.decay.clone().into()))
                 }
             } else {
                 let path = long_term_path(&root);
-                Arc::new(FileLongTerm::new(path, 2000))
+                Arc::new(FileLongTerm::new(path, 2000).with_decay(cfg.memory.decay.clone().into()))
             };
             (lt, lessons, procedural, preferences)
         }
@@ -169,7 +247,10 @@ pub fn create_context_with_long_term_for_assistant(
     let mut ctx = ContextManager::new(max_turns)
         .with_long_term(long_term)
         .with_auto_lesson_on_hallucination(cfg.evolution.auto_lesson_on_hallucination)
-        .with_record_tool_success(cfg.evolution.record_tool_success);
+        .with_record_tool_success(cfg.evolution.record_tool_success)
+        .with_compaction_policy(cfg.memory.compaction.clone().into())
+        .with_observation_summary(cfg.tools.observation_summary.effective_threshold())
+        .with_scope(scope.clone());
     if let Some(p) = lessons_path_opt {
         ctx = ctx.with_lessons_path(p);
     }
@@ -179,6 +260,17 @@ pub fn create_context_with_long_term_for_assistant(
     if let Some(p) = preferences_path_opt {
         ctx = ctx.with_preferences_path(p);
     }
+    if let Some(w) = workspace {
+        let root = scope.root(w);
+        ctx = ctx
+            .with_memory_root(root.clone())
+            .with_persistence_db(w.join(".bee/conversations.db"))
+            .with_artifacts_dir(w.join("artifacts"))
+            .with_episodic(Arc::new(EpisodicMemory::new(episodes_path(&root), 500)));
+        if cfg.memory.graph_enabled {
+            ctx = ctx.with_graph(Arc::new(GraphMemory::new(graph_path(&root))));
+        }
+    }
     ctx
 }
 
@@ -186,7 +278,7 @@ pub fn create_context_with_long_term_for_assistant(
 pub fn create_context_default(
     max_turns: usize,
     workspace: Option<&Path>,
-    shared_vector_long_term: Option<Arc<InMemoryVectorLongTerm>>,
+    shared_vector_long_term: Option<Arc<dyn LongTermMemory>>,
 ) -> ContextManager {
     let cfg = crate::config::AppConfig::default();
     create_context_with_long_term(&cfg, max_turns, workspace, shared_vector_long_term)
@@ -198,13 +290,21 @@ pub async fn consolidate_memory_with_llm(
     workspace: &Path,
     since_days: u32,
 ) -> Result<ConsolidateResult, AgentError> {
-    let root = memory_root(workspace);
-    let list = list_daily_logs_for_llm(&root, since_days)
+    consolidate_root_with_llm(planner, &memory_root(workspace), since_days).await
+}
+
+/// 对指定记忆根目录（全局 memory/ 或某助手的 memory/{assistant_id}/）做 LLM 摘要整理
+pub async fn consolidate_root_with_llm(
+    planner: &Planner,
+    root: &Path,
+    since_days: u32,
+) -> Result<ConsolidateResult, AgentError> {
+    let list = list_daily_logs_for_llm(root, since_days)
         .map_err(|e| AgentError::ConfigError(e.to_string()))?;
     if list.is_empty() {
         return Ok(ConsolidateResult::default());
     }
-    let path = long_term_path(&root);
+    let path = long_term_path(root);
     let lt = FileLongTerm::new(path, 2000);
     let mut dates_processed = Vec::new();
     for (date, content) in list {
@@ -236,23 +336,76 @@ pub async fn process_message(
     context: &mut ContextManager,
     user_input: &str,
     allowed_tools: Option<&[String]>,
+) -> Result<String, AgentError> {
+    process_message_with_limits(components, context, user_input, allowed_tools, components.react_limits()).await
+}
+
+/// 同 process_message，使用指定的步数与时限（助手或请求级覆盖）
+pub async fn process_message_with_limits(
+    components: &AgentComponents,
+    context: &mut ContextManager,
+    user_input: &str,
+    allowed_tools: Option<&[String]>,
+    limits: ReactLimits,
 ) -> Result<String, AgentError> {
     let cancel_token = tokio_util::sync::CancellationToken::new();
-    let result = react_loop(
+    let mut session = ReactSession::new(
         &components.planner,
         &components.executor,
         &components.recovery,
-        context,
-        user_input,
-        None,
-        None,
-        cancel_token,
-        components.critic.as_ref(),
-        Some(&components.task_scheduler),
-        None,
-        allowed_tools,
+        cancel_token.clone(),
+    )
+    .with_task_scheduler(&components.task_scheduler)
+    .with_limits(limits)
+    .with_guardrails(components.guardrails.as_ref());
+    if let Some(critic) = components.critic.as_ref() {
+        session = session.with_critic(critic);
+    }
+    if let Some(tools) = allowed_tools {
+        session = session.with_allowed_tools(tools);
+    }
+    // 无事件流，不做卡住检测；仅登记到看门狗，以便在运行列表中查看与取消
+    let _run = components.watchdog.register("default", &cancel_token);
+    let result = react_loop_v2(&session, context, user_input).await?;
+    Ok(result.response)
+}
+
+/// 从检查点继续中断的任务（无 stream），返回最终回复文本
+/// system_prompt_override / allowed_tools / assistant_id / limits 与 process_message_stream 相同。
+pub async fn resume_task(
+    components: &AgentComponents,
+    context: &mut ContextManager,
+    checkpoint: ReactCheckpoint,
+    system_prompt_override: Option<&str>,
+    allowed_tools: Option<&[String]>,
+    assistant_id: Option<&str>,
+    limits: ReactLimits,
+) -> Result<String, AgentError> {
+    let mut session = ReactSession::new(
+        &components.planner,
+        &components.executor,
+        &components.recovery,
+        tokio_util::sync::CancellationToken::new(),
     )
-    .await?;
+    .with_task_scheduler(&components.task_scheduler)
+    .with_limits(limits)
+    .with_guardrails(components.guardrails.as_ref());
+    if let Some(critic) = components.critic.as_ref() {
+        session = session.with_critic(critic);
+    }
+    if let Some(prompt) = system_prompt_override {
+        session = session.with_system_prompt(prompt);
+    }
+    if let Some(tools) = allowed_tools {
+        session = session.with_allowed_tools(tools);
+    }
+    let long_term = context.long_term.clone();
+    let result = crate::tools::CURRENT_ASSISTANT_ID
+        .scope(
+            assistant_id.map(str::to_string),
+            crate::tools::CURRENT_LONG_TERM.scope(long_term, resume_react_loop(&session, context, checkpoint)),
+        )
+        .await?;
     Ok(result.response)
 }
 
@@ -261,7 +414,8 @@ pub async fn process_message(
 /// planner_override：可切换模型时传入该模型的 Planner，否则用 components 默认。
 /// allowed_tools：该智能体可用的工具名列表，None 或空表示全部。
 /// assistant_id：当前助手 id，用于 send 工具等；web 多助手时必传。
-#[allow(unused_variables)]
+/// limits：步数与时限，一般为 components.react_limits() 叠加助手或请求级覆盖。
+#[allow(unused_variables, clippy::too_many_arguments)]
 pub async fn process_message_stream(
     components: &AgentComponents,
     context: &mut ContextManager,
@@ -271,71 +425,42 @@ pub async fn process_message_stream(
     planner_override: Option<&Planner>,
     allowed_tools: Option<&[String]>,
     assistant_id: Option<&str>,
+    limits: ReactLimits,
 ) -> Result<String, AgentError> {
     let cancel_token = tokio_util::sync::CancellationToken::new();
     let planner = planner_override.unwrap_or(&components.planner);
 
-    let result = {
-        #[cfg(feature = "web")]
-        {
-            if let Some(aid) = assistant_id {
-                crate::tools::CURRENT_ASSISTANT_ID
-                    .scope(Some(aid.to_string()), async {
-                        react_loop(
-                            planner,
-                            &components.executor,
-                            &components.recovery,
-                            context,
-                            user_input,
-                            None,
-                            Some(&event_tx),
-                            cancel_token,
-                            components.critic.as_ref(),
-                            Some(&components.task_scheduler),
-                            system_prompt_override,
-                            allowed_tools,
-                        )
-                        .await
-                    })
-                    .await
-            } else {
-                react_loop(
-                    planner,
-                    &components.executor,
-                    &components.recovery,
-                    context,
-                    user_input,
-                    None,
-                    Some(&event_tx),
-                    cancel_token,
-                    components.critic.as_ref(),
-                    Some(&components.task_scheduler),
-                    system_prompt_override,
-                    allowed_tools,
-                )
-                .await
-            }
-        }
-        #[cfg(not(feature = "web"))]
-        {
-            react_loop(
-                planner,
-                &components.executor,
-                &components.recovery,
-                context,
-                user_input,
-                None,
-                Some(&event_tx),
-                cancel_token,
-                components.critic.as_ref(),
-                Some(&components.task_scheduler),
-                system_prompt_override,
-                allowed_tools,
-            )
-            .await
-        }
-    }?;
-    Ok(result.response)
+    // 事件经看门狗转发到 event_tx：长时间无事件时取消循环并记录教训
+    let (watched_tx, watched_rx) = mpsc::unbounded_channel::<ReactEvent>();
+    let mut session = ReactSession::new(planner, &components.executor, &components.recovery, cancel_token.clone())
+        .with_task_scheduler(&components.task_scheduler)
+        .with_event_tx(&watched_tx)
+        .with_limits(limits)
+        .with_guardrails(components.guardrails.as_ref());
+    if let Some(critic) = components.critic.as_ref() {
+        session = session.with_critic(critic);
+    }
+    if let Some(prompt) = system_prompt_override {
+        session = session.with_system_prompt(prompt);
+    }
+    if let Some(tools) = allowed_tools {
+        session = session.with_allowed_tools(tools);
+    }
+    // 当前会话长期记忆：doc_read 的 rag 选项写入
+    let long_term = context.long_term.clone();
+    // 当前助手 id：send / create 工具与按助手的工具策略读取
+    let run = crate::tools::CURRENT_ASSISTANT_ID.scope(
+        assistant_id.map(str::to_string),
+        crate::tools::CURRENT_LONG_TERM.scope(long_term, react_loop_v2(&session, context, user_input)),
+    );
+    let result = components
+        .watchdog
+        .supervise(assistant_id.unwrap_or("default"), &cancel_token, watched_rx, Some(&event_tx), run)
+        .await;
+    if let Err(AgentError::Stalled { ref activity, idle_secs }) = result {
+        context.append_stall_lesson(activity, idle_secs);
+    }
+    Ok(result?.response)
 }
 
 /// 按需选择技能并处理消息（带技能增强的流式处理）
@@ -384,6 +509,7 @@ pub async fn process_message_with_skills(
         planner_override,
         allowed_tools,
         None,
+        components.react_limits(),
     )
     .await
 }
@@ -0,0 +1,382 @@
+//! 质量基准（bee-bench）
+//!
+//! 固定任务集（文件操作、工作区检索问答、代码修复）逐个在独立的临时工作区中跑完整 ReAct 循环，
+//! 按任务配置的评分方式打分（精确匹配 / 包含 / 文件内容检查 / LLM 评审），输出 JSON 报告，
+//! 便于在不同版本之间客观比较 prompt、记忆与循环的改动。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::process_message;
+use crate::config::AppConfig;
+use crate::core::AgentBuilder;
+use crate::llm::LlmClient;
+use crate::memory::Message;
+use crate::react::ContextManager;
+
+/// 内置任务集路径（相对项目根目录）
+pub const DEFAULT_SUITE_PATH: &str = "config/bench/suite.toml";
+
+/// 判定通过的最低得分
+pub const PASS_SCORE: f32 = 0.7;
+
+/// 任务类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskCategory {
+    FileOps,
+    SearchQa,
+    Coding,
+}
+
+/// 评分方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Scorer {
+    /// 回复（规范化后）与 expected 完全一致，或回复最后一行与之一致
+    ExactMatch { expected: String },
+    /// 回复包含全部关键词（不区分大小写），按命中比例给分
+    Contains { expected: Vec<String> },
+    /// 任务结束后工作区文件包含全部片段，按命中比例给分
+    FileContains { path: String, expected: Vec<String> },
+    /// 由评审模型按 rubric 打 0~10 分
+    LlmJudge { rubric: String },
+}
+
+/// 单个基准任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchTask {
+    pub id: String,
+    pub category: TaskCategory,
+    pub prompt: String,
+    /// 任务开始前写入工作区的文件（相对路径 -> 内容）
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    pub scorer: Scorer,
+}
+
+/// 任务集（TOML：[[tasks]]）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchSuite {
+    #[serde(default)]
+    pub tasks: Vec<BenchTask>,
+}
+
+impl BenchSuite {
+    pub fn from_toml(content: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// 只保留 id 以 prefix 开头或类别名等于 prefix 的任务
+    pub fn filter(mut self, prefix: &str) -> Self {
+        self.tasks.retain(|t| {
+            t.id.starts_with(prefix)
+                || serde_json::to_value(t.category).ok().and_then(|v| v.as_str().map(|c| c == prefix)) == Some(true)
+        });
+        self
+    }
+}
+
+/// 单个任务结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskResult {
+    pub id: String,
+    pub category: TaskCategory,
+    pub score: f32,
+    pub passed: bool,
+    pub answer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 类别汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategorySummary {
+    pub tasks: usize,
+    pub passed: usize,
+    pub mean_score: f32,
+}
+
+/// 基准报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub model: String,
+    pub provider: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub tasks: Vec<TaskResult>,
+    pub by_category: BTreeMap<String, CategorySummary>,
+    pub mean_score: f32,
+    pub pass_rate: f32,
+}
+
+impl BenchReport {
+    fn summarize(model: &str, provider: &str, started_at: String, duration: Duration, tasks: Vec<TaskResult>) -> Self {
+        let mut by_category: BTreeMap<String, CategorySummary> = BTreeMap::new();
+        for t in &tasks {
+            let key = serde_json::to_value(t.category)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            let entry = by_category.entry(key).or_default();
+            entry.tasks += 1;
+            entry.passed += t.passed as usize;
+            entry.mean_score += t.score;
+        }
+        for s in by_category.values_mut() {
+            s.mean_score /= s.tasks.max(1) as f32;
+        }
+        let n = tasks.len().max(1) as f32;
+        Self {
+            model: model.to_string(),
+            provider: provider.to_string(),
+            started_at,
+            duration_ms: duration.as_millis() as u64,
+            mean_score: tasks.iter().map(|t| t.score).sum::<f32>() / n,
+            pass_rate: tasks.iter().filter(|t| t.passed).count() as f32 / n,
+            by_category,
+            tasks,
+        }
+    }
+}
+
+/// 规范化比较用文本：去首尾空白与结尾标点、折叠空白、转小写、去掉反引号
+fn normalize(s: &str) -> String {
+    s.replace('`', "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['.', '。', '!', '！'])
+        .to_lowercase()
+}
+
+/// 精确匹配：整段或最后一个非空行一致记 1 分
+pub fn score_exact(answer: &str, expected: &str) -> f32 {
+    let expected = normalize(expected);
+    let last_line = answer.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("");
+    if normalize(answer) == expected || normalize(last_line) == expected {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// 包含：按命中关键词比例给分
+pub fn score_contains(text: &str, expected: &[String]) -> f32 {
+    if expected.is_empty() {
+        return 1.0;
+    }
+    let text = text.to_lowercase();
+    let hits = expected.iter().filter(|e| text.contains(&e.to_lowercase())).count();
+    hits as f32 / expected.len() as f32
+}
+
+/// 解析评审输出中的第一个 0~10 数字，归一化到 0~1
+pub fn parse_judge_score(output: &str) -> Option<f32> {
+    let start = output.find(|c: char| c.is_ascii_digit())?;
+    let num: String = output[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let v: f32 = num.trim_end_matches('.').parse().ok()?;
+    Some((v / 10.0).clamp(0.0, 1.0))
+}
+
+async fn judge(llm: &dyn LlmClient, task: &BenchTask, rubric: &str, answer: &str) -> Result<f32, String> {
+    let messages = vec![
+        Message::system(
+            "You grade an AI assistant's answer. Reply with a single integer score from 0 (wrong) to 10 (fully correct) on the first line, then one sentence of justification.".to_string(),
+        ),
+        Message::user(format!(
+            "Task:\n{}\n\nRubric:\n{}\n\nAnswer:\n{}",
+            task.prompt, rubric, answer
+        )),
+    ];
+    let output = llm.complete(&messages).await.map_err(|e| e.to_string())?;
+    parse_judge_score(&output).ok_or_else(|| format!("unparseable judge output: {}", output))
+}
+
+/// 基准运行器：每个任务使用 runs_dir 下独立的工作区
+pub struct BenchRunner {
+    config: AppConfig,
+    runs_dir: PathBuf,
+    judge: Arc<dyn LlmClient>,
+    task_timeout: Duration,
+}
+
+impl BenchRunner {
+    pub fn new(config: AppConfig, runs_dir: PathBuf, judge: Arc<dyn LlmClient>) -> Self {
+        Self {
+            config,
+            runs_dir,
+            judge,
+            task_timeout: Duration::from_secs(300),
+        }
+    }
+
+    /// 单个任务的超时（默认 300 秒）
+    pub fn with_task_timeout(mut self, timeout: Duration) -> Self {
+        self.task_timeout = timeout;
+        self
+    }
+
+    fn prepare_workspace(&self, task: &BenchTask) -> anyhow::Result<PathBuf> {
+        let ws = self.runs_dir.join(&task.id);
+        if ws.exists() {
+            std::fs::remove_dir_all(&ws)?;
+        }
+        std::fs::create_dir_all(&ws)?;
+        for (rel, content) in &task.files {
+            let path = ws.join(rel);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)?;
+        }
+        Ok(ws.canonicalize().unwrap_or(ws))
+    }
+
+    async fn score(&self, task: &BenchTask, workspace: &Path, answer: &str) -> Result<f32, String> {
+        match &task.scorer {
+            Scorer::ExactMatch { expected } => Ok(score_exact(answer, expected)),
+            Scorer::Contains { expected } => Ok(score_contains(answer, expected)),
+            Scorer::FileContains { path, expected } => {
+                let content = std::fs::read_to_string(workspace.join(path)).unwrap_or_default();
+                Ok(score_contains(&content, expected))
+            }
+            Scorer::LlmJudge { rubric } => judge(self.judge.as_ref(), task, rubric, answer).await,
+        }
+    }
+
+    /// 运行单个任务
+    pub async fn run_task(&self, task: &BenchTask) -> TaskResult {
+        let started = Instant::now();
+        let mut result = TaskResult {
+            id: task.id.clone(),
+            category: task.category,
+            score: 0.0,
+            passed: false,
+            answer: String::new(),
+            error: None,
+            duration_ms: 0,
+        };
+        let workspace = match self.prepare_workspace(task) {
+            Ok(ws) => ws,
+            Err(e) => {
+                result.error = Some(format!("workspace setup failed: {}", e));
+                return result;
+            }
+        };
+        let components = AgentBuilder::new(self.config.clone(), workspace.clone())
+            .with_system_prompt_from_file()
+            .with_critic(false)
+            .with_skills(false)
+            .build_components();
+        let mut context = ContextManager::new(self.config.app.max_context_turns);
+        let run = process_message(&components, &mut context, &task.prompt, None);
+        match tokio::time::timeout(self.task_timeout, run).await {
+            Ok(Ok(answer)) => result.answer = answer,
+            Ok(Err(e)) => result.error = Some(e.to_string()),
+            Err(_) => result.error = Some(format!("timed out after {}s", self.task_timeout.as_secs())),
+        }
+        // 出错时仍对工作区评分（代码修复类任务可能已写好文件）
+        match self.score(task, &workspace, &result.answer).await {
+            Ok(score) => result.score = score,
+            Err(e) => {
+                result.error.get_or_insert(e);
+            }
+        }
+        result.passed = result.score >= PASS_SCORE;
+        result.duration_ms = started.elapsed().as_millis() as u64;
+        result
+    }
+
+    /// 依次运行全部任务并汇总；on_result 在每个任务完成后回调（用于打印进度）
+    pub async fn run(&self, suite: &BenchSuite, mut on_result: impl FnMut(&TaskResult)) -> BenchReport {
+        let started_at = chrono::Utc::now().to_rfc3339();
+        let started = Instant::now();
+        let mut results = Vec::with_capacity(suite.tasks.len());
+        for task in &suite.tasks {
+            let r = self.run_task(task).await;
+            on_result(&r);
+            results.push(r);
+        }
+        BenchReport::summarize(
+            &self.config.llm.model,
+            &self.config.llm.provider,
+            started_at,
+            started.elapsed(),
+            results,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_suite_and_scoring() {
+        let suite = BenchSuite::from_toml(
+            r#"
+[[tasks]]
+id = "file-count"
+category = "file_ops"
+prompt = "How many lines?"
+files = { "notes.txt" = "a\nb\n" }
+scorer = { type = "exact_match", expected = "2" }
+
+[[tasks]]
+id = "fix-add"
+category = "coding"
+prompt = "Fix add"
+scorer = { type = "file_contains", path = "src/lib.rs", expected = ["a + b"] }
+"#,
+        )
+        .unwrap();
+        assert_eq!(suite.tasks.len(), 2);
+        assert_eq!(suite.clone().filter("coding").tasks[0].id, "fix-add");
+        assert_eq!(suite.filter("file-").tasks.len(), 1);
+
+        assert_eq!(score_exact("The file has these lines.\n`2`.", "2"), 1.0);
+        assert_eq!(score_exact("3", "2"), 0.0);
+        assert_eq!(score_contains("Tokio and Axum", &["tokio".into(), "serde".into()]), 0.5);
+        assert_eq!(parse_judge_score("8\nMostly right."), Some(0.8));
+        assert_eq!(parse_judge_score("Score: 12"), Some(1.0));
+        assert_eq!(parse_judge_score("no idea"), None);
+
+        let tasks = vec![
+            TaskResult {
+                id: "a".into(),
+                category: TaskCategory::Coding,
+                score: 1.0,
+                passed: true,
+                answer: String::new(),
+                error: None,
+                duration_ms: 1,
+            },
+            TaskResult {
+                id: "b".into(),
+                category: TaskCategory::Coding,
+                score: 0.0,
+                passed: false,
+                answer: String::new(),
+                error: Some("x".into()),
+                duration_ms: 1,
+            },
+        ];
+        let report = BenchReport::summarize("m", "p", String::new(), Duration::ZERO, tasks);
+        assert_eq!(report.pass_rate, 0.5);
+        assert_eq!(report.by_category["coding"].passed, 1);
+        assert_eq!(report.by_category["coding"].mean_score, 0.5);
+    }
+}
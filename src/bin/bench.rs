@@ -0,0 +1,145 @@
+//! Bee Bench - 智能体质量回归基准
+//!
+//! 对配置的模型跑固定任务集（config/bench/suite.toml），按任务打分并写出 JSON 报告。
+//!
+//! 运行方式：
+//! ```bash
+//! cargo run --bin bee-bench -- --model gpt-4o-mini --out bench-report.json
+//! ```
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use bee::bench::{BenchRunner, BenchSuite, DEFAULT_SUITE_PATH};
+use bee::config::load_config;
+use bee::core::orchestrator::create_llm_from_config;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const USAGE: &str = "\
+Usage: bee-bench [options]
+  --suite <path>        task suite TOML (default: config/bench/suite.toml)
+  --model <name>        override [llm] model
+  --provider <name>     override [llm] provider
+  --judge-model <name>  model for llm_judge tasks (default: same as --model)
+  --filter <prefix>     only run tasks whose id starts with prefix, or a category (file_ops/search_qa/coding)
+  --timeout <secs>      per-task timeout (default: 300)
+  --out <path>          report path (default: workspace/bench/report-<time>.json)
+  --min-score <0..1>    exit with status 1 when the mean score is below this value";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env().add_directive("warn".parse().unwrap()))
+        .with(fmt::layer())
+        .init();
+
+    let mut suite_path = PathBuf::from(DEFAULT_SUITE_PATH);
+    let mut model = None;
+    let mut provider = None;
+    let mut judge_model = None;
+    let mut filter = None;
+    let mut timeout_secs = 300u64;
+    let mut out = None;
+    let mut min_score = None;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut it = args.iter();
+    while let Some(flag) = it.next() {
+        let mut value = || it.next().with_context(|| format!("{} requires a value\n{}", flag, USAGE));
+        match flag.as_str() {
+            "--suite" => suite_path = PathBuf::from(value()?),
+            "--model" => model = Some(value()?.clone()),
+            "--provider" => provider = Some(value()?.clone()),
+            "--judge-model" => judge_model = Some(value()?.clone()),
+            "--filter" => filter = Some(value()?.clone()),
+            "--timeout" => timeout_secs = value()?.parse().context("--timeout expects seconds")?,
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--min-score" => min_score = Some(value()?.parse::<f32>().context("--min-score expects a number")?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            other => bail!("unknown option {}\n{}", other, USAGE),
+        }
+    }
+
+    let mut cfg = load_config(None).unwrap_or_default();
+    if let Some(m) = model {
+        cfg.llm.model = m;
+    }
+    if let Some(p) = provider {
+        cfg.llm.provider = p;
+    }
+    let judge = match judge_model {
+        Some(m) => {
+            let mut judge_cfg = cfg.clone();
+            judge_cfg.llm.model = m;
+            create_llm_from_config(&judge_cfg)
+        }
+        None => create_llm_from_config(&cfg),
+    };
+
+    let mut suite = BenchSuite::load(&suite_path)
+        .with_context(|| format!("failed to load suite {}", suite_path.display()))?;
+    if let Some(ref f) = filter {
+        suite = suite.filter(f);
+    }
+    if suite.tasks.is_empty() {
+        bail!("no tasks to run");
+    }
+
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let bench_root = cfg
+        .app
+        .workspace_root
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("workspace"))
+        .join("bench");
+    let runs_dir = bench_root.join(&stamp);
+    let out = out.unwrap_or_else(|| bench_root.join(format!("report-{}.json", stamp)));
+
+    println!(
+        "bee-bench: {} tasks, model {} ({}), workspaces in {}",
+        suite.tasks.len(),
+        cfg.llm.model,
+        cfg.llm.provider,
+        runs_dir.display()
+    );
+    let runner = BenchRunner::new(cfg, runs_dir, judge).with_task_timeout(Duration::from_secs(timeout_secs));
+    let report = runner
+        .run(&suite, |r| {
+            println!(
+                "  {} {:<28} {:<10} score {:.2}  {:>6}ms{}",
+                if r.passed { "PASS" } else { "FAIL" },
+                r.id,
+                format!("{:?}", r.category),
+                r.score,
+                r.duration_ms,
+                r.error.as_deref().map(|e| format!("  ({})", e)).unwrap_or_default()
+            );
+        })
+        .await;
+
+    for (category, s) in &report.by_category {
+        println!("  {:<10} {}/{} passed, mean {:.2}", category, s.passed, s.tasks, s.mean_score);
+    }
+    println!(
+        "mean score {:.3}, pass rate {:.1}%",
+        report.mean_score,
+        report.pass_rate * 100.0
+    );
+
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&out, serde_json::to_string_pretty(&report)?)?;
+    println!("report written to {}", out.display());
+
+    if let Some(min) = min_score {
+        if report.mean_score < min {
+            bail!("mean score {:.3} is below --min-score {:.3}", report.mean_score, min);
+        }
+    }
+    Ok(())
+}
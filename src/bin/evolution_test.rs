@@ -10,7 +10,7 @@ use bee::{
     tools::{
         ToolExecutor, ToolRegistry, CatTool, LsTool, EchoTool, ShellTool, SearchTool,
         CodeReadTool, CodeGrepTool, CodeEditTool, CodeWriteTool,
-        TestRunTool, TestCheckTool, GitCommitTool,
+        TestRunTool, TestCheckTool, GitCommitTool, GithubTool,
     },
     evolution::{EvolutionLoop, EvolutionConfig},
 };
@@ -52,8 +52,11 @@ async fn main() -> anyhow::Result<()> {
     tools.register(TestRunTool::new(&project_root));
     tools.register(TestCheckTool::new(&project_root));
     tools.register(GitCommitTool::new(&project_root));
+    if cfg.tools.github.repo.is_some() || !cfg.tools.github.allowed_repos.is_empty() {
+        tools.register(GithubTool::new(&cfg.tools.github, &project_root));
+    }
 
-    let executor = ToolExecutor::new(tools, cfg.tools.tool_timeout_secs);
+    let executor = ToolExecutor::new(tools, cfg.tools.tool_timeout_secs).with_limits(&cfg.tools.limits);
     let executor = Arc::new(executor);
 
     let mut evolution_config = EvolutionConfig::from(cfg.evolution);
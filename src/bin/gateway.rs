@@ -6,10 +6,16 @@
 //! ```bash
 //! cargo run --bin bee-gateway --features gateway
 //! ```
+//!
+//! 启用 `discord` feature 并设置 DISCORD_BOT_TOKEN、DISCORD_APPLICATION_ID（可选 DISCORD_GUILD_ID）后，
+//! 同时接入 Discord（/ask、/task 斜杠命令）。
+//! 启用 `email` feature 并配置 [tools.email.spoke] 后，同时轮询该邮箱账户，按邮件线程对话。
+//! 启用 `mq` feature 并配置 [mq] 后，同时从 NATS / Redis Streams 消费请求并发布结果。
+//! 启用 `grpc` feature 后，同时在 GATEWAY_GRPC_BIND（默认 127.0.0.1:50051）提供 gRPC 接口（proto/hub.proto）。
 
 use std::path::PathBuf;
 
-use bee::config::load_config;
+use bee::config::{apply_safe_mode_flag, load_config};
 use bee::gateway::{Hub, HubConfig, RuntimeConfig};
 
 #[tokio::main]
@@ -21,6 +27,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if apply_safe_mode_flag(&args) {
+        tracing::info!("safe mode enabled: read-only tools only");
+    }
     let cfg = load_config(None).unwrap_or_default();
 
     let bind_addr = std::env::var("GATEWAY_BIND")
@@ -35,6 +45,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let task_db_path = workspace.join("gateway_tasks.db");
     let user_memory_dir = workspace.join("memory/users");
     
+    #[cfg(feature = "mq")]
+    let mq_section = cfg.mq.clone();
+    #[cfg(feature = "email")]
+    let email_spoke = bee::integrations::email::EmailSpoke::from_config(&cfg.tools.email);
+
     let hub_config = HubConfig {
         bind_addr: bind_addr.clone(),
         max_connections: 1000,
@@ -59,6 +74,49 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Press Ctrl+C to stop");
 
     hub.start().await?;
+    #[cfg(feature = "discord")]
+    if let Some(discord) = bee::integrations::discord::DiscordConfig::from_env() {
+        let spoke = std::sync::Arc::new(bee::integrations::discord::DiscordSpoke::new(discord));
+        if let Err(e) = hub.register_spoke(spoke).await {
+            tracing::warn!("Discord spoke disabled: {}", e);
+        }
+    }
+    #[cfg(feature = "email")]
+    match email_spoke {
+        Ok(Some(spoke)) => {
+            if let Err(e) = hub.register_spoke(std::sync::Arc::new(spoke)).await {
+                tracing::warn!("Email spoke disabled: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Email spoke disabled: {}", e),
+    }
+    #[cfg(feature = "mq")]
+    if let Some(mq) = mq_section {
+        let spoke = std::sync::Arc::new(bee::integrations::mq::QueueSpoke::new(mq));
+        if let Err(e) = hub.register_spoke(spoke).await {
+            tracing::warn!("Queue spoke disabled: {}", e);
+        }
+    }
+    #[cfg(feature = "grpc")]
+    {
+        let grpc_bind = std::env::var("GATEWAY_GRPC_BIND")
+            .unwrap_or_else(|_| bee::gateway::grpc::DEFAULT_BIND.to_string());
+        match grpc_bind.parse() {
+            Ok(addr) => {
+                let spoke = bee::gateway::grpc::GrpcSpoke::new(addr, std::sync::Arc::clone(hub.session_store()))
+                    .with_auth(std::sync::Arc::clone(hub.authenticator()))
+                    .with_tenancy(std::sync::Arc::clone(hub.tenancy()));
+                if let Err(e) = hub.register_spoke(std::sync::Arc::new(spoke)).await {
+                    tracing::warn!("gRPC spoke disabled: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("gRPC spoke disabled: invalid GATEWAY_GRPC_BIND {}: {}", grpc_bind, e),
+        }
+    }
+    // 后台任务完成通知与 watch 工具的文件监听
+    hub.start_notification_handler().await;
+    hub.start_file_watcher();
 
     tokio::signal::ctrl_c().await?;
 
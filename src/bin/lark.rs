@@ -17,6 +17,7 @@ async fn main() -> anyhow::Result<()> {
     use std::sync::Arc;
     use bee::agent::create_agent_components;
     use bee::config::load_config;
+    use bee::core::{ReminderStore, TaskScheduler};
     use bee::integrations::lark::{create_router, LarkState};
     use tokio::sync::RwLock;
     use tracing_subscriber::{fmt, prelude::*, EnvFilter};
@@ -30,6 +31,10 @@ async fn main() -> anyhow::Result<()> {
     let app_secret = std::env::var("LARK_APP_SECRET").expect("LARK_APP_SECRET must be set");
     let base_url = std::env::var("LARK_BASE_URL")
         .unwrap_or_else(|_| "https://open.feishu.cn".to_string());
+    let verification_token = std::env::var("LARK_VERIFICATION_TOKEN").ok().filter(|t| !t.is_empty());
+    if verification_token.is_none() {
+        tracing::warn!("LARK_VERIFICATION_TOKEN not set: callbacks are not authenticated and approval cards are disabled");
+    }
 
     let cfg = load_config(None).unwrap_or_default();
     let workspace = cfg
@@ -46,11 +51,23 @@ async fn main() -> anyhow::Result<()> {
         components,
         sessions: Arc::new(RwLock::new(HashMap::new())),
         processed_events: Arc::new(RwLock::new(HashSet::new())),
+        pending_approvals: Arc::new(RwLock::new(HashMap::new())),
         app_id,
         app_secret,
         base_url,
+        verification_token,
+        workspace,
     });
 
+    // remind 工具的到期提醒：推送回创建提醒的会话
+    match ReminderStore::open(&state.workspace) {
+        Ok(store) => {
+            let poll = std::time::Duration::from_secs(cfg.tools.remind.poll_secs);
+            TaskScheduler::spawn_reminders(Arc::new(store), state.clone(), poll);
+        }
+        Err(e) => tracing::warn!("reminders disabled: {}", e),
+    }
+
     let app = create_router(state);
 
     let port = std::env::var("LARK_PORT").unwrap_or_else(|_| "3001".to_string());
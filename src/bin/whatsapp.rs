@@ -20,6 +20,7 @@ async fn main() -> anyhow::Result<()> {
     use axum::Router;
     use bee::agent::create_agent_components;
     use bee::config::load_config;
+    use bee::core::{ReminderStore, TaskScheduler};
     use bee::integrations::whatsapp::{create_router, WhatsappState};
     use tokio::sync::RwLock;
     use tracing_subscriber::{fmt, prelude::*, EnvFilter};
@@ -50,8 +51,18 @@ async fn main() -> anyhow::Result<()> {
         sessions: Arc::new(RwLock::new(HashMap::new())),
         access_token,
         phone_number_id,
+        workspace,
     });
 
+    // remind 工具的到期提醒：推送回创建提醒的会话
+    match ReminderStore::open(&state.workspace) {
+        Ok(store) => {
+            let poll = std::time::Duration::from_secs(cfg.tools.remind.poll_secs);
+            TaskScheduler::spawn_reminders(Arc::new(store), state.clone(), poll);
+        }
+        Err(e) => tracing::warn!("reminders disabled: {}", e),
+    }
+
     let app = create_router(state);
 
     let addr = std::net::SocketAddr::from(([0, 0, 0, 0], 3000));
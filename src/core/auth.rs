@@ -0,0 +1,503 @@
+//! 认证与 API Key：bee-web 的 axum 中间件、网关 WebSocket 握手与 gRPC 拦截器共用
+//!
+//! - 静态 API Key：[[auth.keys]]，密钥从环境变量读取，按 Key 授予作用域；
+//! - 可选 OIDC / JWT：[auth.jwt]，HS 共享密钥或 JWKS 公钥（jwks_url，未设置时由 issuer 的
+//!   `.well-known/openid-configuration` 发现）校验签名、iss、aud 与有效期，作用域取自 scopes_claim 声明。
+//!
+//! 校验是同步的，便于在 WebSocket 握手回调中直接使用：JWKS 由 [`Authenticator::start_key_refresh`] 在后台定期拉取，
+//! 遇到未知 kid 时提前刷新。
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::config::{AuthSection, JwtSection};
+
+/// JWKS 定期刷新间隔
+const JWKS_REFRESH: Duration = Duration::from_secs(600);
+/// 未知 kid 触发刷新的最小间隔，避免伪造令牌反复触发拉取
+const JWKS_MIN_REFETCH: Duration = Duration::from_secs(30);
+
+/// 作用域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// 对话、会话、任务等日常接口
+    Chat,
+    /// 指标与诊断
+    Metrics,
+    /// 配置重载、技能 / 提示词 / 记忆管理；包含全部作用域
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Chat => "chat",
+            Scope::Metrics => "metrics",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "chat" => Some(Scope::Chat),
+            "metrics" => Some(Scope::Metrics),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+fn parse_scopes<'a>(items: impl IntoIterator<Item = &'a str>) -> HashSet<Scope> {
+    items.into_iter().filter_map(Scope::parse).collect()
+}
+
+const ANONYMOUS: &str = "anonymous";
+
+/// 凭据类型：派生用户 ID 时作为命名空间，同名的 API Key 与 JWT sub 不会归到同一用户
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrincipalKind {
+    /// 未启用认证
+    Anonymous,
+    /// [[auth.keys]] 静态 API Key
+    ApiKey,
+    /// [auth.jwt] 签发的令牌
+    Jwt,
+}
+
+impl PrincipalKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrincipalKind::Anonymous => ANONYMOUS,
+            PrincipalKind::ApiKey => "key",
+            PrincipalKind::Jwt => "jwt",
+        }
+    }
+}
+
+/// 通过认证的调用方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// API Key 名称或 JWT 的 sub
+    pub name: String,
+    pub kind: PrincipalKind,
+    pub scopes: HashSet<Scope>,
+}
+
+impl Principal {
+    /// 未启用认证时的调用方（拥有全部作用域）
+    pub fn anonymous() -> Self {
+        Self {
+            name: ANONYMOUS.to_string(),
+            kind: PrincipalKind::Anonymous,
+            scopes: HashSet::from([Scope::Admin]),
+        }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.kind == PrincipalKind::Anonymous
+    }
+
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+/// 认证错误：Missing / Invalid 对应 401，Forbidden 对应 403
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthError {
+    #[error("missing credentials")]
+    Missing,
+    #[error("invalid API key or token")]
+    Invalid,
+    #[error("missing scope: {0}")]
+    Forbidden(&'static str),
+}
+
+struct ApiKey {
+    name: String,
+    digest: [u8; 32],
+    scopes: HashSet<Scope>,
+}
+
+fn digest(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// 已拉取的 JWKS 公钥
+struct Jwk {
+    kid: Option<String>,
+    key: DecodingKey,
+}
+
+#[derive(Default)]
+struct JwksState {
+    keys: RwLock<Vec<Jwk>>,
+    last_fetch: Mutex<Option<Instant>>,
+}
+
+enum JwtKeys {
+    Secret(DecodingKey),
+    Jwks(Arc<JwksState>),
+}
+
+struct JwtVerifier {
+    issuer: Option<String>,
+    audience: Option<String>,
+    jwks_url: Option<String>,
+    scopes_claim: String,
+    default_scopes: HashSet<Scope>,
+    keys: JwtKeys,
+}
+
+/// 认证器：由 [auth] 段构造，密钥在构造时从环境变量读取
+pub struct Authenticator {
+    enabled: bool,
+    keys: Vec<ApiKey>,
+    jwt: Option<JwtVerifier>,
+    http: reqwest::Client,
+}
+
+fn read_env(name: &str) -> Option<String> {
+    match std::env::var(name) {
+        Ok(v) if !v.trim().is_empty() => Some(v.trim().to_string()),
+        _ => {
+            tracing::warn!("auth: env {} is not set", name);
+            None
+        }
+    }
+}
+
+impl From<&JwtSection> for JwtVerifier {
+    fn from(section: &JwtSection) -> Self {
+        let keys = match section.secret_env.as_deref().and_then(read_env) {
+            Some(secret) => JwtKeys::Secret(DecodingKey::from_secret(secret.as_bytes())),
+            None => JwtKeys::Jwks(Arc::new(JwksState::default())),
+        };
+        Self {
+            issuer: section.issuer.clone(),
+            audience: section.audience.clone(),
+            jwks_url: section.jwks_url.clone(),
+            scopes_claim: section.scopes_claim.clone(),
+            default_scopes: parse_scopes(section.default_scopes.iter().map(String::as_str)),
+            keys,
+        }
+    }
+}
+
+impl From<&AuthSection> for Authenticator {
+    fn from(section: &AuthSection) -> Self {
+        let keys: Vec<ApiKey> = section
+            .keys
+            .iter()
+            .filter_map(|k| {
+                let secret = read_env(&k.key_env)?;
+                Some(ApiKey {
+                    name: k.name.clone(),
+                    digest: digest(&secret),
+                    scopes: parse_scopes(k.scopes.iter().map(String::as_str)),
+                })
+            })
+            .collect();
+        let jwt = section.jwt.as_ref().map(JwtVerifier::from);
+        if section.enabled && keys.is_empty() && jwt.is_none() {
+            tracing::warn!("auth is enabled but no API key or JWT issuer is configured; all protected endpoints will reject requests");
+        }
+        Self {
+            enabled: section.enabled,
+            keys,
+            jwt,
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Authenticator {
+    /// 不校验的认证器（[auth] 未启用时的行为）
+    pub fn disabled() -> Self {
+        Self::from(&AuthSection::default())
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 校验凭据（API Key 或 JWT）；未启用时任何请求都视为拥有全部作用域
+    pub fn authenticate(&self, token: Option<&str>) -> Result<Principal, AuthError> {
+        if !self.enabled {
+            return Ok(Principal::anonymous());
+        }
+        let token = token
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or(AuthError::Missing)?;
+        let hashed = digest(token);
+        if let Some(key) = self.keys.iter().find(|k| k.digest == hashed) {
+            return Ok(Principal {
+                name: key.name.clone(),
+                kind: PrincipalKind::ApiKey,
+                scopes: key.scopes.clone(),
+            });
+        }
+        match &self.jwt {
+            Some(jwt) if token.matches('.').count() == 2 => self.verify_jwt(jwt, token),
+            _ => Err(AuthError::Invalid),
+        }
+    }
+
+    /// 校验凭据并要求作用域
+    pub fn authorize(&self, token: Option<&str>, scope: Scope) -> Result<Principal, AuthError> {
+        let principal = self.authenticate(token)?;
+        if principal.allows(scope) {
+            Ok(principal)
+        } else {
+            Err(AuthError::Forbidden(scope.as_str()))
+        }
+    }
+
+    fn verify_jwt(&self, jwt: &JwtVerifier, token: &str) -> Result<Principal, AuthError> {
+        let header = jsonwebtoken::decode_header(token).map_err(|_| AuthError::Invalid)?;
+        let (key, algorithms) = match &jwt.keys {
+            JwtKeys::Secret(key) => (key.clone(), vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]),
+            JwtKeys::Jwks(state) => {
+                // JWKS 只接受非对称算法，避免以公钥作为 HMAC 密钥的算法混淆
+                if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+                    return Err(AuthError::Invalid);
+                }
+                let found = {
+                    let keys = state.keys.read().unwrap_or_else(|e| e.into_inner());
+                    keys.iter()
+                        .find(|k| header.kid.is_none() || k.kid == header.kid)
+                        .map(|k| k.key.clone())
+                };
+                match found {
+                    Some(key) => (key, vec![header.alg]),
+                    None => {
+                        self.refresh_soon(jwt, state);
+                        return Err(AuthError::Invalid);
+                    }
+                }
+            }
+        };
+        let mut validation = Validation::new(algorithms[0]);
+        validation.algorithms = algorithms;
+        if let Some(iss) = &jwt.issuer {
+            validation.set_issuer(&[iss]);
+        }
+        match &jwt.audience {
+            Some(aud) => validation.set_audience(&[aud]),
+            None => validation.validate_aud = false,
+        }
+        let claims = jsonwebtoken::decode::<Value>(token, &key, &validation)
+            .map_err(|e| {
+                tracing::debug!("auth: JWT rejected: {}", e);
+                AuthError::Invalid
+            })?
+            .claims;
+        let mut scopes = match claims.get(&jwt.scopes_claim) {
+            Some(Value::String(s)) => parse_scopes(s.split_whitespace()),
+            Some(Value::Array(items)) => parse_scopes(items.iter().filter_map(|v| v.as_str())),
+            _ => HashSet::new(),
+        };
+        if scopes.is_empty() {
+            scopes = jwt.default_scopes.clone();
+        }
+        Ok(Principal {
+            name: claims.get("sub").and_then(|v| v.as_str()).unwrap_or("jwt").to_string(),
+            kind: PrincipalKind::Jwt,
+            scopes,
+        })
+    }
+
+    /// 未知 kid：若距上次拉取已超过最小间隔，在后台刷新 JWKS（本次请求仍被拒绝，客户端重试即可）
+    fn refresh_soon(&self, jwt: &JwtVerifier, state: &Arc<JwksState>) {
+        {
+            let mut last = state.last_fetch.lock().unwrap_or_else(|e| e.into_inner());
+            if last.is_some_and(|t| t.elapsed() < JWKS_MIN_REFETCH) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let (http, state) = (self.http.clone(), Arc::clone(state));
+        let (jwks_url, issuer) = (jwt.jwks_url.clone(), jwt.issuer.clone());
+        handle.spawn(async move {
+            if let Err(e) = fetch_jwks(&http, jwks_url.as_deref(), issuer.as_deref(), &state).await {
+                tracing::warn!("auth: JWKS refresh failed: {}", e);
+            }
+        });
+    }
+
+    /// 使用 JWKS 时在后台立即拉取一次并定期刷新；HS 共享密钥或未配置 JWT 时不做任何事
+    pub fn start_key_refresh(&self) {
+        let Some(jwt) = &self.jwt else {
+            return;
+        };
+        let JwtKeys::Jwks(state) = &jwt.keys else {
+            return;
+        };
+        if !self.enabled {
+            return;
+        }
+        let (http, state) = (self.http.clone(), Arc::clone(state));
+        let (jwks_url, issuer) = (jwt.jwks_url.clone(), jwt.issuer.clone());
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(JWKS_REFRESH);
+            loop {
+                interval.tick().await;
+                *state.last_fetch.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+                if let Err(e) = fetch_jwks(&http, jwks_url.as_deref(), issuer.as_deref(), &state).await {
+                    tracing::warn!("auth: JWKS refresh failed: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// 拉取 JWKS（jwks_url 未设置时先读 issuer 的 OIDC 发现文档）并替换缓存的公钥
+async fn fetch_jwks(
+    http: &reqwest::Client,
+    jwks_url: Option<&str>,
+    issuer: Option<&str>,
+    state: &JwksState,
+) -> Result<(), String> {
+    let url = match (jwks_url, issuer) {
+        (Some(url), _) => url.to_string(),
+        (None, Some(issuer)) => {
+            let discovery = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+            let doc: Value = http
+                .get(&discovery)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("{}: {}", discovery, e))?
+                .json()
+                .await
+                .map_err(|e| format!("{}: {}", discovery, e))?;
+            doc.get("jwks_uri")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| format!("{}: no jwks_uri", discovery))?
+                .to_string()
+        }
+        (None, None) => return Err("[auth.jwt] needs jwks_url, issuer or secret_env".to_string()),
+    };
+    let set: JwkSet = http
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("{}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("{}: {}", url, e))?;
+    let keys: Vec<Jwk> = set
+        .keys
+        .iter()
+        .filter_map(|jwk| {
+            Some(Jwk {
+                kid: jwk.common.key_id.clone(),
+                key: DecodingKey::from_jwk(jwk).ok()?,
+            })
+        })
+        .collect();
+    tracing::info!("auth: loaded {} JWKS key(s) from {}", keys.len(), url);
+    *state.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+    Ok(())
+}
+
+/// 从 Authorization 头取 Bearer 令牌
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiKeySection;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    #[test]
+    fn test_api_keys_and_jwt_scopes() {
+        std::env::set_var("BEE_TEST_AUTH_OPS", "ops-key");
+        std::env::set_var("BEE_TEST_AUTH_GRAFANA", "grafana-key");
+        std::env::set_var("BEE_TEST_AUTH_JWT", "jwt-secret");
+        let section = AuthSection {
+            enabled: true,
+            keys: vec![
+                ApiKeySection {
+                    name: "ops".to_string(),
+                    key_env: "BEE_TEST_AUTH_OPS".to_string(),
+                    scopes: vec!["admin".to_string()],
+                },
+                ApiKeySection {
+                    name: "grafana".to_string(),
+                    key_env: "BEE_TEST_AUTH_GRAFANA".to_string(),
+                    scopes: vec!["metrics".to_string()],
+                },
+            ],
+            jwt: Some(JwtSection {
+                issuer: Some("https://id.example.com".to_string()),
+                audience: Some("bee".to_string()),
+                jwks_url: None,
+                secret_env: Some("BEE_TEST_AUTH_JWT".to_string()),
+                scopes_claim: "scope".to_string(),
+                default_scopes: vec!["chat".to_string()],
+            }),
+        };
+        let auth = Authenticator::from(&section);
+
+        assert_eq!(auth.authorize(None, Scope::Chat), Err(AuthError::Missing));
+        assert_eq!(auth.authorize(Some("nope"), Scope::Chat), Err(AuthError::Invalid));
+        assert_eq!(auth.authorize(Some("ops-key"), Scope::Metrics).unwrap().name, "ops");
+        assert!(auth.authorize(Some("grafana-key"), Scope::Metrics).is_ok());
+        assert_eq!(
+            auth.authorize(Some("grafana-key"), Scope::Admin),
+            Err(AuthError::Forbidden("admin"))
+        );
+
+        let exp = chrono::Utc::now().timestamp() + 600;
+        let sign = |claims: Value, secret: &[u8]| {
+            jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(secret)).unwrap()
+        };
+        let token = sign(
+            serde_json::json!({ "sub": "alice", "iss": "https://id.example.com", "aud": "bee", "exp": exp, "scope": "openid metrics" }),
+            b"jwt-secret",
+        );
+        let principal = auth.authorize(Some(&token), Scope::Metrics).unwrap();
+        assert_eq!(principal.name, "alice");
+        assert_eq!(principal.kind, PrincipalKind::Jwt);
+        assert!(!principal.allows(Scope::Chat));
+
+        let default_scoped = sign(
+            serde_json::json!({ "sub": "bob", "iss": "https://id.example.com", "aud": "bee", "exp": exp }),
+            b"jwt-secret",
+        );
+        assert!(auth.authorize(Some(&default_scoped), Scope::Chat).is_ok());
+        let wrong_aud = sign(
+            serde_json::json!({ "sub": "eve", "iss": "https://id.example.com", "aud": "other", "exp": exp }),
+            b"jwt-secret",
+        );
+        assert_eq!(auth.authorize(Some(&wrong_aud), Scope::Chat), Err(AuthError::Invalid));
+        let forged = sign(
+            serde_json::json!({ "sub": "eve", "iss": "https://id.example.com", "aud": "bee", "exp": exp }),
+            b"guess",
+        );
+        assert_eq!(auth.authorize(Some(&forged), Scope::Chat), Err(AuthError::Invalid));
+
+        assert_eq!(bearer_token("Bearer abc "), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert!(Authenticator::disabled().authorize(None, Scope::Admin).is_ok());
+    }
+}
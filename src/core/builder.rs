@@ -6,20 +6,25 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::config::AppConfig;
-use crate::core::{RecoveryEngine, TaskScheduler};
-use crate::llm::LlmClient;
-use crate::react::{Critic, Planner};
+use crate::core::{PromptError, PromptLibrary, RecoveryEngine, ReminderStore, SessionWatchdog, TaskScheduler, WatchStore};
+use crate::llm::{context_window_for_model, LlmClient};
+use crate::react::validators::builtin_validator;
+use crate::react::{Critic, Guardrails, Planner, ReactLimits};
 use crate::skills::{SkillCache, SkillLoader};
 use crate::tools::{
-    CatTool, CodeEditTool, CodeGrepTool, CodeReadTool, CodeWriteTool,
-    DeepSearchTool, EchoTool, GitCommitTool, KnowledgeGraphBuilder, LsTool, PluginTool,
-    ReportGeneratorTool, SearchTool, ShellTool, SourceValidatorTool, TestCheckTool, TestRunTool,
-    ToolExecutor, ToolRegistry,
+    AudioTranscribeTool, CalendarTool, CatTool, CodeEditTool, CodeGrepTool, CodeReadTool, CodeWriteTool,
+    CompositeTool, DeepSearchTool, DelegateTool, DocReadTool, EchoTool, GitCommitTool, GithubTool, HttpFetchTool, ImageReadTool, KnowledgeGraphBuilder, LsTool, PluginTool, PolitePolicy,
+    RemindTool, ReportGeneratorTool, SearchTool, SendFileTool, ShellTool, SourceValidatorTool, TestCheckTool, TestRunTool,
+    ToolCache, ToolExecutor, ToolHelpTool, ToolPolicy, ToolRegistry, WatchTool, SAFE_MODE_TOOLS, providers_from_config,
 };
 #[cfg(feature = "browser")]
 use crate::tools::BrowserTool;
+#[cfg(feature = "email")]
+use crate::tools::EmailTool;
+#[cfg(feature = "desktop")]
+use crate::tools::{ClipboardTool, ScreenshotTool};
 #[cfg(feature = "web")]
-use crate::tools::{CreateGroupTool, CreateTool, ListAgentsTool, SendTool};
+use crate::tools::{CreateGroupTool, CreateTool, HandoffTool, ListAgentsTool, SendTool};
 
 /// Agent 构建器：统一配置和初始化 Agent 的各个组件
 pub struct AgentBuilder {
@@ -48,22 +53,36 @@ impl AgentBuilder {
         self
     }
 
-    /// 从文件加载系统提示词
-    pub fn with_system_prompt_from_file(mut self) -> Self {
-        self.system_prompt = [
-            "config/prompts/system.md",
-            "../config/prompts/system.md",
-            "config/prompts/default.md",
-            "../config/prompts/default.md",
-        ]
-        .into_iter()
-        .find_map(|p| std::fs::read_to_string(p).ok())
-        .unwrap_or_else(|| {
-            "You are Bee, a helpful AI assistant with access to various tools.".to_string()
-        });
+    /// 按 [prompts] system 加载系统提示词模板
+    pub fn with_system_prompt_from_file(self) -> Self {
+        let id = self.config.prompts.system.clone();
+        self.with_system_prompt_template(&id)
+    }
+
+    /// 以提示词库中的模板（代入 [prompts.vars]）作为系统提示词；不存在时依次尝试 default 模板与内置提示词
+    pub fn with_system_prompt_template(mut self, id: &str) -> Self {
+        self.system_prompt = [id, "default"]
+            .into_iter()
+            .find_map(|id| self.render_prompt(id))
+            .unwrap_or_else(|| {
+                "You are Bee, a helpful AI assistant with access to various tools.".to_string()
+            });
         self
     }
 
+    /// 渲染提示词库中的模板；不存在或缺少变量时为 None
+    fn render_prompt(&self, id: &str) -> Option<String> {
+        let library = PromptLibrary::discover(Path::new("config"));
+        match library.render(id, &self.config.prompts.vars) {
+            Ok(prompt) => Some(prompt),
+            Err(PromptError::NotFound(_)) => None,
+            Err(e) => {
+                tracing::warn!(prompt = %id, "failed to render prompt template: {}", e);
+                None
+            }
+        }
+    }
+
     /// 是否启用 Critic
     pub fn with_critic(mut self, enable: bool) -> Self {
         self.enable_critic = enable;
@@ -84,22 +103,81 @@ impl AgentBuilder {
 
         tools.register(CatTool::new(&self.workspace));
         tools.register(LsTool::new(&self.workspace));
+        tools.register(DocReadTool::new(&self.workspace));
         tools.register(EchoTool);
         tools.register(ShellTool::new(
             self.config.tools.shell.allowed_commands.clone(),
-            self.config.tools.tool_timeout_secs,
+            self.config.tools.timeout_for("shell"),
         ));
-        tools.register(SearchTool::new(
+        // Search 与 Browser 共享同一礼貌策略，按域名限速互相感知
+        let polite = self
+            .config
+            .tools
+            .polite
+            .enabled
+            .then(|| Arc::new(PolitePolicy::new(self.config.tools.polite.clone())));
+
+        let mut search = SearchTool::new(
             self.config.tools.search.allowed_domains.clone(),
             self.config.tools.search.timeout_secs,
             self.config.tools.search.max_result_chars,
-        ));
+        );
+        if let Some(ref polite) = polite {
+            search = search.with_politeness(Arc::clone(polite));
+        }
+        let providers = providers_from_config(&self.config.tools.search.providers, search.client());
+        let search = search.with_providers(providers, self.config.tools.search.max_results);
+        tools.register(search);
+
+        let mut http_fetch = HttpFetchTool::new(
+            &self.config.tools.http_fetch,
+            &self.config.tools.search.allowed_domains,
+        );
+        if let Some(ref polite) = polite {
+            http_fetch = http_fetch.with_politeness(Arc::clone(polite));
+        }
+        tools.register(http_fetch);
+        tools.register(ImageReadTool::new(&self.workspace, &self.config.tools.image_read));
+        tools.register(AudioTranscribeTool::new(&self.workspace, &self.config.tools.audio_transcribe));
+        tools.register(SendFileTool::new(&self.workspace));
+        if let Some(calendar) = CalendarTool::from_config(&self.config.tools.calendar) {
+            tools.register(calendar);
+        }
+        match ReminderStore::open(&self.workspace) {
+            Ok(store) => tools.register(RemindTool::new(Arc::new(store), self.config.tools.remind.max_active)),
+            Err(e) => tracing::warn!("remind tool disabled: {}", e),
+        }
+        match WatchStore::open(&self.workspace) {
+            Ok(store) => tools.register(WatchTool::new(Arc::new(store), self.config.tools.watch.max_watches)),
+            Err(e) => tracing::warn!("watch tool disabled: {}", e),
+        }
 
         #[cfg(feature = "browser")]
-        tools.register(BrowserTool::new(
-            self.config.tools.search.allowed_domains.clone(),
-            self.config.tools.search.max_result_chars,
-        ));
+        {
+            let mut browser = BrowserTool::new(
+                self.config.tools.search.allowed_domains.clone(),
+                self.config.tools.search.max_result_chars,
+            );
+            if let Some(ref polite) = polite {
+                browser = browser.with_politeness(Arc::clone(polite));
+            }
+            tools.register(
+                browser
+                    .with_download_dir(self.workspace.join("downloads"))
+                    .with_profiles(self.workspace.join("browser_profiles"), &self.config.tools.browser),
+            );
+        }
+
+        #[cfg(feature = "email")]
+        if !self.config.tools.email.accounts.is_empty() {
+            tools.register(EmailTool::new(&self.config.tools.email, &self.workspace));
+        }
+
+        #[cfg(feature = "desktop")]
+        {
+            tools.register(ClipboardTool::new());
+            tools.register(ScreenshotTool::new(&self.workspace));
+        }
 
         for entry in &self.config.tools.plugins {
             tools.register(PluginTool::new(
@@ -113,15 +191,27 @@ impl AgentBuilder {
         tools.register(CodeGrepTool::new(&self.workspace));
         tools.register(CodeEditTool::new(&self.workspace));
         tools.register(CodeWriteTool::new(&self.workspace));
-        tools.register(TestRunTool::new(&self.workspace));
-        tools.register(TestCheckTool::new(&self.workspace));
+        let mut test_run = TestRunTool::new(&self.workspace);
+        if let Some(secs) = self.config.tools.limits.get("test_run").and_then(|l| l.timeout_secs) {
+            test_run = test_run.with_timeout(secs);
+        }
+        tools.register(test_run);
+        let mut test_check = TestCheckTool::new(&self.workspace);
+        if let Some(secs) = self.config.tools.limits.get("test_check").and_then(|l| l.timeout_secs) {
+            test_check = test_check.with_timeout(secs);
+        }
+        tools.register(test_check);
         tools.register(GitCommitTool::new(&self.workspace));
+        let github = &self.config.tools.github;
+        if github.repo.is_some() || !github.allowed_repos.is_empty() {
+            tools.register(GithubTool::new(github, &self.workspace));
+        }
         tools.register(DeepSearchTool::new(llm.clone()));
         tools.register(SourceValidatorTool::new(
             self.config.tools.search.allowed_domains.clone(),
         ));
-        tools.register(ReportGeneratorTool::new(llm.clone()));
-        tools.register(KnowledgeGraphBuilder::new(llm));
+        tools.register(ReportGeneratorTool::new(llm.clone()).with_default_language(self.config.tools.report_language));
+        tools.register(KnowledgeGraphBuilder::new(llm.clone()));
 
         #[cfg(feature = "web")]
         tools.register(CreateTool::new(&self.workspace));
@@ -132,6 +222,37 @@ impl AgentBuilder {
         #[cfg(feature = "web")]
         tools.register(SendTool::new(&self.workspace));
 
+        // 组合工具：步骤引用上面已注册的工具（含先声明的组合工具）
+        for entry in &self.config.tools.composites {
+            match CompositeTool::new(entry, &tools) {
+                Ok(composite) => tools.register(composite),
+                Err(e) => tracing::warn!("composite tool skipped: {}", e),
+            }
+        }
+
+        // 子 Agent 委派：快照上面的工具（不含自身，子任务中不能再委派）
+        if self.config.tools.delegate.enabled {
+            let delegate = DelegateTool::from_registry(&tools, llm.clone(), &self.config);
+            tools.register(delegate);
+        }
+
+        // 会话交接：不进入 delegate 快照，子 Agent 不能交接
+        #[cfg(feature = "web")]
+        if self.config.tools.handoff.enabled {
+            tools.register(HandoffTool);
+        }
+
+        // 安全模式：只保留只读工具（在 delegate / handoff 注册之后过滤，二者同样被移除），
+        // 所有助手的 allowed_tools 随之收窄
+        if self.config.tools.safe_mode {
+            let removed = tools.retain_only(SAFE_MODE_TOOLS);
+            tracing::info!(removed = ?removed, "safe mode: mutating tools disabled");
+        }
+
+        // 最后注册：快照上面所有工具的完整说明，prompt 中只注入简短描述
+        let help = ToolHelpTool::from_registry(&tools);
+        tools.register(help);
+
         tools
     }
 
@@ -140,6 +261,36 @@ impl AgentBuilder {
         crate::core::orchestrator::create_llm_from_config(&self.config)
     }
 
+    /// 输入 token 预算：配置的上下文窗口（未设置时按模型名推断）减去输出预留
+    pub fn context_budget(&self) -> usize {
+        let llm = &self.config.llm;
+        let window = llm
+            .context_window
+            .unwrap_or_else(|| context_window_for_model(&llm.model));
+        window.saturating_sub(llm.reserve_output_tokens)
+    }
+
+    /// 为辅助任务（Critic、输出摘要）构建独立模型的 LLM；provider 为空时沿用主配置
+    fn build_llm_for_model(&self, model: &str, provider: Option<&str>) -> Arc<dyn LlmClient> {
+        let provider = provider.unwrap_or(&self.config.llm.provider);
+        if provider.to_lowercase() == "deepseek" {
+            Arc::new(crate::llm::create_deepseek_client(Some(model)))
+        } else {
+            let base_url = self.config.llm.base_url.as_deref();
+            let api_key = std::env::var("OPENAI_API_KEY").ok();
+            Arc::new(crate::llm::OpenAiClient::new(base_url, model, api_key.as_deref()))
+        }
+    }
+
+    /// 超长工具输出摘要使用的廉价模型（[tools.observation_summary].model），未配置时返回 None（使用主模型）
+    pub fn build_summarizer_llm(&self) -> Option<Arc<dyn LlmClient>> {
+        let section = &self.config.tools.observation_summary;
+        section
+            .model
+            .as_deref()
+            .map(|model| self.build_llm_for_model(model, section.provider.as_deref()))
+    }
+
     /// 构建 Critic（可选，解决问题 4.3：配置化与模型分离）
     pub fn build_critic(&self, planner_llm: Arc<dyn LlmClient>) -> Option<Critic> {
         // 检查配置是否启用 Critic
@@ -152,35 +303,43 @@ impl AgentBuilder {
         }
 
         // 如果配置了独立的 Critic 模型，使用独立的 LLM 实例
-        let critic_llm: Arc<dyn LlmClient> = if let Some(ref model) = self.config.critic.model {
-            let provider = self.config.critic.provider.as_deref()
-                .unwrap_or(&self.config.llm.provider);
-            
-            if provider.to_lowercase() == "deepseek" {
-                Arc::new(crate::llm::create_deepseek_client(Some(model)))
-            } else {
-                let base_url = self.config.llm.base_url.as_deref();
-                let api_key = std::env::var("OPENAI_API_KEY").ok();
-                Arc::new(crate::llm::OpenAiClient::new(base_url, model, api_key.as_deref()))
-            }
-        } else {
-            planner_llm
+        let critic_llm: Arc<dyn LlmClient> = match self.config.critic.model {
+            Some(ref model) => self.build_llm_for_model(model, self.config.critic.provider.as_deref()),
+            None => planner_llm,
         };
 
-        // 尝试从文件加载 prompt，否则使用配置中的模板
-        let critic_prompt = [
-            "config/prompts/critic.md",
-            "../config/prompts/critic.md",
-        ]
-        .into_iter()
-        .find_map(|p| std::fs::read_to_string(p).ok())
-        .unwrap_or_else(|| self.config.critic.prompt_template.clone());
+        // 优先使用提示词库中的 [prompts] critic 模板，否则使用配置中的模板
+        let critic_prompt = self
+            .render_prompt(&self.config.prompts.critic)
+            .unwrap_or_else(|| self.config.critic.prompt_template.clone());
 
-        // 创建修改后的配置副本，使用文件中的 prompt
+        // 创建修改后的配置副本，使用模板中的 prompt
         let mut critic_config = self.config.critic.clone();
         critic_config.prompt_template = critic_prompt;
 
-        Some(Critic::from_config(critic_llm, &critic_config))
+        let mut critic = Critic::from_config(critic_llm, &critic_config);
+        for (tool, names) in &self.config.critic.validators {
+            for name in names {
+                match builtin_validator(name, &self.workspace) {
+                    Some(validator) => critic = critic.with_validator(tool, validator),
+                    None => tracing::warn!(tool = %tool, "unknown critic validator skipped: {}", name),
+                }
+            }
+        }
+        Some(critic)
+    }
+
+    /// 构建内容护栏（[guardrails] 未启用时返回 None）；分类器可配置独立模型，未配置时使用主模型
+    pub fn build_guardrails(&self, planner_llm: Arc<dyn LlmClient>) -> Option<Guardrails> {
+        let section = &self.config.guardrails;
+        if !section.enabled {
+            return None;
+        }
+        let classifier_llm = match section.classifier.model {
+            Some(ref model) => self.build_llm_for_model(model, section.classifier.provider.as_deref()),
+            None => planner_llm,
+        };
+        Some(Guardrails::new(section).with_classifier(section, classifier_llm))
     }
 
     /// 构建技能加载器（返回 Arc 可共享）
@@ -208,7 +367,7 @@ impl AgentBuilder {
             self.system_prompt.clone()
         } else {
             format!(
-                "{}\n\n## Tool call JSON Schema (you must output valid JSON matching this)\n```json\n{}\n```",
+                "{}\n\n## Tool call JSON Schema (you must output valid JSON matching this)\nDescriptions are abbreviated; call `tool_help` with {{\"name\": \"<tool>\"}} for full usage.\n```json\n{}\n```",
                 self.system_prompt, tool_schema
             )
         }
@@ -218,18 +377,38 @@ impl AgentBuilder {
     pub fn build_components(&self) -> AgentComponents {
         let llm = self.build_llm();
         let critic = self.build_critic(llm.clone());
+        let guardrails = self.build_guardrails(llm.clone());
         let tools = self.build_tool_registry(llm.clone());
         let full_system_prompt = self.build_full_system_prompt(&tools);
         let skill_loader = self.build_skill_loader();
 
         AgentComponents {
-            planner: Planner::new(llm.clone(), full_system_prompt),
-            executor: ToolExecutor::new(tools, self.config.tools.tool_timeout_secs),
-            recovery: RecoveryEngine::new(),
+            planner: Planner::new(llm.clone(), full_system_prompt)
+                .with_context_budget(self.context_budget())
+                .with_summarizer(self.build_summarizer_llm()),
+            executor: ToolExecutor::new(tools, self.config.tools.tool_timeout_secs)
+            .with_limits(&self.config.tools.limits)
+            .with_policy(
+                self.config
+                    .tools
+                    .policy
+                    .enabled
+                    .then(|| ToolPolicy::from(self.config.tools.policy.clone()).with_composites(&self.config.tools.composites)),
+            )
+            .with_cache(
+                self.config
+                    .tools
+                    .cache
+                    .enabled
+                    .then(|| ToolCache::new(&self.config.tools.cache, &self.workspace)),
+            ),
+            recovery: RecoveryEngine::from_config(&self.config.recovery),
             critic,
-            task_scheduler: TaskScheduler::default(),
+            guardrails,
+            task_scheduler: TaskScheduler::from_config(&self.config.scheduler),
             skill_loader,
             llm,
+            watchdog: Arc::new(SessionWatchdog::new(&self.config.watchdog)),
             config: self.config.clone(),
         }
     }
@@ -252,9 +431,13 @@ pub struct AgentComponents {
     pub executor: ToolExecutor,
     pub recovery: RecoveryEngine,
     pub critic: Option<Critic>,
+    /// 内容护栏（[guardrails]）
+    pub guardrails: Option<Guardrails>,
     pub task_scheduler: TaskScheduler,
     pub skill_loader: Arc<SkillLoader>,
     pub llm: Arc<dyn LlmClient>,
+    /// 会话看门狗：卡住的 ReAct 循环超时取消
+    pub watchdog: Arc<SessionWatchdog>,
     pub config: AppConfig,
 }
 
@@ -273,6 +456,11 @@ impl AgentComponents {
     pub fn config(&self) -> &AppConfig {
         &self.config
     }
+
+    /// [react] 配置的步数与时限
+    pub fn react_limits(&self) -> ReactLimits {
+        (&self.config.react).into()
+    }
 }
 
 /// 便捷函数：从默认路径创建 AgentBuilder
@@ -294,3 +482,26 @@ pub fn create_agent_builder(config_path: Option<PathBuf>) -> AgentBuilder {
 
     AgentBuilder::new(config, workspace).with_system_prompt_from_file()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockLlmClient;
+
+    #[test]
+    fn test_safe_mode_filters_late_registered_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.tools.safe_mode = true;
+        config.tools.delegate.enabled = true;
+        config.tools.handoff.enabled = true;
+        let builder = AgentBuilder::new(config, dir.path().to_path_buf());
+        let tools = builder.build_tool_registry(Arc::new(MockLlmClient));
+
+        // delegate / handoff 在过滤之后才注册时会绕过安全模式
+        assert!(tools.get("delegate").is_none());
+        assert!(tools.get("handoff").is_none());
+        assert!(tools.tool_names().iter().all(|name| SAFE_MODE_TOOLS.contains(&name.as_str())));
+        assert!(tools.get("tool_help").is_some());
+    }
+}
@@ -0,0 +1,314 @@
+//! 启动自检（`bee doctor` / GET /api/diagnostics）
+//!
+//! 逐项检查 API Key、模型可达性、browser feature 所需的 Chrome、SQLite 可写、workspace 权限与嵌入配置，
+//! 每项给出状态与可操作的修复建议。offline 模式跳过需要联网的检查。
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::config::AppConfig;
+use crate::llm::deepseek::DEEPSEEK_BASE_URL;
+use crate::llm::OpenAiEmbedder;
+
+/// 联网检查的超时
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// 单项检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+    Skipped,
+}
+
+impl CheckStatus {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Ok => "✔",
+            Self::Warn => "!",
+            Self::Fail => "✘",
+            Self::Skipped => "-",
+        }
+    }
+}
+
+/// 单项检查
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// 修复建议（Ok / Skipped 时通常为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+/// 全部检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    /// 没有 Fail 项
+    pub ok: bool,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+impl DiagnosticsReport {
+    /// 终端输出：每项一行，失败与警告项附修复建议
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        for c in &self.checks {
+            out.push_str(&format!("{} {:<12} {}\n", c.status.symbol(), c.name, c.detail));
+            if let Some(fix) = &c.fix {
+                out.push_str(&format!("  {:<12} → {}\n", "", fix));
+            }
+        }
+        let failed = self.checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+        let warned = self.checks.iter().filter(|c| c.status == CheckStatus::Warn).count();
+        out.push_str(&format!("\n{} failed, {} warnings\n", failed, warned));
+        out
+    }
+}
+
+/// 当前配置实际使用的 LLM 端点（与 create_llm_from_config 的选择一致）
+struct LlmEndpoint {
+    provider: &'static str,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+fn llm_endpoint(cfg: &AppConfig) -> Option<LlmEndpoint> {
+    let provider = cfg.llm.provider.to_lowercase();
+    let deepseek_key = std::env::var("DEEPSEEK_API_KEY").ok();
+    let openai_key = std::env::var("OPENAI_API_KEY").ok();
+    if deepseek_key.is_some() || (provider == "deepseek" && openai_key.is_some()) {
+        return Some(LlmEndpoint {
+            provider: "deepseek",
+            base_url: DEEPSEEK_BASE_URL.to_string(),
+            api_key: deepseek_key.or(openai_key)?,
+            model: cfg.llm.deepseek.model.clone().unwrap_or_else(|| cfg.llm.model.clone()),
+        });
+    }
+    if provider != "deepseek" {
+        if let Some(key) = openai_key {
+            return Some(LlmEndpoint {
+                provider: "openai",
+                base_url: cfg.llm.base_url.clone().unwrap_or_else(|| OPENAI_BASE_URL.to_string()),
+                api_key: key,
+                model: cfg.llm.openai.model.clone().unwrap_or_else(|| "gpt-4o-mini".to_string()),
+            });
+        }
+    }
+    None
+}
+
+/// 执行全部检查；offline 为 true 时跳过模型与嵌入的联网检查
+pub async fn run_diagnostics(cfg: &AppConfig, workspace: &Path, offline: bool) -> DiagnosticsReport {
+    let endpoint = llm_endpoint(cfg);
+    let mut checks = vec![check_api_key(cfg, endpoint.as_ref())];
+    checks.push(match (&endpoint, offline) {
+        (None, _) => DiagnosticCheck::new("model", CheckStatus::Skipped, "no API key, nothing to reach"),
+        (Some(_), true) => DiagnosticCheck::new("model", CheckStatus::Skipped, "offline mode"),
+        (Some(ep), false) => check_model(ep).await,
+    });
+    checks.push(check_chrome());
+    checks.push(check_workspace(workspace));
+    checks.push(check_sqlite(workspace));
+    checks.push(check_embedding(cfg, offline).await);
+    DiagnosticsReport {
+        ok: checks.iter().all(|c| c.status != CheckStatus::Fail),
+        checks,
+    }
+}
+
+fn check_api_key(cfg: &AppConfig, endpoint: Option<&LlmEndpoint>) -> DiagnosticCheck {
+    match endpoint {
+        Some(ep) if ep.api_key.trim().is_empty() || ep.api_key == "sk-placeholder" => {
+            DiagnosticCheck::new("api_key", CheckStatus::Fail, format!("{} key is empty or a placeholder", ep.provider))
+                .with_fix("export DEEPSEEK_API_KEY=... (or OPENAI_API_KEY) with a real key")
+        }
+        Some(ep) => DiagnosticCheck::new(
+            "api_key",
+            CheckStatus::Ok,
+            format!("{} key {}", ep.provider, mask_key(&ep.api_key)),
+        ),
+        None if cfg.llm.provider.eq_ignore_ascii_case("mock") => {
+            DiagnosticCheck::new("api_key", CheckStatus::Warn, "provider is mock, replies are simulated")
+                .with_fix("set [llm] provider = \"deepseek\" or \"openai\" and export its API key")
+        }
+        None => DiagnosticCheck::new(
+            "api_key",
+            CheckStatus::Fail,
+            format!("no API key for provider '{}', falling back to the mock LLM", cfg.llm.provider),
+        )
+        .with_fix("export DEEPSEEK_API_KEY=... or OPENAI_API_KEY=... (e.g. in .env) and restart"),
+    }
+}
+
+/// 请求 {base_url}/models 验证端点可达且 Key 有效（不消耗 token）
+async fn check_model(ep: &LlmEndpoint) -> DiagnosticCheck {
+    let url = format!("{}/models", ep.base_url.trim_end_matches('/'));
+    let client = match reqwest::Client::builder().timeout(NETWORK_TIMEOUT).build() {
+        Ok(c) => c,
+        Err(e) => return DiagnosticCheck::new("model", CheckStatus::Fail, format!("http client: {}", e)),
+    };
+    match client.get(&url).bearer_auth(&ep.api_key).send().await {
+        Ok(resp) if resp.status().is_success() => DiagnosticCheck::new(
+            "model",
+            CheckStatus::Ok,
+            format!("{} reachable ({})", ep.base_url, ep.model),
+        ),
+        Ok(resp) if matches!(resp.status().as_u16(), 401 | 403) => {
+            DiagnosticCheck::new("model", CheckStatus::Fail, format!("{} rejected the API key (HTTP {})", ep.base_url, resp.status()))
+                .with_fix("check that the key is valid, not expired, and belongs to this provider")
+        }
+        Ok(resp) => DiagnosticCheck::new(
+            "model",
+            CheckStatus::Warn,
+            format!("{} answered HTTP {} for /models", ep.base_url, resp.status()),
+        )
+        .with_fix("verify [llm] base_url points to an OpenAI-compatible endpoint"),
+        Err(e) => DiagnosticCheck::new("model", CheckStatus::Fail, format!("cannot reach {}: {}", ep.base_url, e))
+            .with_fix("check network / proxy (HTTPS_PROXY) and [llm] base_url"),
+    }
+}
+
+#[cfg(feature = "browser")]
+fn check_chrome() -> DiagnosticCheck {
+    match headless_chrome::browser::default_executable() {
+        Ok(path) => DiagnosticCheck::new("chrome", CheckStatus::Ok, path.display().to_string()),
+        Err(e) => DiagnosticCheck::new("chrome", CheckStatus::Fail, format!("Chrome/Chromium not found: {}", e))
+            .with_fix("install Chrome or Chromium, or set CHROME=/path/to/chrome"),
+    }
+}
+
+#[cfg(not(feature = "browser"))]
+fn check_chrome() -> DiagnosticCheck {
+    DiagnosticCheck::new("chrome", CheckStatus::Skipped, "browser feature not enabled")
+}
+
+/// workspace 存在且可写（写入并删除探测文件）
+fn check_workspace(workspace: &Path) -> DiagnosticCheck {
+    if let Err(e) = std::fs::create_dir_all(workspace) {
+        return DiagnosticCheck::new("workspace", CheckStatus::Fail, format!("cannot create {}: {}", workspace.display(), e))
+            .with_fix("set [tools] filesystem_root to a writable directory");
+    }
+    let probe = workspace.join(".bee_doctor_probe");
+    match std::fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            DiagnosticCheck::new("workspace", CheckStatus::Ok, format!("{} is writable", workspace.display()))
+        }
+        Err(e) => DiagnosticCheck::new("workspace", CheckStatus::Fail, format!("{} is not writable: {}", workspace.display(), e))
+            .with_fix(format!("chown/chmod {} for the user running bee", workspace.display())),
+    }
+}
+
+/// workspace.db 可打开并能获取写锁（不修改数据）
+fn check_sqlite(workspace: &Path) -> DiagnosticCheck {
+    let db = workspace.join(crate::core::workspace_store::WORKSPACE_DB_FILE);
+    let result = rusqlite::Connection::open(&db).and_then(|conn| {
+        conn.busy_timeout(Duration::from_secs(2))?;
+        conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;")
+    });
+    match result {
+        Ok(()) => DiagnosticCheck::new("sqlite", CheckStatus::Ok, format!("{} is writable", db.display())),
+        Err(e) => DiagnosticCheck::new("sqlite", CheckStatus::Fail, format!("{}: {}", db.display(), e))
+            .with_fix("make sure the file is writable and not locked by another process; delete it if corrupted"),
+    }
+}
+
+/// 向量记忆启用时检查嵌入 Key 与模型（联网时实际嵌入一次）
+async fn check_embedding(cfg: &AppConfig, offline: bool) -> DiagnosticCheck {
+    if !cfg.memory.vector_enabled {
+        return DiagnosticCheck::new("embedding", CheckStatus::Skipped, "vector memory disabled ([memory] vector_enabled = false)");
+    }
+    let key = cfg
+        .memory
+        .embedding_api_key
+        .clone()
+        .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+        .filter(|k| !k.trim().is_empty() && k != "sk-placeholder");
+    let Some(key) = key else {
+        return DiagnosticCheck::new("embedding", CheckStatus::Fail, "vector memory enabled but no embedding API key")
+            .with_fix("set [memory] embedding_api_key or export OPENAI_API_KEY, or disable vector_enabled (falls back to keyword search)");
+    };
+    let model = &cfg.memory.embedding_model;
+    if offline {
+        return DiagnosticCheck::new("embedding", CheckStatus::Ok, format!("{} configured (not verified offline)", model));
+    }
+    let base_url = cfg.memory.embedding_base_url.as_deref().or(cfg.llm.base_url.as_deref());
+    let embedder = OpenAiEmbedder::new(base_url, model, Some(&key));
+    match tokio::time::timeout(NETWORK_TIMEOUT, embedder.embed_async("bee doctor")).await {
+        Ok(Ok(v)) if !v.is_empty() => {
+            DiagnosticCheck::new("embedding", CheckStatus::Ok, format!("{} returned {} dimensions", model, v.len()))
+        }
+        Ok(Ok(_)) => DiagnosticCheck::new("embedding", CheckStatus::Warn, format!("{} returned an empty vector", model))
+            .with_fix("check [memory] embedding_model"),
+        Ok(Err(e)) => DiagnosticCheck::new("embedding", CheckStatus::Fail, format!("{}: {}", model, e))
+            .with_fix("check [memory] embedding_model / embedding_base_url and the key's access to embeddings"),
+        Err(_) => DiagnosticCheck::new("embedding", CheckStatus::Fail, format!("{} timed out", model))
+            .with_fix("check network / proxy and [memory] embedding_base_url"),
+    }
+}
+
+/// 只显示 Key 的首尾几位
+fn mask_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!(
+        "{}…{}",
+        chars[..4].iter().collect::<String>(),
+        chars[chars.len() - 4..].iter().collect::<String>()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_diagnostics_report() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let dir = std::path::PathBuf::from("./target/test_doctor");
+            let _ = std::fs::remove_dir_all(&dir);
+            let mut cfg = AppConfig::default();
+            cfg.memory.vector_enabled = true;
+            cfg.memory.embedding_api_key = Some("sk-test-embedding-key".to_string());
+
+            let report = run_diagnostics(&cfg, &dir, true).await;
+            let status = |name: &str| report.checks.iter().find(|c| c.name == name).unwrap().status;
+            assert_eq!(status("workspace"), CheckStatus::Ok);
+            assert_eq!(status("sqlite"), CheckStatus::Ok);
+            assert_eq!(status("embedding"), CheckStatus::Ok);
+            assert!(!dir.join(".bee_doctor_probe").exists());
+            assert!(report.render_text().contains("workspace"));
+
+            assert_eq!(mask_key("sk-1234567890abcd"), "sk-1…abcd");
+            std::fs::remove_dir_all(&dir).ok();
+        });
+    }
+}
@@ -5,6 +5,7 @@
 use thiserror::Error;
 
 use crate::llm::LlmError;
+use crate::tools::ToolError;
 
 /// Agent 运行过程中可能出现的错误（网络、解析、工具、路径逃逸等）
 #[derive(Error, Debug)]
@@ -24,6 +25,10 @@ pub enum AgentError {
     #[error("Tool execution failed: {0}")]
     ToolExecutionFailed(String),
 
+    /// 工具返回的结构化错误（参数 / 权限 / 不存在 / 临时故障），RecoveryEngine 按类别处理
+    #[error("Tool execution failed: {0}")]
+    ToolFailed(#[from] ToolError),
+
     #[error("Tool timeout: {0}")]
     ToolTimeout(String),
 
@@ -45,6 +50,10 @@ pub enum AgentError {
 
     #[error("Path escape attempt: {0}")]
     PathEscape(String),
+
+    /// 会话长时间无任何事件，被看门狗取消（activity 为最后一次活动）
+    #[error("Session stalled: no progress for {idle_secs}s after {activity}")]
+    Stalled { activity: String, idle_secs: u64 },
 }
 
 /// 恢复引擎根据错误类型给出的建议动作
@@ -0,0 +1,369 @@
+//! 工作区文件监听：watch 工具登记的 glob 规则，后台轮询匹配文件的新增 / 修改 / 删除
+//!
+//! 规则存于 workspace.db 的 file_watches 表，进程重启后继续生效。bee-web 与 bee-gateway 用
+//! [TaskScheduler::spawn_file_watcher] 每隔 poll 扫描一次，把变化转为 [ReactEvent::FileChanged]
+//! 交给 [FileWatchSink]（页面 SSE / 网关广播）；带 task 的规则再由接入端让 Agent 处理该文件，
+//! 如「inbox/ 下新到 CSV 时总结」。每条规则的首次扫描只建立快照，不为已存在的文件产生事件。
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::core::task_scheduler::{ReminderOrigin, TaskScheduler};
+use crate::core::workspace_store::{StoreError, WORKSPACE_DB_FILE};
+use crate::react::ReactEvent;
+
+/// 单条规则最多跟踪的文件数，避免 `**/*` 之类的规则扫描整个工作区
+const MAX_FILES_PER_WATCH: usize = 2000;
+
+/// 文件监听规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchRule {
+    pub id: String,
+    /// 相对 workspace 的 glob，如 `inbox/*.csv`
+    pub pattern: String,
+    /// 文件变化时交给 Agent 的任务；None 时只产生事件
+    #[serde(default)]
+    pub task: Option<String>,
+    /// 创建规则的会话（task 的结果写回这里）；None 表示无来源会话（TUI 等）
+    #[serde(default)]
+    pub origin: Option<ReminderOrigin>,
+    pub created_at: i64,
+}
+
+impl WatchRule {
+    /// 规则是否由该接入端处理：无来源会话的规则各接入端都处理
+    pub fn handled_by(&self, channel: &str) -> bool {
+        self.origin.as_ref().is_none_or(|o| o.channel == channel)
+    }
+}
+
+/// 校验并规范化 glob：必须是 workspace 内的相对路径
+pub fn normalize_watch_pattern(pattern: &str) -> Result<String, String> {
+    let trimmed = pattern.trim().trim_start_matches("./");
+    if trimmed.is_empty() {
+        return Err("pattern is empty".to_string());
+    }
+    let path = Path::new(trimmed);
+    if path.is_absolute()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!("pattern '{}' must be relative to the workspace", pattern));
+    }
+    glob::Pattern::new(trimmed).map_err(|e| format!("invalid glob '{}': {}", pattern, e))?;
+    Ok(trimmed.to_string())
+}
+
+/// 文件变化类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl FileChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileChangeKind::Created => "created",
+            FileChangeKind::Modified => "modified",
+            FileChangeKind::Removed => "removed",
+        }
+    }
+}
+
+/// 一次文件变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub watch_id: String,
+    /// 相对 workspace 的路径（`/` 分隔）
+    pub path: String,
+    pub kind: FileChangeKind,
+}
+
+impl FileChange {
+    pub fn to_event(&self) -> ReactEvent {
+        ReactEvent::FileChanged {
+            watch_id: self.watch_id.clone(),
+            path: self.path.clone(),
+            change: self.kind,
+        }
+    }
+
+    /// 交给 Agent 的消息：说明哪个文件发生了什么变化，再附上规则的 task
+    pub fn task_prompt(&self, task: &str) -> String {
+        format!(
+            "[文件监听 {}] 工作区文件 {} 已{}。\n任务：{}",
+            self.watch_id,
+            self.path,
+            match self.kind {
+                FileChangeKind::Created => "新增",
+                FileChangeKind::Modified => "修改",
+                FileChangeKind::Removed => "删除",
+            },
+            task
+        )
+    }
+}
+
+/// 监听规则存储（workspace.db 的 file_watches 表）
+pub struct WatchStore {
+    conn: Mutex<Connection>,
+}
+
+impl WatchStore {
+    /// 打开 workspace/workspace.db
+    pub fn open(workspace: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(workspace)?;
+        Self::open_at(&workspace.join(WORKSPACE_DB_FILE))
+    }
+
+    pub fn open_at(db_path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS file_watches (
+                 id TEXT PRIMARY KEY,
+                 created_at INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 插入或覆盖
+    pub fn upsert(&self, rule: &WatchRule) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO file_watches (id, created_at, data) VALUES (?1, ?2, ?3)",
+            params![rule.id, rule.created_at, serde_json::to_string(rule)?],
+        )?;
+        Ok(())
+    }
+
+    /// 删除规则，返回是否存在
+    pub fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        Ok(self.conn().execute("DELETE FROM file_watches WHERE id = ?1", [id])? > 0)
+    }
+
+    /// 按创建时间列出；origin 为 Some 时只列该会话的规则
+    pub fn list(&self, origin: Option<&ReminderOrigin>) -> Result<Vec<WatchRule>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM file_watches ORDER BY created_at")?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .iter()
+            .filter_map(|d| serde_json::from_str::<WatchRule>(d).ok())
+            .filter(|r| {
+                origin.is_none_or(|o| {
+                    r.origin
+                        .as_ref()
+                        .is_some_and(|ro| ro.channel == o.channel && ro.target == o.target && ro.user_id == o.user_id)
+                })
+            })
+            .collect())
+    }
+}
+
+/// 文件快照：修改时间与大小，任一变化即视为修改
+type FileStamp = (Option<SystemTime>, u64);
+
+/// 轮询扫描器：按规则保存上一轮的文件快照，对比得出变化
+pub struct WorkspaceScanner {
+    workspace: PathBuf,
+    snapshots: HashMap<String, HashMap<String, FileStamp>>,
+}
+
+impl WorkspaceScanner {
+    pub fn new(workspace: impl AsRef<Path>) -> Self {
+        Self {
+            workspace: workspace.as_ref().to_path_buf(),
+            snapshots: HashMap::new(),
+        }
+    }
+
+    fn snapshot(&self, pattern: &str) -> HashMap<String, FileStamp> {
+        // workspace 路径本身可能含 glob 特殊字符，需转义
+        let base = glob::Pattern::escape(&self.workspace.to_string_lossy());
+        let Ok(paths) = glob::glob(&format!("{}/{}", base.trim_end_matches('/'), pattern)) else {
+            return HashMap::new();
+        };
+        paths
+            .filter_map(Result::ok)
+            .filter_map(|path| {
+                let meta = std::fs::metadata(&path).ok().filter(|m| m.is_file())?;
+                let rel = path.strip_prefix(&self.workspace).ok()?;
+                let rel = rel.to_string_lossy().replace('\\', "/");
+                Some((rel, (meta.modified().ok(), meta.len())))
+            })
+            .take(MAX_FILES_PER_WATCH)
+            .collect()
+    }
+
+    /// 扫描一轮：新规则只建立快照；已删除规则的快照随之丢弃
+    pub fn scan(&mut self, rules: &[WatchRule]) -> Vec<FileChange> {
+        let mut changes = Vec::new();
+        let mut next = HashMap::new();
+        for rule in rules {
+            let current = self.snapshot(&rule.pattern);
+            if let Some(previous) = self.snapshots.get(&rule.id) {
+                let mut rule_changes: Vec<FileChange> = current
+                    .iter()
+                    .filter_map(|(path, stamp)| {
+                        let kind = match previous.get(path) {
+                            None => FileChangeKind::Created,
+                            Some(old) if old != stamp => FileChangeKind::Modified,
+                            Some(_) => return None,
+                        };
+                        Some(FileChange {
+                            watch_id: rule.id.clone(),
+                            path: path.clone(),
+                            kind,
+                        })
+                    })
+                    .chain(
+                        previous
+                            .keys()
+                            .filter(|p| !current.contains_key(*p))
+                            .map(|path| FileChange {
+                                watch_id: rule.id.clone(),
+                                path: path.clone(),
+                                kind: FileChangeKind::Removed,
+                            }),
+                    )
+                    .collect();
+                rule_changes.sort_by(|a, b| a.path.cmp(&b.path));
+                changes.extend(rule_changes);
+            }
+            next.insert(rule.id.clone(), current);
+        }
+        self.snapshots = next;
+        changes
+    }
+}
+
+/// 文件变化的处理端：每个接入端实现一次（推送事件，带 task 时让 Agent 处理）
+#[async_trait]
+pub trait FileWatchSink: Send + Sync {
+    /// 接入端名称（与 ReminderOrigin::channel 对应）
+    fn channel(&self) -> &str;
+
+    async fn on_change(&self, rule: &WatchRule, change: &FileChange);
+}
+
+impl TaskScheduler {
+    /// 后台轮询工作区文件变化并交给 sink（Background 任务，不占用工具并发许可）
+    pub fn spawn_file_watcher(
+        store: Arc<WatchStore>,
+        sink: Arc<dyn FileWatchSink>,
+        workspace: PathBuf,
+        poll: Duration,
+    ) -> JoinHandle<()> {
+        tracing::info!(
+            channel = sink.channel(),
+            "file watcher started, poll {}s",
+            poll.as_secs()
+        );
+        tokio::spawn(async move {
+            let mut scanner = WorkspaceScanner::new(workspace);
+            let mut interval = tokio::time::interval(poll.max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                let rules: Vec<WatchRule> = match store.list(None) {
+                    Ok(rules) => rules.into_iter().filter(|r| r.handled_by(sink.channel())).collect(),
+                    Err(e) => {
+                        tracing::warn!("failed to load file watches: {}", e);
+                        continue;
+                    }
+                };
+                for change in scanner.scan(&rules) {
+                    let Some(rule) = rules.iter().find(|r| r.id == change.watch_id) else {
+                        continue;
+                    };
+                    tracing::info!(watch = %rule.id, path = %change.path, change = change.kind.as_str(), "file changed");
+                    sink.on_change(rule, &change).await;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_rules_and_scanner() {
+        assert_eq!(normalize_watch_pattern("./inbox/*.csv").unwrap(), "inbox/*.csv");
+        assert!(normalize_watch_pattern("../secrets/*").is_err());
+        assert!(normalize_watch_pattern("/etc/*").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = WatchStore::open(dir.path()).unwrap();
+        let web = ReminderOrigin {
+            channel: "web".into(),
+            target: "s1".into(),
+            assistant_id: None,
+            user_id: None,
+        };
+        let rule = WatchRule {
+            id: "w1".into(),
+            pattern: "inbox/*.csv".into(),
+            task: Some("summarize it".into()),
+            origin: Some(web.clone()),
+            created_at: 1,
+        };
+        store.upsert(&rule).unwrap();
+        assert_eq!(store.list(Some(&web)).unwrap(), vec![rule.clone()]);
+        assert!(store
+            .list(Some(&ReminderOrigin {
+                target: "s2".into(),
+                ..web
+            }))
+            .unwrap()
+            .is_empty());
+        assert!(rule.handled_by("web") && !rule.handled_by("gateway"));
+
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir_all(&inbox).unwrap();
+        std::fs::write(inbox.join("old.csv"), "a").unwrap();
+        let mut scanner = WorkspaceScanner::new(dir.path());
+        // 首次扫描只建立快照
+        assert!(scanner.scan(std::slice::from_ref(&rule)).is_empty());
+
+        std::fs::write(inbox.join("new.csv"), "a,b").unwrap();
+        std::fs::write(inbox.join("note.txt"), "ignored").unwrap();
+        std::fs::write(inbox.join("old.csv"), "a,b,c").unwrap();
+        let changes = scanner.scan(std::slice::from_ref(&rule));
+        let summary: Vec<(&str, FileChangeKind)> = changes.iter().map(|c| (c.path.as_str(), c.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("inbox/new.csv", FileChangeKind::Created),
+                ("inbox/old.csv", FileChangeKind::Modified)
+            ]
+        );
+        assert!(changes[0].task_prompt("summarize it").contains("inbox/new.csv 已新增"));
+
+        std::fs::remove_file(inbox.join("new.csv")).unwrap();
+        let changes = scanner.scan(std::slice::from_ref(&rule));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, FileChangeKind::Removed);
+        assert!(scanner.scan(std::slice::from_ref(&rule)).is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! 看板任务编排：依赖、截止时间与重复
+//!
+//! 任务可声明 depends_on（前置任务）、due_at（截止时间）与 recurrence（cron 重复规则）。
+//! 有未完成前置任务的任务处于 Blocked，前置任务全部完成后自动回到 Todo；
+//! 统筹启动前检查前置任务；重复任务完成时按 cron 生成下一期。
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+
+use super::task_scheduler::CronSchedule;
+use super::workspace_store::{StoreError, Task, TaskRepository, TaskStatus};
+
+/// 依赖校验失败
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum DependencyError {
+    #[error("dependency not found: {0}")]
+    Missing(String),
+    #[error("task cannot depend on itself")]
+    SelfReference,
+    #[error("dependency cycle through {0}")]
+    Cycle(String),
+}
+
+/// 尚未完成的前置任务 id；已不存在的前置任务不再阻塞
+pub fn unmet_dependencies(task: &Task, tasks: &[Task]) -> Vec<String> {
+    task.depends_on
+        .iter()
+        .filter(|dep| tasks.iter().any(|t| &t.id == *dep && t.status != TaskStatus::Done))
+        .cloned()
+        .collect()
+}
+
+/// 校验 task_id 的前置任务：须为同一用户的已有任务，且不形成环
+pub fn validate_dependencies(
+    task_id: &str,
+    owner: Option<&str>,
+    depends_on: &[String],
+    tasks: &[Task],
+) -> Result<(), DependencyError> {
+    for dep in depends_on {
+        if dep == task_id {
+            return Err(DependencyError::SelfReference);
+        }
+        if !tasks.iter().any(|t| &t.id == dep && t.owner.as_deref() == owner) {
+            return Err(DependencyError::Missing(dep.clone()));
+        }
+    }
+    // 从新的前置任务出发沿 depends_on 向上走，回到 task_id 即成环
+    let mut stack: Vec<&str> = depends_on.iter().map(String::as_str).collect();
+    let mut seen = HashSet::new();
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let Some(t) = tasks.iter().find(|t| t.id == id) else {
+            continue;
+        };
+        if t.depends_on.iter().any(|d| d == task_id) {
+            return Err(DependencyError::Cycle(id.to_string()));
+        }
+        stack.extend(t.depends_on.iter().map(String::as_str));
+    }
+    Ok(())
+}
+
+/// 按前置任务调整状态：Todo 有未完成前置任务时转 Blocked，Blocked 的前置任务全部完成时转 Todo；返回是否有变化
+pub fn refresh_blocked(task: &mut Task, tasks: &[Task]) -> bool {
+    let blocked = !unmet_dependencies(task, tasks).is_empty();
+    let status = match task.status {
+        TaskStatus::Todo if blocked => TaskStatus::Blocked,
+        TaskStatus::Blocked if !blocked => TaskStatus::Todo,
+        s => s,
+    };
+    let changed = status != task.status;
+    task.status = status;
+    changed
+}
+
+/// 把前置任务已全部完成的 Blocked 任务移回 Todo，返回被解除阻塞的任务
+pub fn unblock_ready_tasks(repo: &dyn TaskRepository) -> Result<Vec<Task>, StoreError> {
+    let tasks = repo.list_tasks()?;
+    let mut unblocked = Vec::new();
+    for task in tasks.iter().filter(|t| t.status == TaskStatus::Blocked) {
+        if !unmet_dependencies(task, &tasks).is_empty() {
+            continue;
+        }
+        let updated = repo.update_task(&task.id, &mut |t| {
+            if t.status == TaskStatus::Blocked {
+                t.status = TaskStatus::Todo;
+                t.updated_at = Utc::now().to_rfc3339();
+            }
+        })?;
+        unblocked.extend(updated.filter(|t| t.status == TaskStatus::Todo));
+    }
+    Ok(unblocked)
+}
+
+/// 解析截止时间：RFC 3339，或 YYYY-MM-DD（当天 23:59 本地时间），统一为 RFC 3339
+pub fn parse_due(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.to_rfc3339());
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("invalid due date '{}', expected RFC 3339 or YYYY-MM-DD", s))?;
+    date.and_hms_opt(23, 59, 0)
+        .and_then(|t| Local.from_local_datetime(&t).earliest())
+        .map(|t| t.to_rfc3339())
+        .ok_or_else(|| format!("invalid due date '{}'", s))
+}
+
+/// 重复任务完成后的下一期：沿用标题、描述、成员与重复规则，不带前置任务；
+/// 截止时间为 cron 在原截止时间与 now 中较晚者之后的下一个时刻。无重复规则或规则无效时返回 None
+pub fn next_occurrence(task: &Task, now: DateTime<Local>) -> Option<Task> {
+    let schedule = CronSchedule::parse(task.recurrence.as_deref()?).ok()?;
+    let due = task
+        .due_at
+        .as_deref()
+        .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+        .map(|d| d.with_timezone(&Local))
+        .filter(|d| *d > now)
+        .unwrap_or(now);
+    let next_due = schedule.next_after(due)?;
+    let created = Utc::now().to_rfc3339();
+    Some(Task {
+        id: uuid::Uuid::new_v4().to_string(),
+        status: TaskStatus::Todo,
+        depends_on: Vec::new(),
+        due_at: Some(next_due.to_rfc3339()),
+        created_at: created.clone(),
+        updated_at: created,
+        ..task.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, status: TaskStatus, depends_on: &[&str]) -> Task {
+        Task {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: None,
+            status,
+            assignee_ids: Vec::new(),
+            group_id: None,
+            coordinator_id: None,
+            depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+            due_at: None,
+            recurrence: None,
+            owner: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_dependencies_block_and_recurrence() {
+        let tasks = vec![
+            task("a", TaskStatus::Done, &[]),
+            task("b", TaskStatus::InProgress, &["a"]),
+            task("c", TaskStatus::Blocked, &["a", "b"]),
+        ];
+        assert_eq!(unmet_dependencies(&tasks[2], &tasks), vec!["b".to_string()]);
+        assert_eq!(validate_dependencies("d", None, &["c".to_string()], &tasks), Ok(()));
+        assert_eq!(
+            validate_dependencies("a", None, &["c".to_string()], &tasks),
+            Err(DependencyError::Cycle("c".to_string()))
+        );
+        assert_eq!(
+            validate_dependencies("a", None, &["a".to_string()], &tasks),
+            Err(DependencyError::SelfReference)
+        );
+        assert_eq!(
+            validate_dependencies("d", Some("alice"), &["a".to_string()], &tasks),
+            Err(DependencyError::Missing("a".to_string()))
+        );
+
+        let mut todo = task("d", TaskStatus::Todo, &["b"]);
+        assert!(refresh_blocked(&mut todo, &tasks));
+        assert_eq!(todo.status, TaskStatus::Blocked);
+
+        // b 完成后 c 解除阻塞
+        let dir = tempfile::tempdir().unwrap();
+        let store = super::super::SqliteWorkspaceStore::open(dir.path()).unwrap();
+        for t in &tasks {
+            store.insert_task(t).unwrap();
+        }
+        assert!(unblock_ready_tasks(&store).unwrap().is_empty());
+        store.update_task("b", &mut |t| t.status = TaskStatus::Done).unwrap();
+        let unblocked = unblock_ready_tasks(&store).unwrap();
+        assert_eq!(unblocked.len(), 1);
+        assert_eq!((unblocked[0].id.as_str(), unblocked[0].status), ("c", TaskStatus::Todo));
+
+        // 每周一 9:00 重复：下一期在原截止时间之后
+        let now = Local.with_ymd_and_hms(2026, 3, 4, 12, 0, 0).unwrap();
+        let mut weekly = task("w", TaskStatus::Done, &["a"]);
+        weekly.recurrence = Some("0 9 * * 1".to_string());
+        weekly.due_at = Some(Local.with_ymd_and_hms(2026, 3, 9, 9, 0, 0).unwrap().to_rfc3339());
+        let next = next_occurrence(&weekly, now).unwrap();
+        assert_ne!(next.id, "w");
+        assert_eq!(next.status, TaskStatus::Todo);
+        assert!(next.depends_on.is_empty());
+        assert_eq!(
+            next.due_at,
+            Some(Local.with_ymd_and_hms(2026, 3, 16, 9, 0, 0).unwrap().to_rfc3339())
+        );
+        weekly.recurrence = None;
+        assert!(next_occurrence(&weekly, now).is_none());
+
+        assert!(parse_due("2026-03-09").unwrap().starts_with("2026-03-09T23:59:00"));
+        assert!(parse_due("next week").is_err());
+    }
+}
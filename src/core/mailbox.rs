@@ -0,0 +1,538 @@
+//! 助手间信箱：send 工具投递的私信统一存于 workspace.db 的 mailbox 表，按收件人排队处理
+//!
+//! - 消息分三类（[MailKind]）：`request` 处理后自动把回复作为 `reply` 寄回发送方，`notice` 与 `reply`
+//!   只交给收件人处理、不再回信；同一会话链共享 thread_id，depth 超过 [MAX_THREAD_DEPTH] 的投递被拒绝，防止两个助手无限往返
+//! - 未读：`read_at` 为空即未读，收件人处理或页面标记已读后置位
+//! - 投递保证：至少一次。[Mailbox::claim_next] 领取时加租约（[CLAIM_LEASE]），进程中断后租约到期的消息重新领取；
+//!   处理失败按退避重试，[MAX_DELIVERY_ATTEMPTS] 次后标记失败；同一收件人同时只处理一条，保证按序
+//! - 自动处理：[TaskScheduler::spawn_mailbox] 在有新信件时立即（否则按轮询间隔）交给 [MailboxHandler]，
+//!   不再依赖页面调用 `/api/inbox/process`
+
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::core::task_scheduler::TaskScheduler;
+use crate::core::workspace_store::{StoreError, WORKSPACE_DB_FILE};
+
+/// P2P 群 ID 前缀：两个助手之间的私信记录在该群的会话中
+pub const P2P_PREFIX: &str = "p2p_";
+
+/// 同一会话链允许的最大深度（request → reply 各算一层）
+pub const MAX_THREAD_DEPTH: u32 = 8;
+
+/// 单条消息最多尝试处理的次数
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+
+/// 领取后的处理租约；超时未完成（如进程退出）的消息重新投递
+pub const CLAIM_LEASE: Duration = Duration::from_secs(15 * 60);
+
+/// 失败重试的基础退避（秒），按尝试次数翻倍
+const RETRY_BACKOFF_SECS: i64 = 30;
+
+/// 生成 P2P 群 id：按字母序排列 (a, b) 保证唯一
+pub fn p2p_group_id(a: &str, b: &str) -> String {
+    let (x, y) = if a <= b { (a, b) } else { (b, a) };
+    format!("{}{}_{}", P2P_PREFIX, x, y)
+}
+
+/// 信件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MailKind {
+    /// 需要回复：处理结果自动寄回发送方
+    #[default]
+    Request,
+    /// 仅通知，不回信
+    Notice,
+    /// 对 request 的回复
+    Reply,
+}
+
+impl MailKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "request" => Some(Self::Request),
+            "notice" => Some(Self::Notice),
+            "reply" => Some(Self::Reply),
+            _ => None,
+        }
+    }
+}
+
+/// 投递状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailStatus {
+    Pending,
+    Processing,
+    Processed,
+    Failed,
+}
+
+impl MailStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Processing => "processing",
+            Self::Processed => "processed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// 一封信
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailMessage {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    pub kind: MailKind,
+    pub content: String,
+    /// reply 对应的 request id
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// 会话链 id（首封信的 id）
+    pub thread_id: String,
+    /// 在会话链中的深度，首封为 0
+    #[serde(default)]
+    pub depth: u32,
+    pub status: MailStatus,
+    #[serde(default)]
+    pub attempts: u32,
+    /// 可被领取的时刻（unix 秒）：待处理时为重试时间，处理中为租约到期时间
+    pub available_at: i64,
+    pub created_at: i64,
+    #[serde(default)]
+    pub read_at: Option<i64>,
+    #[serde(default)]
+    pub processed_at: Option<i64>,
+    /// 收件人处理后的回复
+    #[serde(default)]
+    pub reply: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl MailMessage {
+    /// 新会话链的首封信
+    pub fn new(from: &str, to: &str, kind: MailKind, content: &str) -> Self {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().timestamp();
+        Self {
+            thread_id: id.clone(),
+            id,
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+            content: content.to_string(),
+            reply_to: None,
+            depth: 0,
+            status: MailStatus::Pending,
+            attempts: 0,
+            available_at: now,
+            created_at: now,
+            read_at: None,
+            processed_at: None,
+            reply: None,
+            error: None,
+        }
+    }
+
+    /// 在当前会话链中继续发信（处理本信时由收件人发出）
+    pub fn follow_up(&self, to: &str, kind: MailKind, content: &str) -> Self {
+        Self {
+            thread_id: self.thread_id.clone(),
+            depth: self.depth + 1,
+            ..Self::new(&self.to, to, kind, content)
+        }
+    }
+
+    /// 对本信的回复
+    pub fn reply(&self, content: &str) -> Self {
+        Self {
+            reply_to: Some(self.id.clone()),
+            ..self.follow_up(&self.from, MailKind::Reply, content)
+        }
+    }
+
+    pub fn is_unread(&self) -> bool {
+        self.read_at.is_none()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailboxError {
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("cannot send mail to yourself")]
+    SelfMessage,
+    #[error("thread {0} exceeded the maximum depth of {MAX_THREAD_DEPTH}")]
+    ThreadTooDeep(String),
+    #[error("mail {0} not found")]
+    NotFound(String),
+}
+
+impl From<rusqlite::Error> for MailboxError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Store(e.into())
+    }
+}
+
+impl From<serde_json::Error> for MailboxError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Store(e.into())
+    }
+}
+
+/// 进程内的新信通知：send 后唤醒 [TaskScheduler::spawn_mailbox]，send 工具与处理循环各自打开 Mailbox 也能收到
+fn wake() -> &'static Notify {
+    static WAKE: OnceLock<Notify> = OnceLock::new();
+    WAKE.get_or_init(Notify::new)
+}
+
+tokio::task_local! {
+    /// 当前正在处理的信件；处理期间 send 工具据此把新信挂到同一会话链
+    pub static CURRENT_MAIL: MailMessage;
+}
+
+/// 信箱：workspace.db 的 mailbox 表
+pub struct Mailbox {
+    conn: Mutex<Connection>,
+}
+
+impl Mailbox {
+    /// 打开 workspace/workspace.db
+    pub fn open(workspace: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(workspace)?;
+        Self::open_at(&workspace.join(WORKSPACE_DB_FILE))
+    }
+
+    pub fn open_at(db_path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS mailbox (
+                 id TEXT PRIMARY KEY,
+                 recipient TEXT NOT NULL,
+                 status TEXT NOT NULL,
+                 available_at INTEGER NOT NULL,
+                 created_at INTEGER NOT NULL,
+                 unread INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS mailbox_recipient ON mailbox (recipient, created_at);
+             CREATE INDEX IF NOT EXISTS mailbox_status ON mailbox (status, available_at);",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save(conn: &Connection, msg: &MailMessage) -> Result<(), StoreError> {
+        conn.execute(
+            "INSERT OR REPLACE INTO mailbox (id, recipient, status, available_at, created_at, unread, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                msg.id,
+                msg.to,
+                msg.status.as_str(),
+                msg.available_at,
+                msg.created_at,
+                msg.is_unread(),
+                serde_json::to_string(msg)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn query(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<Vec<MailMessage>, StoreError> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt
+            .query_map(params, |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.iter().filter_map(|d| serde_json::from_str(d).ok()).collect())
+    }
+
+    /// 投递一封信并唤醒处理循环
+    pub fn send(&self, msg: &MailMessage) -> Result<(), MailboxError> {
+        if msg.from == msg.to {
+            return Err(MailboxError::SelfMessage);
+        }
+        if msg.depth > MAX_THREAD_DEPTH {
+            return Err(MailboxError::ThreadTooDeep(msg.thread_id.clone()));
+        }
+        Self::save(&self.conn(), msg)?;
+        wake().notify_one();
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<MailMessage>, StoreError> {
+        let data: Option<String> = self
+            .conn()
+            .query_row("SELECT data FROM mailbox WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+    }
+
+    /// 收件人的信件，新的在前
+    pub fn inbox(&self, recipient: &str, unread_only: bool, limit: usize) -> Result<Vec<MailMessage>, StoreError> {
+        Self::query(
+            &self.conn(),
+            "SELECT data FROM mailbox WHERE recipient = ?1 AND (?2 = 0 OR unread = 1)
+             ORDER BY created_at DESC, rowid DESC LIMIT ?3",
+            params![recipient, unread_only, limit as i64],
+        )
+    }
+
+    pub fn unread_count(&self, recipient: &str) -> Result<usize, StoreError> {
+        let n: i64 = self.conn().query_row(
+            "SELECT COUNT(*) FROM mailbox WHERE recipient = ?1 AND unread = 1",
+            [recipient],
+            |row| row.get(0),
+        )?;
+        Ok(n as usize)
+    }
+
+    /// 标记已读，返回标记后的信件
+    pub fn mark_read(&self, id: &str) -> Result<MailMessage, MailboxError> {
+        let conn = self.conn();
+        let data: Option<String> = conn
+            .query_row("SELECT data FROM mailbox WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        let mut msg: MailMessage = match data {
+            Some(d) => serde_json::from_str(&d)?,
+            None => return Err(MailboxError::NotFound(id.to_string())),
+        };
+        if msg.read_at.is_none() {
+            msg.read_at = Some(Utc::now().timestamp());
+            Self::save(&conn, &msg)?;
+        }
+        Ok(msg)
+    }
+
+    /// 领取下一封可处理的信（recipient 为 None 时不限收件人）：跳过已有信件在处理中的收件人，
+    /// 租约到期的处理中信件视为中断、重新领取
+    pub fn claim_next(&self, now: DateTime<Utc>, recipient: Option<&str>) -> Result<Option<MailMessage>, StoreError> {
+        let now = now.timestamp();
+        let conn = self.conn();
+        let candidates = Self::query(
+            &conn,
+            "SELECT data FROM mailbox m
+             WHERE status IN ('pending', 'processing') AND available_at <= ?1 AND (?2 IS NULL OR recipient = ?2)
+               AND NOT EXISTS (
+                   SELECT 1 FROM mailbox b
+                   WHERE b.recipient = m.recipient AND b.status = 'processing' AND b.available_at > ?1
+               )
+             ORDER BY created_at, rowid LIMIT 1",
+            params![now, recipient],
+        )?;
+        let Some(mut msg) = candidates.into_iter().next() else {
+            return Ok(None);
+        };
+        msg.status = MailStatus::Processing;
+        msg.attempts += 1;
+        msg.available_at = now + CLAIM_LEASE.as_secs() as i64;
+        Self::save(&conn, &msg)?;
+        Ok(Some(msg))
+    }
+
+    /// 处理完成：记录回复并标记已读
+    pub fn complete(&self, id: &str, reply: &str) -> Result<(), MailboxError> {
+        let mut msg = self.get(id)?.ok_or_else(|| MailboxError::NotFound(id.to_string()))?;
+        let now = Utc::now().timestamp();
+        msg.status = MailStatus::Processed;
+        msg.processed_at = Some(now);
+        msg.read_at.get_or_insert(now);
+        msg.reply = Some(reply.to_string());
+        msg.error = None;
+        Self::save(&self.conn(), &msg)?;
+        Ok(())
+    }
+
+    /// 处理失败：未达上限时按退避重新排队，否则标记失败；返回更新后的状态
+    pub fn fail(&self, id: &str, error: &str) -> Result<MailStatus, MailboxError> {
+        let mut msg = self.get(id)?.ok_or_else(|| MailboxError::NotFound(id.to_string()))?;
+        let now = Utc::now().timestamp();
+        msg.error = Some(error.to_string());
+        if msg.attempts >= MAX_DELIVERY_ATTEMPTS {
+            msg.status = MailStatus::Failed;
+            msg.processed_at = Some(now);
+        } else {
+            msg.status = MailStatus::Pending;
+            msg.available_at = now + (RETRY_BACKOFF_SECS << msg.attempts.saturating_sub(1).min(10));
+        }
+        Self::save(&self.conn(), &msg)?;
+        Ok(msg.status)
+    }
+
+    /// 会话链中的全部信件，按时间顺序
+    pub fn thread(&self, thread_id: &str) -> Result<Vec<MailMessage>, StoreError> {
+        Self::query(
+            &self.conn(),
+            "SELECT data FROM mailbox WHERE json_extract(data, '$.thread_id') = ?1 ORDER BY created_at, rowid",
+            [thread_id],
+        )
+    }
+}
+
+/// 信件的处理端：让收件人处理信件，返回其回复
+#[async_trait]
+pub trait MailboxHandler: Send + Sync {
+    async fn handle(&self, msg: &MailMessage) -> Result<String, String>;
+}
+
+/// 处理一封已领取的信：成功时记录回复（request 自动回信），失败时按重试策略重新排队；返回处理后的状态
+pub async fn deliver(mailbox: &Mailbox, handler: &dyn MailboxHandler, msg: MailMessage) -> MailStatus {
+    let result = CURRENT_MAIL.scope(msg.clone(), handler.handle(&msg)).await;
+    let outcome = match result {
+        Ok(reply) => {
+            if msg.kind == MailKind::Request {
+                match mailbox.send(&msg.reply(&reply)) {
+                    Ok(()) => {}
+                    Err(MailboxError::ThreadTooDeep(thread)) => {
+                        tracing::info!(mail = %msg.id, %thread, "thread too deep, reply not sent");
+                    }
+                    Err(e) => tracing::warn!(mail = %msg.id, "failed to send reply: {}", e),
+                }
+            }
+            mailbox.complete(&msg.id, &reply).map(|_| MailStatus::Processed)
+        }
+        Err(e) => {
+            tracing::warn!(mail = %msg.id, to = %msg.to, attempt = msg.attempts, "mail processing failed: {}", e);
+            mailbox.fail(&msg.id, &e)
+        }
+    };
+    outcome.unwrap_or_else(|e| {
+        tracing::warn!(mail = %msg.id, "failed to update mail: {}", e);
+        MailStatus::Failed
+    })
+}
+
+impl TaskScheduler {
+    /// 后台处理信箱：有新信时立即、否则每 poll 检查一次（含重试与租约到期的信件）；
+    /// 不同收件人并行处理，同一收件人按序
+    pub fn spawn_mailbox(mailbox: Arc<Mailbox>, handler: Arc<dyn MailboxHandler>, poll: Duration) -> JoinHandle<()> {
+        tracing::info!("mailbox processor started, poll {}s", poll.as_secs());
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll.max(Duration::from_secs(1)));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = wake().notified() => {}
+                }
+                loop {
+                    let msg = match mailbox.claim_next(Utc::now(), None) {
+                        Ok(Some(msg)) => msg,
+                        Ok(None) => break,
+                        Err(e) => {
+                            tracing::warn!("failed to claim mail: {}", e);
+                            break;
+                        }
+                    };
+                    let (mailbox, handler) = (Arc::clone(&mailbox), Arc::clone(&handler));
+                    tokio::spawn(async move {
+                        tracing::info!(mail = %msg.id, from = %msg.from, to = %msg.to, "processing mail");
+                        deliver(&mailbox, handler.as_ref(), msg).await;
+                        // 收件人空闲后可能还有排队的信
+                        wake().notify_one();
+                    });
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 回显处理端：内容为 "fail" 时失败
+    struct Echo;
+
+    #[async_trait]
+    impl MailboxHandler for Echo {
+        async fn handle(&self, msg: &MailMessage) -> Result<String, String> {
+            if msg.content == "fail" {
+                return Err("boom".to_string());
+            }
+            let depth = CURRENT_MAIL.with(|m| m.depth);
+            Ok(format!("{} got '{}' at depth {}", msg.to, msg.content, depth))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_delivery_and_replies() {
+        assert_eq!(p2p_group_id("research", "code"), "p2p_code_research");
+        let dir = tempfile::tempdir().unwrap();
+        let mailbox = Mailbox::open(dir.path()).unwrap();
+        assert!(matches!(
+            mailbox.send(&MailMessage::new("code", "code", MailKind::Notice, "hi")),
+            Err(MailboxError::SelfMessage)
+        ));
+
+        let first = MailMessage::new("code", "research", MailKind::Request, "find docs");
+        let second = MailMessage::new("default", "research", MailKind::Notice, "fyi");
+        mailbox.send(&first).unwrap();
+        mailbox.send(&second).unwrap();
+        assert_eq!(mailbox.unread_count("research").unwrap(), 2);
+
+        // 同一收件人同时只领取一封，按投递顺序
+        let now = Utc::now();
+        let claimed = mailbox.claim_next(now, Some("research")).unwrap().unwrap();
+        assert_eq!((claimed.id.as_str(), claimed.attempts), (first.id.as_str(), 1));
+        assert!(mailbox.claim_next(now, None).unwrap().is_none());
+
+        // 处理 request 后自动回信给发送方
+        assert_eq!(deliver(&mailbox, &Echo, claimed).await, MailStatus::Processed);
+        let done = mailbox.get(&first.id).unwrap().unwrap();
+        assert!(!done.is_unread());
+        assert_eq!(done.reply.as_deref(), Some("research got 'find docs' at depth 0"));
+        let replies = mailbox.inbox("code", true, 10).unwrap();
+        assert_eq!(replies.len(), 1);
+        assert_eq!((replies[0].kind, replies[0].depth), (MailKind::Reply, 1));
+        assert_eq!(replies[0].reply_to.as_deref(), Some(first.id.as_str()));
+        assert_eq!(mailbox.thread(&first.id).unwrap().len(), 2);
+
+        // reply 与 notice 不再回信
+        let reply = mailbox.claim_next(now, Some("code")).unwrap().unwrap();
+        deliver(&mailbox, &Echo, reply).await;
+        let notice = mailbox.claim_next(now, None).unwrap().unwrap();
+        assert_eq!(notice.id, second.id);
+        deliver(&mailbox, &Echo, notice).await;
+        assert!(mailbox.inbox("default", false, 10).unwrap().is_empty());
+        assert_eq!(mailbox.unread_count("research").unwrap(), 0);
+
+        // 中断（租约到期）的信重新领取；失败按退避重试，超过次数后标记失败
+        let broken = MailMessage::new("code", "research", MailKind::Notice, "fail");
+        mailbox.send(&broken).unwrap();
+        mailbox.claim_next(now, None).unwrap().unwrap();
+        let later = now + chrono::Duration::from_std(CLAIM_LEASE).unwrap() + chrono::Duration::seconds(1);
+        let reclaimed = mailbox.claim_next(later, None).unwrap().unwrap();
+        assert_eq!(reclaimed.attempts, 2);
+        assert_eq!(deliver(&mailbox, &Echo, reclaimed).await, MailStatus::Pending);
+        assert!(mailbox.claim_next(Utc::now(), None).unwrap().is_none());
+        let retry = mailbox
+            .claim_next(Utc::now() + chrono::Duration::hours(1), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(deliver(&mailbox, &Echo, retry).await, MailStatus::Failed);
+        assert_eq!(mailbox.get(&broken.id).unwrap().unwrap().error.as_deref(), Some("boom"));
+
+        // 会话链深度上限
+        let mut deep = MailMessage::new("code", "research", MailKind::Request, "again");
+        deep.depth = MAX_THREAD_DEPTH + 1;
+        assert!(matches!(mailbox.send(&deep), Err(MailboxError::ThreadTooDeep(_))));
+        assert!(mailbox.mark_read("missing").is_err());
+    }
+}
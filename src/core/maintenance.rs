@@ -0,0 +1,253 @@
+//! 后台记忆整理调度
+//!
+//! 按 [memory.maintenance] 配置周期性整理记忆：对全局 memory/ 与各助手 memory/{assistant_id}/
+//! 分别把最近的每日日志归纳进长期记忆（截断式或 LLM 摘要），随后清理近似重复的长期记忆块。
+//! 每个目标独立计时，可按助手覆盖周期与策略。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::agent::consolidate_root_with_llm;
+use crate::config::{ConsolidationStrategy, MaintenanceSection};
+use crate::memory::{consolidate_memory, long_term_path, memory_root, FileLongTerm};
+use crate::react::Planner;
+
+/// memory/ 下不属于助手的保留目录
+const RESERVED_DIRS: &[&str] = &["logs", "users", "ns"];
+
+/// 整理目标：全局记忆（assistant_id 为 None）或单个助手
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceTarget {
+    pub assistant_id: Option<String>,
+    pub root: PathBuf,
+}
+
+/// 单个目标的一次整理结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceReport {
+    pub assistant_id: Option<String>,
+    pub strategy: &'static str,
+    pub dates_processed: Vec<String>,
+    pub blocks_added: usize,
+    pub duplicates_removed: usize,
+}
+
+/// 记忆整理调度器：由调用方定期 tick，到期的目标才会执行
+pub struct MemoryMaintenanceScheduler {
+    config: MaintenanceSection,
+    workspace: PathBuf,
+    started_at: Instant,
+    last_run: HashMap<PathBuf, Instant>,
+}
+
+impl MemoryMaintenanceScheduler {
+    pub fn new(config: MaintenanceSection, workspace: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            workspace: workspace.into(),
+            started_at: Instant::now(),
+            last_run: HashMap::new(),
+        }
+    }
+
+    /// 建议的 tick 间隔：最短整理周期，限制在 1 分钟 ~ 1 小时
+    pub fn check_interval(&self) -> Duration {
+        let min_hours = self
+            .config
+            .overrides
+            .values()
+            .filter_map(|o| o.interval_hours)
+            .chain(std::iter::once(self.config.interval_hours))
+            .min()
+            .unwrap_or(self.config.interval_hours);
+        Duration::from_secs((min_hours * 3600).clamp(60, 3600))
+    }
+
+    /// 当前需要整理的目标：配置了 assistants 时只取这些助手，否则为全局记忆 + memory/ 下所有助手目录
+    pub fn targets(&self) -> Vec<MaintenanceTarget> {
+        let root = memory_root(&self.workspace);
+        if !self.config.assistants.is_empty() {
+            return self
+                .config
+                .assistants
+                .iter()
+                .map(|id| MaintenanceTarget {
+                    assistant_id: Some(id.clone()),
+                    root: crate::memory::assistant_memory_root(&self.workspace, id),
+                })
+                .collect();
+        }
+        let mut targets = vec![MaintenanceTarget {
+            assistant_id: None,
+            root: root.clone(),
+        }];
+        let mut assistants: Vec<String> = std::fs::read_dir(&root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|name| !RESERVED_DIRS.contains(&name.as_str()))
+            .collect();
+        assistants.sort();
+        targets.extend(assistants.into_iter().map(|id| MaintenanceTarget {
+            root: root.join(&id),
+            assistant_id: Some(id),
+        }));
+        targets
+    }
+
+    /// 目标的生效配置：(是否启用, 周期, 策略)
+    fn settings_for(&self, assistant_id: Option<&str>) -> (bool, Duration, ConsolidationStrategy) {
+        let ov = assistant_id.and_then(|id| self.config.overrides.get(id));
+        let enabled = ov.and_then(|o| o.enabled).unwrap_or(self.config.enabled);
+        let hours = ov
+            .and_then(|o| o.interval_hours)
+            .unwrap_or(self.config.interval_hours);
+        let strategy = ov.and_then(|o| o.strategy).unwrap_or(self.config.strategy);
+        (enabled, Duration::from_secs(hours * 3600), strategy)
+    }
+
+    /// 在 now 时刻到期的目标及其策略（首次计时从调度器创建时开始）
+    pub fn due_targets(&self, now: Instant) -> Vec<(MaintenanceTarget, ConsolidationStrategy)> {
+        self.targets()
+            .into_iter()
+            .filter_map(|t| {
+                let (enabled, interval, strategy) = self.settings_for(t.assistant_id.as_deref());
+                let last = self.last_run.get(&t.root).copied().unwrap_or(self.started_at);
+                (enabled && now.saturating_duration_since(last) >= interval).then_some((t, strategy))
+            })
+            .collect()
+    }
+
+    /// 整理所有到期目标；planner 为 None 时 LLM 策略退化为截断式
+    pub async fn tick(&mut self, planner: Option<&Planner>) -> Vec<MaintenanceReport> {
+        let now = Instant::now();
+        let mut reports = Vec::new();
+        for (target, strategy) in self.due_targets(now) {
+            match run_target(&target, strategy, planner, &self.config).await {
+                Ok(r) => reports.push(r),
+                Err(e) => tracing::warn!(root = %target.root.display(), "memory maintenance failed: {}", e),
+            }
+            self.last_run.insert(target.root, now);
+        }
+        reports
+    }
+}
+
+/// 对单个目标执行一次整理：归纳每日日志 → 长期记忆去重
+pub async fn run_target(
+    target: &MaintenanceTarget,
+    strategy: ConsolidationStrategy,
+    planner: Option<&Planner>,
+    config: &MaintenanceSection,
+) -> anyhow::Result<MaintenanceReport> {
+    let (label, consolidated) = match (strategy, planner) {
+        (ConsolidationStrategy::Llm, Some(planner)) => (
+            "llm",
+            consolidate_root_with_llm(planner, &target.root, config.since_days).await?,
+        ),
+        _ => ("truncate", consolidate_memory(&target.root, config.since_days)?),
+    };
+    let duplicates_removed = dedup_long_term(&target.root, config.dedup_threshold);
+    if consolidated.blocks_added > 0 || duplicates_removed > 0 {
+        tracing::info!(
+            assistant = target.assistant_id.as_deref().unwrap_or("(global)"),
+            strategy = label,
+            blocks_added = consolidated.blocks_added,
+            duplicates_removed,
+            "memory maintenance"
+        );
+    }
+    Ok(MaintenanceReport {
+        assistant_id: target.assistant_id.clone(),
+        strategy: label,
+        dates_processed: consolidated.dates_processed,
+        blocks_added: consolidated.blocks_added,
+        duplicates_removed,
+    })
+}
+
+/// 长期记忆去重（不限条数加载，避免重写时截掉旧块）
+fn dedup_long_term(root: &Path, threshold: f32) -> usize {
+    let path = long_term_path(root);
+    if threshold <= 0.0 || !path.exists() {
+        return 0;
+    }
+    FileLongTerm::new(path, usize::MAX).dedup_near_duplicates(threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MaintenanceOverride;
+    use crate::memory::{append_daily_log, assistant_memory_root, Message};
+
+    #[test]
+    fn test_maintenance_scheduler_due_and_dedup() {
+        let dir = tempfile::tempdir().unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let coder_root = assistant_memory_root(dir.path(), "coder");
+        append_daily_log(
+            &coder_root,
+            &today,
+            "s1",
+            &[Message::user("how do I parse toml"), Message::assistant("use the toml crate")],
+        )
+        .unwrap();
+        std::fs::create_dir_all(assistant_memory_root(dir.path(), "quiet")).unwrap();
+
+        let mut config = MaintenanceSection::default();
+        config.overrides.insert(
+            "quiet".to_string(),
+            MaintenanceOverride {
+                enabled: Some(false),
+                ..Default::default()
+            },
+        );
+        config.overrides.insert(
+            "coder".to_string(),
+            MaintenanceOverride {
+                interval_hours: Some(1),
+                ..Default::default()
+            },
+        );
+        let scheduler = MemoryMaintenanceScheduler::new(config.clone(), dir.path());
+        assert_eq!(scheduler.check_interval(), Duration::from_secs(3600));
+
+        let ids = |due: Vec<(MaintenanceTarget, ConsolidationStrategy)>| {
+            due.into_iter().map(|(t, _)| t.assistant_id).collect::<Vec<_>>()
+        };
+        let start = Instant::now();
+        assert!(scheduler.due_targets(start).is_empty());
+        assert_eq!(
+            ids(scheduler.due_targets(start + Duration::from_secs(2 * 3600))),
+            vec![Some("coder".to_string())]
+        );
+        assert_eq!(
+            ids(scheduler.due_targets(start + Duration::from_secs(25 * 3600))),
+            vec![None, Some("coder".to_string())]
+        );
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let target = MaintenanceTarget {
+            assistant_id: Some("coder".to_string()),
+            root: coder_root.clone(),
+        };
+        let first = rt
+            .block_on(run_target(&target, ConsolidationStrategy::Truncate, None, &config))
+            .unwrap();
+        assert_eq!(first.blocks_added, 1);
+        // 再次整理同一天日志产生重复块，去重后仅保留一块
+        let second = rt
+            .block_on(run_target(&target, ConsolidationStrategy::Llm, None, &config))
+            .unwrap();
+        assert_eq!(second.strategy, "truncate");
+        assert!(second.duplicates_removed > 0);
+        let content = std::fs::read_to_string(long_term_path(&coder_root)).unwrap();
+        assert_eq!(content.matches("toml crate").count(), 1);
+    }
+}
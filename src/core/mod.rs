@@ -1,25 +1,71 @@
-//! 核心编排层：错误与恢复、状态投影、会话监管、任务调度、主控循环
+//! 核心编排层：错误与恢复、状态投影、会话监管、看门狗、任务调度、看板编排、助手信箱、文件监听、主控循环、提示词库、启动自检、认证、多用户、限流
 //!
 //! 白皮书 §3.1 命名对应：`MemoryManager` = ContextManager，`ToolBox` = ToolExecutor，
 //! `InternalState` 的投影源 = InternalStateSnapshot（memory/tool_box 由 Orchestrator 分别持有）。
 
+pub mod auth;
 pub mod builder;
+pub mod doctor;
 pub mod error;
+pub mod file_watch;
+pub mod kanban;
+pub mod mailbox;
+pub mod maintenance;
 pub mod orchestrator;
+pub mod prompt_library;
+pub mod rate_limit;
 pub mod recovery;
+pub mod scheduled_jobs;
 pub mod session_supervisor;
+pub mod share;
 pub mod shutdown;
 pub mod state;
 pub mod task_scheduler;
+pub mod tenant;
+pub mod watchdog;
+pub mod workspace_store;
 
+pub use auth::{bearer_token, AuthError, Authenticator, Principal, PrincipalKind, Scope};
 pub use builder::{create_agent_builder, AgentBuilder, AgentComponents};
+pub use doctor::{run_diagnostics, CheckStatus, DiagnosticCheck, DiagnosticsReport};
 pub use error::{AgentError, RecoveryAction};
+pub use file_watch::{
+    normalize_watch_pattern, FileChange, FileChangeKind, FileWatchSink, WatchRule, WatchStore, WorkspaceScanner,
+};
+pub use kanban::{
+    next_occurrence, parse_due, refresh_blocked, unblock_ready_tasks, unmet_dependencies, validate_dependencies,
+    DependencyError,
+};
+pub use mailbox::{
+    deliver, p2p_group_id, MailKind, MailMessage, MailStatus, Mailbox, MailboxError, MailboxHandler, CURRENT_MAIL,
+    MAX_DELIVERY_ATTEMPTS, MAX_THREAD_DEPTH,
+};
+pub use maintenance::{MaintenanceReport, MaintenanceTarget, MemoryMaintenanceScheduler};
 pub use orchestrator::{create_agent, Command};
-pub use recovery::RecoveryEngine;
+pub use prompt_library::{
+    render_template, template_variables, DiffLine, PromptError, PromptLibrary, PromptVersion, PromptVersionInfo,
+};
+pub use rate_limit::{Admission, LoopPermit, RateLimitError, RateLimiter, RateLimits};
+pub use recovery::{ErrorClass, RecoveryActionKind, RecoveryDecision, RecoveryEngine, RecoveryHook, RecoveryPolicy};
+pub use scheduled_jobs::{
+    run_job, JobConfig, JobRun, JobRunStatus, JobSchedule, JobSink, JobSource, JobStore, RunningJob, ScheduledJob,
+    MAX_RUNS_PER_JOB,
+};
 pub use session_supervisor::SessionSupervisor;
+pub use share::{ShareClaims, ShareError, ShareSigner};
 pub use state::{AgentPhase, InternalStateSnapshot, UiState};
 pub use shutdown::{run_with_graceful_shutdown, ShutdownCleanup, ShutdownCoordinator, ShutdownManager, ShutdownReason};
-pub use task_scheduler::{TaskKind, TaskScheduler};
+pub use task_scheduler::{
+    current_priority, fire_due_reminders, AssistantLoad, CronSchedule, LaneSnapshot, Reminder, ReminderOrigin,
+    ReminderSink, ReminderStore, SchedulerSnapshot, TaskKind, TaskScheduler, WorkItem, WorkLane, WorkPermit,
+    WorkPriority, CURRENT_PRIORITY,
+};
+pub use tenant::{QuotaError, QuotaLimits, QuotaUsage, RunGuard, Tenancy, UserId};
+pub use watchdog::{RunRegistration, SessionWatchdog, WatchedSession};
+pub use workspace_store::{
+    GroupInfo, GroupMode, GroupRepository, SessionMeta, SessionMetaRepository, SessionPromptVersion, SqliteWorkspaceStore,
+    StoreError, Task, TaskRepository, TaskStatus,
+};
 
 /// 白皮书 §3.1：记忆管理器，实现中即 [ContextManager](crate::react::ContextManager)
 pub type MemoryManager = crate::react::ContextManager;
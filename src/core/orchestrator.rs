@@ -1,7 +1,7 @@
 //! Agent 编排器：主控循环
 //!
 //! 负责：加载配置、创建 LLM/工具/Planner/Recovery、建立 cmd/state/stream 三通道，
-//! 并在后台任务中消费用户命令（Submit/Cancel/Clear/Quit），驱动 ReAct 循环并更新 UI 状态。
+//! 并在后台任务中消费用户命令（Submit/Cancel/Clear/Quit/Approve），驱动 ReAct 循环并更新 UI 状态。
 
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -12,7 +12,8 @@ use crate::config::AppConfig;
 use crate::core::{create_agent_builder, AgentPhase, SessionSupervisor, UiState};
 use crate::llm::{create_deepseek_client, LlmClient, OpenAiClient};
 use crate::memory::{InMemoryLongTerm, SqlitePersistence};
-use crate::react::{react_loop, ContextManager};
+use crate::react::{react_loop_v2, ContextManager, PendingQuestion, QuestionBroker, ReactEvent, ReactSession};
+use crate::tools::{ApprovalBroker, ApprovalRequest};
 
 /// 从 UI 发往编排器的用户命令
 #[derive(Debug, Clone)]
@@ -25,6 +26,10 @@ pub enum Command {
     Clear,
     /// 退出应用
     Quit,
+    /// 批准或拒绝一次待审批的工具调用（[tools.policy]）
+    Approve { id: String, approved: bool },
+    /// 回答循环中等待的问题（AskUser）
+    Answer { id: String, text: String },
 }
 
 /// 根据配置与环境变量选择 LLM 后端（DeepSeek / OpenAI 兼容 / Mock）
@@ -78,11 +83,13 @@ pub async fn create_agent(
     let components = builder.build_components();
     let workspace = builder.workspace().to_path_buf();
     let cfg = builder.config().clone();
+    let react_limits = components.react_limits();
 
     let planner = components.planner;
     let executor = components.executor;
     let recovery = components.recovery;
     let critic = components.critic;
+    let guardrails = components.guardrails;
     let task_scheduler = components.task_scheduler;
     let supervisor = SessionSupervisor::new();
 
@@ -133,10 +140,7 @@ pub async fn create_agent(
                             {
                                 let persistence = sqlite_persistence_clone.lock().await;
                                 if let Some(ref p) = *persistence {
-                                    let _ = p.save_message(&session_id_clone, &crate::memory::Message {
-                                        role: crate::memory::Role::User,
-                                        content: input.clone(),
-                                    });
+                                    let _ = p.save_message(&session_id_clone, &crate::memory::Message::user(input.clone()));
                                 }
                             }
 
@@ -147,22 +151,85 @@ pub async fn create_agent(
                                 active_tool: None,
                                 input_locked: true,
                                 error_message: None,
+                                pending_approval: None,
+                                pending_question: None,
                             });
+                            let thinking_history = context.conversation.messages().to_vec();
 
-                            let result = react_loop(
-                                &planner,
-                                &executor,
-                                &recovery,
-                                &mut context,
-                                &input,
-                                Some(&stream_tx),
-                                None,
-                                cancel_token,
-                                critic.as_ref(),
-                                Some(&task_scheduler),
-                                None,
-                                None,
-                            ).await;
+                            // 循环运行期间继续处理命令：审批结果与取消需在循环阻塞时送达
+                            let (event_tx, mut event_rx) = mpsc::unbounded_channel::<ReactEvent>();
+                            let result = {
+                                let mut session = ReactSession::new(&planner, &executor, &recovery, cancel_token)
+                                    .with_stream_tx(&stream_tx)
+                                    .with_event_tx(&event_tx)
+                                    .with_task_scheduler(&task_scheduler)
+                                    .with_limits(react_limits)
+                                    .with_guardrails(guardrails.as_ref());
+                                if let Some(c) = critic.as_ref() {
+                                    session = session.with_critic(c);
+                                }
+                                let run = react_loop_v2(&session, &mut context, &input);
+                                tokio::pin!(run);
+                                loop {
+                                    tokio::select! {
+                                        result = &mut run => break result,
+                                        Some(ev) = event_rx.recv() => match ev {
+                                            ReactEvent::ApprovalRequired { id, tool, args, risk } => {
+                                                let _ = state_tx.send(UiState {
+                                                    phase: AgentPhase::ToolExecuting,
+                                                    history: thinking_history.clone(),
+                                                    active_tool: Some(tool.clone()),
+                                                    input_locked: true,
+                                                    error_message: None,
+                                                    pending_approval: Some(ApprovalRequest { id, tool, args, risk, assistant_id: None, user_id: None }),
+                                                    pending_question: None,
+                                                });
+                                            }
+                                            // 等待回答时解锁输入框，Enter 送回回答
+                                            ReactEvent::AskUser { id, question } => {
+                                                let _ = state_tx.send(UiState {
+                                                    phase: AgentPhase::Thinking,
+                                                    history: thinking_history.clone(),
+                                                    active_tool: None,
+                                                    input_locked: false,
+                                                    error_message: None,
+                                                    pending_approval: None,
+                                                    pending_question: Some(PendingQuestion { id, question, session_id: None, assistant_id: None, user_id: None }),
+                                                });
+                                            }
+                                            _ => {}
+                                        },
+                                        Some(cmd) = cmd_rx.recv() => match cmd {
+                                            Command::Approve { id, approved } => {
+                                                ApprovalBroker::global().resolve(&id, approved);
+                                                let _ = state_tx.send(UiState {
+                                                    phase: AgentPhase::ToolExecuting,
+                                                    history: thinking_history.clone(),
+                                                    active_tool: None,
+                                                    input_locked: true,
+                                                    error_message: None,
+                                                    pending_approval: None,
+                                                    pending_question: None,
+                                                });
+                                            }
+                                            Command::Answer { id, text } => {
+                                                QuestionBroker::global().answer(&id, &text);
+                                                let _ = state_tx.send(UiState {
+                                                    phase: AgentPhase::Thinking,
+                                                    history: thinking_history.clone(),
+                                                    active_tool: None,
+                                                    input_locked: true,
+                                                    error_message: None,
+                                                    pending_approval: None,
+                                                    pending_question: None,
+                                                });
+                                            }
+                                            Command::Cancel => supervisor.cancel(),
+                                            other => tracing::debug!(?other, "command ignored while agent is running"),
+                                        },
+                                    }
+                                }
+                            };
 
                             match result {
                                 Ok(react_result) => {
@@ -182,6 +249,8 @@ pub async fn create_agent(
                                         active_tool: None,
                                         input_locked: false,
                                         error_message: None,
+                                        pending_approval: None,
+                                        pending_question: None,
                                     });
                                 }
                                 Err(e) => {
@@ -191,6 +260,8 @@ pub async fn create_agent(
                                         active_tool: None,
                                         input_locked: false,
                                         error_message: Some(e.to_string()),
+                                        pending_approval: None,
+                                        pending_question: None,
                                     });
                                 }
                             }
@@ -208,9 +279,18 @@ pub async fn create_agent(
                                 active_tool: None,
                                 input_locked: false,
                                 error_message: None,
+                                pending_approval: None,
+                                pending_question: None,
                             });
                         }
                         Command::Quit => break,
+                        // 审批只在循环运行中有效，空闲时收到的是过期请求
+                        Command::Approve { id, approved } => {
+                            ApprovalBroker::global().resolve(&id, approved);
+                        }
+                        Command::Answer { id, text } => {
+                            QuestionBroker::global().answer(&id, &text);
+                        }
                     }
                 }
                 else => break,  // cmd_tx 已关闭，退出循环
@@ -0,0 +1,411 @@
+//! 提示词库
+//!
+//! 把 config/prompts 下的 *.md 视为带版本的提示词：每次保存（含回滚）追加一个版本，
+//! 历史存于 prompts/.history/{id}.json；在库外直接修改的文件在下次读取时记为新版本。
+//! 会话元数据记录所用版本号，便于排查行为变化。
+//!
+//! 提示词即模板：`{{name}}` 为变量，`{{name|默认值}}` 带默认值，由 [PromptLibrary::render] 代入。
+//! Planner（`[prompts] system`）、Critic（`[prompts] critic`）与 assistants.toml 的 `prompt` 都以模板 id 引用，
+//! 提示词的每次变化都有版本可查。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// 历史目录（相对 prompts 目录）
+const HISTORY_DIR: &str = ".history";
+
+/// 提示词库错误
+#[derive(Debug, Error)]
+pub enum PromptError {
+    #[error("invalid prompt id: {0}")]
+    InvalidId(String),
+    #[error("prompt not found: {0}")]
+    NotFound(String),
+    #[error("prompt {0} has no version {1}")]
+    VersionNotFound(String, u32),
+    #[error("prompt {0} requires variable '{1}'")]
+    MissingVariable(String, String),
+    #[error("prompt io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// 单个历史版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptVersion {
+    pub version: u32,
+    /// 内容 SHA-256 前 12 位
+    pub hash: String,
+    /// 保存时间（RFC 3339）
+    pub saved_at: String,
+    /// 修改说明（如 "rollback to v2"、"external edit"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub content: String,
+}
+
+/// 版本摘要（列表与历史展示用，不含内容）
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptVersionInfo {
+    pub version: u32,
+    pub hash: String,
+    pub saved_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl From<&PromptVersion> for PromptVersionInfo {
+    fn from(v: &PromptVersion) -> Self {
+        Self {
+            version: v.version,
+            hash: v.hash.clone(),
+            saved_at: v.saved_at.clone(),
+            note: v.note.clone(),
+        }
+    }
+}
+
+/// 行级差异
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", content = "line", rename_all = "snake_case")]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// 版本化的提示词库（目录通常为 config/prompts）
+pub struct PromptLibrary {
+    dir: PathBuf,
+}
+
+impl PromptLibrary {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// 默认库目录：依次查找 config/prompts、../config/prompts（在 bee 目录或其子目录下运行均可）
+    pub fn discover(config_base: &Path) -> Self {
+        let dir = [config_base.join("prompts"), Path::new("../config/prompts").to_path_buf()]
+            .into_iter()
+            .find(|p| p.is_dir())
+            .unwrap_or_else(|| config_base.join("prompts"));
+        Self::new(dir)
+    }
+
+    /// 引用（模板 id 如 "system"，或旧式路径如 "prompts/system.md"）对应的模板 id；不在库内时为 None
+    pub fn id_for_ref(reference: &str) -> Option<String> {
+        if valid_id(reference) {
+            return Some(reference.to_string());
+        }
+        Self::id_for_path(reference)
+    }
+
+    /// 由 assistants.toml 的 prompt 路径（如 "prompts/system.md"）得到提示词 id；不在库内时为 None
+    pub fn id_for_path(prompt_path: &str) -> Option<String> {
+        let path = Path::new(prompt_path);
+        let in_library = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .is_some_and(|name| name == "prompts");
+        if !in_library || path.extension().is_none_or(|e| e != "md") {
+            return None;
+        }
+        let id = path.file_stem()?.to_str()?.to_string();
+        valid_id(&id).then_some(id)
+    }
+
+    /// 库中全部提示词 id（按名称排序）
+    pub fn list(&self) -> Vec<String> {
+        let mut ids: Vec<String> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| {
+                let path = e.path();
+                if path.extension().is_none_or(|ext| ext != "md") {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            })
+            .filter(|id| valid_id(id))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// 当前内容
+    pub fn content(&self, id: &str) -> Result<String, PromptError> {
+        let path = self.prompt_path(id)?;
+        std::fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => PromptError::NotFound(id.to_string()),
+            _ => PromptError::Io(e),
+        })
+    }
+
+    /// 全部历史版本（旧 → 新）；文件被库外修改或尚无历史时先记录当前内容为新版本
+    pub fn history(&self, id: &str) -> Result<Vec<PromptVersion>, PromptError> {
+        let content = self.content(id)?;
+        let mut history = self.load_history(id);
+        if history.last().map(|v| v.hash.as_str()) != Some(content_hash(&content).as_str()) {
+            let note = (!history.is_empty()).then(|| "external edit".to_string());
+            push_version(&mut history, content, note);
+            self.save_history(id, &history)?;
+        }
+        Ok(history)
+    }
+
+    /// 当前版本号
+    pub fn current_version(&self, id: &str) -> Result<u32, PromptError> {
+        Ok(self.history(id)?.last().map(|v| v.version).unwrap_or(0))
+    }
+
+    /// 指定版本
+    pub fn version(&self, id: &str, version: u32) -> Result<PromptVersion, PromptError> {
+        self.history(id)?
+            .into_iter()
+            .find(|v| v.version == version)
+            .ok_or_else(|| PromptError::VersionNotFound(id.to_string(), version))
+    }
+
+    /// 保存新内容并返回版本号；内容未变化时不产生新版本
+    pub fn save(&self, id: &str, content: &str, note: Option<String>) -> Result<u32, PromptError> {
+        let path = self.prompt_path(id)?;
+        let mut history = if path.exists() { self.history(id)? } else { self.load_history(id) };
+        if let Some(last) = history.last() {
+            if last.hash == content_hash(content) {
+                return Ok(last.version);
+            }
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&path, content)?;
+        let version = push_version(&mut history, content.to_string(), note);
+        self.save_history(id, &history)?;
+        Ok(version)
+    }
+
+    /// 回滚到指定版本：以该版本内容保存为新版本（历史保持只增）
+    pub fn rollback(&self, id: &str, version: u32) -> Result<u32, PromptError> {
+        let target = self.version(id, version)?;
+        self.save(id, &target.content, Some(format!("rollback to v{}", version)))
+    }
+
+    /// 以 vars 代入当前版本的模板变量
+    pub fn render(&self, id: &str, vars: &HashMap<String, String>) -> Result<String, PromptError> {
+        render_template(&self.content(id)?, vars).map_err(|name| PromptError::MissingVariable(id.to_string(), name))
+    }
+
+    /// 两个版本之间的行级差异
+    pub fn diff(&self, id: &str, from: u32, to: u32) -> Result<Vec<DiffLine>, PromptError> {
+        let history = self.history(id)?;
+        let find = |v: u32| {
+            history
+                .iter()
+                .find(|x| x.version == v)
+                .ok_or_else(|| PromptError::VersionNotFound(id.to_string(), v))
+        };
+        Ok(diff_lines(&find(from)?.content, &find(to)?.content))
+    }
+
+    fn prompt_path(&self, id: &str) -> Result<PathBuf, PromptError> {
+        if !valid_id(id) {
+            return Err(PromptError::InvalidId(id.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.md", id)))
+    }
+
+    fn history_path(&self, id: &str) -> PathBuf {
+        self.dir.join(HISTORY_DIR).join(format!("{}.json", id))
+    }
+
+    fn load_history(&self, id: &str) -> Vec<PromptVersion> {
+        std::fs::read_to_string(self.history_path(id))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_history(&self, id: &str, history: &[PromptVersion]) -> Result<(), PromptError> {
+        let path = self.history_path(id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(history)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// id 仅允许字母、数字、- 与 _，防止路径穿越
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn content_hash(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
+fn push_version(history: &mut Vec<PromptVersion>, content: String, note: Option<String>) -> u32 {
+    let version = history.last().map(|v| v.version + 1).unwrap_or(1);
+    history.push(PromptVersion {
+        version,
+        hash: content_hash(&content),
+        saved_at: chrono::Utc::now().to_rfc3339(),
+        note,
+        content,
+    });
+    version
+}
+
+/// 模板中的变量占位：(变量名, 默认值)，按首次出现的顺序去重
+pub fn template_variables(template: &str) -> Vec<(String, Option<String>)> {
+    let mut vars: Vec<(String, Option<String>)> = Vec::new();
+    for (_, name, default) in placeholders(template) {
+        if vars.iter().all(|(n, _)| n != name) {
+            vars.push((name.to_string(), default.map(str::to_string)));
+        }
+    }
+    vars
+}
+
+/// 代入模板变量；缺少且无默认值的变量返回其名称
+pub fn render_template(template: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut last = 0;
+    for (range, name, default) in placeholders(template) {
+        let value = vars.get(name).map(String::as_str).or(default).ok_or_else(|| name.to_string())?;
+        out.push_str(&template[last..range.start]);
+        out.push_str(value);
+        last = range.end;
+    }
+    out.push_str(&template[last..]);
+    Ok(out)
+}
+
+/// 扫描 `{{name}}` / `{{name|默认值}}`；变量名仅含字母、数字、_ 与 .，其余 `{{…}}` 原样保留
+fn placeholders(template: &str) -> Vec<(std::ops::Range<usize>, &str, Option<&str>)> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = template[pos..].find("{{").map(|i| pos + i) {
+        let Some(end) = template[start + 2..].find("}}").map(|i| start + 2 + i) else {
+            break;
+        };
+        let inner = &template[start + 2..end];
+        let (name, default) = match inner.split_once('|') {
+            Some((name, default)) => (name.trim(), Some(default.trim())),
+            None => (inner.trim(), None),
+        };
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            found.push((start..end + 2, name, default));
+            pos = end + 2;
+        } else {
+            pos = start + 2;
+        }
+    }
+    found
+}
+
+/// 基于最长公共子序列的行级 diff（提示词文件较小，O(n·m) 足够）
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::with_capacity(n.max(m));
+    while i < n && j < m {
+        if a[i] == b[j] {
+            out.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            out.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            out.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    out.extend(a[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    out.extend(b[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_library_versions_diff_and_rollback() {
+        let dir = PathBuf::from("./target/test_prompt_library");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("system.md"), "You are Bee.\nBe concise.").unwrap();
+        let lib = PromptLibrary::new(&dir);
+
+        assert_eq!(lib.list(), vec!["system".to_string()]);
+        assert_eq!(lib.current_version("system").unwrap(), 1);
+        assert_eq!(lib.save("system", "You are Bee.\nBe concise.", None).unwrap(), 1);
+        assert_eq!(lib.save("system", "You are Bee.\nBe friendly.", Some("tone".into())).unwrap(), 2);
+
+        // 库外修改记为新版本
+        std::fs::write(dir.join("system.md"), "You are Bee!").unwrap();
+        let history = lib.history("system").unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[2].note.as_deref(), Some("external edit"));
+
+        assert_eq!(
+            lib.diff("system", 1, 2).unwrap(),
+            vec![
+                DiffLine::Same("You are Bee.".into()),
+                DiffLine::Removed("Be concise.".into()),
+                DiffLine::Added("Be friendly.".into()),
+            ]
+        );
+
+        assert_eq!(lib.rollback("system", 1).unwrap(), 4);
+        assert_eq!(lib.content("system").unwrap(), "You are Bee.\nBe concise.");
+        assert!(matches!(lib.rollback("system", 9), Err(PromptError::VersionNotFound(_, 9))));
+        assert!(matches!(lib.content("../secret"), Err(PromptError::InvalidId(_))));
+
+        assert_eq!(PromptLibrary::id_for_path("prompts/assistant-media.md").as_deref(), Some("assistant-media"));
+        assert_eq!(PromptLibrary::id_for_path("/etc/bee/custom.md"), None);
+        assert_eq!(PromptLibrary::id_for_ref("critic").as_deref(), Some("critic"));
+        assert_eq!(PromptLibrary::id_for_ref("prompts/critic.md").as_deref(), Some("critic"));
+
+        // 模板变量：默认值、缺失报错，非变量的 {{…}} 与单花括号原样保留
+        lib.save("greet", "Hi {{ user }}, I am {{name|Bee}}. {{ not a var }} {goal}", None).unwrap();
+        assert_eq!(
+            template_variables(&lib.content("greet").unwrap()),
+            vec![("user".to_string(), None), ("name".to_string(), Some("Bee".to_string()))]
+        );
+        let mut vars = HashMap::from([("user".to_string(), "Ann".to_string())]);
+        assert_eq!(lib.render("greet", &vars).unwrap(), "Hi Ann, I am Bee. {{ not a var }} {goal}");
+        vars.insert("name".into(), "Max".into());
+        assert_eq!(lib.render("greet", &vars).unwrap(), "Hi Ann, I am Max. {{ not a var }} {goal}");
+        assert!(matches!(
+            lib.render("greet", &HashMap::new()),
+            Err(PromptError::MissingVariable(_, name)) if name == "user"
+        ));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,286 @@
+//! 限流：按「接入端:用户」的令牌桶（每分钟消息数）与 ReAct 循环并发名额
+//!
+//! Web 中间件与网关 Hub 在消息进入 ReAct 循环前调用 [`RateLimiter::check`] 扣减令牌，超出时返回 429（网关为 rate_limited 错误）；
+//! 随后由 [`RateLimiter::admit`] 领取循环名额：名额已满时排队，调用方推送 `ReactEvent::Queued` 后等待 [`Admission::start`]。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::RateLimitSection;
+
+/// 超过该数量的限流键时清理空闲的令牌桶与循环名额
+const MAX_TRACKED_KEYS: usize = 4096;
+
+/// 限流拒绝
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RateLimitError {
+    #[error("rate limit exceeded, retry after {retry_after_secs}s")]
+    TooManyMessages { retry_after_secs: u64 },
+    #[error("too many queued requests (limit {0})")]
+    QueueFull(u32),
+}
+
+impl RateLimitError {
+    /// 建议的重试等待（秒），用于 Retry-After
+    pub fn retry_after_secs(&self) -> u64 {
+        match self {
+            Self::TooManyMessages { retry_after_secs } => *retry_after_secs,
+            Self::QueueFull(_) => 1,
+        }
+    }
+}
+
+/// 某个接入端生效的限流参数（0 表示不限）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimits {
+    pub messages_per_minute: u32,
+    pub burst: u32,
+    pub max_concurrent_loops: u32,
+    pub max_queued: u32,
+}
+
+impl RateLimits {
+    fn capacity(&self) -> f64 {
+        if self.burst == 0 {
+            self.messages_per_minute as f64
+        } else {
+            self.burst as f64
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Lane {
+    capacity: usize,
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+}
+
+impl Lane {
+    fn idle(&self) -> bool {
+        self.semaphore.available_permits() == self.capacity && self.waiting.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// 令牌桶与循环名额，Web 与网关各持有一个
+pub struct RateLimiter {
+    enabled: bool,
+    defaults: RateLimits,
+    spokes: HashMap<String, RateLimits>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    lanes: Mutex<HashMap<String, Lane>>,
+}
+
+impl From<&RateLimitSection> for RateLimiter {
+    fn from(section: &RateLimitSection) -> Self {
+        let defaults = RateLimits {
+            messages_per_minute: section.messages_per_minute,
+            burst: section.burst,
+            max_concurrent_loops: section.max_concurrent_loops,
+            max_queued: section.max_queued,
+        };
+        let spokes = section
+            .spokes
+            .iter()
+            .map(|(name, o)| {
+                let limits = RateLimits {
+                    messages_per_minute: o.messages_per_minute.unwrap_or(defaults.messages_per_minute),
+                    burst: o.burst.unwrap_or(defaults.burst),
+                    max_concurrent_loops: o.max_concurrent_loops.unwrap_or(defaults.max_concurrent_loops),
+                    max_queued: o.max_queued.unwrap_or(defaults.max_queued),
+                };
+                (name.clone(), limits)
+            })
+            .collect();
+        Self {
+            enabled: section.enabled,
+            defaults,
+            spokes,
+            buckets: Mutex::default(),
+            lanes: Mutex::default(),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn disabled() -> Self {
+        Self::from(&RateLimitSection::default())
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn limits(&self, spoke: &str) -> RateLimits {
+        self.spokes.get(spoke).copied().unwrap_or(self.defaults)
+    }
+
+    /// 收到一条消息：从该用户的令牌桶扣一个令牌，桶空时返回需等待的秒数
+    pub fn check(&self, spoke: &str, user: &str) -> Result<(), RateLimitError> {
+        self.check_at(spoke, user, Instant::now())
+    }
+
+    fn check_at(&self, spoke: &str, user: &str, now: Instant) -> Result<(), RateLimitError> {
+        let limits = self.limits(spoke);
+        if !self.enabled || limits.messages_per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = limits.capacity();
+        let rate = limits.messages_per_minute as f64 / 60.0;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > MAX_TRACKED_KEYS {
+            // 已回满的桶与新建的桶等价，可以丢弃
+            buckets.retain(|_, b| b.tokens + now.saturating_duration_since(b.updated).as_secs_f64() * rate < capacity);
+        }
+        let bucket = buckets.entry(format!("{}:{}", spoke, user)).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let retry_after_secs = ((1.0 - bucket.tokens) / rate).ceil().max(1.0) as u64;
+            Err(RateLimitError::TooManyMessages { retry_after_secs })
+        }
+    }
+
+    /// 领取一个 ReAct 循环名额；名额已满时进入排队（[`Admission::position`]），排队已满时拒绝
+    pub fn admit(&self, spoke: &str, user: &str) -> Result<Admission, RateLimitError> {
+        let limits = self.limits(spoke);
+        if !self.enabled || limits.max_concurrent_loops == 0 {
+            return Ok(Admission::unlimited());
+        }
+        let mut lanes = self.lanes.lock().unwrap_or_else(|e| e.into_inner());
+        if lanes.len() > MAX_TRACKED_KEYS {
+            lanes.retain(|_, lane| !lane.idle());
+        }
+        let capacity = limits.max_concurrent_loops as usize;
+        let lane = lanes.entry(format!("{}:{}", spoke, user)).or_insert_with(|| Lane {
+            capacity,
+            semaphore: Arc::new(Semaphore::new(capacity)),
+            waiting: Arc::default(),
+        });
+        if let Ok(permit) = Arc::clone(&lane.semaphore).try_acquire_owned() {
+            return Ok(Admission {
+                permit: Some(permit),
+                lane: None,
+                position: None,
+            });
+        }
+        if limits.max_queued > 0 && lane.waiting.load(Ordering::SeqCst) >= limits.max_queued as usize {
+            return Err(RateLimitError::QueueFull(limits.max_queued));
+        }
+        let position = lane.waiting.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(Admission {
+            permit: None,
+            lane: Some((Arc::clone(&lane.semaphore), Arc::clone(&lane.waiting))),
+            position: Some(position),
+        })
+    }
+}
+
+/// 已通过限流的请求：拿到名额或在排队中，丢弃时退出排队
+pub struct Admission {
+    permit: Option<OwnedSemaphorePermit>,
+    lane: Option<(Arc<Semaphore>, Arc<AtomicUsize>)>,
+    position: Option<usize>,
+}
+
+impl Admission {
+    fn unlimited() -> Self {
+        Self {
+            permit: None,
+            lane: None,
+            position: None,
+        }
+    }
+
+    /// 排队时的位置（从 1 开始），可立即运行时为 None
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    /// 等到循环名额；返回的 permit 释放时名额归还
+    pub async fn start(mut self) -> LoopPermit {
+        if let Some(permit) = self.permit.take() {
+            return LoopPermit { _permit: Some(permit) };
+        }
+        let permit = match &self.lane {
+            Some((semaphore, _)) => Arc::clone(semaphore).acquire_owned().await.ok(),
+            None => None,
+        };
+        LoopPermit { _permit: permit }
+    }
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        if let Some((_, waiting)) = &self.lane {
+            waiting.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// 运行中的 ReAct 循环占用的名额
+pub struct LoopPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpokeRateLimit;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_token_bucket_and_loop_queue() {
+        let section = RateLimitSection {
+            enabled: true,
+            messages_per_minute: 60,
+            burst: 2,
+            max_concurrent_loops: 1,
+            max_queued: 1,
+            spokes: HashMap::from([(
+                "discord".to_string(),
+                SpokeRateLimit {
+                    messages_per_minute: Some(0),
+                    ..Default::default()
+                },
+            )]),
+        };
+        let limiter = RateLimiter::from(&section);
+        let t0 = Instant::now();
+        assert!(limiter.check_at("web", "alice", t0).is_ok());
+        assert!(limiter.check_at("web", "alice", t0).is_ok());
+        assert_eq!(
+            limiter.check_at("web", "alice", t0),
+            Err(RateLimitError::TooManyMessages { retry_after_secs: 1 })
+        );
+        assert!(limiter.check_at("web", "bob", t0).is_ok());
+        assert!(limiter.check_at("web", "alice", t0 + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_at("discord", "alice", t0).is_ok());
+
+        let first = limiter.admit("web", "alice").unwrap();
+        assert_eq!(first.position(), None);
+        let running = first.start().await;
+        let queued = limiter.admit("web", "alice").unwrap();
+        assert_eq!(queued.position(), Some(1));
+        assert_eq!(limiter.admit("web", "alice").err(), Some(RateLimitError::QueueFull(1)));
+        drop(running);
+        let _running = queued.start().await;
+        assert!(limiter.admit("web", "alice").is_ok());
+    }
+}
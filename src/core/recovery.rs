@@ -1,21 +1,235 @@
 //! 错误恢复引擎
 //!
 //! 根据 AgentError 类型返回 RecoveryAction，供 ReAct 循环决定是重试、剪枝、询问用户还是终止。
+//! [[recovery.policies]] 可按错误类别覆盖默认动作（重试次数、退避、降级模型、压缩、询问用户）；
+//! 实现 `RecoveryHook` 后用 `with_hook` 或插件注册表（`PluginRegistry::register_recovery_hook`）挂载自定义策略，
+//! 钩子按注册顺序优先于配置与默认映射。
 
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{RecoveryPolicySection, RecoverySection};
 use crate::core::{AgentError, RecoveryAction};
 use crate::memory::Message;
+use crate::tools::ToolError;
+
+/// 错误类别（策略表的键）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    JsonParse,
+    ContextWindow,
+    HallucinatedTool,
+    ToolTimeout,
+    ToolInvalidArgs,
+    ToolNotFound,
+    ToolTransient,
+    ToolPermissionDenied,
+    ToolFailed,
+    NetworkTimeout,
+    Llm,
+    Other,
+}
+
+impl From<&AgentError> for ErrorClass {
+    fn from(err: &AgentError) -> Self {
+        match err {
+            AgentError::JsonParseError(_) => ErrorClass::JsonParse,
+            AgentError::ContextWindowExceeded => ErrorClass::ContextWindow,
+            AgentError::HallucinatedTool(_) => ErrorClass::HallucinatedTool,
+            AgentError::ToolTimeout(_) => ErrorClass::ToolTimeout,
+            AgentError::ToolExecutionFailed(_) => ErrorClass::ToolFailed,
+            AgentError::ToolFailed(e) => match e {
+                ToolError::InvalidArgs(_) => ErrorClass::ToolInvalidArgs,
+                ToolError::NotFound(_) => ErrorClass::ToolNotFound,
+                ToolError::Transient(_) => ErrorClass::ToolTransient,
+                ToolError::PermissionDenied(_) => ErrorClass::ToolPermissionDenied,
+                ToolError::Failed(_) => ErrorClass::ToolFailed,
+            },
+            AgentError::NetworkTimeout => ErrorClass::NetworkTimeout,
+            AgentError::LlmError(_) => ErrorClass::Llm,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// 策略表中可配置的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryActionKind {
+    /// 注入提示后重试
+    Retry,
+    /// 压缩上下文后重试
+    Compact,
+    AskUser,
+    DowngradeModel,
+    Abort,
+}
+
+/// 单个错误类别的恢复策略
+#[derive(Debug, Clone)]
+pub struct RecoveryPolicy {
+    pub action: RecoveryActionKind,
+    /// 本次运行中该类错误最多按 action 处理的次数，超过后执行 on_exhausted
+    pub max_retries: u32,
+    /// 首次重试前的等待，之后每次翻倍
+    pub backoff: Duration,
+    pub on_exhausted: RecoveryActionKind,
+    /// 重试 / 询问用户时使用的提示，缺省沿用默认提示
+    pub prompt: Option<String>,
+}
+
+impl From<&RecoveryPolicySection> for RecoveryPolicy {
+    fn from(s: &RecoveryPolicySection) -> Self {
+        Self {
+            action: s.action,
+            max_retries: s.max_retries,
+            backoff: Duration::from_millis(s.backoff_ms),
+            on_exhausted: s.on_exhausted,
+            prompt: s.prompt.clone(),
+        }
+    }
+}
+
+/// 自定义恢复钩子：返回 Some 时采用该动作，None 时交给下一个钩子或策略表
+pub trait RecoveryHook: Send + Sync {
+    /// 名称（日志中使用）
+    fn name(&self) -> &str;
+
+    /// attempt 为本次运行中同类错误此前已处理的次数
+    fn recover(&self, err: &AgentError, attempt: u32) -> Option<RecoveryAction>;
+}
+
+/// 恢复决策：动作与执行前的退避时间
+#[derive(Debug, Clone)]
+pub struct RecoveryDecision {
+    pub action: RecoveryAction,
+    pub backoff: Duration,
+}
 
 /// 语义化错误恢复：将错误映射为可执行动作（重试提示 / 剪枝 / 问用户 / 终止）
-#[derive(Debug, Default)]
-pub struct RecoveryEngine;
+#[derive(Default)]
+pub struct RecoveryEngine {
+    policies: HashMap<ErrorClass, RecoveryPolicy>,
+    hooks: Vec<Arc<dyn RecoveryHook>>,
+}
+
+impl std::fmt::Debug for RecoveryEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecoveryEngine")
+            .field("policies", &self.policies)
+            .field("hooks", &self.hooks.iter().map(|h| h.name()).collect::<Vec<_>>())
+            .finish()
+    }
+}
 
 impl RecoveryEngine {
     pub fn new() -> Self {
-        Self
+        Self::default()
     }
 
-    /// 根据错误类型返回建议的恢复动作；history 预留用于未来「剪枝后重试」等逻辑
-    pub fn handle(&self, err: &AgentError, _history: &mut [Message]) -> RecoveryAction {
+    /// 按 [recovery] 配置创建；同一错误类别配置多次时以最后一条为准
+    pub fn from_config(section: &RecoverySection) -> Self {
+        section
+            .policies
+            .iter()
+            .fold(Self::new(), |engine, p| engine.with_policy(p.error, RecoveryPolicy::from(p)))
+    }
+
+    pub fn with_policy(mut self, class: ErrorClass, policy: RecoveryPolicy) -> Self {
+        self.policies.insert(class, policy);
+        self
+    }
+
+    /// 挂载自定义恢复钩子（按挂载顺序调用）
+    pub fn with_hook(mut self, hook: Arc<dyn RecoveryHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// 挂载插件注册表中的恢复钩子
+    pub fn with_plugin_hooks(self, plugins: &crate::plugins::PluginRegistry) -> Self {
+        plugins
+            .recovery_hooks()
+            .iter()
+            .fold(self, |engine, hook| engine.with_hook(hook.clone()))
+    }
+
+    /// 根据错误类型返回建议的恢复动作（不计重试次数）；history 预留用于未来「剪枝后重试」等逻辑
+    pub fn handle(&self, err: &AgentError, history: &mut [Message]) -> RecoveryAction {
+        self.decide(err, history, 0).action
+    }
+
+    /// 按钩子、策略表、默认映射的顺序给出恢复决策；attempt 为本次运行中同类错误此前已处理的次数
+    pub fn decide(&self, err: &AgentError, _history: &mut [Message], attempt: u32) -> RecoveryDecision {
+        // 用户取消不参与策略与钩子
+        if matches!(err, AgentError::Cancelled) {
+            return RecoveryDecision {
+                action: RecoveryAction::Abort,
+                backoff: Duration::ZERO,
+            };
+        }
+        for hook in &self.hooks {
+            if let Some(action) = hook.recover(err, attempt) {
+                tracing::debug!(hook = hook.name(), ?action, "recovery hook handled error");
+                return RecoveryDecision {
+                    action,
+                    backoff: Duration::ZERO,
+                };
+            }
+        }
+        let Some(policy) = self.policies.get(&ErrorClass::from(err)) else {
+            return RecoveryDecision {
+                action: Self::default_action(err),
+                backoff: Duration::ZERO,
+            };
+        };
+        if attempt >= policy.max_retries {
+            return RecoveryDecision {
+                action: Self::action_for(policy.on_exhausted, policy.prompt.as_deref(), err),
+                backoff: Duration::ZERO,
+            };
+        }
+        let backoff = match policy.action {
+            RecoveryActionKind::Retry | RecoveryActionKind::Compact => {
+                policy.backoff.saturating_mul(1u32 << attempt.min(16))
+            }
+            _ => Duration::ZERO,
+        };
+        RecoveryDecision {
+            action: Self::action_for(policy.action, policy.prompt.as_deref(), err),
+            backoff,
+        }
+    }
+
+    /// 将配置的动作转换为 RecoveryAction；未配置提示时沿用默认提示
+    fn action_for(kind: RecoveryActionKind, prompt: Option<&str>, err: &AgentError) -> RecoveryAction {
+        match kind {
+            RecoveryActionKind::Retry => RecoveryAction::RetryWithPrompt(match prompt {
+                Some(p) => p.to_string(),
+                None => match Self::default_action(err) {
+                    RecoveryAction::RetryWithPrompt(p) => p,
+                    _ => format!("上一步失败: {err}。请调整后重试。"),
+                },
+            }),
+            RecoveryActionKind::Compact => RecoveryAction::SummarizeAndPrune,
+            RecoveryActionKind::AskUser => RecoveryAction::AskUser(match prompt {
+                Some(p) => p.to_string(),
+                None => match Self::default_action(err) {
+                    RecoveryAction::AskUser(q) => q,
+                    _ => format!("执行失败: {err}，是否重试？"),
+                },
+            }),
+            RecoveryActionKind::DowngradeModel => RecoveryAction::DowngradeModel,
+            RecoveryActionKind::Abort => RecoveryAction::Abort,
+        }
+    }
+
+    /// 未配置策略时的默认映射
+    fn default_action(err: &AgentError) -> RecoveryAction {
         match err {
             AgentError::JsonParseError(raw) => RecoveryAction::RetryWithPrompt(format!(
                 "上一轮输出的 JSON 格式错误: {raw}。\
@@ -33,6 +247,21 @@ impl RecoveryEngine {
             AgentError::ToolExecutionFailed(msg) => {
                 RecoveryAction::AskUser(format!("工具执行失败: {msg}"))
             }
+            AgentError::ToolFailed(e) => match e {
+                ToolError::InvalidArgs(msg) => RecoveryAction::RetryWithPrompt(format!(
+                    "工具参数错误: {msg}。请对照该工具的参数 schema 修正 args 后重新调用（可用 tool_help 查看完整说明）。"
+                )),
+                ToolError::NotFound(msg) => RecoveryAction::RetryWithPrompt(format!(
+                    "目标不存在: {msg}。请确认路径或名称（可先用 ls 等工具查看）后再调用。"
+                )),
+                ToolError::Transient(msg) => RecoveryAction::RetryWithPrompt(format!(
+                    "工具临时故障（已自动重试）: {msg}。可稍后再试或换用其它方式获取信息。"
+                )),
+                ToolError::PermissionDenied(msg) => {
+                    RecoveryAction::AskUser(format!("工具权限不足: {msg}，是否调整权限或换用其它方式？"))
+                }
+                ToolError::Failed(msg) => RecoveryAction::AskUser(format!("工具执行失败: {msg}")),
+            },
             AgentError::NetworkTimeout => RecoveryAction::RetryWithPrompt(
                 "网络请求超时，请重试。".to_string(),
             ),
@@ -90,6 +319,18 @@ mod tests {
         assert!(matches!(action, RecoveryAction::AskUser(_)));
     }
 
+    #[test]
+    fn test_recovery_tool_error_by_kind() {
+        let engine = RecoveryEngine::new();
+        let err = AgentError::ToolFailed(ToolError::missing("path"));
+        match engine.handle(&err, &mut []) {
+            RecoveryAction::RetryWithPrompt(msg) => assert!(msg.contains("schema")),
+            _ => panic!("Expected RetryWithPrompt"),
+        }
+        let err = AgentError::ToolFailed(ToolError::PermissionDenied("rm".to_string()));
+        assert!(matches!(engine.handle(&err, &mut []), RecoveryAction::AskUser(_)));
+    }
+
     #[test]
     fn test_recovery_llm_error() {
         let engine = RecoveryEngine::new();
@@ -113,4 +354,61 @@ mod tests {
         let action = engine.handle(&err, &mut []);
         assert!(matches!(action, RecoveryAction::RetryWithPrompt(_)));
     }
+
+    struct SkipPermissionHook;
+
+    impl RecoveryHook for SkipPermissionHook {
+        fn name(&self) -> &str {
+            "skip_permission"
+        }
+
+        fn recover(&self, err: &AgentError, _attempt: u32) -> Option<RecoveryAction> {
+            matches!(err, AgentError::ToolFailed(ToolError::PermissionDenied(_)))
+                .then(|| RecoveryAction::RetryWithPrompt("skip this step".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_recovery_policy_and_hooks() {
+        let section: RecoverySection = toml::from_str(
+            r#"
+            [[policies]]
+            error = "network_timeout"
+            action = "retry"
+            max_retries = 2
+            backoff_ms = 100
+            on_exhausted = "downgrade_model"
+
+            [[policies]]
+            error = "llm"
+            action = "ask_user"
+            prompt = "The model keeps failing, switch provider?"
+            "#,
+        )
+        .unwrap();
+        let mut plugins = crate::plugins::PluginRegistry::new();
+        plugins.register_recovery_hook(Arc::new(SkipPermissionHook));
+        let engine = RecoveryEngine::from_config(&section).with_plugin_hooks(&plugins);
+
+        let err = AgentError::NetworkTimeout;
+        let first = engine.decide(&err, &mut [], 0);
+        assert!(matches!(first.action, RecoveryAction::RetryWithPrompt(_)));
+        assert_eq!(first.backoff, Duration::from_millis(100));
+        assert_eq!(engine.decide(&err, &mut [], 1).backoff, Duration::from_millis(200));
+        assert!(matches!(engine.decide(&err, &mut [], 2).action, RecoveryAction::DowngradeModel));
+
+        let err = AgentError::LlmError(LlmError::RateLimited { retry_after_ms: 1000 });
+        match engine.handle(&err, &mut []) {
+            RecoveryAction::AskUser(q) => assert!(q.contains("switch provider")),
+            other => panic!("unexpected action: {:?}", other),
+        }
+
+        let err = AgentError::ToolFailed(ToolError::PermissionDenied("rm".to_string()));
+        assert!(matches!(engine.handle(&err, &mut []), RecoveryAction::RetryWithPrompt(_)));
+        // 未配置的类别沿用默认映射
+        assert!(matches!(
+            engine.handle(&AgentError::ContextWindowExceeded, &mut []),
+            RecoveryAction::SummarizeAndPrune
+        ));
+    }
 }
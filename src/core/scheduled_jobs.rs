@@ -0,0 +1,536 @@
+//! 助手定时任务：按 cron 或固定间隔让助手执行一段提示，如「每天 8 点总结我的订阅」
+//!
+//! 任务来自 assistants.toml 中各助手的 `[[assistants.jobs]]`（启动时同步，[heartbeat] 心跳也注册为其中一项），
+//! 或由 Web API 创建；统一存于 workspace.db 的 scheduled_jobs 表，每次执行记入 scheduled_job_runs
+//! （每项保留最近 [MAX_RUNS_PER_JOB] 次）。bee-web 用 [TaskScheduler::spawn_jobs] 轮询到期任务，
+//! 交给 [JobSink] 在任务的会话中执行。重启期间错过的执行在启动后补一次。
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::core::task_scheduler::{CronSchedule, TaskScheduler};
+use crate::core::workspace_store::{StoreError, WORKSPACE_DB_FILE};
+
+/// 每个任务保留的执行记录数
+pub const MAX_RUNS_PER_JOB: usize = 50;
+
+/// 执行记录中保存的输出长度（字符）
+const MAX_RUN_OUTPUT_CHARS: usize = 2000;
+
+/// 执行计划：5 段 cron（本地时区）或 `@every 30m` 固定间隔（单位 s / m / h，缺省为秒）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobSchedule {
+    Cron(CronSchedule),
+    Every(Duration),
+}
+
+impl JobSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let Some(every) = expr.trim().strip_prefix("@every") else {
+            return CronSchedule::parse(expr).map(Self::Cron);
+        };
+        let every = every.trim();
+        let (num, unit) = every.split_at(every.find(|c: char| !c.is_ascii_digit()).unwrap_or(every.len()));
+        let n: u64 = num.parse().map_err(|_| format!("invalid interval '{}'", every))?;
+        let secs = match unit.trim() {
+            "" | "s" => n,
+            "m" => n * 60,
+            "h" => n * 3600,
+            other => return Err(format!("invalid interval unit '{}', expected s / m / h", other)),
+        };
+        if secs == 0 {
+            return Err("interval must be positive".to_string());
+        }
+        Ok(Self::Every(Duration::from_secs(secs)))
+    }
+
+    /// after 之后的下一次执行时刻
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Self::Cron(cron) => cron.next_after(after),
+            Self::Every(every) => Some(after + chrono::Duration::from_std(*every).ok()?),
+        }
+    }
+}
+
+/// assistants.toml 中助手的定时任务（`[[assistants.jobs]]`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobConfig {
+    /// 助手内唯一，任务 id 为 `{assistant_id}:{name}`
+    pub name: String,
+    /// cron 表达式或 `@every 30m`
+    pub schedule: String,
+    /// 每次执行发给助手的消息
+    pub prompt: String,
+    /// 首次同步时的启停状态；之后以 Web API 的开关为准
+    #[serde(default = "default_job_enabled")]
+    pub enabled: bool,
+    /// 结果写入的会话，缺省为 `job_<任务 id>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+fn default_job_enabled() -> bool {
+    true
+}
+
+/// 任务来源：配置文件中的任务只能启停，API 创建的可修改与删除
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobSource {
+    Config,
+    Api,
+}
+
+/// 一次执行的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobRunStatus {
+    Success,
+    Failed,
+}
+
+/// 定时任务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: String,
+    pub assistant_id: String,
+    pub name: String,
+    pub schedule: String,
+    pub prompt: String,
+    pub enabled: bool,
+    /// 结果写入的会话；None 时为 [ScheduledJob::session]
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// 多用户部署中任务所属的用户（[users] isolate），None 为默认用户
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub source: JobSource,
+    /// 下次执行时间（Unix 秒）
+    pub next_run: i64,
+    #[serde(default)]
+    pub last_run: Option<i64>,
+    #[serde(default)]
+    pub last_status: Option<JobRunStatus>,
+    pub created_at: i64,
+}
+
+impl ScheduledJob {
+    /// 新建任务，校验执行计划并算出首次执行时间
+    pub fn new(
+        id: impl Into<String>,
+        assistant_id: impl Into<String>,
+        name: impl Into<String>,
+        schedule: impl Into<String>,
+        prompt: impl Into<String>,
+        source: JobSource,
+    ) -> Result<Self, String> {
+        let now = Utc::now();
+        let mut job = Self {
+            id: id.into(),
+            assistant_id: assistant_id.into(),
+            name: name.into(),
+            schedule: schedule.into(),
+            prompt: prompt.into(),
+            enabled: true,
+            session_id: None,
+            user_id: None,
+            source,
+            next_run: 0,
+            last_run: None,
+            last_status: None,
+            created_at: now.timestamp(),
+        };
+        job.reschedule(now)?;
+        Ok(job)
+    }
+
+    /// assistants.toml 中的任务
+    pub fn from_config(assistant_id: &str, config: &JobConfig) -> Result<Self, String> {
+        let mut job = Self::new(
+            format!("{}:{}", assistant_id, config.name),
+            assistant_id,
+            &config.name,
+            &config.schedule,
+            &config.prompt,
+            JobSource::Config,
+        )?;
+        job.enabled = config.enabled;
+        job.session_id = config.session_id.clone().filter(|s| !s.is_empty());
+        Ok(job)
+    }
+
+    /// 结果写入的会话 id
+    pub fn session(&self) -> String {
+        self.session_id.clone().unwrap_or_else(|| {
+            let safe: String = self
+                .id
+                .chars()
+                .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+                .collect();
+            format!("job_{}", safe)
+        })
+    }
+
+    /// 按执行计划把 next_run 推到 now 之后；计划无效或永不触发时返回错误
+    pub fn reschedule(&mut self, now: DateTime<Utc>) -> Result<(), String> {
+        let next = JobSchedule::parse(&self.schedule)?
+            .next_after(now.with_timezone(&Local))
+            .ok_or_else(|| format!("schedule '{}' never fires", self.schedule))?;
+        self.next_run = next.timestamp();
+        Ok(())
+    }
+}
+
+/// 一次执行记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRun {
+    pub id: String,
+    pub job_id: String,
+    /// 开始、结束时间（Unix 秒）
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub status: JobRunStatus,
+    /// 回复或错误说明（截断）
+    pub output: String,
+}
+
+/// 定时任务存储（workspace.db 的 scheduled_jobs 与 scheduled_job_runs 表）
+pub struct JobStore {
+    conn: Mutex<Connection>,
+    /// 正在执行的任务 id：定时轮询与手动执行共用，同一任务不并发执行
+    running: Mutex<HashSet<String>>,
+}
+
+/// 任务执行中的标记，drop 时清除
+pub struct RunningJob {
+    store: Arc<JobStore>,
+    id: String,
+}
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        self.store.running.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+    }
+}
+
+impl JobStore {
+    /// 打开 workspace/workspace.db
+    pub fn open(workspace: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(workspace)?;
+        Self::open_at(&workspace.join(WORKSPACE_DB_FILE))
+    }
+
+    pub fn open_at(db_path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS scheduled_jobs (
+                 id TEXT PRIMARY KEY,
+                 next_run INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS scheduled_job_runs (
+                 id TEXT PRIMARY KEY,
+                 job_id TEXT NOT NULL,
+                 started_at INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS scheduled_job_runs_job ON scheduled_job_runs (job_id, started_at);",
+        )?;
+        Ok(Self { conn: Mutex::new(conn), running: Mutex::new(HashSet::new()) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 插入或覆盖
+    pub fn upsert(&self, job: &ScheduledJob) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO scheduled_jobs (id, next_run, data) VALUES (?1, ?2, ?3)",
+            params![job.id, job.next_run, serde_json::to_string(job)?],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<ScheduledJob>, StoreError> {
+        let data: Option<String> = self
+            .conn()
+            .query_row("SELECT data FROM scheduled_jobs WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+    }
+
+    /// 删除任务及其执行记录，返回是否存在
+    pub fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        let conn = self.conn();
+        conn.execute("DELETE FROM scheduled_job_runs WHERE job_id = ?1", [id])?;
+        Ok(conn.execute("DELETE FROM scheduled_jobs WHERE id = ?1", [id])? > 0)
+    }
+
+    /// 按下次执行时间列出全部任务
+    pub fn list(&self) -> Result<Vec<ScheduledJob>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM scheduled_jobs ORDER BY next_run")?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.iter().filter_map(|d| serde_json::from_str(d).ok()).collect())
+    }
+
+    /// 同步配置文件中的任务：新增的插入，已有的更新计划与提示（保留启停、执行历史与未变计划的下次时间），
+    /// 已从配置中移除的删除；API 创建的任务不受影响
+    pub fn sync_config(&self, jobs: &[ScheduledJob]) -> Result<(), StoreError> {
+        let existing = self.list()?;
+        for stale in existing
+            .iter()
+            .filter(|j| j.source == JobSource::Config && jobs.iter().all(|c| c.id != j.id))
+        {
+            self.remove(&stale.id)?;
+        }
+        for job in jobs {
+            let merged = match existing.iter().find(|j| j.id == job.id) {
+                Some(old) => ScheduledJob {
+                    enabled: old.enabled,
+                    next_run: if old.schedule == job.schedule {
+                        old.next_run
+                    } else {
+                        job.next_run
+                    },
+                    last_run: old.last_run,
+                    last_status: old.last_status,
+                    created_at: old.created_at,
+                    ..job.clone()
+                },
+                None => job.clone(),
+            };
+            self.upsert(&merged)?;
+        }
+        Ok(())
+    }
+
+    /// 取出已到期的启用任务，并把它们的下次执行时间推到 now 之后（计划失效的任务停用）
+    pub fn claim_due(&self, now: DateTime<Utc>) -> Result<Vec<ScheduledJob>, StoreError> {
+        let due: Vec<ScheduledJob> = {
+            let conn = self.conn();
+            let mut stmt = conn.prepare("SELECT data FROM scheduled_jobs WHERE next_run <= ?1 ORDER BY next_run")?;
+            let rows = stmt
+                .query_map([now.timestamp()], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.iter()
+                .filter_map(|d| serde_json::from_str::<ScheduledJob>(d).ok())
+                .filter(|j| j.enabled)
+                .collect()
+        };
+        let mut claimed = Vec::with_capacity(due.len());
+        for mut job in due {
+            if let Err(e) = job.reschedule(now) {
+                tracing::warn!(job = %job.id, "disabling scheduled job: {}", e);
+                job.enabled = false;
+            }
+            self.upsert(&job)?;
+            if job.enabled {
+                claimed.push(job);
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// 标记任务开始执行；上一次尚未结束时返回 None
+    pub fn begin_run(self: &Arc<Self>, id: &str) -> Option<RunningJob> {
+        let inserted = self.running.lock().unwrap_or_else(|e| e.into_inner()).insert(id.to_string());
+        inserted.then(|| RunningJob { store: Arc::clone(self), id: id.to_string() })
+    }
+
+    /// 记录一次执行，更新任务的最近执行信息，并只保留最近 MAX_RUNS_PER_JOB 条
+    pub fn record_run(&self, run: &JobRun) -> Result<(), StoreError> {
+        {
+            let conn = self.conn();
+            conn.execute(
+                "INSERT OR REPLACE INTO scheduled_job_runs (id, job_id, started_at, data) VALUES (?1, ?2, ?3, ?4)",
+                params![run.id, run.job_id, run.started_at, serde_json::to_string(run)?],
+            )?;
+            conn.execute(
+                "DELETE FROM scheduled_job_runs WHERE job_id = ?1 AND id NOT IN (
+                     SELECT id FROM scheduled_job_runs WHERE job_id = ?1 ORDER BY started_at DESC, rowid DESC LIMIT ?2
+                 )",
+                params![run.job_id, MAX_RUNS_PER_JOB as i64],
+            )?;
+        }
+        if let Some(mut job) = self.get(&run.job_id)? {
+            job.last_run = Some(run.started_at);
+            job.last_status = Some(run.status);
+            self.upsert(&job)?;
+        }
+        Ok(())
+    }
+
+    /// 任务最近的执行记录，新的在前
+    pub fn runs(&self, job_id: &str, limit: usize) -> Result<Vec<JobRun>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT data FROM scheduled_job_runs WHERE job_id = ?1 ORDER BY started_at DESC, rowid DESC LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![job_id, limit as i64], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.iter().filter_map(|d| serde_json::from_str(d).ok()).collect())
+    }
+}
+
+/// 定时任务的执行端：在任务的会话中让助手处理 prompt，返回回复
+#[async_trait]
+pub trait JobSink: Send + Sync {
+    async fn run(&self, job: &ScheduledJob) -> Result<String, String>;
+}
+
+/// 执行一次任务并写入执行记录
+pub async fn run_job(store: &JobStore, sink: &dyn JobSink, job: &ScheduledJob) -> JobRun {
+    let started_at = Utc::now().timestamp();
+    let (status, output) = match sink.run(job).await {
+        Ok(reply) => (JobRunStatus::Success, reply),
+        Err(e) => (JobRunStatus::Failed, e),
+    };
+    let run = JobRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        job_id: job.id.clone(),
+        started_at,
+        finished_at: Utc::now().timestamp(),
+        status,
+        output: output.chars().take(MAX_RUN_OUTPUT_CHARS).collect(),
+    };
+    if let Err(e) = store.record_run(&run) {
+        tracing::warn!(job = %job.id, "failed to record job run: {}", e);
+    }
+    run
+}
+
+impl TaskScheduler {
+    /// 后台轮询到期的定时任务并交给 sink 执行；同一任务上一次（含手动执行）尚未结束时跳过本次
+    pub fn spawn_jobs(store: Arc<JobStore>, sink: Arc<dyn JobSink>, poll: Duration) -> JoinHandle<()> {
+        tracing::info!("job scheduler started, poll {}s", poll.as_secs());
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll.max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                let due = match store.claim_due(Utc::now()) {
+                    Ok(due) => due,
+                    Err(e) => {
+                        tracing::warn!("failed to load due jobs: {}", e);
+                        continue;
+                    }
+                };
+                for job in due {
+                    let Some(running) = store.begin_run(&job.id) else {
+                        tracing::info!(job = %job.id, "previous run still in progress, skipping");
+                        continue;
+                    };
+                    let (store, sink) = (Arc::clone(&store), Arc::clone(&sink));
+                    tokio::spawn(async move {
+                        let _running = running;
+                        tracing::info!(job = %job.id, assistant = %job.assistant_id, "running scheduled job");
+                        run_job(&store, sink.as_ref(), &job).await;
+                    });
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait]
+    impl JobSink for Echo {
+        async fn run(&self, job: &ScheduledJob) -> Result<String, String> {
+            if job.prompt == "fail" {
+                Err("boom".to_string())
+            } else {
+                Ok(format!("done: {}", job.prompt))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_sync_claim_and_history() {
+        assert_eq!(
+            JobSchedule::parse("@every 30m").unwrap(),
+            JobSchedule::Every(Duration::from_secs(1800))
+        );
+        assert!(matches!(JobSchedule::parse("0 8 * * *"), Ok(JobSchedule::Cron(_))));
+        assert!(JobSchedule::parse("@every 0s").is_err());
+        assert!(JobSchedule::parse("@every 5d").is_err());
+        assert!(ScheduledJob::new("x", "default", "x", "0 0 31 2 *", "p", JobSource::Api).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(JobStore::open(dir.path()).unwrap());
+        let config = |name: &str, schedule: &str| JobConfig {
+            name: name.into(),
+            schedule: schedule.into(),
+            prompt: "summarize my feeds".into(),
+            enabled: true,
+            session_id: None,
+        };
+        let feeds = ScheduledJob::from_config("media", &config("feeds", "0 8 * * *")).unwrap();
+        let old = ScheduledJob::from_config("media", &config("old", "@daily")).unwrap();
+        let api = ScheduledJob::new("j1", "default", "report", "@every 1h", "fail", JobSource::Api).unwrap();
+        assert_eq!(feeds.id, "media:feeds");
+        assert_eq!(feeds.session(), "job_media_feeds");
+        store.sync_config(&[feeds.clone(), old]).unwrap();
+        store.upsert(&api).unwrap();
+
+        // 页面停用后重新同步：保留启停，移除配置中已删除的任务，API 任务不受影响
+        let mut disabled = store.get("media:feeds").unwrap().unwrap();
+        disabled.enabled = false;
+        store.upsert(&disabled).unwrap();
+        let updated = ScheduledJob::from_config("media", &config("feeds", "0 9 * * *")).unwrap();
+        store.sync_config(&[updated]).unwrap();
+        let jobs = store.list().unwrap();
+        assert_eq!(jobs.len(), 2);
+        let feeds = store.get("media:feeds").unwrap().unwrap();
+        assert!(!feeds.enabled);
+        assert_eq!(feeds.schedule, "0 9 * * *");
+        assert!(store.get("j1").unwrap().is_some());
+
+        // 到期后领取并推进下次执行时间；停用的任务不领取
+        let now = Utc::now() + chrono::Duration::days(2);
+        let due = store.claim_due(now).unwrap();
+        assert_eq!(due.iter().map(|j| j.id.as_str()).collect::<Vec<_>>(), vec!["j1"]);
+        assert!(store.get("j1").unwrap().unwrap().next_run > now.timestamp());
+        assert!(store.claim_due(now).unwrap().is_empty());
+
+        let run = run_job(&store, &Echo, &due[0]).await;
+        assert_eq!((run.status, run.output.as_str()), (JobRunStatus::Failed, "boom"));
+        let job = store.get("j1").unwrap().unwrap();
+        assert_eq!(job.last_status, Some(JobRunStatus::Failed));
+        for _ in 0..MAX_RUNS_PER_JOB + 2 {
+            run_job(&store, &Echo, &feeds).await;
+        }
+        let runs = store.runs("media:feeds", 100).unwrap();
+        assert_eq!(runs.len(), MAX_RUNS_PER_JOB);
+        assert_eq!(runs[0].output, "done: summarize my feeds");
+
+        // 同一任务同时只能有一次执行，结束后可再次执行
+        let running = store.begin_run("j1").unwrap();
+        assert!(store.begin_run("j1").is_none());
+        assert!(store.begin_run("media:feeds").is_some());
+        drop(running);
+        assert!(store.begin_run("j1").is_some());
+
+        assert!(store.remove("j1").unwrap());
+        assert!(store.runs("j1", 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! 会话只读分享链接
+//!
+//! 分享 token = hex(payload) + "." + hex(HMAC-SHA256(secret, payload))，payload 为
+//! `session_id \n assistant_id \n 过期时间戳`（非默认用户的会话再追加 `\n user_id`）。服务端无需存储分享记录，校验签名与过期时间即可；
+//! 更换 secret 会使所有已发出的链接失效。
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 分享 token 校验错误
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShareError {
+    #[error("malformed share token")]
+    Malformed,
+    #[error("invalid share token signature")]
+    BadSignature,
+    #[error("share link expired")]
+    Expired,
+}
+
+/// 分享 token 中携带的会话信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareClaims {
+    pub session_id: String,
+    pub assistant_id: String,
+    /// 过期时间（Unix 秒），0 表示永不过期
+    pub expires_at: i64,
+    /// 会话所属用户（[users] isolate），None 为默认用户
+    pub user_id: Option<String>,
+}
+
+/// 分享链接签名器
+pub struct ShareSigner {
+    secret: Vec<u8>,
+}
+
+impl ShareSigner {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(payload);
+        mac
+    }
+
+    /// 为会话签发分享 token
+    pub fn sign(&self, claims: &ShareClaims) -> String {
+        let mut payload = format!(
+            "{}\n{}\n{}",
+            claims.session_id, claims.assistant_id, claims.expires_at
+        );
+        // 默认用户不写入，保持旧 token 格式
+        if let Some(user) = &claims.user_id {
+            payload.push('\n');
+            payload.push_str(user);
+        }
+        let sig = self.mac(payload.as_bytes()).finalize().into_bytes();
+        format!("{}.{}", to_hex(payload.as_bytes()), to_hex(&sig))
+    }
+
+    /// 校验 token（签名与过期时间，now 为当前 Unix 秒）并取出会话信息
+    pub fn verify(&self, token: &str, now: i64) -> Result<ShareClaims, ShareError> {
+        let (payload_hex, sig_hex) = token.split_once('.').ok_or(ShareError::Malformed)?;
+        let payload = from_hex(payload_hex).ok_or(ShareError::Malformed)?;
+        let sig = from_hex(sig_hex).ok_or(ShareError::Malformed)?;
+        self.mac(&payload)
+            .verify_slice(&sig)
+            .map_err(|_| ShareError::BadSignature)?;
+
+        let payload = String::from_utf8(payload).map_err(|_| ShareError::Malformed)?;
+        let mut parts = payload.splitn(4, '\n');
+        let (Some(session_id), Some(assistant_id), Some(expires_at)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(ShareError::Malformed);
+        };
+        let expires_at: i64 = expires_at.parse().map_err(|_| ShareError::Malformed)?;
+        if expires_at > 0 && now > expires_at {
+            return Err(ShareError::Expired);
+        }
+        Ok(ShareClaims {
+            session_id: session_id.to_string(),
+            assistant_id: assistant_id.to_string(),
+            expires_at,
+            user_id: parts.next().map(str::to_string),
+        })
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_token_sign_and_verify() {
+        let signer = ShareSigner::new("secret");
+        let claims = ShareClaims {
+            session_id: "s1".to_string(),
+            assistant_id: "coder".to_string(),
+            expires_at: 1_000,
+            user_id: None,
+        };
+        let token = signer.sign(&claims);
+        assert_eq!(signer.verify(&token, 999), Ok(claims.clone()));
+        assert_eq!(signer.verify(&token, 1_001), Err(ShareError::Expired));
+
+        assert_eq!(
+            ShareSigner::new("other").verify(&token, 0),
+            Err(ShareError::BadSignature)
+        );
+        let (payload, sig) = token.split_once('.').unwrap();
+        let forged = format!("{}00.{}", payload, sig);
+        assert_eq!(signer.verify(&forged, 0), Err(ShareError::BadSignature));
+        assert_eq!(signer.verify("not-a-token", 0), Err(ShareError::Malformed));
+
+        let forever = ShareClaims { expires_at: 0, ..claims };
+        assert!(signer.verify(&signer.sign(&forever), i64::MAX).is_ok());
+
+        let scoped = ShareClaims {
+            user_id: Some("bob".to_string()),
+            ..forever
+        };
+        assert_eq!(signer.verify(&signer.sign(&scoped), 0), Ok(scoped));
+    }
+}
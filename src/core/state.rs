@@ -5,6 +5,8 @@
 use serde::Serialize;
 
 use crate::memory::Message;
+use crate::react::PendingQuestion;
+use crate::tools::ApprovalRequest;
 
 /// UI 看到的「投影」状态，轻量且易于渲染
 #[derive(Clone, Debug, Serialize)]
@@ -14,6 +16,10 @@ pub struct UiState {
     pub active_tool: Option<String>,
     pub input_locked: bool,
     pub error_message: Option<String>,
+    /// 等待用户批准的工具调用（[tools.policy]），TUI 按 y / n 回复
+    pub pending_approval: Option<ApprovalRequest>,
+    /// 等待用户回答的问题（AskUser），TUI 在输入框回答
+    pub pending_question: Option<PendingQuestion>,
 }
 
 impl Default for UiState {
@@ -24,6 +30,8 @@ impl Default for UiState {
             active_tool: None,
             input_locked: false,
             error_message: None,
+            pending_approval: None,
+            pending_question: None,
         }
     }
 }
@@ -58,6 +66,8 @@ impl InternalStateSnapshot {
             active_tool: self.active_tool.clone(),
             input_locked,
             error_message,
+            pending_approval: None,
+            pending_question: None,
         }
     }
 }
@@ -1,14 +1,30 @@
-//! 任务调度：Foreground / Background / Tool Pool
+//! 任务调度：Foreground / Background / Tool Pool，以及定时提醒
 //!
-//! 按任务类型（AgentStep / ToolExecution / Background）分类；工具执行使用 Semaphore 限制并发。
+//! 按任务类型（AgentStep / ToolExecution / Background）分类；LLM 规划调用与工具执行各有一个优先级队列，
+//! 受全局并发上限与按助手配额（[scheduler]）限制，排队情况可通过 [TaskScheduler::snapshot] 查看。
+//! 定时提醒（remind 工具创建）存于 workspace.db 的 reminders 表，进程重启后继续生效；
+//! 各接入端（bee-web / bee-whatsapp / bee-lark）用 [TaskScheduler::spawn_reminders] 轮询到期提醒，
+//! 经 [ReminderSink] 推送回创建提醒的会话。重启期间错过的提醒在启动后立即补发一次。
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use tokio::sync::Semaphore;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
+use crate::config::SchedulerSection;
+use crate::core::workspace_store::{StoreError, WORKSPACE_DB_FILE};
+use crate::observability::Metrics;
+use crate::tools::CURRENT_ASSISTANT_ID;
+
 /// 任务类型
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum TaskKind {
@@ -38,29 +54,327 @@ impl TaskId {
     }
 }
 
-/// 任务调度器
+/// 调度队列：LLM 规划调用与工具执行分别排队、分别限流
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkLane {
+    Llm,
+    Tool,
+}
+
+/// 排队优先级：同一队列中高优先级先出队，同优先级先到先得
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkPriority {
+    /// 后台任务（任务看板、网关后台任务）
+    Low,
+    /// 交互请求
+    #[default]
+    Normal,
+    High,
+}
+
+tokio::task_local! {
+    /// 当前任务的调度优先级，由接入端在运行后台任务时设置；未设置时为 Normal
+    pub static CURRENT_PRIORITY: WorkPriority;
+}
+
+/// 当前任务的调度优先级（未设置时为 Normal）
+pub fn current_priority() -> WorkPriority {
+    CURRENT_PRIORITY.try_with(|p| *p).unwrap_or_default()
+}
+
+/// 队列中或运行中的一项工作（GET /api/scheduler 展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkItem {
+    pub id: u64,
+    /// LLM 为 "plan"，工具为工具名
+    pub label: String,
+    pub assistant_id: Option<String>,
+    pub priority: WorkPriority,
+    /// 已排队（运行中的工作为出队前排队）的毫秒数
+    pub waited_ms: u64,
+    /// 已运行的毫秒数，排队中为 None
+    pub running_ms: Option<u64>,
+}
+
+/// 单个队列的快照
+#[derive(Debug, Clone, Serialize)]
+pub struct LaneSnapshot {
+    pub lane: WorkLane,
+    pub capacity: usize,
+    pub running: Vec<WorkItem>,
+    /// 按出队顺序排列
+    pub waiting: Vec<WorkItem>,
+}
+
+/// 单个助手的负载与并发配额
+#[derive(Debug, Clone, Serialize)]
+pub struct AssistantLoad {
+    pub assistant_id: String,
+    pub running: usize,
+    pub waiting: usize,
+    /// None 表示不限
+    pub quota: Option<usize>,
+}
+
+/// 调度器快照：各队列排队与运行中的工作、各助手负载
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerSnapshot {
+    pub lanes: Vec<LaneSnapshot>,
+    pub assistants: Vec<AssistantLoad>,
+}
+
+struct Waiter {
+    info: Work,
+    tx: oneshot::Sender<WorkPermit>,
+}
+
+#[derive(Clone)]
+struct Work {
+    id: u64,
+    lane: WorkLane,
+    label: String,
+    assistant_id: Option<String>,
+    priority: WorkPriority,
+    enqueued: Instant,
+    started: Option<Instant>,
+}
+
+impl Work {
+    fn item(&self, now: Instant) -> WorkItem {
+        let waited = self.started.unwrap_or(now).duration_since(self.enqueued);
+        WorkItem {
+            id: self.id,
+            label: self.label.clone(),
+            assistant_id: self.assistant_id.clone(),
+            priority: self.priority,
+            waited_ms: waited.as_millis() as u64,
+            running_ms: self.started.map(|s| now.duration_since(s).as_millis() as u64),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_id: u64,
+    running: Vec<Work>,
+    waiting: Vec<Waiter>,
+}
+
+impl SchedulerState {
+    fn running_in(&self, lane: WorkLane) -> usize {
+        self.running.iter().filter(|w| w.lane == lane).count()
+    }
+
+    fn running_for(&self, assistant_id: &str) -> usize {
+        self.running.iter().filter(|w| w.assistant_id.as_deref() == Some(assistant_id)).count()
+    }
+}
+
+struct SchedulerInner {
+    llm_capacity: usize,
+    tool_capacity: usize,
+    assistant_quota: usize,
+    assistant_quotas: HashMap<String, usize>,
+    state: Mutex<SchedulerState>,
+}
+
+impl SchedulerInner {
+    fn capacity(&self, lane: WorkLane) -> usize {
+        match lane {
+            WorkLane::Llm => self.llm_capacity,
+            WorkLane::Tool => self.tool_capacity,
+        }
+    }
+
+    /// 助手的并发配额，None 表示不限
+    fn quota(&self, assistant_id: &str) -> Option<usize> {
+        let quota = self.assistant_quotas.get(assistant_id).copied().unwrap_or(self.assistant_quota);
+        (quota > 0).then_some(quota)
+    }
+
+    fn can_start(&self, state: &SchedulerState, work: &Work) -> bool {
+        state.running_in(work.lane) < self.capacity(work.lane)
+            && work
+                .assistant_id
+                .as_deref()
+                .and_then(|a| self.quota(a).map(|q| state.running_for(a) < q))
+                .unwrap_or(true)
+    }
+
+    fn start(self: &Arc<Self>, state: &mut SchedulerState, mut work: Work) -> WorkPermit {
+        let now = Instant::now();
+        Metrics::global().scheduler.record_dispatch(now.duration_since(work.enqueued));
+        work.started = Some(now);
+        let permit = WorkPermit {
+            scheduler: Arc::clone(self),
+            id: work.id,
+            armed: true,
+        };
+        state.running.push(work);
+        permit
+    }
+
+    /// 释放后出队：每个队列按优先级（同级先到先得）取出配额允许的等待者，直到队列占满
+    fn dispatch(self: &Arc<Self>, state: &mut SchedulerState) {
+        state.waiting.retain(|w| !w.tx.is_closed());
+        loop {
+            let next = state
+                .waiting
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| self.can_start(state, &w.info))
+                .max_by_key(|(_, w)| (w.info.priority, std::cmp::Reverse(w.info.id)))
+                .map(|(i, _)| i);
+            let Some(i) = next else { break };
+            let waiter = state.waiting.remove(i);
+            let permit = self.start(state, waiter.info);
+            // 等待方已放弃（请求被取消）：撤销本次出队，继续取下一个
+            if let Err(mut permit) = waiter.tx.send(permit) {
+                permit.armed = false;
+                state.running.retain(|w| w.id != permit.id);
+            }
+        }
+        self.publish(state);
+    }
+
+    fn publish(&self, state: &SchedulerState) {
+        let depth = |lane| {
+            (
+                state.waiting.iter().filter(|w| w.info.lane == lane).count(),
+                state.running_in(lane),
+            )
+        };
+        Metrics::global().scheduler.set_depth(depth(WorkLane::Llm), depth(WorkLane::Tool));
+    }
+}
+
+/// 调度许可：持有期间占用队列与助手配额，释放时唤醒下一个等待者
+pub struct WorkPermit {
+    scheduler: Arc<SchedulerInner>,
+    id: u64,
+    armed: bool,
+}
+
+impl Drop for WorkPermit {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let mut state = self.scheduler.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running.retain(|w| w.id != self.id);
+        self.scheduler.dispatch(&mut state);
+    }
+}
+
+/// 任务调度器：LLM 与工具两个优先级队列，全局并发上限与按助手配额（[scheduler]），可多会话共享
 pub struct TaskScheduler {
-    /// 工具并发限制（默认 3）
-    tool_semaphore: Arc<Semaphore>,
-    /// 活跃任务
-    _active_tasks: HashMap<TaskId, TaskKind>,
+    inner: Arc<SchedulerInner>,
 }
 
 impl TaskScheduler {
     pub fn new(max_concurrent_tools: usize) -> Self {
+        Self::from_config(&SchedulerSection {
+            max_concurrent_tools,
+            ..SchedulerSection::default()
+        })
+    }
+
+    pub fn from_config(section: &SchedulerSection) -> Self {
         Self {
-            tool_semaphore: Arc::new(Semaphore::new(max_concurrent_tools.max(1))),
-            _active_tasks: HashMap::new(),
+            inner: Arc::new(SchedulerInner {
+                llm_capacity: section.max_concurrent_llm.max(1),
+                tool_capacity: section.max_concurrent_tools.max(1),
+                assistant_quota: section.assistant_quota,
+                assistant_quotas: section.assistant_quotas.clone(),
+                state: Mutex::new(SchedulerState::default()),
+            }),
         }
     }
 
+    /// 获取许可：队列有空位且助手未超配额时立即返回，否则按优先级排队；
+    /// 助手取自 CURRENT_ASSISTANT_ID，优先级取自 CURRENT_PRIORITY
+    pub async fn acquire(&self, lane: WorkLane, label: impl Into<String>) -> WorkPermit {
+        let work = Work {
+            id: 0,
+            lane,
+            label: label.into(),
+            assistant_id: CURRENT_ASSISTANT_ID.try_with(|a| a.clone()).ok().flatten(),
+            priority: current_priority(),
+            enqueued: Instant::now(),
+            started: None,
+        };
+        let rx = {
+            let mut state = self.inner.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.next_id += 1;
+            let work = Work { id: state.next_id, ..work };
+            if self.inner.can_start(&state, &work) {
+                let permit = self.inner.start(&mut state, work);
+                self.inner.publish(&state);
+                return permit;
+            }
+            let (tx, rx) = oneshot::channel();
+            state.waiting.push(Waiter { info: work, tx });
+            self.inner.publish(&state);
+            rx
+        };
+        rx.await.expect("scheduler keeps waiters until dispatch")
+    }
+
+    /// 获取 LLM 规划调用许可
+    pub async fn acquire_llm(&self) -> WorkPermit {
+        self.acquire(WorkLane::Llm, "plan").await
+    }
+
     /// 获取工具执行许可
-    pub async fn acquire_tool(&self) -> tokio::sync::OwnedSemaphorePermit {
-        self.tool_semaphore
-            .clone()
-            .acquire_owned()
-            .await
-            .expect("semaphore closed")
+    pub async fn acquire_tool(&self, tool: &str) -> WorkPermit {
+        self.acquire(WorkLane::Tool, tool).await
+    }
+
+    /// 当前排队与运行中的工作
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        let state = self.inner.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let lanes = [WorkLane::Llm, WorkLane::Tool]
+            .into_iter()
+            .map(|lane| {
+                let mut waiting: Vec<&Work> = state
+                    .waiting
+                    .iter()
+                    .filter(|w| w.info.lane == lane && !w.tx.is_closed())
+                    .map(|w| &w.info)
+                    .collect();
+                waiting.sort_by_key(|w| (std::cmp::Reverse(w.priority), w.id));
+                LaneSnapshot {
+                    lane,
+                    capacity: self.inner.capacity(lane),
+                    running: state.running.iter().filter(|w| w.lane == lane).map(|w| w.item(now)).collect(),
+                    waiting: waiting.into_iter().map(|w| w.item(now)).collect(),
+                }
+            })
+            .collect();
+        let mut loads: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for work in &state.running {
+            if let Some(a) = &work.assistant_id {
+                loads.entry(a.clone()).or_default().0 += 1;
+            }
+        }
+        for waiter in state.waiting.iter().filter(|w| !w.tx.is_closed()) {
+            if let Some(a) = &waiter.info.assistant_id {
+                loads.entry(a.clone()).or_default().1 += 1;
+            }
+        }
+        let assistants = loads
+            .into_iter()
+            .map(|(assistant_id, (running, waiting))| AssistantLoad {
+                quota: self.inner.quota(&assistant_id),
+                assistant_id,
+                running,
+                waiting,
+            })
+            .collect();
+        SchedulerSnapshot { lanes, assistants }
     }
 
     /// 检查是否应取消
@@ -71,6 +385,490 @@ impl TaskScheduler {
 
 impl Default for TaskScheduler {
     fn default() -> Self {
-        Self::new(3)
+        Self::from_config(&SchedulerSection::default())
+    }
+}
+
+/// 投递失败达到该次数后放弃本次提醒（一次性提醒删除，周期提醒跳到下一次）
+const MAX_DELIVERY_FAILURES: u32 = 5;
+
+/// 提醒的投递目标
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReminderOrigin {
+    /// 接入端：web / whatsapp / lark
+    pub channel: String,
+    /// 接入端内的会话：web 为 session_id，whatsapp 为手机号，lark 为 chat_id
+    pub target: String,
+    #[serde(default)]
+    pub assistant_id: Option<String>,
+    /// 多用户部署中会话所属的用户（[users] isolate），None 为默认用户
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// 定时提醒
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: String,
+    pub text: String,
+    pub origin: ReminderOrigin,
+    /// 5 段 cron 表达式（本地时区）；None 为一次性提醒
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// 下次触发时间（Unix 秒）
+    pub next_fire: i64,
+    pub created_at: i64,
+    /// 连续投递失败次数
+    #[serde(default)]
+    pub failures: u32,
+}
+
+impl Reminder {
+    pub fn next_fire_local(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.next_fire, 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+
+    /// 触发后推进：周期提醒返回下一次的提醒，一次性提醒返回 None
+    fn advance(&self, now: DateTime<Utc>) -> Option<Reminder> {
+        let cron = CronSchedule::parse(self.cron.as_deref()?).ok()?;
+        let next = cron.next_after(now.with_timezone(&Local))?;
+        Some(Reminder {
+            next_fire: next.timestamp(),
+            failures: 0,
+            ..self.clone()
+        })
+    }
+}
+
+/// 5 段 cron 表达式（分 时 日 月 周），支持 * , - / 与 @hourly / @daily / @weekly / @monthly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// 日与周都被限定时按「任一匹配」处理（与 crontab 一致）
+    day_restricted: bool,
+    weekday_restricted: bool,
+}
+
+fn cron_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (
+                r,
+                s.parse::<u32>()
+                    .map_err(|_| format!("invalid step in '{}'", part))?,
+            ),
+            None => (part, 1),
+        };
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (
+                    a.parse().map_err(|_| format!("invalid value '{}'", a))?,
+                    b.parse().map_err(|_| format!("invalid value '{}'", b))?,
+                ),
+                // "5/15" 表示从 5 开始每 15
+                None => {
+                    let v: u32 = r.parse().map_err(|_| format!("invalid value '{}'", r))?;
+                    (v, if part.contains('/') { max } else { v })
+                }
+            },
+        };
+        if step == 0 || lo < min || hi > max || lo > hi {
+            return Err(format!("'{}' out of range {}-{}", part, min, max));
+        }
+        for v in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let mut weekdays = cron_field(weekday, 0, 7)?;
+        // 7 与 0 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            minutes: cron_field(minute, 0, 59)?,
+            hours: cron_field(hour, 0, 23)?,
+            days: cron_field(day, 1, 31)?,
+            months: cron_field(month, 1, 12)?,
+            weekdays,
+            day_restricted: day != "*",
+            weekday_restricted: weekday != "*",
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.day_restricted, self.weekday_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// after 之后（不含）的下一个触发时刻；5 年内无匹配（如 2 月 31 日）时返回 None
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start =
+            after.naive_local().with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let limit = start + chrono::Duration::days(5 * 366);
+        let mut t = start;
+        while t < limit {
+            if self.months & (1 << t.month()) == 0 {
+                let (y, m) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(y, m, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(t.date()) {
+                t = (t.date() + chrono::Duration::days(1)).and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.with_minute(0)? + chrono::Duration::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += chrono::Duration::minutes(1);
+            } else {
+                // 夏令时跳过的本地时刻不存在，顺延
+                match Local.from_local_datetime(&t).earliest() {
+                    Some(local) => return Some(local),
+                    None => t += chrono::Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 提醒存储（workspace.db 的 reminders 表）
+pub struct ReminderStore {
+    conn: Mutex<Connection>,
+}
+
+impl ReminderStore {
+    /// 打开 workspace/workspace.db
+    pub fn open(workspace: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(workspace)?;
+        Self::open_at(&workspace.join(WORKSPACE_DB_FILE))
+    }
+
+    pub fn open_at(db_path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS reminders (
+                 id TEXT PRIMARY KEY,
+                 channel TEXT NOT NULL,
+                 next_fire INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS reminders_due ON reminders (channel, next_fire);",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 插入或覆盖
+    pub fn upsert(&self, reminder: &Reminder) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO reminders (id, channel, next_fire, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                reminder.id,
+                reminder.origin.channel,
+                reminder.next_fire,
+                serde_json::to_string(reminder)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 删除提醒，返回是否存在
+    pub fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        Ok(self
+            .conn()
+            .execute("DELETE FROM reminders WHERE id = ?1", [id])?
+            > 0)
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Reminder>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt
+            .query_map(params, |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .iter()
+            .filter_map(|d| serde_json::from_str(d).ok())
+            .collect())
+    }
+
+    /// 按触发时间列出；origin 为 Some 时只列该会话的提醒
+    pub fn list(&self, origin: Option<&ReminderOrigin>) -> Result<Vec<Reminder>, StoreError> {
+        let all = self.query("SELECT data FROM reminders ORDER BY next_fire", [])?;
+        Ok(all
+            .into_iter()
+            .filter(|r| {
+                origin.is_none_or(|o| {
+                    r.origin.channel == o.channel && r.origin.target == o.target && r.origin.user_id == o.user_id
+                })
+            })
+            .collect())
+    }
+
+    /// 指定接入端已到期的提醒
+    pub fn due(&self, channel: &str, now: DateTime<Utc>) -> Result<Vec<Reminder>, StoreError> {
+        self.query(
+            "SELECT data FROM reminders WHERE channel = ?1 AND next_fire <= ?2 ORDER BY next_fire",
+            params![channel, now.timestamp()],
+        )
+    }
+}
+
+/// 提醒投递端：每个接入端实现一次，负责把提醒推送到 origin.target
+#[async_trait]
+pub trait ReminderSink: Send + Sync {
+    /// 处理的接入端（与 ReminderOrigin::channel 对应）
+    fn channel(&self) -> &str;
+
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String>;
+}
+
+/// 投递一轮到期提醒，返回成功投递的数量
+pub async fn fire_due_reminders(
+    store: &ReminderStore,
+    sink: &dyn ReminderSink,
+    now: DateTime<Utc>,
+) -> usize {
+    let due = match store.due(sink.channel(), now) {
+        Ok(due) => due,
+        Err(e) => {
+            tracing::warn!("failed to load due reminders: {}", e);
+            return 0;
+        }
+    };
+    let mut delivered = 0;
+    for reminder in due {
+        let next = match sink.deliver(&reminder).await {
+            Ok(()) => {
+                delivered += 1;
+                reminder.advance(now)
+            }
+            Err(e) if reminder.failures + 1 >= MAX_DELIVERY_FAILURES => {
+                tracing::warn!(id = %reminder.id, "giving up reminder after {} failures: {}", MAX_DELIVERY_FAILURES, e);
+                reminder.advance(now)
+            }
+            Err(e) => {
+                tracing::warn!(id = %reminder.id, "reminder delivery failed, will retry: {}", e);
+                let failures = reminder.failures + 1;
+                Some(Reminder {
+                    next_fire: now.timestamp() + 60 * i64::from(failures),
+                    failures,
+                    ..reminder.clone()
+                })
+            }
+        };
+        let result = match next {
+            Some(next) => store.upsert(&next),
+            None => store.remove(&reminder.id).map(|_| ()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(id = %reminder.id, "failed to update reminder: {}", e);
+        }
+    }
+    delivered
+}
+
+impl TaskScheduler {
+    /// 后台轮询到期提醒并经 sink 推送（Background 任务，不占用工具并发许可）
+    pub fn spawn_reminders(
+        store: Arc<ReminderStore>,
+        sink: Arc<dyn ReminderSink>,
+        poll: Duration,
+    ) -> JoinHandle<()> {
+        tracing::info!(
+            channel = sink.channel(),
+            "reminder scheduler started, poll {}s",
+            poll.as_secs()
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll.max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                fire_due_reminders(&store, sink.as_ref(), Utc::now()).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    struct Collect(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl ReminderSink for Collect {
+        fn channel(&self) -> &str {
+            "web"
+        }
+
+        async fn deliver(&self, reminder: &Reminder) -> Result<(), String> {
+            self.0.lock().unwrap().push(reminder.text.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cron_and_persistent_reminders() {
+        let at = |s: &str| {
+            let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+            Local.from_local_datetime(&naive).earliest().unwrap()
+        };
+        let weekdays_9am = CronSchedule::parse("0 9 * * 1-5").unwrap();
+        // 2026-10-16 是周五：下一次为周一 09:00
+        assert_eq!(
+            weekdays_9am.next_after(at("2026-10-16 09:00")),
+            Some(at("2026-10-19 09:00"))
+        );
+        assert_eq!(
+            CronSchedule::parse("*/15 * * * *")
+                .unwrap()
+                .next_after(at("2026-10-16 09:07")),
+            Some(at("2026-10-16 09:15"))
+        );
+        assert!(CronSchedule::parse("0 0 31 2 *")
+            .unwrap()
+            .next_after(at("2026-01-01 00:00"))
+            .is_none());
+        assert!(CronSchedule::parse("61 * * * *").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let origin = ReminderOrigin {
+            channel: "web".into(),
+            target: "s1".into(),
+            assistant_id: None,
+            user_id: None,
+        };
+        let now = Utc::now();
+        let once = Reminder {
+            id: "r1".into(),
+            text: "stand up".into(),
+            origin: origin.clone(),
+            cron: None,
+            next_fire: now.timestamp() - 10,
+            created_at: now.timestamp() - 100,
+            failures: 0,
+        };
+        let daily = Reminder {
+            id: "r2".into(),
+            text: "water".into(),
+            cron: Some("@daily".into()),
+            ..once.clone()
+        };
+        let lark = Reminder {
+            id: "r3".into(),
+            origin: ReminderOrigin {
+                channel: "lark".into(),
+                ..origin.clone()
+            },
+            ..once.clone()
+        };
+        {
+            let store = ReminderStore::open(dir.path()).unwrap();
+            for r in [&once, &daily, &lark] {
+                store.upsert(r).unwrap();
+            }
+        }
+        // 重新打开（模拟重启）后补发到期提醒：一次性删除，周期提醒推进到下一次，其它接入端不受影响
+        let store = ReminderStore::open(dir.path()).unwrap();
+        let sink = Collect(Mutex::new(Vec::new()));
+        assert_eq!(fire_due_reminders(&store, &sink, now).await, 2);
+        assert_eq!(*sink.0.lock().unwrap(), vec!["stand up", "water"]);
+        let left = store.list(None).unwrap();
+        assert_eq!(
+            left.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(),
+            vec!["r3", "r2"]
+        );
+        assert!(left[1].next_fire > now.timestamp());
+        assert_eq!(store.list(Some(&origin)).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_priority_queue_and_assistant_quota() {
+        let scheduler = Arc::new(TaskScheduler::from_config(&SchedulerSection {
+            max_concurrent_llm: 1,
+            max_concurrent_tools: 2,
+            assistant_quota: 0,
+            assistant_quotas: HashMap::from([("coder".to_string(), 1)]),
+        }));
+
+        // LLM 队列占满：后到的交互请求排在后台任务之前
+        let held = scheduler.acquire_llm().await;
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        for (label, priority) in [("background", WorkPriority::Low), ("interactive", WorkPriority::Normal)] {
+            let (sched, tx) = (scheduler.clone(), order_tx.clone());
+            tokio::spawn(CURRENT_PRIORITY.scope(priority, async move {
+                let _permit = sched.acquire(WorkLane::Llm, label).await;
+                tx.send(label).unwrap();
+            }));
+        }
+        while scheduler.snapshot().lanes[0].waiting.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        let snapshot = scheduler.snapshot();
+        assert_eq!(snapshot.lanes[0].running[0].label, "plan");
+        assert_eq!(snapshot.lanes[0].waiting[0].label, "interactive");
+        drop(held);
+        assert_eq!(order_rx.recv().await, Some("interactive"));
+        assert_eq!(order_rx.recv().await, Some("background"));
+
+        // 助手配额：工具队列仍有空位，但 coder 同时只能进行一项工作
+        let as_coder = |sched: Arc<TaskScheduler>, tool: &'static str| {
+            CURRENT_ASSISTANT_ID.scope(Some("coder".to_string()), async move { sched.acquire_tool(tool).await })
+        };
+        let first = as_coder(scheduler.clone(), "shell").await;
+        let second = tokio::spawn(as_coder(scheduler.clone(), "cat"));
+        while scheduler.snapshot().lanes[1].waiting.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let load = &scheduler.snapshot().assistants[0];
+        assert_eq!((load.assistant_id.as_str(), load.running, load.waiting, load.quota), ("coder", 1, 1, Some(1)));
+        drop(first);
+        let second = second.await.unwrap();
+        assert_eq!(scheduler.snapshot().lanes[1].running[0].label, "cat");
+        drop(second);
+        assert!(scheduler.snapshot().lanes.iter().all(|l| l.running.is_empty() && l.waiting.is_empty()));
     }
 }
@@ -0,0 +1,307 @@
+//! 多用户：用户标识、每用户工作区与配额
+//!
+//! [users] isolate 开启时，请求归属于认证身份对应的 [`UserId`]（按凭据类型加命名空间，如 `key.ops`、`jwt.alice`），
+//! 其会话、记忆与任务位于 `workspace/users/<user_id>/`；默认用户仍使用 workspace 根目录，
+//! 因此单用户部署开启隔离前后目录布局不变。配额（每日请求数、并发运行数）按用户分别计数。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDate;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::auth::Principal;
+use crate::config::{UserQuotaOverride, UsersSection};
+
+/// 用户 ID 的最大长度
+const MAX_USER_ID_LEN: usize = 64;
+
+/// 用户标识：只含字母数字与 `-_.@`（可直接作为目录名），空值为默认用户
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UserId(String);
+
+impl UserId {
+    pub const DEFAULT: &'static str = "default";
+
+    pub fn new(raw: &str) -> Self {
+        let mut id: String = raw
+            .trim()
+            .chars()
+            .take(MAX_USER_ID_LEN)
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_.@".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        // 避免 "." / ".." 等路径分量
+        if id.starts_with('.') {
+            id.replace_range(..1, "_");
+        }
+        if id.is_empty() {
+            id = Self::DEFAULT.to_string();
+        }
+        Self(id)
+    }
+
+    /// 带命名空间的用户 ID `<namespace>.<name>`：name 中字母数字与 `-.@` 原样保留，其余字节（含 `_`）编码为 `_xx`，
+    /// 超长时截断并附加 `_h<摘要>`。编码可逆、命名空间不同即不同，因此不同身份不会映射到同一用户，也不会与默认用户重名
+    pub fn scoped(namespace: &str, name: &str) -> Self {
+        let mut id = format!("{}.", namespace);
+        for b in name.bytes() {
+            if b.is_ascii_alphanumeric() || b"-.@".contains(&b) {
+                id.push(b as char);
+            } else {
+                id.push_str(&format!("_{:02x}", b));
+            }
+        }
+        if id.len() > MAX_USER_ID_LEN {
+            let digest = Sha256::digest(format!("{}\0{}", namespace, name).as_bytes());
+            let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+            // 截断处不能落在 `_xx` 转义中间
+            let mut cut = MAX_USER_ID_LEN - hash.len() - 2;
+            while id[..cut].rfind('_').is_some_and(|i| i + 3 > cut) {
+                cut -= 1;
+            }
+            id.truncate(cut);
+            id.push_str("_h");
+            id.push_str(&hash);
+        }
+        Self(id)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.0 == Self::DEFAULT
+    }
+
+    /// 该用户的工作区：默认用户为 root 本身，其它用户为 root/users/<id>
+    pub fn workspace(&self, root: &Path) -> PathBuf {
+        if self.is_default() {
+            root.to_path_buf()
+        } else {
+            root.join("users").join(&self.0)
+        }
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Self::DEFAULT.to_string())
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 配额超限
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum QuotaError {
+    #[error("daily request quota exceeded ({0} per day)")]
+    DailyRequests(u32),
+    #[error("too many concurrent runs (limit {0})")]
+    ConcurrentRuns(u32),
+}
+
+/// 用户的配额上限（0 表示不限）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize)]
+pub struct QuotaLimits {
+    pub max_requests_per_day: u32,
+    pub max_concurrent_runs: u32,
+}
+
+/// 用户当日用量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct QuotaUsage {
+    pub requests_today: u32,
+    pub running: u32,
+    pub limits: QuotaLimits,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Counter {
+    day: NaiveDate,
+    requests: u32,
+    running: u32,
+}
+
+type Counters = Arc<Mutex<HashMap<UserId, Counter>>>;
+
+/// 多用户策略：决定请求归属的用户并执行配额
+pub struct Tenancy {
+    isolate: bool,
+    limits: QuotaLimits,
+    overrides: HashMap<String, UserQuotaOverride>,
+    counters: Counters,
+}
+
+impl From<&UsersSection> for Tenancy {
+    fn from(section: &UsersSection) -> Self {
+        Self {
+            isolate: section.isolate,
+            limits: QuotaLimits {
+                max_requests_per_day: section.max_requests_per_day,
+                max_concurrent_runs: section.max_concurrent_runs,
+            },
+            overrides: section.overrides.clone(),
+            counters: Arc::default(),
+        }
+    }
+}
+
+impl Tenancy {
+    pub fn isolating(&self) -> bool {
+        self.isolate
+    }
+
+    /// 请求归属的用户：隔离关闭或未启用认证（匿名）时为默认用户，否则按凭据类型加命名空间（`key.<名称>` / `jwt.<sub>`）
+    pub fn user_of(&self, principal: &Principal) -> UserId {
+        if self.isolate && !principal.is_anonymous() {
+            UserId::scoped(principal.kind.as_str(), &principal.name)
+        } else {
+            UserId::default()
+        }
+    }
+
+    pub fn limits(&self, user: &UserId) -> QuotaLimits {
+        let mut limits = self.limits;
+        if let Some(o) = self.overrides.get(user.as_str()) {
+            limits.max_requests_per_day = o.max_requests_per_day.unwrap_or(limits.max_requests_per_day);
+            limits.max_concurrent_runs = o.max_concurrent_runs.unwrap_or(limits.max_concurrent_runs);
+        }
+        limits
+    }
+
+    /// 开始一次运行：检查并计入当日请求数与并发数，返回的 guard 释放时并发数减一
+    pub fn acquire(&self, user: &UserId) -> Result<RunGuard, QuotaError> {
+        self.acquire_on(user, chrono::Utc::now().date_naive())
+    }
+
+    fn acquire_on(&self, user: &UserId, today: NaiveDate) -> Result<RunGuard, QuotaError> {
+        let limits = self.limits(user);
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let counter = counters.entry(user.clone()).or_insert(Counter {
+            day: today,
+            requests: 0,
+            running: 0,
+        });
+        if counter.day != today {
+            counter.day = today;
+            counter.requests = 0;
+        }
+        if limits.max_requests_per_day > 0 && counter.requests >= limits.max_requests_per_day {
+            return Err(QuotaError::DailyRequests(limits.max_requests_per_day));
+        }
+        if limits.max_concurrent_runs > 0 && counter.running >= limits.max_concurrent_runs {
+            return Err(QuotaError::ConcurrentRuns(limits.max_concurrent_runs));
+        }
+        counter.requests += 1;
+        counter.running += 1;
+        Ok(RunGuard {
+            user: user.clone(),
+            counters: Arc::clone(&self.counters),
+        })
+    }
+
+    /// 用户当日用量（GET /api/me）
+    pub fn usage(&self, user: &UserId) -> QuotaUsage {
+        let today = chrono::Utc::now().date_naive();
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let (requests_today, running) = counters
+            .get(user)
+            .map(|c| (if c.day == today { c.requests } else { 0 }, c.running))
+            .unwrap_or_default();
+        QuotaUsage {
+            requests_today,
+            running,
+            limits: self.limits(user),
+        }
+    }
+}
+
+/// 一次运行占用的并发名额
+pub struct RunGuard {
+    user: UserId,
+    counters: Counters,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(c) = counters.get_mut(&self.user) {
+            c.running = c.running.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::auth::PrincipalKind;
+
+    #[test]
+    fn test_user_ids_and_quotas() {
+        assert_eq!(UserId::new("alice@example.com").as_str(), "alice@example.com");
+        assert_eq!(UserId::new("../etc").as_str(), "_._etc");
+        assert!(UserId::new("  ").is_default());
+        let root = Path::new("/ws");
+        assert_eq!(UserId::default().workspace(root), PathBuf::from("/ws"));
+        assert_eq!(UserId::new("bob").workspace(root), PathBuf::from("/ws/users/bob"));
+
+        let section = UsersSection {
+            isolate: true,
+            max_requests_per_day: 2,
+            max_concurrent_runs: 1,
+            overrides: HashMap::from([(
+                "ops".to_string(),
+                UserQuotaOverride {
+                    max_requests_per_day: Some(0),
+                    max_concurrent_runs: None,
+                },
+            )]),
+        };
+        let tenancy = Tenancy::from(&section);
+        let principal = |name: &str, kind: PrincipalKind| Principal {
+            name: name.to_string(),
+            kind,
+            scopes: Default::default(),
+        };
+        assert_eq!(tenancy.user_of(&principal("bob", PrincipalKind::ApiKey)).as_str(), "key.bob");
+        assert!(tenancy.user_of(&Principal::anonymous()).is_default());
+        // 派生是单射的：同名的 Key 与 JWT、名为 default 的身份、仅特殊字符不同的名称互不相同
+        assert_eq!(tenancy.user_of(&principal("bob", PrincipalKind::Jwt)).as_str(), "jwt.bob");
+        assert!(!tenancy.user_of(&principal("default", PrincipalKind::ApiKey)).is_default());
+        assert_eq!(UserId::scoped("jwt", "a b").as_str(), "jwt.a_20b");
+        assert_ne!(UserId::scoped("jwt", "a b"), UserId::scoped("jwt", "a_b"));
+        assert_eq!(UserId::scoped("jwt", "a_b").as_str(), "jwt.a_5fb");
+        let long_a = UserId::scoped("jwt", &"x".repeat(100));
+        let long_b = UserId::scoped("jwt", &format!("{}y", "x".repeat(99)));
+        assert!(long_a.as_str().len() <= MAX_USER_ID_LEN);
+        assert_ne!(long_a, long_b);
+        assert_eq!(UserId::new(long_a.as_str()), long_a);
+
+        let bob = UserId::new("bob");
+        let day = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let guard = tenancy.acquire_on(&bob, day).unwrap();
+        assert_eq!(tenancy.acquire_on(&bob, day).err(), Some(QuotaError::ConcurrentRuns(1)));
+        drop(guard);
+        drop(tenancy.acquire_on(&bob, day).unwrap());
+        assert_eq!(tenancy.acquire_on(&bob, day).err(), Some(QuotaError::DailyRequests(2)));
+        assert!(tenancy.acquire_on(&bob, day.succ_opt().unwrap()).is_ok());
+
+        let ops = UserId::new("ops");
+        assert_eq!(tenancy.limits(&ops).max_requests_per_day, 0);
+        assert_eq!(tenancy.limits(&ops).max_concurrent_runs, 1);
+    }
+}
@@ -0,0 +1,365 @@
+//! 会话看门狗
+//!
+//! 监管运行中的 ReAct 会话：循环发出的每个事件都刷新心跳，超过 [watchdog] stall_secs 无任何事件
+//! （工具卡死、LLM 流中断等）即取消该会话并返回 AgentError::Stalled，由调用方记录教训并通知所属端点，
+//! 避免卡住的循环永久占用会话。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::WatchdogSection;
+use crate::core::AgentError;
+use crate::react::ReactEvent;
+use crate::tools::remind::current_origin;
+
+/// 取消后等待循环自行收尾的时间，超时则放弃运行（工具或 LLM 调用卡住时也能及时释放）
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// 正在监管的会话（供状态查询与 /api/admin/runs）
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedSession {
+    /// 运行 id，POST /api/admin/runs/:id/cancel 使用
+    pub id: u64,
+    pub session_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assistant_id: Option<String>,
+    /// 接入端：web / whatsapp / lark，未知时为 None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// 多用户部署中运行所属的用户，None 为默认用户
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// 当前 ReAct 步数
+    pub step: usize,
+    /// 本次运行累计消耗的 token
+    pub tokens: u64,
+    /// 已运行的秒数
+    pub elapsed_secs: u64,
+    /// 最近一次事件对应的活动（如 "tool shell"）
+    pub last_activity: String,
+    /// 距最近一次事件的秒数
+    pub idle_secs: u64,
+    /// 已请求取消、正在收尾
+    pub cancelling: bool,
+}
+
+struct Heartbeat {
+    session_id: String,
+    assistant_id: Option<String>,
+    channel: Option<String>,
+    user_id: Option<String>,
+    started: Instant,
+    last_event: Instant,
+    last_activity: String,
+    step: usize,
+    tokens: u64,
+    cancel_token: CancellationToken,
+}
+
+/// 会话看门狗（AgentComponents 持有，多会话共享）
+pub struct SessionWatchdog {
+    /// 无事件多久视为卡住；None 表示关闭（仍转发事件）
+    stall_after: Option<Duration>,
+    next_id: AtomicU64,
+    active: Mutex<HashMap<u64, Heartbeat>>,
+}
+
+impl SessionWatchdog {
+    pub fn new(section: &WatchdogSection) -> Self {
+        let stall_after = (section.enabled && section.stall_secs > 0).then(|| Duration::from_secs(section.stall_secs));
+        Self::with_stall_after(stall_after)
+    }
+
+    pub fn with_stall_after(stall_after: Option<Duration>) -> Self {
+        Self {
+            stall_after,
+            next_id: AtomicU64::new(0),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 当前正在监管的会话，按开始时间排列
+    pub fn active(&self) -> Vec<WatchedSession> {
+        let mut runs: Vec<(Instant, WatchedSession)> = self
+            .lock()
+            .iter()
+            .map(|(id, h)| {
+                let run = WatchedSession {
+                    id: *id,
+                    session_id: h.session_id.clone(),
+                    assistant_id: h.assistant_id.clone(),
+                    channel: h.channel.clone(),
+                    user_id: h.user_id.clone(),
+                    step: h.step,
+                    tokens: h.tokens,
+                    elapsed_secs: h.started.elapsed().as_secs(),
+                    last_activity: h.last_activity.clone(),
+                    idle_secs: h.last_event.elapsed().as_secs(),
+                    cancelling: h.cancel_token.is_cancelled(),
+                };
+                (h.started, run)
+            })
+            .collect();
+        runs.sort_by_key(|(started, run)| (*started, run.id));
+        runs.into_iter().map(|(_, run)| run).collect()
+    }
+
+    /// 取消运行中的会话（触发其 CancellationToken）；id 不存在时返回 false
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.lock().get(&id) {
+            Some(h) => {
+                tracing::info!(id, session_id = %h.session_id, "watchdog: run cancelled");
+                h.cancel_token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 登记一次运行（不做卡住检测），返回的登记在 drop 时移除；
+    /// 会话 id 与助手优先取自当前消息来源（CURRENT_ORIGIN），否则用 session_id
+    pub fn register(&self, session_id: &str, cancel_token: &CancellationToken) -> RunRegistration<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let origin = current_origin();
+        let now = Instant::now();
+        self.lock().insert(
+            id,
+            Heartbeat {
+                session_id: origin.as_ref().map_or(session_id, |o| o.target.as_str()).to_string(),
+                assistant_id: origin.as_ref().and_then(|o| o.assistant_id.clone()),
+                channel: origin.as_ref().map(|o| o.channel.clone()),
+                user_id: origin.and_then(|o| o.user_id),
+                started: now,
+                last_event: now,
+                last_activity: "start".to_string(),
+                step: 0,
+                tokens: 0,
+                cancel_token: cancel_token.clone(),
+            },
+        );
+        RunRegistration { watchdog: self, id }
+    }
+
+    /// 在看门狗监管下运行 run：把 events 中的事件转发到 forward 并刷新心跳；
+    /// 超时无事件时取消 cancel_token、向 forward 发出 Error 事件，并放弃 run 返回 AgentError::Stalled；
+    /// cancel_token 被取消（如 /api/admin/runs/:id/cancel）后 run 在宽限期内未结束则放弃并返回 AgentError::Cancelled
+    pub async fn supervise<T>(
+        &self,
+        session_id: &str,
+        cancel_token: &CancellationToken,
+        mut events: mpsc::UnboundedReceiver<ReactEvent>,
+        forward: Option<&mpsc::UnboundedSender<ReactEvent>>,
+        run: impl Future<Output = Result<T, AgentError>>,
+    ) -> Result<T, AgentError> {
+        let registration = self.register(session_id, cancel_token);
+        let id = registration.id;
+        let send = |ev: ReactEvent| {
+            if let Some(tx) = forward {
+                let _ = tx.send(ev);
+            }
+        };
+
+        tokio::pin!(run);
+        let mut last_event = Instant::now();
+        let mut activity = "start".to_string();
+        let mut cancel_deadline: Option<Instant> = None;
+        loop {
+            let remaining = match cancel_deadline {
+                Some(deadline) => Some(deadline.saturating_duration_since(Instant::now())),
+                None => self.stall_after.map(|d| d.saturating_sub(last_event.elapsed())),
+            };
+            tokio::select! {
+                result = &mut run => {
+                    while let Ok(ev) = events.try_recv() {
+                        send(ev);
+                    }
+                    return result;
+                }
+                Some(ev) = events.recv() => {
+                    last_event = Instant::now();
+                    activity = describe(&ev);
+                    if let Some(h) = self.lock().get_mut(&id) {
+                        h.last_event = last_event;
+                        h.last_activity = activity.clone();
+                        match ev {
+                            ReactEvent::StepUpdate { step, .. } => h.step = step,
+                            ReactEvent::TokenUsage { total_tokens, .. } => h.tokens += total_tokens,
+                            _ => {}
+                        }
+                    }
+                    send(ev);
+                }
+                _ = cancel_token.cancelled(), if cancel_deadline.is_none() => {
+                    cancel_deadline = Some(Instant::now() + CANCEL_GRACE);
+                }
+                _ = tokio::time::sleep(remaining.unwrap_or_default()), if remaining.is_some() => {
+                    if cancel_deadline.is_some() {
+                        tracing::warn!(session_id, %activity, "watchdog: cancelled run did not stop in time, abandoning");
+                        send(ReactEvent::Error { text: "Cancelled".to_string() });
+                        return Err(AgentError::Cancelled);
+                    }
+                    let idle_secs = last_event.elapsed().as_secs();
+                    tracing::warn!(session_id, %activity, idle_secs, "watchdog: session stalled, cancelling");
+                    cancel_token.cancel();
+                    send(ReactEvent::Error {
+                        text: format!("会话在「{}」后 {} 秒无进展，已被看门狗取消", activity, idle_secs),
+                    });
+                    return Err(AgentError::Stalled { activity, idle_secs });
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Heartbeat>> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 运行登记，结束（含被 drop）时从监管表移除
+pub struct RunRegistration<'a> {
+    watchdog: &'a SessionWatchdog,
+    id: u64,
+}
+
+impl Drop for RunRegistration<'_> {
+    fn drop(&mut self) {
+        self.watchdog.lock().remove(&self.id);
+    }
+}
+
+/// 事件对应的活动描述，写入日志与教训
+fn describe(ev: &ReactEvent) -> String {
+    match ev {
+        ReactEvent::ToolCall { tool, .. } => format!("tool {}", tool),
+        ReactEvent::Observation { tool, .. } | ReactEvent::ToolFailure { tool, .. } => format!("after tool {}", tool),
+        ReactEvent::ApprovalRequired { tool, .. } => format!("approval of {}", tool),
+        ReactEvent::Thinking | ReactEvent::ThinkingContent { .. } => "thinking".to_string(),
+        ReactEvent::MessageChunk { .. } => "streaming reply".to_string(),
+        _ => "react step".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_forwards_and_cancels_stalled_session() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let watchdog = SessionWatchdog::with_stall_after(Some(Duration::from_millis(100)));
+            let (forward_tx, mut forward_rx) = mpsc::unbounded_channel();
+
+            // 正常完成：事件全部转发
+            let token = CancellationToken::new();
+            let (tx, rx) = mpsc::unbounded_channel();
+            let result = watchdog
+                .supervise("s1", &token, rx, Some(&forward_tx), async move {
+                    tx.send(ReactEvent::Thinking).unwrap();
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    tx.send(ReactEvent::MessageDone).unwrap();
+                    Ok::<_, AgentError>("done")
+                })
+                .await;
+            assert_eq!(result.unwrap(), "done");
+            assert!(matches!(forward_rx.try_recv(), Ok(ReactEvent::Thinking)));
+            assert!(matches!(forward_rx.try_recv(), Ok(ReactEvent::MessageDone)));
+            assert!(!token.is_cancelled());
+
+            // 卡在工具里：取消并返回 Stalled
+            let (tx, rx) = mpsc::unbounded_channel();
+            tx.send(ReactEvent::ToolCall { tool: "shell".to_string(), args: serde_json::json!({}) })
+                .unwrap();
+            let result = watchdog
+                .supervise("s2", &token, rx, Some(&forward_tx), std::future::pending::<Result<(), AgentError>>())
+                .await;
+            match result {
+                Err(AgentError::Stalled { activity, .. }) => assert_eq!(activity, "tool shell"),
+                other => panic!("expected stalled, got {:?}", other),
+            }
+            assert!(token.is_cancelled());
+            assert!(watchdog.active().is_empty());
+            assert!(matches!(forward_rx.try_recv(), Ok(ReactEvent::ToolCall { .. })));
+            assert!(matches!(forward_rx.try_recv(), Ok(ReactEvent::Error { .. })));
+
+            // 管理端取消：active 列出运行的步数与 token，cancel 触发其令牌
+            let token = CancellationToken::new();
+            let (tx, rx) = mpsc::unbounded_channel();
+            tx.send(ReactEvent::StepUpdate { step: 3, max_steps: 10 }).unwrap();
+            tx.send(ReactEvent::TokenUsage {
+                prompt_tokens: 30,
+                completion_tokens: 12,
+                total_tokens: 42,
+                cumulative_prompt: 30,
+                cumulative_completion: 12,
+                cumulative_total: 42,
+            })
+            .unwrap();
+            let run_token = token.clone();
+            let run = watchdog.supervise("s3", &token, rx, None, async move {
+                run_token.cancelled().await;
+                Err::<(), _>(AgentError::Cancelled)
+            });
+            tokio::pin!(run);
+            tokio::select! {
+                _ = &mut run => panic!("run finished before cancel"),
+                _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+            }
+            let runs = watchdog.active();
+            assert_eq!(runs.len(), 1);
+            assert_eq!((runs[0].session_id.as_str(), runs[0].step, runs[0].tokens), ("s3", 3, 42));
+            assert!(!runs[0].cancelling);
+            assert!(watchdog.cancel(runs[0].id));
+            assert!(matches!(run.await, Err(AgentError::Cancelled)));
+            assert!(watchdog.active().is_empty());
+            assert!(!watchdog.cancel(runs[0].id));
+            drop(tx);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_register_lists_origin_and_cancels_by_id() {
+        use crate::core::ReminderOrigin;
+        use crate::tools::remind::CURRENT_ORIGIN;
+
+        let watchdog = SessionWatchdog::with_stall_after(None);
+        let token = CancellationToken::new();
+        let plain = watchdog.register("tui", &token);
+        let origin = ReminderOrigin {
+            channel: "whatsapp".into(),
+            target: "+8613800000000".into(),
+            assistant_id: Some("coder".into()),
+            user_id: Some("key.alice".into()),
+        };
+        let other = CancellationToken::new();
+        let from_chat = CURRENT_ORIGIN
+            .scope(Some(origin), async { watchdog.register("ignored", &other) })
+            .await;
+
+        // 有消息来源时按来源列出会话、助手、接入端与用户
+        let runs = watchdog.active();
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[0].session_id.as_str(), runs[0].channel.as_deref()), ("tui", None));
+        assert_eq!(runs[1].session_id, "+8613800000000");
+        assert_eq!(runs[1].assistant_id.as_deref(), Some("coder"));
+        assert_eq!(runs[1].channel.as_deref(), Some("whatsapp"));
+        assert_eq!(runs[1].user_id.as_deref(), Some("key.alice"));
+
+        // 按 id 取消只影响该运行；登记 drop 后从列表移除
+        assert!(watchdog.cancel(runs[1].id));
+        assert!(other.is_cancelled() && !token.is_cancelled());
+        assert!(watchdog.active()[1].cancelling);
+        drop(from_chat);
+        assert_eq!(watchdog.active().len(), 1);
+        assert!(!watchdog.cancel(runs[1].id));
+        drop(plain);
+        assert!(watchdog.active().is_empty());
+    }
+}
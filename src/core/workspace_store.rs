@@ -0,0 +1,561 @@
+//! 工作区任务、群组与会话元数据存储
+//!
+//! 任务与群组原先保存在 tasks.json / groups.json，每次请求整文件读改写，web 接口与 create / send 等工具
+//! 并发更新时后写者会覆盖先写者。现统一存入 workspace/workspace.db（SQLite，每行一条 JSON），
+//! 读改写在 IMMEDIATE 事务内完成；首次打开时导入旧 JSON 文件并改名为 *.migrated。
+//! Web 会话的标题、置顶、标签等元数据（原 session_meta.json）同样存于此库。
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// 数据库文件名（位于 workspace 根目录）
+pub const WORKSPACE_DB_FILE: &str = "workspace.db";
+
+/// 其它连接持有写锁时的等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 存储错误
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("sqlite: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("serialize: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// 任务状态：看板列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// 有未完成的前置任务（depends_on），全部完成后自动回到 Todo
+    Blocked,
+    Todo,
+    InProgress,
+    Done,
+}
+
+impl TaskStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Blocked => "blocked",
+            TaskStatus::Todo => "todo",
+            TaskStatus::InProgress => "in_progress",
+            TaskStatus::Done => "done",
+        }
+    }
+}
+
+/// 任务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub status: TaskStatus,
+    #[serde(default)]
+    pub assignee_ids: Vec<String>,
+    #[serde(default)]
+    pub group_id: Option<String>,
+    /// 统筹负责人 agent id，负责拆分任务、创建子 agent、组队、分配职责
+    #[serde(default)]
+    pub coordinator_id: Option<String>,
+    /// 前置任务 id，均完成后本任务才可开始
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// 截止时间（RFC 3339）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<String>,
+    /// 重复规则（5 段 cron 或 @daily / @weekly / @monthly），完成时生成下一期任务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+    /// 多用户部署中创建任务的用户（[users] isolate），None 为默认用户
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// 群聊模式：serial 依次回复；debate 成员独立作答若干轮后由裁判综合
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupMode {
+    #[default]
+    Serial,
+    Debate,
+}
+
+pub fn default_debate_rounds() -> usize {
+    1
+}
+
+/// 群组定义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupInfo {
+    pub id: String,
+    pub name: Option<String>,
+    pub member_ids: Vec<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub mode: GroupMode,
+    /// debate 模式的裁判助手（为空时使用第一个成员）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judge_id: Option<String>,
+    /// debate 模式的辩论轮数
+    #[serde(default = "default_debate_rounds")]
+    pub rounds: usize,
+}
+
+impl GroupInfo {
+    /// serial 模式的新群组，created_at 为当前时间
+    pub fn new(id: impl Into<String>, name: Option<String>, member_ids: Vec<String>) -> Self {
+        Self {
+            id: id.into(),
+            name,
+            member_ids,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            mode: GroupMode::Serial,
+            judge_id: None,
+            rounds: default_debate_rounds(),
+        }
+    }
+}
+
+/// 会话使用过的提示词版本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionPromptVersion {
+    pub prompt: String,
+    pub version: u32,
+    /// 首次使用时间（RFC 3339）
+    pub used_at: String,
+}
+
+/// Web 会话元数据，key 为会话的复合 key（{session_id}::{assistant_id}，非默认用户带 {user_id}/ 前缀）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMeta {
+    /// 用户设置的标题，优先于自动标题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 首轮对话后由 LLM 生成的标题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_title: Option<String>,
+    /// 置顶：会话列表中排在最前
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 会话使用过的提示词版本（按首次使用顺序），便于排查行为变化
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompt_versions: Vec<SessionPromptVersion>,
+}
+
+impl SessionMeta {
+    /// 展示用标题：用户标题，其次自动标题
+    pub fn display_title(&self) -> Option<&str> {
+        self.title.as_deref().or(self.auto_title.as_deref()).filter(|t| !t.is_empty())
+    }
+
+    /// 设置标签：去空白、去重并保持顺序
+    pub fn set_tags<I: IntoIterator<Item = String>>(&mut self, tags: I) {
+        self.tags.clear();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !self.tags.iter().any(|t| t == tag) {
+                self.tags.push(tag.to_string());
+            }
+        }
+    }
+}
+
+/// 任务仓库
+pub trait TaskRepository: Send + Sync {
+    /// 按创建顺序列出全部任务
+    fn list_tasks(&self) -> Result<Vec<Task>, StoreError>;
+
+    fn get_task(&self, id: &str) -> Result<Option<Task>, StoreError>;
+
+    fn insert_task(&self, task: &Task) -> Result<(), StoreError>;
+
+    /// 在同一事务内读取、修改并写回任务；任务不存在时返回 None
+    fn update_task(&self, id: &str, f: &mut dyn FnMut(&mut Task)) -> Result<Option<Task>, StoreError>;
+}
+
+/// 群组仓库
+pub trait GroupRepository: Send + Sync {
+    fn list_groups(&self) -> Result<Vec<GroupInfo>, StoreError>;
+
+    fn get_group(&self, id: &str) -> Result<Option<GroupInfo>, StoreError>;
+
+    /// 插入或覆盖群组
+    fn upsert_group(&self, group: &GroupInfo) -> Result<(), StoreError>;
+
+    /// 群组不存在时插入（如 P2P 群），返回是否新建
+    fn insert_group_if_absent(&self, group: &GroupInfo) -> Result<bool, StoreError>;
+}
+
+/// 会话元数据仓库
+pub trait SessionMetaRepository: Send + Sync {
+    fn get_session_meta(&self, key: &str) -> Result<Option<SessionMeta>, StoreError>;
+
+    /// 全部会话元数据（key -> 元数据）
+    fn list_session_meta(&self) -> Result<std::collections::HashMap<String, SessionMeta>, StoreError>;
+
+    /// 在同一事务内读取（不存在时为默认值）、修改并写回，返回修改后的元数据
+    fn update_session_meta(&self, key: &str, f: &mut dyn FnMut(&mut SessionMeta)) -> Result<SessionMeta, StoreError>;
+
+    /// 删除会话元数据，返回是否存在
+    fn delete_session_meta(&self, key: &str) -> Result<bool, StoreError>;
+}
+
+/// SQLite 实现：同进程内经 Mutex 串行，跨连接（工具与 web 各自打开）由 SQLite 写锁串行
+pub struct SqliteWorkspaceStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteWorkspaceStore {
+    /// 打开 workspace/workspace.db，并导入旧的 tasks.json / groups.json
+    pub fn open(workspace: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(workspace)?;
+        let store = Self::open_at(&workspace.join(WORKSPACE_DB_FILE))?;
+        store.import_legacy(&workspace.join("tasks.json"), "tasks")?;
+        store.import_legacy(&workspace.join("groups.json"), "groups")?;
+        store.import_legacy_session_meta(&workspace.join("session_meta.json"))?;
+        Ok(store)
+    }
+
+    /// 打开指定路径的数据库（不做旧文件导入）
+    pub fn open_at(db_path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS tasks (
+                 seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                 id TEXT NOT NULL UNIQUE,
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS groups (
+                 seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                 id TEXT NOT NULL UNIQUE,
+                 data TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS session_meta (
+                 seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                 id TEXT NOT NULL UNIQUE,
+                 data TEXT NOT NULL
+             );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 旧 JSON 文件（tasks.json 为数组，groups.json 为 id -> 群组对象）导入对应表，成功后改名为 *.migrated
+    fn import_legacy(&self, path: &Path, table: &str) -> Result<(), StoreError> {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Ok(());
+        };
+        let value: serde_json::Value = serde_json::from_str(&content).unwrap_or_default();
+        let rows: Vec<serde_json::Value> = match value {
+            serde_json::Value::Array(items) => items,
+            serde_json::Value::Object(map) => map.into_iter().map(|(_, v)| v).collect(),
+            _ => Vec::new(),
+        };
+        {
+            let mut conn = self.conn();
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            for row in rows {
+                let Some(id) = row.get("id").and_then(|v| v.as_str()).map(str::to_string) else {
+                    continue;
+                };
+                tx.execute(
+                    &format!("INSERT OR IGNORE INTO {} (id, data) VALUES (?1, ?2)", table),
+                    params![id, row.to_string()],
+                )?;
+            }
+            tx.commit()?;
+        }
+        let mut migrated = path.as_os_str().to_owned();
+        migrated.push(".migrated");
+        std::fs::rename(path, PathBuf::from(migrated))?;
+        tracing::info!(path = %path.display(), "imported legacy {} into {}", table, WORKSPACE_DB_FILE);
+        Ok(())
+    }
+
+    /// 旧 session_meta.json（key -> { title, renamed, prompt_versions }）：renamed 的标题导入为用户标题，
+    /// 其余为自动标题；成功后改名为 session_meta.json.migrated
+    fn import_legacy_session_meta(&self, path: &Path) -> Result<(), StoreError> {
+        #[derive(Deserialize)]
+        struct LegacyMeta {
+            #[serde(default)]
+            title: String,
+            #[serde(default)]
+            renamed: bool,
+            #[serde(default)]
+            prompt_versions: Vec<SessionPromptVersion>,
+        }
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Ok(());
+        };
+        let legacy: std::collections::HashMap<String, LegacyMeta> =
+            serde_json::from_str(&content).unwrap_or_default();
+        {
+            let mut conn = self.conn();
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            for (key, old) in legacy {
+                let title = Some(old.title).filter(|t| !t.is_empty());
+                let meta = SessionMeta {
+                    title: title.clone().filter(|_| old.renamed),
+                    auto_title: title.filter(|_| !old.renamed),
+                    prompt_versions: old.prompt_versions,
+                    ..Default::default()
+                };
+                tx.execute(
+                    "INSERT OR IGNORE INTO session_meta (id, data) VALUES (?1, ?2)",
+                    params![key, serde_json::to_string(&meta)?],
+                )?;
+            }
+            tx.commit()?;
+        }
+        let mut migrated = path.as_os_str().to_owned();
+        migrated.push(".migrated");
+        std::fs::rename(path, PathBuf::from(migrated))?;
+        tracing::info!(path = %path.display(), "imported legacy session meta into {}", WORKSPACE_DB_FILE);
+        Ok(())
+    }
+
+    fn list<T: for<'de> Deserialize<'de>>(&self, table: &str) -> Result<Vec<T>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!("SELECT data FROM {} ORDER BY seq", table))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        // 单行损坏不影响其它记录
+        Ok(rows.iter().filter_map(|d| serde_json::from_str(d).ok()).collect())
+    }
+
+    fn get<T: for<'de> Deserialize<'de>>(&self, table: &str, id: &str) -> Result<Option<T>, StoreError> {
+        let data: Option<String> = self
+            .conn()
+            .query_row(&format!("SELECT data FROM {} WHERE id = ?1", table), [id], |row| row.get(0))
+            .optional()?;
+        Ok(data.map(|d| serde_json::from_str(&d)).transpose()?)
+    }
+}
+
+impl TaskRepository for SqliteWorkspaceStore {
+    fn list_tasks(&self) -> Result<Vec<Task>, StoreError> {
+        self.list("tasks")
+    }
+
+    fn get_task(&self, id: &str) -> Result<Option<Task>, StoreError> {
+        self.get("tasks", id)
+    }
+
+    fn insert_task(&self, task: &Task) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT INTO tasks (id, data) VALUES (?1, ?2)",
+            params![task.id, serde_json::to_string(task)?],
+        )?;
+        Ok(())
+    }
+
+    fn update_task(&self, id: &str, f: &mut dyn FnMut(&mut Task)) -> Result<Option<Task>, StoreError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let data: Option<String> = tx
+            .query_row("SELECT data FROM tasks WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        let Some(data) = data else {
+            return Ok(None);
+        };
+        let mut task: Task = serde_json::from_str(&data)?;
+        f(&mut task);
+        tx.execute(
+            "UPDATE tasks SET data = ?1 WHERE id = ?2",
+            params![serde_json::to_string(&task)?, id],
+        )?;
+        tx.commit()?;
+        Ok(Some(task))
+    }
+}
+
+impl GroupRepository for SqliteWorkspaceStore {
+    fn list_groups(&self) -> Result<Vec<GroupInfo>, StoreError> {
+        self.list("groups")
+    }
+
+    fn get_group(&self, id: &str) -> Result<Option<GroupInfo>, StoreError> {
+        self.get("groups", id)
+    }
+
+    fn upsert_group(&self, group: &GroupInfo) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT INTO groups (id, data) VALUES (?1, ?2) ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            params![group.id, serde_json::to_string(group)?],
+        )?;
+        Ok(())
+    }
+
+    fn insert_group_if_absent(&self, group: &GroupInfo) -> Result<bool, StoreError> {
+        let n = self.conn().execute(
+            "INSERT OR IGNORE INTO groups (id, data) VALUES (?1, ?2)",
+            params![group.id, serde_json::to_string(group)?],
+        )?;
+        Ok(n > 0)
+    }
+}
+
+impl SessionMetaRepository for SqliteWorkspaceStore {
+    fn get_session_meta(&self, key: &str) -> Result<Option<SessionMeta>, StoreError> {
+        self.get("session_meta", key)
+    }
+
+    fn list_session_meta(&self) -> Result<std::collections::HashMap<String, SessionMeta>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT id, data FROM session_meta")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, data)| serde_json::from_str(&data).ok().map(|m| (id, m)))
+            .collect())
+    }
+
+    fn update_session_meta(&self, key: &str, f: &mut dyn FnMut(&mut SessionMeta)) -> Result<SessionMeta, StoreError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let data: Option<String> = tx
+            .query_row("SELECT data FROM session_meta WHERE id = ?1", [key], |row| row.get(0))
+            .optional()?;
+        let mut meta: SessionMeta = data
+            .and_then(|d| serde_json::from_str(&d).ok())
+            .unwrap_or_default();
+        f(&mut meta);
+        tx.execute(
+            "INSERT INTO session_meta (id, data) VALUES (?1, ?2) ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            params![key, serde_json::to_string(&meta)?],
+        )?;
+        tx.commit()?;
+        Ok(meta)
+    }
+
+    fn delete_session_meta(&self, key: &str) -> Result<bool, StoreError> {
+        let n = self.conn().execute("DELETE FROM session_meta WHERE id = ?1", [key])?;
+        Ok(n > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn task(id: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            title: format!("task {}", id),
+            description: None,
+            status: TaskStatus::Todo,
+            assignee_ids: Vec::new(),
+            group_id: None,
+            coordinator_id: None,
+            depends_on: Vec::new(),
+            due_at: None,
+            recurrence: None,
+            owner: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_workspace_store_concurrent_updates_and_legacy_import() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("tasks.json"),
+            serde_json::to_string(&vec![task("legacy")]).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("groups.json"),
+            r#"{"g1": {"id": "g1", "name": null, "member_ids": ["a", "b"], "created_at": "", "mode": "debate", "rounds": 3}}"#,
+        )
+        .unwrap();
+
+        let store = Arc::new(SqliteWorkspaceStore::open(dir.path()).unwrap());
+        assert!(!dir.path().join("tasks.json").exists());
+        assert_eq!(store.get_task("legacy").unwrap().unwrap().title, "task legacy");
+        let g1 = store.get_group("g1").unwrap().unwrap();
+        assert_eq!((g1.mode, g1.rounds), (GroupMode::Debate, 3));
+
+        // 两个连接（web 与工具）并发读改写同一任务，不丢更新
+        store.insert_task(&task("t")).unwrap();
+        let other = Arc::new(SqliteWorkspaceStore::open(dir.path()).unwrap());
+        let handles: Vec<_> = [Arc::clone(&store), other]
+            .into_iter()
+            .flat_map(|s| (0..10).map(move |_| Arc::clone(&s)))
+            .map(|s| {
+                std::thread::spawn(move || {
+                    s.update_task("t", &mut |t| t.assignee_ids.push("x".into())).unwrap();
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(store.get_task("t").unwrap().unwrap().assignee_ids.len(), 20);
+        assert!(store.update_task("missing", &mut |_| {}).unwrap().is_none());
+
+        let p2p = GroupInfo::new("p2p_a_b", None, vec!["a".into(), "b".into()]);
+        assert!(store.insert_group_if_absent(&p2p).unwrap());
+        assert!(!store.insert_group_if_absent(&p2p).unwrap());
+        let ids: Vec<String> = store.list_groups().unwrap().into_iter().map(|g| g.id).collect();
+        assert_eq!(ids, vec!["g1", "p2p_a_b"]);
+        assert_eq!(store.list_tasks().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_session_meta_store_and_legacy_import() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("session_meta.json"),
+            r#"{"s1::default": {"title": "周报", "renamed": true},
+                "s2::coder": {"title": "修复编译错误", "prompt_versions": [{"prompt": "coder", "version": 2, "used_at": ""}]}}"#,
+        )
+        .unwrap();
+        let store = SqliteWorkspaceStore::open(dir.path()).unwrap();
+        assert!(dir.path().join("session_meta.json.migrated").exists());
+        let s1 = store.get_session_meta("s1::default").unwrap().unwrap();
+        assert_eq!((s1.title.as_deref(), s1.auto_title.as_deref()), (Some("周报"), None));
+        let s2 = store.get_session_meta("s2::coder").unwrap().unwrap();
+        assert_eq!(s2.display_title(), Some("修复编译错误"));
+        assert_eq!(s2.prompt_versions.len(), 1);
+
+        let updated = store
+            .update_session_meta("s3::default", &mut |m| {
+                m.pinned = true;
+                m.set_tags(vec![" work ".into(), "work".into(), "".into(), "rust".into()]);
+            })
+            .unwrap();
+        assert_eq!(updated.tags, vec!["work", "rust"]);
+        store
+            .update_session_meta("s3::default", &mut |m| m.auto_title = Some("自动".into()))
+            .unwrap();
+        let s3 = store.get_session_meta("s3::default").unwrap().unwrap();
+        assert!(s3.pinned && s3.display_title() == Some("自动"));
+        assert_eq!(store.list_session_meta().unwrap().len(), 3);
+        assert!(store.delete_session_meta("s3::default").unwrap());
+        assert!(!store.delete_session_meta("s3::default").unwrap());
+    }
+}
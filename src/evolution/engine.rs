@@ -36,6 +36,8 @@ pub struct EvolutionConfig {
     pub allowed_operation_types: Vec<String>,
     pub rollback_enabled: bool,
     pub backup_before_edit: bool,
+    pub auto_pr: bool,
+    pub pr_base: Option<String>,
 }
 
 impl From<EvolutionSection> for EvolutionConfig {
@@ -63,6 +65,8 @@ impl From<EvolutionSection> for EvolutionConfig {
             allowed_operation_types: section.allowed_operation_types,
             rollback_enabled: section.rollback_enabled,
             backup_before_edit: section.backup_before_edit,
+            auto_pr: section.auto_pr,
+            pr_base: section.pr_base,
         }
     }
 }
@@ -101,6 +101,12 @@ impl ExecutionEngine {
 
         if self.config.auto_commit {
             self.commit_changes(plan).await?;
+            if self.config.auto_pr {
+                match self.open_pull_request(plan, &changes_made, tests_passed).await {
+                    Ok(result) => changes_made.push(result),
+                    Err(e) => lessons_learned.push(format!("PR creation failed: {}", e)),
+                }
+            }
         }
 
         Ok(IterationResult {
@@ -475,6 +481,32 @@ mod tests {
         Ok(())
     }
 
+    /// 推送当前分支并通过 github 工具创建 PR，供人工审查后合并
+    async fn open_pull_request(
+        &self,
+        plan: &ImprovementPlan,
+        changes: &[String],
+        tests_passed: bool,
+    ) -> Result<String, String> {
+        let body = format!(
+            "{}\n\n预期结果: {}\n\n变更:\n{}\n\n测试: {}",
+            plan.description,
+            plan.expected_outcome,
+            changes.iter().map(|c| format!("- {}", c)).collect::<Vec<_>>().join("\n"),
+            if tests_passed { "通过" } else { "未通过" }
+        );
+        let mut args = serde_json::json!({
+            "action": "create_pr",
+            "title": format!("{}: {}", plan.improvement_type, plan.title),
+            "body": body,
+            "push": true,
+        });
+        if let Some(base) = &self.config.pr_base {
+            args["base"] = serde_json::json!(base);
+        }
+        self.executor.execute("github", args).await.map_err(|e| e.to_string())
+    }
+
     async fn check_approval(&self, plan: &ImprovementPlan) -> Result<bool, String> {
         match self.config.approval_mode {
             ApprovalMode::None => Ok(true),
@@ -0,0 +1,239 @@
+//! Hub 联邦：把远端 Hub 注册为能力端点
+//!
+//! [federation.remotes.<名称>] 中的每个远端是一个 [`RemoteHubSpoke`]。runtime 收到消息时识别意图，意图类别在某个远端的
+//! capabilities 白名单中时，经网关 WebSocket 协议（Auth + UserMessage）把请求转交该远端，转发其流式事件并以远端的
+//! 最终回复作为本次回复；远端不可用时回退本地处理。
+//!
+//! 转交时以 `federation:<本地会话 ID>` 作为远端的 client_id，远端按该会话保持上下文；来自联邦的请求不会被再次转交，
+//! 避免两个互为远端的 Hub 来回转发。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use super::intent::Intent;
+use super::message::{ClientInfo, GatewayMessage, MessageType, SpokeType};
+use super::spoke::{CapabilitySpoke, CapabilitySpokeType};
+use crate::config::{FederationSection, RemoteHubSection};
+
+/// 联邦请求在远端使用的 client_id 前缀
+pub const FEDERATED_CLIENT_PREFIX: &str = "federation:";
+
+/// 远端 Hub 能力端点
+pub struct RemoteHubSpoke {
+    name: String,
+    description: String,
+    url: String,
+    token: Option<String>,
+    capabilities: HashSet<String>,
+    timeout: Duration,
+}
+
+impl RemoteHubSpoke {
+    /// 按配置创建，token 从 token_env 指定的环境变量读取
+    pub fn new(name: &str, section: &RemoteHubSection) -> Self {
+        let token = section
+            .token_env
+            .as_deref()
+            .and_then(|var| std::env::var(var).ok())
+            .filter(|t| !t.trim().is_empty());
+        Self {
+            name: name.to_string(),
+            description: format!("远端 Hub {}（{}）", name, section.capabilities.join(", ")),
+            url: section.url.clone(),
+            token,
+            capabilities: section
+                .capabilities
+                .iter()
+                .map(|c| c.trim().to_ascii_lowercase())
+                .collect(),
+            timeout: Duration::from_secs(section.timeout_secs.max(1)),
+        }
+    }
+
+    /// 该远端是否接收此意图
+    pub fn handles(&self, intent: &Intent) -> bool {
+        self.capabilities.contains(intent.kind())
+    }
+
+    /// 把一条消息转交远端：流式事件经 on_event 回调，返回远端的最终回复
+    pub async fn forward(
+        &self,
+        client_id: &str,
+        content: &str,
+        mut on_event: impl FnMut(MessageType) + Send,
+    ) -> Result<String, String> {
+        tokio::time::timeout(self.timeout, self.exchange(client_id, content, &mut on_event))
+            .await
+            .map_err(|_| format!("remote hub {} timed out after {}s", self.name, self.timeout.as_secs()))?
+    }
+
+    async fn exchange(
+        &self,
+        client_id: &str,
+        content: &str,
+        on_event: &mut (impl FnMut(MessageType) + Send),
+    ) -> Result<String, String> {
+        let (ws, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .map_err(|e| format!("connect {}: {}", self.url, e))?;
+        let (mut ws_tx, mut ws_rx) = ws.split();
+        let send = |message: MessageType| {
+            let json = serde_json::to_string(&GatewayMessage::new(None, message)).unwrap_or_default();
+            WsMessage::Text(json)
+        };
+        let auth = MessageType::Auth {
+            token: self.token.clone(),
+            client_info: ClientInfo {
+                client_id: client_id.to_string(),
+                platform: SpokeType::Hub,
+                display_name: None,
+                metadata: None,
+            },
+        };
+        ws_tx.send(send(auth)).await.map_err(|e| e.to_string())?;
+
+        let result = loop {
+            let text = match ws_rx.next().await {
+                Some(Ok(WsMessage::Text(text))) => text,
+                Some(Ok(WsMessage::Close(_))) | None => break Err("connection closed".to_string()),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break Err(e.to_string()),
+            };
+            let Ok(msg) = serde_json::from_str::<GatewayMessage>(&text) else {
+                continue;
+            };
+            match msg.message {
+                MessageType::AuthResult { success: true, .. } => {
+                    let request = MessageType::UserMessage {
+                        content: content.to_string(),
+                        assistant_id: None,
+                        model: None,
+                    };
+                    if let Err(e) = ws_tx.send(send(request)).await {
+                        break Err(e.to_string());
+                    }
+                }
+                MessageType::AuthResult { message, .. } => {
+                    break Err(format!("authentication rejected: {}", message.unwrap_or_default()))
+                }
+                MessageType::ResponseEnd { full_content, .. } => break Ok(full_content),
+                // react_error 为循环中途的可恢复错误，其余错误（runtime_error、rate_limited 等）结束本次请求
+                MessageType::Error { code, message, .. } if code != "react_error" => {
+                    break Err(format!("{}: {}", code, message))
+                }
+                other => on_event(other),
+            }
+        };
+        let _ = ws_tx.close().await;
+        result.map_err(|e| format!("remote hub {}: {}", self.name, e))
+    }
+}
+
+#[async_trait]
+impl CapabilitySpoke for RemoteHubSpoke {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn capability_type(&self) -> CapabilitySpokeType {
+        CapabilitySpokeType::RemoteHub {
+            name: self.name.clone(),
+        }
+    }
+
+    /// 输入 `{"content": "...", "client_id": "..."}`，返回 `{"content": 远端回复}`
+    async fn execute(&self, input: serde_json::Value) -> Result<serde_json::Value, String> {
+        let content = input
+            .get("content")
+            .and_then(|v| v.as_str())
+            .ok_or("content is required")?;
+        let client_id = input
+            .get("client_id")
+            .and_then(|v| v.as_str())
+            .map(|id| format!("{}{}", FEDERATED_CLIENT_PREFIX, id))
+            .unwrap_or_else(|| format!("{}{}", FEDERATED_CLIENT_PREFIX, uuid::Uuid::new_v4()));
+        let reply = self.forward(&client_id, content, |_| {}).await?;
+        Ok(serde_json::json!({ "content": reply }))
+    }
+}
+
+/// 已配置的远端 Hub（按名称排序，意图命中多个远端时取第一个）
+#[derive(Default)]
+pub struct Federation {
+    remotes: Vec<Arc<RemoteHubSpoke>>,
+}
+
+impl From<&FederationSection> for Federation {
+    fn from(section: &FederationSection) -> Self {
+        let mut names: Vec<&String> = section.remotes.keys().collect();
+        names.sort();
+        let remotes = names
+            .into_iter()
+            .map(|name| Arc::new(RemoteHubSpoke::new(name, &section.remotes[name])))
+            .collect();
+        Self { remotes }
+    }
+}
+
+impl Federation {
+    pub fn is_empty(&self) -> bool {
+        self.remotes.is_empty()
+    }
+
+    pub fn remotes(&self) -> &[Arc<RemoteHubSpoke>] {
+        &self.remotes
+    }
+
+    /// 接收该意图的远端
+    pub fn route(&self, intent: &Intent) -> Option<Arc<RemoteHubSpoke>> {
+        self.remotes.iter().find(|r| r.handles(intent)).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::intent::CodeAction;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_route_by_capability_allowlist() {
+        let remote = |capabilities: &[&str]| RemoteHubSection {
+            url: "ws://127.0.0.1:9".to_string(),
+            token_env: None,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            timeout_secs: 5,
+        };
+        let section = FederationSection {
+            remotes: HashMap::from([
+                ("workstation".to_string(), remote(&["browse", "Code"])),
+                ("gpu".to_string(), remote(&["code"])),
+            ]),
+        };
+        let federation = Federation::from(&section);
+        assert!(!federation.is_empty());
+        let code = Intent::Code {
+            action: CodeAction::Debug,
+        };
+        assert_eq!(
+            federation.route(&code).map(|r| r.name().to_string()).as_deref(),
+            Some("gpu")
+        );
+        let browse = Intent::Browse { url: None };
+        assert_eq!(
+            federation.route(&browse).map(|r| r.name().to_string()).as_deref(),
+            Some("workstation")
+        );
+        assert!(federation.route(&Intent::Chat).is_none());
+        assert!(Federation::from(&FederationSection::default()).is_empty());
+    }
+}
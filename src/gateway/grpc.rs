@@ -0,0 +1,484 @@
+//! gRPC 接入端（grpc feature）：以 proto/hub.proto 定义的强类型接口暴露 Hub，供非 HTTP 服务与移动端后端集成
+//!
+//! - SubmitMessage：一元调用，等待完整回复；
+//! - StreamEvents：双向流，客户端发送消息 / 后台任务，服务端推送该 client_id 的流式事件与任务完成通知
+//!   （只带 client_id、不带 event 的 ClientEvent 仅订阅，可用于只接收通知的后端）；
+//! - ListSessions：列出 Hub 中的会话。
+//!
+//! 与其它 Spoke 一样，消息经 Hub 的 spoke 路由处理，同一 client_id 对应同一个会话。
+//! 启用 [auth] 时每个调用须在 metadata 中携带 `authorization: Bearer <API Key 或 JWT>`（或 `x-api-key`），并具备 chat 作用域；
+//! [users] isolate 时请求中的 client_id 被替换为认证身份对应的用户 ID，ListSessions 也只返回该用户的会话。
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures_util::Stream;
+use tokio::sync::{mpsc, watch, RwLock};
+use tokio_stream::wrappers::{TcpListenerStream, UnboundedReceiverStream};
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status, Streaming};
+
+use super::message::{ClientInfo, GatewayMessage, MessageType, SpokeType};
+use super::session::SessionSummary;
+use super::session_store::SessionStore;
+use super::spoke::{CommunicationSpoke, SpokeAdapter};
+use crate::config::UsersSection;
+use crate::core::{bearer_token, AuthError, Authenticator, Principal, Scope, Tenancy};
+
+/// 由 proto/hub.proto 生成的消息类型与服务端 / 客户端代码
+pub mod proto {
+    tonic::include_proto!("bee.hub.v1");
+}
+
+use proto::hub_server::{Hub as HubService, HubServer};
+
+/// 默认监听地址（bee-gateway 中可由 GATEWAY_GRPC_BIND 覆盖）
+pub const DEFAULT_BIND: &str = "127.0.0.1:50051";
+
+type Outbound = mpsc::UnboundedSender<GatewayMessage>;
+
+/// 各 client_id 的订阅者（StreamEvents 流与等待中的 SubmitMessage），Hub 的回复按 client_id 分发
+#[derive(Default)]
+struct Subscribers(RwLock<HashMap<String, Vec<Outbound>>>);
+
+impl Subscribers {
+    async fn add(&self, client_id: &str, tx: Outbound) {
+        self.0.write().await.entry(client_id.to_string()).or_default().push(tx);
+    }
+
+    async fn remove(&self, client_id: &str, tx: &Outbound) {
+        let mut map = self.0.write().await;
+        if let Some(subs) = map.get_mut(client_id) {
+            subs.retain(|s| !s.same_channel(tx));
+            if subs.is_empty() {
+                map.remove(client_id);
+            }
+        }
+    }
+
+    /// 发送给该客户端的所有订阅者，顺带清理已断开的
+    async fn dispatch(&self, client_id: &str, message: GatewayMessage) {
+        let mut map = self.0.write().await;
+        if let Some(subs) = map.get_mut(client_id) {
+            subs.retain(|tx| tx.send(message.clone()).is_ok());
+            if subs.is_empty() {
+                map.remove(client_id);
+            }
+        }
+    }
+}
+
+/// gRPC 接入端：start 时监听地址并启动 tonic 服务
+pub struct GrpcSpoke {
+    addr: SocketAddr,
+    session_store: Arc<dyn SessionStore>,
+    subscribers: Arc<Subscribers>,
+    shutdown: watch::Sender<bool>,
+    auth: Arc<Authenticator>,
+    tenancy: Arc<Tenancy>,
+}
+
+impl GrpcSpoke {
+    /// `session_store` 用于 ListSessions（传入 `Hub::session_store()`）
+    pub fn new(addr: SocketAddr, session_store: Arc<dyn SessionStore>) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            addr,
+            session_store,
+            subscribers: Arc::new(Subscribers::default()),
+            shutdown,
+            auth: Arc::new(Authenticator::disabled()),
+            tenancy: Arc::new(Tenancy::from(&UsersSection::default())),
+        }
+    }
+
+    /// 使用 Hub 的认证器校验每个调用（传入 `Hub::authenticator()`）
+    pub fn with_auth(mut self, auth: Arc<Authenticator>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// 按 Hub 的多用户策略把调用绑定到认证用户（传入 `Hub::tenancy()`）
+    pub fn with_tenancy(mut self, tenancy: Arc<Tenancy>) -> Self {
+        self.tenancy = tenancy;
+        self
+    }
+}
+
+/// 校验调用 metadata 中的 `authorization: Bearer` 或 `x-api-key`，通过后把认证身份放入请求扩展
+#[derive(Clone)]
+struct AuthInterceptor(Arc<Authenticator>);
+
+impl tonic::service::Interceptor for AuthInterceptor {
+    fn call(&mut self, mut req: Request<()>) -> Result<Request<()>, Status> {
+        let metadata = req.metadata();
+        let token = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(bearer_token)
+            .or_else(|| metadata.get("x-api-key").and_then(|v| v.to_str().ok()));
+        match self.0.authorize(token, Scope::Chat) {
+            Ok(principal) => {
+                req.extensions_mut().insert(principal);
+                Ok(req)
+            }
+            Err(e @ AuthError::Forbidden(_)) => Err(Status::permission_denied(e.to_string())),
+            Err(e) => Err(Status::unauthenticated(e.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl SpokeAdapter for GrpcSpoke {
+    fn spoke_type(&self) -> SpokeType {
+        SpokeType::Grpc
+    }
+
+    async fn start(&self, message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) -> Result<(), String> {
+        let listener = tokio::net::TcpListener::bind(self.addr)
+            .await
+            .map_err(|e| format!("gRPC bind {} failed: {}", self.addr, e))?;
+        let service = HubGrpc {
+            message_tx,
+            session_store: Arc::clone(&self.session_store),
+            subscribers: Arc::clone(&self.subscribers),
+            tenancy: Arc::clone(&self.tenancy),
+        };
+        let interceptor = AuthInterceptor(Arc::clone(&self.auth));
+        let mut shutdown = self.shutdown.subscribe();
+        tokio::spawn(async move {
+            let result = tonic::transport::Server::builder()
+                .add_service(HubServer::with_interceptor(service, interceptor))
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+                    let _ = shutdown.wait_for(|stop| *stop).await;
+                })
+                .await;
+            if let Err(e) = result {
+                tracing::warn!("gRPC server stopped: {}", e);
+            }
+        });
+        tracing::info!("gRPC spoke listening on {}", self.addr);
+        Ok(())
+    }
+
+    /// 没有该 client_id 的订阅者时直接忽略（如其它 Spoke 客户端的任务通知）
+    async fn send(&self, client_id: &str, message: GatewayMessage) -> Result<(), String> {
+        self.subscribers.dispatch(client_id, message).await;
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl CommunicationSpoke for GrpcSpoke {}
+
+/// tonic 服务实现
+struct HubGrpc {
+    message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+    session_store: Arc<dyn SessionStore>,
+    subscribers: Arc<Subscribers>,
+    tenancy: Arc<Tenancy>,
+}
+
+impl HubGrpc {
+    /// 调用绑定的用户：隔离开启且已认证时为认证用户的 ID，否则为 None（使用请求中的 client_id）
+    fn bound_user<T>(&self, request: &Request<T>) -> Option<String> {
+        let user = self.tenancy.user_of(request.extensions().get::<Principal>()?);
+        (!user.is_default()).then(|| user.to_string())
+    }
+}
+
+fn client_info(client_id: &str) -> ClientInfo {
+    ClientInfo {
+        client_id: client_id.to_string(),
+        platform: SpokeType::Grpc,
+        display_name: None,
+        metadata: None,
+    }
+}
+
+/// 请求结束的错误（runtime 在循环失败或卡死时发送）；react_error 为循环中途的可恢复错误
+fn is_terminal_error(code: &str) -> bool {
+    matches!(code, "runtime_error" | "session_stalled")
+}
+
+#[tonic::async_trait]
+impl HubService for HubGrpc {
+    async fn submit_message(
+        &self,
+        request: Request<proto::SubmitMessageRequest>,
+    ) -> Result<Response<proto::SubmitMessageResponse>, Status> {
+        let bound = self.bound_user(&request);
+        let mut req = request.into_inner();
+        if let Some(user) = bound {
+            req.client_id = user;
+        }
+        if req.client_id.trim().is_empty() || req.content.trim().is_empty() {
+            return Err(Status::invalid_argument("client_id and content are required"));
+        }
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        self.subscribers.add(&req.client_id, tx.clone()).await;
+        let result = async {
+            let message = MessageType::UserMessage {
+                content: req.content.clone(),
+                assistant_id: req.assistant_id.clone(),
+                model: req.model.clone(),
+            };
+            self.message_tx
+                .send((client_info(&req.client_id), GatewayMessage::new(None, message)))
+                .map_err(|_| Status::unavailable("hub is not running"))?;
+            let mut reply = proto::SubmitMessageResponse::default();
+            while let Some(msg) = rx.recv().await {
+                if let Some(sid) = msg.session_id {
+                    reply.session_id = sid;
+                }
+                match msg.message {
+                    MessageType::TaskSubmitted { task_id } => reply.task_id = Some(task_id),
+                    MessageType::ResponseEnd {
+                        request_id,
+                        full_content,
+                    } => {
+                        reply.request_id = request_id;
+                        reply.content = full_content;
+                        return Ok(reply);
+                    }
+                    MessageType::Error { code, message, .. } if is_terminal_error(&code) => {
+                        return Err(Status::internal(message));
+                    }
+                    _ => {}
+                }
+            }
+            Err(Status::unavailable("hub closed the request"))
+        }
+        .await;
+        self.subscribers.remove(&req.client_id, &tx).await;
+        result.map(Response::new)
+    }
+
+    type StreamEventsStream = Pin<Box<dyn Stream<Item = Result<proto::HubEvent, Status>> + Send>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<Streaming<proto::ClientEvent>>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let bound = self.bound_user(&request);
+        let mut inbound = request.into_inner();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let message_tx = self.message_tx.clone();
+        let subscribers = Arc::clone(&self.subscribers);
+        tokio::spawn(async move {
+            let mut subscribed: Vec<String> = Vec::new();
+            while let Ok(Some(mut event)) = inbound.message().await {
+                if let Some(user) = &bound {
+                    event.client_id.clone_from(user);
+                }
+                if event.client_id.trim().is_empty() {
+                    let _ = tx.send(GatewayMessage::error("invalid_argument", "client_id is required"));
+                    continue;
+                }
+                if !subscribed.contains(&event.client_id) {
+                    subscribers.add(&event.client_id, tx.clone()).await;
+                    subscribed.push(event.client_id.clone());
+                }
+                let Some(message) = event.event.map(client_message) else {
+                    continue;
+                };
+                if message_tx
+                    .send((client_info(&event.client_id), GatewayMessage::new(None, message)))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        let stream = UnboundedReceiverStream::new(rx).filter_map(|msg| hub_event(msg).map(Ok));
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn list_sessions(
+        &self,
+        request: Request<proto::ListSessionsRequest>,
+    ) -> Result<Response<proto::ListSessionsResponse>, Status> {
+        let bound = self.bound_user(&request);
+        let user_id = bound.or(request.into_inner().user_id);
+        let sessions = self
+            .session_store
+            .list_sessions()
+            .await
+            .into_iter()
+            .filter(|s| user_id.as_ref().is_none_or(|u| &s.user_id == u))
+            .map(session_info)
+            .collect();
+        Ok(Response::new(proto::ListSessionsResponse { sessions }))
+    }
+}
+
+/// 客户端事件转为网关消息
+fn client_message(event: proto::client_event::Event) -> MessageType {
+    match event {
+        proto::client_event::Event::Message(m) => MessageType::UserMessage {
+            content: m.content,
+            assistant_id: m.assistant_id,
+            model: m.model,
+        },
+        proto::client_event::Event::Task(t) => MessageType::SubmitTask {
+            instruction: t.instruction,
+            priority: t.priority,
+        },
+    }
+}
+
+/// 网关消息转为 gRPC 事件；与 gRPC 客户端无关的消息（心跳、认证等）返回 None
+fn hub_event(msg: GatewayMessage) -> Option<proto::HubEvent> {
+    use proto::hub_event::Event;
+
+    let event = match msg.message {
+        MessageType::ResponseStart { request_id } => Event::ResponseStart(proto::ResponseStart { request_id }),
+        MessageType::ResponseChunk { request_id, content } => {
+            Event::ResponseChunk(proto::ResponseChunk { request_id, content })
+        }
+        MessageType::ResponseEnd {
+            request_id,
+            full_content,
+        } => Event::ResponseEnd(proto::ResponseEnd {
+            request_id,
+            full_content,
+        }),
+        MessageType::ToolCall {
+            request_id,
+            tool_name,
+            arguments,
+        } => Event::ToolCall(proto::ToolCall {
+            request_id,
+            tool_name,
+            arguments_json: arguments.to_string(),
+        }),
+        MessageType::ToolResult {
+            request_id,
+            tool_name,
+            result,
+            success,
+        } => Event::ToolResult(proto::ToolResult {
+            request_id,
+            tool_name,
+            result,
+            success,
+        }),
+        MessageType::Thinking { request_id, content } => Event::Thinking(proto::Thinking { request_id, content }),
+        MessageType::Error {
+            request_id,
+            code,
+            message,
+        } => Event::Error(proto::Error {
+            request_id,
+            code,
+            message,
+        }),
+        MessageType::TaskSubmitted { task_id } => Event::TaskSubmitted(proto::TaskSubmitted { task_id }),
+        MessageType::TaskComplete {
+            task_id,
+            user_id,
+            success,
+            result,
+            error,
+        } => Event::TaskComplete(proto::TaskComplete {
+            task_id,
+            user_id,
+            success,
+            result,
+            error,
+        }),
+        MessageType::FileChanged { watch_id, path, change } => Event::FileChanged(proto::FileChanged {
+            watch_id,
+            path,
+            change: change.as_str().to_string(),
+        }),
+        MessageType::Queued { position } => Event::Queued(proto::Queued {
+            position: position as u32,
+        }),
+        _ => return None,
+    };
+    Some(proto::HubEvent {
+        id: msg.id,
+        session_id: msg.session_id,
+        timestamp: msg.timestamp,
+        event: Some(event),
+    })
+}
+
+fn session_info(summary: SessionSummary) -> proto::SessionInfo {
+    proto::SessionInfo {
+        status: serde_json::to_value(summary.status)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        platforms: summary.platforms.iter().map(|p| p.to_string()).collect(),
+        session_id: summary.session_id,
+        user_id: summary.user_id,
+        message_count: summary.message_count as u64,
+        idle_secs: summary.idle_secs,
+        assistant_id: summary.assistant_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::message::SessionStatus;
+
+    #[tokio::test]
+    async fn test_event_mapping_and_dispatch() {
+        let chunk = GatewayMessage::new(
+            Some("session_1".to_string()),
+            MessageType::ResponseChunk {
+                request_id: "r1".to_string(),
+                content: "hi".to_string(),
+            },
+        );
+        let event = hub_event(chunk).unwrap();
+        assert_eq!(event.session_id.as_deref(), Some("session_1"));
+        assert_eq!(
+            event.event,
+            Some(proto::hub_event::Event::ResponseChunk(proto::ResponseChunk {
+                request_id: "r1".to_string(),
+                content: "hi".to_string(),
+            }))
+        );
+        assert!(hub_event(GatewayMessage::pong(1)).is_none());
+
+        let task = client_message(proto::client_event::Event::Task(proto::SubmitTask {
+            instruction: "index docs".to_string(),
+            priority: Some("high".to_string()),
+        }));
+        assert!(matches!(task, MessageType::SubmitTask { ref instruction, .. } if instruction == "index docs"));
+
+        let info = session_info(SessionSummary {
+            session_id: "session_1".to_string(),
+            user_id: "svc".to_string(),
+            status: SessionStatus::WaitingInput,
+            platforms: vec![SpokeType::Grpc, SpokeType::Web],
+            message_count: 4,
+            idle_secs: 2,
+            assistant_id: None,
+        });
+        assert_eq!(info.status, "waiting_input");
+        assert_eq!(info.platforms, vec!["grpc", "web"]);
+
+        let subscribers = Subscribers::default();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        subscribers.add("svc", tx.clone()).await;
+        subscribers.dispatch("svc", GatewayMessage::pong(2)).await;
+        subscribers.dispatch("other", GatewayMessage::pong(3)).await;
+        assert!(matches!(
+            rx.recv().await.unwrap().message,
+            MessageType::Pong { timestamp: 2 }
+        ));
+        subscribers.remove("svc", &tx).await;
+        assert!(subscribers.0.read().await.is_empty());
+    }
+}
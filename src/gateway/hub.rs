@@ -13,14 +13,22 @@ use std::sync::Arc;
 use futures_util::{SinkExt, StreamExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{mpsc, RwLock};
+use tokio_tungstenite::tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
 use tokio_tungstenite::tungstenite::Message as WsMessage;
 
+use super::federation::Federation;
 use super::intent::IntentRecognizer;
+use super::intent_examples::IntentExamples;
 use super::message::{ClientInfo, GatewayMessage, HistoryMessage, MessageType};
 use super::runtime::{AgentRuntime, RuntimeConfig};
+use super::session::{SessionCommand, SessionId};
 use super::session_store::{SessionStore, create_session_store};
 use super::spoke::SpokeAdapter;
-use super::task_queue::{TaskNotification, TaskQueue};
+use super::task_queue::{BackgroundTask, TaskExecutor, TaskNotification, TaskPriority, TaskQueue};
+use crate::core::{bearer_token, AuthError, Authenticator, Principal, RateLimiter, Scope, Tenancy, UserId};
+use crate::core::{FileChange, FileWatchSink, TaskScheduler, WatchRule, WatchStore};
+use crate::integrations::webhook::{WebhookEvent, WebhookSpoke};
 use crate::llm::{create_embedder_from_config, EmbeddingProvider};
 use crate::memory::{UserMemoryConfig, UserMemoryManager};
 
@@ -33,6 +41,40 @@ impl EmbeddingProvider for NoopEmbedder {
     }
 }
 
+/// 网关的文件监听处理：广播 file_changed，task 交给后台任务队列（完成后经 TaskComplete 通知）
+struct GatewayFileWatchSink {
+    connections: Arc<RwLock<HashMap<String, Connection>>>,
+    task_queue: Arc<TaskQueue>,
+}
+
+#[async_trait::async_trait]
+impl FileWatchSink for GatewayFileWatchSink {
+    fn channel(&self) -> &str {
+        "gateway"
+    }
+
+    async fn on_change(&self, rule: &WatchRule, change: &FileChange) {
+        let msg = GatewayMessage::new(
+            None,
+            MessageType::FileChanged {
+                watch_id: change.watch_id.clone(),
+                path: change.path.clone(),
+                change: change.kind,
+            },
+        );
+        if let Ok(json) = serde_json::to_string(&msg) {
+            for conn in self.connections.read().await.values() {
+                let _ = conn.tx.send(json.clone());
+            }
+        }
+        if let Some(task) = &rule.task {
+            let task = BackgroundTask::new("file-watch".to_string(), change.task_prompt(task));
+            let task_id = self.task_queue.submit(task).await;
+            tracing::info!(watch = %rule.id, task = %task_id, "file watch task submitted");
+        }
+    }
+}
+
 /// Hub 配置
 #[derive(Debug, Clone)]
 pub struct HubConfig {
@@ -87,6 +129,12 @@ pub struct Hub {
     notification_rx: Arc<RwLock<Option<mpsc::UnboundedReceiver<TaskNotification>>>>,
     /// 用户记忆管理器
     user_memory: Arc<UserMemoryManager>,
+    /// [auth]：WebSocket 握手 / Auth 消息与 gRPC 共用的认证器
+    auth: Arc<Authenticator>,
+    /// [users]：会话归属与每用户配额
+    tenancy: Arc<Tenancy>,
+    /// [rate_limit]：按「接入端:client_id」的消息速率与 ReAct 循环并发
+    rate_limiter: Arc<RateLimiter>,
 }
 
 impl Hub {
@@ -98,17 +146,8 @@ impl Hub {
             config.session_timeout,
         ).await;
         
-        let runtime = Arc::new(AgentRuntime::new(
-            config.runtime.clone(),
-            Arc::clone(&session_store),
-        ));
-        let intent_recognizer = Arc::new(IntentRecognizer::new(
-            Arc::clone(&runtime.components().llm),
-        ));
-        let (shutdown_tx, _) = tokio::sync::watch::channel(false);
-
         #[cfg(feature = "async-sqlite")]
-        let (task_queue, _pending_rx, notification_rx) = if let Some(ref db_path) = config.runtime.task_db_path {
+        let (task_queue, pending_rx, notification_rx) = if let Some(ref db_path) = config.runtime.task_db_path {
             match TaskQueue::with_persistence(db_path).await {
                 Ok(q) => q,
                 Err(e) => {
@@ -121,13 +160,8 @@ impl Hub {
         };
 
         #[cfg(not(feature = "async-sqlite"))]
-        let (task_queue, _pending_rx, notification_rx) = TaskQueue::new();
-
-        let user_memory_config = UserMemoryConfig {
-            max_entries_per_user: 500,
-            snapshot_dir: config.runtime.user_memory_dir.clone(),
-            vector_enabled: config.runtime.app_config.memory.vector_enabled,
-        };
+        let (task_queue, pending_rx, notification_rx) = TaskQueue::new();
+        let task_queue = Arc::new(task_queue);
 
         let api_key = std::env::var("OPENAI_API_KEY")
             .or_else(|_| std::env::var("DEEPSEEK_API_KEY"))
@@ -138,7 +172,44 @@ impl Hub {
             &config.runtime.app_config.memory.embedding_model,
             api_key.as_deref(),
         ).unwrap_or_else(|| Arc::new(NoopEmbedder));
+
+        let runtime = AgentRuntime::new(config.runtime.clone(), Arc::clone(&session_store))
+            .with_task_queue(Arc::clone(&task_queue));
+        let intent_examples = Arc::new(IntentExamples::from_section(
+            &config.runtime.app_config.intent,
+            &config.runtime.workspace,
+            Arc::clone(&embedder),
+        ));
+        let intent_recognizer = Arc::new(
+            IntentRecognizer::new(Arc::clone(&runtime.components().llm)).with_examples(intent_examples),
+        );
+        let federation = Arc::new(Federation::from(&config.runtime.app_config.federation));
+        let runtime = Arc::new(runtime.with_federation(federation, Arc::clone(&intent_recognizer)));
+        let (shutdown_tx, _) = tokio::sync::watch::channel(false);
+
+        // 后台任务执行器：结果由 runtime 写回所属会话，完成通知经 notification_rx 推送
+        let executor_runtime = Arc::clone(&runtime);
+        tokio::spawn(
+            TaskExecutor::new(Arc::clone(&task_queue), config.runtime.max_concurrent).start(
+                pending_rx,
+                move |task| {
+                    let runtime = Arc::clone(&executor_runtime);
+                    Box::pin(async move { runtime.run_background_task(task).await })
+                },
+            ),
+        );
+
+        let user_memory_config = UserMemoryConfig {
+            max_entries_per_user: 500,
+            snapshot_dir: config.runtime.user_memory_dir.clone(),
+            vector_enabled: config.runtime.app_config.memory.vector_enabled,
+        };
+
         let user_memory = Arc::new(UserMemoryManager::new(user_memory_config, embedder));
+        let auth = Arc::new(Authenticator::from(&config.runtime.app_config.auth));
+        auth.start_key_refresh();
+        let tenancy = Arc::new(Tenancy::from(&config.runtime.app_config.users));
+        let rate_limiter = Arc::new(RateLimiter::from(&config.runtime.app_config.rate_limit));
 
         Self {
             config,
@@ -148,15 +219,41 @@ impl Hub {
             connections: Arc::new(RwLock::new(HashMap::new())),
             spokes: Arc::new(RwLock::new(Vec::new())),
             shutdown: shutdown_tx,
-            task_queue: Arc::new(task_queue),
+            task_queue,
             notification_rx: Arc::new(RwLock::new(Some(notification_rx))),
             user_memory,
+            auth,
+            tenancy,
+            rate_limiter,
         }
     }
 
-    /// 注册 Spoke 适配器
-    pub async fn register_spoke(&self, spoke: Arc<dyn SpokeAdapter>) {
-        self.spokes.write().await.push(spoke);
+    /// 注册并启动 Spoke 适配器：其收到的消息按 client_id 映射到会话交给 runtime 处理，回复经 `spoke.send` 发回
+    pub async fn register_spoke(&self, spoke: Arc<dyn SpokeAdapter>) -> Result<(), String> {
+        let (message_tx, mut message_rx) = mpsc::unbounded_channel::<(ClientInfo, GatewayMessage)>();
+        spoke.start(message_tx).await?;
+        self.spokes.write().await.push(Arc::clone(&spoke));
+
+        let session_store = Arc::clone(&self.session_store);
+        let runtime = Arc::clone(&self.runtime);
+        let task_queue = Arc::clone(&self.task_queue);
+        let tenancy = Arc::clone(&self.tenancy);
+        let rate_limiter = Arc::clone(&self.rate_limiter);
+        tokio::spawn(async move {
+            while let Some((info, message)) = message_rx.recv().await {
+                tokio::spawn(route_spoke_message(
+                    Arc::clone(&spoke),
+                    Arc::clone(&session_store),
+                    Arc::clone(&runtime),
+                    Arc::clone(&task_queue),
+                    Arc::clone(&tenancy),
+                    Arc::clone(&rate_limiter),
+                    info,
+                    message,
+                ));
+            }
+        });
+        Ok(())
     }
 
     /// 启动网关
@@ -178,6 +275,10 @@ impl Hub {
         let session_store = Arc::clone(&self.session_store);
         let runtime = Arc::clone(&self.runtime);
         let heartbeat_interval = self.config.heartbeat_interval;
+        let auth = Arc::clone(&self.auth);
+        let tenancy = Arc::clone(&self.tenancy);
+        let rate_limiter = Arc::clone(&self.rate_limiter);
+        let intent_recognizer = Arc::clone(&self.intent_recognizer);
 
         tokio::spawn(async move {
             let cleanup_interval = tokio::time::Duration::from_secs(60);
@@ -202,6 +303,10 @@ impl Hub {
                                 let connections = Arc::clone(&connections);
                                 let session_store = Arc::clone(&session_store);
                                 let runtime = Arc::clone(&runtime);
+                                let auth = Arc::clone(&auth);
+                                let tenancy = Arc::clone(&tenancy);
+                                let rate_limiter = Arc::clone(&rate_limiter);
+                                let intent_recognizer = Arc::clone(&intent_recognizer);
 
                                 tokio::spawn(async move {
                                     if let Err(e) = handle_connection(
@@ -210,6 +315,10 @@ impl Hub {
                                         connections,
                                         session_store,
                                         runtime,
+                                        auth,
+                                        tenancy,
+                                        rate_limiter,
+                                        intent_recognizer,
                                         heartbeat_interval,
                                     ).await {
                                         tracing::error!("Connection error from {}: {}", addr, e);
@@ -279,6 +388,21 @@ impl Hub {
         self.session_store.active_count().await
     }
 
+    /// 获取会话存储（供 gRPC 等接入端列举会话）
+    pub fn session_store(&self) -> &Arc<dyn SessionStore> {
+        &self.session_store
+    }
+
+    /// 获取认证器（供 gRPC 等接入端校验凭据）
+    pub fn authenticator(&self) -> &Arc<Authenticator> {
+        &self.auth
+    }
+
+    /// 获取多用户策略（供 gRPC 等接入端绑定用户）
+    pub fn tenancy(&self) -> &Arc<Tenancy> {
+        &self.tenancy
+    }
+
     /// 获取任务队列
     pub fn task_queue(&self) -> &Arc<TaskQueue> {
         &self.task_queue
@@ -289,9 +413,13 @@ impl Hub {
         &self.user_memory
     }
 
-    /// 启动任务完成通知处理
+    /// 启动任务完成通知处理：通知写入任务所属用户的发件箱，推送给该用户在线的客户端与接入端（如邮件线程）并标记为已送达，
+    /// 用户不在线时保留到其重新连接；同时发送给订阅了 task_finished 的出站 Webhook
     pub async fn start_notification_handler(&self) {
         let connections = Arc::clone(&self.connections);
+        let spokes = Arc::clone(&self.spokes);
+        let session_store = Arc::clone(&self.session_store);
+        let webhooks = WebhookSpoke::from(&self.config.runtime.app_config.webhooks);
         
         let notification_rx = {
             let mut guard = self.notification_rx.write().await;
@@ -301,6 +429,17 @@ impl Hub {
         if let Some(mut rx) = notification_rx {
             tokio::spawn(async move {
                 while let Some(notification) = rx.recv().await {
+                    webhooks.notify(
+                        WebhookEvent::TaskFinished,
+                        serde_json::json!({
+                            "kind": "background",
+                            "task_id": notification.task_id,
+                            "user_id": notification.user_id,
+                            "success": notification.status == super::task_queue::TaskStatus::Completed,
+                            "result": notification.result,
+                            "error": notification.error,
+                        }),
+                    );
                     let msg = GatewayMessage::new(
                         None,
                         MessageType::TaskComplete {
@@ -312,17 +451,34 @@ impl Hub {
                         },
                     );
 
-                    let connections = connections.read().await;
-                    for conn in connections.values() {
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            let _ = conn.tx.send(json);
-                        }
+                    let user_id = notification.user_id;
+                    let entry = session_store.enqueue_outbox(&user_id, msg).await;
+                    if deliver_to_user(&connections, &spokes, session_store.as_ref(), &user_id, &entry.message).await {
+                        session_store.mark_delivered(&user_id, entry.id()).await;
+                    } else {
+                        tracing::debug!("User {} offline, task notification {} queued", user_id, entry.id());
                     }
                 }
             });
         }
     }
 
+    /// 启动工作区文件监听（watch 工具的规则）：变化广播给所有客户端，带 task 的规则转为后台任务
+    pub fn start_file_watcher(&self) {
+        let runtime = &self.config.runtime;
+        match WatchStore::open(&runtime.workspace) {
+            Ok(store) => {
+                let sink = Arc::new(GatewayFileWatchSink {
+                    connections: Arc::clone(&self.connections),
+                    task_queue: Arc::clone(&self.task_queue),
+                });
+                let poll = std::time::Duration::from_secs(runtime.app_config.tools.watch.poll_secs);
+                TaskScheduler::spawn_file_watcher(Arc::new(store), sink, runtime.workspace.clone(), poll);
+            }
+            Err(e) => tracing::warn!("file watcher disabled: {}", e),
+        }
+    }
+
     /// 为用户添加长期记忆
     pub async fn add_user_memory(&self, user_id: &str, text: &str) {
         self.user_memory.add(user_id, text).await;
@@ -339,15 +495,281 @@ impl Hub {
     }
 }
 
+/// 把通知推送给用户在线的客户端：属于其当前会话（或以其身份认证）的 WebSocket 连接，以及其会话当前连接的平台对应的
+/// Spoke（Spoke 提交的任务以 client_id 为 user_id，由对应 Spoke 回到原线程）；返回是否至少送达一处
+async fn deliver_to_user(
+    connections: &RwLock<HashMap<String, Connection>>,
+    spokes: &RwLock<Vec<Arc<dyn SpokeAdapter>>>,
+    session_store: &dyn SessionStore,
+    user_id: &str,
+    message: &GatewayMessage,
+) -> bool {
+    let session = session_store.get_user_session(user_id).await;
+    let platforms = match &session {
+        Some(sid) => session_store
+            .list_user_sessions(user_id)
+            .await
+            .into_iter()
+            .find(|s| &s.session_id == sid)
+            .map(|s| s.platforms)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let mut delivered = false;
+    if let Ok(json) = serde_json::to_string(message) {
+        for conn in connections.read().await.values() {
+            let owned = session.as_ref() == Some(&conn.session_id) || conn.client_info.client_id == user_id;
+            if owned && conn.tx.send(json.clone()).is_ok() {
+                delivered = true;
+            }
+        }
+    }
+    for spoke in spokes.read().await.iter() {
+        if !platforms.contains(&spoke.spoke_type()) {
+            continue;
+        }
+        match spoke.send(user_id, message.clone()).await {
+            Ok(()) => delivered = true,
+            Err(e) => tracing::warn!("{} spoke task notification failed: {}", spoke.spoke_type(), e),
+        }
+    }
+    delivered
+}
+
+/// 执行会话命令（`/link`、`/switch`），返回命令执行后的当前会话与回复（ResponseEnd，各平台按普通回复展示）
+async fn run_session_command(
+    session_store: &dyn SessionStore,
+    info: &ClientInfo,
+    session_id: &str,
+    command: SessionCommand,
+) -> (SessionId, GatewayMessage) {
+    let user_id = info.client_id.as_str();
+    let (sid, reply) = match command {
+        SessionCommand::IssueLinkCode => {
+            let code = session_store.issue_link_code(user_id).await;
+            let reply = format!("链接码 {code}（10 分钟内有效），在其它平台发送 /link {code} 即可接续本会话。");
+            (session_id.to_string(), reply)
+        }
+        SessionCommand::Link(code) => match session_store.link(user_id, &code, info.clone()).await {
+            Ok(sid) => (sid.clone(), format!("已链接，接续会话 {}。", sid)),
+            Err(e) => (session_id.to_string(), format!("链接失败：{}", e)),
+        },
+        SessionCommand::ListSessions => {
+            let lines: Vec<String> = session_store
+                .list_user_sessions(user_id)
+                .await
+                .iter()
+                .map(|s| {
+                    let current = if s.session_id == session_id { "*" } else { "-" };
+                    format!("{} {}（{} 条消息）", current, s.session_id, s.message_count)
+                })
+                .collect();
+            (
+                session_id.to_string(),
+                format!("可切换的会话（/switch <session_id>）：\n{}", lines.join("\n")),
+            )
+        }
+        SessionCommand::Switch(target) => match session_store.switch(user_id, &target, info.clone()).await {
+            Ok(sid) => (sid.clone(), format!("已切换到会话 {}。", sid)),
+            Err(e) => (session_id.to_string(), format!("切换失败：{}", e)),
+        },
+    };
+    let message = GatewayMessage::new(
+        Some(sid.clone()),
+        MessageType::ResponseEnd {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            full_content: reply,
+        },
+    );
+    (sid, message)
+}
+
+/// 处理 Spoke 收到的一条消息：会话命令直接执行；UserMessage 按「接入端.client_id」计入配额、按 client_id 限流（排队时先发送 queued）后交给 runtime
+/// 并把流式回复转发给 spoke，SubmitTask 直接提交后台任务
+#[allow(clippy::too_many_arguments)]
+async fn route_spoke_message(
+    spoke: Arc<dyn SpokeAdapter>,
+    session_store: Arc<dyn SessionStore>,
+    runtime: Arc<AgentRuntime>,
+    task_queue: Arc<TaskQueue>,
+    tenancy: Arc<Tenancy>,
+    rate_limiter: Arc<RateLimiter>,
+    info: ClientInfo,
+    message: GatewayMessage,
+) {
+    let client_id = info.client_id.clone();
+    let sid = session_store.get_or_create(&client_id, info.clone()).await;
+    // 用户经该接入端重新出现：补发离线期间的通知
+    for entry in session_store.pending_outbox(&client_id).await {
+        match spoke.send(&client_id, entry.message.clone()).await {
+            Ok(()) => {
+                session_store.mark_delivered(&client_id, entry.id()).await;
+            }
+            Err(e) => tracing::warn!("{} spoke outbox delivery failed: {}", spoke.spoke_type(), e),
+        }
+    }
+    if let MessageType::UserMessage { content, .. } = &message.message {
+        if let Some(command) = SessionCommand::parse(content) {
+            let (_, reply) = run_session_command(session_store.as_ref(), &info, &sid, command).await;
+            if let Err(e) = spoke.send(&client_id, reply).await {
+                tracing::warn!("{} spoke send failed: {}", spoke.spoke_type(), e);
+            }
+            return;
+        }
+    }
+    match message.message {
+        MessageType::UserMessage {
+            content,
+            assistant_id,
+            model,
+        } => {
+            let spoke_name = spoke.spoke_type().to_string();
+            let _run = match tenancy.acquire(&UserId::scoped(&spoke_name, &client_id)) {
+                Ok(guard) => guard,
+                Err(e) => {
+                    let error = GatewayMessage::error("quota_exceeded", &e.to_string());
+                    if let Err(e) = spoke.send(&client_id, error).await {
+                        tracing::warn!("{} spoke send failed: {}", spoke.spoke_type(), e);
+                    }
+                    return;
+                }
+            };
+            let admission = match rate_limiter
+                .check(&spoke_name, &client_id)
+                .and_then(|()| rate_limiter.admit(&spoke_name, &client_id))
+            {
+                Ok(admission) => admission,
+                Err(e) => {
+                    let error = GatewayMessage::error("rate_limited", &e.to_string());
+                    if let Err(e) = spoke.send(&client_id, error).await {
+                        tracing::warn!("{} spoke send failed: {}", spoke.spoke_type(), e);
+                    }
+                    return;
+                }
+            };
+            if let Some(position) = admission.position() {
+                let queued = GatewayMessage::new(Some(sid.clone()), MessageType::Queued { position });
+                if let Err(e) = spoke.send(&client_id, queued).await {
+                    tracing::warn!("{} spoke send failed: {}", spoke.spoke_type(), e);
+                }
+            }
+            let _loop = admission.start().await;
+            let (response_tx, mut response_rx) = mpsc::unbounded_channel();
+            let forward_spoke = Arc::clone(&spoke);
+            let forward_client = client_id.clone();
+            tokio::spawn(async move {
+                while let Some(msg) = response_rx.recv().await {
+                    if let Err(e) = forward_spoke.send(&forward_client, msg).await {
+                        tracing::warn!("{} spoke send failed: {}", forward_spoke.spoke_type(), e);
+                    }
+                }
+            });
+            let _ = runtime
+                .process_message(&sid, &content, assistant_id.as_deref(), model.as_deref(), response_tx)
+                .await;
+        }
+        MessageType::SubmitTask { instruction, priority } => {
+            let priority = match priority.as_deref() {
+                Some("low") => TaskPriority::Low,
+                Some("high") => TaskPriority::High,
+                Some("urgent") => TaskPriority::Urgent,
+                _ => TaskPriority::Normal,
+            };
+            let task = BackgroundTask::new(client_id.clone(), instruction)
+                .with_session(sid.clone())
+                .with_priority(priority);
+            let task_id = task_queue.submit(task).await;
+            let reply = GatewayMessage::new(Some(sid), MessageType::TaskSubmitted { task_id });
+            if let Err(e) = spoke.send(&client_id, reply).await {
+                tracing::warn!("{} spoke send failed: {}", spoke.spoke_type(), e);
+            }
+        }
+        MessageType::MarkRead { message_ids } => {
+            for id in &message_ids {
+                session_store.mark_read(&client_id, id).await;
+            }
+        }
+        MessageType::GetDeliveries { limit } => {
+            let receipts = session_store
+                .recent_outbox(&client_id, limit.unwrap_or(20))
+                .await
+                .iter()
+                .map(|e| e.receipt())
+                .collect();
+            let reply = GatewayMessage::new(Some(sid), MessageType::Deliveries { receipts });
+            if let Err(e) = spoke.send(&client_id, reply).await {
+                tracing::warn!("{} spoke send failed: {}", spoke.spoke_type(), e);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 握手阶段的认证：携带有效凭据时记下认证身份，未携带时放行（等待 Auth 消息），无效则拒绝升级
+struct HandshakeAuth<'a> {
+    auth: &'a Authenticator,
+    principal: &'a mut Option<Principal>,
+}
+
+impl Callback for HandshakeAuth<'_> {
+    fn on_request(self, req: &Request, resp: Response) -> Result<Response, ErrorResponse> {
+        match self.auth.authorize(handshake_token(req).as_deref(), Scope::Chat) {
+            Ok(principal) => {
+                *self.principal = Some(principal);
+                Ok(resp)
+            }
+            Err(AuthError::Missing) => Ok(resp),
+            Err(e) => {
+                let mut rejection = ErrorResponse::new(Some(e.to_string()));
+                *rejection.status_mut() = match e {
+                    AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+                    _ => StatusCode::UNAUTHORIZED,
+                };
+                Err(rejection)
+            }
+        }
+    }
+}
+
+/// WebSocket 握手请求中的凭据：Authorization: Bearer 或 URL 查询参数 token（浏览器无法设置握手头）
+fn handshake_token(req: &Request) -> Option<String> {
+    if let Some(token) = req
+        .headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(bearer_token)
+    {
+        return Some(token.to_string());
+    }
+    req.uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="))
+        .map(|v| v.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_connection(
     stream: TcpStream,
     addr: SocketAddr,
     connections: Arc<RwLock<HashMap<String, Connection>>>,
     session_store: Arc<dyn SessionStore>,
     runtime: Arc<AgentRuntime>,
+    auth: Arc<Authenticator>,
+    tenancy: Arc<Tenancy>,
+    rate_limiter: Arc<RateLimiter>,
+    intent_recognizer: Arc<IntentRecognizer>,
     _heartbeat_interval: u64,
 ) -> Result<(), String> {
-    let ws_stream = tokio_tungstenite::accept_async(stream)
+    // 握手携带凭据（Authorization: Bearer 或 ?token=）时立即校验，无效则 401 / 403 拒绝升级；
+    // 未携带时须在 Auth 消息的 token 中提供
+    let mut principal: Option<Principal> = None;
+    let handshake = HandshakeAuth {
+        auth: &auth,
+        principal: &mut principal,
+    };
+    let ws_stream = tokio_tungstenite::accept_hdr_async(stream, handshake)
         .await
         .map_err(|e| format!("WebSocket handshake failed: {}", e))?;
 
@@ -357,6 +779,8 @@ async fn handle_connection(
     let client_id = format!("ws_{}_{}", addr, uuid::Uuid::new_v4());
     let mut session_id: Option<String> = None;
     let mut client_info: Option<ClientInfo> = None;
+    // 认证得到的用户，配额按它计数
+    let mut user_id = UserId::default();
 
     tracing::info!("New WebSocket connection from {}", addr);
 
@@ -389,13 +813,38 @@ async fn handle_connection(
                 };
 
                 match gateway_msg.message {
-                    MessageType::Auth { token: _, client_info: info } => {
+                    MessageType::Auth { token, client_info: mut info } => {
+                        let user = match &principal {
+                            Some(p) => tenancy.user_of(p),
+                            None => match auth.authorize(token.as_deref(), Scope::Chat) {
+                                Ok(p) => tenancy.user_of(principal.insert(p)),
+                                Err(e) => {
+                                    tracing::warn!("WebSocket auth rejected from {}: {}", addr, e);
+                                    let response = GatewayMessage::new(
+                                        None,
+                                        MessageType::AuthResult {
+                                            success: false,
+                                            session_id: None,
+                                            message: Some(e.to_string()),
+                                        },
+                                    );
+                                    let _ = tx.send(serde_json::to_string(&response).unwrap_or_default());
+                                    continue;
+                                }
+                            },
+                        };
+                        // [users] isolate 时会话归属认证身份，客户端自报的 client_id 不能进入他人的会话
+                        if tenancy.isolating() {
+                            info.client_id = user.to_string();
+                        }
+                        user_id = user;
                         let sid = session_store
                             .get_or_create(&info.client_id, info.clone())
                             .await;
 
                         session_id = Some(sid.clone());
                         client_info = Some(info.clone());
+                        let owner = info.client_id.clone();
 
                         connections.write().await.insert(
                             client_id.clone(),
@@ -415,6 +864,13 @@ async fn handle_connection(
                             },
                         );
                         let _ = tx.send(serde_json::to_string(&response).unwrap_or_default());
+
+                        // 补发离线期间的通知
+                        for entry in session_store.pending_outbox(&owner).await {
+                            if tx.send(serde_json::to_string(&entry.message).unwrap_or_default()).is_ok() {
+                                session_store.mark_delivered(&owner, entry.id()).await;
+                            }
+                        }
                     }
 
                     MessageType::UserMessage {
@@ -422,14 +878,47 @@ async fn handle_connection(
                         assistant_id,
                         model,
                     } => {
-                        let sid = match &session_id {
-                            Some(s) => s.clone(),
-                            None => {
+                        let (sid, info) = match (&session_id, &client_info) {
+                            (Some(s), Some(info)) => (s.clone(), info),
+                            _ => {
                                 let error = GatewayMessage::error("not_authenticated", "Please authenticate first");
                                 let _ = tx.send(serde_json::to_string(&error).unwrap_or_default());
                                 continue;
                             }
                         };
+                        if let Some(command) = SessionCommand::parse(&content) {
+                            let (sid, reply) = run_session_command(session_store.as_ref(), info, &sid, command).await;
+                            if let Some(conn) = connections.write().await.get_mut(&client_id) {
+                                conn.session_id.clone_from(&sid);
+                            }
+                            session_id = Some(sid);
+                            let _ = tx.send(serde_json::to_string(&reply).unwrap_or_default());
+                            continue;
+                        }
+                        let run = match tenancy.acquire(&user_id) {
+                            Ok(guard) => guard,
+                            Err(e) => {
+                                let error = GatewayMessage::error("quota_exceeded", &e.to_string());
+                                let _ = tx.send(serde_json::to_string(&error).unwrap_or_default());
+                                continue;
+                            }
+                        };
+                        let spoke_name = info.platform.to_string();
+                        let admission = match rate_limiter
+                            .check(&spoke_name, &info.client_id)
+                            .and_then(|()| rate_limiter.admit(&spoke_name, &info.client_id))
+                        {
+                            Ok(admission) => admission,
+                            Err(e) => {
+                                let error = GatewayMessage::error("rate_limited", &e.to_string());
+                                let _ = tx.send(serde_json::to_string(&error).unwrap_or_default());
+                                continue;
+                            }
+                        };
+                        if let Some(position) = admission.position() {
+                            let queued = GatewayMessage::new(Some(sid.clone()), MessageType::Queued { position });
+                            let _ = tx.send(serde_json::to_string(&queued).unwrap_or_default());
+                        }
 
                         let (response_tx, mut response_rx) = mpsc::unbounded_channel();
                         let tx_for_response = tx.clone();
@@ -445,6 +934,8 @@ async fn handle_connection(
 
                         let runtime_clone = Arc::clone(&runtime);
                         tokio::spawn(async move {
+                            let _run = run;
+                            let _loop = admission.start().await;
                             let _ = runtime_clone
                                 .process_message(
                                     &sid,
@@ -488,6 +979,43 @@ async fn handle_connection(
                         let _ = tx.send(serde_json::to_string(&pong).unwrap_or_default());
                     }
 
+                    MessageType::MarkRead { message_ids } => {
+                        if let Some(info) = &client_info {
+                            for id in &message_ids {
+                                session_store.mark_read(&info.client_id, id).await;
+                            }
+                        }
+                    }
+
+                    MessageType::GetDeliveries { limit } => {
+                        if let Some(info) = &client_info {
+                            let receipts = session_store
+                                .recent_outbox(&info.client_id, limit.unwrap_or(20))
+                                .await
+                                .iter()
+                                .map(|e| e.receipt())
+                                .collect();
+                            let response = GatewayMessage::new(session_id.clone(), MessageType::Deliveries { receipts });
+                            let _ = tx.send(serde_json::to_string(&response).unwrap_or_default());
+                        }
+                    }
+
+                    MessageType::AddIntentExample { intent, content } => {
+                        let response = if !principal.as_ref().is_some_and(|p| p.allows(Scope::Admin)) {
+                            GatewayMessage::error("forbidden", "admin scope required")
+                        } else {
+                            match intent_recognizer.examples().map(|e| e.add(&intent, &content)) {
+                                Some(Ok(count)) => GatewayMessage::new(
+                                    session_id.clone(),
+                                    MessageType::IntentExampleAdded { intent, count },
+                                ),
+                                Some(Err(e)) => GatewayMessage::error("invalid_intent_example", &e),
+                                None => GatewayMessage::error("intent_examples_disabled", "intent examples are not configured"),
+                            }
+                        };
+                        let _ = tx.send(serde_json::to_string(&response).unwrap_or_default());
+                    }
+
                     _ => {}
                 }
             }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
+use super::intent_examples::IntentExamples;
 use crate::llm::LlmClient;
 use crate::memory::Message;
 
@@ -52,6 +53,96 @@ pub enum Intent {
     Unclear,
 }
 
+impl Intent {
+    /// 意图类别名（与序列化的 snake_case 标签一致），用于 [federation] 的 capabilities 匹配
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Intent::Chat => "chat",
+            Intent::Code { .. } => "code",
+            Intent::Search { .. } => "search",
+            Intent::FileOperation { .. } => "file_operation",
+            Intent::Shell { .. } => "shell",
+            Intent::UseSkill { .. } => "use_skill",
+            Intent::Memory { .. } => "memory",
+            Intent::Task { .. } => "task",
+            Intent::Browse { .. } => "browse",
+            Intent::Unclear => "unclear",
+        }
+    }
+
+    /// 由分类标签（见 [`INTENT_LABELS`]）构造意图，user_input 用于补全查询词与 URL；未知标签为 None
+    pub fn from_label(label: &str, user_input: &str) -> Option<Intent> {
+        Some(match label {
+            "chat" => Intent::Chat,
+            "code_write" => Intent::Code {
+                action: CodeAction::Write,
+            },
+            "code_edit" => Intent::Code {
+                action: CodeAction::Edit,
+            },
+            "code_review" => Intent::Code {
+                action: CodeAction::Review,
+            },
+            "code_debug" => Intent::Code {
+                action: CodeAction::Debug,
+            },
+            "search" => Intent::Search {
+                query: user_input.to_string(),
+            },
+            "file_read" => Intent::FileOperation {
+                action: FileAction::Read,
+                path: None,
+            },
+            "file_write" => Intent::FileOperation {
+                action: FileAction::Write,
+                path: None,
+            },
+            "file_list" => Intent::FileOperation {
+                action: FileAction::List,
+                path: None,
+            },
+            "shell" => Intent::Shell { command: None },
+            "memory_recall" => Intent::Memory {
+                action: MemoryAction::Recall,
+            },
+            "memory_summarize" => Intent::Memory {
+                action: MemoryAction::Summarize,
+            },
+            "task_create" => Intent::Task {
+                action: TaskAction::Create,
+            },
+            "task_list" => Intent::Task {
+                action: TaskAction::List,
+            },
+            "browse" => Intent::Browse {
+                url: extract_url(user_input),
+            },
+            "unclear" => Intent::Unclear,
+            _ => return None,
+        })
+    }
+}
+
+/// LLM 分类与示例句共用的意图标签
+pub const INTENT_LABELS: &[&str] = &[
+    "chat",
+    "code_write",
+    "code_edit",
+    "code_review",
+    "code_debug",
+    "search",
+    "file_read",
+    "file_write",
+    "file_list",
+    "shell",
+    "memory_recall",
+    "memory_summarize",
+    "task_create",
+    "task_list",
+    "browse",
+    "unclear",
+];
+
 /// 代码操作类型
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -99,6 +190,8 @@ pub struct IntentRecognizer {
     llm: Arc<dyn LlmClient>,
     /// 启用快速规则匹配（不调用 LLM）
     enable_fast_match: bool,
+    /// 示例句向量分类（规则未命中时先于 LLM 使用）
+    examples: Option<Arc<IntentExamples>>,
 }
 
 impl IntentRecognizer {
@@ -106,10 +199,22 @@ impl IntentRecognizer {
         Self {
             llm,
             enable_fast_match: true,
+            examples: None,
         }
     }
 
-    /// 识别用户意图
+    /// 挂载示例句分类器
+    pub fn with_examples(mut self, examples: Arc<IntentExamples>) -> Self {
+        self.examples = Some(examples);
+        self
+    }
+
+    /// 示例句分类器（用于追加误判的消息）
+    pub fn examples(&self) -> Option<&Arc<IntentExamples>> {
+        self.examples.as_ref()
+    }
+
+    /// 识别用户意图：规则 → 示例句相似度 → LLM
     pub async fn recognize(&self, user_input: &str) -> Intent {
         if self.enable_fast_match {
             if let Some(intent) = self.fast_match(user_input) {
@@ -117,6 +222,10 @@ impl IntentRecognizer {
             }
         }
 
+        if let Some(intent) = self.examples.as_ref().and_then(|e| e.recognize(user_input)) {
+            return intent;
+        }
+
         self.llm_recognize(user_input).await.unwrap_or(Intent::Chat)
     }
 
@@ -300,54 +409,7 @@ Output format: just the intent type, nothing else."#;
 
         let intent_str = response.trim().to_lowercase();
 
-        Ok(match intent_str.as_str() {
-            "chat" => Intent::Chat,
-            "code_write" => Intent::Code {
-                action: CodeAction::Write,
-            },
-            "code_edit" => Intent::Code {
-                action: CodeAction::Edit,
-            },
-            "code_review" => Intent::Code {
-                action: CodeAction::Review,
-            },
-            "code_debug" => Intent::Code {
-                action: CodeAction::Debug,
-            },
-            "search" => Intent::Search {
-                query: user_input.to_string(),
-            },
-            "file_read" => Intent::FileOperation {
-                action: FileAction::Read,
-                path: None,
-            },
-            "file_write" => Intent::FileOperation {
-                action: FileAction::Write,
-                path: None,
-            },
-            "file_list" => Intent::FileOperation {
-                action: FileAction::List,
-                path: None,
-            },
-            "shell" => Intent::Shell { command: None },
-            "memory_recall" => Intent::Memory {
-                action: MemoryAction::Recall,
-            },
-            "memory_summarize" => Intent::Memory {
-                action: MemoryAction::Summarize,
-            },
-            "task_create" => Intent::Task {
-                action: TaskAction::Create,
-            },
-            "task_list" => Intent::Task {
-                action: TaskAction::List,
-            },
-            "browse" => Intent::Browse {
-                url: extract_url(user_input),
-            },
-            "unclear" => Intent::Unclear,
-            _ => Intent::Chat,
-        })
+        Ok(Intent::from_label(&intent_str, user_input).unwrap_or(Intent::Chat))
     }
 
     /// 根据意图推荐使用的工具
@@ -417,6 +479,7 @@ mod tests {
         let recognizer = IntentRecognizer {
             llm: Arc::new(crate::llm::MockLlmClient),
             enable_fast_match: true,
+            examples: None,
         };
 
         let intent = recognizer.fast_match("搜索 Rust 异步编程");
@@ -428,6 +491,7 @@ mod tests {
         let recognizer = IntentRecognizer {
             llm: Arc::new(crate::llm::MockLlmClient),
             enable_fast_match: true,
+            examples: None,
         };
 
         let intent = recognizer.fast_match("打开 https://example.com");
@@ -439,6 +503,7 @@ mod tests {
         let recognizer = IntentRecognizer {
             llm: Arc::new(crate::llm::MockLlmClient),
             enable_fast_match: true,
+            examples: None,
         };
 
         let intent = recognizer.fast_match("运行 cargo test");
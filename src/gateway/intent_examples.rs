@@ -0,0 +1,218 @@
+//! 意图示例句：按向量相似度做意图分类
+//!
+//! 示例句按意图标签存放在 TOML 文件（[intent] examples_path）中，可手工编辑；分类时将输入与全部示例句的嵌入向量比较，
+//! 取最相近示例的标签，相似度低于 min_similarity 时不作判断（由 [`super::IntentRecognizer`] 回退 LLM）。
+//! 被误判的消息可通过 [`IntentExamples::add`] 追加为示例，立即生效并写回文件。
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::intent::{Intent, INTENT_LABELS};
+use crate::config::IntentSection;
+use crate::llm::EmbeddingProvider;
+use crate::memory::long_term::cosine_similarity;
+
+/// 示例句文件内容
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExamplesFile {
+    #[serde(default)]
+    examples: BTreeMap<String, Vec<String>>,
+}
+
+/// 可训练的意图示例集
+pub struct IntentExamples {
+    path: PathBuf,
+    embedder: Arc<dyn EmbeddingProvider>,
+    min_similarity: f32,
+    examples: RwLock<BTreeMap<String, Vec<String>>>,
+    /// 示例句 → 嵌入向量（首次分类时计算）
+    vectors: RwLock<HashMap<String, Vec<f32>>>,
+}
+
+impl IntentExamples {
+    /// 从示例句文件加载；文件不存在时为空集，未知标签被忽略
+    pub fn load(path: impl Into<PathBuf>, embedder: Arc<dyn EmbeddingProvider>, min_similarity: f32) -> Self {
+        let path = path.into();
+        let mut examples = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str::<ExamplesFile>(&text)
+                .map(|f| f.examples)
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to parse intent examples {}: {}", path.display(), e);
+                    BTreeMap::new()
+                }),
+            Err(_) => BTreeMap::new(),
+        };
+        examples.retain(|label, _| {
+            let known = INTENT_LABELS.contains(&label.as_str());
+            if !known {
+                tracing::warn!("Ignoring intent examples for unknown label '{}'", label);
+            }
+            known
+        });
+        Self {
+            path,
+            embedder,
+            min_similarity,
+            examples: RwLock::new(examples),
+            vectors: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 按 [intent] 段加载，示例句路径相对 workspace
+    pub fn from_section(section: &IntentSection, workspace: &Path, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        Self::load(workspace.join(&section.examples_path), embedder, section.min_similarity)
+    }
+
+    /// 示例句总数
+    pub fn len(&self) -> usize {
+        self.examples.read().unwrap().values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 最相近示例的标签与相似度；示例为空、嵌入失败或低于阈值时为 None
+    pub fn classify(&self, input: &str) -> Option<(String, f32)> {
+        if self.is_empty() {
+            return None;
+        }
+        let query = match self.embedder.embed_sync(input) {
+            Ok(v) if !v.is_empty() => v,
+            Ok(_) => return None,
+            Err(e) => {
+                tracing::debug!("intent embedding skipped: {}", e);
+                return None;
+            }
+        };
+        let examples = self.examples.read().unwrap().clone();
+        self.embed_missing(&examples);
+
+        let vectors = self.vectors.read().unwrap();
+        examples
+            .iter()
+            .flat_map(|(label, texts)| texts.iter().map(move |text| (label, text)))
+            .filter_map(|(label, text)| vectors.get(text).map(|v| (label, cosine_similarity(&query, v))))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, score)| *score >= self.min_similarity)
+            .map(|(label, score)| (label.clone(), score))
+    }
+
+    /// 识别为意图（见 [`Self::classify`]）
+    pub fn recognize(&self, input: &str) -> Option<Intent> {
+        let (label, score) = self.classify(input)?;
+        tracing::debug!(label = %label, score, "intent matched by example");
+        Intent::from_label(&label, input)
+    }
+
+    /// 把一条消息追加为某意图的示例并写回文件，返回该意图的示例数；重复的示例不再追加
+    pub fn add(&self, label: &str, text: &str) -> Result<usize, String> {
+        let label = label.trim().to_ascii_lowercase();
+        if !INTENT_LABELS.contains(&label.as_str()) {
+            return Err(format!(
+                "unknown intent '{}', expected one of: {}",
+                label,
+                INTENT_LABELS.join(", ")
+            ));
+        }
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("example text is empty".to_string());
+        }
+
+        let mut examples = self.examples.write().unwrap();
+        let texts = examples.entry(label).or_default();
+        if !texts.iter().any(|t| t == text) {
+            texts.push(text.to_string());
+        }
+        let count = texts.len();
+        self.save(&examples)?;
+        Ok(count)
+    }
+
+    /// 为尚未缓存的示例句计算向量（嵌入失败的示例本次跳过）
+    fn embed_missing(&self, examples: &BTreeMap<String, Vec<String>>) {
+        let missing: Vec<&String> = {
+            let vectors = self.vectors.read().unwrap();
+            examples
+                .values()
+                .flatten()
+                .filter(|text| !vectors.contains_key(*text))
+                .collect()
+        };
+        for text in missing {
+            match self.embedder.embed_sync(text) {
+                Ok(v) if !v.is_empty() => {
+                    self.vectors.write().unwrap().insert(text.clone(), v);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("intent example embedding failed: {}", e),
+            }
+        }
+    }
+
+    fn save(&self, examples: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        let file = ExamplesFile {
+            examples: examples.clone(),
+        };
+        let text = toml::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&self.path, text).map_err(|e| format!("write {}: {}", self.path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::intent::CodeAction;
+
+    /// 按关键词出现与否生成向量，便于构造可预期的相似度
+    struct KeywordEmbedder;
+
+    impl EmbeddingProvider for KeywordEmbedder {
+        fn embed_sync(&self, text: &str) -> Result<Vec<f32>, String> {
+            let keywords = ["报错", "编译", "天气", "打开", "仓库"];
+            Ok(keywords
+                .iter()
+                .map(|k| if text.contains(k) { 1.0 } else { 0.0 })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_classify_and_add_examples() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("intent_examples.toml");
+        std::fs::write(
+            &path,
+            "[examples]\ncode_debug = [\"编译报错了\"]\nsearch = [\"查天气\"]\nbogus = [\"x\"]\n",
+        )
+        .unwrap();
+
+        let examples = IntentExamples::load(&path, Arc::new(KeywordEmbedder), 0.8);
+        assert_eq!(examples.len(), 2);
+        assert_eq!(
+            examples.recognize("又编译报错了"),
+            Some(Intent::Code {
+                action: CodeAction::Debug
+            })
+        );
+        assert!(examples.classify("打开这个仓库").is_none());
+
+        assert_eq!(examples.add("browse", "打开这个仓库"), Ok(1));
+        assert_eq!(examples.add("browse", "打开这个仓库"), Ok(1));
+        assert!(examples.add("nonsense", "打开").is_err());
+        assert!(matches!(
+            examples.recognize("打开那个仓库"),
+            Some(Intent::Browse { .. })
+        ));
+
+        let reloaded = IntentExamples::load(&path, Arc::new(KeywordEmbedder), 0.8);
+        assert_eq!(reloaded.len(), 3);
+    }
+}
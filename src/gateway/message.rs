@@ -4,6 +4,9 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::outbox::DeliveryReceipt;
+use crate::core::FileChangeKind;
+
 /// 客户端信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientInfo {
@@ -29,8 +32,18 @@ pub enum SpokeType {
     WhatsApp,
     /// 飞书
     Lark,
+    /// Discord
+    Discord,
+    /// 邮件
+    Email,
+    /// gRPC 客户端
+    Grpc,
     /// HTTP API
     Api,
+    /// 消息队列（NATS / Redis Streams）
+    Queue,
+    /// 联邦的其它 Hub
+    Hub,
     /// 其他
     Other,
 }
@@ -42,7 +55,12 @@ impl std::fmt::Display for SpokeType {
             SpokeType::Tui => write!(f, "tui"),
             SpokeType::WhatsApp => write!(f, "whatsapp"),
             SpokeType::Lark => write!(f, "lark"),
+            SpokeType::Discord => write!(f, "discord"),
+            SpokeType::Email => write!(f, "email"),
+            SpokeType::Grpc => write!(f, "grpc"),
             SpokeType::Api => write!(f, "api"),
+            SpokeType::Queue => write!(f, "queue"),
+            SpokeType::Hub => write!(f, "hub"),
             SpokeType::Other => write!(f, "other"),
         }
     }
@@ -183,6 +201,45 @@ pub enum MessageType {
         result: Option<String>,
         error: Option<String>,
     },
+
+    /// 工作区文件变化（watch 规则匹配）
+    FileChanged {
+        watch_id: String,
+        path: String,
+        change: FileChangeKind,
+    },
+
+    /// 请求在限流队列中等待 ReAct 循环名额（[rate_limit]），position 从 1 开始
+    Queued {
+        position: usize,
+    },
+
+    /// 把被误判的消息追加为某意图的示例句（需 admin 作用域），intent 为分类标签（如 code_debug）
+    AddIntentExample {
+        intent: String,
+        content: String,
+    },
+
+    /// 示例句已追加，count 为该意图的示例数
+    IntentExampleAdded {
+        intent: String,
+        count: usize,
+    },
+
+    /// 已读回执：message_ids 为已展示给用户的通知（GatewayMessage.id）
+    MarkRead {
+        message_ids: Vec<String>,
+    },
+
+    /// 查询最近通知的投递状态
+    GetDeliveries {
+        limit: Option<usize>,
+    },
+
+    /// 投递状态响应
+    Deliveries {
+        receipts: Vec<DeliveryReceipt>,
+    },
 }
 
 /// 会话状态
@@ -16,13 +16,14 @@
 //! - Telegram、Slack、WhatsApp、Discord
 //! - 终端命令行（TUI）
 //! - Web 浏览器
-//! - HTTP API
+//! - HTTP API、gRPC（grpc feature，proto/hub.proto）
 //!
 //! ### 2. 能力端点（Capability Spokes）
 //! - Skills 技能（知识增强、模板、脚本）
 //! - 本地工具（文件操作、Shell、代码编辑）
 //! - API 插件（搜索、浏览器、外部服务）
 //! - 自动化脚本（Python/Shell）
+//! - 远端 Hub（[federation]：按意图把浏览器、代码等重任务转交更强的服务器）
 //!
 //! ## 架构优势
 //!
@@ -31,9 +32,14 @@
 //! - 后台持续运行：支持异步任务和长时间处理
 //! - 统一的会话管理和消息路由
 
+mod federation;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 mod hub;
 mod intent;
+mod intent_examples;
 mod message;
+mod outbox;
 #[cfg(feature = "async-sqlite")]
 mod persistent_session;
 mod runtime;
@@ -42,13 +48,16 @@ mod session_store;
 mod spoke;
 mod task_queue;
 
+pub use federation::{Federation, RemoteHubSpoke, FEDERATED_CLIENT_PREFIX};
 pub use hub::{Hub, HubConfig};
-pub use intent::{Intent, IntentRecognizer};
+pub use intent::{Intent, IntentRecognizer, INTENT_LABELS};
+pub use intent_examples::IntentExamples;
 pub use message::{GatewayMessage, MessageType, ClientInfo, SpokeType};
+pub use outbox::{DeliveryReceipt, DeliveryStatus, OutboxEntry};
 #[cfg(feature = "async-sqlite")]
 pub use persistent_session::PersistentSessionManager;
 pub use runtime::{AgentRuntime, RuntimeConfig};
-pub use session::{Session, SessionManager, SessionId};
+pub use session::{Session, SessionCommand, SessionLinkError, SessionManager, SessionId, SessionSummary};
 pub use session_store::{SessionStore, MemorySessionStore, create_session_store};
 #[cfg(feature = "async-sqlite")]
 pub use session_store::PersistentSessionStore;
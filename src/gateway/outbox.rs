@@ -0,0 +1,208 @@
+//! 离线投递：按用户暂存后台任务通知
+//!
+//! 通知先写入用户的发件箱（pending），送达任一在线客户端后标记为 delivered；用户不在线时保留，
+//! 待其重新连接（WebSocket 认证或经接入端再次发消息）时补发。客户端以 mark_read 回执将其标记为 read。
+//! 发件箱以规范用户为键（已 /link 的平台身份共用），每个用户最多保留 [`MAX_OUTBOX_PER_USER`] 条。
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use super::message::GatewayMessage;
+
+/// 每个用户保留的通知条数，超出时丢弃最早的
+pub const MAX_OUTBOX_PER_USER: usize = 200;
+
+/// 投递状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// 等待客户端上线
+    Pending,
+    /// 已送达客户端
+    Delivered,
+    /// 客户端已读
+    Read,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Read => "read",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "read" => Some(DeliveryStatus::Read),
+            _ => None,
+        }
+    }
+}
+
+/// 发件箱中的一条通知（id 即通知消息的 GatewayMessage.id）
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub user_id: String,
+    pub message: GatewayMessage,
+    pub status: DeliveryStatus,
+    /// 送达与已读时间（毫秒）
+    pub delivered_at: Option<u64>,
+    pub read_at: Option<u64>,
+}
+
+impl OutboxEntry {
+    pub fn id(&self) -> &str {
+        &self.message.id
+    }
+
+    pub fn receipt(&self) -> DeliveryReceipt {
+        DeliveryReceipt {
+            message_id: self.message.id.clone(),
+            status: self.status,
+            created_at: self.message.timestamp,
+            delivered_at: self.delivered_at,
+            read_at: self.read_at,
+        }
+    }
+}
+
+/// 投递回执（get_deliveries 的返回项）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryReceipt {
+    pub message_id: String,
+    pub status: DeliveryStatus,
+    pub created_at: u64,
+    pub delivered_at: Option<u64>,
+    pub read_at: Option<u64>,
+}
+
+/// 按用户分组的发件箱（按入队顺序）
+#[derive(Default)]
+pub struct Outbox {
+    entries: HashMap<String, VecDeque<OutboxEntry>>,
+}
+
+impl Outbox {
+    /// 加入一条记录（恢复时使用），超出上限时丢弃最早的
+    pub fn insert(&mut self, entry: OutboxEntry) {
+        let queue = self.entries.entry(entry.user_id.clone()).or_default();
+        queue.push_back(entry);
+        while queue.len() > MAX_OUTBOX_PER_USER {
+            queue.pop_front();
+        }
+    }
+
+    /// 为用户暂存一条通知
+    pub fn enqueue(&mut self, user_id: &str, message: GatewayMessage) -> OutboxEntry {
+        let entry = OutboxEntry {
+            user_id: user_id.to_string(),
+            message,
+            status: DeliveryStatus::Pending,
+            delivered_at: None,
+            read_at: None,
+        };
+        self.insert(entry.clone());
+        entry
+    }
+
+    /// 尚未送达的通知
+    pub fn pending(&self, user_id: &str) -> Vec<OutboxEntry> {
+        self.entries
+            .get(user_id)
+            .map(|q| {
+                q.iter()
+                    .filter(|e| e.status == DeliveryStatus::Pending)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 最近 limit 条通知（新的在后）
+    pub fn recent(&self, user_id: &str, limit: usize) -> Vec<OutboxEntry> {
+        self.entries
+            .get(user_id)
+            .map(|q| q.iter().skip(q.len().saturating_sub(limit)).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// pending → delivered，返回更新后的记录（其它状态不变，返回 None）
+    pub fn mark_delivered(&mut self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        let entry = self.find_mut(user_id, message_id)?;
+        if entry.status != DeliveryStatus::Pending {
+            return None;
+        }
+        entry.status = DeliveryStatus::Delivered;
+        entry.delivered_at = Some(now_millis());
+        Some(entry.clone())
+    }
+
+    /// 标记已读（未送达的通知同时视为已送达），返回更新后的记录；已读或不存在时返回 None
+    pub fn mark_read(&mut self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        let entry = self.find_mut(user_id, message_id)?;
+        if entry.status == DeliveryStatus::Read {
+            return None;
+        }
+        let now = now_millis();
+        entry.status = DeliveryStatus::Read;
+        entry.delivered_at.get_or_insert(now);
+        entry.read_at = Some(now);
+        Some(entry.clone())
+    }
+
+    fn find_mut(&mut self, user_id: &str, message_id: &str) -> Option<&mut OutboxEntry> {
+        self.entries.get_mut(user_id)?.iter_mut().find(|e| e.id() == message_id)
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::MessageType;
+
+    #[test]
+    fn test_outbox_delivery_and_read() {
+        let mut outbox = Outbox::default();
+        let msg = |n: u8| GatewayMessage::new(None, MessageType::Queued { position: n as usize });
+        let first = outbox.enqueue("alice", msg(1));
+        let second = outbox.enqueue("alice", msg(2));
+        outbox.enqueue("bob", msg(3));
+        assert_eq!(outbox.pending("alice").len(), 2);
+
+        let delivered = outbox.mark_delivered("alice", first.id()).unwrap();
+        assert_eq!(delivered.status, DeliveryStatus::Delivered);
+        assert!(outbox.mark_delivered("alice", first.id()).is_none());
+        assert!(outbox.mark_delivered("bob", first.id()).is_none());
+        assert_eq!(outbox.pending("alice").len(), 1);
+
+        let read = outbox.mark_read("alice", second.id()).unwrap();
+        assert_eq!(read.status, DeliveryStatus::Read);
+        assert!(read.delivered_at.is_some() && read.read_at.is_some());
+        assert!(outbox.pending("alice").is_empty());
+        assert_eq!(
+            outbox
+                .recent("alice", 1)
+                .iter()
+                .map(|e| e.receipt().status)
+                .collect::<Vec<_>>(),
+            vec![DeliveryStatus::Read]
+        );
+
+        for n in 0..MAX_OUTBOX_PER_USER {
+            outbox.enqueue("bob", msg(n as u8));
+        }
+        assert_eq!(outbox.recent("bob", usize::MAX).len(), MAX_OUTBOX_PER_USER);
+    }
+}
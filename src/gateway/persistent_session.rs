@@ -12,8 +12,12 @@ use sqlx::Row;
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 
-use super::message::{ClientInfo, SessionStatus, SpokeType};
-use super::session::{Session, SessionId};
+use super::message::{ClientInfo, GatewayMessage, SessionStatus, SpokeType};
+use super::outbox::{DeliveryStatus, Outbox, OutboxEntry, MAX_OUTBOX_PER_USER};
+use super::session::{
+    sessions_of, summarize, switch_session, IdentityLinks, Session, SessionId, SessionLinkError, SessionSummary,
+};
+use crate::memory::MemoryScope;
 use crate::react::ContextManager;
 
 /// 持久化会话管理器
@@ -21,12 +25,18 @@ use crate::react::ContextManager;
 /// 与内存版 SessionManager 的区别：
 /// - 会话元数据持久化到 SQLite
 /// - 消息历史持久化到 SQLite
+/// - 平台身份的链接关系持久化到 SQLite
+/// - 待补发的通知及其投递状态持久化到 SQLite
 /// - 服务重启后可恢复会话
 pub struct PersistentSessionManager {
     /// 活跃会话（内存缓存）
     sessions: RwLock<HashMap<SessionId, Session>>,
-    /// 用户到会话的映射
+    /// 用户到会话的映射（规范 user_id -> session_id）
     user_sessions: RwLock<HashMap<String, SessionId>>,
+    /// 平台身份到规范用户的映射
+    identities: RwLock<IdentityLinks>,
+    /// 待补发的通知与投递状态（规范 user_id）
+    outbox: RwLock<Outbox>,
     /// SQLite 连接池
     pool: sqlx::sqlite::SqlitePool,
     /// 最大上下文轮数
@@ -52,6 +62,8 @@ impl PersistentSessionManager {
         let manager = Self {
             sessions: RwLock::new(HashMap::new()),
             user_sessions: RwLock::new(HashMap::new()),
+            identities: RwLock::new(IdentityLinks::default()),
+            outbox: RwLock::new(Outbox::default()),
             pool,
             max_context_turns,
             session_timeout: Duration::from_secs(session_timeout_secs),
@@ -85,12 +97,58 @@ impl PersistentSessionManager {
                 role TEXT NOT NULL,
                 content TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                tool_call_id TEXT,
+                tool_name TEXT,
                 FOREIGN KEY (session_id) REFERENCES gateway_sessions(id) ON DELETE CASCADE
             )"
         )
         .execute(&self.pool)
         .await?;
 
+        // 旧库升级：补充工具调用列
+        for column in ["tool_call_id", "tool_name"] {
+            let exists = sqlx::query("SELECT 1 FROM pragma_table_info('gateway_messages') WHERE name = ?")
+                .bind(column)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+            if !exists {
+                sqlx::query(&format!("ALTER TABLE gateway_messages ADD COLUMN {} TEXT", column))
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS gateway_identities (
+                alias TEXT PRIMARY KEY,
+                canonical TEXT NOT NULL,
+                linked_at TEXT NOT NULL
+            )"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS gateway_outbox (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                message TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                delivered_at INTEGER,
+                read_at INTEGER
+            )"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_gateway_outbox_user ON gateway_outbox(user_id, created_at)"
+        )
+        .execute(&self.pool)
+        .await?;
+
         sqlx::query(
             "CREATE INDEX IF NOT EXISTS idx_gateway_sessions_user ON gateway_sessions(user_id)"
         )
@@ -114,12 +172,47 @@ impl PersistentSessionManager {
         let rows = sqlx::query(
             "SELECT id, user_id, assistant_id, model_id, created_at, updated_at 
              FROM gateway_sessions 
-             WHERE updated_at > ?"
+             WHERE updated_at > ?
+             ORDER BY updated_at ASC"
         )
         .bind(&cutoff_str)
         .fetch_all(&self.pool)
         .await?;
 
+        let mut identities = self.identities.write().await;
+        for row in sqlx::query("SELECT alias, canonical FROM gateway_identities")
+            .fetch_all(&self.pool)
+            .await?
+        {
+            identities.insert(row.get("alias"), row.get("canonical"));
+        }
+        drop(identities);
+
+        let mut outbox = self.outbox.write().await;
+        for row in sqlx::query(
+            "SELECT user_id, message, status, delivered_at, read_at FROM gateway_outbox ORDER BY created_at ASC"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            let message: String = row.get("message");
+            let status: String = row.get("status");
+            let (Ok(message), Some(status)) = (
+                serde_json::from_str::<GatewayMessage>(&message),
+                DeliveryStatus::parse(&status),
+            ) else {
+                continue;
+            };
+            outbox.insert(OutboxEntry {
+                user_id: row.get("user_id"),
+                message,
+                status,
+                delivered_at: row.get::<Option<i64>, _>("delivered_at").map(|t| t as u64),
+                read_at: row.get::<Option<i64>, _>("read_at").map(|t| t as u64),
+            });
+        }
+        drop(outbox);
+
         let mut sessions = self.sessions.write().await;
         let mut user_sessions = self.user_sessions.write().await;
 
@@ -135,7 +228,8 @@ impl PersistentSessionManager {
                 id: session_id.clone(),
                 user_id: user_id.clone(),
                 clients: HashMap::new(),
-                context: ContextManager::new(self.max_context_turns),
+                context: ContextManager::new(self.max_context_turns)
+                    .with_scope(MemoryScope::for_user(user_id.clone())),
                 status: SessionStatus::Idle,
                 cancel_token: None,
                 last_active: Instant::now(),
@@ -148,6 +242,7 @@ impl PersistentSessionManager {
                 session.context.push_message(msg);
             }
 
+            // 按 updated_at 升序恢复：同一用户有多个会话时，最近活跃的成为当前会话
             user_sessions.insert(user_id, session_id.clone());
             sessions.insert(session_id, session);
         }
@@ -163,7 +258,7 @@ impl PersistentSessionManager {
     /// 加载会话消息
     async fn load_messages(&self, session_id: &str) -> Result<Vec<crate::memory::Message>, sqlx::Error> {
         let rows = sqlx::query(
-            "SELECT role, content FROM gateway_messages WHERE session_id = ? ORDER BY id ASC"
+            "SELECT role, content, tool_call_id, tool_name FROM gateway_messages WHERE session_id = ? ORDER BY id ASC"
         )
         .bind(session_id)
         .fetch_all(&self.pool)
@@ -182,7 +277,10 @@ impl PersistentSessionManager {
                 _ => continue,
             };
             
-            messages.push(crate::memory::Message { role, content });
+            messages.push(
+                crate::memory::Message::new(role, content)
+                    .with_tool_call(row.get("tool_call_id"), row.get("tool_name")),
+            );
         }
 
         Ok(messages)
@@ -199,12 +297,14 @@ impl PersistentSessionManager {
         let now = chrono::Utc::now().to_rfc3339();
 
         sqlx::query(
-            "INSERT INTO gateway_messages (session_id, role, content, created_at) VALUES (?, ?, ?, ?)"
+            "INSERT INTO gateway_messages (session_id, role, content, created_at, tool_call_id, tool_name) VALUES (?, ?, ?, ?, ?, ?)"
         )
         .bind(session_id)
         .bind(role_str)
         .bind(&message.content)
         .bind(&now)
+        .bind(&message.tool_call_id)
+        .bind(&message.tool_name)
         .execute(&self.pool)
         .await?;
 
@@ -237,20 +337,65 @@ impl PersistentSessionManager {
         Ok(())
     }
 
-    /// 获取或创建用户的会话
+    /// 保存身份链接
+    async fn save_identity_links(&self, links: &[(String, String)]) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        for (alias, canonical) in links {
+            sqlx::query("INSERT OR REPLACE INTO gateway_identities (alias, canonical, linked_at) VALUES (?, ?, ?)")
+                .bind(alias)
+                .bind(canonical)
+                .bind(&now)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 保存发件箱记录，新记录入队时清理该用户超出上限的旧记录
+    async fn save_outbox_entry(&self, entry: &OutboxEntry) -> Result<(), sqlx::Error> {
+        let message = serde_json::to_string(&entry.message).unwrap_or_default();
+        sqlx::query(
+            "INSERT OR REPLACE INTO gateway_outbox (id, user_id, message, status, created_at, delivered_at, read_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(entry.id())
+        .bind(&entry.user_id)
+        .bind(message)
+        .bind(entry.status.as_str())
+        .bind(entry.message.timestamp as i64)
+        .bind(entry.delivered_at.map(|t| t as i64))
+        .bind(entry.read_at.map(|t| t as i64))
+        .execute(&self.pool)
+        .await?;
+
+        if entry.status == DeliveryStatus::Pending {
+            sqlx::query(
+                "DELETE FROM gateway_outbox WHERE user_id = ? AND id NOT IN
+                 (SELECT id FROM gateway_outbox WHERE user_id = ? ORDER BY created_at DESC LIMIT ?)"
+            )
+            .bind(&entry.user_id)
+            .bind(&entry.user_id)
+            .bind(MAX_OUTBOX_PER_USER as i64)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// 获取或创建用户的会话（已链接的平台身份进入规范用户的会话）
     pub async fn get_or_create(&self, user_id: &str, client: ClientInfo) -> SessionId {
-        let user_sessions = self.user_sessions.read().await;
-        
-        if let Some(session_id) = user_sessions.get(user_id) {
+        let user_id = self.identities.read().await.canonical(user_id);
+        let existing = self.user_sessions.read().await.get(&user_id).cloned();
+
+        if let Some(session_id) = existing {
             let mut sessions = self.sessions.write().await;
-            if let Some(session) = sessions.get_mut(session_id) {
+            if let Some(session) = sessions.get_mut(&session_id) {
                 session.add_client(client);
-                return session_id.clone();
+                return session_id;
             }
         }
-        drop(user_sessions);
 
-        let mut session = Session::new(user_id.to_string(), self.max_context_turns);
+        let mut session = Session::new(user_id.clone(), self.max_context_turns);
         session.add_client(client);
         let session_id = session.id.clone();
 
@@ -259,11 +404,88 @@ impl PersistentSessionManager {
         }
 
         self.sessions.write().await.insert(session_id.clone(), session);
-        self.user_sessions.write().await.insert(user_id.to_string(), session_id.clone());
+        self.user_sessions.write().await.insert(user_id, session_id.clone());
 
         session_id
     }
 
+    /// 为用户生成链接码（`/link`）
+    pub async fn issue_link_code(&self, user_id: &str) -> String {
+        self.identities.write().await.issue_code(user_id)
+    }
+
+    /// 兑换链接码（`/link <code>`）：当前平台身份并入生成者（同时持久化），客户端迁移到其会话
+    pub async fn link(&self, user_id: &str, code: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        let mut identities = self.identities.write().await;
+        let target = identities.redeem(code).ok_or(SessionLinkError::InvalidCode)?;
+        let previous = self.user_sessions.read().await.get(&identities.canonical(user_id)).cloned();
+        let changed = identities.link(user_id, &target);
+        drop(identities);
+        if let Err(e) = self.save_identity_links(&changed).await {
+            tracing::error!("Failed to persist identity link: {}", e);
+        }
+        if let Some(previous) = previous {
+            self.remove_client(&previous, client.platform).await;
+        }
+        Ok(self.get_or_create(user_id, client).await)
+    }
+
+    /// 切换到用户名下的另一个会话（`/switch <session_id>`）
+    pub async fn switch(&self, user_id: &str, session_id: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        let identities = self.identities.read().await;
+        let mut sessions = self.sessions.write().await;
+        let mut user_sessions = self.user_sessions.write().await;
+        switch_session(&mut sessions, &mut user_sessions, &identities, user_id, session_id, client)
+    }
+
+    /// 列出用户名下的会话（`/switch`）
+    pub async fn user_sessions(&self, user_id: &str) -> Vec<SessionSummary> {
+        let identities = self.identities.read().await;
+        sessions_of(self.sessions.read().await.values(), &identities, user_id)
+    }
+
+    /// 为用户暂存一条通知（同时持久化）
+    pub async fn enqueue_outbox(&self, user_id: &str, message: GatewayMessage) -> OutboxEntry {
+        let user_id = self.identities.read().await.canonical(user_id);
+        let entry = self.outbox.write().await.enqueue(&user_id, message);
+        if let Err(e) = self.save_outbox_entry(&entry).await {
+            tracing::error!("Failed to persist outbox entry: {}", e);
+        }
+        entry
+    }
+
+    /// 用户尚未送达的通知
+    pub async fn pending_outbox(&self, user_id: &str) -> Vec<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.read().await.pending(&user_id)
+    }
+
+    /// 用户最近的通知及其投递状态
+    pub async fn recent_outbox(&self, user_id: &str, limit: usize) -> Vec<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.read().await.recent(&user_id, limit)
+    }
+
+    /// 标记通知已送达（同时持久化）
+    pub async fn mark_delivered(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        let entry = self.outbox.write().await.mark_delivered(&user_id, message_id)?;
+        if let Err(e) = self.save_outbox_entry(&entry).await {
+            tracing::error!("Failed to persist delivery status: {}", e);
+        }
+        Some(entry)
+    }
+
+    /// 标记通知已读（同时持久化）
+    pub async fn mark_read(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        let entry = self.outbox.write().await.mark_read(&user_id, message_id)?;
+        if let Err(e) = self.save_outbox_entry(&entry).await {
+            tracing::error!("Failed to persist read receipt: {}", e);
+        }
+        Some(entry)
+    }
+
     /// 添加消息到会话（同时持久化）
     pub async fn add_message(&self, session_id: &str, message: crate::memory::Message) {
         let mut sessions = self.sessions.write().await;
@@ -308,7 +530,9 @@ impl PersistentSessionManager {
 
         for (session_id, user_id) in &expired {
             sessions.remove(session_id);
-            user_sessions.remove(user_id);
+            if user_sessions.get(user_id) == Some(session_id) {
+                user_sessions.remove(user_id);
+            }
         }
 
         expired.len()
@@ -321,7 +545,13 @@ impl PersistentSessionManager {
 
     /// 获取用户的会话 ID
     pub async fn get_user_session(&self, user_id: &str) -> Option<SessionId> {
-        self.user_sessions.read().await.get(user_id).cloned()
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.user_sessions.read().await.get(&user_id).cloned()
+    }
+
+    /// 列出内存中的活跃会话（最近活跃在前）
+    pub async fn list(&self) -> Vec<SessionSummary> {
+        summarize(self.sessions.read().await.values())
     }
 
     /// 获取会话上下文
@@ -360,6 +590,7 @@ impl PersistentSessionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gateway::MessageType;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -387,6 +618,18 @@ mod tests {
         manager.add_message(&session_id, msg2).await;
 
         assert_eq!(manager.active_count().await, 1);
+
+        let code = manager.issue_link_code("user_123").await;
+        let phone = ClientInfo {
+            client_id: "+8613800000000".to_string(),
+            platform: SpokeType::WhatsApp,
+            display_name: None,
+            metadata: None,
+        };
+        assert_eq!(manager.link("+8613800000000", &code, phone).await, Ok(session_id.clone()));
+
+        let notification = GatewayMessage::new(None, MessageType::Queued { position: 1 });
+        let queued = manager.enqueue_outbox("+8613800000000", notification).await;
         
         manager.close().await;
 
@@ -399,6 +642,12 @@ mod tests {
         let session_id2 = manager2.get_user_session("user_123").await;
         assert!(session_id2.is_some());
         assert_eq!(session_id2.unwrap(), session_id);
+        assert_eq!(manager2.get_user_session("+8613800000000").await, Some(session_id.clone()));
+        let pending = manager2.pending_outbox("user_123").await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id(), queued.id());
+        assert!(manager2.mark_read("user_123", queued.id()).await.is_some());
+        assert!(manager2.pending_outbox("user_123").await.is_empty());
 
         let ctx = manager2.get_context(&session_id).await.unwrap();
         let messages = ctx.messages();
@@ -7,12 +7,17 @@ use std::sync::Arc;
 
 use tokio::sync::mpsc;
 
+use super::federation::{Federation, RemoteHubSpoke, FEDERATED_CLIENT_PREFIX};
+use super::intent::IntentRecognizer;
 use super::message::{GatewayMessage, MessageType, SessionStatus};
 use super::session_store::SessionStore;
-use crate::agent::create_agent_components;
+use super::spoke::CapabilitySpoke;
+use super::task_queue::{BackgroundTask, TaskQueue};
+use crate::agent::{create_agent_components, create_context_for_scope};
 use crate::config::AppConfig;
-use crate::core::{AgentComponents, AgentError};
-use crate::react::{react_loop, ReactEvent};
+use crate::core::{AgentComponents, AgentError, WorkPriority, CURRENT_PRIORITY};
+use crate::memory::Message;
+use crate::react::{react_loop_v2, ContextManager, ReactEvent, ReactSession};
 use crate::skills::SkillSelector;
 
 /// Runtime 配置
@@ -51,11 +56,56 @@ impl Default for RuntimeConfig {
     }
 }
 
+/// 后台执行标记（中英文，大小写不敏感）：只在消息开头或结尾命中，剥离标记后剩余内容转为后台任务
+const BACKGROUND_MARKERS: &[&str] = &[
+    "in the background",
+    "as a background task",
+    "在后台",
+    "后台执行",
+    "后台运行",
+    "后台处理",
+];
+
+/// 识别「放到后台做」的请求：`/bg ` 前缀，或开头 / 结尾的后台标记（句中出现的标记只是普通提问，不算）；
+/// 返回剥离标记后的任务指令，非后台请求或剩余指令为空时返回 None
+pub fn parse_background_request(input: &str) -> Option<String> {
+    let is_filler = |c: char| c.is_whitespace() || ",，.。;；:：!！".contains(c);
+    let trimmed = input.trim();
+    let (head, tail) = if trimmed.to_ascii_lowercase().starts_with("/bg ") {
+        ("", &trimmed[4..])
+    } else {
+        let core = trimmed.trim_matches(is_filler);
+        let lower = core.to_ascii_lowercase();
+        if let Some(marker) = BACKGROUND_MARKERS.iter().find(|m| lower.starts_with(*m)) {
+            ("", &core[marker.len()..])
+        } else {
+            let marker = BACKGROUND_MARKERS.iter().find(|m| lower.ends_with(*m))?;
+            (&core[..core.len() - marker.len()], "")
+        }
+    };
+    let head = head.trim_matches(is_filler);
+    let tail = tail.trim_matches(is_filler);
+    let instruction = match (head.is_empty(), tail.is_empty()) {
+        (true, _) => tail.to_string(),
+        (false, true) => head.to_string(),
+        (false, false) => format!("{} {}", head, tail),
+    };
+    if instruction.is_empty() {
+        None
+    } else {
+        Some(instruction)
+    }
+}
+
 /// Agent Runtime - AI 处理核心
 pub struct AgentRuntime {
     config: RuntimeConfig,
     components: AgentComponents,
     session_store: Arc<dyn SessionStore>,
+    /// 后台任务队列（设置后支持把长任务从对话中剥离）
+    task_queue: Option<Arc<TaskQueue>>,
+    /// [federation]：远端 Hub 及用于路由的意图识别器
+    federation: Option<(Arc<Federation>, Arc<IntentRecognizer>)>,
 }
 
 impl AgentRuntime {
@@ -65,7 +115,23 @@ impl AgentRuntime {
             config,
             components,
             session_store,
+            task_queue: None,
+            federation: None,
+        }
+    }
+
+    /// 挂载后台任务队列
+    pub fn with_task_queue(mut self, queue: Arc<TaskQueue>) -> Self {
+        self.task_queue = Some(queue);
+        self
+    }
+
+    /// 挂载远端 Hub 与路由用的意图识别器：意图命中其 capabilities 的消息转交远端处理（未配置远端时不做意图识别）
+    pub fn with_federation(mut self, federation: Arc<Federation>, recognizer: Arc<IntentRecognizer>) -> Self {
+        if !federation.is_empty() {
+            self.federation = Some((federation, recognizer));
         }
+        self
     }
 
     /// 获取 Agent 组件（用于共享 LLM 等）
@@ -89,6 +155,14 @@ impl AgentRuntime {
     ) -> Result<String, AgentError> {
         let request_id = uuid::Uuid::new_v4().to_string();
 
+        if let (Some(queue), Some(instruction)) =
+            (&self.task_queue, parse_background_request(user_input))
+        {
+            return Ok(self
+                .detach_to_background(queue, session_id, user_input, &instruction, assistant_id, request_id, response_tx)
+                .await);
+        }
+
         self.session_store.set_status(session_id, SessionStatus::Processing).await;
 
         response_tx
@@ -100,6 +174,16 @@ impl AgentRuntime {
             ))
             .ok();
 
+        if let Some(remote) = self.federation_target(session_id, user_input).await {
+            match self.delegate(&remote, session_id, user_input, &request_id, &response_tx).await {
+                Ok(response) => {
+                    self.session_store.set_status(session_id, SessionStatus::Idle).await;
+                    return Ok(response);
+                }
+                Err(e) => tracing::warn!("{}; handling locally", e),
+            }
+        }
+
         let (event_tx, mut event_rx) = mpsc::unbounded_channel::<ReactEvent>();
 
         let response_tx_clone = response_tx.clone();
@@ -151,6 +235,10 @@ impl AgentRuntime {
                             success: false,
                         },
                     ),
+                    ReactEvent::FileChanged { watch_id, path, change } => GatewayMessage::new(
+                        Some(session_id_owned.clone()),
+                        MessageType::FileChanged { watch_id, path, change },
+                    ),
                     ReactEvent::Error { text } => GatewayMessage::new(
                         Some(session_id_owned.clone()),
                         MessageType::Error {
@@ -186,12 +274,16 @@ impl AgentRuntime {
                     .ok();
             }
             Err(e) => {
+                let code = match e {
+                    AgentError::Stalled { .. } => "session_stalled",
+                    _ => "runtime_error",
+                };
                 response_tx
                     .send(GatewayMessage::new(
                         Some(session_id.to_string()),
                         MessageType::Error {
                             request_id: Some(request_id),
-                            code: "runtime_error".to_string(),
+                            code: code.to_string(),
                             message: e.to_string(),
                         },
                     ))
@@ -202,27 +294,192 @@ impl AgentRuntime {
         result
     }
 
-    async fn run_react_loop(
+    /// 意图命中 [federation] 中某个远端的 capabilities 时返回该远端；来自联邦的请求不再转交
+    async fn federation_target(&self, session_id: &str, user_input: &str) -> Option<Arc<RemoteHubSpoke>> {
+        let (federation, recognizer) = self.federation.as_ref()?;
+        let user_id = self.session_store.get_context(session_id).await?.scope.user_id?;
+        if user_id.starts_with(FEDERATED_CLIENT_PREFIX) {
+            return None;
+        }
+        let intent = recognizer.recognize(user_input).await;
+        let remote = federation.route(&intent)?;
+        tracing::info!(intent = intent.kind(), remote = remote.name(), "delegating to remote hub");
+        Some(remote)
+    }
+
+    /// 转交远端 Hub：流式事件改用本地 request_id 转发，远端回复写入本地会话并作为本次回复
+    async fn delegate(
         &self,
+        remote: &RemoteHubSpoke,
         session_id: &str,
         user_input: &str,
-        event_tx: mpsc::UnboundedSender<ReactEvent>,
-        _assistant_id: Option<&str>,
-        _model: Option<&str>,
-    ) -> Result<String, AgentError> {
-        let cancel_token = self
+        request_id: &str,
+        response_tx: &mpsc::UnboundedSender<GatewayMessage>,
+    ) -> Result<String, String> {
+        let sid = Some(session_id.to_string());
+        let relay = |message: MessageType| {
+            let message = match message {
+                MessageType::ResponseChunk { content, .. } => MessageType::ResponseChunk {
+                    request_id: request_id.to_string(),
+                    content,
+                },
+                MessageType::Thinking { content, .. } => MessageType::Thinking {
+                    request_id: request_id.to_string(),
+                    content,
+                },
+                MessageType::ToolCall { tool_name, arguments, .. } => MessageType::ToolCall {
+                    request_id: request_id.to_string(),
+                    tool_name,
+                    arguments,
+                },
+                MessageType::ToolResult { tool_name, result, success, .. } => MessageType::ToolResult {
+                    request_id: request_id.to_string(),
+                    tool_name,
+                    result,
+                    success,
+                },
+                _ => return,
+            };
+            response_tx.send(GatewayMessage::new(sid.clone(), message)).ok();
+        };
+        let client_id = format!("{}{}", FEDERATED_CLIENT_PREFIX, session_id);
+        let response = remote.forward(&client_id, user_input, relay).await?;
+
+        self.session_store.add_message(session_id, Message::user(user_input)).await;
+        self.session_store.add_message(session_id, Message::assistant(response.clone())).await;
+        response_tx
+            .send(GatewayMessage::new(
+                Some(session_id.to_string()),
+                MessageType::ResponseEnd {
+                    request_id: request_id.to_string(),
+                    full_content: response.clone(),
+                },
+            ))
+            .ok();
+        Ok(response)
+    }
+
+    /// 把请求转为绑定当前会话的后台任务，立即释放对话并回执任务 ID
+    #[allow(clippy::too_many_arguments)]
+    async fn detach_to_background(
+        &self,
+        queue: &TaskQueue,
+        session_id: &str,
+        user_input: &str,
+        instruction: &str,
+        assistant_id: Option<&str>,
+        request_id: String,
+        response_tx: mpsc::UnboundedSender<GatewayMessage>,
+    ) -> String {
+        let user_id = self
             .session_store
-            .new_cancel_token(session_id)
+            .get_context(session_id)
             .await
-            .unwrap_or_else(tokio_util::sync::CancellationToken::new);
+            .and_then(|c| c.scope.user_id)
+            .unwrap_or_else(|| session_id.to_string());
+
+        let mut task = BackgroundTask::new(user_id, instruction.to_string())
+            .with_session(session_id.to_string());
+        task.metadata = Some(serde_json::json!({ "assistant_id": assistant_id }));
+        let task_id = queue.submit(task).await;
+
+        let ack = format!("已转入后台任务 {}，完成后结果会发回本会话。", task_id);
+        self.session_store.add_message(session_id, Message::user(user_input)).await;
+        self.session_store.add_message(session_id, Message::assistant(ack.clone())).await;
+
+        let sid = Some(session_id.to_string());
+        response_tx
+            .send(GatewayMessage::new(sid.clone(), MessageType::TaskSubmitted { task_id }))
+            .ok();
+        response_tx
+            .send(GatewayMessage::new(
+                sid,
+                MessageType::ResponseEnd {
+                    request_id,
+                    full_content: ack.clone(),
+                },
+            ))
+            .ok();
+        ack
+    }
+
+    /// 执行后台任务（供 TaskExecutor 调用）：独立上下文，取消令牌取自任务队列（TaskQueue::cancel 可中止），
+    /// 完成后把结果写回所属会话
+    pub async fn run_background_task(&self, task: BackgroundTask) -> Result<String, String> {
+        let assistant_id = task
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get("assistant_id"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let mut context = match task.session_id.as_deref() {
+            Some(sid) => self.load_context(sid, assistant_id.as_deref()).await,
+            None => ContextManager::new(self.config.app_config.app.max_context_turns),
+        };
+        let system_prompt = self.system_prompt_for(&task.instruction).await;
+
+        let cancel_token = self
+            .task_queue
+            .as_ref()
+            .map(|q| q.cancel_token(&task.id))
+            .unwrap_or_default();
+        let (watched_tx, watched_rx) = mpsc::unbounded_channel::<ReactEvent>();
+        let session = self.react_session(&cancel_token, &watched_tx, system_prompt.as_deref());
+        let run = react_loop_v2(&session, &mut context, &task.instruction);
+        // 后台任务调度时让位于交互请求
+        let supervised = self.components.watchdog.supervise(&task.id, &cancel_token, watched_rx, None, run);
+        let result = CURRENT_PRIORITY.scope(WorkPriority::Low, supervised).await;
+        if let Err(AgentError::Stalled { ref activity, idle_secs }) = result {
+            context.append_stall_lesson(activity, idle_secs);
+        }
+        let result = result.map(|r| r.response).map_err(|e| e.to_string());
 
+        // 只追加结果消息，不整体覆盖会话上下文，避免与期间的对话相互踩踏
+        if let Some(sid) = task.session_id.as_deref() {
+            let text = match &result {
+                Ok(response) => format!("[后台任务 {} 完成]\n{}", task.id, response),
+                Err(e) => format!("[后台任务 {} 失败] {}", task.id, e),
+            };
+            let msg = Message::assistant(text);
+            if let Some(mut ctx) = self.session_store.get_context(sid).await {
+                ctx.push_message(msg.clone());
+                self.session_store.set_context(sid, ctx).await;
+            }
+            self.session_store.add_message(sid, msg).await;
+        }
+
+        result
+    }
+
+    /// 读取会话上下文；首次处理时按 (user, assistant) 作用域挂载长期记忆
+    async fn load_context(&self, session_id: &str, assistant_id: Option<&str>) -> ContextManager {
         let mut context = self
             .session_store
             .get_context(session_id)
             .await
-            .unwrap_or_else(|| crate::react::ContextManager::new(20));
+            .unwrap_or_else(|| ContextManager::new(20));
 
-        let system_prompt = if self.config.enable_skills {
+        // 首次处理时按 (user, assistant) 作用域挂载长期记忆，避免不同用户的记忆互相泄漏
+        if context.long_term.is_none() {
+            let scope = context.scope.clone().with_assistant(assistant_id);
+            let mut scoped = create_context_for_scope(
+                &self.config.app_config,
+                self.config.app_config.app.max_context_turns,
+                Some(&self.config.workspace),
+                None,
+                &scope,
+            );
+            scoped.conversation = context.conversation;
+            scoped.working = context.working;
+            context = scoped;
+        }
+        context
+    }
+
+    /// 按输入选择技能并拼接到系统提示词；未启用技能或未命中时返回 None
+    async fn system_prompt_for(&self, user_input: &str) -> Option<String> {
+        if self.config.enable_skills {
             let selector = SkillSelector::new(
                 self.components.skill_cache(),
                 Arc::clone(&self.components.llm),
@@ -236,23 +493,65 @@ impl AgentRuntime {
             }
         } else {
             None
-        };
+        }
+    }
 
-        let result = react_loop(
-            &self.components.planner,
-            &self.components.executor,
-            &self.components.recovery,
-            &mut context,
-            user_input,
-            None,
-            Some(&event_tx),
-            cancel_token,
-            self.components.critic.as_ref(),
-            Some(&self.components.task_scheduler),
-            system_prompt.as_deref(),
-            None,
+    /// 本运行时的 ReactSession：共享组件 + [react] 步数与时限
+    fn react_session<'a>(
+        &'a self,
+        cancel_token: &tokio_util::sync::CancellationToken,
+        event_tx: &'a mpsc::UnboundedSender<ReactEvent>,
+        system_prompt: Option<&'a str>,
+    ) -> ReactSession<'a> {
+        let components = &self.components;
+        let mut session = ReactSession::new(
+            &components.planner,
+            &components.executor,
+            &components.recovery,
+            cancel_token.clone(),
         )
-        .await;
+        .with_event_tx(event_tx)
+        .with_task_scheduler(&components.task_scheduler)
+        .with_limits(components.react_limits())
+        .with_guardrails(components.guardrails.as_ref());
+        if let Some(critic) = components.critic.as_ref() {
+            session = session.with_critic(critic);
+        }
+        if let Some(prompt) = system_prompt {
+            session = session.with_system_prompt(prompt);
+        }
+        session
+    }
+
+    async fn run_react_loop(
+        &self,
+        session_id: &str,
+        user_input: &str,
+        event_tx: mpsc::UnboundedSender<ReactEvent>,
+        assistant_id: Option<&str>,
+        _model: Option<&str>,
+    ) -> Result<String, AgentError> {
+        let cancel_token = self
+            .session_store
+            .new_cancel_token(session_id)
+            .await
+            .unwrap_or_else(tokio_util::sync::CancellationToken::new);
+
+        let mut context = self.load_context(session_id, assistant_id).await;
+        let system_prompt = self.system_prompt_for(user_input).await;
+
+        // 看门狗转发事件到所属端点，卡住时取消并以 Error 事件通知
+        let (watched_tx, watched_rx) = mpsc::unbounded_channel::<ReactEvent>();
+        let session = self.react_session(&cancel_token, &watched_tx, system_prompt.as_deref());
+        let run = react_loop_v2(&session, &mut context, user_input);
+        let result = self
+            .components
+            .watchdog
+            .supervise(session_id, &cancel_token, watched_rx, Some(&event_tx), run)
+            .await;
+        if let Err(AgentError::Stalled { ref activity, idle_secs }) = result {
+            context.append_stall_lesson(activity, idle_secs);
+        }
 
         self.session_store.set_context(session_id, context).await;
 
@@ -275,3 +574,35 @@ impl AgentRuntime {
         self.session_store.get_history(session_id, limit).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_background_request() {
+        assert_eq!(
+            parse_background_request("Please summarize the repo in the background.").as_deref(),
+            Some("Please summarize the repo")
+        );
+        assert_eq!(
+            parse_background_request("In the background: crawl the docs site").as_deref(),
+            Some("crawl the docs site")
+        );
+        assert_eq!(
+            parse_background_request("在后台整理本周的周报").as_deref(),
+            Some("整理本周的周报")
+        );
+        assert_eq!(
+            parse_background_request("/bg crawl the docs site").as_deref(),
+            Some("crawl the docs site")
+        );
+        assert_eq!(parse_background_request("In the background."), None);
+        assert_eq!(parse_background_request("what's the weather"), None);
+        // 句中出现的标记只是普通提问
+        assert_eq!(parse_background_request("what runs in the background on Linux?"), None);
+        assert_eq!(parse_background_request("What is running in the background?"), None);
+        assert_eq!(parse_background_request("这张照片在后台显示不出来"), None);
+        assert_eq!(parse_background_request("Summarize the repo in the background, please"), None);
+    }
+}
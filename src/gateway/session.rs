@@ -1,20 +1,138 @@
 //! 会话管理
 //!
 //! 统一管理所有平台的会话状态，支持跨平台上下文连贯
+//!
+//! 各平台的用户 ID（WhatsApp 号码、Discord ID、Web client_id…）经身份映射表 [`IdentityLinks`]
+//! 归并到规范用户 ID，同一规范用户共享一个当前会话：在 WhatsApp 发送 `/link` 取得链接码，
+//! 在 Web 发送 `/link <code>` 即接续同一会话；`/switch <session_id>` 在该用户名下的会话间切换。
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use thiserror::Error;
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 
-use super::message::{ClientInfo, SessionStatus, SpokeType};
+use super::message::{ClientInfo, GatewayMessage, SessionStatus, SpokeType};
+use super::outbox::{Outbox, OutboxEntry};
+use crate::memory::MemoryScope;
 use crate::react::ContextManager;
 
 /// 会话 ID（用户维度，跨平台共享）
 pub type SessionId = String;
 
+/// 链接码有效期
+const LINK_CODE_TTL: Duration = Duration::from_secs(600);
+
+/// 会话链接 / 切换失败
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SessionLinkError {
+    #[error("link code is invalid or expired")]
+    InvalidCode,
+    #[error("session not found or not owned by this user")]
+    UnknownSession,
+}
+
+/// 会话命令（任一平台以聊天消息发送）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionCommand {
+    /// `/link`：为当前用户生成链接码
+    IssueLinkCode,
+    /// `/link <code>`：把当前平台身份链接到生成该码的用户，接续其会话
+    Link(String),
+    /// `/switch`：列出可切换的会话
+    ListSessions,
+    /// `/switch <session_id>`：切换到该用户名下的另一个会话
+    Switch(String),
+}
+
+impl SessionCommand {
+    /// 解析会话命令，普通消息返回 None
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.split_whitespace();
+        let command = parts.next()?.to_ascii_lowercase();
+        let arg = parts.next().map(str::to_string);
+        if parts.next().is_some() {
+            return None;
+        }
+        match (command.as_str(), arg) {
+            ("/link", None) => Some(Self::IssueLinkCode),
+            ("/link", Some(code)) => Some(Self::Link(code)),
+            ("/switch", None) => Some(Self::ListSessions),
+            ("/switch", Some(id)) => Some(Self::Switch(id)),
+            _ => None,
+        }
+    }
+}
+
+/// 用户身份映射表：平台用户 ID -> 规范用户 ID，以及待兑换的链接码
+#[derive(Default)]
+pub struct IdentityLinks {
+    aliases: HashMap<String, String>,
+    codes: HashMap<String, (String, Instant)>,
+}
+
+impl IdentityLinks {
+    /// 规范用户 ID（未链接时为自身）
+    pub fn canonical(&self, user_id: &str) -> String {
+        self.aliases.get(user_id).cloned().unwrap_or_else(|| user_id.to_string())
+    }
+
+    /// 为用户生成一次性链接码
+    pub fn issue_code(&mut self, user_id: &str) -> String {
+        self.codes.retain(|_, (_, issued)| issued.elapsed() < LINK_CODE_TTL);
+        let code = uuid::Uuid::new_v4().simple().to_string()[..8].to_ascii_uppercase();
+        self.codes.insert(code.clone(), (self.canonical(user_id), Instant::now()));
+        code
+    }
+
+    /// 兑换链接码，返回生成者的规范用户 ID
+    pub fn redeem(&mut self, code: &str) -> Option<String> {
+        let (user_id, issued) = self.codes.remove(&code.trim().to_ascii_uppercase())?;
+        (issued.elapsed() < LINK_CODE_TTL).then_some(user_id)
+    }
+
+    /// 把 user_id（连同已链接到它的身份）并入 canonical，返回变更的映射
+    pub fn link(&mut self, user_id: &str, canonical: &str) -> Vec<(String, String)> {
+        let from = self.canonical(user_id);
+        let canonical = self.canonical(canonical);
+        if from == canonical {
+            return Vec::new();
+        }
+        let mut changed: Vec<(String, String)> = self
+            .aliases
+            .iter()
+            .filter(|(_, c)| **c == from)
+            .map(|(alias, _)| (alias.clone(), canonical.clone()))
+            .collect();
+        changed.push((from, canonical));
+        for (alias, c) in &changed {
+            self.aliases.insert(alias.clone(), c.clone());
+        }
+        changed
+    }
+
+    /// 恢复已持久化的映射
+    pub fn insert(&mut self, alias: String, canonical: String) {
+        self.aliases.insert(alias, canonical);
+    }
+}
+
+/// 会话摘要（供 gRPC ListSessions 等列举接口使用）
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub session_id: SessionId,
+    pub user_id: String,
+    pub status: SessionStatus,
+    /// 当前连接的平台
+    pub platforms: Vec<SpokeType>,
+    pub message_count: usize,
+    /// 距最后活跃的秒数
+    pub idle_secs: u64,
+    pub assistant_id: Option<String>,
+}
+
 /// 单个会话
 pub struct Session {
     /// 会话 ID
@@ -42,11 +160,13 @@ pub struct Session {
 impl Session {
     pub fn new(user_id: String, max_context_turns: usize) -> Self {
         let id = format!("session_{}", uuid::Uuid::new_v4());
+        let context = ContextManager::new(max_context_turns)
+            .with_scope(MemoryScope::for_user(user_id.clone()));
         Self {
             id,
             user_id,
             clients: HashMap::new(),
-            context: ContextManager::new(max_context_turns),
+            context,
             status: SessionStatus::Idle,
             cancel_token: None,
             last_active: Instant::now(),
@@ -98,14 +218,76 @@ impl Session {
     pub fn is_expired(&self, timeout: Duration) -> bool {
         self.last_active.elapsed() > timeout && !self.has_active_clients()
     }
+
+    /// 会话摘要
+    pub fn summary(&self) -> SessionSummary {
+        let mut platforms: Vec<SpokeType> = self.clients.keys().copied().collect();
+        platforms.sort_by_key(|p| p.to_string());
+        SessionSummary {
+            session_id: self.id.clone(),
+            user_id: self.user_id.clone(),
+            status: self.status,
+            platforms,
+            message_count: self.context.messages().len(),
+            idle_secs: self.last_active.elapsed().as_secs(),
+            assistant_id: self.assistant_id.clone(),
+        }
+    }
+}
+
+/// 按最近活跃排序的会话摘要列表
+pub(crate) fn summarize<'a>(sessions: impl Iterator<Item = &'a Session>) -> Vec<SessionSummary> {
+    let mut list: Vec<SessionSummary> = sessions.map(Session::summary).collect();
+    list.sort_by_key(|s| s.idle_secs);
+    list
+}
+
+/// 把规范用户的当前会话切换到 session_id（须属于同一规范用户），客户端随之迁移
+pub(crate) fn switch_session(
+    sessions: &mut HashMap<SessionId, Session>,
+    user_sessions: &mut HashMap<String, SessionId>,
+    identities: &IdentityLinks,
+    user_id: &str,
+    session_id: &str,
+    client: ClientInfo,
+) -> Result<SessionId, SessionLinkError> {
+    let canonical = identities.canonical(user_id);
+    let owned = sessions
+        .get(session_id)
+        .is_some_and(|s| identities.canonical(&s.user_id) == canonical);
+    if !owned {
+        return Err(SessionLinkError::UnknownSession);
+    }
+    if let Some(current) = user_sessions.get(&canonical).and_then(|id| sessions.get_mut(id)) {
+        current.remove_client(client.platform);
+    }
+    if let Some(target) = sessions.get_mut(session_id) {
+        target.add_client(client);
+    }
+    user_sessions.insert(canonical, session_id.to_string());
+    Ok(session_id.to_string())
+}
+
+/// 规范用户名下的会话（含链接前各平台身份各自的会话）
+pub(crate) fn sessions_of<'a>(
+    sessions: impl Iterator<Item = &'a Session>,
+    identities: &IdentityLinks,
+    user_id: &str,
+) -> Vec<SessionSummary> {
+    let canonical = identities.canonical(user_id);
+    summarize(sessions.filter(|s| identities.canonical(&s.user_id) == canonical))
 }
 
 /// 会话管理器
 pub struct SessionManager {
     /// 所有会话（session_id -> Session）
     sessions: RwLock<HashMap<SessionId, Session>>,
-    /// 用户到会话的映射（user_id -> session_id）
+    /// 用户到会话的映射（规范 user_id -> session_id）
     user_sessions: RwLock<HashMap<String, SessionId>>,
+    /// 平台身份到规范用户的映射
+    identities: RwLock<IdentityLinks>,
+    /// 待补发的通知与投递状态（规范 user_id）
+    outbox: RwLock<Outbox>,
     /// 最大上下文轮数
     max_context_turns: usize,
     /// 会话过期时间
@@ -117,34 +299,98 @@ impl SessionManager {
         Self {
             sessions: RwLock::new(HashMap::new()),
             user_sessions: RwLock::new(HashMap::new()),
+            identities: RwLock::new(IdentityLinks::default()),
+            outbox: RwLock::new(Outbox::default()),
             max_context_turns,
             session_timeout: Duration::from_secs(session_timeout_secs),
         }
     }
 
-    /// 获取或创建用户的会话
+    /// 获取或创建用户的会话（已链接的平台身份进入规范用户的会话）
     pub async fn get_or_create(&self, user_id: &str, client: ClientInfo) -> SessionId {
-        let user_sessions = self.user_sessions.read().await;
-        
-        if let Some(session_id) = user_sessions.get(user_id) {
+        let user_id = self.identities.read().await.canonical(user_id);
+        let existing = self.user_sessions.read().await.get(&user_id).cloned();
+
+        if let Some(session_id) = existing {
             let mut sessions = self.sessions.write().await;
-            if let Some(session) = sessions.get_mut(session_id) {
+            if let Some(session) = sessions.get_mut(&session_id) {
                 session.add_client(client);
-                return session_id.clone();
+                return session_id;
             }
         }
-        drop(user_sessions);
 
-        let mut session = Session::new(user_id.to_string(), self.max_context_turns);
+        let mut session = Session::new(user_id.clone(), self.max_context_turns);
         session.add_client(client);
         let session_id = session.id.clone();
 
         self.sessions.write().await.insert(session_id.clone(), session);
-        self.user_sessions.write().await.insert(user_id.to_string(), session_id.clone());
+        self.user_sessions.write().await.insert(user_id, session_id.clone());
 
         session_id
     }
 
+    /// 为用户生成链接码（`/link`）
+    pub async fn issue_link_code(&self, user_id: &str) -> String {
+        self.identities.write().await.issue_code(user_id)
+    }
+
+    /// 兑换链接码（`/link <code>`）：当前平台身份并入生成者，客户端迁移到其会话
+    pub async fn link(&self, user_id: &str, code: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        let mut identities = self.identities.write().await;
+        let target = identities.redeem(code).ok_or(SessionLinkError::InvalidCode)?;
+        let previous = self.user_sessions.read().await.get(&identities.canonical(user_id)).cloned();
+        identities.link(user_id, &target);
+        drop(identities);
+        if let Some(previous) = previous {
+            self.remove_client(&previous, client.platform).await;
+        }
+        Ok(self.get_or_create(user_id, client).await)
+    }
+
+    /// 切换到用户名下的另一个会话（`/switch <session_id>`）
+    pub async fn switch(&self, user_id: &str, session_id: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        let identities = self.identities.read().await;
+        let mut sessions = self.sessions.write().await;
+        let mut user_sessions = self.user_sessions.write().await;
+        switch_session(&mut sessions, &mut user_sessions, &identities, user_id, session_id, client)
+    }
+
+    /// 列出用户名下的会话（`/switch`）
+    pub async fn user_sessions(&self, user_id: &str) -> Vec<SessionSummary> {
+        let identities = self.identities.read().await;
+        sessions_of(self.sessions.read().await.values(), &identities, user_id)
+    }
+
+    /// 为用户暂存一条通知（待送达）
+    pub async fn enqueue_outbox(&self, user_id: &str, message: GatewayMessage) -> OutboxEntry {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.write().await.enqueue(&user_id, message)
+    }
+
+    /// 用户尚未送达的通知
+    pub async fn pending_outbox(&self, user_id: &str) -> Vec<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.read().await.pending(&user_id)
+    }
+
+    /// 用户最近的通知及其投递状态
+    pub async fn recent_outbox(&self, user_id: &str, limit: usize) -> Vec<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.read().await.recent(&user_id, limit)
+    }
+
+    /// 标记通知已送达
+    pub async fn mark_delivered(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.write().await.mark_delivered(&user_id, message_id)
+    }
+
+    /// 标记通知已读
+    pub async fn mark_read(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.outbox.write().await.mark_read(&user_id, message_id)
+    }
+
     /// 获取会话
     pub async fn get(&self, session_id: &str) -> Option<Arc<RwLock<Session>>> {
         let sessions = self.sessions.read().await;
@@ -188,7 +434,10 @@ impl SessionManager {
 
         for (session_id, user_id) in &expired {
             sessions.remove(session_id);
-            user_sessions.remove(user_id);
+            // 切换过会话的用户，其当前会话可能不是过期的这个
+            if user_sessions.get(user_id) == Some(session_id) {
+                user_sessions.remove(user_id);
+            }
         }
 
         expired.len()
@@ -201,7 +450,13 @@ impl SessionManager {
 
     /// 获取用户的会话 ID
     pub async fn get_user_session(&self, user_id: &str) -> Option<SessionId> {
-        self.user_sessions.read().await.get(user_id).cloned()
+        let user_id = self.identities.read().await.canonical(user_id);
+        self.user_sessions.read().await.get(&user_id).cloned()
+    }
+
+    /// 列出所有会话（最近活跃在前）
+    pub async fn list(&self) -> Vec<SessionSummary> {
+        summarize(self.sessions.read().await.values())
     }
 }
 
@@ -210,3 +465,61 @@ impl Default for SessionManager {
         Self::new(20, 3600)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: &str, platform: SpokeType) -> ClientInfo {
+        ClientInfo {
+            client_id: id.to_string(),
+            platform,
+            display_name: None,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_link_and_switch_across_spokes() {
+        assert_eq!(SessionCommand::parse("/link"), Some(SessionCommand::IssueLinkCode));
+        assert_eq!(SessionCommand::parse("/LINK ab12"), Some(SessionCommand::Link("ab12".into())));
+        assert_eq!(SessionCommand::parse("/switch"), Some(SessionCommand::ListSessions));
+        assert_eq!(SessionCommand::parse("/switch s1"), Some(SessionCommand::Switch("s1".into())));
+        assert_eq!(SessionCommand::parse("/link me to the docs"), None);
+        assert_eq!(SessionCommand::parse("hello"), None);
+
+        let manager = SessionManager::default();
+        let phone = manager.get_or_create("+8613800000000", client("+8613800000000", SpokeType::WhatsApp)).await;
+        let web = manager.get_or_create("browser_1", client("browser_1", SpokeType::Web)).await;
+        assert_ne!(phone, web);
+
+        let code = manager.issue_link_code("+8613800000000").await;
+        assert_eq!(
+            manager.link("browser_1", "nope", client("browser_1", SpokeType::Web)).await,
+            Err(SessionLinkError::InvalidCode)
+        );
+        let linked = manager
+            .link("browser_1", &code.to_lowercase(), client("browser_1", SpokeType::Web))
+            .await
+            .unwrap();
+        assert_eq!(linked, phone);
+        assert_eq!(manager.get_user_session("browser_1").await, Some(phone.clone()));
+        // 链接码只能兑换一次
+        assert!(manager.link("browser_2", &code, client("browser_2", SpokeType::Web)).await.is_err());
+
+        // 链接前 Web 身份的会话仍归同一用户，可以切回
+        assert_eq!(manager.user_sessions("+8613800000000").await.len(), 2);
+        let switched = manager
+            .switch("+8613800000000", &web, client("+8613800000000", SpokeType::WhatsApp))
+            .await
+            .unwrap();
+        assert_eq!(switched, web);
+        assert_eq!(manager.get_user_session("browser_1").await, Some(web));
+
+        let other = manager.get_or_create("mallory", client("mallory", SpokeType::Discord)).await;
+        assert_eq!(
+            manager.switch("browser_1", &other, client("browser_1", SpokeType::Web)).await,
+            Err(SessionLinkError::UnknownSession)
+        );
+    }
+}
@@ -7,8 +7,9 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use tokio_util::sync::CancellationToken;
 
-use super::message::{ClientInfo, SessionStatus, SpokeType};
-use super::session::SessionId;
+use super::message::{ClientInfo, GatewayMessage, SessionStatus, SpokeType};
+use super::outbox::OutboxEntry;
+use super::session::{SessionId, SessionLinkError, SessionSummary};
 use crate::memory::Message;
 use crate::react::ContextManager;
 
@@ -53,6 +54,36 @@ pub trait SessionStore: Send + Sync {
 
     /// 获取会话历史
     async fn get_history(&self, session_id: &str, limit: Option<usize>) -> Vec<(String, String)>;
+
+    /// 列出会话摘要（最近活跃在前）
+    async fn list_sessions(&self) -> Vec<SessionSummary>;
+
+    /// 为用户生成跨平台链接码
+    async fn issue_link_code(&self, user_id: &str) -> String;
+
+    /// 兑换链接码：把该平台身份链接到生成者，返回接续的会话 ID
+    async fn link(&self, user_id: &str, code: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError>;
+
+    /// 切换到用户名下的另一个会话
+    async fn switch(&self, user_id: &str, session_id: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError>;
+
+    /// 列出用户名下的会话（最近活跃在前）
+    async fn list_user_sessions(&self, user_id: &str) -> Vec<SessionSummary>;
+
+    /// 把通知放入用户的发件箱（pending）
+    async fn enqueue_outbox(&self, user_id: &str, message: GatewayMessage) -> OutboxEntry;
+
+    /// 用户尚未送达的通知（入队顺序）
+    async fn pending_outbox(&self, user_id: &str) -> Vec<OutboxEntry>;
+
+    /// 用户最近 limit 条通知及投递状态
+    async fn recent_outbox(&self, user_id: &str, limit: usize) -> Vec<OutboxEntry>;
+
+    /// 标记已送达，状态未变化时返回 None
+    async fn mark_delivered(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry>;
+
+    /// 标记已读，状态未变化时返回 None
+    async fn mark_read(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry>;
 }
 
 /// 内存会话存储（包装 SessionManager）
@@ -133,6 +164,46 @@ impl SessionStore for MemorySessionStore {
             limited.iter().map(|m| (format!("{:?}", m.role), m.content.clone())).collect()
         }).await.unwrap_or_default()
     }
+
+    async fn list_sessions(&self) -> Vec<SessionSummary> {
+        self.inner.list().await
+    }
+
+    async fn issue_link_code(&self, user_id: &str) -> String {
+        self.inner.issue_link_code(user_id).await
+    }
+
+    async fn link(&self, user_id: &str, code: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        self.inner.link(user_id, code, client).await
+    }
+
+    async fn switch(&self, user_id: &str, session_id: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        self.inner.switch(user_id, session_id, client).await
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Vec<SessionSummary> {
+        self.inner.user_sessions(user_id).await
+    }
+
+    async fn enqueue_outbox(&self, user_id: &str, message: GatewayMessage) -> OutboxEntry {
+        self.inner.enqueue_outbox(user_id, message).await
+    }
+
+    async fn pending_outbox(&self, user_id: &str) -> Vec<OutboxEntry> {
+        self.inner.pending_outbox(user_id).await
+    }
+
+    async fn recent_outbox(&self, user_id: &str, limit: usize) -> Vec<OutboxEntry> {
+        self.inner.recent_outbox(user_id, limit).await
+    }
+
+    async fn mark_delivered(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        self.inner.mark_delivered(user_id, message_id).await
+    }
+
+    async fn mark_read(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        self.inner.mark_read(user_id, message_id).await
+    }
 }
 
 /// 持久化会话存储（包装 PersistentSessionManager）
@@ -217,6 +288,46 @@ impl SessionStore for PersistentSessionStore {
             limited.iter().map(|m| (format!("{:?}", m.role), m.content.clone())).collect()
         }).await.unwrap_or_default()
     }
+
+    async fn list_sessions(&self) -> Vec<SessionSummary> {
+        self.inner.list().await
+    }
+
+    async fn issue_link_code(&self, user_id: &str) -> String {
+        self.inner.issue_link_code(user_id).await
+    }
+
+    async fn link(&self, user_id: &str, code: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        self.inner.link(user_id, code, client).await
+    }
+
+    async fn switch(&self, user_id: &str, session_id: &str, client: ClientInfo) -> Result<SessionId, SessionLinkError> {
+        self.inner.switch(user_id, session_id, client).await
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Vec<SessionSummary> {
+        self.inner.user_sessions(user_id).await
+    }
+
+    async fn enqueue_outbox(&self, user_id: &str, message: GatewayMessage) -> OutboxEntry {
+        self.inner.enqueue_outbox(user_id, message).await
+    }
+
+    async fn pending_outbox(&self, user_id: &str) -> Vec<OutboxEntry> {
+        self.inner.pending_outbox(user_id).await
+    }
+
+    async fn recent_outbox(&self, user_id: &str, limit: usize) -> Vec<OutboxEntry> {
+        self.inner.recent_outbox(user_id, limit).await
+    }
+
+    async fn mark_delivered(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        self.inner.mark_delivered(user_id, message_id).await
+    }
+
+    async fn mark_read(&self, user_id: &str, message_id: &str) -> Option<OutboxEntry> {
+        self.inner.mark_read(user_id, message_id).await
+    }
 }
 
 /// 创建会话存储
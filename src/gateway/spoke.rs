@@ -89,6 +89,8 @@ pub enum CapabilitySpokeType {
     ApiPlugin { name: String },
     /// 自动化脚本
     Script { path: String },
+    /// 联邦的远端 Hub
+    RemoteHub { name: String },
 }
 
 /// 能力端点 trait
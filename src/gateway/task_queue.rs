@@ -14,6 +14,7 @@ use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 
 /// 任务 ID
 pub type TaskId = String;
@@ -136,6 +137,8 @@ pub struct TaskQueue {
     pending_tx: mpsc::UnboundedSender<TaskId>,
     /// 通知发送器
     notification_tx: mpsc::UnboundedSender<TaskNotification>,
+    /// 执行中任务的取消令牌（cancel 时触发）
+    running: std::sync::Mutex<HashMap<TaskId, CancellationToken>>,
     /// SQLite 连接池（可选）
     #[cfg(feature = "async-sqlite")]
     pool: Option<sqlx::sqlite::SqlitePool>,
@@ -153,6 +156,7 @@ impl TaskQueue {
                 user_tasks: RwLock::new(HashMap::new()),
                 pending_tx,
                 notification_tx,
+                running: std::sync::Mutex::new(HashMap::new()),
                 #[cfg(feature = "async-sqlite")]
                 pool: None,
             },
@@ -209,6 +213,7 @@ impl TaskQueue {
             user_tasks: RwLock::new(HashMap::new()),
             pending_tx,
             notification_tx,
+            running: std::sync::Mutex::new(HashMap::new()),
             pool: Some(pool),
         };
 
@@ -217,7 +222,7 @@ impl TaskQueue {
         Ok((queue, pending_rx, notification_rx))
     }
 
-    /// 从数据库恢复待执行任务
+    /// 从数据库恢复任务：已结束的任务供查询，中断的 Running 任务重新排队
     #[cfg(feature = "async-sqlite")]
     async fn restore_pending_tasks(&self) -> Result<(), sqlx::Error> {
         let pool = match &self.pool {
@@ -229,7 +234,6 @@ impl TaskQueue {
             "SELECT id, user_id, session_id, instruction, status, priority, result, error,
                     created_at, started_at, completed_at, estimated_duration, progress, metadata
              FROM background_tasks
-             WHERE status IN ('Pending', 'Running')
              ORDER BY priority DESC, created_at ASC"
         )
         .fetch_all(pool)
@@ -241,7 +245,7 @@ impl TaskQueue {
         for row in rows {
             use sqlx::Row;
             
-            let task = BackgroundTask {
+            let mut task = BackgroundTask {
                 id: row.get("id"),
                 user_id: row.get("user_id"),
                 session_id: row.get("session_id"),
@@ -261,7 +265,13 @@ impl TaskQueue {
 
             let task_id = task.id.clone();
             let user_id = task.user_id.clone();
-            
+
+            // 上次进程退出时仍在执行的任务从头再来
+            if task.status == TaskStatus::Running {
+                task.status = TaskStatus::Pending;
+                task.started_at = None;
+                task.progress = 0;
+            }
             if task.status == TaskStatus::Pending {
                 let _ = self.pending_tx.send(task_id.clone());
             }
@@ -328,10 +338,10 @@ impl TaskQueue {
         Ok(())
     }
 
-    /// 更新任务状态
-    pub async fn update_status(&self, task_id: &str, status: TaskStatus) {
+    /// 更新任务状态；已结束的任务不再变化，返回是否更新
+    pub async fn update_status(&self, task_id: &str, status: TaskStatus) -> bool {
         let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(task_id) {
+        if let Some(task) = tasks.get_mut(task_id).filter(|t| !t.is_finished()) {
             task.status = status;
             
             match status {
@@ -372,13 +382,15 @@ impl TaskQueue {
                     .await;
                 });
             }
+            return true;
         }
+        false
     }
 
-    /// 设置任务结果
+    /// 设置任务结果（任务已结束时忽略，如执行中被取消）
     pub async fn set_result(&self, task_id: &str, result: String) {
         let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(task_id) {
+        if let Some(task) = tasks.get_mut(task_id).filter(|t| !t.is_finished()) {
             task.result = Some(result.clone());
             task.status = TaskStatus::Completed;
             task.completed_at = Some(chrono::Utc::now().timestamp_millis());
@@ -411,10 +423,10 @@ impl TaskQueue {
         }
     }
 
-    /// 设置任务错误
+    /// 设置任务错误（任务已结束时忽略）
     pub async fn set_error(&self, task_id: &str, error: String) {
         let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(task_id) {
+        if let Some(task) = tasks.get_mut(task_id).filter(|t| !t.is_finished()) {
             task.error = Some(error.clone());
             task.status = TaskStatus::Failed;
             task.completed_at = Some(chrono::Utc::now().timestamp_millis());
@@ -483,17 +495,46 @@ impl TaskQueue {
             .collect()
     }
 
-    /// 取消任务
+    /// 取消任务：与其他结束状态一样持久化并发出通知；任务不存在或已结束时返回 false。
+    /// 执行中的任务通过 cancel_token 领取的令牌中止，之后的 set_result / set_error 会被忽略
     pub async fn cancel(&self, task_id: &str) -> bool {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(task_id) {
-            if !task.is_finished() {
-                task.status = TaskStatus::Cancelled;
-                task.completed_at = Some(chrono::Utc::now().timestamp_millis());
-                return true;
-            }
+        let cancelled = self.update_status(task_id, TaskStatus::Cancelled).await;
+        if let Some(token) = self.lock_running().get(task_id) {
+            token.cancel();
         }
-        false
+        cancelled
+    }
+
+    /// 执行方领取任务的取消令牌：cancel(task_id) 时触发；TaskExecutor 在任务开始前登记、结束后释放，
+    /// 执行中途才领取的也能拿到已触发的令牌
+    pub fn cancel_token(&self, task_id: &str) -> CancellationToken {
+        self.lock_running().entry(task_id.to_string()).or_default().clone()
+    }
+
+    fn release_cancel_token(&self, task_id: &str) {
+        self.lock_running().remove(task_id);
+    }
+
+    fn lock_running(&self) -> std::sync::MutexGuard<'_, HashMap<TaskId, CancellationToken>> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 移除单个任务（内存与数据库），返回被移除的任务
+    pub async fn remove(&self, task_id: &str) -> Option<BackgroundTask> {
+        let task = self.tasks.write().await.remove(task_id)?;
+        if let Some(ids) = self.user_tasks.write().await.get_mut(&task.user_id) {
+            ids.retain(|id| id != task_id);
+        }
+
+        #[cfg(feature = "async-sqlite")]
+        if let Some(pool) = &self.pool {
+            let _ = sqlx::query("DELETE FROM background_tasks WHERE id = ?")
+                .bind(task_id)
+                .execute(pool)
+                .await;
+        }
+
+        Some(task)
     }
 
     /// 清理已完成的旧任务
@@ -593,9 +634,12 @@ impl TaskExecutor {
                     _ => return,
                 };
 
+                queue.cancel_token(&task_id);
                 queue.update_status(&task_id, TaskStatus::Running).await;
 
-                match process_fn(task).await {
+                let result = process_fn(task).await;
+                queue.release_cancel_token(&task_id);
+                match result {
                     Ok(result) => {
                         queue.set_result(&task_id, result).await;
                     }
@@ -634,5 +678,63 @@ mod tests {
         let notification = notification_rx.try_recv().unwrap();
         assert_eq!(notification.task_id, task_id);
         assert_eq!(notification.status, TaskStatus::Completed);
+        assert!(!queue.cancel(&task_id).await);
+
+        // 取消后发出通知，执行方随后写入的结果被忽略
+        let task_id = queue.submit(BackgroundTask::new("user_123".to_string(), "Long job".to_string())).await;
+        assert!(queue.cancel(&task_id).await);
+        assert_eq!(notification_rx.try_recv().unwrap().status, TaskStatus::Cancelled);
+        queue.set_result(&task_id, "late".to_string()).await;
+        let task = queue.get(&task_id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Cancelled);
+        assert!(task.result.is_none());
+        assert!(notification_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_running_task() {
+        let (queue, pending_rx, mut notification_rx) = TaskQueue::new();
+        let queue = Arc::new(queue);
+        let runner = Arc::clone(&queue);
+        tokio::spawn(TaskExecutor::new(Arc::clone(&queue), 2).start(pending_rx, move |task| {
+            let token = runner.cancel_token(&task.id);
+            Box::pin(async move {
+                token.cancelled().await;
+                Err("cancelled".to_string())
+            })
+        }));
+
+        let task_id = queue.submit(BackgroundTask::new("user_123".to_string(), "Long job".to_string())).await;
+        while queue.get(&task_id).await.unwrap().status != TaskStatus::Running {
+            tokio::task::yield_now().await;
+        }
+        assert!(queue.cancel(&task_id).await);
+        assert_eq!(notification_rx.recv().await.unwrap().status, TaskStatus::Cancelled);
+        // 执行方收到取消后结束，令牌随之释放
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while queue.lock_running().contains_key(&task_id) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(queue.get(&task_id).await.unwrap().status, TaskStatus::Cancelled);
+    }
+
+    #[cfg(feature = "async-sqlite")]
+    #[tokio::test]
+    async fn test_task_queue_restores_from_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("background_tasks.db");
+        let task_id = {
+            let (queue, _pending_rx, _notification_rx) = TaskQueue::with_persistence(&db).await.unwrap();
+            queue.submit(BackgroundTask::new("user_123".to_string(), "Write a report".to_string())).await
+        };
+
+        let (queue, mut pending_rx, _notification_rx) = TaskQueue::with_persistence(&db).await.unwrap();
+        assert_eq!(pending_rx.try_recv().unwrap(), task_id);
+        let tasks = queue.get_user_tasks("user_123").await;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].status, TaskStatus::Pending);
     }
 }
@@ -0,0 +1,585 @@
+//! Discord 集成：作为 Gateway 的通讯端点（Spoke）
+//!
+//! 启动时注册 `/ask` 与 `/task` 斜杠命令，经 Discord Gateway（WebSocket 长连接）接收交互，无需公网 Webhook 域名。
+//! 收到交互后先回复「思考中」（deferred），再通过编辑原始消息流式更新回复；超出 2000 字符的部分以 follow-up 消息补发。
+//! 每个 Discord 线程映射为一个 Gateway 会话（不在线程中时按频道），同一线程内的多次提问共享上下文。
+//!
+//! 环境变量：
+//! - DISCORD_BOT_TOKEN：Bot Token
+//! - DISCORD_APPLICATION_ID：应用 ID
+//! - DISCORD_GUILD_ID：可选，设置后命令注册到该服务器（即时生效），否则注册为全局命令
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::gateway::{ClientInfo, CommunicationSpoke, GatewayMessage, MessageType, SpokeAdapter, SpokeType};
+
+const API_BASE: &str = "https://discord.com/api/v10";
+
+/// Discord 单条消息的最大字符数
+pub const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// 流式回复时两次编辑消息的最小间隔（避免触发速率限制）
+const EDIT_INTERVAL: Duration = Duration::from_millis(1200);
+
+/// 断线后的重连等待
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// 线程频道类型：公告线程、公开线程、私有线程
+const THREAD_CHANNEL_TYPES: [u64; 3] = [10, 11, 12];
+
+/// Discord 连接配置
+#[derive(Debug, Clone)]
+pub struct DiscordConfig {
+    pub bot_token: String,
+    pub application_id: String,
+    /// 设置后命令只注册到该服务器
+    pub guild_id: Option<String>,
+}
+
+impl DiscordConfig {
+    /// 从环境变量读取；未设置 DISCORD_BOT_TOKEN 或 DISCORD_APPLICATION_ID 时返回 None
+    pub fn from_env() -> Option<Self> {
+        let var = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        Some(Self {
+            bot_token: var("DISCORD_BOT_TOKEN")?,
+            application_id: var("DISCORD_APPLICATION_ID")?,
+            guild_id: var("DISCORD_GUILD_ID"),
+        })
+    }
+}
+
+/// 支持的斜杠命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlashCommand {
+    /// /ask question：在当前线程的会话中提问
+    Ask,
+    /// /task instruction：提交后台任务
+    Task,
+}
+
+/// 从 INTERACTION_CREATE 事件解析出的斜杠命令调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandInvocation {
+    pub command: SlashCommand,
+    pub text: String,
+    pub interaction_id: String,
+    pub token: String,
+    pub channel_id: String,
+    pub in_thread: bool,
+    pub user_id: String,
+    pub user_name: Option<String>,
+}
+
+impl CommandInvocation {
+    /// 对应的 Gateway 会话键（同时作为 client_id）
+    pub fn session_key(&self) -> String {
+        session_key(&self.channel_id, self.in_thread)
+    }
+}
+
+/// 线程映射为 `discord:thread:{id}`，普通频道映射为 `discord:channel:{id}`
+pub fn session_key(channel_id: &str, in_thread: bool) -> String {
+    if in_thread {
+        format!("discord:thread:{}", channel_id)
+    } else {
+        format!("discord:channel:{}", channel_id)
+    }
+}
+
+/// 注册的斜杠命令定义（PUT 覆盖式注册）
+pub fn command_definitions() -> Value {
+    let command = |name: &str, description: &str, option: &str, option_description: &str| {
+        json!({
+            "name": name,
+            "type": 1,
+            "description": description,
+            "options": [{
+                "name": option,
+                "description": option_description,
+                "type": 3,
+                "required": true
+            }]
+        })
+    };
+    json!([
+        command("ask", "Ask the assistant in this thread", "question", "Your question"),
+        command(
+            "task",
+            "Run a task in the background",
+            "instruction",
+            "What the task should do"
+        ),
+    ])
+}
+
+/// 解析交互事件；非斜杠命令或不支持的命令返回 None
+pub fn parse_interaction(d: &Value) -> Option<CommandInvocation> {
+    if d.get("type").and_then(|v| v.as_u64()) != Some(2) {
+        return None;
+    }
+    let data = d.get("data")?;
+    let (command, option) = match data.get("name").and_then(|v| v.as_str())? {
+        "ask" => (SlashCommand::Ask, "question"),
+        "task" => (SlashCommand::Task, "instruction"),
+        _ => return None,
+    };
+    let text = data
+        .get("options")
+        .and_then(|v| v.as_array())?
+        .iter()
+        .find(|o| o.get("name").and_then(|v| v.as_str()) == Some(option))
+        .and_then(|o| o.get("value"))
+        .and_then(|v| v.as_str())?
+        .trim()
+        .to_string();
+    if text.is_empty() {
+        return None;
+    }
+    let str_field = |v: &Value, key: &str| v.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    // 服务器内的交互带 member.user，私信中只有 user
+    let user = d.get("member").and_then(|m| m.get("user")).or_else(|| d.get("user"))?;
+    let in_thread = d
+        .get("channel")
+        .and_then(|c| c.get("type"))
+        .and_then(|v| v.as_u64())
+        .is_some_and(|t| THREAD_CHANNEL_TYPES.contains(&t));
+    Some(CommandInvocation {
+        command,
+        text,
+        interaction_id: str_field(d, "id")?,
+        token: str_field(d, "token")?,
+        channel_id: str_field(d, "channel_id")?,
+        in_thread,
+        user_id: str_field(user, "id")?,
+        user_name: str_field(user, "global_name").or_else(|| str_field(user, "username")),
+    })
+}
+
+/// 按 Discord 长度限制切分消息，尽量在换行处断开
+pub fn split_message(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest: Vec<char> = text.chars().collect();
+    while rest.len() > max_chars {
+        let cut = rest[..max_chars]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .filter(|pos| *pos > max_chars / 2)
+            .map(|pos| pos + 1)
+            .unwrap_or(max_chars);
+        chunks.push(rest[..cut].iter().collect::<String>().trim_end().to_string());
+        rest.drain(..cut);
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest.into_iter().collect());
+    }
+    chunks
+}
+
+/// 流式预览：超长时截断并加省略号
+fn preview(text: &str) -> String {
+    if text.chars().count() <= MAX_MESSAGE_CHARS {
+        return text.to_string();
+    }
+    let mut s: String = text.chars().take(MAX_MESSAGE_CHARS - 1).collect();
+    s.push('…');
+    s
+}
+
+/// 正在回复的交互（按会话键索引，同一线程同时只跟踪最近一次交互）
+struct PendingReply {
+    token: String,
+    buffer: String,
+    last_edit: Option<Instant>,
+}
+
+/// Discord Spoke
+pub struct DiscordSpoke {
+    config: DiscordConfig,
+    http: reqwest::Client,
+    replies: Arc<Mutex<HashMap<String, PendingReply>>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl DiscordSpoke {
+    pub fn new(config: DiscordConfig) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            replies: Arc::new(Mutex::new(HashMap::new())),
+            shutdown,
+        }
+    }
+
+    fn auth(&self) -> String {
+        format!("Bot {}", self.config.bot_token)
+    }
+
+    /// 覆盖式注册 /ask 与 /task
+    async fn register_commands(&self) -> Result<(), String> {
+        let url = match &self.config.guild_id {
+            Some(guild) => format!(
+                "{}/applications/{}/guilds/{}/commands",
+                API_BASE, self.config.application_id, guild
+            ),
+            None => format!("{}/applications/{}/commands", API_BASE, self.config.application_id),
+        };
+        let resp = self
+            .http
+            .put(url)
+            .header("Authorization", self.auth())
+            .json(&command_definitions())
+            .send()
+            .await
+            .map_err(|e| format!("register commands: {}", e))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("register commands: {} {}", status, body));
+        }
+        Ok(())
+    }
+
+    async fn gateway_url(&self) -> Result<String, String> {
+        let resp: Value = self
+            .http
+            .get(format!("{}/gateway/bot", API_BASE))
+            .header("Authorization", self.auth())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let url = resp
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("gateway/bot: {}", resp))?;
+        Ok(format!("{}/?v=10&encoding=json", url))
+    }
+
+    /// 编辑交互的原始消息
+    async fn edit_original(&self, token: &str, content: &str) -> Result<(), String> {
+        let url = format!(
+            "{}/webhooks/{}/{}/messages/@original",
+            API_BASE, self.config.application_id, token
+        );
+        self.webhook_request(self.http.patch(url), content).await
+    }
+
+    /// 追加 follow-up 消息
+    async fn follow_up(&self, token: &str, content: &str) -> Result<(), String> {
+        let url = format!("{}/webhooks/{}/{}", API_BASE, self.config.application_id, token);
+        self.webhook_request(self.http.post(url), content).await
+    }
+
+    async fn webhook_request(&self, req: reqwest::RequestBuilder, content: &str) -> Result<(), String> {
+        let resp = req
+            .json(&json!({ "content": content }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("discord webhook: {}", resp.status()));
+        }
+        Ok(())
+    }
+
+    /// 确认交互：回复 deferred（客户端显示「思考中」），随后通过编辑原始消息给出内容
+    async fn defer(&self, invocation: &CommandInvocation) -> Result<(), String> {
+        let url = format!(
+            "{}/interactions/{}/{}/callback",
+            API_BASE, invocation.interaction_id, invocation.token
+        );
+        let resp = self
+            .http
+            .post(url)
+            .json(&json!({ "type": 5 }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("interaction callback: {}", resp.status()));
+        }
+        Ok(())
+    }
+
+    async fn handle_interaction(
+        &self,
+        invocation: CommandInvocation,
+        message_tx: &mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+    ) {
+        if let Err(e) = self.defer(&invocation).await {
+            tracing::warn!("discord defer failed: {}", e);
+            return;
+        }
+        let client_id = invocation.session_key();
+        self.replies.lock().await.insert(
+            client_id.clone(),
+            PendingReply {
+                token: invocation.token.clone(),
+                buffer: String::new(),
+                last_edit: None,
+            },
+        );
+        let info = ClientInfo {
+            client_id,
+            platform: SpokeType::Discord,
+            display_name: invocation.user_name.clone(),
+            metadata: Some(json!({
+                "discord_user_id": invocation.user_id,
+                "channel_id": invocation.channel_id,
+                "in_thread": invocation.in_thread,
+            })),
+        };
+        let message = match invocation.command {
+            SlashCommand::Ask => MessageType::UserMessage {
+                content: invocation.text,
+                assistant_id: None,
+                model: None,
+            },
+            SlashCommand::Task => MessageType::SubmitTask {
+                instruction: invocation.text,
+                priority: None,
+            },
+        };
+        let _ = message_tx.send((info, GatewayMessage::new(None, message)));
+    }
+
+    /// 一次 Gateway 连接的生命周期；返回 true 表示收到停止信号，false 表示需要重连
+    async fn run_connection(
+        &self,
+        message_tx: &mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+        shutdown_rx: &mut watch::Receiver<bool>,
+    ) -> Result<bool, String> {
+        let url = self.gateway_url().await?;
+        let (ws, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .map_err(|e| format!("connect gateway: {}", e))?;
+        let (mut ws_tx, mut ws_rx) = ws.split();
+
+        let mut sequence: Option<u64> = None;
+        let mut heartbeat = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        let _ = ws_tx.send(WsMessage::Close(None)).await;
+                        return Ok(true);
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    let beat = json!({ "op": 1, "d": sequence }).to_string();
+                    ws_tx.send(WsMessage::Text(beat)).await.map_err(|e| e.to_string())?;
+                }
+                msg = ws_rx.next() => {
+                    let text = match msg {
+                        Some(Ok(WsMessage::Text(text))) => text,
+                        Some(Ok(WsMessage::Close(frame))) => {
+                            tracing::info!("discord gateway closed: {:?}", frame);
+                            return Ok(false);
+                        }
+                        Some(Ok(_)) => continue,
+                        Some(Err(e)) => return Err(e.to_string()),
+                        None => return Ok(false),
+                    };
+                    let Ok(payload) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+                    if let Some(s) = payload.get("s").and_then(|v| v.as_u64()) {
+                        sequence = Some(s);
+                    }
+                    match payload.get("op").and_then(|v| v.as_u64()) {
+                        // Hello：按服务端给的间隔心跳，并发送 Identify（交互事件不需要任何 intent）
+                        Some(10) => {
+                            let interval = payload["d"]["heartbeat_interval"].as_u64().unwrap_or(41_250);
+                            heartbeat = tokio::time::interval(Duration::from_millis(interval));
+                            let identify = json!({
+                                "op": 2,
+                                "d": {
+                                    "token": self.config.bot_token,
+                                    "intents": 0,
+                                    "properties": { "os": std::env::consts::OS, "browser": "bee", "device": "bee" }
+                                }
+                            });
+                            ws_tx.send(WsMessage::Text(identify.to_string())).await.map_err(|e| e.to_string())?;
+                        }
+                        Some(1) => heartbeat.reset_immediately(),
+                        // Reconnect / Invalid Session：重新连接
+                        Some(7) | Some(9) => return Ok(false),
+                        Some(0) if payload.get("t").and_then(|v| v.as_str()) == Some("INTERACTION_CREATE") => {
+                            if let Some(invocation) = parse_interaction(&payload["d"]) {
+                                self.handle_interaction(invocation, message_tx).await;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run(self: Arc<Self>, message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) {
+        let mut shutdown_rx = self.shutdown.subscribe();
+        loop {
+            match self.run_connection(&message_tx, &mut shutdown_rx).await {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!("discord gateway error: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+            if *shutdown_rx.borrow() {
+                break;
+            }
+        }
+    }
+
+    /// 把最终回复写入原始消息，超长部分以 follow-up 补发
+    async fn finish_reply(&self, token: &str, content: &str) -> Result<(), String> {
+        let mut chunks = split_message(content, MAX_MESSAGE_CHARS).into_iter();
+        let first = chunks
+            .next()
+            .filter(|c| !c.is_empty())
+            .unwrap_or_else(|| "（无回复）".to_string());
+        self.edit_original(token, &first).await?;
+        for chunk in chunks {
+            self.follow_up(token, &chunk).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SpokeAdapter for DiscordSpoke {
+    fn spoke_type(&self) -> SpokeType {
+        SpokeType::Discord
+    }
+
+    async fn start(&self, message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) -> Result<(), String> {
+        self.register_commands().await?;
+        let spoke = Arc::new(Self {
+            config: self.config.clone(),
+            http: self.http.clone(),
+            replies: Arc::clone(&self.replies),
+            shutdown: self.shutdown.clone(),
+        });
+        tokio::spawn(spoke.run(message_tx));
+        tracing::info!("Discord spoke started (application {})", self.config.application_id);
+        Ok(())
+    }
+
+    async fn send(&self, client_id: &str, message: GatewayMessage) -> Result<(), String> {
+        match message.message {
+            MessageType::ResponseChunk { content, .. } => {
+                let edit = {
+                    let mut replies = self.replies.lock().await;
+                    let Some(reply) = replies.get_mut(client_id) else {
+                        return Ok(());
+                    };
+                    reply.buffer.push_str(&content);
+                    if reply.last_edit.is_some_and(|t| t.elapsed() < EDIT_INTERVAL) {
+                        None
+                    } else {
+                        reply.last_edit = Some(Instant::now());
+                        Some((reply.token.clone(), preview(&reply.buffer)))
+                    }
+                };
+                if let Some((token, content)) = edit {
+                    self.edit_original(&token, &content).await?;
+                }
+            }
+            MessageType::ResponseEnd { full_content, .. } => {
+                if let Some(reply) = self.replies.lock().await.remove(client_id) {
+                    self.finish_reply(&reply.token, &full_content).await?;
+                }
+            }
+            MessageType::TaskSubmitted { task_id } => {
+                if let Some(reply) = self.replies.lock().await.remove(client_id) {
+                    let content = format!("已提交后台任务 {}，完成后结果会写回本线程的会话。", task_id);
+                    self.edit_original(&reply.token, &content).await?;
+                }
+            }
+            MessageType::Error { message, .. } => {
+                if let Some(reply) = self.replies.lock().await.remove(client_id) {
+                    self.edit_original(&reply.token, &preview(&format!("出错了：{}", message)))
+                        .await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl CommunicationSpoke for DiscordSpoke {
+    fn max_message_length(&self) -> Option<usize> {
+        Some(MAX_MESSAGE_CHARS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interaction_and_split() {
+        let event = json!({
+            "id": "1001",
+            "type": 2,
+            "token": "tok",
+            "channel_id": "555",
+            "channel": { "id": "555", "type": 11 },
+            "member": { "user": { "id": "42", "username": "alice", "global_name": "Alice" } },
+            "data": { "name": "ask", "options": [{ "name": "question", "type": 3, "value": " what changed? " }] }
+        });
+        let inv = parse_interaction(&event).unwrap();
+        assert_eq!(inv.command, SlashCommand::Ask);
+        assert_eq!(inv.text, "what changed?");
+        assert_eq!(inv.session_key(), "discord:thread:555");
+        assert_eq!(inv.user_name.as_deref(), Some("Alice"));
+
+        // 私信中的 /task：不在线程内，按频道映射会话
+        let dm = json!({
+            "id": "1002", "type": 2, "token": "tok2", "channel_id": "777",
+            "channel": { "id": "777", "type": 1 },
+            "user": { "id": "42", "username": "alice" },
+            "data": { "name": "task", "options": [{ "name": "instruction", "value": "nightly report" }] }
+        });
+        let inv = parse_interaction(&dm).unwrap();
+        assert_eq!(inv.command, SlashCommand::Task);
+        assert_eq!(inv.session_key(), "discord:channel:777");
+        // 非斜杠命令（如按钮）忽略
+        assert!(parse_interaction(&json!({"type": 3})).is_none());
+
+        let commands = command_definitions();
+        let names: Vec<&str> = commands
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["ask", "task"]);
+
+        let long = format!("{}\n{}", "a".repeat(1500), "b".repeat(1500));
+        let chunks = split_message(&long, MAX_MESSAGE_CHARS);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], "a".repeat(1500));
+        assert!(chunks.iter().all(|c| c.chars().count() <= MAX_MESSAGE_CHARS));
+        assert_eq!(split_message("", MAX_MESSAGE_CHARS), vec![String::new()]);
+        assert_eq!(preview(&"x".repeat(2500)).chars().count(), MAX_MESSAGE_CHARS);
+    }
+}
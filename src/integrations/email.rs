@@ -0,0 +1,379 @@
+//! 邮件接入端（Email Spoke，需 email 与 gateway feature）
+//!
+//! 定期轮询 [tools.email.spoke] 指定账户的收件箱，把未读邮件转为网关消息：同一邮件线程（References 链首的
+//! Message-ID）对应同一个会话，回复经 SMTP 发回该线程（带 In-Reply-To / References，邮件客户端中保持成串）。
+//! 主题以 task_prefix 开头的邮件作为后台任务提交，任务完成后结果同样回复到原线程，适合与长时间运行的任务异步交互。
+//!
+//! 回复只能发往账户的 allowed_recipients，因此也只处理来自这些地址的邮件；其余邮件保持未读、不再重复检查。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use serde_json::json;
+use tokio::sync::{mpsc, watch, Mutex};
+
+use crate::config::{EmailAccountSection, EmailSection};
+use crate::gateway::{ClientInfo, CommunicationSpoke, GatewayMessage, MessageType, SpokeAdapter, SpokeType};
+use crate::tools::email::{recipient_allowed, ParsedEmail};
+use crate::tools::{EmailTool, ToolError};
+
+/// 邮件线程：回复收件人、主题与已知的 Message-ID 链（含 bee 发出的回复）
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MailThread {
+    to: String,
+    subject: String,
+    references: Vec<String>,
+}
+
+/// 线程根：References 链首，新线程为邮件自身的 Message-ID
+fn thread_root(email: &ParsedEmail) -> String {
+    email
+        .references
+        .first()
+        .or(email.message_id.as_ref())
+        .cloned()
+        .unwrap_or_else(|| format!("uid-{}", email.uid))
+}
+
+/// 回复主题：已有 Re: 前缀时不重复添加
+fn reply_subject(subject: &str) -> String {
+    if subject.trim().to_ascii_lowercase().starts_with("re:") {
+        subject.trim().to_string()
+    } else {
+        format!("Re: {}", subject.trim())
+    }
+}
+
+/// 去掉回复中引用的原文：从第一行 `>` 引用（或 Outlook 的 Original Message 分隔线）起截断，并去掉其前的「… wrote:」行
+fn strip_quoted(body: &str) -> String {
+    let mut kept: Vec<&str> = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('>') || trimmed.starts_with("-----Original Message-----") {
+            if kept
+                .last()
+                .is_some_and(|l| l.trim_end().ends_with("wrote:") || l.trim_end().ends_with("写道："))
+            {
+                kept.pop();
+            }
+            break;
+        }
+        kept.push(line);
+    }
+    kept.join("\n").trim().to_string()
+}
+
+/// 邮件转为网关消息：主题带任务前缀时提交后台任务，否则作为对话消息（新线程带上主题）
+fn inbound_message(email: &ParsedEmail, task_prefix: &str) -> MessageType {
+    let body = strip_quoted(&email.body);
+    let subject = email.subject.trim();
+    let prefix_len = task_prefix.len();
+    let is_task = !task_prefix.is_empty()
+        && subject
+            .get(..prefix_len)
+            .is_some_and(|head| head.eq_ignore_ascii_case(task_prefix));
+    if is_task {
+        let title = subject[prefix_len..].trim();
+        let instruction = match (title.is_empty(), body.is_empty()) {
+            (false, false) => format!("{}\n\n{}", title, body),
+            (false, true) => title.to_string(),
+            _ => body,
+        };
+        return MessageType::SubmitTask {
+            instruction,
+            priority: None,
+        };
+    }
+    let content = if email.references.is_empty() || body.is_empty() {
+        format!("{}\n\n{}", subject, body).trim().to_string()
+    } else {
+        body
+    };
+    MessageType::UserMessage {
+        content,
+        assistant_id: None,
+        model: None,
+    }
+}
+
+/// 邮件接入端
+pub struct EmailSpoke {
+    account_name: String,
+    account: EmailAccountSection,
+    poll: Duration,
+    task_prefix: String,
+    /// client_id（email:<账户>:<线程根>）-> 线程
+    threads: Arc<Mutex<HashMap<String, MailThread>>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl EmailSpoke {
+    /// 按 [tools.email.spoke] 创建；未配置时返回 Ok(None)，账户不存在时返回错误
+    pub fn from_config(section: &EmailSection) -> Result<Option<Self>, String> {
+        let Some(spoke) = &section.spoke else {
+            return Ok(None);
+        };
+        let account = section
+            .accounts
+            .get(&spoke.account)
+            .cloned()
+            .ok_or_else(|| format!("email spoke: unknown account {}", spoke.account))?;
+        let (shutdown, _) = watch::channel(false);
+        Ok(Some(Self {
+            account_name: spoke.account.clone(),
+            account,
+            poll: Duration::from_secs(spoke.poll_secs.max(10)),
+            task_prefix: spoke.task_prefix.clone(),
+            threads: Arc::new(Mutex::new(HashMap::new())),
+            shutdown,
+        }))
+    }
+
+    fn client_id(&self, root: &str) -> String {
+        format!("email:{}:{}", self.account_name, root)
+    }
+
+    /// 拉取一次未读邮件：允许的发件人标记已读并转发，其余记入 skipped 不再处理
+    async fn poll_once(
+        &self,
+        message_tx: &mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+        skipped: &mut HashSet<u32>,
+    ) -> Result<(), ToolError> {
+        let mut session = EmailTool::imap_session(&self.account).await?;
+        let result = async {
+            session
+                .select(&self.account.mailbox)
+                .await
+                .map_err(|e| ToolError::Failed(format!("IMAP select {}: {}", self.account.mailbox, e)))?;
+            let uids: Vec<u32> = session
+                .uid_search("UNSEEN")
+                .await
+                .map_err(|e| ToolError::Failed(format!("IMAP search: {}", e)))?
+                .into_iter()
+                .filter(|uid| !skipped.contains(uid))
+                .collect();
+            let mut emails = EmailTool::fetch(&mut session, &uids).await?;
+            // 按到达顺序处理
+            emails.reverse();
+            for email in emails {
+                let allowed = email
+                    .from_address
+                    .as_deref()
+                    .is_some_and(|addr| recipient_allowed(&self.account.allowed_recipients, addr));
+                if !allowed {
+                    tracing::info!(uid = email.uid, from = %email.from, "email spoke: sender not allowed, skipped");
+                    skipped.insert(email.uid);
+                    continue;
+                }
+                let _: Vec<_> = session
+                    .uid_store(email.uid.to_string(), "+FLAGS (\\Seen)")
+                    .await
+                    .map_err(|e| ToolError::Failed(format!("IMAP store: {}", e)))?
+                    .try_collect()
+                    .await
+                    .map_err(|e| ToolError::Failed(format!("IMAP store: {}", e)))?;
+                self.dispatch(email, message_tx).await;
+            }
+            Ok(())
+        }
+        .await;
+        let _ = session.logout().await;
+        result
+    }
+
+    async fn dispatch(&self, email: ParsedEmail, message_tx: &mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) {
+        let client_id = self.client_id(&thread_root(&email));
+        let mut references = email.references.clone();
+        references.extend(email.message_id.clone());
+        self.threads.lock().await.insert(
+            client_id.clone(),
+            MailThread {
+                to: email.from_address.clone().unwrap_or_default(),
+                subject: reply_subject(&email.subject),
+                references,
+            },
+        );
+        let info = ClientInfo {
+            client_id,
+            platform: SpokeType::Email,
+            display_name: Some(email.from.clone()),
+            metadata: Some(json!({
+                "account": self.account_name,
+                "from": email.from,
+                "subject": email.subject,
+            })),
+        };
+        let message = inbound_message(&email, &self.task_prefix);
+        let _ = message_tx.send((info, GatewayMessage::new(None, message)));
+    }
+
+    async fn run(self: Arc<Self>, message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) {
+        let mut shutdown_rx = self.shutdown.subscribe();
+        let mut interval = tokio::time::interval(self.poll);
+        let mut skipped = HashSet::new();
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        break;
+                    }
+                }
+                _ = interval.tick() => {
+                    if let Err(e) = self.poll_once(&message_tx, &mut skipped).await {
+                        tracing::warn!(account = %self.account_name, "email spoke poll failed: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 回复到线程，并把新邮件的 Message-ID 接到链尾，后续回复保持在同一线程
+    async fn reply(&self, client_id: &str, body: &str) -> Result<(), String> {
+        let Some(thread) = self.threads.lock().await.get(client_id).cloned() else {
+            return Ok(());
+        };
+        let domain = self.account.address.rsplit('@').next().unwrap_or("bee");
+        let message_id = format!("{}@{}", uuid::Uuid::new_v4(), domain);
+        let from = match &self.account.display_name {
+            Some(name) => format!("{} <{}>", name, self.account.address),
+            None => self.account.address.clone(),
+        };
+        let mailbox = |s: &str| {
+            s.parse::<Mailbox>()
+                .map_err(|e| format!("invalid address '{}': {}", s, e))
+        };
+        let mut builder = lettre::Message::builder()
+            .from(mailbox(&from)?)
+            .to(mailbox(&thread.to)?)
+            .subject(thread.subject.clone())
+            .message_id(Some(format!("<{}>", message_id)));
+        if let Some(parent) = thread.references.last() {
+            let chain = thread
+                .references
+                .iter()
+                .map(|id| format!("<{}>", id))
+                .collect::<Vec<_>>()
+                .join(" ");
+            builder = builder.in_reply_to(format!("<{}>", parent)).references(chain);
+        }
+        let message = builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())
+            .map_err(|e| format!("build reply: {}", e))?;
+        EmailTool::send(&self.account, message)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(thread) = self.threads.lock().await.get_mut(client_id) {
+            thread.references.push(message_id);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SpokeAdapter for EmailSpoke {
+    fn spoke_type(&self) -> SpokeType {
+        SpokeType::Email
+    }
+
+    async fn start(&self, message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) -> Result<(), String> {
+        let spoke = Arc::new(Self {
+            account_name: self.account_name.clone(),
+            account: self.account.clone(),
+            poll: self.poll,
+            task_prefix: self.task_prefix.clone(),
+            threads: Arc::clone(&self.threads),
+            shutdown: self.shutdown.clone(),
+        });
+        tokio::spawn(spoke.run(message_tx));
+        tracing::info!(
+            "Email spoke started ({}, polling every {}s)",
+            self.account.address,
+            self.poll.as_secs()
+        );
+        Ok(())
+    }
+
+    async fn send(&self, client_id: &str, message: GatewayMessage) -> Result<(), String> {
+        let body = match message.message {
+            MessageType::ResponseEnd { full_content, .. } => full_content,
+            MessageType::TaskSubmitted { task_id } => {
+                format!("已提交后台任务 {}，完成后结果会回复到本邮件线程。", task_id)
+            }
+            MessageType::TaskComplete {
+                task_id,
+                success,
+                result,
+                error,
+                ..
+            } => {
+                if success {
+                    format!("后台任务 {} 已完成：\n\n{}", task_id, result.unwrap_or_default())
+                } else {
+                    format!("后台任务 {} 失败：{}", task_id, error.unwrap_or_default())
+                }
+            }
+            MessageType::Error { message, .. } => format!("处理失败：{}", message),
+            _ => return Ok(()),
+        };
+        self.reply(client_id, &body).await
+    }
+
+    async fn stop(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl CommunicationSpoke for EmailSpoke {
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    fn supports_rich_text(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_mapping_and_inbound_message() {
+        let first = b"From: Boss <boss@example.com>\r\nTo: bee@example.com\r\nSubject: Weekly numbers\r\nMessage-ID: <root@example.com>\r\n\r\nCan you pull the weekly numbers?\r\n";
+        let first = ParsedEmail::parse(1, first).unwrap();
+        assert_eq!(first.from_address.as_deref(), Some("boss@example.com"));
+        assert_eq!(thread_root(&first), "root@example.com");
+        match inbound_message(&first, "[task]") {
+            MessageType::UserMessage { content, .. } => {
+                assert_eq!(content, "Weekly numbers\n\nCan you pull the weekly numbers?")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // 回复 bee 的邮件：线程根仍是 References 链首，正文去掉引用
+        let reply = b"From: boss@example.com\r\nTo: bee@example.com\r\nSubject: Re: Weekly numbers\r\nMessage-ID: <r2@example.com>\r\nIn-Reply-To: <bee1@example.com>\r\nReferences: <root@example.com> <bee1@example.com>\r\n\r\nAlso split by region.\r\n\r\nOn Mon, Bee wrote:\r\n> Here are the numbers\r\n";
+        let reply = ParsedEmail::parse(2, reply).unwrap();
+        assert_eq!(thread_root(&reply), "root@example.com");
+        assert_eq!(reply.references, vec!["root@example.com", "bee1@example.com"]);
+        match inbound_message(&reply, "[task]") {
+            MessageType::UserMessage { content, .. } => assert_eq!(content, "Also split by region."),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(reply_subject(&reply.subject), "Re: Weekly numbers");
+
+        let task = b"From: boss@example.com\r\nSubject: [TASK] Audit dependencies\r\nMessage-ID: <t1@example.com>\r\n\r\nList outdated crates.\r\n";
+        let task = ParsedEmail::parse(3, task).unwrap();
+        match inbound_message(&task, "[task]") {
+            MessageType::SubmitTask { instruction, .. } => {
+                assert_eq!(instruction, "Audit dependencies\n\nList outdated crates.")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
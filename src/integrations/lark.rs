@@ -3,11 +3,20 @@
 //! 通过事件订阅 Webhook 接收消息，调用 Agent 处理后回复。
 //! 支持单聊和群聊。
 //!
+//! 需审批的工具调用（[tools.policy]）以交互卡片发到会话，点击「批准 / 拒绝」后飞书回调
+//! `card.action.trigger`，结果经 [`ApprovalBroker`] 送回等待中的 ReAct 循环；调用过工具的任务
+//! 完成后以完成卡片回复（含耗时与工具调用次数），否则仍回复纯文本。
+//!
+//! 回调须带与 `LARK_VERIFICATION_TOKEN` 一致的 Verification Token（v1 在顶层 `token`，v2.0 在 `header.token`），
+//! 不一致时返回 401；未配置时仍处理消息，但拒绝审批卡片回调。
+//!
 //! 重要：飞书要求 Webhook 在 **3 秒内** 返回 200，否则判失败并重试。
 //! 本模块在解析事件后立即返回，耗时处理在后台异步执行。
 
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
 use axum::{
     extract::State,
@@ -19,9 +28,11 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::agent::{create_context_default, process_message};
-use crate::core::AgentComponents;
-use crate::react::ContextManager;
+use crate::agent::{create_context_default, process_message_stream};
+use crate::core::{AgentComponents, Reminder, ReminderOrigin, ReminderSink};
+use crate::integrations::{constant_time_eq, image_message_text, save_inbound_image};
+use crate::react::{ContextManager, ReactEvent, ReactLimits};
+use crate::tools::{ApprovalBroker, ApprovalRequest, CURRENT_ORIGIN};
 
 /// 会话存储：chat_id -> ContextManager
 pub type SessionStore = Arc<RwLock<HashMap<String, ContextManager>>>;
@@ -29,14 +40,25 @@ pub type SessionStore = Arc<RwLock<HashMap<String, ContextManager>>>;
 /// 已处理事件 ID 缓存（用于去重，防止飞书重试时重复处理）
 pub type ProcessedEvents = Arc<RwLock<HashSet<String>>>;
 
+/// 已发出审批卡片的请求：approval_id -> chat_id（只接受来自同一会话的点击）
+pub type PendingApprovals = Arc<RwLock<HashMap<String, String>>>;
+
+/// 卡片正文上限，超出时完成结果改为分段纯文本
+const CARD_MAX_CHARS: usize = 4000;
+
 /// 飞书服务状态
 pub struct LarkState {
     pub components: AgentComponents,
     pub sessions: SessionStore,
     pub processed_events: ProcessedEvents,
+    pub pending_approvals: PendingApprovals,
     pub app_id: String,
     pub app_secret: String,
     pub base_url: String,
+    /// 事件订阅的 Verification Token（LARK_VERIFICATION_TOKEN）；未配置时不接受审批卡片回调
+    pub verification_token: Option<String>,
+    /// Agent 的 workspace：收到的图片保存到其下 inbox/，供 image_read 识别
+    pub workspace: PathBuf,
 }
 
 /// URL 校验请求（未配置 Encrypt Key 时）
@@ -68,6 +90,22 @@ pub struct EventPayload {
 pub struct EventHeader {
     pub event_id: Option<String>,
     pub event_type: Option<String>,
+    pub token: Option<String>,
+}
+
+impl EventPayload {
+    /// 回调携带的 Verification Token（v2.0 在 header 内，v1 与 URL 校验在顶层）
+    fn verification_token(&self) -> Option<&str> {
+        self.header.as_ref().and_then(|h| h.token.as_deref()).or(self.token.as_deref())
+    }
+}
+
+/// 校验回调的 Verification Token：未配置时放行，配置后须一致
+fn token_valid(expected: Option<&str>, payload: &EventPayload) -> bool {
+    match expected {
+        None => true,
+        Some(expected) => payload.verification_token().is_some_and(|t| constant_time_eq(t, expected)),
+    }
 }
 
 /// 事件数据（v1 的 event 或 v2 的 event）
@@ -77,6 +115,24 @@ pub struct EventData {
     pub type_: Option<String>,
     pub event_id: Option<String>,
     pub message: Option<MessageData>,
+    /// card.action.trigger：被点击的按钮
+    pub action: Option<CardAction>,
+    /// card.action.trigger：卡片所在的消息与会话
+    pub context: Option<CardContext>,
+}
+
+/// 卡片按钮回调中的动作（value 为发送卡片时按钮携带的值）
+#[derive(Debug, Deserialize)]
+pub struct CardAction {
+    pub value: Option<serde_json::Value>,
+    pub tag: Option<String>,
+}
+
+/// 卡片按钮回调的上下文
+#[derive(Debug, Deserialize)]
+pub struct CardContext {
+    pub open_message_id: Option<String>,
+    pub open_chat_id: Option<String>,
 }
 
 /// 消息数据
@@ -96,6 +152,12 @@ struct ContentText {
     pub text: Option<String>,
 }
 
+/// 图片 content JSON
+#[derive(Debug, Deserialize)]
+struct ContentImage {
+    pub image_key: Option<String>,
+}
+
 /// 发送消息请求
 #[derive(Debug, Serialize)]
 struct SendMessageRequest {
@@ -122,6 +184,11 @@ async fn webhook_handler(
         payload.type_.as_deref().unwrap_or("(none)")
     );
 
+    if !token_valid(state.verification_token.as_deref(), &payload) {
+        tracing::warn!("Lark webhook: verification token mismatch, rejecting");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
     if payload.type_.as_deref() == Some("url_verification") {
         if let Some(challenge) = payload.challenge {
             return Ok(Json(serde_json::json!({ "challenge": challenge })));
@@ -147,6 +214,9 @@ async fn webhook_handler(
         .as_ref()
         .and_then(|h| h.event_type.as_deref())
         .or(event.type_.as_deref());
+    if event_type == Some("card.action.trigger") {
+        return Ok(Json(handle_card_action(&state, event).await));
+    }
     if event_type != Some("im.message.receive_v1") {
         tracing::info!(
             "Lark webhook: event type {:?} not im.message.receive_v1, ignoring",
@@ -165,26 +235,38 @@ async fn webhook_handler(
         return Ok(Json(serde_json::json!({})));
     };
 
-    if msg.message_type.as_deref() != Some("text") {
-        tracing::info!(
-            "Lark webhook: message_type {:?} not text, ignoring",
-            msg.message_type.as_deref()
-        );
-        return Ok(Json(serde_json::json!({})));
-    }
-
     let content_str = msg.content.as_deref().unwrap_or("{}");
-    let content: ContentText = serde_json::from_str(content_str).unwrap_or(ContentText { text: None });
-    let Some(body) = content.text else {
-        tracing::warn!("Lark webhook: no text in content, ignoring. raw content: {}", content_str);
-        return Ok(Json(serde_json::json!({})));
+    // 图片消息在后台下载后再交给 Agent（Webhook 需 3 秒内返回）
+    let mut image_key = None;
+    let body = match msg.message_type.as_deref() {
+        Some("text") => {
+            let content: ContentText = serde_json::from_str(content_str).unwrap_or(ContentText { text: None });
+            let Some(body) = content.text else {
+                tracing::warn!("Lark webhook: no text in content, ignoring. raw content: {}", content_str);
+                return Ok(Json(serde_json::json!({})));
+            };
+            let body = strip_at_mentions(body.trim());
+            if body.is_empty() {
+                "你好".to_string()
+            } else {
+                body
+            }
+        }
+        Some("image") => {
+            let content: ContentImage = serde_json::from_str(content_str).unwrap_or(ContentImage { image_key: None });
+            let Some(key) = content.image_key.filter(|_| msg.message_id.is_some()) else {
+                tracing::warn!("Lark webhook: image without image_key or message_id, ignoring");
+                return Ok(Json(serde_json::json!({})));
+            };
+            image_key = Some(key);
+            String::new()
+        }
+        other => {
+            tracing::info!("Lark webhook: message_type {:?} not text or image, ignoring", other);
+            return Ok(Json(serde_json::json!({})));
+        }
     };
 
-    let mut body = strip_at_mentions(body.trim());
-    if body.is_empty() {
-        body = "你好".to_string();
-    }
-
     let event_id = payload
         .header
         .as_ref()
@@ -207,7 +289,7 @@ async fn webhook_handler(
 
     let state_clone = Arc::clone(&state);
     let chat_id_clone = chat_id.clone();
-    let body_clone = body.clone();
+    let message_id = msg.message_id.clone().unwrap_or_default();
 
     tracing::info!(
         "Lark webhook: accepted message chat_id={} body_len={}, spawning background task",
@@ -216,7 +298,17 @@ async fn webhook_handler(
     );
 
     tokio::spawn(async move {
-        if let Err(e) = process_and_reply(state_clone, &chat_id_clone, &body_clone).await {
+        let body = match image_key {
+            Some(key) => match receive_image(&state_clone, &message_id, &key).await {
+                Ok(body) => body,
+                Err(e) => {
+                    tracing::error!("Lark image download error: {}", e);
+                    return;
+                }
+            },
+            None => body,
+        };
+        if let Err(e) = process_and_reply(state_clone, &chat_id_clone, &body).await {
             tracing::error!("Lark background process error: {}", e);
         } else {
             tracing::info!("Lark webhook: reply sent for chat_id={}", chat_id_clone);
@@ -243,25 +335,256 @@ async fn process_and_reply(state: Arc<LarkState>, chat_id: &str, body: &str) ->
             .unwrap_or_else(|| create_context_default(20, None, None))
     };
 
-    let result = process_message(&state.components, &mut context, body, None).await;
+    let origin = ReminderOrigin {
+        channel: "lark".to_string(),
+        target: chat_id.to_string(),
+        assistant_id: None,
+        user_id: None,
+    };
+    // 审批请求转为卡片，并统计工具调用次数（决定是否以完成卡片回复）
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
+    let events = tokio::spawn(forward_events(Arc::clone(&state), chat_id.to_string(), event_rx));
+    // 飞书会话无法回答中途提问，保持原行为：不询问，按错误结束
+    let limits = ReactLimits {
+        ask_user_timeout: None,
+        ..state.components.react_limits()
+    };
+    let started = Instant::now();
+    let result = CURRENT_ORIGIN
+        .scope(
+            Some(origin),
+            process_message_stream(&state.components, &mut context, body, event_tx, None, None, None, None, limits),
+        )
+        .await;
+    let tool_calls = events.await.unwrap_or(0);
 
     {
         let mut sessions = state.sessions.write().await;
         sessions.insert(chat_id.to_string(), context);
     }
 
+    let elapsed = started.elapsed();
     match result {
+        Ok(response) if tool_calls > 0 && response.chars().count() <= CARD_MAX_CHARS => {
+            let card = completion_card(true, &response, tool_calls, elapsed);
+            send_lark_card(&state, chat_id, &card).await?;
+        }
         Ok(response) => {
             send_lark_message(&state, chat_id, &response).await?;
         }
         Err(e) => {
             tracing::error!("Agent error: {}", e);
-            send_lark_message(&state, chat_id, &format!("抱歉，处理时出错: {}", e)).await?;
+            let text = format!("抱歉，处理时出错: {}", e);
+            if tool_calls > 0 {
+                send_lark_card(&state, chat_id, &completion_card(false, &text, tool_calls, elapsed)).await?;
+            } else {
+                send_lark_message(&state, chat_id, &text).await?;
+            }
         }
     }
     Ok(())
 }
 
+/// 消费本轮 ReAct 事件：审批请求发为卡片，返回工具调用次数
+async fn forward_events(
+    state: Arc<LarkState>,
+    chat_id: String,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<ReactEvent>,
+) -> usize {
+    let timeout = state
+        .components
+        .executor
+        .policy()
+        .map(|p| p.approval_timeout())
+        .unwrap_or_default();
+    let mut tool_calls = 0;
+    while let Some(event) = rx.recv().await {
+        match event {
+            ReactEvent::ToolCall { .. } => tool_calls += 1,
+            ReactEvent::ApprovalRequired { id, tool, args, risk } => {
+                let request = ApprovalRequest {
+                    id,
+                    tool,
+                    args,
+                    risk,
+                    assistant_id: None,
+                    user_id: None,
+                };
+                state
+                    .pending_approvals
+                    .write()
+                    .await
+                    .insert(request.id.clone(), chat_id.clone());
+                if let Err(e) = send_lark_card(&state, &chat_id, &approval_card(&request, timeout)).await {
+                    // 卡片发不出去则无人可审批，立即拒绝而不是等到超时
+                    tracing::error!("Failed to send Lark approval card: {}", e);
+                    state.pending_approvals.write().await.remove(&request.id);
+                    ApprovalBroker::global().resolve(&request.id, false);
+                }
+            }
+            _ => {}
+        }
+    }
+    tool_calls
+}
+
+/// 处理审批卡片的按钮回调：送回审批结果，返回 toast 并把卡片更新为结果状态。
+/// 只接受已校验 Token 的回调，且点击须来自发出该审批卡片的会话
+async fn handle_card_action(state: &LarkState, event: EventData) -> serde_json::Value {
+    let value = event.action.and_then(|a| a.value).unwrap_or_default();
+    let (Some(id), Some(approved)) = (value["approval_id"].as_str(), value["approved"].as_bool()) else {
+        tracing::warn!("Lark card action without approval_id, ignoring: {}", value);
+        return serde_json::json!({});
+    };
+    if state.verification_token.is_none() {
+        tracing::warn!(id, "Lark card action rejected: LARK_VERIFICATION_TOKEN is not set");
+        return card_toast("error", "未配置 Verification Token，无法处理审批", None);
+    }
+    let chat_id = event.context.and_then(|c| c.open_chat_id);
+    let owner = state.pending_approvals.read().await.get(id).cloned();
+    if !owner_matches(owner.as_deref(), chat_id.as_deref()) {
+        tracing::warn!(id, "Lark card action for unknown approval or from another chat, ignoring");
+        return card_toast("error", "无权处理该审批", None);
+    }
+    state.pending_approvals.write().await.remove(id);
+    let tool = value["tool"].as_str().unwrap_or("");
+    if ApprovalBroker::global().resolve(id, approved) {
+        tracing::info!(id, approved, "Lark approval resolved");
+        let status = if approved { "已批准" } else { "已拒绝" };
+        card_toast("success", status, Some(resolved_approval_card(tool, approved)))
+    } else {
+        card_toast("warning", "审批已超时或已处理", Some(expired_approval_card(tool)))
+    }
+}
+
+/// 审批卡片的点击须来自发出卡片的会话；任一方缺失都拒绝
+fn owner_matches(owner: Option<&str>, chat_id: Option<&str>) -> bool {
+    matches!((owner, chat_id), (Some(owner), Some(chat_id)) if owner == chat_id)
+}
+
+/// 卡片回调的响应：toast 提示，可选替换原卡片
+fn card_toast(kind: &str, content: &str, card: Option<serde_json::Value>) -> serde_json::Value {
+    let mut resp = serde_json::json!({ "toast": { "type": kind, "content": content } });
+    if let Some(card) = card {
+        resp["card"] = serde_json::json!({ "type": "raw", "data": card });
+    }
+    resp
+}
+
+/// 工具审批卡片：列出工具、风险与参数，附「批准 / 拒绝」按钮
+fn approval_card(request: &ApprovalRequest, timeout: Duration) -> serde_json::Value {
+    let args = serde_json::to_string_pretty(&request.args).unwrap_or_default();
+    let args: String = if args.chars().count() > 800 {
+        args.chars().take(800).chain("\n…".chars()).collect()
+    } else {
+        args
+    };
+    let risk = serde_json::to_value(request.risk)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let button = |label: &str, kind: &str, approved: bool| {
+        serde_json::json!({
+            "tag": "button",
+            "text": { "tag": "plain_text", "content": label },
+            "type": kind,
+            "value": { "approval_id": request.id, "approved": approved, "tool": request.tool },
+        })
+    };
+    serde_json::json!({
+        "config": { "wide_screen_mode": true },
+        "header": {
+            "template": "orange",
+            "title": { "tag": "plain_text", "content": "🔐 工具调用待审批" },
+        },
+        "elements": [
+            {
+                "tag": "div",
+                "text": {
+                    "tag": "lark_md",
+                    "content": format!(
+                        "**工具**：{}\n**风险**：{}\n**参数**：\n```json\n{}\n```",
+                        request.tool, risk, args
+                    ),
+                },
+            },
+            { "tag": "action", "actions": [button("批准", "primary", true), button("拒绝", "danger", false)] },
+            {
+                "tag": "note",
+                "elements": [{ "tag": "plain_text", "content": format!("{} 秒内未处理视为拒绝", timeout.as_secs()) }],
+            },
+        ],
+    })
+}
+
+/// 审批完成后的卡片（去掉按钮）
+fn resolved_approval_card(tool: &str, approved: bool) -> serde_json::Value {
+    let (template, title) = if approved {
+        ("green", "✅ 已批准")
+    } else {
+        ("red", "⛔ 已拒绝")
+    };
+    status_card(template, title, &format!("工具调用 **{}**", tool))
+}
+
+/// 审批已超时或已被处理时的卡片
+fn expired_approval_card(tool: &str) -> serde_json::Value {
+    status_card("grey", "⌛ 审批已失效", &format!("工具调用 **{}** 已超时或已处理", tool))
+}
+
+/// 任务完成卡片：结果正文 + 耗时与工具调用次数
+fn completion_card(ok: bool, body: &str, tool_calls: usize, elapsed: Duration) -> serde_json::Value {
+    let (template, title) = if ok {
+        ("green", "✅ 任务完成")
+    } else {
+        ("red", "❌ 任务失败")
+    };
+    let mut card = status_card(template, title, body);
+    if let Some(elements) = card["elements"].as_array_mut() {
+        elements.push(serde_json::json!({
+            "tag": "note",
+            "elements": [{
+                "tag": "plain_text",
+                "content": format!("耗时 {:.1} 秒 · 调用工具 {} 次", elapsed.as_secs_f64(), tool_calls),
+            }],
+        }));
+    }
+    card
+}
+
+/// 带标题与 Markdown 正文的卡片
+fn status_card(template: &str, title: &str, body: &str) -> serde_json::Value {
+    serde_json::json!({
+        "config": { "wide_screen_mode": true },
+        "header": { "template": template, "title": { "tag": "plain_text", "content": title } },
+        "elements": [{ "tag": "div", "text": { "tag": "lark_md", "content": body } }],
+    })
+}
+
+/// 下载消息中的图片到 workspace/inbox，返回转给 Agent 的文本
+async fn receive_image(state: &LarkState, message_id: &str, image_key: &str) -> anyhow::Result<String> {
+    let token = get_tenant_token(state).await?;
+    let url = format!(
+        "{}/open-apis/im/v1/messages/{}/resources/{}?type=image",
+        state.base_url, message_id, image_key
+    );
+    let resp = reqwest::Client::new()
+        .get(&url)
+        .bearer_auth(&token)
+        .send()
+        .await?
+        .error_for_status()?;
+    let mime = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("image/png")
+        .to_string();
+    let bytes = resp.bytes().await?;
+    let rel = save_inbound_image(&state.workspace, "lark", message_id, &mime, &bytes)?;
+    Ok(image_message_text(&rel, None))
+}
+
 /// 获取 tenant_access_token（带缓存）
 async fn get_tenant_token(state: &LarkState) -> anyhow::Result<String> {
     let url = format!("{}/open-apis/auth/v3/tenant_access_token/internal", state.base_url);
@@ -286,6 +609,26 @@ async fn get_tenant_token(state: &LarkState) -> anyhow::Result<String> {
     Ok(token.to_string())
 }
 
+/// remind 工具的到期提醒：发回创建提醒的会话
+#[async_trait::async_trait]
+impl ReminderSink for LarkState {
+    fn channel(&self) -> &str {
+        "lark"
+    }
+
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String> {
+        send_lark_message(self, &reminder.origin.target, &format!("⏰ 提醒：{}", reminder.text))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// 发送飞书交互卡片
+async fn send_lark_card(state: &LarkState, chat_id: &str, card: &serde_json::Value) -> anyhow::Result<()> {
+    let token = get_tenant_token(state).await?;
+    post_lark_message(state, &token, chat_id, "interactive", card.to_string()).await
+}
+
 /// 发送飞书消息
 async fn send_lark_message(state: &LarkState, chat_id: &str, body: &str) -> anyhow::Result<()> {
     let token = get_tenant_token(state).await?;
@@ -301,32 +644,100 @@ async fn send_lark_message(state: &LarkState, chat_id: &str, body: &str) -> anyh
             .collect()
     };
 
+    for chunk in chunks {
+        let content = serde_json::json!({ "text": chunk }).to_string();
+        post_lark_message(state, &token, chat_id, "text", content).await?;
+    }
+
+    Ok(())
+}
+
+/// 调用发送消息接口（content 为对应 msg_type 的 JSON 字符串）
+async fn post_lark_message(
+    state: &LarkState,
+    token: &str,
+    chat_id: &str,
+    msg_type: &str,
+    content: String,
+) -> anyhow::Result<()> {
     let url = format!(
         "{}/open-apis/im/v1/messages?receive_id_type=chat_id",
         state.base_url
     );
+    let req = SendMessageRequest {
+        receive_id: chat_id.to_string(),
+        msg_type: msg_type.to_string(),
+        content,
+    };
 
-    for chunk in chunks {
-        let content = serde_json::json!({ "text": chunk }).to_string();
-        let req = SendMessageRequest {
-            receive_id: chat_id.to_string(),
-            msg_type: "text".to_string(),
-            content,
-        };
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(&url)
+        .bearer_auth(token)
+        .json(&req)
+        .send()
+        .await?;
 
-        let client = reqwest::Client::new();
-        let resp = client
-            .post(&url)
-            .bearer_auth(&token)
-            .json(&req)
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            let text = resp.text().await?;
-            anyhow::bail!("Lark API error: {}", text);
-        }
+    if !resp.status().is_success() {
+        let text = resp.text().await?;
+        anyhow::bail!("Lark API error: {}", text);
     }
-
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::RiskLevel;
+
+    #[test]
+    fn test_approval_and_completion_cards() {
+        let request = ApprovalRequest {
+            id: "req-1".to_string(),
+            tool: "write".to_string(),
+            args: serde_json::json!({ "path": "notes.md" }),
+            risk: RiskLevel::Mutating,
+            assistant_id: None,
+            user_id: None,
+        };
+        let card = approval_card(&request, Duration::from_secs(120));
+        let buttons = card["elements"][1]["actions"].as_array().unwrap();
+        assert_eq!(buttons.len(), 2);
+        assert_eq!(buttons[0]["value"]["approval_id"], "req-1");
+        assert_eq!(buttons[0]["value"]["approved"], true);
+        assert_eq!(buttons[1]["value"]["approved"], false);
+        let text = card["elements"][0]["text"]["content"].as_str().unwrap();
+        assert!(text.contains("write") && text.contains("mutating") && text.contains("notes.md"));
+
+        let done = completion_card(true, "已写入 notes.md", 3, Duration::from_millis(2500));
+        assert_eq!(done["header"]["template"], "green");
+        assert_eq!(done["elements"][1]["elements"][0]["content"], "耗时 2.5 秒 · 调用工具 3 次");
+
+        let resp = card_toast("success", "已批准", Some(resolved_approval_card("write", true)));
+        assert_eq!(resp["card"]["data"]["header"]["template"], "green");
+        assert!(resp["card"]["data"]["elements"][0]["text"]["content"]
+            .as_str()
+            .unwrap()
+            .contains("write"));
+    }
+
+    #[test]
+    fn test_callback_token_and_approval_owner() {
+        let payload: EventPayload = serde_json::from_value(serde_json::json!({
+            "schema": "2.0",
+            "header": { "event_type": "card.action.trigger", "token": "secret" },
+            "event": {}
+        }))
+        .unwrap();
+        assert!(token_valid(Some("secret"), &payload));
+        assert!(!token_valid(Some("other"), &payload));
+        assert!(token_valid(None, &payload));
+        let v1: EventPayload = serde_json::from_value(serde_json::json!({ "type": "event_callback" })).unwrap();
+        assert!(!token_valid(Some("secret"), &v1));
+
+        assert!(owner_matches(Some("oc_1"), Some("oc_1")));
+        assert!(!owner_matches(Some("oc_1"), Some("oc_2")));
+        assert!(!owner_matches(None, Some("oc_1")));
+        assert!(!owner_matches(Some("oc_1"), None));
+    }
+}
@@ -1,7 +1,237 @@
-//! 外部集成：WhatsApp、飞书等（需对应 feature 与公网 Webhook 域名）
+//! 外部集成：WhatsApp、飞书（需对应 feature 与公网 Webhook 域名）、Discord（经 Gateway 长连接，无需公网域名）、邮件（轮询 IMAP）、
+//! 通用 Webhook（入站按模板转为消息，出站带签名推送事件）、OpenAI 兼容 API（/v1/chat/completions）、
+//! 消息队列（NATS / Redis Streams，经网关）
+
+use std::path::Path;
 
 #[cfg(feature = "whatsapp")]
 pub mod whatsapp;
 
 #[cfg(feature = "lark")]
 pub mod lark;
+
+#[cfg(feature = "discord")]
+pub mod discord;
+
+#[cfg(all(feature = "email", feature = "gateway"))]
+pub mod email;
+
+#[cfg(feature = "mq")]
+pub mod mq;
+
+pub mod webhook;
+
+#[cfg(feature = "openai-api")]
+pub mod openai_api;
+
+/// 收到的图片、文件与语音保存目录（相对 workspace）
+pub const INBOX_DIR: &str = "inbox";
+
+/// Web 聊天上传的附件保存目录（相对用户工作区），其下按会话分目录
+pub const UPLOADS_DIR: &str = "uploads";
+
+/// 将渠道收到的图片保存到 workspace/inbox/{channel}-{message_id}.{ext}，返回相对 workspace 的路径
+pub fn save_inbound_image(
+    workspace: &Path,
+    channel: &str,
+    message_id: &str,
+    mime_type: &str,
+    bytes: &[u8],
+) -> std::io::Result<String> {
+    let ext = match mime_type.split(';').next().unwrap_or("").trim() {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        _ => "jpg",
+    };
+    save_inbox(workspace, channel, message_id, ext, bytes)
+}
+
+/// 将渠道收到的文档、语音等文件保存到 workspace/inbox/{channel}-{message_id}.{ext}，返回相对 workspace 的路径；
+/// 扩展名取原文件名的扩展名，没有时按 MIME 类型推断
+pub fn save_inbound_file(
+    workspace: &Path,
+    channel: &str,
+    message_id: &str,
+    file_name: Option<&str>,
+    mime_type: &str,
+    bytes: &[u8],
+) -> std::io::Result<String> {
+    let from_name = file_name
+        .and_then(|n| Path::new(n).extension())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .filter(|e| !e.is_empty() && e.len() <= 8 && e.chars().all(|c| c.is_ascii_alphanumeric()));
+    let ext = from_name.unwrap_or_else(|| {
+        match mime_type.split(';').next().unwrap_or("").trim() {
+            "application/pdf" => "pdf",
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => "docx",
+            "application/epub+zip" => "epub",
+            "text/plain" => "txt",
+            "text/csv" => "csv",
+            "audio/ogg" | "audio/opus" => "ogg",
+            "audio/mpeg" => "mp3",
+            "audio/mp4" | "audio/x-m4a" => "m4a",
+            "audio/aac" => "aac",
+            "audio/amr" => "amr",
+            _ => "bin",
+        }
+        .to_string()
+    });
+    save_inbox(workspace, channel, message_id, &ext, bytes)
+}
+
+fn save_inbox(workspace: &Path, channel: &str, message_id: &str, ext: &str, bytes: &[u8]) -> std::io::Result<String> {
+    let id: String = message_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .take(64)
+        .collect();
+    let rel = format!("{}/{}-{}.{}", INBOX_DIR, channel, id, ext);
+    std::fs::create_dir_all(workspace.join(INBOX_DIR))?;
+    std::fs::write(workspace.join(&rel), bytes)?;
+    Ok(rel)
+}
+
+/// 保存 Web 聊天上传的文件到 {workspace}/uploads/{session_id}/{文件名}，返回相对 workspace 的路径；
+/// 文件名只保留最后一段并替换不安全字符，同名文件已存在时追加 -1、-2 …
+pub fn save_upload(workspace: &Path, session_id: &str, file_name: &str, bytes: &[u8]) -> std::io::Result<String> {
+    let session: String = session_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .take(64)
+        .collect();
+    if session.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid session id"));
+    }
+    let name = upload_file_name(file_name);
+    let dir = format!("{}/{}", UPLOADS_DIR, session);
+    std::fs::create_dir_all(workspace.join(&dir))?;
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{}", ext)),
+        _ => (name.clone(), String::new()),
+    };
+    let mut rel = format!("{}/{}", dir, name);
+    let mut n = 1;
+    while workspace.join(&rel).exists() {
+        rel = format!("{}/{}-{}{}", dir, stem, n, ext);
+        n += 1;
+    }
+    std::fs::write(workspace.join(&rel), bytes)?;
+    Ok(rel)
+}
+
+/// 上传文件名：去掉目录部分，控制字符与路径 / shell 特殊字符替换为 _，不以 . 开头
+fn upload_file_name(file_name: &str) -> String {
+    let base = file_name.rsplit(['/', '\\']).next().unwrap_or("").trim();
+    let mut name: String = base
+        .chars()
+        .map(|c| if c.is_control() || ":*?\"<>|".contains(c) { '_' } else { c })
+        .take(120)
+        .collect();
+    if name.starts_with('.') {
+        name.replace_range(..1, "_");
+    }
+    if name.is_empty() {
+        name = "upload".to_string();
+    }
+    name
+}
+
+/// 带附件的聊天消息转给 Agent 的文本：在用户消息后列出附件路径，并提示读取方式
+pub fn attachments_message_text(message: &str, rel_paths: &[String]) -> String {
+    if rel_paths.is_empty() {
+        return message.to_string();
+    }
+    let message = match message.trim() {
+        "" => "请看上传的附件。",
+        m => m,
+    };
+    let list: Vec<String> = rel_paths.iter().map(|p| format!("- {}", p)).collect();
+    format!(
+        "{}\n[用户上传了附件：\n{}\nPDF / DOCX / EPUB 用 doc_read 工具读取，CSV 等文本文件用 cat，图片用 image_read]",
+        message,
+        list.join("\n")
+    )
+}
+
+/// 图片消息转给 Agent 的文本：说明图片路径并提示用 image_read 识别
+pub fn image_message_text(rel_path: &str, caption: Option<&str>) -> String {
+    let note = format!(
+        "[用户发送了一张图片，已保存为 {}；需要时用 image_read 工具识别其中的文字或内容]",
+        rel_path
+    );
+    match caption.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(c) => format!("{}\n{}", c, note),
+        None => format!("请看这张图片。\n{}", note),
+    }
+}
+
+/// 文件消息转给 Agent 的文本：说明文件路径并提示用 doc_read / cat 读取
+pub fn document_message_text(rel_path: &str, file_name: Option<&str>, caption: Option<&str>) -> String {
+    let name = file_name.map(str::trim).filter(|n| !n.is_empty()).unwrap_or("文件");
+    let note = format!(
+        "[用户发送了文件 {}，已保存为 {}；PDF / DOCX / EPUB 用 doc_read 工具读取，文本文件用 cat]",
+        name, rel_path
+    );
+    match caption.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(c) => format!("{}\n{}", c, note),
+        None => format!("请看这个文件。\n{}", note),
+    }
+}
+
+/// 语音消息转给 Agent 的文本：转写成功时以转写内容作为用户消息，失败时提示可用 audio_transcribe 重试
+pub fn voice_message_text(rel_path: &str, transcript: Result<&str, &str>) -> String {
+    match transcript.map(str::trim) {
+        Ok(text) if !text.is_empty() => {
+            format!("{}\n[以上为用户语音消息的转写，原音频保存为 {}]", text, rel_path)
+        }
+        Ok(_) => format!("[用户发送了一条语音消息（{}），转写结果为空]", rel_path),
+        Err(e) => format!(
+            "[用户发送了一条语音消息，已保存为 {}；自动转写失败（{}），可用 audio_transcribe 工具重试]",
+            rel_path, e
+        ),
+    }
+}
+
+/// 常量时间比较共享密钥（Token、API Key），避免按首个不同字节提前返回泄露前缀
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_inbound_image() {
+        let dir = std::path::PathBuf::from("./target/test_integrations_inbox");
+        let _ = std::fs::remove_dir_all(&dir);
+        let rel = save_inbound_image(&dir, "lark", "om_1/../x", "image/png", b"png").unwrap();
+        assert_eq!(rel, "inbox/lark-om_1x.png");
+        assert_eq!(std::fs::read(dir.join(&rel)).unwrap(), b"png");
+        let text = image_message_text(&rel, Some("这是什么报错？"));
+        assert!(text.starts_with("这是什么报错？") && text.contains("image_read"));
+
+        let doc = save_inbound_file(&dir, "whatsapp", "wamid.1", Some("周报.PDF"), "application/octet-stream", b"pdf")
+            .unwrap();
+        assert_eq!(doc, "inbox/whatsapp-wamid1.pdf");
+        let voice = save_inbound_file(&dir, "whatsapp", "wamid.2", None, "audio/ogg; codecs=opus", b"ogg").unwrap();
+        assert_eq!(voice, "inbox/whatsapp-wamid2.ogg");
+        assert!(document_message_text(&doc, Some("周报.PDF"), None).contains("doc_read"));
+        assert!(voice_message_text(&voice, Ok("明天提醒我开会")).starts_with("明天提醒我开会"));
+        assert!(voice_message_text(&voice, Err("no key")).contains("audio_transcribe"));
+
+        let first = save_upload(&dir, "sess-1", "C:\\Users\\me\\销售.csv", b"a,b").unwrap();
+        assert_eq!(first, "uploads/sess-1/销售.csv");
+        assert_eq!(save_upload(&dir, "sess-1", "销售.csv", b"c,d").unwrap(), "uploads/sess-1/销售-1.csv");
+        assert_eq!(save_upload(&dir, "../x", "../../.env", b"k").unwrap(), "uploads/x/_env");
+        assert!(save_upload(&dir, "..", "a.txt", b"").is_err());
+        let text = attachments_message_text("", &[first]);
+        assert!(text.starts_with("请看上传的附件。") && text.contains("uploads/sess-1/销售.csv"));
+        assert_eq!(attachments_message_text("hi", &[]), "hi");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,386 @@
+//! 消息队列接入端（Queue Spoke，需 mq feature）
+//!
+//! 从 [mq] 配置的 NATS subject（队列组订阅）或 Redis Stream（消费组 XREADGROUP）消费请求，交给 Hub 处理，
+//! 结果以 JSON 发布到 result_topic。多个网关实例使用相同 group 时每条请求只由一个实例处理，
+//! 因此可在队列后水平扩展 Agent 运行时，并与现有的事件驱动系统对接。
+//!
+//! 请求：`{"id": "req-1", "client_id": "billing", "content": "...", "task": false}`
+//! （可选 assistant_id / model / priority；task 为 true 时作为后台任务提交）。
+//! 结果：`{"client_id": "billing", "correlation_id": "req-1", "message": <GatewayMessage>}`。
+//! client_id 在网关内加 `mq:` 前缀，与其它平台的身份隔离（需要时用 `/link` 接续）；
+//! correlation_id 为该 client_id 最近一次请求的 id，同一 client_id 的请求应串行发送。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use redis::streams::{StreamMaxlen, StreamReadOptions, StreamReadReply};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch, Mutex};
+
+use crate::config::{MqBackend, MqSection};
+use crate::gateway::{ClientInfo, CommunicationSpoke, GatewayMessage, MessageType, SpokeAdapter, SpokeType};
+
+/// 网关内 client_id 的前缀
+const CLIENT_PREFIX: &str = "mq:";
+
+/// Redis XREADGROUP 单次最多读取的条数
+const REDIS_READ_COUNT: usize = 16;
+
+/// Redis XREADGROUP 阻塞等待时长（毫秒）
+const REDIS_BLOCK_MS: usize = 5000;
+
+/// 队列中的一条请求
+#[derive(Debug, Clone, Deserialize)]
+struct QueueRequest {
+    #[serde(default)]
+    id: Option<String>,
+    client_id: String,
+    content: String,
+    #[serde(default)]
+    assistant_id: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    /// 作为后台任务提交
+    #[serde(default)]
+    task: bool,
+    #[serde(default)]
+    priority: Option<String>,
+}
+
+/// 发布到 result_topic 的一条结果
+#[derive(Debug, Serialize)]
+struct QueueResult<'a> {
+    client_id: &'a str,
+    correlation_id: Option<&'a str>,
+    message: &'a GatewayMessage,
+}
+
+/// 解析请求为网关消息，返回 (客户端, 消息, 请求 id)
+fn parse_request(payload: &[u8]) -> Result<(ClientInfo, GatewayMessage, Option<String>), String> {
+    let request: QueueRequest = serde_json::from_slice(payload).map_err(|e| format!("invalid request: {}", e))?;
+    if request.client_id.trim().is_empty() || request.content.trim().is_empty() {
+        return Err("client_id and content are required".to_string());
+    }
+    let message = if request.task {
+        MessageType::SubmitTask {
+            instruction: request.content,
+            priority: request.priority,
+        }
+    } else {
+        MessageType::UserMessage {
+            content: request.content,
+            assistant_id: request.assistant_id,
+            model: request.model,
+        }
+    };
+    let info = ClientInfo {
+        client_id: format!("{}{}", CLIENT_PREFIX, request.client_id.trim()),
+        platform: SpokeType::Queue,
+        display_name: None,
+        metadata: None,
+    };
+    Ok((info, GatewayMessage::new(None, message), request.id))
+}
+
+/// 是否发布该事件：默认只发布结果类事件，publish_stream 时发布全部
+fn should_publish(message: &MessageType, publish_stream: bool) -> bool {
+    publish_stream
+        || matches!(
+            message,
+            MessageType::ResponseEnd { .. }
+                | MessageType::Error { .. }
+                | MessageType::TaskSubmitted { .. }
+                | MessageType::TaskComplete { .. }
+                | MessageType::Queued { .. }
+        )
+}
+
+enum Backend {
+    Nats(async_nats::Client),
+    Redis {
+        client: redis::Client,
+        publisher: redis::aio::MultiplexedConnection,
+    },
+}
+
+/// 消息队列接入端
+pub struct QueueSpoke {
+    config: MqSection,
+    backend: Mutex<Option<Arc<Backend>>>,
+    /// client_id -> 最近一次请求的 id
+    correlations: Arc<Mutex<HashMap<String, String>>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl QueueSpoke {
+    pub fn new(config: MqSection) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            config,
+            backend: Mutex::new(None),
+            correlations: Arc::new(Mutex::new(HashMap::new())),
+            shutdown,
+        }
+    }
+
+    async fn connect(&self) -> Result<Backend, String> {
+        match self.config.backend {
+            MqBackend::Nats => async_nats::connect(self.config.url.as_str())
+                .await
+                .map(Backend::Nats)
+                .map_err(|e| format!("NATS connect {}: {}", self.config.url, e)),
+            MqBackend::Redis => {
+                let client = redis::Client::open(self.config.url.as_str())
+                    .map_err(|e| format!("Redis URL {}: {}", self.config.url, e))?;
+                let publisher = client
+                    .get_multiplexed_async_connection()
+                    .await
+                    .map_err(|e| format!("Redis connect {}: {}", self.config.url, e))?;
+                Ok(Backend::Redis { client, publisher })
+            }
+        }
+    }
+
+    async fn publish(&self, payload: Vec<u8>) -> Result<(), String> {
+        let Some(backend) = self.backend.lock().await.clone() else {
+            return Err("queue spoke is not started".to_string());
+        };
+        let topic = self.config.result_topic.clone();
+        match backend.as_ref() {
+            Backend::Nats(client) => client
+                .publish(topic, payload.into())
+                .await
+                .map_err(|e| format!("NATS publish: {}", e)),
+            Backend::Redis { publisher, .. } => {
+                let mut conn = publisher.clone();
+                let maxlen = StreamMaxlen::Approx(self.config.max_len);
+                conn.xadd_maxlen::<_, _, _, _, ()>(topic, maxlen, "*", &[("payload", payload)])
+                    .await
+                    .map_err(|e| format!("Redis XADD: {}", e))
+            }
+        }
+    }
+}
+
+/// 把一条请求交给 Hub，并记下其 id 供结果关联
+async fn dispatch(
+    payload: &[u8],
+    correlations: &Mutex<HashMap<String, String>>,
+    message_tx: &mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+) {
+    match parse_request(payload) {
+        Ok((info, message, id)) => {
+            let mut correlations = correlations.lock().await;
+            match id {
+                Some(id) => correlations.insert(info.client_id.clone(), id),
+                None => correlations.remove(&info.client_id),
+            };
+            drop(correlations);
+            let _ = message_tx.send((info, message));
+        }
+        Err(e) => tracing::warn!("queue spoke: dropped request: {}", e),
+    }
+}
+
+async fn consume_nats(
+    client: async_nats::Client,
+    config: MqSection,
+    correlations: Arc<Mutex<HashMap<String, String>>>,
+    message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let mut subscriber = match client
+        .queue_subscribe(config.request_topic.clone(), config.group.clone())
+        .await
+    {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!("queue spoke: NATS subscribe {} failed: {}", config.request_topic, e);
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.changed() => {
+                if *shutdown_rx.borrow() {
+                    break;
+                }
+            }
+            msg = subscriber.next() => {
+                let Some(msg) = msg else { break };
+                dispatch(&msg.payload, &correlations, &message_tx).await;
+            }
+        }
+    }
+    let _ = subscriber.unsubscribe().await;
+}
+
+async fn consume_redis(
+    client: redis::Client,
+    config: MqSection,
+    correlations: Arc<Mutex<HashMap<String, String>>>,
+    message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    // XREADGROUP 会阻塞连接，使用独立于发布的连接
+    let mut conn = match client.get_multiplexed_async_connection().await {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::warn!("queue spoke: Redis connect failed: {}", e);
+            return;
+        }
+    };
+    // 消费组已存在时返回 BUSYGROUP，忽略
+    let _: Result<(), _> = conn
+        .xgroup_create_mkstream(&config.request_topic, &config.group, "$")
+        .await;
+    let consumer = format!("bee-{}", uuid::Uuid::new_v4().simple());
+    let options = StreamReadOptions::default()
+        .group(&config.group, &consumer)
+        .count(REDIS_READ_COUNT)
+        .block(REDIS_BLOCK_MS);
+    let keys = [config.request_topic.as_str()];
+    while !*shutdown_rx.borrow() {
+        let reply: Option<StreamReadReply> = tokio::select! {
+            changed = shutdown_rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                continue;
+            }
+            reply = conn.xread_options(&keys, &[">"], &options) => match reply {
+                Ok(reply) => reply,
+                Err(e) => {
+                    tracing::warn!("queue spoke: Redis XREADGROUP failed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+        };
+        for entry in reply.into_iter().flat_map(|r| r.keys).flat_map(|k| k.ids) {
+            match entry.get::<Vec<u8>>("payload") {
+                Some(payload) => dispatch(&payload, &correlations, &message_tx).await,
+                None => tracing::warn!("queue spoke: entry {} has no payload field", entry.id),
+            }
+            let acked: Result<usize, _> = conn.xack(&config.request_topic, &config.group, &[&entry.id]).await;
+            if let Err(e) = acked {
+                tracing::warn!("queue spoke: Redis XACK {} failed: {}", entry.id, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SpokeAdapter for QueueSpoke {
+    fn spoke_type(&self) -> SpokeType {
+        SpokeType::Queue
+    }
+
+    async fn start(&self, message_tx: mpsc::UnboundedSender<(ClientInfo, GatewayMessage)>) -> Result<(), String> {
+        let backend = Arc::new(self.connect().await?);
+        *self.backend.lock().await = Some(Arc::clone(&backend));
+        let config = self.config.clone();
+        let correlations = Arc::clone(&self.correlations);
+        let shutdown_rx = self.shutdown.subscribe();
+        match backend.as_ref() {
+            Backend::Nats(client) => {
+                tokio::spawn(consume_nats(
+                    client.clone(),
+                    config,
+                    correlations,
+                    message_tx,
+                    shutdown_rx,
+                ));
+            }
+            Backend::Redis { client, .. } => {
+                tokio::spawn(consume_redis(
+                    client.clone(),
+                    config,
+                    correlations,
+                    message_tx,
+                    shutdown_rx,
+                ));
+            }
+        }
+        tracing::info!(
+            "Queue spoke started ({:?} {}, {} -> {})",
+            self.config.backend,
+            self.config.url,
+            self.config.request_topic,
+            self.config.result_topic
+        );
+        Ok(())
+    }
+
+    async fn send(&self, client_id: &str, message: GatewayMessage) -> Result<(), String> {
+        if !should_publish(&message.message, self.config.publish_stream) {
+            return Ok(());
+        }
+        let correlation = self.correlations.lock().await.get(client_id).cloned();
+        let result = QueueResult {
+            client_id: client_id.strip_prefix(CLIENT_PREFIX).unwrap_or(client_id),
+            correlation_id: correlation.as_deref(),
+            message: &message,
+        };
+        let payload = serde_json::to_vec(&result).map_err(|e| format!("Serialize error: {}", e))?;
+        self.publish(payload).await
+    }
+
+    async fn stop(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl CommunicationSpoke for QueueSpoke {
+    fn supports_streaming(&self) -> bool {
+        self.config.publish_stream
+    }
+
+    fn supports_rich_text(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_and_publish_filter() {
+        let (info, message, id) =
+            parse_request(br#"{"id": "req-1", "client_id": "billing", "content": "summarize invoices"}"#).unwrap();
+        assert_eq!(info.client_id, "mq:billing");
+        assert_eq!(info.platform, SpokeType::Queue);
+        assert_eq!(id.as_deref(), Some("req-1"));
+        assert!(
+            matches!(message.message, MessageType::UserMessage { ref content, .. } if content == "summarize invoices")
+        );
+
+        let (_, task, id) =
+            parse_request(br#"{"client_id": "etl", "content": "rebuild index", "task": true, "priority": "low"}"#)
+                .unwrap();
+        assert_eq!(id, None);
+        assert!(
+            matches!(task.message, MessageType::SubmitTask { ref priority, .. } if priority.as_deref() == Some("low"))
+        );
+
+        assert!(parse_request(br#"{"client_id": " ", "content": "x"}"#).is_err());
+        assert!(parse_request(b"not json").is_err());
+
+        let chunk = MessageType::ResponseChunk {
+            request_id: "r".into(),
+            content: "partial".into(),
+        };
+        let end = MessageType::ResponseEnd {
+            request_id: "r".into(),
+            full_content: "done".into(),
+        };
+        assert!(!should_publish(&chunk, false));
+        assert!(should_publish(&chunk, true));
+        assert!(should_publish(&end, false));
+    }
+}
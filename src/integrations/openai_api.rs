@@ -0,0 +1,326 @@
+//! OpenAI 兼容 API（openai-api feature）：让任意 OpenAI 兼容客户端（聊天 UI、IDE 插件）把 Bee 当作一个模型使用
+//!
+//! bee-web 的 `POST /v1/chat/completions` 与 `GET /v1/models` 使用本模块的请求 / 响应类型与事件转换：
+//! - `model` 为 `bee` 时使用 default 助手，`bee:<助手 id>` 选择其它助手，其它名称（客户端写死的模型名）同样回退到 default；
+//! - 请求是无状态的：客户端每次带上完整历史，除最后一条用户消息外都作为本次请求的上下文，
+//!   其中 system / developer 消息追加在助手自身 prompt 之后；
+//! - 工具由 Bee 自己执行，因此工具调用、Observation 与思考过程不以 `tool_calls` 下发（客户端会试图执行），
+//!   而是放在 `delta.reasoning_content`（DeepSeek 等采用的推理字段，多数客户端显示为可折叠的思考过程），最终回复放在 `delta.content`。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::memory::Message;
+use crate::react::ReactEvent;
+
+/// 模型名前缀：`bee` 或 `bee:<助手 id>`
+pub const MODEL_PREFIX: &str = "bee";
+
+/// Observation 预览在推理内容中保留的最大字符数
+const OBSERVATION_CHARS: usize = 500;
+
+/// POST /v1/chat/completions 请求体（只取 Bee 用得到的字段，其余如 temperature 忽略）
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    #[serde(default)]
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamOptions {
+    /// 为 true 时在 [DONE] 前追加一个只含 usage 的 chunk
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// 请求中的一条消息；content 可以是字符串或 `[{type: "text", text}]` 分段数组
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Value,
+}
+
+impl ChatMessage {
+    /// 文本内容（分段数组只取 text 段）
+    pub fn text(&self) -> String {
+        match &self.content {
+            Value::String(s) => s.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        }
+    }
+}
+
+/// 模型名对应的助手 id
+pub fn assistant_for_model(model: &str) -> &str {
+    match model.split_once(':') {
+        Some((MODEL_PREFIX, id)) if !id.trim().is_empty() => id.trim(),
+        _ => "default",
+    }
+}
+
+/// 拆分后的请求消息
+#[derive(Debug, Clone)]
+pub struct SplitMessages {
+    /// 客户端的 system / developer 消息，只能追加在助手自身 prompt 之后（见 [`append_instructions`]）
+    pub instructions: Vec<String>,
+    /// 历史上下文（用户与助手消息）
+    pub history: Vec<Message>,
+    /// 本轮用户输入
+    pub input: String,
+}
+
+/// 拆分请求消息；最后一条必须是非空的用户消息。客户端回传的 tool 消息与 Bee 无关，忽略
+pub fn split_messages(messages: &[ChatMessage]) -> Result<SplitMessages, String> {
+    let (last, rest) = messages.split_last().ok_or("messages must not be empty")?;
+    let input = last.text();
+    if last.role != "user" || input.trim().is_empty() {
+        return Err("the last message must be a non-empty user message".to_string());
+    }
+    let mut instructions = Vec::new();
+    let mut history = Vec::new();
+    for m in rest {
+        let text = m.text();
+        if text.trim().is_empty() {
+            continue;
+        }
+        match m.role.as_str() {
+            "system" | "developer" => instructions.push(text),
+            "user" => history.push(Message::user(text)),
+            "assistant" => history.push(Message::assistant(text)),
+            _ => {}
+        }
+    }
+    Ok(SplitMessages { instructions, history, input })
+}
+
+/// 把客户端的 system 指令追加在助手 prompt 之后：助手自身的指令与工具约束在前，不会被客户端替换
+pub fn append_instructions(prompt: &str, instructions: &[String]) -> String {
+    if instructions.is_empty() {
+        return prompt.to_string();
+    }
+    format!("{}\n\n## Client Instructions\n{}", prompt, instructions.join("\n\n"))
+}
+
+/// Token 用量（由循环的 TokenUsage 事件累加）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl Usage {
+    pub fn add(&mut self, event: &ReactEvent) {
+        if let ReactEvent::TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens,
+            ..
+        } = event
+        {
+            self.prompt_tokens += prompt_tokens;
+            self.completion_tokens += completion_tokens;
+            self.total_tokens += total_tokens;
+        }
+    }
+}
+
+/// 事件对应的增量：最终回复为 content，思考与工具活动为 reasoning_content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delta {
+    Content(String),
+    Reasoning(String),
+}
+
+/// ReactEvent 转为流式增量；与客户端无关的事件（步数、建议、审批等）返回 None
+pub fn event_delta(event: &ReactEvent) -> Option<Delta> {
+    let reasoning = |text: String| Some(Delta::Reasoning(text));
+    match event {
+        ReactEvent::MessageChunk { text } => Some(Delta::Content(text.clone())),
+        ReactEvent::ThinkingContent { text } => reasoning(format!("{}\n", text.trim())),
+        ReactEvent::ToolCall { tool, args } => reasoning(format!("→ {} {}\n", tool, args)),
+        ReactEvent::Observation { tool, preview } => {
+            let mut text: String = preview.chars().take(OBSERVATION_CHARS).collect();
+            if preview.chars().count() > OBSERVATION_CHARS {
+                text.push('…');
+            }
+            reasoning(format!("← {}: {}\n", tool, text.trim()))
+        }
+        ReactEvent::ToolFailure { tool, reason } => reasoning(format!("✗ {}: {}\n", tool, reason)),
+        ReactEvent::Error { text } => reasoning(format!("✗ {}\n", text)),
+        ReactEvent::Handoff { to, reason, .. } => {
+            reasoning(format!("handoff → {}: {}\n", to.as_deref().unwrap_or("auto"), reason))
+        }
+        _ => None,
+    }
+}
+
+/// 构造同一次补全的 chunk / 响应（共享 id、created 与 model）
+#[derive(Debug, Clone)]
+pub struct CompletionBuilder {
+    id: String,
+    created: i64,
+    model: String,
+}
+
+impl CompletionBuilder {
+    pub fn new(model: &str) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+            created: chrono::Utc::now().timestamp(),
+            model: if model.is_empty() {
+                MODEL_PREFIX.to_string()
+            } else {
+                model.to_string()
+            },
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        })
+    }
+
+    /// 首个 chunk：声明 assistant 角色
+    pub fn role_chunk(&self) -> Value {
+        self.chunk(json!({ "role": "assistant", "content": "" }), None)
+    }
+
+    pub fn delta_chunk(&self, delta: &Delta) -> Value {
+        match delta {
+            Delta::Content(text) => self.chunk(json!({ "content": text }), None),
+            Delta::Reasoning(text) => self.chunk(json!({ "reasoning_content": text }), None),
+        }
+    }
+
+    pub fn finish_chunk(&self, finish_reason: &str) -> Value {
+        self.chunk(json!({}), Some(finish_reason))
+    }
+
+    /// stream_options.include_usage 时的用量 chunk（choices 为空）
+    pub fn usage_chunk(&self, usage: &Usage) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [],
+            "usage": usage,
+        })
+    }
+
+    /// 非流式响应
+    pub fn completion(&self, content: &str, reasoning: &str, usage: &Usage) -> Value {
+        let mut message = json!({ "role": "assistant", "content": content });
+        if !reasoning.is_empty() {
+            message["reasoning_content"] = json!(reasoning);
+        }
+        json!({
+            "id": self.id,
+            "object": "chat.completion",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{ "index": 0, "message": message, "finish_reason": "stop" }],
+            "usage": usage,
+        })
+    }
+}
+
+/// OpenAI 风格的错误体
+pub fn error_body(message: &str, error_type: &str) -> Value {
+    json!({ "error": { "message": message, "type": error_type, "param": null, "code": null } })
+}
+
+/// GET /v1/models 响应：`bee` 与每个助手的 `bee:<id>`
+pub fn models_list<'a>(assistant_ids: impl IntoIterator<Item = &'a str>) -> Value {
+    let created = chrono::Utc::now().timestamp();
+    let model = |id: String| json!({ "id": id, "object": "model", "created": created, "owned_by": "bee" });
+    let mut data = vec![model(MODEL_PREFIX.to_string())];
+    data.extend(
+        assistant_ids
+            .into_iter()
+            .map(|id| model(format!("{}:{}", MODEL_PREFIX, id))),
+    );
+    json!({ "object": "list", "data": data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Role;
+
+    #[test]
+    fn test_request_split_and_event_chunks() {
+        assert_eq!(assistant_for_model("bee"), "default");
+        assert_eq!(assistant_for_model("bee:coder"), "coder");
+        assert_eq!(assistant_for_model("gpt-4o"), "default");
+
+        let req: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "bee:coder",
+            "stream": true,
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": "hello" },
+                { "role": "user", "content": [{ "type": "text", "text": "list files" }] }
+            ]
+        }))
+        .unwrap();
+        let split = split_messages(&req.messages).unwrap();
+        assert_eq!(split.input, "list files");
+        assert_eq!(split.instructions, vec!["Be brief."]);
+        assert_eq!(split.history.len(), 2);
+        assert!(split.history.iter().all(|m| m.role != Role::System));
+        assert!(split_messages(&req.messages[..3]).is_err());
+        let prompt = append_instructions("You are Bee.", &split.instructions);
+        assert!(prompt.starts_with("You are Bee.") && prompt.ends_with("Be brief."));
+        assert_eq!(append_instructions("You are Bee.", &[]), "You are Bee.");
+
+        let builder = CompletionBuilder::new(&req.model);
+        let call = event_delta(&ReactEvent::ToolCall {
+            tool: "ls".into(),
+            args: json!({"path": "."}),
+        })
+        .unwrap();
+        let chunk = builder.delta_chunk(&call);
+        assert_eq!(chunk["object"], "chat.completion.chunk");
+        assert!(chunk["choices"][0]["delta"]["reasoning_content"]
+            .as_str()
+            .unwrap()
+            .contains("ls"));
+        let text = event_delta(&ReactEvent::MessageChunk { text: "done".into() }).unwrap();
+        assert_eq!(builder.delta_chunk(&text)["choices"][0]["delta"]["content"], "done");
+        assert!(event_delta(&ReactEvent::MessageDone).is_none());
+        assert_eq!(builder.finish_chunk("stop")["choices"][0]["finish_reason"], "stop");
+
+        let mut usage = Usage::default();
+        usage.add(&ReactEvent::TokenUsage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+            cumulative_prompt: 10,
+            cumulative_completion: 5,
+            cumulative_total: 15,
+        });
+        let full = builder.completion("done", "", &usage);
+        assert_eq!(full["usage"]["total_tokens"], 15);
+        assert!(full["choices"][0]["message"].get("reasoning_content").is_none());
+        assert_eq!(models_list(["coder"])["data"][1]["id"], "bee:coder");
+    }
+}
@@ -0,0 +1,539 @@
+//! 通用 Webhook 接入端
+//!
+//! - 入站：`POST /hooks/<名称>` 的请求体按 [webhooks.inbound.<名称>] 的模板转为一条消息，交给指定助手处理；
+//!   配置了密钥时校验 `X-Bee-Timestamp: <Unix 秒>` 与 `X-Bee-Signature: sha256=<HMAC-SHA256("<timestamp>.<body>")>`，
+//!   时间戳与当前时间相差超过 [`MAX_TIMESTAMP_SKEW_SECS`] 的请求视为重放并拒绝；secret_env 指向的环境变量未设置时拒绝全部请求。
+//!   未配置 secret_env 的 hook 须显式设置 `allow_unsigned = true` 才接受请求（启动时告警），否则一律拒绝。
+//! - 出站：后台任务完成、心跳有发现时，把事件 POST 到 [[webhooks.outbound]] 中订阅了该事件的 URL，
+//!   请求体为 `{"event", "timestamp", "data"}`，配置了密钥时同样带时间戳与签名头，接收方可据此校验来源（密钥读不到时不发送）。
+//!   属于某个用户的事件（[`WebhookSpoke::notify_user`]）只发给 users 包含该用户的目标；users 为空的目标只收默认用户的事件。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::config::WebhooksSection;
+use crate::core::UserId;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 签名请求头（入站校验与出站签名共用）
+pub const SIGNATURE_HEADER: &str = "X-Bee-Signature";
+/// 时间戳请求头（Unix 秒），参与签名以防重放
+pub const TIMESTAMP_HEADER: &str = "X-Bee-Timestamp";
+/// 入站请求时间戳与当前时间允许的最大偏差（秒），超出视为重放
+pub const MAX_TIMESTAMP_SKEW_SECS: i64 = 300;
+/// 出站请求中标明事件类型的请求头
+pub const EVENT_HEADER: &str = "X-Bee-Event";
+
+/// 出站通知的超时
+const OUTBOUND_TIMEOUT_SECS: u64 = 10;
+
+/// 出站通知事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    /// 后台任务（看板任务、watch 任务、网关后台任务）完成
+    TaskFinished,
+    /// 心跳发现了需要跟进的事项
+    Heartbeat,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::TaskFinished => "task_finished",
+            WebhookEvent::Heartbeat => "heartbeat",
+        }
+    }
+}
+
+/// 入站请求处理错误
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WebhookError {
+    #[error("unknown webhook: {0}")]
+    NotFound(String),
+    #[error("missing or invalid webhook signature")]
+    BadSignature,
+    /// 时间戳缺失、无法解析或超出允许的偏差（可能是重放的旧请求）
+    #[error("missing or stale webhook timestamp")]
+    StaleTimestamp,
+    /// 未配置 secret_env 且未设置 allow_unsigned：不接受匿名请求
+    #[error("webhook has no secret and unsigned requests are not allowed")]
+    UnsignedNotAllowed,
+    /// 配置了 secret_env 但环境变量未设置：拒绝所有请求，而不是跳过校验
+    #[error("webhook secret is not available")]
+    SecretUnavailable,
+}
+
+/// 入站请求转换后的消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InboundMessage {
+    pub assistant_id: String,
+    /// 每个 hook 固定一个会话：hook_<名称>
+    pub session_id: String,
+    pub content: String,
+}
+
+/// 签名密钥：未配置为 Ok(None)；配置了 secret_env 但读不到时为 Err(环境变量名)
+type Secret = Result<Option<String>, String>;
+
+struct InboundHook {
+    template: String,
+    assistant_id: String,
+    secret: Secret,
+    allow_unsigned: bool,
+}
+
+struct OutboundTarget {
+    url: String,
+    events: Vec<String>,
+    users: Vec<String>,
+    secret: Secret,
+}
+
+impl OutboundTarget {
+    fn subscribes(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event.as_str())
+    }
+
+    /// 是否接收该用户的事件；user 为 None 时是工作区级事件，所有目标都接收
+    fn receives(&self, user: Option<&UserId>) -> bool {
+        match user {
+            None => true,
+            Some(user) if self.users.is_empty() => user.is_default(),
+            Some(user) => self.users.iter().any(|u| u == user.as_str()),
+        }
+    }
+}
+
+/// Webhook 接入端：持有入站 hook 与出站目标（密钥在构造时从环境变量读取）
+pub struct WebhookSpoke {
+    inbound: HashMap<String, InboundHook>,
+    outbound: Arc<Vec<OutboundTarget>>,
+    http: reqwest::Client,
+}
+
+fn read_secret(env: Option<&str>) -> Secret {
+    let Some(name) = env else {
+        return Ok(None);
+    };
+    match std::env::var(name) {
+        Ok(v) if !v.is_empty() => Ok(Some(v)),
+        _ => {
+            tracing::warn!("webhook secret env {} is not set; the webhook is disabled until it is", name);
+            Err(name.to_string())
+        }
+    }
+}
+
+impl From<&WebhooksSection> for WebhookSpoke {
+    fn from(section: &WebhooksSection) -> Self {
+        let inbound = section
+            .inbound
+            .iter()
+            .map(|(name, hook)| {
+                let secret = read_secret(hook.secret_env.as_deref());
+                if matches!(secret, Ok(None)) {
+                    if hook.allow_unsigned {
+                        tracing::warn!("webhook {} accepts unsigned requests from anyone (allow_unsigned = true)", name);
+                    } else {
+                        tracing::warn!(
+                            "webhook {} has no secret_env; it is disabled until a secret or allow_unsigned = true is configured",
+                            name
+                        );
+                    }
+                }
+                (
+                    name.clone(),
+                    InboundHook {
+                        template: hook.template.clone(),
+                        assistant_id: hook.assistant_id.clone().unwrap_or_else(|| "default".to_string()),
+                        secret,
+                        allow_unsigned: hook.allow_unsigned,
+                    },
+                )
+            })
+            .collect();
+        let outbound = section
+            .outbound
+            .iter()
+            .map(|target| OutboundTarget {
+                url: target.url.clone(),
+                events: target.events.clone(),
+                users: target.users.clone(),
+                secret: read_secret(target.secret_env.as_deref()),
+            })
+            .collect();
+        Self {
+            inbound,
+            outbound: Arc::new(outbound),
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(OUTBOUND_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl WebhookSpoke {
+    /// 校验时间戳与签名并按模板把请求体转为消息；请求体不是 JSON 时整体作为字符串，只能用 `{{payload}}` 引用
+    pub fn inbound_message(
+        &self,
+        name: &str,
+        timestamp: Option<&str>,
+        signature: Option<&str>,
+        body: &[u8],
+    ) -> Result<InboundMessage, WebhookError> {
+        self.inbound_message_at(name, timestamp, signature, body, chrono::Utc::now().timestamp())
+    }
+
+    fn inbound_message_at(
+        &self,
+        name: &str,
+        timestamp: Option<&str>,
+        signature: Option<&str>,
+        body: &[u8],
+        now: i64,
+    ) -> Result<InboundMessage, WebhookError> {
+        let hook = self
+            .inbound
+            .get(name)
+            .ok_or_else(|| WebhookError::NotFound(name.to_string()))?;
+        match &hook.secret {
+            Ok(Some(secret)) => {
+                let timestamp = timestamp
+                    .map(str::trim)
+                    .filter(|t| t.parse::<i64>().is_ok_and(|t| (now - t).abs() <= MAX_TIMESTAMP_SKEW_SECS))
+                    .ok_or(WebhookError::StaleTimestamp)?;
+                if !signature.is_some_and(|sig| verify_signature(secret.as_bytes(), timestamp, body, sig)) {
+                    return Err(WebhookError::BadSignature);
+                }
+            }
+            Ok(None) if hook.allow_unsigned => {}
+            Ok(None) => return Err(WebhookError::UnsignedNotAllowed),
+            Err(_) => return Err(WebhookError::SecretUnavailable),
+        }
+        let payload =
+            serde_json::from_slice(body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()));
+        Ok(InboundMessage {
+            assistant_id: hook.assistant_id.clone(),
+            session_id: format!("hook_{}", name),
+            content: render_template(&hook.template, &payload),
+        })
+    }
+
+    /// 把工作区级事件异步发送给订阅了它的出站目标；发送失败只记日志
+    pub fn notify(&self, event: WebhookEvent, data: Value) {
+        self.send(event, None, data);
+    }
+
+    /// 把属于某个用户的事件只发送给接收该用户的出站目标，data 中补上 user_id
+    pub fn notify_user(&self, event: WebhookEvent, user: &UserId, mut data: Value) {
+        if let Value::Object(map) = &mut data {
+            map.insert("user_id".to_string(), Value::String(user.as_str().to_string()));
+        }
+        self.send(event, Some(user.clone()), data);
+    }
+
+    fn send(&self, event: WebhookEvent, user: Option<UserId>, data: Value) {
+        if !self.outbound.iter().any(|t| t.subscribes(event) && t.receives(user.as_ref())) {
+            return;
+        }
+        let body = serde_json::json!({
+            "event": event.as_str(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "data": data,
+        })
+        .to_string();
+        let targets = Arc::clone(&self.outbound);
+        let http = self.http.clone();
+        tokio::spawn(async move {
+            for target in targets.iter().filter(|t| t.subscribes(event) && t.receives(user.as_ref())) {
+                // 应签名却读不到密钥时不发送未签名的请求
+                let secret = match &target.secret {
+                    Ok(secret) => secret,
+                    Err(env) => {
+                        tracing::warn!("webhook {} -> {}: secret env {} is not set", event.as_str(), target.url, env);
+                        continue;
+                    }
+                };
+                let mut req = http
+                    .post(&target.url)
+                    .header("Content-Type", "application/json")
+                    .header(EVENT_HEADER, event.as_str())
+                    .body(body.clone());
+                if let Some(secret) = secret {
+                    let timestamp = chrono::Utc::now().timestamp().to_string();
+                    req = req
+                        .header(SIGNATURE_HEADER, sign(secret.as_bytes(), &timestamp, body.as_bytes()))
+                        .header(TIMESTAMP_HEADER, timestamp);
+                }
+                match req.send().await {
+                    Ok(resp) if resp.status().is_success() => {}
+                    Ok(resp) => tracing::warn!("webhook {} -> {}: {}", event.as_str(), target.url, resp.status()),
+                    Err(e) => tracing::warn!("webhook {} -> {}: {}", event.as_str(), target.url, e),
+                }
+            }
+        });
+    }
+}
+
+/// 渲染模板：`{{a.b.0.c}}` 按路径取值（数组用下标），字符串原样插入、其它值插入 JSON 文本，缺失时为空；
+/// `{{payload}}` 为整个请求体
+pub fn render_template(template: &str, payload: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let path = rest[start + 2..start + 2 + len].trim();
+        let value = if path == "payload" {
+            Some(payload)
+        } else {
+            path.split('.').try_fold(payload, |v, key| match v {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => v.get(key),
+            })
+        };
+        match value {
+            Some(Value::String(s)) => out.push_str(s),
+            Some(Value::Null) | None => {}
+            Some(v) => out.push_str(&v.to_string()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 签名内容为 `<timestamp>.<body>`：时间戳参与签名，改写时间戳重放旧请求会使签名失效
+fn mac(secret: &[u8], timestamp: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// 签名头的值：`sha256=<十六进制 HMAC-SHA256(secret, "<timestamp>.<body>")>`
+pub fn sign(secret: &[u8], timestamp: &str, body: &[u8]) -> String {
+    let sig = mac(secret, timestamp, body).finalize().into_bytes();
+    format!(
+        "sha256={}",
+        sig.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+    )
+}
+
+/// 校验签名头（兼容不带 `sha256=` 前缀的写法），常数时间比较
+pub fn verify_signature(secret: &[u8], timestamp: &str, body: &[u8], header: &str) -> bool {
+    let hex = header.trim();
+    let hex = hex.strip_prefix("sha256=").unwrap_or(hex);
+    if !hex.len().is_multiple_of(2) {
+        return false;
+    }
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+        .collect();
+    bytes.is_some_and(|sig| mac(secret, timestamp, body).verify_slice(&sig).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{InboundWebhookSection, OutboundWebhookSection};
+
+    #[test]
+    fn test_inbound_template_and_signature() {
+        let payload = serde_json::json!({
+            "action": "opened",
+            "issue": { "title": "Crash on start", "number": 7 },
+            "commits": [{ "message": "fix" }],
+        });
+        assert_eq!(
+            render_template(
+                "{{action}} #{{issue.number}}: {{ issue.title }} ({{commits.0.message}}){{missing}}",
+                &payload
+            ),
+            "opened #7: Crash on start (fix)"
+        );
+
+        std::env::set_var("BEE_TEST_WEBHOOK_SECRET", "s3cret");
+        let mut section = WebhooksSection::default();
+        section.inbound.insert(
+            "github".to_string(),
+            InboundWebhookSection {
+                template: "{{action}}: {{issue.title}}".to_string(),
+                assistant_id: None,
+                secret_env: Some("BEE_TEST_WEBHOOK_SECRET".to_string()),
+                allow_unsigned: false,
+            },
+        );
+        section.outbound.push(OutboundWebhookSection {
+            url: "http://127.0.0.1:9/hook".to_string(),
+            events: vec!["heartbeat".to_string()],
+            users: Vec::new(),
+            secret_env: None,
+        });
+        section.outbound.push(OutboundWebhookSection {
+            url: "http://127.0.0.1:9/ops".to_string(),
+            events: Vec::new(),
+            users: vec!["key.ops".to_string()],
+            secret_env: None,
+        });
+        let spoke = WebhookSpoke::from(&section);
+        let body = payload.to_string();
+        let now = 1_700_000_000;
+        let ts = now.to_string();
+        let sig = sign(b"s3cret", &ts, body.as_bytes());
+
+        let msg = spoke
+            .inbound_message_at("github", Some(&ts), Some(&sig), body.as_bytes(), now)
+            .unwrap();
+        assert_eq!(msg.content, "opened: Crash on start");
+        assert_eq!(msg.session_id, "hook_github");
+        assert_eq!(msg.assistant_id, "default");
+        assert_eq!(
+            spoke.inbound_message_at("github", Some(&ts), None, body.as_bytes(), now),
+            Err(WebhookError::BadSignature)
+        );
+        assert_eq!(
+            spoke.inbound_message_at("github", Some(&ts), Some("sha256=00"), body.as_bytes(), now),
+            Err(WebhookError::BadSignature)
+        );
+        assert!(matches!(
+            spoke.inbound_message("nope", None, None, b"{}"),
+            Err(WebhookError::NotFound(_))
+        ));
+
+        // 重放：同一签名请求在窗口外被拒绝；改写时间戳则签名不符
+        assert_eq!(
+            spoke.inbound_message_at(
+                "github",
+                Some(&ts),
+                Some(&sig),
+                body.as_bytes(),
+                now + MAX_TIMESTAMP_SKEW_SECS + 1
+            ),
+            Err(WebhookError::StaleTimestamp)
+        );
+        let later = now + MAX_TIMESTAMP_SKEW_SECS + 1;
+        assert_eq!(
+            spoke.inbound_message_at("github", Some(&later.to_string()), Some(&sig), body.as_bytes(), later),
+            Err(WebhookError::BadSignature)
+        );
+        assert_eq!(
+            spoke.inbound_message_at("github", None, Some(&sig), body.as_bytes(), now),
+            Err(WebhookError::StaleTimestamp)
+        );
+
+        // 配置了密钥但环境变量缺失：拒绝请求而不是跳过校验
+        section.inbound.insert(
+            "unset".to_string(),
+            InboundWebhookSection {
+                template: "{{payload}}".to_string(),
+                assistant_id: None,
+                secret_env: Some("BEE_TEST_WEBHOOK_SECRET_UNSET".to_string()),
+                allow_unsigned: false,
+            },
+        );
+        // 未配置密钥：只有显式 allow_unsigned 时才接受匿名请求
+        for (name, allow_unsigned) in [("anon", false), ("open", true)] {
+            section.inbound.insert(
+                name.to_string(),
+                InboundWebhookSection {
+                    template: "{{payload}}".to_string(),
+                    assistant_id: None,
+                    secret_env: None,
+                    allow_unsigned,
+                },
+            );
+        }
+        let spoke = WebhookSpoke::from(&section);
+        assert_eq!(
+            spoke.inbound_message_at("unset", Some(&ts), Some(&sig), body.as_bytes(), now),
+            Err(WebhookError::SecretUnavailable)
+        );
+        assert_eq!(
+            spoke.inbound_message("anon", None, None, b"ping"),
+            Err(WebhookError::UnsignedNotAllowed)
+        );
+        assert_eq!(spoke.inbound_message("open", None, None, b"ping").unwrap().content, "ping");
+
+        assert!(spoke.outbound[0].subscribes(WebhookEvent::Heartbeat));
+        assert!(!spoke.outbound[0].subscribes(WebhookEvent::TaskFinished));
+
+        // 用户事件只发给接收该用户的目标：users 为空时只收默认用户
+        let ops = UserId::new("key.ops");
+        assert!(spoke.outbound[0].receives(None));
+        assert!(spoke.outbound[0].receives(Some(&UserId::default())));
+        assert!(!spoke.outbound[0].receives(Some(&ops)));
+        assert!(spoke.outbound[1].receives(Some(&ops)));
+        assert!(!spoke.outbound[1].receives(Some(&UserId::default())));
+    }
+
+    fn inbound_spoke(name: &str, secret_env: Option<&str>, allow_unsigned: bool) -> WebhookSpoke {
+        let mut section = WebhooksSection::default();
+        section.inbound.insert(
+            name.to_string(),
+            InboundWebhookSection {
+                template: "{{payload}}".to_string(),
+                assistant_id: None,
+                secret_env: secret_env.map(str::to_string),
+                allow_unsigned,
+            },
+        );
+        WebhookSpoke::from(&section)
+    }
+
+    #[test]
+    fn test_signed_payload_outside_window_is_rejected() {
+        std::env::set_var("BEE_TEST_WEBHOOK_WINDOW_SECRET", "w1ndow");
+        let spoke = inbound_spoke("ci", Some("BEE_TEST_WEBHOOK_WINDOW_SECRET"), false);
+        let body = b"build done";
+        let sent = 1_700_000_000;
+        let ts = sent.to_string();
+        let sig = sign(b"w1ndow", &ts, body);
+
+        // 窗口边界内接受
+        for now in [sent, sent + MAX_TIMESTAMP_SKEW_SECS, sent - MAX_TIMESTAMP_SKEW_SECS] {
+            assert!(spoke.inbound_message_at("ci", Some(&ts), Some(&sig), body, now).is_ok());
+        }
+        // 签名正确但时间戳过旧或来自未来：拒绝
+        for now in [sent + MAX_TIMESTAMP_SKEW_SECS + 1, sent - MAX_TIMESTAMP_SKEW_SECS - 1] {
+            assert_eq!(
+                spoke.inbound_message_at("ci", Some(&ts), Some(&sig), body, now),
+                Err(WebhookError::StaleTimestamp)
+            );
+        }
+        assert_eq!(
+            spoke.inbound_message_at("ci", Some("not-a-number"), Some(&sig), body, sent),
+            Err(WebhookError::StaleTimestamp)
+        );
+    }
+
+    #[test]
+    fn test_secretless_hook_requires_allow_unsigned() {
+        let closed = inbound_spoke("anon", None, false);
+        // 无论是否带签名头，未设置 allow_unsigned 都拒绝
+        assert_eq!(
+            closed.inbound_message("anon", None, None, b"ping"),
+            Err(WebhookError::UnsignedNotAllowed)
+        );
+        let ts = chrono::Utc::now().timestamp().to_string();
+        let sig = sign(b"", &ts, b"ping");
+        assert_eq!(
+            closed.inbound_message("anon", Some(&ts), Some(&sig), b"ping"),
+            Err(WebhookError::UnsignedNotAllowed)
+        );
+
+        let open = inbound_spoke("anon", None, true);
+        assert_eq!(open.inbound_message("anon", None, None, b"ping").unwrap().content, "ping");
+    }
+}
@@ -1,8 +1,11 @@
 //! WhatsApp Cloud API 集成
 //!
-//! 通过 Webhook 接收消息，调用 Agent 处理后发送回复。
+//! 通过 Webhook 接收消息，调用 Agent 处理后发送回复。图片、文件与语音消息下载到 workspace/inbox：
+//! 语音先经 audio_transcribe 转写再交给 Agent，图片与文件提示 Agent 用 image_read / doc_read 读取；
+//! Agent 调用 send_file 选定的工作区文件在文字回复之后作为图片 / 文档 / 音频发回。
 
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use axum::{
@@ -15,8 +18,14 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
 use crate::agent::{create_context_default, process_message};
-use crate::core::AgentComponents;
+use crate::core::{AgentComponents, Reminder, ReminderOrigin, ReminderSink};
+use crate::integrations::{
+    document_message_text, image_message_text, save_inbound_file, save_inbound_image, voice_message_text,
+};
 use crate::react::ContextManager;
+use crate::tools::{AttachmentQueue, OutboundFile, CURRENT_ATTACHMENTS, CURRENT_ORIGIN};
+
+const GRAPH_API: &str = "https://graph.facebook.com/v18.0";
 
 /// 会话存储：user_id -> ContextManager
 pub type SessionStore = Arc<RwLock<HashMap<String, ContextManager>>>;
@@ -27,6 +36,8 @@ pub struct WhatsappState {
     pub sessions: SessionStore,
     pub access_token: String,
     pub phone_number_id: String,
+    /// Agent 的 workspace：收到的图片、文件与语音保存到其下 inbox/
+    pub workspace: PathBuf,
 }
 
 /// Webhook 验证参数
@@ -93,6 +104,9 @@ pub struct WebhookMessage {
     #[serde(rename = "type")]
     pub msg_type: Option<String>,
     pub text: Option<WebhookText>,
+    pub image: Option<WebhookImage>,
+    pub document: Option<WebhookDocument>,
+    pub audio: Option<WebhookAudio>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -100,6 +114,31 @@ pub struct WebhookText {
     pub body: String,
 }
 
+/// 图片消息：id 为媒体 ID，需再换取下载地址
+#[derive(Debug, Deserialize)]
+pub struct WebhookImage {
+    pub id: String,
+    pub mime_type: Option<String>,
+    pub caption: Option<String>,
+}
+
+/// 文件消息（PDF、Word 等）
+#[derive(Debug, Deserialize)]
+pub struct WebhookDocument {
+    pub id: String,
+    pub mime_type: Option<String>,
+    pub filename: Option<String>,
+    pub caption: Option<String>,
+}
+
+/// 音频消息：voice 为 true 时是按住录制的语音消息
+#[derive(Debug, Deserialize)]
+pub struct WebhookAudio {
+    pub id: String,
+    pub mime_type: Option<String>,
+    pub voice: Option<bool>,
+}
+
 /// WhatsApp 发送消息 API 请求体
 #[derive(Debug, Serialize)]
 struct SendMessageRequest {
@@ -158,12 +197,24 @@ async fn webhook_receive(
             let Some(messages) = value.messages else { continue };
 
             for msg in messages {
-                if msg.msg_type.as_deref() != Some("text") {
-                    continue;
-                }
-                let Some(text) = msg.text else { continue };
                 let user_id = msg.from.clone();
-                let body = text.body.clone();
+                let message_id = msg.id.clone().unwrap_or_default();
+                let received = match (msg.msg_type.as_deref(), msg.text, msg.image, msg.document, msg.audio) {
+                    (Some("text"), Some(text), ..) => Ok(text.body),
+                    (Some("image"), _, Some(image), ..) => receive_image(&state, &message_id, &image).await,
+                    (Some("document"), _, _, Some(document), _) => {
+                        receive_document(&state, &message_id, &document).await
+                    }
+                    (Some("audio"), .., Some(audio)) => receive_audio(&state, &message_id, &audio).await,
+                    _ => continue,
+                };
+                let body = match received {
+                    Ok(body) => body,
+                    Err(e) => {
+                        tracing::error!("Failed to download WhatsApp media: {}", e);
+                        continue;
+                    }
+                };
 
                 // 获取或创建会话（取出以释放锁，避免持锁期间调用 LLM）
                 let mut context = {
@@ -174,8 +225,19 @@ async fn webhook_receive(
                 };
 
                 // 处理消息
-                let result: Result<String, crate::core::AgentError> =
-                    process_message(&state.components, &mut context, &body, None).await;
+                let origin = ReminderOrigin {
+                    channel: "whatsapp".to_string(),
+                    target: user_id.clone(),
+                    assistant_id: None,
+                    user_id: None,
+                };
+                let attachments = AttachmentQueue::default();
+                let result: Result<String, crate::core::AgentError> = CURRENT_ATTACHMENTS
+                    .scope(
+                        Some(attachments.clone()),
+                        CURRENT_ORIGIN.scope(Some(origin), process_message(&state.components, &mut context, &body, None)),
+                    )
+                    .await;
 
                 match result {
                     Ok(response) => {
@@ -195,6 +257,21 @@ async fn webhook_receive(
                         {
                             tracing::error!("Failed to send WhatsApp message: {}", e);
                         }
+                        let files = std::mem::take(&mut *attachments.lock().unwrap_or_else(|e| e.into_inner()));
+                        for file in files {
+                            if let Err(e) =
+                                send_whatsapp_file(&state.access_token, &state.phone_number_id, &user_id, &file).await
+                            {
+                                tracing::error!("Failed to send WhatsApp file {}: {}", file.path.display(), e);
+                                let _ = send_whatsapp_message(
+                                    &state.access_token,
+                                    &state.phone_number_id,
+                                    &user_id,
+                                    &format!("文件 {} 发送失败：{}", file.file_name, e),
+                                )
+                                .await;
+                            }
+                        }
                     }
                     Err(e) => {
                         tracing::error!("Agent error: {}", e);
@@ -214,6 +291,182 @@ async fn webhook_receive(
     StatusCode::OK
 }
 
+/// 用媒体 ID 换取临时下载地址，再带 token 下载，返回内容与 MIME 类型
+async fn download_media(state: &WhatsappState, media_id: &str) -> anyhow::Result<(Vec<u8>, Option<String>)> {
+    let client = reqwest::Client::new();
+    let media: serde_json::Value = client
+        .get(format!("{}/{}", GRAPH_API, media_id))
+        .bearer_auth(&state.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let url = media["url"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("No url in media response"))?;
+    let bytes = client
+        .get(url)
+        .bearer_auth(&state.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok((bytes.to_vec(), media["mime_type"].as_str().map(str::to_string)))
+}
+
+/// 下载图片消息到 workspace/inbox，返回转给 Agent 的文本
+async fn receive_image(state: &WhatsappState, message_id: &str, image: &WebhookImage) -> anyhow::Result<String> {
+    let (bytes, media_mime) = download_media(state, &image.id).await?;
+    let mime = image
+        .mime_type
+        .as_deref()
+        .or(media_mime.as_deref())
+        .unwrap_or("image/jpeg");
+    let rel = save_inbound_image(&state.workspace, "whatsapp", message_id, mime, &bytes)?;
+    Ok(image_message_text(&rel, image.caption.as_deref()))
+}
+
+/// 下载文件消息到 workspace/inbox，返回转给 Agent 的文本
+async fn receive_document(
+    state: &WhatsappState,
+    message_id: &str,
+    document: &WebhookDocument,
+) -> anyhow::Result<String> {
+    let (bytes, media_mime) = download_media(state, &document.id).await?;
+    let mime = document.mime_type.as_deref().or(media_mime.as_deref()).unwrap_or("");
+    let rel = save_inbound_file(
+        &state.workspace,
+        "whatsapp",
+        message_id,
+        document.filename.as_deref(),
+        mime,
+        &bytes,
+    )?;
+    Ok(document_message_text(&rel, document.filename.as_deref(), document.caption.as_deref()))
+}
+
+/// 下载语音 / 音频消息到 workspace/inbox 并经 audio_transcribe 转写，返回转给 Agent 的文本
+async fn receive_audio(state: &WhatsappState, message_id: &str, audio: &WebhookAudio) -> anyhow::Result<String> {
+    let (bytes, media_mime) = download_media(state, &audio.id).await?;
+    let mime = audio.mime_type.as_deref().or(media_mime.as_deref()).unwrap_or("audio/ogg");
+    let rel = save_inbound_file(&state.workspace, "whatsapp", message_id, None, mime, &bytes)?;
+    tracing::info!(path = %rel, voice = audio.voice.unwrap_or(false), "transcribing WhatsApp audio");
+    let transcript = state
+        .components
+        .executor
+        .execute("audio_transcribe", serde_json::json!({ "path": rel }))
+        .await
+        .map_err(|e| e.to_string());
+    // 工具输出首行为「[路径 · 来源]」
+    let transcript = transcript
+        .as_deref()
+        .map(|out| out.split_once('\n').map_or(out, |(_, text)| text));
+    Ok(voice_message_text(&rel, transcript.map_err(String::as_str)))
+}
+
+/// remind 工具的到期提醒：发回创建提醒的号码
+#[async_trait::async_trait]
+impl ReminderSink for WhatsappState {
+    fn channel(&self) -> &str {
+        "whatsapp"
+    }
+
+    async fn deliver(&self, reminder: &Reminder) -> Result<(), String> {
+        let text = format!("⏰ 提醒：{}", reminder.text);
+        send_whatsapp_message(&self.access_token, &self.phone_number_id, &reminder.origin.target, &text)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// 附件按扩展名对应的 WhatsApp 消息类型与 MIME 类型（图片仅支持 JPEG / PNG，其余作为文档发送）
+fn outbound_kind(file_name: &str) -> (&'static str, &'static str) {
+    let ext = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "jpg" | "jpeg" => ("image", "image/jpeg"),
+        "png" => ("image", "image/png"),
+        "mp3" => ("audio", "audio/mpeg"),
+        "ogg" | "opus" => ("audio", "audio/ogg"),
+        "m4a" => ("audio", "audio/mp4"),
+        "aac" => ("audio", "audio/aac"),
+        "amr" => ("audio", "audio/amr"),
+        "mp4" => ("video", "video/mp4"),
+        "pdf" => ("document", "application/pdf"),
+        "docx" => ("document", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+        "xlsx" => ("document", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        "pptx" => ("document", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+        "txt" | "md" | "log" => ("document", "text/plain"),
+        "csv" => ("document", "text/csv"),
+        _ => ("document", "application/octet-stream"),
+    }
+}
+
+/// 上传文件到 WhatsApp 媒体库后按类型发送（音频不支持说明文字）
+async fn send_whatsapp_file(
+    access_token: &str,
+    phone_number_id: &str,
+    to: &str,
+    file: &OutboundFile,
+) -> anyhow::Result<()> {
+    let (kind, mime) = outbound_kind(&file.file_name);
+    let bytes = tokio::fs::read(&file.path).await?;
+    let form = reqwest::multipart::Form::new()
+        .text("messaging_product", "whatsapp")
+        .text("type", mime)
+        .part(
+            "file",
+            reqwest::multipart::Part::bytes(bytes)
+                .file_name(file.file_name.clone())
+                .mime_str(mime)?,
+        );
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("{}/{}/media", GRAPH_API, phone_number_id))
+        .bearer_auth(access_token)
+        .multipart(form)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("WhatsApp media upload error: {}", resp.text().await?);
+    }
+    let uploaded: serde_json::Value = resp.json().await?;
+    let media_id = uploaded["id"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("No id in media upload response"))?;
+
+    let mut media = serde_json::json!({ "id": media_id });
+    if kind != "audio" {
+        if let Some(caption) = &file.caption {
+            media["caption"] = caption.as_str().into();
+        }
+    }
+    if kind == "document" {
+        media["filename"] = file.file_name.as_str().into();
+    }
+    let body = serde_json::json!({
+        "messaging_product": "whatsapp",
+        "to": to.replace('+', ""),
+        "type": kind,
+        kind: media,
+    });
+    let resp = client
+        .post(format!("{}/{}/messages", GRAPH_API, phone_number_id))
+        .bearer_auth(access_token)
+        .json(&body)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("WhatsApp API error: {}", resp.text().await?);
+    }
+    Ok(())
+}
+
 /// 通过 WhatsApp Cloud API 发送消息
 async fn send_whatsapp_message(
     access_token: &str,
@@ -233,10 +486,7 @@ async fn send_whatsapp_message(
             .collect()
     };
 
-    let url = format!(
-        "https://graph.facebook.com/v18.0/{}/messages",
-        phone_number_id
-    );
+    let url = format!("{}/{}/messages", GRAPH_API, phone_number_id);
 
     for chunk in chunks {
         let req = SendMessageRequest {
@@ -262,3 +512,32 @@ async fn send_whatsapp_message(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_media_messages_and_outbound_kind() {
+        let payload: WebhookMessage = serde_json::from_value(serde_json::json!({
+            "from": "8613800000000",
+            "id": "wamid.1",
+            "type": "audio",
+            "audio": { "id": "media-1", "mime_type": "audio/ogg; codecs=opus", "voice": true }
+        }))
+        .unwrap();
+        assert_eq!(payload.audio.map(|a| (a.id, a.voice)), Some(("media-1".to_string(), Some(true))));
+        let document: WebhookMessage = serde_json::from_value(serde_json::json!({
+            "from": "8613800000000",
+            "type": "document",
+            "document": { "id": "media-2", "filename": "周报.pdf", "mime_type": "application/pdf" }
+        }))
+        .unwrap();
+        assert_eq!(document.document.and_then(|d| d.filename).as_deref(), Some("周报.pdf"));
+
+        assert_eq!(outbound_kind("chart.PNG"), ("image", "image/png"));
+        assert_eq!(outbound_kind("reply.ogg").0, "audio");
+        assert_eq!(outbound_kind("report.pdf"), ("document", "application/pdf"));
+        assert_eq!(outbound_kind("archive.tar.gz"), ("document", "application/octet-stream"));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 模块划分：
 //! - **agent**: 无头 Agent 运行时（供 WhatsApp / HTTP 等调用）
+//! - **bench**: 质量回归基准（bee-bench：固定任务集 + 打分 + JSON 报告）
 //! - **config**: 应用配置加载（TOML + 环境变量）
 //! - **core**: 编排、状态、恢复、会话监管、任务调度
 //! - **gateway**: 轮毂式网关架构（WebSocket 服务器 + Agent Runtime）
@@ -13,6 +14,7 @@
 //! - **ui**: Ratatui TUI 界面
 
 pub mod agent;
+pub mod bench;
 pub mod config;
 pub mod core;
 pub mod evolution;
@@ -173,6 +175,107 @@ mod integration_tests {
         assert!(section.contains("Permission denied"));
     }
 
+    #[test]
+    fn test_compaction_keeps_pinned_and_recent_turns() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let (planner, _, _) = create_test_components();
+            let mut context = ContextManager::new(20);
+            context.push_pinned_message(Message::user("Task: translate everything to French"));
+            for i in 0..6 {
+                context.push_message(Message::user(format!("question {}", i)));
+                context.push_message(Message::assistant(format!("answer {}", i)));
+            }
+
+            let outcome = crate::react::compact_context(&planner, &mut context).await.unwrap();
+            assert!(matches!(outcome, crate::react::CompactionOutcome::Compacted { .. }));
+
+            let messages = context.messages();
+            assert!(messages[0].pinned);
+            assert_eq!(messages[1].role, crate::memory::Role::System);
+            assert_eq!(messages.last().unwrap().content, "answer 5");
+            assert_eq!(messages.len(), 2 + context.compaction.keep_recent_turns * 2);
+        });
+    }
+
+    #[test]
+    fn test_compaction_keeps_important_and_merges_observations() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let (planner, _, _) = create_test_components();
+            let mut context = ContextManager::new(20);
+            context.push_message(Message::user("You must never delete files"));
+            for i in 0..4 {
+                context.push_message(Message::user("Observation from ls: a.txt b.txt"));
+                context.push_message(Message::assistant(format!("step {}", i)));
+            }
+            context.push_message(Message::user("question"));
+            context.push_message(Message::assistant("answer"));
+
+            let outcome = crate::react::compact_context(&planner, &mut context).await.unwrap();
+            assert!(matches!(
+                outcome,
+                crate::react::CompactionOutcome::Compacted { summarized: 3, .. }
+            ));
+            let messages = context.messages();
+            assert_eq!(messages[0].content, "You must never delete files");
+            assert_eq!(messages[1].role, crate::memory::Role::System);
+        });
+    }
+
+    #[test]
+    fn test_replay_fixture_drives_react_loop() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let fixture: crate::react::ReplayFixture = serde_json::from_str(include_str!(
+                "../tests/fixtures/replay/read_then_answer.json"
+            ))
+            .unwrap();
+            let llm = Arc::new(fixture.llm_client());
+            let planner = Planner::new(llm.clone(), "You are a test assistant.".to_string());
+            let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+            let recovery = RecoveryEngine::new();
+            let mut context = ContextManager::new(10);
+            let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+
+            let result = react_loop(
+                &planner,
+                &executor,
+                &recovery,
+                &mut context,
+                "What is on my todo list?",
+                None,
+                Some(&event_tx),
+                tokio_util::sync::CancellationToken::new(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(
+                result.response,
+                "Your todo list has two items: buy milk and renew the passport."
+            );
+            assert_eq!(llm.remaining(), 0, "loop should consume every recorded response");
+            assert!(result
+                .messages
+                .iter()
+                .any(|m| m.role == crate::memory::Role::Tool && m.content.contains("- buy milk")));
+
+            drop(event_tx);
+            let mut tool_calls = Vec::new();
+            while let Some(ev) = event_rx.recv().await {
+                if let crate::react::ReactEvent::ToolCall { tool, .. } = ev {
+                    tool_calls.push(tool);
+                }
+            }
+            assert_eq!(tool_calls, vec!["cat".to_string()]);
+        });
+    }
+
     #[test]
     fn test_cancel_token_integration() {
         let rt = tokio::runtime::Runtime::new().unwrap();
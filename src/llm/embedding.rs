@@ -67,11 +67,14 @@ impl OpenAiEmbedder {
 
 impl EmbeddingProvider for OpenAiEmbedder {
     fn embed_sync(&self, text: &str) -> Result<Vec<f32>, String> {
+        // block_in_place 在无运行时或单线程运行时下会 panic，此时返回错误交由调用方降级
+        let handle = tokio::runtime::Handle::try_current().map_err(|e| e.to_string())?;
+        if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::CurrentThread {
+            return Err("embedding requires a multi-thread tokio runtime".to_string());
+        }
         let text = text.to_string();
         let this = self.clone();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(this.embed_async(&text))
-        })
+        tokio::task::block_in_place(|| handle.block_on(this.embed_async(&text)))
     }
 }
 
@@ -5,6 +5,7 @@ pub mod embedding;
 pub mod mock;
 pub mod openai;
 pub mod router;
+pub mod tokens;
 pub mod traits;
 
 pub use deepseek::{create_deepseek_client, DEEPSEEK_CHAT, DEEPSEEK_REASONER};
@@ -14,4 +15,7 @@ pub use openai::{OpenAiClient, TokenUsage};
 pub use router::{
     ModelCapabilities, ModelRouter, RoutingLlmClient, RoutingStrategy, TaskClassifier, TaskType,
 };
+pub use tokens::{
+    context_window_for_model, estimate_messages_tokens, estimate_tokens, truncate_messages_to_budget,
+};
 pub use traits::{LlmClient, LlmError, RetryConfig, RetryingLlmClient};
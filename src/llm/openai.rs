@@ -9,9 +9,10 @@ use std::sync::Arc;
 
 use async_openai::config::OpenAIConfig;
 use async_openai::types::chat::{
-    ChatCompletionRequestMessage, ChatCompletionRequestAssistantMessageArgs,
-    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-    CreateChatCompletionRequestArgs,
+    ChatCompletionMessageToolCall, ChatCompletionMessageToolCalls, ChatCompletionRequestMessage,
+    ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestToolMessageArgs, ChatCompletionRequestUserMessageArgs,
+    CreateChatCompletionRequestArgs, FunctionCall,
 };
 use async_openai::Client;
 use async_trait::async_trait;
@@ -85,10 +86,21 @@ impl OpenAiClient {
         self.usage.get()
     }
 
+    /// 转换为 OpenAI 消息；工具调用与紧随其后的同 id Tool 结果使用原生 tool_calls / tool 角色，
+    /// 不成对的（如剪枝后只剩一半）退回纯文本，避免端点拒绝孤立的 tool 消息
     fn to_openai_messages(&self, messages: &[Message]) -> Vec<ChatCompletionRequestMessage> {
+        let paired = |i: usize| {
+            let (call, result) = (&messages[i], messages.get(i + 1));
+            call.is_tool_call()
+                && result.is_some_and(|r| {
+                    r.role == crate::memory::Role::Tool && r.tool_call_id.is_some() && r.tool_call_id == call.tool_call_id
+                })
+        };
+        let native_result = |i: usize| i > 0 && paired(i - 1);
         messages
             .iter()
-            .map(|m| match m.role {
+            .enumerate()
+            .map(|(i, m)| match m.role {
                 crate::memory::Role::System => ChatCompletionRequestMessage::System(
                     ChatCompletionRequestSystemMessageArgs::default()
                         .content(m.content.clone())
@@ -101,15 +113,34 @@ impl OpenAiClient {
                         .build()
                         .unwrap(),
                 ),
+                crate::memory::Role::Assistant if paired(i) => ChatCompletionRequestMessage::Assistant(
+                    ChatCompletionRequestAssistantMessageArgs::default()
+                        .tool_calls(vec![ChatCompletionMessageToolCalls::Function(ChatCompletionMessageToolCall {
+                            id: m.tool_call_id.clone().unwrap_or_default(),
+                            function: FunctionCall {
+                                name: m.tool_name.clone().unwrap_or_default(),
+                                arguments: m.content.clone(),
+                            },
+                        })])
+                        .build()
+                        .unwrap(),
+                ),
                 crate::memory::Role::Assistant => ChatCompletionRequestMessage::Assistant(
                     ChatCompletionRequestAssistantMessageArgs::default()
+                        .content(m.transcript_text())
+                        .build()
+                        .unwrap(),
+                ),
+                crate::memory::Role::Tool if native_result(i) => ChatCompletionRequestMessage::Tool(
+                    ChatCompletionRequestToolMessageArgs::default()
                         .content(m.content.clone())
+                        .tool_call_id(m.tool_call_id.clone().unwrap_or_default())
                         .build()
                         .unwrap(),
                 ),
                 crate::memory::Role::Tool => ChatCompletionRequestMessage::User(
                     ChatCompletionRequestUserMessageArgs::default()
-                        .content(format!("[Tool Result]\n{}", m.content))
+                        .content(m.transcript_text())
                         .build()
                         .unwrap(),
                 ),
@@ -259,3 +290,33 @@ impl LlmClient for OpenAiClient {
         Ok(Box::pin(mapped_stream))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_messages_use_native_roles_when_paired() {
+        let client = OpenAiClient::new(None, "gpt-4o-mini", Some("test"));
+        let messages = vec![
+            Message::user("read a.rs"),
+            Message::tool_call("call_1", "cat", r#"{"path":"a.rs"}"#),
+            Message::tool_result("call_1", "cat", "fn main() {}"),
+            Message::tool_result("call_0", "ls", "a.rs"),
+        ];
+        let converted = client.to_openai_messages(&messages);
+        match &converted[1] {
+            ChatCompletionRequestMessage::Assistant(a) => {
+                assert!(a.content.is_none());
+                assert_eq!(a.tool_calls.as_ref().map(Vec::len), Some(1));
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        match &converted[2] {
+            ChatCompletionRequestMessage::Tool(t) => assert_eq!(t.tool_call_id, "call_1"),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        // 没有对应工具调用的结果退回纯文本
+        assert!(matches!(converted[3], ChatCompletionRequestMessage::User(_)));
+    }
+}
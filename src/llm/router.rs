@@ -435,10 +435,7 @@ mod tests {
     fn test_task_with_tool_history() {
         let messages = vec![
             Message::user("执行命令"),
-            Message {
-                role: Role::Tool,
-                content: "执行结果".to_string(),
-            },
+            Message::new(Role::Tool, "执行结果"),
             Message::user("继续"),
         ];
         let task_type = TaskClassifier::classify(&messages);
@@ -0,0 +1,108 @@
+//! Token 估算与上下文窗口
+//!
+//! 调用 LLM 前粗略估算 system + messages 的 token 数（不依赖具体 tokenizer）：
+//! ASCII 约 4 字符 1 token，CJK 等非 ASCII 字符约 1 字符 1 token，每条消息另加固定开销。
+//! ReAct 循环据此在超出模型窗口前主动压缩/截断，避免服务端返回 400。
+
+use crate::memory::Message;
+
+/// 每条消息的结构开销（role、分隔符等）
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// 未知模型的默认上下文窗口
+pub const DEFAULT_CONTEXT_WINDOW: usize = 32_000;
+
+/// 估算一段文本的 token 数
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), c| {
+        if c.is_ascii() {
+            (a + 1, o)
+        } else {
+            (a, o + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// 估算消息列表的 token 数
+pub fn estimate_messages_tokens(messages: &[Message]) -> usize {
+    messages
+        .iter()
+        .map(|m| estimate_tokens(&m.content) + MESSAGE_OVERHEAD_TOKENS)
+        .sum()
+}
+
+/// 按模型名推断上下文窗口（tokens），未识别时返回 DEFAULT_CONTEXT_WINDOW
+pub fn context_window_for_model(model: &str) -> usize {
+    let m = model.to_lowercase();
+    if m.contains("gemini") {
+        1_000_000
+    } else if m.contains("claude") {
+        200_000
+    } else if m.contains("gpt-5") {
+        400_000
+    } else if m.contains("gpt-4.1") {
+        1_000_000
+    } else if m.contains("gpt-4o") || m.contains("gpt-4-turbo") || m.starts_with("o1") || m.starts_with("o3") {
+        128_000
+    } else if m.contains("deepseek") || m.contains("qwen") || m.contains("kimi") || m.contains("moonshot") {
+        64_000
+    } else if m.contains("gpt-3.5") {
+        16_000
+    } else {
+        DEFAULT_CONTEXT_WINDOW
+    }
+}
+
+/// 将消息裁剪到预算内：从最早的非置顶消息开始丢弃，始终保留最后一条；
+/// 若仅剩的最后一条仍超出预算，截断其内容。返回被丢弃的消息数。
+pub fn truncate_messages_to_budget(messages: &mut Vec<Message>, budget: usize) -> usize {
+    let mut dropped = 0;
+    while estimate_messages_tokens(messages) > budget && messages.len() > 1 {
+        let Some(idx) = messages[..messages.len() - 1].iter().position(|m| !m.pinned) else {
+            break;
+        };
+        messages.remove(idx);
+        dropped += 1;
+    }
+    let total = estimate_messages_tokens(messages);
+    if total > budget {
+        if let Some(last) = messages.last_mut() {
+            let other = total - estimate_tokens(&last.content);
+            let allowed = budget.saturating_sub(other);
+            // 按最坏情况（1 字符 1 token）截断，保证落在预算内
+            let kept: String = last.content.chars().take(allowed).collect();
+            last.content = format!("{}\n[truncated to fit the context window]", kept);
+        }
+    }
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_and_truncate() {
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("你好"), 2);
+        assert_eq!(context_window_for_model("deepseek-reasoner"), 64_000);
+        assert_eq!(context_window_for_model("my-local-model"), DEFAULT_CONTEXT_WINDOW);
+
+        let mut messages = vec![
+            Message::user("task: keep answers short").pinned(),
+            Message::user("a".repeat(400)),
+            Message::assistant("b".repeat(400)),
+            Message::user("latest question"),
+        ];
+        let dropped = truncate_messages_to_budget(&mut messages, 100);
+        assert_eq!(dropped, 2);
+        assert!(messages[0].pinned);
+        assert_eq!(messages[1].content, "latest question");
+
+        let mut huge = vec![Message::user("x".repeat(10_000))];
+        truncate_messages_to_budget(&mut huge, 100);
+        assert!(estimate_messages_tokens(&huge) <= 100);
+    }
+}
@@ -1,11 +1,91 @@
 //! Bee - Rust 个人智能体系统
 //!
 //! 入口：初始化日志、创建 Agent 编排器与 TUI，并运行主循环。
+//! 子命令 `bee memory export|import` 用于在机器间迁移助手记忆（不启动 TUI）；
+//! `bee doctor [--offline]` 检查 API Key、模型可达性、Chrome、SQLite、workspace 与嵌入配置并给出修复建议。
+//! `bee calendar auth` 通过本地回环完成 Google Calendar OAuth 授权并保存令牌。
+//! `--safe-mode` 只启用只读工具（同 [tools] safe_mode = true）。
 
-use anyhow::Context;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use bee::config::apply_safe_mode_flag;
+use bee::core::{create_agent_builder, run_diagnostics};
+use bee::memory::{export_assistant_memory, import_assistant_memory, MemoryBundle};
+use bee::tools::google_authorize;
 use bee::{core::create_agent, ui::run_app};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+const MEMORY_USAGE: &str = "\
+Usage:
+  bee memory export <assistant_id> <bundle.json>
+  bee memory import <bundle.json> [--assistant <id>] [--overwrite]";
+
+/// `bee memory ...`：导出/导入单个助手的记忆包
+fn run_memory_command(args: &[String]) -> anyhow::Result<()> {
+    let workspace = create_agent_builder(None).workspace().to_path_buf();
+    match args.first().map(String::as_str) {
+        Some("export") => {
+            let (Some(assistant_id), Some(out)) = (args.get(1), args.get(2)) else {
+                bail!("{}", MEMORY_USAGE);
+            };
+            let bundle = export_assistant_memory(&workspace, assistant_id)?;
+            bundle.write_to(&PathBuf::from(out))?;
+            println!("Exported {} files of '{}' to {}", bundle.files.len(), assistant_id, out);
+        }
+        Some("import") => {
+            let Some(input) = args.get(1) else {
+                bail!("{}", MEMORY_USAGE);
+            };
+            let bundle = MemoryBundle::read_from(&PathBuf::from(input))?;
+            let mut assistant_id = bundle.assistant_id.clone();
+            let mut overwrite = false;
+            let mut rest = args[2..].iter();
+            while let Some(flag) = rest.next() {
+                match flag.as_str() {
+                    "--assistant" => {
+                        assistant_id = rest.next().context("--assistant requires an id")?.clone();
+                    }
+                    "--overwrite" => overwrite = true,
+                    other => bail!("unknown option {}\n{}", other, MEMORY_USAGE),
+                }
+            }
+            let report = import_assistant_memory(&workspace, &assistant_id, &bundle, overwrite)?;
+            println!(
+                "Imported into '{}': {} written, {} skipped (already exist; use --overwrite)",
+                assistant_id,
+                report.written.len(),
+                report.skipped.len()
+            );
+        }
+        _ => bail!("{}", MEMORY_USAGE),
+    }
+    Ok(())
+}
+
+/// `bee doctor`：启动自检，有失败项时以非零状态退出
+async fn run_doctor_command(args: &[String]) -> anyhow::Result<()> {
+    let offline = args.iter().any(|a| a == "--offline");
+    let builder = create_agent_builder(None);
+    let report = run_diagnostics(builder.config(), builder.workspace(), offline).await;
+    print!("{}", report.render_text());
+    if !report.ok {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `bee calendar auth`：Google Calendar OAuth 授权
+async fn run_calendar_command(args: &[String]) -> anyhow::Result<()> {
+    if args.first().map(String::as_str) != Some("auth") {
+        bail!("Usage:\n  bee calendar auth");
+    }
+    let builder = create_agent_builder(None);
+    let token_file = google_authorize(&builder.config().tools.calendar.google).await?;
+    println!("Saved Google Calendar token to {}", token_file.display());
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // 日志：默认 info，可通过 RUST_LOG 覆盖
@@ -14,6 +94,20 @@ async fn main() -> anyhow::Result<()> {
         .with(fmt::layer())
         .init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if apply_safe_mode_flag(&args) {
+        tracing::info!("safe mode enabled: read-only tools only");
+    }
+    if args.first().map(String::as_str) == Some("memory") {
+        return run_memory_command(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("doctor") {
+        return run_doctor_command(&args[1..]).await;
+    }
+    if args.first().map(String::as_str) == Some("calendar") {
+        return run_calendar_command(&args[1..]).await;
+    }
+
     // 确保工作目录与 Prompt 目录存在
     let _ = std::fs::create_dir_all("workspace");
     let _ = std::fs::create_dir_all("config/prompts");
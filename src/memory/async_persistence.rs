@@ -58,12 +58,28 @@ mod sqlx_impl {
                     role TEXT NOT NULL,
                     content TEXT NOT NULL,
                     created_at TEXT NOT NULL,
+                    tool_call_id TEXT,
+                    tool_name TEXT,
                     FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
                 )"
             )
             .execute(&self.pool)
             .await?;
 
+            // 旧库升级：补充工具调用列
+            for column in ["tool_call_id", "tool_name"] {
+                let exists = sqlx::query("SELECT 1 FROM pragma_table_info('messages') WHERE name = ?")
+                    .bind(column)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .is_some();
+                if !exists {
+                    sqlx::query(&format!("ALTER TABLE messages ADD COLUMN {} TEXT", column))
+                        .execute(&self.pool)
+                        .await?;
+                }
+            }
+
             sqlx::query(
                 "CREATE TABLE IF NOT EXISTS checkpoints (
                     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -122,12 +138,14 @@ mod sqlx_impl {
             let now = chrono::Utc::now().to_rfc3339();
 
             sqlx::query(
-                "INSERT INTO messages (session_id, role, content, created_at) VALUES (?, ?, ?, ?)"
+                "INSERT INTO messages (session_id, role, content, created_at, tool_call_id, tool_name) VALUES (?, ?, ?, ?, ?, ?)"
             )
             .bind(session_id)
             .bind(role_str)
             .bind(&message.content)
             .bind(&now)
+            .bind(&message.tool_call_id)
+            .bind(&message.tool_name)
             .execute(&self.pool)
             .await?;
 
@@ -158,12 +176,14 @@ mod sqlx_impl {
                 };
 
                 sqlx::query(
-                    "INSERT INTO messages (session_id, role, content, created_at) VALUES (?, ?, ?, ?)"
+                    "INSERT INTO messages (session_id, role, content, created_at, tool_call_id, tool_name) VALUES (?, ?, ?, ?, ?, ?)"
                 )
                 .bind(session_id)
                 .bind(role_str)
                 .bind(&message.content)
                 .bind(&now)
+                .bind(&message.tool_call_id)
+                .bind(&message.tool_name)
                 .execute(&mut *tx)
                 .await?;
             }
@@ -181,7 +201,7 @@ mod sqlx_impl {
         /// 加载消息
         pub async fn load_messages(&self, session_id: &str) -> Result<Vec<Message>, sqlx::Error> {
             let rows = sqlx::query(
-                "SELECT role, content FROM messages WHERE session_id = ? ORDER BY id ASC"
+                "SELECT role, content, tool_call_id, tool_name FROM messages WHERE session_id = ? ORDER BY id ASC"
             )
             .bind(session_id)
             .fetch_all(&self.pool)
@@ -198,7 +218,7 @@ mod sqlx_impl {
                         "tool" => Role::Tool,
                         _ => Role::System,
                     };
-                    Message { role, content }
+                    Message::new(role, content).with_tool_call(row.get("tool_call_id"), row.get("tool_name"))
                 })
                 .collect();
 
@@ -408,6 +428,38 @@ mod tests {
         assert_eq!(loaded.len(), 4);
     }
 
+    #[tokio::test]
+    async fn test_async_persistence_tool_messages() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("test.db");
+        
+        let persistence = AsyncSqlitePersistence::new(&db_path).await.unwrap();
+        persistence.create_session("tool-session", None).await.unwrap();
+        
+        let messages = vec![
+            Message::user("Q1"),
+            Message::tool_call("call_1", "ls", "{}"),
+            Message::tool_result("call_1", "ls", "a.txt"),
+        ];
+        persistence.save_messages("tool-session", &messages).await.unwrap();
+        // 单条保存同样保留 tool_call_id / tool_name
+        persistence
+            .save_message("tool-session", &Message::tool_result("call_2", "cat", "hello"))
+            .await
+            .unwrap();
+        
+        let loaded = persistence.load_messages("tool-session").await.unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert!(loaded[1].is_tool_call());
+        assert_eq!(loaded[2].role, Role::Tool);
+        assert_eq!(loaded[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(loaded[2].tool_name.as_deref(), Some("ls"));
+        assert_eq!(loaded[3].role, Role::Tool);
+        assert_eq!(loaded[3].tool_call_id.as_deref(), Some("call_2"));
+        assert_eq!(loaded[3].tool_name.as_deref(), Some("cat"));
+        assert_eq!(loaded[3].content, "hello");
+    }
+
     #[tokio::test]
     async fn test_async_persistence_checkpoints() {
         let dir = TempDir::new().unwrap();
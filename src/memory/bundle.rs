@@ -0,0 +1,186 @@
+//! 记忆导出/导入包
+//!
+//! 将单个助手的记忆目录 memory/{assistant_id}/（long-term、lessons、preferences、procedural、
+//! 每日日志、向量快照、知识图谱、用户子作用域等）打包为一个 JSON 文件，用于在机器间迁移助手。
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::memory::markdown_store::{assistant_memory_root, write_atomic};
+
+/// 当前包格式版本
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// 包内单个文件（路径相对助手记忆根目录，统一使用 '/' 分隔）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// 单个助手的记忆包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryBundle {
+    pub version: u32,
+    pub assistant_id: String,
+    pub exported_at: String,
+    pub files: Vec<BundleFile>,
+}
+
+/// 导入结果：写入与因已存在而跳过的文件
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub assistant_id: String,
+    pub written: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+impl MemoryBundle {
+    /// 写出为单个 JSON 文件
+    pub fn write_to(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("write {}", path.display()))
+    }
+
+    /// 从 JSON 文件读取，校验版本
+    pub fn read_from(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("read {}", path.display()))?;
+        let bundle: MemoryBundle = serde_json::from_str(&content)?;
+        if bundle.version > BUNDLE_VERSION {
+            bail!("unsupported memory bundle version {}", bundle.version);
+        }
+        Ok(bundle)
+    }
+}
+
+/// 递归收集目录下的文本文件（跳过原子写入遗留的 .tmp 与非 UTF-8 文件）
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<BundleFile>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, out)?;
+            continue;
+        }
+        if path.extension().is_some_and(|e| e == "tmp") {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            tracing::warn!(path = %path.display(), "skip non-text file in memory bundle");
+            continue;
+        };
+        let rel = path
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        out.push(BundleFile { path: rel, content });
+    }
+    Ok(())
+}
+
+/// 导出助手记忆（目录不存在时返回空包）
+pub fn export_assistant_memory(workspace: &Path, assistant_id: &str) -> anyhow::Result<MemoryBundle> {
+    let root = assistant_memory_root(workspace, assistant_id);
+    let mut files = Vec::new();
+    if root.is_dir() {
+        collect_files(&root, &root, &mut files)?;
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(MemoryBundle {
+        version: BUNDLE_VERSION,
+        assistant_id: assistant_id.to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        files,
+    })
+}
+
+/// 包内路径只允许普通相对片段，防止写出助手目录
+fn safe_relative_path(path: &str) -> Option<PathBuf> {
+    let rel = PathBuf::from(path);
+    let ok = !path.is_empty()
+        && rel.components().all(|c| matches!(c, Component::Normal(_)));
+    ok.then_some(rel)
+}
+
+/// 导入记忆包到指定助手（可与导出时的助手不同）；overwrite 为 false 时保留已存在的文件
+pub fn import_assistant_memory(
+    workspace: &Path,
+    assistant_id: &str,
+    bundle: &MemoryBundle,
+    overwrite: bool,
+) -> anyhow::Result<ImportReport> {
+    let root = assistant_memory_root(workspace, assistant_id);
+    let targets = bundle
+        .files
+        .iter()
+        .map(|f| {
+            safe_relative_path(&f.path)
+                .map(|rel| (f, root.join(rel)))
+                .with_context(|| format!("unsafe path in memory bundle: {}", f.path))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut report = ImportReport {
+        assistant_id: assistant_id.to_string(),
+        ..Default::default()
+    };
+    for (file, target) in targets {
+        if target.exists() && !overwrite {
+            report.skipped.push(file.path.clone());
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(&target, &file.content)
+            .with_context(|| format!("write {}", target.display()))?;
+        report.written.push(file.path.clone());
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_bundle_export_import_roundtrip() {
+        let src = tempfile::tempdir().unwrap();
+        let root = assistant_memory_root(src.path(), "coder");
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        std::fs::write(root.join("lessons.md"), "- 先读再改\n").unwrap();
+        std::fs::write(root.join("logs/2026-01-01.md"), "## 2026-01-01\n").unwrap();
+        std::fs::write(root.join("long-term.md.tmp"), "partial").unwrap();
+
+        let bundle = export_assistant_memory(src.path(), "coder").unwrap();
+        let paths: Vec<&str> = bundle.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["lessons.md", "logs/2026-01-01.md"]);
+
+        let file = src.path().join("coder.bundle.json");
+        bundle.write_to(&file).unwrap();
+        let loaded = MemoryBundle::read_from(&file).unwrap();
+
+        let dst = tempfile::tempdir().unwrap();
+        let dst_root = assistant_memory_root(dst.path(), "helper");
+        std::fs::create_dir_all(&dst_root).unwrap();
+        std::fs::write(dst_root.join("lessons.md"), "- 已有\n").unwrap();
+
+        let report = import_assistant_memory(dst.path(), "helper", &loaded, false).unwrap();
+        assert_eq!(report.written, vec!["logs/2026-01-01.md"]);
+        assert_eq!(report.skipped, vec!["lessons.md"]);
+        assert_eq!(std::fs::read_to_string(dst_root.join("lessons.md")).unwrap(), "- 已有\n");
+
+        let report = import_assistant_memory(dst.path(), "helper", &loaded, true).unwrap();
+        assert_eq!(report.written.len(), 2);
+        assert_eq!(std::fs::read_to_string(dst_root.join("lessons.md")).unwrap(), "- 先读再改\n");
+
+        let mut evil = loaded.clone();
+        evil.files.push(BundleFile { path: "../escape.md".into(), content: String::new() });
+        assert!(import_assistant_memory(dst.path(), "helper", &evil, true).is_err());
+        assert!(!dst.path().join("memory/escape.md").exists());
+    }
+}
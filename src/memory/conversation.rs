@@ -3,14 +3,15 @@
 //! 保留最近 N 轮对话（user/assistant 对），超出时智能剪枝，供 LLM 上下文与 UI 渲染使用。
 //! 
 //! 智能剪枝策略（解决问题 5.3）：
-//! - 保留 System 消息不被剪枝
-//! - 按重要性评分决定保留哪些（用户消息 > 助手回复 > 工具结果）
+//! - 保留 System 消息与置顶（pinned）消息不被剪枝
+//! - 按重要性评分决定保留哪些（用户消息 > 助手回复 > 工具结果），含约束/错误等关键词的消息加分
+//! - 重复的工具观察只保留最新一条
 //! - 可选：剪枝前将丢弃内容通知回调
 
 use serde::{Deserialize, Serialize};
 
 /// 消息角色（与 LLM API 一致）
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Role {
     User,
     Assistant,
@@ -24,37 +25,84 @@ pub enum Role {
 pub struct Message {
     pub role: Role,
     pub content: String,
+    /// 置顶消息：剪枝与 Context Compaction 时始终原样保留（如任务说明）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// 工具调用 id：Assistant 的工具调用与对应的 Tool 结果消息共用同一 id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// 工具名（工具调用与 Tool 结果消息）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
 }
 
 impl Message {
-    pub fn user(content: impl Into<String>) -> Self {
+    /// 按角色创建消息（用于从持久化层恢复）
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
         Self {
-            role: Role::User,
+            role,
             content: content.into(),
+            pinned: false,
+            tool_call_id: None,
+            tool_name: None,
         }
     }
 
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
     pub fn assistant(content: impl Into<String>) -> Self {
-        Self {
-            role: Role::Assistant,
-            content: content.into(),
-        }
+        Self::new(Role::Assistant, content)
     }
 
     pub fn system(content: impl Into<String>) -> Self {
-        Self {
-            role: Role::System,
-            content: content.into(),
-        }
+        Self::new(Role::System, content)
     }
 
     /// 工具调用结果消息
     pub fn tool(content: impl Into<String>) -> Self {
-        Self {
-            role: Role::Tool,
-            content: content.into(),
+        Self::new(Role::Tool, content)
+    }
+
+    /// Assistant 发起的工具调用（content 为 JSON 参数）
+    pub fn tool_call(id: impl Into<String>, tool: impl Into<String>, arguments: impl Into<String>) -> Self {
+        Self::assistant(arguments).with_tool_call(Some(id.into()), Some(tool.into()))
+    }
+
+    /// 与工具调用 id 对应的 Tool 结果消息
+    pub fn tool_result(id: impl Into<String>, tool: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::tool(content).with_tool_call(Some(id.into()), Some(tool.into()))
+    }
+
+    /// 设置工具调用 id 与工具名（用于从持久化层恢复）
+    pub fn with_tool_call(mut self, id: Option<String>, tool: Option<String>) -> Self {
+        self.tool_call_id = id;
+        self.tool_name = tool;
+        self
+    }
+
+    /// 是否为 Assistant 发起的工具调用
+    pub fn is_tool_call(&self) -> bool {
+        self.role == Role::Assistant && self.tool_call_id.is_some()
+    }
+
+    /// 纯文本形式：不支持工具角色的后端、日志与摘要中使用
+    /// （工具调用为 "Tool call: name args"，Tool 结果为 "Observation from name: ..."）
+    pub fn transcript_text(&self) -> String {
+        let tool = self.tool_name.as_deref().unwrap_or("tool");
+        match self.role {
+            Role::Assistant if self.is_tool_call() => format!("Tool call: {} {}", tool, self.content),
+            Role::Tool => format!("Observation from {}: {}", tool, self.content),
+            _ => self.content.clone(),
         }
     }
+
+    /// 标记为置顶消息
+    pub fn pinned(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
 }
 
 /// 消息重要性评分（用于智能剪枝）
@@ -81,6 +129,68 @@ impl From<&Role> for MessageImportance {
     }
 }
 
+/// 约束类关键词：命中的消息视为任务要求，重要性加分
+const CONSTRAINT_KEYWORDS: &[&str] = &[
+    "must", "always", "never", "don't", "do not", "remember", "important", "requirement",
+    "必须", "务必", "一定", "不要", "不能", "记住", "要求", "注意",
+];
+
+/// 错误类关键词：失败的观察对后续规划有价值，重要性加分
+const ERROR_KEYWORDS: &[&str] = &["error", "failed", "错误", "失败"];
+
+/// 是否为工具观察（工具调用与 Tool 结果消息，或旧版本写回对话的 Tool call / Observation 文本消息）
+pub fn is_observation(msg: &Message) -> bool {
+    match msg.role {
+        Role::Tool => true,
+        Role::User => msg.content.starts_with("Observation from "),
+        Role::Assistant => msg.is_tool_call() || msg.content.starts_with("Tool call: "),
+        Role::System => false,
+    }
+}
+
+/// 启发式重要性评分：角色基础分（观察按工具结果计）+ 约束关键词 20 + 错误观察 10 + 代码块 5
+pub fn importance_score(msg: &Message) -> u32 {
+    let observation = is_observation(msg);
+    let base = if observation {
+        MessageImportance::Tool
+    } else {
+        MessageImportance::from(&msg.role)
+    } as u32;
+    let lower = msg.content.to_lowercase();
+    let mut score = base;
+    if !observation && CONSTRAINT_KEYWORDS.iter().any(|k| lower.contains(k)) {
+        score += 20;
+    }
+    if observation && ERROR_KEYWORDS.iter().any(|k| lower.contains(k)) {
+        score += 10;
+    }
+    if msg.content.contains("```") {
+        score += 5;
+    }
+    score
+}
+
+/// 合并重复的工具观察：内容相同的观察只保留最后一次出现，返回被移除的消息
+pub fn dedup_observations(messages: &mut Vec<Message>) -> Vec<Message> {
+    let mut seen = std::collections::HashSet::new();
+    let mut keep = vec![true; messages.len()];
+    for (i, m) in messages.iter().enumerate().rev() {
+        if is_observation(m) && !m.pinned && !seen.insert((m.role.clone(), m.transcript_text().trim().to_string())) {
+            keep[i] = false;
+        }
+    }
+    let mut removed = Vec::new();
+    let mut flags = keep.into_iter();
+    messages.retain(|m| {
+        let k = flags.next().unwrap_or(true);
+        if !k {
+            removed.push(m.clone());
+        }
+        k
+    });
+    removed
+}
+
 /// 剪枝配置
 #[derive(Clone, Debug)]
 pub struct PruneConfig {
@@ -90,6 +200,8 @@ pub struct PruneConfig {
     pub tool_result_ratio: f32,
     /// 是否启用智能剪枝（false 则使用简单的 FIFO）
     pub smart_prune: bool,
+    /// 剪枝前是否先合并重复的工具观察
+    pub dedup_observations: bool,
 }
 
 impl Default for PruneConfig {
@@ -98,6 +210,7 @@ impl Default for PruneConfig {
             preserve_system: true,
             tool_result_ratio: 0.5,
             smart_prune: true,
+            dedup_observations: true,
         }
     }
 }
@@ -192,18 +305,30 @@ impl ConversationMemory {
             };
         }
 
+        // 先合并重复观察，若已回到上限内则无需继续剪枝
+        let mut deduped = Vec::new();
+        if self.prune_config.dedup_observations {
+            deduped = dedup_observations(&mut self.messages);
+            if self.messages.len() <= max_messages {
+                return PruneResult {
+                    pruned_messages: deduped,
+                    retained_count: self.messages.len(),
+                };
+            }
+        }
+
         // 智能剪枝
-        let mut indexed: Vec<(usize, &Message, MessageImportance)> = self
+        let mut indexed: Vec<(usize, &Message, u32)> = self
             .messages
             .iter()
             .enumerate()
-            .map(|(i, m)| (i, m, MessageImportance::from(&m.role)))
+            .map(|(i, m)| (i, m, importance_score(m)))
             .collect();
 
-        // 分离 System 消息
+        // 分离 System 消息与置顶消息
         let (system_msgs, mut other_msgs): (Vec<_>, Vec<_>) = indexed
             .drain(..)
-            .partition(|(_, _, imp)| *imp == MessageImportance::System);
+            .partition(|(_, m, _)| m.role == Role::System || m.pinned);
 
         // 计算非 System 消息的目标数量
         let target_non_system = if self.prune_config.preserve_system {
@@ -223,8 +348,8 @@ impl ConversationMemory {
             let tool_limit = (target_non_system as f32 * self.prune_config.tool_result_ratio) as usize;
             let mut tool_count = 0;
             
-            other_msgs.retain(|(_, _, imp)| {
-                if *imp == MessageImportance::Tool {
+            other_msgs.retain(|(_, m, _)| {
+                if is_observation(m) {
                     tool_count += 1;
                     tool_count <= tool_limit
                 } else {
@@ -244,18 +369,14 @@ impl ConversationMemory {
         kept_indices.extend(other_msgs.iter().map(|(i, _, _)| *i));
         kept_indices.sort();
 
-        let pruned_messages: Vec<Message> = self
-            .messages
-            .iter()
-            .enumerate()
-            .filter_map(|(i, m)| {
-                if !kept_indices.contains(&i) {
-                    Some(m.clone())
-                } else {
-                    None
-                }
-            })
-            .collect();
+        let mut pruned_messages = deduped;
+        pruned_messages.extend(self.messages.iter().enumerate().filter_map(|(i, m)| {
+            if !kept_indices.contains(&i) {
+                Some(m.clone())
+            } else {
+                None
+            }
+        }));
 
         let new_messages: Vec<Message> = kept_indices
             .iter()
@@ -284,10 +405,11 @@ impl ConversationMemory {
                 Role::System => "System",
                 Role::Tool => "Tool",
             };
-            let content = if msg.content.len() > 100 {
-                format!("{}...", &msg.content[..100])
+            let text = msg.transcript_text();
+            let content = if text.len() > 100 {
+                format!("{}...", &text[..100])
             } else {
-                msg.content.clone()
+                text
             };
             summary.push_str(&format!("- {}: {}\n", role, content));
         }
@@ -342,6 +464,7 @@ mod tests {
             preserve_system: true,
             tool_result_ratio: 0.25,
             smart_prune: true,
+            dedup_observations: true,
         };
         let mut mem = ConversationMemory::with_config(3, config); // 最多 6 条
         
@@ -388,6 +511,73 @@ mod tests {
         assert!(summary.contains("Hello"));
     }
 
+    #[test]
+    fn test_pinned_messages_survive_prune() {
+        let mut mem = ConversationMemory::new(1);
+
+        mem.push(Message::user("task: always answer in French").pinned());
+        mem.push(Message::assistant("ok"));
+        mem.push(Message::user("msg2"));
+        mem.push(Message::assistant("reply2"));
+
+        assert!(mem.messages().iter().any(|m| m.pinned && m.content.contains("French")));
+    }
+
+    #[test]
+    fn test_importance_score_and_dedup_observations() {
+        let rule = Message::user("You must keep the public API unchanged");
+        let chat = Message::user("what next?");
+        let obs_ok = Message::user("Observation from cat: fn main() {}");
+        let obs_err = Message::user("Observation from shell: Error: command failed");
+        assert!(importance_score(&rule) > importance_score(&chat));
+        assert!(importance_score(&chat) > importance_score(&obs_err));
+        assert!(importance_score(&obs_err) > importance_score(&obs_ok));
+
+        let mut messages = vec![
+            obs_ok.clone(),
+            Message::assistant("Tool call: cat | Result: fn main() {}"),
+            chat.clone(),
+            obs_ok.clone(),
+            Message::assistant("Tool call: cat | Result: fn main() {}"),
+        ];
+        let removed = dedup_observations(&mut messages);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, "what next?");
+    }
+
+    #[test]
+    fn test_tool_call_and_result_messages() {
+        let call = Message::tool_call("call_1", "cat", r#"{"path":"a.rs"}"#);
+        let result = Message::tool_result("call_1", "cat", "fn main() {}");
+        assert!(call.is_tool_call() && is_observation(&call));
+        assert!(!result.is_tool_call() && is_observation(&result));
+        assert_eq!(call.transcript_text(), r#"Tool call: cat {"path":"a.rs"}"#);
+        assert_eq!(result.transcript_text(), "Observation from cat: fn main() {}");
+
+        let json = serde_json::to_string(&result).unwrap();
+        let back: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(back.tool_name.as_deref(), Some("cat"));
+        let plain: Message = serde_json::from_str(r#"{"role":"User","content":"hi"}"#).unwrap();
+        assert!(plain.tool_call_id.is_none());
+    }
+
+    #[test]
+    fn test_prune_keeps_constraints_over_chatter() {
+        let mut mem = ConversationMemory::new(2); // 最多 4 条
+        mem.push(Message::user("Never touch the migrations folder"));
+        mem.push(Message::assistant("ok"));
+        mem.push(Message::user("Observation from ls: a b c"));
+        mem.push(Message::user("Observation from ls: a b c"));
+        mem.push(Message::user("list again"));
+        mem.push(Message::assistant("done"));
+
+        let contents: Vec<&str> = mem.messages().iter().map(|m| m.content.as_str()).collect();
+        assert!(contents.contains(&"Never touch the migrations folder"));
+        assert_eq!(contents.iter().filter(|c| c.starts_with("Observation")).count(), 0);
+    }
+
     #[test]
     fn test_message_importance() {
         assert!(MessageImportance::System > MessageImportance::User);
@@ -0,0 +1,209 @@
+//! 情景记忆（Episodic Memory）：每次完成的会话记录一条结构化摘要
+//!
+//! 摘要包含目标、使用的工具、结果与时间，按行存于 memory/episodes.jsonl；
+//! 新任务开始时按目标相似度检索「相似的过往经历」注入 Planner system prompt。
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::long_term::matches_pattern;
+use super::markdown_store::write_atomic;
+use super::tokenizer;
+
+/// 单条情景：一次完成的会话
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Episode {
+    /// 用户目标
+    pub goal: String,
+    /// 使用过的工具（去重）
+    #[serde(default)]
+    pub tools_used: Vec<String>,
+    /// 结果摘要（最终回复预览或失败原因）
+    pub outcome: String,
+    /// 是否正常完成（达到步数上限等为 false）
+    #[serde(default = "default_success")]
+    pub success: bool,
+    /// 完成时间（unix 秒）
+    pub timestamp: i64,
+}
+
+fn default_success() -> bool {
+    true
+}
+
+/// 结果摘要保留的最大字符数
+const OUTCOME_MAX_CHARS: usize = 200;
+
+impl Episode {
+    pub fn new(goal: &str, tools_used: &[String], outcome: &str, success: bool) -> Self {
+        let outcome: String = outcome.trim().chars().take(OUTCOME_MAX_CHARS).collect();
+        Self {
+            goal: goal.trim().to_string(),
+            tools_used: tools_used.to_vec(),
+            outcome,
+            success,
+            timestamp: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    fn tokens(&self) -> std::collections::HashSet<String> {
+        tokenizer::tokenize_to_set(&format!("{} {}", self.goal, self.tools_used.join(" ")))
+    }
+
+    /// 注入 prompt 的单行描述
+    pub fn to_prompt_line(&self) -> String {
+        let date = chrono::DateTime::from_timestamp(self.timestamp, 0)
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let tools = if self.tools_used.is_empty() {
+            "none".to_string()
+        } else {
+            self.tools_used.join(", ")
+        };
+        format!(
+            "- [{}] goal: \"{}\"; tools: {}; {}: {}",
+            date,
+            self.goal,
+            tools,
+            if self.success { "outcome" } else { "failed" },
+            self.outcome
+        )
+    }
+}
+
+/// 情景记忆存储：内存缓存 + JSONL 追加写
+pub struct EpisodicMemory {
+    path: PathBuf,
+    episodes: Arc<RwLock<Vec<Episode>>>,
+    max_entries: usize,
+}
+
+impl EpisodicMemory {
+    /// 从 JSONL 文件加载（文件不存在时为空；无法解析的行跳过）
+    pub fn new(path: PathBuf, max_entries: usize) -> Self {
+        let mut episodes: Vec<Episode> = std::fs::read_to_string(&path)
+            .map(|data| {
+                data.lines()
+                    .filter_map(|l| serde_json::from_str(l).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let n = episodes.len();
+        if n > max_entries {
+            episodes.drain(0..n - max_entries);
+        }
+        Self {
+            path,
+            episodes: Arc::new(RwLock::new(episodes)),
+            max_entries,
+        }
+    }
+
+    /// 记录一条情景并追加写入文件
+    pub fn record(&self, episode: Episode) {
+        if episode.goal.is_empty() {
+            return;
+        }
+        if let Ok(line) = serde_json::to_string(&episode) {
+            if let Some(p) = self.path.parent() {
+                let _ = std::fs::create_dir_all(p);
+            }
+            let _ = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .and_then(|mut f| writeln!(f, "{}", line));
+        }
+        let mut episodes = self.episodes.write().unwrap();
+        episodes.push(episode);
+        let n = episodes.len();
+        if n > self.max_entries {
+            episodes.drain(0..n - self.max_entries);
+        }
+    }
+
+    /// 按目标（及工具名）相似度检索最相似的 k 条情景，越新越靠前（同分时）
+    pub fn similar(&self, query: &str, k: usize) -> Vec<Episode> {
+        let query_tokens = tokenizer::tokenize_to_set(query);
+        if query_tokens.is_empty() {
+            return Vec::new();
+        }
+        let episodes = self.episodes.read().unwrap();
+        let mut scored: Vec<(f32, &Episode)> = episodes
+            .iter()
+            .rev()
+            .map(|e| (tokenizer::jaccard_similarity(&query_tokens, &e.tokens()), e))
+            .filter(|(s, _)| *s > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.into_iter().take(k).map(|(_, e)| e.clone()).collect()
+    }
+
+    /// 删除目标或结果包含 pattern 的情景（forget，含已不在内存缓存中的旧行），dry_run 时只列出；
+    /// 文件经临时文件 + rename 原子替换
+    pub fn forget(&self, pattern: &str, dry_run: bool) -> std::io::Result<Vec<String>> {
+        let matches = |e: &Episode| matches_pattern(&e.goal, pattern) || matches_pattern(&e.outcome, pattern);
+        let mut episodes = self.episodes.write().unwrap();
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut hits = Vec::new();
+        let mut kept = String::new();
+        for line in content.lines() {
+            match serde_json::from_str::<Episode>(line) {
+                Ok(e) if matches(&e) => hits.push(e.goal),
+                _ => {
+                    kept.push_str(line);
+                    kept.push('\n');
+                }
+            }
+        }
+        if !dry_run && !hits.is_empty() {
+            write_atomic(&self.path, &kept)?;
+            episodes.retain(|e| !matches(e));
+        }
+        Ok(hits)
+    }
+
+    pub fn len(&self) -> usize {
+        self.episodes.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_retrieve_similar_episodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episodes.jsonl");
+        let mem = EpisodicMemory::new(path.clone(), 10);
+        mem.record(Episode::new(
+            "summarize the rust files in src",
+            &["ls".to_string(), "cat".to_string()],
+            "Wrote a summary of 12 files",
+            true,
+        ));
+        mem.record(Episode::new("weather in Paris tomorrow", &["search".to_string()], "Sunny", true));
+
+        let hits = mem.similar("summarize python files", 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].goal, "summarize the rust files in src");
+        assert!(hits[0].to_prompt_line().contains("tools: ls, cat"));
+
+        // 重新加载后仍可检索
+        let reloaded = EpisodicMemory::new(path, 10);
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.similar("Paris weather", 1)[0].outcome, "Sunny");
+    }
+}
@@ -0,0 +1,331 @@
+//! 知识图谱记忆：从对话中抽取实体与关系，持久化到 memory/graph.json，
+//! 检索时从与问题匹配的实体出发做多跳遍历，回答「谁向我介绍了 X？」这类多跳问题。
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use super::long_term::matches_pattern;
+use super::markdown_store::write_atomic;
+use super::tokenizer;
+use crate::llm::LlmClient;
+use crate::memory::Message;
+
+/// 图谱节点（实体）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeNode {
+    pub id: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default, rename = "type")]
+    pub node_type: String,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+/// 图谱边（关系）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeEdge {
+    pub source: String,
+    pub target: String,
+    pub relationship: String,
+}
+
+/// 知识图谱：主题 + 节点 + 边
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    #[serde(default)]
+    pub topic: String,
+    #[serde(default)]
+    pub nodes: Vec<KnowledgeNode>,
+    #[serde(default)]
+    pub edges: Vec<KnowledgeEdge>,
+}
+
+/// 实体抽取 prompt
+const EXTRACT_PROMPT: &str = r#"Extract entities and relationships from the following text to build a knowledge graph.
+
+Topic: {topic}
+
+Information:
+{text}
+
+Output format (JSON):
+{
+    "nodes": [
+        {"id": "entity1", "label": "Entity Label", "type": "concept|person|organization|event", "properties": {"key": "value"}}
+    ],
+    "edges": [
+        {"source": "entity1", "target": "entity2", "relationship": "related_to"}
+    ]
+}
+Refer to the user as the entity "user".
+
+Knowledge graph:"#;
+
+/// 解析 LLM 返回的图谱 JSON（容忍 ```json 代码块与前后说明文字）
+pub fn parse_graph_json(response: &str) -> Result<KnowledgeGraph, String> {
+    let trimmed = response.trim();
+    let body = match trimmed.find("```json") {
+        Some(start) => {
+            let rest = &trimmed[start + 7..];
+            rest.find("```").map(|end| &rest[..end]).unwrap_or(rest)
+        }
+        None => match (trimmed.find('{'), trimmed.rfind('}')) {
+            (Some(s), Some(e)) if e > s => &trimmed[s..=e],
+            _ => trimmed,
+        },
+    };
+    serde_json::from_str(body.trim()).map_err(|e| format!("Failed to parse graph: {}", e))
+}
+
+/// 调用 LLM 从文本中抽取实体与关系
+pub async fn extract_graph(
+    llm: &dyn LlmClient,
+    topic: &str,
+    text: &str,
+) -> Result<KnowledgeGraph, String> {
+    let prompt = EXTRACT_PROMPT.replace("{topic}", topic).replace("{text}", text);
+    let response = llm
+        .complete(&[Message::user(prompt)])
+        .await
+        .map_err(|e| format!("LLM error: {}", e))?;
+    let mut graph = parse_graph_json(&response)?;
+    graph.topic = topic.to_string();
+    Ok(graph)
+}
+
+fn normalize_id(id: &str) -> String {
+    id.trim().to_lowercase()
+}
+
+/// 实体匹配用分词：去掉标点后按 tokenizer 切分
+fn match_tokens(text: &str) -> HashSet<String> {
+    let cleaned: String = text
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    tokenizer::tokenize_to_set(&cleaned)
+}
+
+/// 持久化知识图谱记忆：节点按规范化 id 合并，边按 (source, target, relationship) 去重
+pub struct GraphMemory {
+    path: PathBuf,
+    graph: RwLock<KnowledgeGraph>,
+}
+
+impl GraphMemory {
+    /// 从 graph.json 加载（不存在或无法解析时为空图）
+    pub fn new(path: PathBuf) -> Self {
+        let graph = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            graph: RwLock::new(graph),
+        }
+    }
+
+    /// 合并一份抽取结果并写回文件，返回新增的边数
+    pub fn merge(&self, extracted: KnowledgeGraph) -> usize {
+        let mut graph = self.graph.write().unwrap();
+        for mut node in extracted.nodes {
+            node.id = normalize_id(&node.id);
+            if node.id.is_empty() {
+                continue;
+            }
+            if node.label.is_empty() {
+                node.label = node.id.clone();
+            }
+            match graph.nodes.iter_mut().find(|n| n.id == node.id) {
+                Some(existing) => existing.properties.extend(node.properties),
+                None => graph.nodes.push(node),
+            }
+        }
+        let mut added = 0;
+        for mut edge in extracted.edges {
+            edge.source = normalize_id(&edge.source);
+            edge.target = normalize_id(&edge.target);
+            edge.relationship = edge.relationship.trim().to_string();
+            if edge.source.is_empty() || edge.target.is_empty() || graph.edges.contains(&edge) {
+                continue;
+            }
+            for id in [&edge.source, &edge.target] {
+                if !graph.nodes.iter().any(|n| &n.id == id) {
+                    graph.nodes.push(KnowledgeNode {
+                        id: id.clone(),
+                        label: id.clone(),
+                        node_type: String::new(),
+                        properties: HashMap::new(),
+                    });
+                }
+            }
+            graph.edges.push(edge);
+            added += 1;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string_pretty(&*graph) {
+            let _ = std::fs::write(&self.path, json);
+        }
+        added
+    }
+
+    /// 多跳检索：以与 query 词重叠的实体为起点，沿边（双向）遍历至多 max_hops 跳，
+    /// 返回「A —关系→ B」形式的事实，最多 max_facts 条（近的优先）
+    pub fn walk(&self, query: &str, max_hops: usize, max_facts: usize) -> Vec<String> {
+        let query_tokens = match_tokens(query);
+        if query_tokens.is_empty() {
+            return Vec::new();
+        }
+        let graph = self.graph.read().unwrap();
+        let label_of = |id: &str| -> String {
+            graph
+                .nodes
+                .iter()
+                .find(|n| n.id == id)
+                .map(|n| n.label.clone())
+                .unwrap_or_else(|| id.to_string())
+        };
+
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<(String, usize)> = VecDeque::new();
+        for node in &graph.nodes {
+            let tokens = match_tokens(&format!("{} {}", node.id, node.label));
+            if !tokens.is_disjoint(&query_tokens) && visited.insert(node.id.clone()) {
+                queue.push_back((node.id.clone(), 0));
+            }
+        }
+
+        let mut facts = Vec::new();
+        let mut seen_edges: HashSet<usize> = HashSet::new();
+        while let Some((id, depth)) = queue.pop_front() {
+            if depth >= max_hops {
+                continue;
+            }
+            for (i, edge) in graph.edges.iter().enumerate() {
+                let next = if edge.source == id {
+                    &edge.target
+                } else if edge.target == id {
+                    &edge.source
+                } else {
+                    continue;
+                };
+                if seen_edges.insert(i) {
+                    facts.push(format!(
+                        "{} —{}→ {}",
+                        label_of(&edge.source),
+                        edge.relationship,
+                        label_of(&edge.target)
+                    ));
+                    if facts.len() >= max_facts {
+                        return facts;
+                    }
+                }
+                if visited.insert(next.clone()) {
+                    queue.push_back((next.clone(), depth + 1));
+                }
+            }
+        }
+        facts
+    }
+
+    /// 删除标签、ID 或属性包含 pattern 的实体及其关联边、关系名包含 pattern 的边（forget），
+    /// dry_run 时只列出；返回命中的实体标签与「A —关系→ B」形式的边
+    pub fn forget(&self, pattern: &str, dry_run: bool) -> std::io::Result<Vec<String>> {
+        let mut graph = self.graph.write().unwrap();
+        let removed_nodes: HashSet<String> = graph
+            .nodes
+            .iter()
+            .filter(|n| {
+                matches_pattern(&n.id, pattern)
+                    || matches_pattern(&n.label, pattern)
+                    || n.properties.iter().any(|(k, v)| matches_pattern(&format!("{} {}", k, v), pattern))
+            })
+            .map(|n| n.id.clone())
+            .collect();
+        let edge_hit = |e: &KnowledgeEdge| {
+            removed_nodes.contains(&e.source)
+                || removed_nodes.contains(&e.target)
+                || matches_pattern(&e.relationship, pattern)
+        };
+        let mut hits: Vec<String> = graph
+            .nodes
+            .iter()
+            .filter(|n| removed_nodes.contains(&n.id))
+            .map(|n| n.label.clone())
+            .collect();
+        hits.extend(
+            graph
+                .edges
+                .iter()
+                .filter(|e| edge_hit(e))
+                .map(|e| format!("{} —{}→ {}", e.source, e.relationship, e.target)),
+        );
+        if !dry_run && !hits.is_empty() {
+            graph.edges.retain(|e| !edge_hit(e));
+            graph.nodes.retain(|n| !removed_nodes.contains(&n.id));
+            let json = serde_json::to_string_pretty(&*graph).map_err(std::io::Error::other)?;
+            write_atomic(&self.path, &json)?;
+        }
+        Ok(hits)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.graph.read().unwrap().nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.graph.read().unwrap().edges.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_graph() -> KnowledgeGraph {
+        parse_graph_json(
+            r#"Here is the graph:
+```json
+{"nodes": [{"id": "Alice", "label": "Alice", "type": "person"},
+           {"id": "Rust", "label": "Rust", "type": "concept"},
+           {"id": "Bob", "label": "Bob", "type": "person"}],
+ "edges": [{"source": "Alice", "target": "Rust", "relationship": "introduced user to"},
+           {"source": "Bob", "target": "Alice", "relationship": "manager of"}]}
+```"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_merge_dedups_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("graph.json");
+        let mem = GraphMemory::new(path.clone());
+        assert_eq!(mem.merge(sample_graph()), 2);
+        assert_eq!(mem.merge(sample_graph()), 0);
+        assert_eq!(mem.node_count(), 3);
+
+        let reloaded = GraphMemory::new(path);
+        assert_eq!(reloaded.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_walk_answers_multi_hop_question() {
+        let dir = tempfile::tempdir().unwrap();
+        let mem = GraphMemory::new(dir.path().join("graph.json"));
+        mem.merge(sample_graph());
+
+        let one_hop = mem.walk("who introduced me to Rust?", 1, 10);
+        assert_eq!(one_hop, vec!["Alice —introduced user to→ Rust".to_string()]);
+
+        let two_hops = mem.walk("who introduced me to Rust?", 2, 10);
+        assert!(two_hops.contains(&"Bob —manager of→ Alice".to_string()));
+        assert!(mem.walk("weather", 2, 10).is_empty());
+    }
+}
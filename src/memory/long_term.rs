@@ -2,6 +2,8 @@
 //!
 //! 支持 add(text) 与 search(query, k)。实现：FileLongTerm（BM25）、InMemoryLongTerm（词重叠）、
 //! InMemoryVectorLongTerm（嵌入 API + 余弦相似度，config [memory].vector_enabled 启用；支持快照持久化）。
+//! DecayPolicy：按条目写入时间做指数衰减与过期清理（config [memory.decay]）。
+//! 嵌入失败时向量后端降级为 long-term.md 关键词检索（with_keyword_fallback），并在 metrics 中标记 retrieval_degraded。
 
 use std::path::Path;
 use std::sync::Arc;
@@ -14,6 +16,26 @@ pub trait LongTermMemory: Send + Sync {
     /// 按查询检索最相关的 k 条，返回文本片段
     fn search(&self, query: &str, k: usize) -> Vec<String>;
 
+    /// 按查询检索并附带得分（降序）；默认按排名给出递减得分，具体实现可返回真实相似度
+    fn search_scored(&self, query: &str, k: usize) -> Vec<(f32, String)> {
+        let hits = self.search(query, k);
+        let n = hits.len().max(1) as f32;
+        hits.into_iter()
+            .enumerate()
+            .map(|(i, t)| (1.0 - i as f32 / n, t))
+            .collect()
+    }
+
+    /// 删除文本完全相同的条目，返回删除数量（默认不支持删除，返回 0）
+    fn remove(&self, _text: &str) -> usize {
+        0
+    }
+
+    /// 列出包含 pattern（不区分大小写）的全部条目原文，供 forget 使用（默认不支持，返回空）
+    fn find_matching(&self, _pattern: &str) -> Vec<String> {
+        Vec::new()
+    }
+
     /// 是否启用（Noop 实现返回 false）
     fn enabled(&self) -> bool {
         true
@@ -25,6 +47,54 @@ pub trait LongTermMemory: Send + Sync {
     }
 }
 
+/// forget 匹配规则：不区分大小写的子串匹配（空 pattern 不匹配任何内容）
+pub fn matches_pattern(text: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    !pattern.is_empty() && text.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+/// 记忆衰减策略：检索得分乘以 0.5^(age / half_life)，超过 ttl 的条目自动过期；
+/// 无时间戳的旧条目不衰减也不过期
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DecayPolicy {
+    /// 半衰期（天），None 表示不衰减
+    pub half_life_days: Option<f64>,
+    /// 过期时间（天），None 表示永不过期
+    pub ttl_days: Option<u64>,
+}
+
+const SECS_PER_DAY: f64 = 86_400.0;
+
+impl DecayPolicy {
+    /// 写入时间为 created_at（unix 秒）的条目在 now 时的得分系数
+    pub fn factor(&self, created_at: Option<i64>, now: i64) -> f64 {
+        match (self.half_life_days, created_at) {
+            (Some(half_life), Some(ts)) if half_life > 0.0 => {
+                let age_days = (now - ts).max(0) as f64 / SECS_PER_DAY;
+                0.5f64.powf(age_days / half_life)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// 条目是否已过期
+    pub fn is_expired(&self, created_at: Option<i64>, now: i64) -> bool {
+        match (self.ttl_days, created_at) {
+            (Some(ttl), Some(ts)) if ttl > 0 => now - ts > (ttl as i64) * SECS_PER_DAY as i64,
+            _ => false,
+        }
+    }
+}
+
+impl From<crate::config::DecaySection> for DecayPolicy {
+    fn from(section: crate::config::DecaySection) -> Self {
+        Self {
+            half_life_days: Some(section.half_life_days).filter(|d| *d > 0.0),
+            ttl_days: Some(section.ttl_days).filter(|d| *d > 0),
+        }
+    }
+}
+
 /// 空实现：未启用长期记忆时使用
 #[derive(Clone, Default)]
 pub struct NoopLongTerm;
@@ -82,6 +152,10 @@ impl LongTermMemory for InMemoryLongTerm {
     }
 
     fn search(&self, query: &str, k: usize) -> Vec<String> {
+        self.search_scored(query, k).into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn search_scored(&self, query: &str, k: usize) -> Vec<(f32, String)> {
         let query_tokens = tokenizer::tokenize_to_set(query);
         if query_tokens.is_empty() {
             return Vec::new();
@@ -93,10 +167,23 @@ impl LongTermMemory for InMemoryLongTerm {
             .filter(|(s, _)| *s > 0.0)
             .collect();
         scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(k);
         scored
-            .into_iter()
-            .take(k)
-            .map(|(_, t)| t)
+    }
+
+    fn remove(&self, text: &str) -> usize {
+        let mut store = self.store.write().unwrap();
+        let before = store.len();
+        store.retain(|(t, _)| t != text.trim());
+        before - store.len()
+    }
+
+    fn find_matching(&self, pattern: &str) -> Vec<String> {
+        let store = self.store.read().unwrap();
+        store
+            .iter()
+            .filter(|(t, _)| matches_pattern(t, pattern))
+            .map(|(t, _)| t.clone())
             .collect()
     }
 }
@@ -107,22 +194,67 @@ impl Default for InMemoryLongTerm {
     }
 }
 
-/// 向量长期记忆：调用嵌入 API 将文本转为向量，检索时按余弦相似度返回 top-k；可选快照路径实现持久化
+/// 向量长期记忆：调用嵌入 API 将文本转为向量，检索时按余弦相似度（乘以衰减系数）返回 top-k；可选快照路径实现持久化
 pub struct InMemoryVectorLongTerm {
-    store: Arc<std::sync::RwLock<Vec<(String, Vec<f32>)>>>,
+    store: Arc<std::sync::RwLock<Vec<VectorSnapshotEntry>>>,
     embedder: Arc<dyn crate::llm::EmbeddingProvider>,
     max_entries: usize,
     snapshot_path: Option<std::path::PathBuf>,
+    decay: DecayPolicy,
+    fallback: Option<Arc<dyn LongTermMemory>>,
 }
 
-/// 快照 JSON 条目（与 vector_snapshot.json 格式一致）
-#[derive(serde::Serialize, serde::Deserialize)]
+/// 快照 JSON 条目（与 vector_snapshot.json 格式一致；created_at 为 unix 秒，旧快照中缺省）
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct VectorSnapshotEntry {
     text: String,
     embedding: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<i64>,
+}
+
+/// 读取 vector_snapshot.json 为 (text, embedding) 列表（供持久化后端迁移）
+pub(crate) fn read_vector_snapshot(path: &Path) -> std::io::Result<Vec<(String, Vec<f32>)>> {
+    let data = std::fs::read_to_string(path)?;
+    let entries: Vec<VectorSnapshotEntry> = serde_json::from_str(&data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    Ok(entries.into_iter().map(|e| (e.text, e.embedding)).collect())
+}
+
+/// 调用嵌入并记录检索健康度；空向量视为失败
+pub(crate) fn embed_tracked(
+    embedder: &dyn crate::llm::EmbeddingProvider,
+    text: &str,
+) -> Result<Vec<f32>, String> {
+    let res = embedder.embed_sync(text).and_then(|v| {
+        if v.is_empty() {
+            Err("empty embedding".to_string())
+        } else {
+            Ok(v)
+        }
+    });
+    crate::observability::Metrics::global()
+        .memory
+        .record_embedding(res.is_ok());
+    res
+}
+
+/// 嵌入失败时降级为关键词检索（未配置 fallback 时返回空）
+pub(crate) fn keyword_fallback_search(
+    fallback: Option<&Arc<dyn LongTermMemory>>,
+    query: &str,
+    k: usize,
+) -> Vec<(f32, String)> {
+    let Some(fallback) = fallback else {
+        return Vec::new();
+    };
+    crate::observability::Metrics::global()
+        .memory
+        .record_fallback_search();
+    fallback.search_scored(query, k)
 }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.is_empty() || b.is_empty() || a.len() != b.len() {
         return 0.0;
     }
@@ -146,6 +278,53 @@ mod tests {
         assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
         assert!((cosine_similarity(&[1.0, 1.0], &[1.0, 1.0]) - 1.0).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_decay_policy() {
+        let now = 100 * SECS_PER_DAY as i64;
+        let policy = DecayPolicy {
+            half_life_days: Some(10.0),
+            ttl_days: Some(30),
+        };
+        assert!((policy.factor(Some(now), now) - 1.0).abs() < 1e-9);
+        assert!((policy.factor(Some(now - 10 * SECS_PER_DAY as i64), now) - 0.5).abs() < 1e-9);
+        assert_eq!(policy.factor(None, now), 1.0);
+        assert!(!policy.is_expired(Some(now - 29 * SECS_PER_DAY as i64), now));
+        assert!(policy.is_expired(Some(now - 31 * SECS_PER_DAY as i64), now));
+        assert!(!policy.is_expired(None, now));
+        assert_eq!(DecayPolicy::default().factor(Some(0), now), 1.0);
+    }
+
+    /// 始终失败的嵌入（模拟嵌入服务不可用）
+    struct FailingEmbedder;
+
+    impl crate::llm::EmbeddingProvider for FailingEmbedder {
+        fn embed_sync(&self, _text: &str) -> Result<Vec<f32>, String> {
+            Err("embedding service unavailable".to_string())
+        }
+    }
+
+    #[test]
+    fn test_vector_long_term_keyword_fallback() {
+        let fallback: Arc<dyn LongTermMemory> = Arc::new(InMemoryLongTerm::default());
+        let lt = InMemoryVectorLongTerm::new(Arc::new(FailingEmbedder), 10)
+            .with_keyword_fallback(Arc::clone(&fallback));
+        lt.add("the deploy script lives in scripts/deploy.sh");
+        assert_eq!(fallback.search("deploy script", 1).len(), 1);
+
+        let hits = lt.search("where is the deploy script", 3);
+        assert_eq!(hits, vec!["the deploy script lives in scripts/deploy.sh".to_string()]);
+        assert!(InMemoryVectorLongTerm::new(Arc::new(FailingEmbedder), 10)
+            .search("deploy", 3)
+            .is_empty());
+
+        let metrics = crate::observability::MemoryMetrics::default();
+        metrics.record_embedding(false);
+        assert!(metrics.is_degraded());
+        metrics.record_embedding(true);
+        assert!(!metrics.is_degraded());
+        assert_eq!(metrics.embedding_failures.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
 }
 
 impl InMemoryVectorLongTerm {
@@ -164,9 +343,7 @@ impl InMemoryVectorLongTerm {
         let store = Arc::new(std::sync::RwLock::new(Vec::new()));
         if let Some(ref path) = path_buf {
             if let Ok(data) = std::fs::read_to_string(path) {
-                if let Ok(entries) = serde_json::from_str::<Vec<VectorSnapshotEntry>>(&data) {
-                    let loaded: Vec<(String, Vec<f32>)> =
-                        entries.into_iter().map(|e| (e.text, e.embedding)).collect();
+                if let Ok(loaded) = serde_json::from_str::<Vec<VectorSnapshotEntry>>(&data) {
                     let n = loaded.len().min(max_entries);
                     let start = loaded.len().saturating_sub(n);
                     store.write().unwrap().extend(loaded.into_iter().skip(start));
@@ -179,21 +356,37 @@ impl InMemoryVectorLongTerm {
             embedder,
             max_entries,
             snapshot_path: path_buf,
+            decay: DecayPolicy::default(),
+            fallback: None,
         }
     }
 
+    /// 嵌入不可用时的降级存储（通常为 long-term.md 上的 FileLongTerm）：add 写入其中，search 改用关键词检索
+    pub fn with_keyword_fallback(mut self, fallback: Arc<dyn LongTermMemory>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// 设置衰减策略，并立即清理已过期条目
+    pub fn with_decay(mut self, decay: DecayPolicy) -> Self {
+        self.decay = decay;
+        self.prune_expired();
+        self
+    }
+
+    /// 删除已过期条目，返回删除数量
+    pub fn prune_expired(&self) -> usize {
+        let now = chrono::Utc::now().timestamp();
+        let mut store = self.store.write().unwrap();
+        let before = store.len();
+        store.retain(|e| !self.decay.is_expired(e.created_at, now));
+        before - store.len()
+    }
+
     /// 将当前 store 写入快照路径（若配置了 snapshot_path）- 同步版本
     pub fn save_snapshot(&self) {
         if let Some(ref path) = self.snapshot_path {
-            let store = self.store.read().unwrap();
-            let entries: Vec<VectorSnapshotEntry> = store
-                .iter()
-                .map(|(text, emb)| VectorSnapshotEntry {
-                    text: text.clone(),
-                    embedding: emb.clone(),
-                })
-                .collect();
-            drop(store);
+            let entries: Vec<VectorSnapshotEntry> = self.store.read().unwrap().clone();
             if let Some(parent) = path.parent() {
                 let _ = std::fs::create_dir_all(parent);
             }
@@ -208,15 +401,7 @@ impl InMemoryVectorLongTerm {
     /// 将当前 store 写入快照路径（若配置了 snapshot_path）- 异步版本
     pub async fn save_snapshot_async(&self) {
         if let Some(ref path) = self.snapshot_path {
-            let store = self.store.read().unwrap();
-            let entries: Vec<VectorSnapshotEntry> = store
-                .iter()
-                .map(|(text, emb)| VectorSnapshotEntry {
-                    text: text.clone(),
-                    embedding: emb.clone(),
-                })
-                .collect();
-            drop(store);
+            let entries: Vec<VectorSnapshotEntry> = self.store.read().unwrap().clone();
             if let Some(parent) = path.parent() {
                 let _ = tokio::fs::create_dir_all(parent).await;
             }
@@ -234,9 +419,7 @@ impl InMemoryVectorLongTerm {
             if let Ok(data) = tokio::fs::read_to_string(path).await {
                 if let Ok(entries) = serde_json::from_str::<Vec<VectorSnapshotEntry>>(&data) {
                     let mut store = self.store.write().unwrap();
-                    for entry in entries {
-                        store.push((entry.text, entry.embedding));
-                    }
+                    store.extend(entries);
                     tracing::debug!("vector snapshot loaded async {} entries", store.len());
                 }
             }
@@ -250,40 +433,86 @@ impl LongTermMemory for InMemoryVectorLongTerm {
         if text.is_empty() {
             return;
         }
-        match self.embedder.embed_sync(text) {
-            Ok(vec) if !vec.is_empty() => {
+        match embed_tracked(self.embedder.as_ref(), text) {
+            Ok(vec) => {
+                let now = chrono::Utc::now().timestamp();
                 let mut store = self.store.write().unwrap();
-                store.push((text.to_string(), vec));
+                store.retain(|e| !self.decay.is_expired(e.created_at, now));
+                store.push(VectorSnapshotEntry {
+                    text: text.to_string(),
+                    embedding: vec,
+                    created_at: Some(now),
+                });
                 let n = store.len();
                 if n > self.max_entries {
                     store.drain(0..n - self.max_entries);
                 }
             }
-            Ok(_) => {}
-            Err(e) => tracing::warn!("vector long-term embed failed: {}", e),
+            Err(e) => {
+                tracing::warn!("vector long-term embed failed: {}", e);
+                if let Some(ref fallback) = self.fallback {
+                    fallback.add(text);
+                }
+            }
         }
     }
 
     fn search(&self, query: &str, k: usize) -> Vec<String> {
+        self.search_scored(query, k).into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn search_scored(&self, query: &str, k: usize) -> Vec<(f32, String)> {
         let query = query.trim();
         if query.is_empty() {
             return Vec::new();
         }
-        let query_vec = match self.embedder.embed_sync(query) {
-            Ok(v) if !v.is_empty() => v,
-            _ => return Vec::new(),
+        let query_vec = match embed_tracked(self.embedder.as_ref(), query) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("vector long-term embed failed, using keyword fallback: {}", e);
+                return keyword_fallback_search(self.fallback.as_ref(), query, k);
+            }
         };
+        let now = chrono::Utc::now().timestamp();
         let store = self.store.read().unwrap();
         let mut scored: Vec<(f32, String)> = store
             .iter()
-            .map(|(text, vec)| (cosine_similarity(&query_vec, vec), text.clone()))
+            .filter(|e| !self.decay.is_expired(e.created_at, now))
+            .map(|e| {
+                let sim = cosine_similarity(&query_vec, &e.embedding);
+                (sim * self.decay.factor(e.created_at, now) as f32, e.text.clone())
+            })
             .filter(|(s, _)| *s > 0.0)
             .collect();
         scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-        scored.into_iter().take(k).map(|(_, t)| t).collect()
+        scored.truncate(k);
+        scored
+    }
+
+    fn remove(&self, text: &str) -> usize {
+        let removed = {
+            let mut store = self.store.write().unwrap();
+            let before = store.len();
+            store.retain(|e| e.text != text.trim());
+            before - store.len()
+        };
+        if removed > 0 {
+            self.save_snapshot();
+        }
+        removed
+    }
+
+    fn find_matching(&self, pattern: &str) -> Vec<String> {
+        let store = self.store.read().unwrap();
+        store
+            .iter()
+            .filter(|e| matches_pattern(&e.text, pattern))
+            .map(|e| e.text.clone())
+            .collect()
     }
 
     fn flush(&self) {
+        self.prune_expired();
         self.save_snapshot();
     }
 }
@@ -7,7 +7,9 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::memory::long_term::LongTermMemory;
+use crate::memory::long_term::{matches_pattern, DecayPolicy, LongTermMemory};
+use crate::memory::scope::{resolve_segment, sanitize_segment};
+use crate::memory::tokenizer::{jaccard_similarity, tokenize_to_set};
 use crate::memory::{Message, Role};
 
 /// 记忆根目录：memory/
@@ -17,12 +19,10 @@ pub fn memory_root(workspace: &Path) -> PathBuf {
 
 /// 指定助手的记忆根目录：memory/{assistant_id}/，使每个助手拥有独立的长期记忆
 pub fn assistant_memory_root(workspace: &Path, assistant_id: &str) -> PathBuf {
-    let safe_id: String = assistant_id
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
-        .collect();
-    let dir = if safe_id.is_empty() { "default" } else { safe_id.as_str() };
-    memory_root(workspace).join(dir)
+    let root = memory_root(workspace);
+    let dir = sanitize_segment(assistant_id).unwrap_or_else(|| "default".to_string());
+    let dir = resolve_segment(&root, &dir);
+    root.join(dir)
 }
 
 /// 当日日志路径：memory/logs/YYYY-MM-DD.md
@@ -55,6 +55,16 @@ pub fn vector_snapshot_path(memory_root: &Path) -> PathBuf {
     memory_root.join("vector_snapshot.json")
 }
 
+/// 情景记忆路径：memory/episodes.jsonl（每次完成的会话一行）
+pub fn episodes_path(memory_root: &Path) -> PathBuf {
+    memory_root.join("episodes.jsonl")
+}
+
+/// 知识图谱记忆路径：memory/graph.json
+pub fn graph_path(memory_root: &Path) -> PathBuf {
+    memory_root.join("graph.json")
+}
+
 /// 心跳日志路径：memory/heartbeat_log.md（后台心跳结果沉淀，供下次心跳或用户查看）
 pub fn heartbeat_log_path(memory_root: &Path) -> PathBuf {
     memory_root.join("heartbeat_log.md")
@@ -134,6 +144,105 @@ pub fn append_lesson(path: &Path, line: &str) -> std::io::Result<()> {
         .write_all(content.as_bytes())
 }
 
+/// lessons / preferences 单行条目的正文（去掉列表前缀「- 」）
+pub fn list_line_text(line: &str) -> &str {
+    let t = line.trim();
+    t.strip_prefix("- ").unwrap_or(t).trim()
+}
+
+/// 原子写：先写同目录临时文件再 rename，避免写到一半失败留下残缺文件
+pub(crate) fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// 上一份完好快照的备份路径：{path}.bak
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut bak = path.as_os_str().to_owned();
+    bak.push(".bak");
+    PathBuf::from(bak)
+}
+
+/// 两阶段提交写：写临时文件并 fsync，旧文件转为 .bak（上一份完好版本），再原子 rename 并 fsync 目录。
+/// 任一时刻崩溃，path 或 path.bak 至少有一份完整内容
+pub fn write_durable(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut f = std::fs::File::create(&tmp)?;
+        f.write_all(content.as_bytes())?;
+        f.sync_all()?;
+    }
+    if path.exists() {
+        std::fs::rename(path, backup_path(path))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// 读取 path 并用 parse 校验；文件缺失或损坏时回退到 .bak。返回 (内容, 是否来自备份)
+pub fn read_with_backup<T>(path: &Path, parse: impl Fn(&str) -> Option<T>) -> Option<(T, bool)> {
+    if let Some(v) = std::fs::read_to_string(path).ok().and_then(|s| parse(&s)) {
+        return Some((v, false));
+    }
+    let bak = backup_path(path);
+    let v = std::fs::read_to_string(&bak).ok().and_then(|s| parse(&s))?;
+    tracing::warn!(path = %path.display(), "snapshot missing or corrupt, recovered from {}", bak.display());
+    Some((v, true))
+}
+
+/// 删除 lessons.md / preferences.md 中满足 pred 的行（dry_run 时只列出），返回命中行的正文
+fn filter_list_lines(
+    path: &Path,
+    dry_run: bool,
+    pred: impl Fn(&str) -> bool,
+) -> std::io::Result<Vec<String>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut hits = Vec::new();
+    let kept: Vec<&str> = content
+        .lines()
+        .filter(|l| {
+            let text = list_line_text(l);
+            let hit = !text.is_empty() && pred(text);
+            if hit {
+                hits.push(text.to_string());
+            }
+            !hit
+        })
+        .collect();
+    if !hits.is_empty() && !dry_run {
+        let mut out = kept.join("\n");
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        write_atomic(path, &out)?;
+    }
+    Ok(hits)
+}
+
+/// 从 lessons.md / preferences.md 中删除正文与 text 相同的行，返回删除行数
+pub fn remove_list_line(path: &Path, text: &str) -> std::io::Result<usize> {
+    let target = text.trim();
+    filter_list_lines(path, false, |t| t == target).map(|hits| hits.len())
+}
+
+/// 从 lessons.md / preferences.md 中删除包含 pattern 的行（forget），dry_run 时只列出
+pub fn forget_list_lines(path: &Path, pattern: &str, dry_run: bool) -> std::io::Result<Vec<String>> {
+    filter_list_lines(path, dry_run, |t| matches_pattern(t, pattern))
+}
+
 /// 追加一条程序记忆（工具名、成功/失败、简要原因），用于自我进化
 pub fn append_procedural(path: &Path, tool: &str, success: bool, detail: &str) -> std::io::Result<()> {
     if let Some(p) = path.parent() {
@@ -285,12 +394,13 @@ pub fn append_daily_log(
     let mut content = String::new();
     content.push_str(&format!("\n## Session {} ({})\n\n", session_id, date));
     for m in messages {
-        let (role, body) = match m.role {
-            Role::User => ("User", m.content.as_str()),
-            Role::Assistant => ("Assistant", m.content.as_str()),
-            Role::System => ("System", m.content.as_str()),
-            Role::Tool => ("Tool", m.content.as_str()),
+        let role = match m.role {
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+            Role::System => "System",
+            Role::Tool => "Tool",
         };
+        let body = m.transcript_text();
         content.push_str(&format!("### {}\n\n{}\n\n", role, body));
     }
     content.push_str("---\n\n");
@@ -302,13 +412,89 @@ pub fn append_daily_log(
     Ok(())
 }
 
+/// 在 memory/logs/*.md 中删除包含 pattern 的消息段落（「### Role」块），dry_run 时只列出；
+/// 先为所有日志计算新内容，再逐个原子替换。返回「日期: 内容预览」列表
+pub fn forget_in_daily_logs(memory_root: &Path, pattern: &str, dry_run: bool) -> std::io::Result<Vec<String>> {
+    let logs_dir = memory_root.join("logs");
+    if !logs_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&logs_dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().map(|x| x == "md").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    let mut hits = Vec::new();
+    let mut rewrites: Vec<(PathBuf, String)> = Vec::new();
+    for path in files {
+        let content = std::fs::read_to_string(&path)?;
+        let date = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        // 按「## / ### / ---」行切分为段落，仅删除命中的「### Role」消息段
+        let mut chunks: Vec<Vec<&str>> = vec![Vec::new()];
+        for line in content.lines() {
+            if line.starts_with("## ") || line.starts_with("### ") || line.starts_with("---") {
+                chunks.push(Vec::new());
+            }
+            chunks.last_mut().unwrap().push(line);
+        }
+        let mut changed = false;
+        let kept: Vec<String> = chunks
+            .into_iter()
+            .filter(|chunk| {
+                let is_message = chunk.first().map(|l| l.starts_with("### ")).unwrap_or(false);
+                let body = chunk[1.min(chunk.len())..].join("\n");
+                if is_message && matches_pattern(&body, pattern) {
+                    let preview: String = body.trim().chars().take(80).collect();
+                    hits.push(format!("{}: {}", date, preview));
+                    changed = true;
+                    return false;
+                }
+                true
+            })
+            .map(|chunk| chunk.join("\n"))
+            .filter(|c| !c.is_empty())
+            .collect();
+        if changed {
+            rewrites.push((path, kept.join("\n") + "\n"));
+        }
+    }
+    if !dry_run {
+        for (path, content) in rewrites {
+            write_atomic(&path, &content)?;
+        }
+    }
+    Ok(hits)
+}
+
 /// 长期记忆：Markdown 文件存储 + BM25 风格关键词检索（预留向量+混合检索扩展）
 #[derive(Clone)]
 pub struct FileLongTerm {
     path: PathBuf,
-    /// 内存缓存 (text, token_set) 用于检索；启动时从文件加载
-    store: Arc<std::sync::RwLock<Vec<(String, std::collections::HashSet<String>)>>>,
+    /// 内存缓存用于检索；启动时从文件加载
+    store: Arc<std::sync::RwLock<Vec<FileEntry>>>,
     max_entries: usize,
+    decay: DecayPolicy,
+}
+
+/// long-term.md 中的一块：标题（通常为写入时间）、正文、分词集合、解析出的写入时间（unix 秒）
+#[derive(Clone)]
+struct FileEntry {
+    heading: Option<String>,
+    text: String,
+    tokens: std::collections::HashSet<String>,
+    created_at: Option<i64>,
+}
+
+/// 块标题时间格式（与 add 写入一致）
+const BLOCK_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+fn parse_block_time(heading: &str) -> Option<i64> {
+    let naive = chrono::NaiveDateTime::parse_from_str(heading.trim(), BLOCK_TIME_FORMAT).ok()?;
+    naive
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|t| t.timestamp())
 }
 
 /// 简单分词：按空白切分、转小写、过滤单字符，用于 BM25 检索
@@ -319,8 +505,8 @@ fn tokenize_lower(s: &str) -> std::collections::HashSet<String> {
         .collect()
 }
 
-/// 按 Markdown 二级标题（## ...）分块；无标题时整段视为一块
-fn split_blocks(content: &str) -> Vec<String> {
+/// 按 Markdown 二级标题（## ...）分块，返回 (标题, 正文)；无标题时整段视为一块
+fn split_blocks(content: &str) -> Vec<(Option<String>, String)> {
     let content = content.trim();
     if content.is_empty() {
         return Vec::new();
@@ -331,17 +517,16 @@ fn split_blocks(content: &str) -> Vec<String> {
         if block.is_empty() {
             continue;
         }
-        let text = if block.contains('\n') {
-            block.split_once('\n').map(|x| x.1).unwrap_or(block).trim()
-        } else {
-            block
+        let (heading, text) = match block.split_once('\n') {
+            Some((h, t)) => (Some(h.trim_start_matches("## ").trim().to_string()), t.trim()),
+            None => (None, block),
         };
         if !text.is_empty() {
-            blocks.push(text.to_string());
+            blocks.push((heading, text.to_string()));
         }
     }
     if blocks.is_empty() {
-        blocks.push(content.to_string());
+        blocks.push((None, content.to_string()));
     }
     blocks
 }
@@ -353,11 +538,78 @@ impl FileLongTerm {
             path,
             store: store.clone(),
             max_entries,
+            decay: DecayPolicy::default(),
         };
         s.load_from_disk();
         s
     }
 
+    /// 设置衰减策略；配置了 ttl 时立即清理过期块（同时重写 long-term.md）
+    pub fn with_decay(mut self, decay: DecayPolicy) -> Self {
+        self.decay = decay;
+        self.prune_expired();
+        self
+    }
+
+    /// 删除已过期块并重写文件，返回删除数量
+    pub fn prune_expired(&self) -> usize {
+        let now = chrono::Utc::now().timestamp();
+        let mut store = self.store.write().unwrap();
+        let before = store.len();
+        store.retain(|e| !self.decay.is_expired(e.created_at, now));
+        let removed = before - store.len();
+        if removed > 0 {
+            self.rewrite_file(&store);
+        }
+        removed
+    }
+
+    /// 清理近似重复块（文本相同或 Jaccard 相似度 >= threshold），仅保留最新一块并重写文件，返回删除数量
+    pub fn dedup_near_duplicates(&self, threshold: f32) -> usize {
+        if threshold <= 0.0 {
+            return 0;
+        }
+        let mut store = self.store.write().unwrap();
+        let sets: Vec<_> = store.iter().map(|e| tokenize_to_set(&e.text)).collect();
+        let mut keep = vec![true; store.len()];
+        // 文件按写入顺序排列，从最新一块往前比较
+        for i in (0..store.len()).rev() {
+            if !keep[i] {
+                continue;
+            }
+            for j in 0..i {
+                if keep[j]
+                    && (store[j].text == store[i].text
+                        || jaccard_similarity(&sets[i], &sets[j]) >= threshold)
+                {
+                    keep[j] = false;
+                }
+            }
+        }
+        let before = store.len();
+        let mut flags = keep.into_iter();
+        store.retain(|_| flags.next().unwrap_or(true));
+        let removed = before - store.len();
+        if removed > 0 {
+            self.rewrite_file(&store);
+        }
+        removed
+    }
+
+    /// 用当前缓存重写 long-term.md（过期清理、删除条目后调用）
+    fn rewrite_file(&self, store: &[FileEntry]) {
+        let content: String = store
+            .iter()
+            .map(|e| match e.heading {
+                Some(ref h) => format!("\n\n## {}\n\n{}\n\n", h, e.text),
+                None => format!("\n\n{}\n\n", e.text),
+            })
+            .collect();
+        if let Err(e) = std::fs::write(&self.path, content) {
+            tracing::warn!("failed to rewrite {:?}: {}", self.path, e);
+        }
+    }
+
     fn load_from_disk(&mut self) {
         if !self.path.exists() {
             if let Some(p) = self.path.parent() {
@@ -368,9 +620,10 @@ impl FileLongTerm {
         if let Ok(content) = std::fs::read_to_string(&self.path) {
             let blocks = split_blocks(&content);
             let mut store = self.store.write().unwrap();
-            for text in blocks {
+            for (heading, text) in blocks {
                 let tokens = tokenize_lower(&text);
-                store.push((text, tokens));
+                let created_at = heading.as_deref().and_then(parse_block_time);
+                store.push(FileEntry { heading, text, tokens, created_at });
             }
             let n = store.len();
             if n > self.max_entries {
@@ -401,15 +654,21 @@ impl super::LongTermMemory for FileLongTerm {
             return;
         }
         let tokens = tokenize_lower(text);
+        let now = chrono::Local::now();
+        let timestamp = now.format(BLOCK_TIME_FORMAT).to_string();
         {
             let mut store = self.store.write().unwrap();
-            store.push((text.to_string(), tokens));
+            store.push(FileEntry {
+                heading: Some(timestamp.clone()),
+                text: text.to_string(),
+                tokens,
+                created_at: Some(now.timestamp()),
+            });
             let n = store.len();
             if n > self.max_entries {
                 store.drain(0..n - self.max_entries);
             }
         }
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M");
         let block = format!("\n\n## {}\n\n{}\n\n", timestamp, text);
         if let Some(p) = self.path.parent() {
             let _ = std::fs::create_dir_all(p);
@@ -422,24 +681,111 @@ impl super::LongTermMemory for FileLongTerm {
     }
 
     fn search(&self, query: &str, k: usize) -> Vec<String> {
+        self.search_scored(query, k).into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn search_scored(&self, query: &str, k: usize) -> Vec<(f32, String)> {
         let query_tokens = tokenize_lower(query);
         if query_tokens.is_empty() {
             return Vec::new();
         }
+        let now = chrono::Utc::now().timestamp();
         let store = self.store.read().unwrap();
-        let mut scored: Vec<(f64, String)> = store
+        let mut scored: Vec<(f32, String)> = store
             .iter()
-            .map(|(text, doc_tokens)| {
-                let s = Self::score(&query_tokens, doc_tokens, doc_tokens.len());
-                (s, text.clone())
+            .filter(|e| !self.decay.is_expired(e.created_at, now))
+            .map(|e| {
+                let s = Self::score(&query_tokens, &e.tokens, e.tokens.len())
+                    * self.decay.factor(e.created_at, now);
+                (s as f32, e.text.clone())
             })
             .filter(|(s, _)| *s > 0.0)
             .collect();
         scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-        scored.into_iter().take(k).map(|(_, t)| t).collect()
+        scored.truncate(k);
+        scored
+    }
+
+    fn remove(&self, text: &str) -> usize {
+        let mut store = self.store.write().unwrap();
+        let before = store.len();
+        store.retain(|e| e.text != text.trim());
+        let removed = before - store.len();
+        if removed > 0 {
+            self.rewrite_file(&store);
+        }
+        removed
+    }
+
+    fn find_matching(&self, pattern: &str) -> Vec<String> {
+        let store = self.store.read().unwrap();
+        store
+            .iter()
+            .filter(|e| matches_pattern(&e.text, pattern))
+            .map(|e| e.text.clone())
+            .collect()
     }
 
     fn enabled(&self) -> bool {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_blocks(path: &Path, blocks: &[(&str, &str)]) {
+        let content: String = blocks
+            .iter()
+            .map(|(h, t)| format!("\n\n## {}\n\n{}\n\n", h, t))
+            .collect();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_file_long_term_decay_prefers_recent_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("long-term.md");
+        let old = (chrono::Local::now() - chrono::Duration::days(90))
+            .format(BLOCK_TIME_FORMAT)
+            .to_string();
+        let recent = chrono::Local::now().format(BLOCK_TIME_FORMAT).to_string();
+        write_blocks(&path, &[(&old, "deploy target staging"), (&recent, "deploy target is the prod cluster")]);
+
+        let lt = FileLongTerm::new(path.clone(), 100);
+        assert_eq!(lt.search("deploy target", 1), vec!["deploy target staging"]);
+
+        let lt = FileLongTerm::new(path.clone(), 100).with_decay(DecayPolicy {
+            half_life_days: Some(30.0),
+            ttl_days: None,
+        });
+        assert_eq!(lt.search("deploy target", 1), vec!["deploy target is the prod cluster"]);
+
+        let lt = FileLongTerm::new(path.clone(), 100).with_decay(DecayPolicy {
+            half_life_days: None,
+            ttl_days: Some(30),
+        });
+        assert_eq!(lt.search("deploy target", 5), vec!["deploy target is the prod cluster"]);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("staging"));
+        assert!(content.contains(&format!("## {}", recent)));
+    }
+
+    #[test]
+    fn test_write_durable_keeps_last_good_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("s.json");
+        let parse = |s: &str| serde_json::from_str::<serde_json::Value>(s).ok();
+        assert!(read_with_backup(&path, parse).is_none());
+
+        write_durable(&path, r#"{"v":1}"#).unwrap();
+        write_durable(&path, r#"{"v":2}"#).unwrap();
+        assert_eq!(read_with_backup(&path, parse), Some((serde_json::json!({"v": 2}), false)));
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), r#"{"v":1}"#);
+
+        // 模拟写到一半崩溃：主文件残缺时从备份恢复
+        std::fs::write(&path, r#"{"v":"#).unwrap();
+        assert_eq!(read_with_backup(&path, parse), Some((serde_json::json!({"v": 1}), true)));
+    }
+}
@@ -5,26 +5,41 @@
 pub mod async_io;
 #[cfg(feature = "async-sqlite")]
 pub mod async_persistence;
+pub mod bundle;
 pub mod conversation;
+pub mod episodic;
+pub mod graph;
 pub mod learnings;
 pub mod long_term;
 pub mod markdown_store;
 pub mod persistence;
 pub mod rag;
+pub mod scope;
 pub mod token_budget;
 pub mod tokenizer;
 pub mod user_memory;
+pub mod vector_backends;
 pub mod working;
 
+pub use bundle::{
+    export_assistant_memory, import_assistant_memory, BundleFile, ImportReport, MemoryBundle,
+};
 pub use conversation::{
-    ConversationMemory, Message, MessageImportance, PruneConfig, PruneResult, Role,
+    dedup_observations, importance_score, is_observation, ConversationMemory, Message, MessageImportance,
+    PruneConfig, PruneResult, Role,
+};
+pub use episodic::{Episode, EpisodicMemory};
+pub use graph::{GraphMemory, KnowledgeEdge, KnowledgeGraph, KnowledgeNode};
+pub use long_term::{
+    matches_pattern, DecayPolicy, InMemoryLongTerm, InMemoryVectorLongTerm, LongTermMemory,
+    NoopLongTerm,
 };
-pub use long_term::{InMemoryLongTerm, InMemoryVectorLongTerm, LongTermMemory, NoopLongTerm};
 pub use markdown_store::{
     append_daily_log, append_lesson, append_preference, append_procedural, assistant_memory_root,
-    consolidate_memory, daily_log_path, list_daily_logs_for_llm, load_lessons, load_preferences,
-    load_procedural, append_heartbeat_log, heartbeat_log_path, long_term_path, lessons_path,
-    memory_root, preferences_path, procedural_path, vector_snapshot_path, ConsolidateResult,
+    consolidate_memory, daily_log_path, episodes_path, forget_in_daily_logs, forget_list_lines, graph_path, list_daily_logs_for_llm, load_lessons,
+    load_preferences, load_procedural, append_heartbeat_log, heartbeat_log_path, list_line_text, long_term_path,
+    backup_path, lessons_path, memory_root, preferences_path, procedural_path, read_with_backup, remove_list_line,
+    vector_snapshot_path, write_durable, ConsolidateResult,
     FileLongTerm,
 };
 pub use learnings::{
@@ -32,6 +47,7 @@ pub use learnings::{
     record_error, record_feature_request, record_learning, soul_path, tools_guide_path,
 };
 pub use persistence::{ConversationPersistence, SqlitePersistence};
+pub use scope::MemoryScope;
 pub use token_budget::{MemoryCache, MemorySegment, TokenBudget, TokenEstimator};
 pub use working::WorkingMemory;
 pub use async_io::{
@@ -46,3 +62,6 @@ pub use async_persistence::{AsyncPersistence, AsyncSqlitePersistence};
 
 pub use rag::{Chunk, Chunker, ChunkingConfig, RagPipeline, RetrievalResult, VectorStore};
 pub use user_memory::{UserMemoryConfig, UserMemoryManager, UserScopedMemory};
+pub use vector_backends::SqliteVectorLongTerm;
+#[cfg(feature = "pgvector")]
+pub use vector_backends::PgVectorLongTerm;
@@ -22,13 +22,14 @@ impl ConversationPersistence {
         let messages: Vec<SerMessage> = serde_json::from_str(&data)?;
         Ok(messages
             .into_iter()
-            .map(|m| Message {
-                role: match m.role.as_str() {
+            .map(|m| {
+                let role = match m.role.as_str() {
                     "user" => Role::User,
                     "assistant" => Role::Assistant,
+                    "tool" => Role::Tool,
                     _ => Role::System,
-                },
-                content: m.content,
+                };
+                Message::new(role, m.content).with_tool_call(m.tool_call_id, m.tool_name)
             })
             .collect())
     }
@@ -48,6 +49,8 @@ impl ConversationPersistence {
                 }
                 .to_string(),
                 content: m.content.clone(),
+                tool_call_id: m.tool_call_id.clone(),
+                tool_name: m.tool_name.clone(),
             })
             .collect();
         std::fs::write(&self.path, serde_json::to_string_pretty(&ser)?)?;
@@ -59,6 +62,10 @@ impl ConversationPersistence {
 struct SerMessage {
     role: String,
     content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
 }
 
 use rusqlite::{params, Connection, Result as SqliteResult};
@@ -82,10 +89,21 @@ impl SqlitePersistence {
                 id TEXT PRIMARY KEY,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL,
-                title TEXT
+                title TEXT,
+                scope TEXT NOT NULL DEFAULT ''
             )",
             [],
         )?;
+
+        // 旧库升级：补充会话所属的记忆作用域列（空串为全局作用域）
+        let has_scope = self
+            .conn
+            .prepare("SELECT 1 FROM pragma_table_info('sessions') WHERE name = 'scope'")?
+            .exists([])?;
+        if !has_scope {
+            self.conn
+                .execute("ALTER TABLE sessions ADD COLUMN scope TEXT NOT NULL DEFAULT ''", [])?;
+        }
         
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS messages (
@@ -94,10 +112,24 @@ impl SqlitePersistence {
                 role TEXT NOT NULL,
                 content TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                tool_call_id TEXT,
+                tool_name TEXT,
                 FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
             )",
             [],
         )?;
+
+        // 旧库升级：补充工具调用列
+        for column in ["tool_call_id", "tool_name"] {
+            let exists = self
+                .conn
+                .prepare("SELECT 1 FROM pragma_table_info('messages') WHERE name = ?1")?
+                .exists([column])?;
+            if !exists {
+                self.conn
+                    .execute(&format!("ALTER TABLE messages ADD COLUMN {} TEXT", column), [])?;
+            }
+        }
         
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS checkpoints (
@@ -120,10 +152,15 @@ impl SqlitePersistence {
     }
 
     pub fn create_session(&self, session_id: &str, title: Option<&str>) -> SqliteResult<()> {
+        self.create_session_in_scope(session_id, title, "")
+    }
+
+    /// 创建属于某记忆作用域的会话（scope 为 MemoryScope::vector_namespace，空串为全局作用域）
+    pub fn create_session_in_scope(&self, session_id: &str, title: Option<&str>, scope: &str) -> SqliteResult<()> {
         let now = Utc::now().to_rfc3339();
         self.conn.execute(
-            "INSERT OR REPLACE INTO sessions (id, created_at, updated_at, title) VALUES (?1, ?2, ?3, ?4)",
-            params![session_id, now, now, title],
+            "INSERT OR REPLACE INTO sessions (id, created_at, updated_at, title, scope) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![session_id, now, now, title, scope],
         )?;
         Ok(())
     }
@@ -138,8 +175,8 @@ impl SqlitePersistence {
         let now = Utc::now().to_rfc3339();
         
         self.conn.execute(
-            "INSERT INTO messages (session_id, role, content, created_at) VALUES (?1, ?2, ?3, ?4)",
-            params![session_id, role_str, message.content, now],
+            "INSERT INTO messages (session_id, role, content, created_at, tool_call_id, tool_name) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![session_id, role_str, message.content, now, message.tool_call_id, message.tool_name],
         )?;
         
         self.conn.execute(
@@ -152,7 +189,7 @@ impl SqlitePersistence {
 
     pub fn load_messages(&self, session_id: &str) -> SqliteResult<Vec<Message>> {
         let mut stmt = self.conn.prepare(
-            "SELECT role, content FROM messages WHERE session_id = ?1 ORDER BY id ASC"
+            "SELECT role, content, tool_call_id, tool_name FROM messages WHERE session_id = ?1 ORDER BY id ASC"
         )?;
         
         let messages = stmt.query_map([session_id], |row| {
@@ -161,9 +198,10 @@ impl SqlitePersistence {
             let role = match role_str.as_str() {
                 "user" => Role::User,
                 "assistant" => Role::Assistant,
+                "tool" => Role::Tool,
                 _ => Role::System,
             };
-            Ok(Message { role, content })
+            Ok(Message::new(role, content).with_tool_call(row.get(2)?, row.get(3)?))
         })?.collect::<SqliteResult<Vec<_>>>()?;
         
         Ok(messages)
@@ -218,4 +256,32 @@ impl SqlitePersistence {
         )?;
         Ok(())
     }
+
+    /// 删除作用域 scope 内会话中内容包含 pattern（不区分大小写）的消息（forget），dry_run 时只列出；
+    /// 查询与删除在同一事务内完成，按命中的消息 id 删除
+    pub fn forget_messages(&self, pattern: &str, scope: &str, dry_run: bool) -> SqliteResult<Vec<String>> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let matched = {
+            let mut stmt = tx.prepare(
+                "SELECT m.id, m.content FROM messages m JOIN sessions s ON s.id = m.session_id
+                 WHERE s.scope = ?2 AND instr(lower(m.content), lower(?1)) > 0 ORDER BY m.id ASC",
+            )?;
+            let rows = stmt.query_map(params![pattern, scope], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+            rows.collect::<SqliteResult<Vec<_>>>()?
+        };
+        if !dry_run {
+            let mut delete = tx.prepare("DELETE FROM messages WHERE id = ?1")?;
+            for (id, _) in &matched {
+                delete.execute([id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(matched.into_iter().map(|(_, content)| content).collect())
+    }
 }
@@ -0,0 +1,202 @@
+//! 记忆作用域：按 user / assistant / namespace 隔离长期记忆
+//!
+//! - 文件记忆目录：memory/[users/{user_id}/][{assistant_id}/][ns/{namespace}/]
+//! - 向量后端 namespace：无 user / namespace 时与旧版一致（assistant_id 或 "default"）
+//!
+//! 多用户 Gateway 部署时每个用户使用独立目录与 namespace，避免记忆串入他人 prompt。
+//! 各片段经 [`sanitize_segment`] 转为目录名：不同的 id 不会共用同一目录。
+//! 旧版本按不带哈希后缀的名字建目录（如 `my.bot` -> `memory/my_bot/`），升级后新目录不存在而旧目录存在时沿用旧目录，
+//! 已有的长期记忆、教训与向量不会丢失（见 [`resolve_segment`]）。
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::memory::markdown_store::memory_root;
+
+/// 记忆作用域；全部为空时即全局 memory/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MemoryScope {
+    pub user_id: Option<String>,
+    pub assistant_id: Option<String>,
+    pub namespace: Option<String>,
+}
+
+/// 路径片段清洗：只含字母数字、'-'、'_' 时原样返回；含其它字符时替换为 '_' 并追加 `~<原文 SHA-256 前 8 字节十六进制>`，
+/// 使不同原文（如 `a/b` 与 `a_b`）不会落到同一目录。空串返回 None
+pub(crate) fn sanitize_segment(s: &str) -> Option<String> {
+    if s.is_empty() {
+        return None;
+    }
+    let safe: String = s
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if safe == s {
+        return Some(safe);
+    }
+    let digest = Sha256::digest(s.as_bytes());
+    let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    Some(format!("{}~{}", safe, hash))
+}
+
+/// parent 下片段 seg 的实际目录名：带哈希后缀的目录不存在、但旧版（去掉 `~<hash>` 的）目录存在时沿用旧目录名
+pub(crate) fn resolve_segment(parent: &Path, seg: &str) -> String {
+    if let Some((legacy, _)) = seg.split_once('~') {
+        if !legacy.is_empty() && !parent.join(seg).exists() && parent.join(legacy).is_dir() {
+            return legacy.to_string();
+        }
+    }
+    seg.to_string()
+}
+
+impl MemoryScope {
+    /// 仅按助手隔离（与 memory/{assistant_id}/ 旧布局一致）
+    pub fn for_assistant(assistant_id: Option<&str>) -> Self {
+        Self::default().with_assistant(assistant_id)
+    }
+
+    /// 仅按用户隔离
+    pub fn for_user(user_id: impl Into<String>) -> Self {
+        let user_id: String = user_id.into();
+        Self::default().with_user(Some(user_id.as_str()))
+    }
+
+    pub fn with_user(mut self, user_id: Option<&str>) -> Self {
+        self.user_id = user_id.and_then(sanitize_segment);
+        self
+    }
+
+    pub fn with_assistant(mut self, assistant_id: Option<&str>) -> Self {
+        self.assistant_id = assistant_id.and_then(sanitize_segment);
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: Option<&str>) -> Self {
+        self.namespace = namespace.and_then(sanitize_segment);
+        self
+    }
+
+    /// 是否为全局作用域（memory/ 根目录）
+    pub fn is_global(&self) -> bool {
+        self.user_id.is_none() && self.assistant_id.is_none() && self.namespace.is_none()
+    }
+
+    /// 该作用域的记忆根目录（旧版目录仍在使用时沿用，见 [`Self::resolve`]）
+    pub fn root(&self, workspace: &Path) -> PathBuf {
+        self.resolve(workspace).plain_root(workspace)
+    }
+
+    fn plain_root(&self, workspace: &Path) -> PathBuf {
+        let mut root = memory_root(workspace);
+        if let Some(ref u) = self.user_id {
+            root = root.join("users").join(u);
+        }
+        if let Some(ref a) = self.assistant_id {
+            root = root.join(a);
+        }
+        if let Some(ref ns) = self.namespace {
+            root = root.join("ns").join(ns);
+        }
+        root
+    }
+
+    /// 按 workspace 中已有的目录解析各片段：旧版目录仍在使用的片段换回旧名，
+    /// 使目录与 vector_namespace 都与升级前一致
+    pub fn resolve(&self, workspace: &Path) -> Self {
+        let mut parent = memory_root(workspace);
+        let mut resolved = self.clone();
+        if let Some(ref u) = self.user_id {
+            parent = parent.join("users");
+            let u = resolve_segment(&parent, u);
+            parent = parent.join(&u);
+            resolved.user_id = Some(u);
+        }
+        if let Some(ref a) = self.assistant_id {
+            let a = resolve_segment(&parent, a);
+            parent = parent.join(&a);
+            resolved.assistant_id = Some(a);
+        }
+        if let Some(ref ns) = self.namespace {
+            resolved.namespace = Some(resolve_segment(&parent.join("ns"), ns));
+        }
+        resolved
+    }
+
+    /// 共享表后端（sqlite / pgvector）使用的 namespace 键
+    pub fn vector_namespace(&self) -> String {
+        let mut key = self.assistant_id.clone().unwrap_or_else(|| "default".to_string());
+        if let Some(ref u) = self.user_id {
+            key = format!("user:{}/{}", u, key);
+        }
+        if let Some(ref ns) = self.namespace {
+            key = format!("{}/ns:{}", key, ns);
+        }
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assistant_scope_matches_legacy_layout() {
+        let ws = Path::new("/tmp/ws");
+        let scope = MemoryScope::for_assistant(Some("coder"));
+        assert_eq!(scope.root(ws), ws.join("memory").join("coder"));
+        assert_eq!(scope.vector_namespace(), "coder");
+        assert_eq!(MemoryScope::default().root(ws), ws.join("memory"));
+        assert_eq!(MemoryScope::default().vector_namespace(), "default");
+    }
+
+    #[test]
+    fn test_users_are_isolated() {
+        let ws = Path::new("/tmp/ws");
+        let alice = MemoryScope::for_user("alice").with_assistant(Some("coder"));
+        let bob = MemoryScope::for_user("bob").with_assistant(Some("coder"));
+        assert_ne!(alice.root(ws), bob.root(ws));
+        assert_ne!(alice.vector_namespace(), bob.vector_namespace());
+        assert_eq!(alice.root(ws), ws.join("memory/users/alice/coder"));
+
+        let escaped = MemoryScope::for_user("../bob");
+        let dir = escaped.user_id.clone().unwrap();
+        assert!(dir.starts_with("___bob~") && !dir.contains('/') && !dir.contains('.'));
+        assert_eq!(escaped.root(ws), ws.join("memory/users").join(&dir));
+    }
+
+    #[test]
+    fn test_sanitize_segment_is_injective() {
+        assert_eq!(sanitize_segment("coder-2_x").as_deref(), Some("coder-2_x"));
+        assert_eq!(sanitize_segment(""), None);
+        let a = sanitize_segment("a/b").unwrap();
+        let b = sanitize_segment("a.b").unwrap();
+        assert_ne!(a, b);
+        assert_ne!(a, "a_b");
+        assert_ne!(sanitize_segment("key.ops"), sanitize_segment("key_ops"));
+        assert_eq!(sanitize_segment("a/b").unwrap(), a);
+    }
+
+    #[test]
+    fn test_legacy_directories_survive_upgrade() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path();
+        // 旧版把 my.bot 存在 memory/my_bot/
+        std::fs::create_dir_all(ws.join("memory/my_bot")).unwrap();
+        std::fs::create_dir_all(ws.join("memory/users/key_ops")).unwrap();
+
+        let scope = MemoryScope::for_assistant(Some("my.bot"));
+        assert_eq!(scope.root(ws), ws.join("memory/my_bot"));
+        assert_eq!(scope.resolve(ws).vector_namespace(), "my_bot");
+        assert_eq!(crate::memory::assistant_memory_root(ws, "my.bot"), ws.join("memory/my_bot"));
+        let user = MemoryScope::for_user("key.ops").with_assistant(Some("coder"));
+        assert_eq!(user.root(ws), ws.join("memory/users/key_ops/coder"));
+
+        // 没有旧目录的新 id 使用带哈希的目录；哈希目录已存在时优先使用
+        let fresh = MemoryScope::for_assistant(Some("new.bot"));
+        assert!(fresh.root(ws).ends_with(fresh.assistant_id.as_deref().unwrap()));
+        let hashed = ws.join("memory").join(scope.assistant_id.as_deref().unwrap());
+        std::fs::create_dir_all(&hashed).unwrap();
+        assert_eq!(scope.root(ws), hashed);
+    }
+}
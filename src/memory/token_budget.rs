@@ -48,6 +48,10 @@ pub enum MemorySegment {
     Procedural,
     /// 长期记忆检索
     LongTerm,
+    /// 知识图谱相关实体
+    KnowledgeGraph,
+    /// 相似经历（情景记忆）
+    Episodes,
 }
 
 impl MemorySegment {
@@ -61,6 +65,8 @@ impl MemorySegment {
             MemorySegment::Lessons => 4,
             MemorySegment::Procedural => 5,
             MemorySegment::LongTerm => 6,
+            MemorySegment::KnowledgeGraph => 7,
+            MemorySegment::Episodes => 8,
         }
     }
 
@@ -74,6 +80,8 @@ impl MemorySegment {
             MemorySegment::Lessons,
             MemorySegment::Procedural,
             MemorySegment::LongTerm,
+            MemorySegment::KnowledgeGraph,
+            MemorySegment::Episodes,
         ]
     }
 }
@@ -152,6 +160,54 @@ impl TokenBudget {
         result
     }
 
+    /// 按比例分配：SystemPrompt / ToolSchema 段完整保留，其余段总量超出剩余预算时按各自大小等比例截断
+    /// （同时不超过 with_segment_limit 设置的上限）。返回顺序与输入一致，空段原样保留
+    pub fn allocate_proportional(&self, segments: &[(MemorySegment, String)]) -> Vec<(MemorySegment, String)> {
+        let fixed = |seg: &MemorySegment| matches!(seg, MemorySegment::SystemPrompt | MemorySegment::ToolSchema);
+        let fixed_tokens: usize = segments
+            .iter()
+            .filter(|(seg, c)| fixed(seg) && !c.is_empty())
+            .map(|(_, c)| TokenEstimator::estimate(c))
+            .sum();
+        let remaining = self.system_prompt_budget().saturating_sub(fixed_tokens);
+        let sizes: Vec<usize> = segments
+            .iter()
+            .map(|(seg, c)| {
+                if fixed(seg) || c.is_empty() {
+                    0
+                } else {
+                    TokenEstimator::estimate(c)
+                }
+            })
+            .collect();
+        let total: usize = sizes.iter().sum();
+        let ratio = if total > remaining {
+            remaining as f64 / total as f64
+        } else {
+            1.0
+        };
+
+        segments
+            .iter()
+            .zip(sizes)
+            .map(|((segment, content), size)| {
+                if size == 0 {
+                    return (*segment, content.clone());
+                }
+                let mut allowed = (size as f64 * ratio).floor() as usize;
+                if let Some(limit) = self.segment_limits.get(segment) {
+                    allowed = allowed.min(*limit);
+                }
+                let content = if allowed == 0 {
+                    String::new()
+                } else {
+                    Self::truncate_to_tokens(content, allowed)
+                };
+                (*segment, content)
+            })
+            .collect()
+    }
+
     /// 将文本截断到指定 token 数
     fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
         let estimated = TokenEstimator::estimate(text);
@@ -263,6 +319,34 @@ mod tests {
         assert!(!allocated.is_empty());
     }
 
+    #[test]
+    fn test_token_budget_proportional_trim() {
+        let base = "You are Bee.".to_string();
+        let working = "goal ".repeat(100);
+        let long_term = "fact ".repeat(300);
+        let segments = vec![
+            (MemorySegment::SystemPrompt, base.clone()),
+            (MemorySegment::WorkingMemory, working.clone()),
+            (MemorySegment::Lessons, String::new()),
+            (MemorySegment::LongTerm, long_term),
+        ];
+
+        // 预算充足时原样返回
+        let roomy = TokenBudget::new(10_000).with_conversation_reserve(0).allocate_proportional(&segments);
+        assert_eq!(roomy, segments);
+
+        // 超出时基础 prompt 不动，其余段按大小等比例截断
+        let tight = TokenBudget::new(203).with_conversation_reserve(0).allocate_proportional(&segments);
+        assert_eq!(tight.len(), 4);
+        assert_eq!(tight[0].1, base);
+        assert!(tight[2].1.is_empty());
+        let working_tokens = TokenEstimator::estimate(&tight[1].1);
+        let long_term_tokens = TokenEstimator::estimate(&tight[3].1);
+        assert!(tight[1].1.ends_with("[truncated due to token budget]"));
+        assert!(long_term_tokens > working_tokens * 2);
+        assert!(working_tokens + long_term_tokens <= 200 + 30);
+    }
+
     #[test]
     fn test_memory_cache() {
         let mut cache = MemoryCache::new();
@@ -0,0 +1,493 @@
+//! 持久化向量长期记忆后端
+//!
+//! - SqliteVectorLongTerm：普通 SQLite 表（不依赖 sqlite-vec 扩展），嵌入以 f32 BLOB 存入 memory_vectors 表，
+//!   可与 SqlitePersistence 共用同一数据库文件；每次检索读出该 namespace 的全部行，在进程内线性计算余弦相似度排序，
+//!   适合 max_entries 量级（数千条）的记忆，更大规模请用 pgvector
+//! - PgVectorLongTerm（feature = "pgvector"）：Postgres + pgvector，`<=>` 余弦距离检索
+//!
+//! 两者都支持从 vector_snapshot.json 迁移（import_snapshot），namespace 区分不同助手。
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection};
+
+use super::long_term::{
+    cosine_similarity, embed_tracked, keyword_fallback_search, read_vector_snapshot, LongTermMemory,
+};
+use crate::llm::EmbeddingProvider;
+
+fn embedding_to_blob(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn blob_to_embedding(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// SQLite 向量长期记忆（线性扫描）：每个 namespace（助手 id）一组条目，超过 max_entries 时删除最旧的
+pub struct SqliteVectorLongTerm {
+    conn: Mutex<Connection>,
+    embedder: Arc<dyn EmbeddingProvider>,
+    namespace: String,
+    max_entries: usize,
+    fallback: Option<Arc<dyn LongTermMemory>>,
+}
+
+impl SqliteVectorLongTerm {
+    pub fn new(
+        db_path: impl AsRef<Path>,
+        embedder: Arc<dyn EmbeddingProvider>,
+        namespace: &str,
+        max_entries: usize,
+    ) -> anyhow::Result<Self> {
+        if let Some(parent) = db_path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(db_path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS memory_vectors (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                namespace TEXT NOT NULL,
+                text TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_memory_vectors_ns ON memory_vectors(namespace)",
+            [],
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            embedder,
+            namespace: namespace.to_string(),
+            max_entries,
+            fallback: None,
+        })
+    }
+
+    /// 嵌入不可用时的降级存储（见 InMemoryVectorLongTerm::with_keyword_fallback）
+    pub fn with_keyword_fallback(mut self, fallback: Arc<dyn LongTermMemory>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    fn insert(&self, text: &str, embedding: &[f32]) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO memory_vectors (namespace, text, embedding, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.namespace,
+                text,
+                embedding_to_blob(embedding),
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM memory_vectors WHERE namespace = ?1 AND id NOT IN (
+                SELECT id FROM memory_vectors WHERE namespace = ?1 ORDER BY id DESC LIMIT ?2
+            )",
+            params![self.namespace, self.max_entries as i64],
+        )?;
+        Ok(())
+    }
+
+    /// 当前 namespace 的条目数
+    pub fn len(&self) -> usize {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*) FROM memory_vectors WHERE namespace = ?1",
+            [&self.namespace],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|n| n as usize)
+        .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 从旧版 vector_snapshot.json 迁移（保留原嵌入，不重新调用嵌入 API），返回导入条数
+    pub fn import_snapshot(&self, snapshot_path: &Path) -> anyhow::Result<usize> {
+        let entries = read_vector_snapshot(snapshot_path)?;
+        let n = entries.len();
+        for (text, embedding) in entries {
+            self.insert(&text, &embedding)?;
+        }
+        tracing::info!(
+            "sqlite vector long-term: imported {} entries from {:?} into namespace {}",
+            n,
+            snapshot_path,
+            self.namespace
+        );
+        Ok(n)
+    }
+}
+
+impl LongTermMemory for SqliteVectorLongTerm {
+    fn add(&self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        match embed_tracked(self.embedder.as_ref(), text) {
+            Ok(vec) => {
+                if let Err(e) = self.insert(text, &vec) {
+                    tracing::warn!("sqlite vector long-term insert failed: {}", e);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("vector long-term embed failed: {}", e);
+                if let Some(ref fallback) = self.fallback {
+                    fallback.add(text);
+                }
+            }
+        }
+    }
+
+    fn search(&self, query: &str, k: usize) -> Vec<String> {
+        self.search_scored(query, k).into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn search_scored(&self, query: &str, k: usize) -> Vec<(f32, String)> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query_vec = match embed_tracked(self.embedder.as_ref(), query) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("vector long-term embed failed, using keyword fallback: {}", e);
+                return keyword_fallback_search(self.fallback.as_ref(), query, k);
+            }
+        };
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = match conn
+            .prepare("SELECT text, embedding FROM memory_vectors WHERE namespace = ?1")
+        {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("sqlite vector long-term query failed: {}", e);
+                return Vec::new();
+            }
+        };
+        let rows = stmt.query_map([&self.namespace], |row| {
+            let text: String = row.get(0)?;
+            let blob: Vec<u8> = row.get(1)?;
+            Ok((text, blob))
+        });
+        let mut scored: Vec<(f32, String)> = match rows {
+            Ok(rows) => rows
+                .filter_map(|r| r.ok())
+                .map(|(text, blob)| (cosine_similarity(&query_vec, &blob_to_embedding(&blob)), text))
+                .filter(|(s, _)| *s > 0.0)
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(k);
+        scored
+    }
+
+    fn remove(&self, text: &str) -> usize {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM memory_vectors WHERE namespace = ?1 AND text = ?2",
+            params![self.namespace, text.trim()],
+        )
+        .unwrap_or_else(|e| {
+            tracing::warn!("sqlite vector long-term delete failed: {}", e);
+            0
+        })
+    }
+
+    fn find_matching(&self, pattern: &str) -> Vec<String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = match conn.prepare(
+            "SELECT text FROM memory_vectors WHERE namespace = ?1 AND instr(lower(text), lower(?2)) > 0",
+        ) {
+            Ok(s) => s,
+            Err(_) => return Vec::new(),
+        };
+        stmt.query_map(params![self.namespace, pattern], |row| row.get::<_, String>(0))
+            .map(|rows| rows.filter_map(|r| r.ok()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Postgres + pgvector 向量长期记忆（需数据库已安装 vector 扩展）
+#[cfg(feature = "pgvector")]
+pub struct PgVectorLongTerm {
+    pool: sqlx::PgPool,
+    embedder: Arc<dyn EmbeddingProvider>,
+    namespace: String,
+    max_entries: usize,
+    fallback: Option<Arc<dyn LongTermMemory>>,
+}
+
+#[cfg(feature = "pgvector")]
+fn pg_vector_literal(embedding: &[f32]) -> String {
+    let parts: Vec<String> = embedding.iter().map(|x| x.to_string()).collect();
+    format!("[{}]", parts.join(","))
+}
+
+#[cfg(feature = "pgvector")]
+impl PgVectorLongTerm {
+    /// 连接数据库并建表（CREATE EXTENSION IF NOT EXISTS vector）
+    pub async fn connect(
+        url: &str,
+        embedder: Arc<dyn EmbeddingProvider>,
+        namespace: &str,
+        max_entries: usize,
+    ) -> Result<Self, sqlx::Error> {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(4)
+            .connect(url)
+            .await?;
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+            .execute(&pool)
+            .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS memory_vectors (
+                id BIGSERIAL PRIMARY KEY,
+                namespace TEXT NOT NULL,
+                text TEXT NOT NULL,
+                embedding vector NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_memory_vectors_ns ON memory_vectors(namespace)")
+            .execute(&pool)
+            .await?;
+        Ok(Self {
+            pool,
+            embedder,
+            namespace: namespace.to_string(),
+            max_entries,
+            fallback: None,
+        })
+    }
+
+    /// 嵌入不可用时的降级存储（见 InMemoryVectorLongTerm::with_keyword_fallback）
+    pub fn with_keyword_fallback(mut self, fallback: Arc<dyn LongTermMemory>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    async fn insert(&self, text: &str, embedding: &[f32]) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO memory_vectors (namespace, text, embedding) VALUES ($1, $2, $3::vector)")
+            .bind(&self.namespace)
+            .bind(text)
+            .bind(pg_vector_literal(embedding))
+            .execute(&self.pool)
+            .await?;
+        sqlx::query(
+            "DELETE FROM memory_vectors WHERE namespace = $1 AND id NOT IN (
+                SELECT id FROM memory_vectors WHERE namespace = $1 ORDER BY id DESC LIMIT $2
+            )",
+        )
+        .bind(&self.namespace)
+        .bind(self.max_entries as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn search_async(
+        &self,
+        query_vec: &[f32],
+        k: usize,
+    ) -> Result<Vec<(f32, String)>, sqlx::Error> {
+        use sqlx::Row;
+        let rows = sqlx::query(
+            "SELECT text, (1 - (embedding <=> $2::vector))::float4 AS score
+             FROM memory_vectors WHERE namespace = $1
+             ORDER BY embedding <=> $2::vector LIMIT $3",
+        )
+        .bind(&self.namespace)
+        .bind(pg_vector_literal(query_vec))
+        .bind(k as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|r| (r.get::<f32, _>("score"), r.get::<String, _>("text")))
+            .collect())
+    }
+
+    async fn find_matching_async(&self, pattern: &str) -> Result<Vec<String>, sqlx::Error> {
+        use sqlx::Row;
+        let rows = sqlx::query(
+            "SELECT text FROM memory_vectors WHERE namespace = $1 AND position(lower($2) in lower(text)) > 0",
+        )
+        .bind(&self.namespace)
+        .bind(pattern)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(|r| r.get::<String, _>("text")).collect())
+    }
+
+    async fn remove_async(&self, text: &str) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query("DELETE FROM memory_vectors WHERE namespace = $1 AND text = $2")
+            .bind(&self.namespace)
+            .bind(text)
+            .execute(&self.pool)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// 从旧版 vector_snapshot.json 迁移，返回导入条数
+    pub async fn import_snapshot(&self, snapshot_path: &Path) -> anyhow::Result<usize> {
+        let entries = read_vector_snapshot(snapshot_path)?;
+        let n = entries.len();
+        for (text, embedding) in entries {
+            self.insert(&text, &embedding).await?;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "pgvector")]
+impl LongTermMemory for PgVectorLongTerm {
+    fn add(&self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        match embed_tracked(self.embedder.as_ref(), text) {
+            Ok(vec) => {
+                let res = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(self.insert(text, &vec))
+                });
+                if let Err(e) = res {
+                    tracing::warn!("pgvector long-term insert failed: {}", e);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("vector long-term embed failed: {}", e);
+                if let Some(ref fallback) = self.fallback {
+                    fallback.add(text);
+                }
+            }
+        }
+    }
+
+    fn search(&self, query: &str, k: usize) -> Vec<String> {
+        self.search_scored(query, k).into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn search_scored(&self, query: &str, k: usize) -> Vec<(f32, String)> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query_vec = match embed_tracked(self.embedder.as_ref(), query) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("vector long-term embed failed, using keyword fallback: {}", e);
+                return keyword_fallback_search(self.fallback.as_ref(), query, k);
+            }
+        };
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.search_async(&query_vec, k))
+        })
+        .unwrap_or_else(|e| {
+            tracing::warn!("pgvector long-term query failed: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn remove(&self, text: &str) -> usize {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.remove_async(text.trim()))
+        })
+        .map(|n| n as usize)
+        .unwrap_or_else(|e| {
+            tracing::warn!("pgvector long-term delete failed: {}", e);
+            0
+        })
+    }
+
+    fn find_matching(&self, pattern: &str) -> Vec<String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.find_matching_async(pattern))
+        })
+        .unwrap_or_else(|e| {
+            tracing::warn!("pgvector long-term query failed: {}", e);
+            Vec::new()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按关键词生成固定维度向量的测试嵌入
+    struct KeywordEmbedder;
+
+    impl EmbeddingProvider for KeywordEmbedder {
+        fn embed_sync(&self, text: &str) -> Result<Vec<f32>, String> {
+            let t = text.to_lowercase();
+            Ok(vec![
+                if t.contains("rust") { 1.0 } else { 0.0 },
+                if t.contains("python") { 1.0 } else { 0.0 },
+                0.1,
+            ])
+        }
+    }
+
+    #[test]
+    fn test_blob_roundtrip() {
+        let v = vec![0.5_f32, -1.25, 3.0];
+        assert_eq!(blob_to_embedding(&embedding_to_blob(&v)), v);
+    }
+
+    #[test]
+    fn test_sqlite_vector_add_search_and_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("bee.db");
+        let a = SqliteVectorLongTerm::new(&db, Arc::new(KeywordEmbedder), "a", 10).unwrap();
+        let b = SqliteVectorLongTerm::new(&db, Arc::new(KeywordEmbedder), "b", 10).unwrap();
+        a.add("I love Rust");
+        a.add("Python is fine");
+        b.add("Rust for b");
+
+        let hits = a.search("rust question", 1);
+        assert_eq!(hits, vec!["I love Rust".to_string()]);
+        assert_eq!(a.len(), 2);
+        assert_eq!(b.len(), 1);
+    }
+
+    #[test]
+    fn test_sqlite_vector_max_entries_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = dir.path().join("vector_snapshot.json");
+        std::fs::write(
+            &snapshot,
+            r#"[{"text":"old rust fact","embedding":[1.0,0.0,0.1]},{"text":"old python fact","embedding":[0.0,1.0,0.1]}]"#,
+        )
+        .unwrap();
+        let lt = SqliteVectorLongTerm::new(dir.path().join("bee.db"), Arc::new(KeywordEmbedder), "default", 2)
+            .unwrap();
+        assert_eq!(lt.import_snapshot(&snapshot).unwrap(), 2);
+        lt.add("new rust fact");
+        assert_eq!(lt.len(), 2);
+        assert!(!lt.search("python", 5).contains(&"old rust fact".to_string()));
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! 在 ReAct 单次对话内有效，用于拼入 system prompt（Current Goal / What has been tried / Failures），减少重复犯错。
 
-#[derive(Clone, Debug, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct WorkingMemory {
     pub goal: Option<String>,
     pub attempts: Vec<String>,
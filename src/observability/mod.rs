@@ -5,9 +5,13 @@
 //! - 工具执行时间
 //! - 请求完整生命周期追踪
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
+use serde::Serialize;
+
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 use uuid::Uuid;
 
@@ -48,6 +52,10 @@ pub struct Metrics {
     pub session: SessionMetrics,
     /// AI 行为质量指标
     pub behavior: BehaviorMetrics,
+    /// 记忆检索健康度
+    pub memory: MemoryMetrics,
+    /// 任务调度队列（LLM / 工具）
+    pub scheduler: SchedulerMetrics,
 }
 
 impl Metrics {
@@ -80,6 +88,22 @@ impl Metrics {
                 "failed_executions": self.tools.failed_executions.load(Ordering::Relaxed),
                 "total_execution_time_ms": self.tools.total_execution_time_ms.load(Ordering::Relaxed),
                 "average_execution_time_ms": self.tools.average_execution_time_ms(),
+                "by_tool": self
+                    .tools
+                    .usage_by_tool()
+                    .iter()
+                    .map(|(tool, u)| (tool.clone(), u.to_json()))
+                    .collect::<serde_json::Map<_, _>>(),
+                "by_assistant": self
+                    .tools
+                    .usage_snapshot()
+                    .iter()
+                    .map(|(assistant, tools)| {
+                        let tools: serde_json::Map<_, _> =
+                            tools.iter().map(|(tool, u)| (tool.clone(), u.to_json())).collect();
+                        (assistant.clone(), serde_json::Value::Object(tools))
+                    })
+                    .collect::<serde_json::Map<_, _>>(),
             },
             "session": {
                 "total_requests": self.session.total_requests.load(Ordering::Relaxed),
@@ -96,6 +120,24 @@ impl Metrics {
                 "tasks_total": self.behavior.tasks_total.load(Ordering::Relaxed),
                 "completion_rate": self.behavior.completion_rate(),
                 "error_rate": self.behavior.error_rate(),
+                "critic_evaluations": self.behavior.critic_evaluations.load(Ordering::Relaxed),
+                "critic_corrections": self.behavior.critic_corrections.load(Ordering::Relaxed),
+                "critic_average_score": self.behavior.average_critic_score(),
+                "validator_failures": self.behavior.validator_failures.load(Ordering::Relaxed),
+            },
+            "memory": {
+                "retrieval_degraded": self.memory.is_degraded(),
+                "embedding_calls": self.memory.embedding_calls.load(Ordering::Relaxed),
+                "embedding_failures": self.memory.embedding_failures.load(Ordering::Relaxed),
+                "keyword_fallback_searches": self.memory.keyword_fallback_searches.load(Ordering::Relaxed),
+            },
+            "scheduler": {
+                "llm_waiting": self.scheduler.llm_waiting.load(Ordering::Relaxed),
+                "llm_running": self.scheduler.llm_running.load(Ordering::Relaxed),
+                "tool_waiting": self.scheduler.tool_waiting.load(Ordering::Relaxed),
+                "tool_running": self.scheduler.tool_running.load(Ordering::Relaxed),
+                "dispatched": self.scheduler.dispatched.load(Ordering::Relaxed),
+                "average_wait_ms": self.scheduler.average_wait_ms(),
             }
         })
     }
@@ -147,6 +189,7 @@ impl Metrics {
             "# TYPE bee_tool_execution_time_ms_total counter\nbee_tool_execution_time_ms_total {}\n",
             self.tools.total_execution_time_ms.load(Ordering::Relaxed)
         ));
+        self.tools.write_prometheus_usage(&mut output);
         
         // Session metrics
         output.push_str(&format!(
@@ -195,6 +238,59 @@ impl Metrics {
             "# TYPE bee_behavior_error_rate gauge\nbee_behavior_error_rate {}\n",
             self.behavior.error_rate()
         ));
+        output.push_str(&format!(
+            "# TYPE bee_behavior_critic_evaluations counter\nbee_behavior_critic_evaluations {}\n",
+            self.behavior.critic_evaluations.load(Ordering::Relaxed)
+        ));
+        output.push_str(&format!(
+            "# TYPE bee_behavior_critic_corrections counter\nbee_behavior_critic_corrections {}\n",
+            self.behavior.critic_corrections.load(Ordering::Relaxed)
+        ));
+        output.push_str(&format!(
+            "# TYPE bee_behavior_critic_average_score gauge\nbee_behavior_critic_average_score {}\n",
+            self.behavior.average_critic_score()
+        ));
+        output.push_str(&format!(
+            "# TYPE bee_behavior_validator_failures counter\nbee_behavior_validator_failures {}\n",
+            self.behavior.validator_failures.load(Ordering::Relaxed)
+        ));
+
+        // Memory metrics
+        output.push_str(&format!(
+            "# TYPE bee_memory_retrieval_degraded gauge\nbee_memory_retrieval_degraded {}\n",
+            self.memory.is_degraded() as u8
+        ));
+        output.push_str(&format!(
+            "# TYPE bee_memory_embedding_failures counter\nbee_memory_embedding_failures {}\n",
+            self.memory.embedding_failures.load(Ordering::Relaxed)
+        ));
+        output.push_str(&format!(
+            "# TYPE bee_memory_keyword_fallback_searches counter\nbee_memory_keyword_fallback_searches {}\n",
+            self.memory.keyword_fallback_searches.load(Ordering::Relaxed)
+        ));
+
+        // Scheduler metrics
+        for (lane, waiting, running) in [
+            ("llm", &self.scheduler.llm_waiting, &self.scheduler.llm_running),
+            ("tool", &self.scheduler.tool_waiting, &self.scheduler.tool_running),
+        ] {
+            output.push_str(&format!(
+                "# TYPE bee_scheduler_{lane}_waiting gauge\nbee_scheduler_{lane}_waiting {}\n",
+                waiting.load(Ordering::Relaxed)
+            ));
+            output.push_str(&format!(
+                "# TYPE bee_scheduler_{lane}_running gauge\nbee_scheduler_{lane}_running {}\n",
+                running.load(Ordering::Relaxed)
+            ));
+        }
+        output.push_str(&format!(
+            "# TYPE bee_scheduler_dispatched_total counter\nbee_scheduler_dispatched_total {}\n",
+            self.scheduler.dispatched.load(Ordering::Relaxed)
+        ));
+        output.push_str(&format!(
+            "# TYPE bee_scheduler_wait_ms_total counter\nbee_scheduler_wait_ms_total {}\n",
+            self.scheduler.total_wait_ms.load(Ordering::Relaxed)
+        ));
         
         output
     }
@@ -252,6 +348,113 @@ pub struct ToolMetrics {
     pub successful_executions: AtomicU64,
     pub failed_executions: AtomicU64,
     pub total_execution_time_ms: AtomicU64,
+    /// 按助手 / 工具的用量：assistant_id -> 工具名 -> 用量
+    usage: Mutex<HashMap<String, HashMap<String, ToolUsage>>>,
+}
+
+/// 执行前不在助手上下文中（如 TUI、后台任务）时，工具用量记入的助手标签
+pub const UNSCOPED_ASSISTANT: &str = "-";
+
+/// 工具耗时直方图的桶上界（毫秒），最后一个桶之外另有 +Inf 桶
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+/// 工具耗时直方图（各桶为非累计计数，最后一个为 +Inf 桶）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LatencyHistogram {
+    pub buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    pub count: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        let idx = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&le| ms <= le)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[idx] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (a, b) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *a += b;
+        }
+        self.count += other.count;
+        self.sum_ms += other.sum_ms;
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+
+    pub fn average_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum_ms as f64 / self.count as f64
+        }
+    }
+
+    /// 近似分位数（取所在桶的上界，+Inf 桶取观测到的最大值）
+    pub fn quantile_ms(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((self.count as f64) * q.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return LATENCY_BUCKETS_MS.get(i).map_or(self.max_ms, |&le| le.min(self.max_ms));
+            }
+        }
+        self.max_ms
+    }
+}
+
+/// 单个助手对单个工具的用量
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ToolUsage {
+    /// 实际执行次数
+    pub calls: u64,
+    /// 执行失败次数
+    pub failures: u64,
+    /// 因不在该助手技能范围内被拦截的次数
+    pub blocked: u64,
+    /// 执行耗时分布
+    pub latency: LatencyHistogram,
+}
+
+impl ToolUsage {
+    pub fn failure_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.calls as f64
+        }
+    }
+
+    fn merge(&mut self, other: &ToolUsage) {
+        self.calls += other.calls;
+        self.failures += other.failures;
+        self.blocked += other.blocked;
+        self.latency.merge(&other.latency);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "calls": self.calls,
+            "failures": self.failures,
+            "blocked": self.blocked,
+            "failure_rate": self.failure_rate(),
+            "average_latency_ms": self.latency.average_ms(),
+            "p50_latency_ms": self.latency.quantile_ms(0.5),
+            "p95_latency_ms": self.latency.quantile_ms(0.95),
+            "max_latency_ms": self.latency.max_ms,
+        })
+    }
 }
 
 impl ToolMetrics {
@@ -265,6 +468,98 @@ impl ToolMetrics {
         self.total_execution_time_ms.fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
     }
 
+    /// 记录某助手的一次工具执行及耗时
+    pub fn record_usage(&self, assistant_id: &str, tool: &str, success: bool, duration: Duration) {
+        self.with_usage(assistant_id, tool, |u| {
+            u.calls += 1;
+            if !success {
+                u.failures += 1;
+            }
+            u.latency.observe(duration);
+        });
+    }
+
+    /// 记录某助手调用了技能范围外的工具（被拦截）
+    pub fn record_blocked(&self, assistant_id: &str, tool: &str) {
+        self.with_usage(assistant_id, tool, |u| u.blocked += 1);
+    }
+
+    /// 某助手的工具用量（工具名 -> 用量）
+    pub fn usage_for(&self, assistant_id: &str) -> HashMap<String, ToolUsage> {
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(assistant_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 全部用量快照（assistant_id -> 工具名 -> 用量）
+    pub fn usage_snapshot(&self) -> HashMap<String, HashMap<String, ToolUsage>> {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 按工具汇总所有助手的用量（工具名 -> 用量）
+    pub fn usage_by_tool(&self) -> HashMap<String, ToolUsage> {
+        let mut by_tool: HashMap<String, ToolUsage> = HashMap::new();
+        for tools in self.usage.lock().unwrap_or_else(|e| e.into_inner()).values() {
+            for (tool, usage) in tools {
+                by_tool.entry(tool.clone()).or_default().merge(usage);
+            }
+        }
+        by_tool
+    }
+
+    /// 按 tool / assistant 标签输出调用、失败、拦截计数与耗时直方图
+    fn write_prometheus_usage(&self, output: &mut String) {
+        let mut rows: Vec<(String, String, ToolUsage)> = self
+            .usage_snapshot()
+            .into_iter()
+            .flat_map(|(assistant, tools)| tools.into_iter().map(move |(tool, u)| (tool, assistant.clone(), u)))
+            .collect();
+        if rows.is_empty() {
+            return;
+        }
+        rows.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        let labels = |tool: &str, assistant: &str| {
+            format!("tool=\"{}\",assistant=\"{}\"", prometheus_escape(tool), prometheus_escape(assistant))
+        };
+        for (name, field) in [
+            ("bee_tool_calls_total", (|u: &ToolUsage| u.calls) as fn(&ToolUsage) -> u64),
+            ("bee_tool_failures_total", |u| u.failures),
+            ("bee_tool_blocked_total", |u| u.blocked),
+        ] {
+            output.push_str(&format!("# TYPE {} counter\n", name));
+            for (tool, assistant, u) in &rows {
+                output.push_str(&format!("{}{{{}}} {}\n", name, labels(tool, assistant), field(u)));
+            }
+        }
+        output.push_str("# TYPE bee_tool_latency_ms histogram\n");
+        for (tool, assistant, u) in &rows {
+            let labels = labels(tool, assistant);
+            let mut cumulative = 0;
+            for (i, n) in u.latency.buckets.iter().enumerate() {
+                cumulative += n;
+                let le = LATENCY_BUCKETS_MS.get(i).map_or("+Inf".to_string(), |b| b.to_string());
+                output.push_str(&format!(
+                    "bee_tool_latency_ms_bucket{{{},le=\"{}\"}} {}\n",
+                    labels, le, cumulative
+                ));
+            }
+            output.push_str(&format!("bee_tool_latency_ms_sum{{{}}} {}\n", labels, u.latency.sum_ms));
+            output.push_str(&format!("bee_tool_latency_ms_count{{{}}} {}\n", labels, u.latency.count));
+        }
+    }
+
+    fn with_usage(&self, assistant_id: &str, tool: &str, f: impl FnOnce(&mut ToolUsage)) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        f(usage
+            .entry(assistant_id.to_string())
+            .or_default()
+            .entry(tool.to_string())
+            .or_default());
+    }
+
     pub fn average_execution_time_ms(&self) -> f64 {
         let total = self.total_execution_time_ms.load(Ordering::Relaxed);
         let count = self.total_executions.load(Ordering::Relaxed);
@@ -276,6 +571,10 @@ impl ToolMetrics {
     }
 }
 
+fn prometheus_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
 /// 会话相关指标
 #[derive(Debug, Default)]
 pub struct SessionMetrics {
@@ -314,6 +613,14 @@ pub struct BehaviorMetrics {
     pub tasks_completed_first_try: AtomicU64,
     /// 总任务数
     pub tasks_total: AtomicU64,
+    /// Critic 评估次数（工具结果与最终回复）
+    pub critic_evaluations: AtomicU64,
+    /// Critic 给出修正的次数
+    pub critic_corrections: AtomicU64,
+    /// Critic 得分累计（0-1 分 × 1000）
+    pub critic_score_milli_total: AtomicU64,
+    /// Critic 工具校验器未通过次数
+    pub validator_failures: AtomicU64,
 }
 
 impl BehaviorMetrics {
@@ -350,6 +657,31 @@ impl BehaviorMetrics {
         }
     }
 
+    /// 记录一次 Critic 评估（score 为 0-1 归一化得分）
+    pub fn record_critic_score(&self, score: f64, approved: bool) {
+        self.critic_evaluations.fetch_add(1, Ordering::Relaxed);
+        self.critic_score_milli_total
+            .fetch_add((score.clamp(0.0, 1.0) * 1000.0).round() as u64, Ordering::Relaxed);
+        if !approved {
+            self.critic_corrections.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 记录工具校验器未通过
+    pub fn record_validator_failure(&self) {
+        self.validator_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Critic 平均得分（0-1，无评估时为 0）
+    pub fn average_critic_score(&self) -> f64 {
+        let total = self.critic_evaluations.load(Ordering::Relaxed);
+        if total == 0 {
+            0.0
+        } else {
+            self.critic_score_milli_total.load(Ordering::Relaxed) as f64 / 1000.0 / total as f64
+        }
+    }
+
     /// 获取总错误数
     pub fn total_errors(&self) -> u64 {
         self.intent_misunderstandings.load(Ordering::Relaxed)
@@ -379,6 +711,78 @@ impl BehaviorMetrics {
     }
 }
 
+/// 记忆检索健康度：嵌入失败时向量检索降级为 long-term.md 关键词检索
+#[derive(Debug, Default)]
+pub struct MemoryMetrics {
+    /// 嵌入调用次数
+    pub embedding_calls: AtomicU64,
+    /// 嵌入失败次数
+    pub embedding_failures: AtomicU64,
+    /// 降级为关键词检索的次数
+    pub keyword_fallback_searches: AtomicU64,
+    /// 最近一次嵌入是否失败（下一次成功即恢复）
+    pub degraded: AtomicBool,
+}
+
+impl MemoryMetrics {
+    /// 记录一次嵌入调用结果
+    pub fn record_embedding(&self, success: bool) {
+        self.embedding_calls.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.embedding_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.degraded.store(!success, Ordering::Relaxed);
+    }
+
+    /// 记录一次关键词降级检索
+    pub fn record_fallback_search(&self) {
+        self.keyword_fallback_searches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 当前向量检索是否处于降级状态
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+}
+
+/// 任务调度队列指标：各队列当前排队 / 运行中的工作数（gauge）与累计排队时长
+#[derive(Debug, Default)]
+pub struct SchedulerMetrics {
+    pub llm_waiting: AtomicU64,
+    pub llm_running: AtomicU64,
+    pub tool_waiting: AtomicU64,
+    pub tool_running: AtomicU64,
+    /// 开始执行的工作总数
+    pub dispatched: AtomicU64,
+    /// 累计排队时长（毫秒）
+    pub total_wait_ms: AtomicU64,
+}
+
+impl SchedulerMetrics {
+    /// 更新各队列深度（调度器每次入队 / 出队 / 释放后调用）
+    pub fn set_depth(&self, llm: (usize, usize), tool: (usize, usize)) {
+        self.llm_waiting.store(llm.0 as u64, Ordering::Relaxed);
+        self.llm_running.store(llm.1 as u64, Ordering::Relaxed);
+        self.tool_waiting.store(tool.0 as u64, Ordering::Relaxed);
+        self.tool_running.store(tool.1 as u64, Ordering::Relaxed);
+    }
+
+    /// 记录一项工作开始执行及其排队时长
+    pub fn record_dispatch(&self, waited: Duration) {
+        self.dispatched.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_ms.fetch_add(waited.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// 平均排队时长（毫秒）
+    pub fn average_wait_ms(&self) -> f64 {
+        let dispatched = self.dispatched.load(Ordering::Relaxed);
+        if dispatched == 0 {
+            return 0.0;
+        }
+        self.total_wait_ms.load(Ordering::Relaxed) as f64 / dispatched as f64
+    }
+}
+
 /// Span 计时器（RAII 风格）
 pub struct SpanTimer {
     name: &'static str,
@@ -462,6 +866,37 @@ mod tests {
         assert_eq!(metrics.average_execution_time_ms(), 75.0);
     }
 
+    #[test]
+    fn test_tool_usage_per_assistant() {
+        let metrics = ToolMetrics::default();
+        metrics.record_usage("coder", "cat", true, Duration::from_millis(5));
+        metrics.record_usage("coder", "cat", false, Duration::from_millis(300));
+        metrics.record_blocked("coder", "shell");
+        metrics.record_usage("media", "cat", true, Duration::from_millis(40));
+
+        let usage = metrics.usage_for("coder");
+        assert_eq!((usage["cat"].calls, usage["cat"].failures, usage["cat"].blocked), (2, 1, 0));
+        assert_eq!(usage["cat"].latency.sum_ms, 305);
+        assert_eq!(usage["shell"].blocked, 1);
+        assert!(metrics.usage_for("writer").is_empty());
+
+        // 跨助手汇总与近似分位数
+        let cat = &metrics.usage_by_tool()["cat"];
+        assert_eq!((cat.calls, cat.failures), (3, 1));
+        assert_eq!(cat.latency.quantile_ms(0.5), 50);
+        assert_eq!(cat.latency.quantile_ms(0.95), 300);
+
+        let m = Metrics::new();
+        m.tools.record_usage("coder", "cat", false, Duration::from_millis(120));
+        let json = m.to_json();
+        assert_eq!(json["tools"]["by_tool"]["cat"]["failure_rate"], 1.0);
+        assert_eq!(json["tools"]["by_assistant"]["coder"]["cat"]["p95_latency_ms"], 120);
+        let prom = m.to_prometheus();
+        assert!(prom.contains("bee_tool_failures_total{tool=\"cat\",assistant=\"coder\"} 1"));
+        assert!(prom.contains("bee_tool_latency_ms_bucket{tool=\"cat\",assistant=\"coder\",le=\"250\"} 1"));
+        assert!(prom.contains("bee_tool_latency_ms_bucket{tool=\"cat\",assistant=\"coder\",le=\"100\"} 0"));
+    }
+
     #[test]
     fn test_session_metrics() {
         let metrics = SessionMetrics::default();
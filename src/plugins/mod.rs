@@ -6,6 +6,7 @@
 //! - 工具插件：扩展可用工具
 //! - 提供者插件：扩展 LLM/嵌入提供者
 //! - 处理器插件：消息预处理/后处理
+//! - 恢复钩子：自定义 RecoveryEngine 的错误恢复策略
 
 use std::any::Any;
 use std::collections::HashMap;
@@ -216,6 +217,7 @@ pub struct PluginRegistry {
     plugins: HashMap<String, Arc<tokio::sync::RwLock<Box<dyn Plugin>>>>,
     tool_plugins: HashMap<String, Arc<tokio::sync::RwLock<Box<dyn ToolPlugin>>>>,
     processor_plugins: Vec<Arc<tokio::sync::RwLock<Box<dyn MessageProcessorPlugin>>>>,
+    recovery_hooks: Vec<Arc<dyn crate::core::RecoveryHook>>,
 }
 
 impl PluginRegistry {
@@ -224,6 +226,7 @@ impl PluginRegistry {
             plugins: HashMap::new(),
             tool_plugins: HashMap::new(),
             processor_plugins: Vec::new(),
+            recovery_hooks: Vec::new(),
         }
     }
 
@@ -259,6 +262,16 @@ impl PluginRegistry {
         self.processor_plugins.push(Arc::new(tokio::sync::RwLock::new(plugin)));
     }
 
+    /// 注册恢复钩子（RecoveryEngine::with_plugin_hooks 挂载）
+    pub fn register_recovery_hook(&mut self, hook: Arc<dyn crate::core::RecoveryHook>) {
+        self.recovery_hooks.push(hook);
+    }
+
+    /// 已注册的恢复钩子（按注册顺序）
+    pub fn recovery_hooks(&self) -> &[Arc<dyn crate::core::RecoveryHook>] {
+        &self.recovery_hooks
+    }
+
     /// 初始化所有插件
     pub async fn initialize_all(&self, ctx: &PluginContext) -> Result<(), PluginError> {
         for (id, plugin) in &self.plugins {
@@ -348,7 +361,7 @@ impl PluginRegistry {
 
     /// 获取插件数量
     pub fn len(&self) -> usize {
-        self.plugins.len() + self.tool_plugins.len() + self.processor_plugins.len()
+        self.plugins.len() + self.tool_plugins.len() + self.processor_plugins.len() + self.recovery_hooks.len()
     }
 
     /// 是否为空
@@ -390,9 +403,12 @@ impl crate::tools::Tool for ToolPluginAdapter {
         serde_json::json!({})
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, crate::tools::ToolError> {
         let plugin = self.plugin.read().await;
-        plugin.execute(args).await.map_err(|e| e.to_string())
+        plugin
+            .execute(args)
+            .await
+            .map_err(|e| crate::tools::ToolError::Failed(e.to_string()))
     }
 }
 
@@ -0,0 +1,146 @@
+//! AskUser 往返：恢复引擎建议询问用户时，循环发出 `ReactEvent::AskUser` 并暂停等待回答
+//!
+//! 提问登记在进程内的 QuestionBroker（按 id，Web 会话另记 session_id / assistant_id），同时写入检查点；
+//! TUI 在输入框回答，Web 调用 `POST /api/sessions/:id/answer`。超时、无事件消费方或被取消时按原逻辑报错结束；
+//! 进程重启后回答写入检查点并从中断处恢复。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+/// 等待用户回答的问题
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingQuestion {
+    pub id: String,
+    pub question: String,
+    /// Web 会话 id（TUI 等无会话的场景为 None）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assistant_id: Option<String>,
+    /// Web 会话所属用户（默认用户与 TUI 为 None）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// 提问中转：登记待回答的问题，TUI / Web 按 id 或会话送回回答
+#[derive(Default)]
+pub struct QuestionBroker {
+    pending: Mutex<HashMap<String, (PendingQuestion, oneshot::Sender<String>)>>,
+}
+
+static QUESTIONS: OnceLock<QuestionBroker> = OnceLock::new();
+
+impl QuestionBroker {
+    /// 进程内共享的提问中转
+    pub fn global() -> &'static QuestionBroker {
+        QUESTIONS.get_or_init(QuestionBroker::default)
+    }
+
+    /// 登记一个问题，返回问题与回答接收端
+    pub fn ask(
+        &self,
+        question: &str,
+        session_id: Option<&str>,
+        assistant_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> (PendingQuestion, oneshot::Receiver<String>) {
+        let pending = PendingQuestion {
+            id: uuid::Uuid::new_v4().to_string(),
+            question: question.to_string(),
+            session_id: session_id.map(str::to_string),
+            assistant_id: assistant_id.map(str::to_string),
+            user_id: user_id.map(str::to_string),
+        };
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(pending.id.clone(), (pending.clone(), tx));
+        (pending, rx)
+    }
+
+    /// 按问题 id 送回回答；id 不存在（已超时或已回答）时返回 false
+    pub fn answer(&self, id: &str, answer: &str) -> bool {
+        match self.lock().remove(id) {
+            Some((_, tx)) => tx.send(answer.to_string()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 回答 user_id 的某个 Web 会话中等待的问题（assistant_id 为 None 时匹配该会话的任意助手）
+    pub fn answer_session(
+        &self,
+        session_id: &str,
+        assistant_id: Option<&str>,
+        user_id: Option<&str>,
+        answer: &str,
+    ) -> bool {
+        let id = self
+            .lock()
+            .values()
+            .find(|(q, _)| {
+                q.session_id.as_deref() == Some(session_id)
+                    && q.user_id.as_deref() == user_id
+                    && assistant_id.is_none_or(|a| q.assistant_id.as_deref() == Some(a))
+            })
+            .map(|(q, _)| q.id.clone());
+        id.is_some_and(|id| self.answer(&id, answer))
+    }
+
+    /// 当前等待回答的问题
+    pub fn pending(&self) -> Vec<PendingQuestion> {
+        self.lock().values().map(|(q, _)| q.clone()).collect()
+    }
+
+    /// 属于 user_id 的等待回答的问题
+    pub fn pending_for(&self, user_id: Option<&str>) -> Vec<PendingQuestion> {
+        self.lock()
+            .values()
+            .filter(|(q, _)| q.user_id.as_deref() == user_id)
+            .map(|(q, _)| q.clone())
+            .collect()
+    }
+
+    pub(crate) fn forget(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (PendingQuestion, oneshot::Sender<String>)>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{Message, WorkingMemory};
+    use crate::react::ReactCheckpoint;
+
+    #[tokio::test]
+    async fn test_question_broker_and_checkpoint_answer() {
+        let broker = QuestionBroker::default();
+        let (q, rx) = broker.ask("Which branch?", None, None, None);
+        assert_eq!(broker.pending(), vec![q.clone()]);
+        assert!(broker.answer(&q.id, "main"));
+        assert_eq!(rx.await.unwrap(), "main");
+        assert!(!broker.answer(&q.id, "again"));
+
+        // 按会话回答：助手或用户不匹配时不送达
+        let (_, rx) = broker.ask("Deploy now?", Some("s1"), Some("coder"), Some("key.bob"));
+        assert!(broker.pending_for(None).is_empty());
+        assert!(!broker.answer_session("s1", Some("writer"), Some("key.bob"), "yes"));
+        assert!(!broker.answer_session("s1", None, None, "yes"));
+        assert!(broker.answer_session("s1", None, Some("key.bob"), "yes"));
+        assert_eq!(rx.await.unwrap(), "yes");
+        assert!(broker.pending().is_empty());
+
+        let mut checkpoint =
+            ReactCheckpoint::new("deploy", 2, vec![Message::user("deploy")], WorkingMemory::new(), None)
+                .with_pending_question(Some("Deploy now?"));
+        assert_eq!(checkpoint.interrupted_messages().len(), 1);
+        assert!(checkpoint.answer_question("yes"));
+        assert!(!checkpoint.answer_question("again"));
+        assert_eq!(checkpoint.messages.last().unwrap().content, "User answer: yes");
+        assert!(checkpoint.interrupted_messages().is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+//! ReAct 检查点：长任务中途崩溃或重新部署后从断点继续
+//!
+//! ContextManager 设置 checkpoint_path 后，循环在每一步开始前与执行工具前把状态（步数、对话、Working Memory、
+//! 进行中的工具调用）写入检查点；正常结束或被取消时删除。进程重启后 resume_react_loop 读取检查点继续，
+//! 已完成的步骤不会重做。执行中被打断的工具调用不会自动重放（可能有副作用），而是告知模型其结果未知。
+//! 等待用户回答时（AskUser）问题也记入检查点，回答可在重启后通过 answer_question 写入再恢复。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::memory::{Message, WorkingMemory};
+
+/// 本进程中正在运行的检查点（同一会话不允许并发恢复）
+static ACTIVE: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+
+fn active() -> std::sync::MutexGuard<'static, HashSet<PathBuf>> {
+    ACTIVE
+        .get_or_init(|| Mutex::new(HashSet::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 写检查点时尚未返回的工具调用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingToolCall {
+    pub tool: String,
+    pub args: Value,
+}
+
+/// ReAct 循环的可恢复状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactCheckpoint {
+    /// 本次任务的用户输入
+    pub user_input: String,
+    /// 下一步的步数（已完成的步数）
+    pub step: usize,
+    pub messages: Vec<Message>,
+    pub working: WorkingMemory,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_tool: Option<PendingToolCall>,
+    /// 等待用户回答的问题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_question: Option<String>,
+    /// 写入时间（RFC 3339）
+    pub updated_at: String,
+}
+
+impl ReactCheckpoint {
+    pub fn new(
+        user_input: &str,
+        step: usize,
+        messages: Vec<Message>,
+        working: WorkingMemory,
+        pending_tool: Option<PendingToolCall>,
+    ) -> Self {
+        Self {
+            user_input: user_input.to_string(),
+            step,
+            messages,
+            working,
+            pending_tool,
+            pending_question: None,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn with_pending_question(mut self, question: Option<&str>) -> Self {
+        self.pending_question = question.map(str::to_string);
+        self
+    }
+
+    /// 写入用户对等待中问题的回答（问答追加到对话），没有等待中的问题时返回 false
+    pub fn answer_question(&mut self, answer: &str) -> bool {
+        let Some(question) = self.pending_question.take() else {
+            return false;
+        };
+        self.messages.push(Message::assistant(question));
+        self.messages.push(Message::user(format!("User answer: {}", answer)));
+        true
+    }
+
+    /// 读取检查点（主文件损坏时回退到 .bak）
+    pub fn load(path: &Path) -> Option<Self> {
+        crate::memory::read_with_backup(path, |s| serde_json::from_str(s).ok()).map(|(c, _)| c)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        crate::memory::write_durable(path, &json)
+    }
+
+    /// 删除检查点及其备份
+    pub fn remove(path: &Path) {
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(crate::memory::backup_path(path));
+    }
+
+    /// 恢复时告知模型被打断的工具调用（结果未知，需先确认再决定是否重试）或未获回答的问题；
+    /// 被打断的工具调用以一对工具调用 / Tool 结果消息写回
+    pub fn interrupted_messages(&self) -> Vec<Message> {
+        if let Some(ref question) = self.pending_question {
+            return vec![Message::user(format!(
+                "(interrupted) the process restarted while waiting for the user to answer: {}. No answer was given; continue without it or ask again in your reply.",
+                question
+            ))];
+        }
+        let Some(p) = self.pending_tool.as_ref() else {
+            return Vec::new();
+        };
+        let call_id = format!("call_{}", uuid::Uuid::new_v4().simple());
+        vec![
+            Message::tool_call(&call_id, &p.tool, p.args.to_string()),
+            Message::tool_result(
+                &call_id,
+                &p.tool,
+                "(interrupted) the process restarted before this call returned, so its result is unknown. Check whether it took effect before retrying.",
+            ),
+        ]
+    }
+}
+
+/// 运行中的检查点占用；drop 时释放
+pub struct CheckpointLease {
+    path: PathBuf,
+}
+
+impl CheckpointLease {
+    /// 占用检查点；本进程已有循环在使用该检查点时返回 None
+    pub fn acquire(path: &Path) -> Option<Self> {
+        active().insert(path.to_path_buf()).then(|| Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// 该检查点是否正被本进程中的循环使用
+    pub fn is_active(path: &Path) -> bool {
+        active().contains(path)
+    }
+}
+
+impl Drop for CheckpointLease {
+    fn drop(&mut self) {
+        active().remove(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::RecoveryEngine;
+    use crate::react::{resume_react_loop, ContextManager, Planner, ReactSession, ReplayFixture};
+    use crate::tools::ToolExecutor;
+
+    #[tokio::test]
+    async fn test_checkpoint_roundtrip_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoints").join("s1---default.json");
+
+        let mut working = WorkingMemory::new();
+        working.set_goal("Summarize my notes");
+        working.add_attempt("ls -> notes/todo.md");
+        let checkpoint = ReactCheckpoint::new(
+            "Summarize my notes",
+            3,
+            vec![Message::user("Summarize my notes")],
+            working,
+            Some(PendingToolCall {
+                tool: "cat".into(),
+                args: json!({"path": "notes/todo.md"}),
+            }),
+        );
+        checkpoint.save(&path).unwrap();
+        let loaded = ReactCheckpoint::load(&path).unwrap();
+        assert_eq!(loaded.step, 3);
+        assert_eq!(loaded.working.attempts, vec!["ls -> notes/todo.md".to_string()]);
+
+        let lease = CheckpointLease::acquire(&path).unwrap();
+        assert!(CheckpointLease::is_active(&path));
+        assert!(CheckpointLease::acquire(&path).is_none());
+        drop(lease);
+        assert!(!CheckpointLease::is_active(&path));
+
+        // 恢复：不重放被打断的工具调用，模型直接给出回答后删除检查点
+        let fixture = ReplayFixture {
+            llm_responses: vec!["Your notes list two todo items.".into()],
+            tool_calls: vec![],
+        };
+        let planner = Planner::new(std::sync::Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        );
+        let mut context = ContextManager::new(10).with_checkpoint_path(path.clone());
+        let result = resume_react_loop(&session, &mut context, loaded).await.unwrap();
+
+        assert_eq!(result.response, "Your notes list two todo items.");
+        assert_eq!(result.messages[0].content, "Summarize my notes");
+        assert!(result.messages[1].is_tool_call());
+        assert_eq!(result.messages[2].role, crate::memory::Role::Tool);
+        assert_eq!(result.messages[2].tool_call_id, result.messages[1].tool_call_id);
+        assert!(result.messages[2].content.starts_with("(interrupted)"));
+        assert_eq!(context.working.goal.as_deref(), Some("Summarize my notes"));
+        assert!(ReactCheckpoint::load(&path).is_none());
+    }
+}
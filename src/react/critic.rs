@@ -6,14 +6,58 @@
 //! 通过配置可以：
 //! - 启用/禁用 Critic
 //! - 使用与 Planner 不同的模型（避免自我认同）
-//! - 仅评估特定工具（减少 token 开销）
+//! - 仅评估特定工具（减少 token 开销），并按 sample_rate 抽样评估 Observation
+//! - 按 rubric 逐项打分（低于 pass_score 时给出修正），分数计入 BehaviorMetrics
+//! - 为工具挂载确定性校验器（见 validators 模块），先于 LLM 评估执行且不受抽样影响
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+use serde_json::Value;
+
 use crate::config::CriticSection;
 use crate::llm::LlmClient;
-use crate::memory::Message;
+use crate::memory::{Message, Role};
+use crate::observability::Metrics;
+use crate::react::validators::ToolValidator;
+
+/// 评分 prompt：按 rubric 逐项 1-5 分
+const RUBRIC_PROMPT: &str = r#"You are a Critic scoring {subject} against a rubric.
+
+Goal: {goal}
+{content}
+
+Rubric:
+{rubric}
+
+Score each criterion from 1 (poor) to 5 (excellent). Respond with JSON only:
+{"scores": [<one score per criterion, in order>], "feedback": "<what to fix; empty if nothing>"}"#;
+
+/// 摘要校验 prompt：Context Compaction 替换前确认摘要未丢失任务说明与约束
+const SUMMARY_CHECK_PROMPT: &str = r#"You are a Critic checking a conversation summary before it replaces the original messages.
+
+Original messages:
+{messages}
+
+Summary:
+{summary}
+
+If the summary preserves every task instruction, constraint, user preference and open question, respond with "OK".
+Otherwise briefly list what is missing.
+
+Response:"#;
+
+/// 最终回复评审 prompt：MessageDone 前确认回答是否完成了用户目标
+const FINAL_ANSWER_PROMPT: &str = r#"You are a Critic reviewing an assistant's final answer before it is shown to the user.
+
+Goal: {goal}
+Answer: {answer}
+
+If the answer fully and correctly addresses the goal, respond with "OK".
+Otherwise briefly explain what is wrong or missing.
+
+Response:"#;
 
 /// Critic 评估结果：通过或需修正
 #[derive(Debug, Clone)]
@@ -34,6 +78,25 @@ pub struct Critic {
     evaluate_all_tools: bool,
     /// 仅评估的工具集合（evaluate_all_tools=false 时生效）
     evaluate_tools: HashSet<String>,
+    /// 是否评审最终回复
+    review_final_answer: bool,
+    /// Observation 抽样评估比例（0-1）
+    sample_rate: f64,
+    /// 已遇到的待评估 Observation 数（抽样计数）
+    sampled: AtomicU64,
+    /// 评分标准；为空时按 OK / 修正意见判断
+    rubric: Vec<String>,
+    /// rubric 归一化得分（0-1）的通过线
+    pass_score: f64,
+    /// 按工具挂载的校验器
+    validators: HashMap<String, Vec<Arc<dyn ToolValidator>>>,
+}
+
+/// 工具执行前由校验器记录的状态，执行后交给 Critic::validate
+pub struct ToolSnapshot {
+    tool: String,
+    args: Value,
+    states: Vec<Option<String>>,
 }
 
 impl Critic {
@@ -44,6 +107,12 @@ impl Critic {
             prompt_template: config.prompt_template.clone(),
             evaluate_all_tools: config.evaluate_all_tools,
             evaluate_tools: config.evaluate_tools.iter().cloned().collect(),
+            review_final_answer: config.review_final_answer,
+            sample_rate: config.sample_rate.clamp(0.0, 1.0),
+            sampled: AtomicU64::new(0),
+            rubric: config.rubric.clone(),
+            pass_score: config.pass_score,
+            validators: HashMap::new(),
         }
     }
 
@@ -54,6 +123,12 @@ impl Critic {
             prompt_template: prompt_template.into(),
             evaluate_all_tools: true,
             evaluate_tools: HashSet::new(),
+            review_final_answer: false,
+            sample_rate: 1.0,
+            sampled: AtomicU64::new(0),
+            rubric: vec![],
+            pass_score: 0.7,
+            validators: HashMap::new(),
         }
     }
 
@@ -70,6 +145,36 @@ impl Critic {
         self
     }
 
+    /// 设置是否评审最终回复
+    pub fn with_final_answer_review(mut self, enabled: bool) -> Self {
+        self.review_final_answer = enabled;
+        self
+    }
+
+    /// 设置 Observation 抽样评估比例（0-1）
+    pub fn with_sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 设置评分标准与通过线（归一化得分 0-1）
+    pub fn with_rubric(mut self, rubric: Vec<String>, pass_score: f64) -> Self {
+        self.rubric = rubric;
+        self.pass_score = pass_score;
+        self
+    }
+
+    /// 为工具挂载校验器
+    pub fn with_validator(mut self, tool: &str, validator: Arc<dyn ToolValidator>) -> Self {
+        self.validators.entry(tool.to_string()).or_default().push(validator);
+        self
+    }
+
+    /// 是否评审最终回复
+    pub fn reviews_final_answer(&self) -> bool {
+        self.review_final_answer
+    }
+
     /// 检查是否应该评估此工具
     fn should_evaluate(&self, tool: &str) -> bool {
         if self.evaluate_all_tools {
@@ -81,15 +186,89 @@ impl Critic {
         self.evaluate_tools.contains(tool)
     }
 
+    /// 按 sample_rate 均匀抽样：第 n 次是否评估
+    fn sample(&self) -> bool {
+        let n = self.sampled.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor()
+    }
+
+    /// 工具执行前记录校验器状态；该工具没有校验器时返回 None
+    pub fn snapshot(&self, tool: &str, args: &Value) -> Option<ToolSnapshot> {
+        let validators = self.validators.get(tool)?;
+        Some(ToolSnapshot {
+            tool: tool.to_string(),
+            args: args.clone(),
+            states: validators.iter().map(|v| v.snapshot(args)).collect(),
+        })
+    }
+
+    /// 工具执行后运行校验器，返回第一个未通过的原因（计入 BehaviorMetrics）
+    pub fn validate(&self, snapshot: &ToolSnapshot, observation: &str) -> Option<String> {
+        let validators = self.validators.get(&snapshot.tool)?;
+        validators.iter().zip(&snapshot.states).find_map(|(v, state)| {
+            v.validate(&snapshot.args, observation, state.as_deref())
+                .err()
+                .map(|reason| {
+                    Metrics::global().behavior.record_validator_failure();
+                    tracing::info!(tool = %snapshot.tool, validator = v.name(), "critic validator failed: {}", reason);
+                    format!("{} check failed for {}: {}", v.name(), snapshot.tool, reason)
+                })
+        })
+    }
+
+    /// 按 rubric 打分：返回归一化得分（0-1）与反馈；无法解析评分时返回 None
+    async fn score(&self, subject: &str, goal: &str, content: &str) -> Result<Option<(f64, String)>, String> {
+        let rubric = self
+            .rubric
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("{}. {}", i + 1, c))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = RUBRIC_PROMPT
+            .replace("{subject}", subject)
+            .replace("{goal}", goal)
+            .replace("{content}", content)
+            .replace("{rubric}", &rubric);
+        let response = self
+            .llm
+            .complete(&[Message::user(prompt)])
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(parse_rubric_scores(&response, self.rubric.len()))
+    }
+
+    /// 记录评分并转为结果：rubric 得分低于通过线时返回 Correction
+    fn rubric_result(&self, scored: Option<(f64, String)>) -> CriticResult {
+        let Some((score, feedback)) = scored else {
+            tracing::warn!("critic rubric reply could not be parsed, treating as approved");
+            return CriticResult::Approved;
+        };
+        let approved = score >= self.pass_score;
+        Metrics::global().behavior.record_critic_score(score, approved);
+        if approved {
+            CriticResult::Approved
+        } else if feedback.trim().is_empty() {
+            CriticResult::Correction(format!("Rubric score {:.2} is below {:.2}", score, self.pass_score))
+        } else {
+            CriticResult::Correction(feedback)
+        }
+    }
+
     pub async fn evaluate(
         &self,
         goal: &str,
         tool: &str,
         observation: &str,
     ) -> Result<CriticResult, String> {
-        if !self.should_evaluate(tool) {
+        if !self.should_evaluate(tool) || !self.sample() {
             return Ok(CriticResult::Skipped);
         }
+        if !self.rubric.is_empty() {
+            let content = format!("Tool used: {}\nObservation: {}", tool, observation);
+            let scored = self.score("a tool result", goal, &content).await?;
+            return Ok(self.rubric_result(scored));
+        }
 
         let prompt = self
             .prompt_template
@@ -102,11 +281,98 @@ impl Critic {
         let response = response.trim().to_uppercase();
 
         if response.starts_with("OK") || response.is_empty() {
+            Metrics::global().behavior.record_critic_score(1.0, true);
             Ok(CriticResult::Approved)
         } else {
+            Metrics::global().behavior.record_critic_score(0.0, false);
             Ok(CriticResult::Correction(response))
         }
     }
+
+    /// 评审最终回复：Correction 给出需要修订的问题
+    pub async fn evaluate_response(&self, goal: &str, answer: &str) -> Result<CriticResult, String> {
+        if !self.rubric.is_empty() {
+            let scored = self.score("an assistant's final answer", goal, &format!("Answer: {}", answer)).await?;
+            return Ok(self.rubric_result(scored));
+        }
+        let prompt = FINAL_ANSWER_PROMPT
+            .replace("{goal}", goal)
+            .replace("{answer}", answer);
+        let response = self
+            .llm
+            .complete(&[Message::user(prompt)])
+            .await
+            .map_err(|e| e.to_string())?;
+        let response = response.trim();
+
+        if response.is_empty() || response.to_uppercase().starts_with("OK") {
+            Metrics::global().behavior.record_critic_score(1.0, true);
+            Ok(CriticResult::Approved)
+        } else {
+            Metrics::global().behavior.record_critic_score(0.0, false);
+            Ok(CriticResult::Correction(response.to_string()))
+        }
+    }
+
+    /// 校验 Context Compaction 摘要：Approved 表示可替换，Correction 给出缺失内容（空回复也按 Correction 处理）
+    pub async fn validate_summary(
+        &self,
+        messages: &[Message],
+        summary: &str,
+    ) -> Result<CriticResult, String> {
+        let transcript = messages
+            .iter()
+            .map(|m| {
+                let role = match m.role {
+                    Role::User => "User",
+                    Role::Assistant => "Assistant",
+                    Role::System => "System",
+                    Role::Tool => "Tool",
+                };
+                format!("{}: {}", role, m.transcript_text())
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = SUMMARY_CHECK_PROMPT
+            .replace("{messages}", &transcript)
+            .replace("{summary}", summary);
+
+        let response = self
+            .llm
+            .complete(&[Message::user(prompt)])
+            .await
+            .map_err(|e| e.to_string())?;
+        let response = response.trim();
+
+        // 空回复说明校验没有完成，不能当作通过：保留原消息
+        if response.is_empty() {
+            Ok(CriticResult::Correction("critic returned an empty reply".to_string()))
+        } else if response.to_uppercase().starts_with("OK") {
+            Ok(CriticResult::Approved)
+        } else {
+            Ok(CriticResult::Correction(response.to_string()))
+        }
+    }
+}
+
+/// 解析 rubric 回复 {"scores": [...], "feedback": "..."}：分数 1-5 的平均值归一化到 0-1
+fn parse_rubric_scores(response: &str, criteria: usize) -> Option<(f64, String)> {
+    let start = response.find('{')?;
+    let end = response.rfind('}')?;
+    let value: Value = serde_json::from_str(response.get(start..=end)?).ok()?;
+    let scores: Vec<f64> = value
+        .get("scores")?
+        .as_array()?
+        .iter()
+        .filter_map(|s| s.as_f64())
+        .map(|s| s.clamp(1.0, 5.0))
+        .collect();
+    if scores.is_empty() || scores.len() < criteria {
+        return None;
+    }
+    let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+    let feedback = value.get("feedback").and_then(|f| f.as_str()).unwrap_or_default();
+    Some(((mean - 1.0) / 4.0, feedback.to_string()))
 }
 
 #[cfg(test)]
@@ -128,4 +394,71 @@ mod tests {
         assert!(critic.should_evaluate("code_edit"));
         assert!(!critic.should_evaluate("cat"));
     }
+
+    #[test]
+    fn test_evaluate_response_flags_non_ok_reply() {
+        let critic = Critic::new(Arc::new(MockLlmClient), "test").with_final_answer_review(true);
+        assert!(critic.reviews_final_answer());
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let result = rt
+            .block_on(critic.evaluate_response("say hi", "bye"))
+            .unwrap();
+        assert!(matches!(result, CriticResult::Correction(ref s) if s.contains("Echo from Mock")));
+    }
+
+    #[tokio::test]
+    async fn test_validate_summary_rejects_empty_reply() {
+        let replies = crate::react::ReplayFixture {
+            llm_responses: vec!["  ".into(), "OK".into()],
+            tool_calls: vec![],
+        };
+        let critic = Critic::new(Arc::new(replies.llm_client()), "test");
+        let messages = [Message::user("keep the port at 8080")];
+        let empty = critic.validate_summary(&messages, "user likes ports").await.unwrap();
+        assert!(matches!(empty, CriticResult::Correction(_)));
+        let ok = critic.validate_summary(&messages, "port stays 8080").await.unwrap();
+        assert!(matches!(ok, CriticResult::Approved));
+    }
+
+    #[tokio::test]
+    async fn test_sampling_rubric_and_validators() {
+        // 抽样：0.5 时每两次评估一次，0 时从不评估
+        let critic = Critic::new(Arc::new(MockLlmClient), "test").with_sample_rate(0.5);
+        let sampled: Vec<bool> = (0..4).map(|_| critic.sample()).collect();
+        assert_eq!(sampled, vec![false, true, false, true]);
+        let critic = Critic::new(Arc::new(MockLlmClient), "test").with_sample_rate(0.0);
+        assert!(matches!(critic.evaluate("g", "cat", "x").await, Ok(CriticResult::Skipped)));
+
+        assert_eq!(
+            parse_rubric_scores(r#"Sure: {"scores": [5, 3], "feedback": ""}"#, 2),
+            Some((0.75, String::new()))
+        );
+        assert_eq!(parse_rubric_scores(r#"{"scores": [5]}"#, 2), None);
+
+        let fixture = crate::react::ReplayFixture {
+            llm_responses: vec![
+                r#"{"scores": [5, 5], "feedback": ""}"#.into(),
+                r#"{"scores": [2, 1], "feedback": "Cite the source file."}"#.into(),
+            ],
+            tool_calls: vec![],
+        };
+        let before = Metrics::global().behavior.critic_evaluations.load(Ordering::Relaxed);
+        let critic = Critic::new(Arc::new(fixture.llm_client()), "test")
+            .with_rubric(vec!["Correct".into(), "Cites sources".into()], 0.7);
+        assert!(matches!(critic.evaluate("g", "cat", "x").await, Ok(CriticResult::Approved)));
+        assert!(matches!(
+            critic.evaluate_response("g", "answer").await,
+            Ok(CriticResult::Correction(ref s)) if s == "Cite the source file."
+        ));
+        assert!(Metrics::global().behavior.critic_evaluations.load(Ordering::Relaxed) >= before + 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}").unwrap();
+        let validator = crate::react::validators::builtin_validator("file_changed", dir.path()).unwrap();
+        let critic = Critic::new(Arc::new(MockLlmClient), "test").with_validator("code_edit", validator);
+        assert!(critic.snapshot("cat", &serde_json::json!({})).is_none());
+        let snapshot = critic.snapshot("code_edit", &serde_json::json!({"file_path": "a.rs"})).unwrap();
+        let problem = critic.validate(&snapshot, "Edited a.rs").unwrap();
+        assert!(problem.starts_with("file_changed check failed for code_edit"));
+    }
 }
@@ -2,6 +2,9 @@
 
 use serde::Serialize;
 
+use crate::core::FileChangeKind;
+use crate::tools::RiskLevel;
+
 /// 单步过程事件（可序列化为 JSON 供前端展示）
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -30,10 +33,24 @@ pub enum ReactEvent {
     MemoryRecovery { preview: String },
     /// 整理对话到长期记忆（写入内容预览）
     MemoryConsolidation { preview: String },
+    /// Critic 评审最终回复不通过后的自动修订：记录初稿、评审意见与修订稿
+    AnswerRevision {
+        draft: String,
+        critique: String,
+        revised: String,
+    },
     /// 最终回复的一小段（流式输出）
     MessageChunk { text: String },
     /// 最终回复结束
     MessageDone,
+    /// 调用 handoff 工具：会话转交给另一个助手（to 为空时由接入端路由选择），附交接摘要
+    Handoff {
+        to: Option<String>,
+        reason: String,
+        summary: String,
+    },
+    /// 基于本轮对话生成的 2~3 条追问建议（前端渲染为快捷回复）
+    Suggestions { items: Vec<String> },
     /// Token 使用统计（本次对话增量 + 累计）
     TokenUsage {
         prompt_tokens: u64,
@@ -46,6 +63,23 @@ pub enum ReactEvent {
         /// 累计 total tokens
         cumulative_total: u64,
     },
+    /// 工具调用需要用户审批（[tools.policy]），循环阻塞直到 TUI 按键或 POST /api/approvals/:id 送回结果
+    ApprovalRequired {
+        id: String,
+        tool: String,
+        args: serde_json::Value,
+        risk: RiskLevel,
+    },
+    /// 需要用户回答才能继续（恢复引擎建议 AskUser），循环暂停直到 TUI 输入或 POST /api/sessions/:id/answer 送回回答
+    AskUser { id: String, question: String },
+    /// watch 规则匹配的工作区文件发生变化（由文件监听器产生，推送给页面 / 网关客户端）
+    FileChanged {
+        watch_id: String,
+        path: String,
+        change: FileChangeKind,
+    },
+    /// 请求在限流队列中等待 ReAct 循环名额（[rate_limit] max_concurrent_loops），position 从 1 开始
+    Queued { position: usize },
     /// 错误
     Error { text: String },
 }
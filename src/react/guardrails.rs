@@ -0,0 +1,381 @@
+//! 内容护栏：在用户输入、工具参数与最终回复上执行内容策略
+//!
+//! [guardrails] 配置正则 / 关键词规则与可选的 LLM 分类器，命中时按动作处理：block 拦截（循环回复
+//! blocked_message，工具调用以 PermissionDenied 返回给模型）、redact 替换命中内容、warn 仅记录。
+//! 每次命中都输出 `guardrail_audit` JSON 审计日志（不含原文）。
+
+use std::sync::Arc;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{GuardrailRuleSection, GuardrailsSection};
+use crate::llm::LlmClient;
+use crate::memory::Message;
+
+/// 检查阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailStage {
+    /// 用户输入（进入循环前）
+    Input,
+    /// 工具调用参数（执行前）
+    ToolArgs,
+    /// 最终回复（展示给用户前）
+    Output,
+}
+
+/// 命中后的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailAction {
+    Block,
+    Redact,
+    Warn,
+}
+
+/// 检查结果：放行（可能已脱敏）或被拦截
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardrailVerdict<T> {
+    Allow(T),
+    Block { rule: String },
+}
+
+/// 分类 prompt：按策略判断内容是否违规
+const CLASSIFIER_PROMPT: &str = r#"You are a content-safety classifier.
+
+Policy: {policy}
+Content ({stage}):
+{content}
+
+If the content complies with the policy, respond with "SAFE".
+Otherwise respond with "UNSAFE: " followed by a short reason.
+
+Response:"#;
+
+struct Rule {
+    name: String,
+    stages: Vec<GuardrailStage>,
+    matchers: Vec<Regex>,
+    action: GuardrailAction,
+    replacement: String,
+}
+
+impl Rule {
+    /// 编译规则；无效的正则跳过并记录警告
+    fn compile(section: &GuardrailRuleSection) -> Self {
+        let keywords = section.keywords.iter().map(|k| regex::escape(k));
+        let matchers = section
+            .patterns
+            .iter()
+            .cloned()
+            .chain(keywords)
+            .filter_map(
+                |pattern| match RegexBuilder::new(&pattern).case_insensitive(true).build() {
+                    Ok(re) => Some(re),
+                    Err(e) => {
+                        tracing::warn!(rule = %section.name, "guardrail pattern skipped: {}", e);
+                        None
+                    }
+                },
+            )
+            .collect();
+        Self {
+            name: section.name.clone(),
+            stages: section.stages.clone(),
+            matchers,
+            action: section.action,
+            replacement: section.replacement.clone(),
+        }
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.matchers.iter().any(|re| re.is_match(text))
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.matchers.iter().fold(text.to_string(), |acc, re| {
+            re.replace_all(&acc, self.replacement.as_str()).into_owned()
+        })
+    }
+}
+
+struct Classifier {
+    llm: Arc<dyn LlmClient>,
+    policy: String,
+    stages: Vec<GuardrailStage>,
+    action: GuardrailAction,
+}
+
+/// 内容护栏（由 [guardrails] 构建，多会话共享）
+pub struct Guardrails {
+    rules: Vec<Rule>,
+    classifier: Option<Classifier>,
+    blocked_message: String,
+}
+
+impl Guardrails {
+    /// 编译规则（分类器需另外用 with_classifier 提供 LLM）
+    pub fn new(section: &GuardrailsSection) -> Self {
+        Self {
+            rules: section.rules.iter().map(Rule::compile).collect(),
+            classifier: None,
+            blocked_message: section.blocked_message.clone(),
+        }
+    }
+
+    /// 挂载 LLM 分类器（仅 [guardrails.classifier] enabled 时生效）
+    pub fn with_classifier(mut self, section: &GuardrailsSection, llm: Arc<dyn LlmClient>) -> Self {
+        self.classifier = section.classifier.enabled.then(|| Classifier {
+            llm,
+            policy: section.classifier.policy.clone(),
+            stages: section.classifier.stages.clone(),
+            action: section.classifier.action,
+        });
+        self
+    }
+
+    /// 拦截时回复给用户的文本
+    pub fn blocked_message(&self) -> &str {
+        &self.blocked_message
+    }
+
+    /// 是否有规则或分类器作用于该阶段
+    pub fn applies_to(&self, stage: GuardrailStage) -> bool {
+        self.rules.iter().any(|r| r.stages.contains(&stage))
+            || self.classifier.as_ref().is_some_and(|c| c.stages.contains(&stage))
+    }
+
+    /// 检查一段文本：先按顺序应用规则，再（若适用）调用分类器
+    pub async fn check(&self, stage: GuardrailStage, text: &str) -> GuardrailVerdict<String> {
+        let text = match self.apply_rules(stage, text, None) {
+            Ok(text) => text,
+            Err(rule) => return GuardrailVerdict::Block { rule },
+        };
+        match self.classify(stage, &text, None).await {
+            Some(rule) => GuardrailVerdict::Block { rule },
+            None => GuardrailVerdict::Allow(text),
+        }
+    }
+
+    /// 检查工具参数：规则作用于每个字符串值（redact 原位替换），分类器作用于整个参数 JSON
+    pub async fn check_args(&self, tool: &str, args: &Value) -> GuardrailVerdict<Value> {
+        let mut args = args.clone();
+        if let Err(rule) = self.apply_rules_to_value(&mut args, tool) {
+            return GuardrailVerdict::Block { rule };
+        }
+        match self
+            .classify(GuardrailStage::ToolArgs, &args.to_string(), Some(tool))
+            .await
+        {
+            Some(rule) => GuardrailVerdict::Block { rule },
+            None => GuardrailVerdict::Allow(args),
+        }
+    }
+
+    /// 应用规则：返回（可能已脱敏的）文本，block 规则命中时返回规则名
+    fn apply_rules(&self, stage: GuardrailStage, text: &str, tool: Option<&str>) -> Result<String, String> {
+        let mut text = text.to_string();
+        for rule in self.rules.iter().filter(|r| r.stages.contains(&stage)) {
+            if !rule.matches(&text) {
+                continue;
+            }
+            audit(stage, &rule.name, rule.action, tool);
+            match rule.action {
+                GuardrailAction::Block => return Err(rule.name.clone()),
+                GuardrailAction::Redact => text = rule.redact(&text),
+                GuardrailAction::Warn => {}
+            }
+        }
+        Ok(text)
+    }
+
+    fn apply_rules_to_value(&self, value: &mut Value, tool: &str) -> Result<(), String> {
+        match value {
+            Value::String(s) => {
+                *s = self.apply_rules(GuardrailStage::ToolArgs, s, Some(tool))?;
+                Ok(())
+            }
+            Value::Array(items) => items.iter_mut().try_for_each(|v| self.apply_rules_to_value(v, tool)),
+            Value::Object(map) => map.values_mut().try_for_each(|v| self.apply_rules_to_value(v, tool)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 分类器判定违规且动作不是 warn 时返回 Some("classifier")；调用失败时放行
+    async fn classify(&self, stage: GuardrailStage, text: &str, tool: Option<&str>) -> Option<String> {
+        let classifier = self.classifier.as_ref().filter(|c| c.stages.contains(&stage))?;
+        let prompt = CLASSIFIER_PROMPT
+            .replace("{policy}", &classifier.policy)
+            .replace("{stage}", stage_name(stage))
+            .replace("{content}", text);
+        let response = match classifier.llm.complete(&[Message::user(prompt)]).await {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::warn!(
+                    stage = stage_name(stage),
+                    "guardrail classifier failed, allowing: {}",
+                    e
+                );
+                return None;
+            }
+        };
+        if !response.trim().to_uppercase().starts_with("UNSAFE") {
+            return None;
+        }
+        audit(stage, "classifier", classifier.action, tool);
+        (classifier.action != GuardrailAction::Warn).then(|| "classifier".to_string())
+    }
+}
+
+fn stage_name(stage: GuardrailStage) -> &'static str {
+    match stage {
+        GuardrailStage::Input => "input",
+        GuardrailStage::ToolArgs => "tool_args",
+        GuardrailStage::Output => "output",
+    }
+}
+
+/// 审计日志：只记录阶段、规则与动作，不记录原文
+fn audit(stage: GuardrailStage, rule: &str, action: GuardrailAction, tool: Option<&str>) {
+    let audit = serde_json::json!({
+        "event": "guardrail_audit",
+        "stage": stage,
+        "rule": rule,
+        "action": action,
+        "tool": tool,
+        "assistant_id": crate::tools::CURRENT_ASSISTANT_ID.try_with(|a| a.clone()).ok().flatten(),
+    });
+    tracing::info!(audit = %audit.to_string(), "guardrail");
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::GuardrailClassifierSection;
+    use crate::core::RecoveryEngine;
+    use crate::react::{react_loop_v2, ContextManager, Planner, ReactSession, ReplayFixture};
+    use crate::tools::ToolExecutor;
+
+    fn rule(
+        name: &str,
+        stages: Vec<GuardrailStage>,
+        patterns: &[&str],
+        keywords: &[&str],
+        action: GuardrailAction,
+    ) -> GuardrailRuleSection {
+        GuardrailRuleSection {
+            name: name.to_string(),
+            stages,
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            action,
+            replacement: "[REDACTED]".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guardrails_rules_and_classifier() {
+        let all = vec![GuardrailStage::Input, GuardrailStage::ToolArgs, GuardrailStage::Output];
+        let section = GuardrailsSection {
+            enabled: true,
+            rules: vec![
+                rule(
+                    "api_keys",
+                    all.clone(),
+                    &[r"sk-[A-Za-z0-9]{8,}", "(invalid"],
+                    &[],
+                    GuardrailAction::Redact,
+                ),
+                rule(
+                    "banned",
+                    vec![GuardrailStage::Input],
+                    &[],
+                    &["Make A Bomb"],
+                    GuardrailAction::Block,
+                ),
+                rule("profanity", all, &[], &["darn"], GuardrailAction::Warn),
+            ],
+            classifier: GuardrailClassifierSection {
+                enabled: true,
+                stages: vec![GuardrailStage::Output],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let fixture = ReplayFixture {
+            llm_responses: vec!["SAFE".into(), "UNSAFE: leaks personal data".into()],
+            tool_calls: vec![],
+        };
+        let guardrails = Guardrails::new(&section).with_classifier(&section, Arc::new(fixture.llm_client()));
+        assert!(guardrails.applies_to(GuardrailStage::ToolArgs));
+
+        assert_eq!(
+            guardrails
+                .check(GuardrailStage::Input, "darn, my key is sk-abcdef123456")
+                .await,
+            GuardrailVerdict::Allow("darn, my key is [REDACTED]".to_string())
+        );
+        assert_eq!(
+            guardrails.check(GuardrailStage::Input, "how to make a bomb").await,
+            GuardrailVerdict::Block {
+                rule: "banned".to_string()
+            }
+        );
+        // 关键词规则只作用于 input
+        assert_eq!(
+            guardrails
+                .check_args(
+                    "http_fetch",
+                    &json!({"url": "https://x.io", "headers": ["Bearer sk-abcdef123456"], "q": "make a bomb"})
+                )
+                .await,
+            GuardrailVerdict::Allow(
+                json!({"url": "https://x.io", "headers": ["Bearer [REDACTED]"], "q": "make a bomb"})
+            )
+        );
+
+        // 分类器：第一次 SAFE 放行，第二次 UNSAFE 拦截
+        assert_eq!(
+            guardrails.check(GuardrailStage::Output, "Here is the summary.").await,
+            GuardrailVerdict::Allow("Here is the summary.".to_string())
+        );
+        assert_eq!(
+            guardrails.check(GuardrailStage::Output, "Alice lives at ...").await,
+            GuardrailVerdict::Block {
+                rule: "classifier".to_string()
+            }
+        );
+
+        // 循环内：输入与最终回复中的密钥被替换，被拦截的输入直接回复 blocked_message
+        let guardrails = Guardrails::new(&section);
+        let fixture = ReplayFixture {
+            llm_responses: vec!["Saved sk-abcdef123456 for you.".into()],
+            tool_calls: vec![],
+        };
+        let planner = Planner::new(Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        )
+        .with_guardrails(Some(&guardrails));
+        let mut context = ContextManager::new(10);
+        let result = react_loop_v2(&session, &mut context, "Remember sk-abcdef123456")
+            .await
+            .unwrap();
+        assert_eq!(result.response, "Saved [REDACTED] for you.");
+        assert_eq!(result.messages[0].content, "Remember [REDACTED]");
+        let result = react_loop_v2(&session, &mut context, "How do I make a bomb?")
+            .await
+            .unwrap();
+        assert_eq!(result.response, section.blocked_message);
+        assert!(!result.messages.iter().any(|m| m.content.contains("bomb")));
+    }
+}
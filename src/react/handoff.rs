@@ -0,0 +1,174 @@
+//! 助手交接（handoff）：把当前会话转交给另一个助手，附带交接摘要
+//!
+//! ReAct 循环中调用 `handoff` 工具后，循环生成交接摘要、以转交说明结束本轮，并把 [`HandoffRequest`] 留在
+//! `ContextManager::pending_handoff`；接入端（bee-web）据此选出目标助手（显式指定或由路由选择），
+//! 把摘要作为 System 消息写入目标助手的会话。目标助手使用自己的技能范围，不继承发起方的工具权限。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::memory::{Message, Role};
+use crate::react::Planner;
+
+/// 交接工具名：循环据此识别交接调用
+pub const HANDOFF_TOOL: &str = "handoff";
+
+/// 生成摘要时最多带入的最近消息数
+const SUMMARY_RECENT_MESSAGES: usize = 20;
+/// 单条消息带入摘要 prompt 的最大字符数
+const SUMMARY_MESSAGE_CHARS: usize = 1000;
+
+const SUMMARY_SYSTEM: &str =
+    "You write handoff notes. Another assistant is taking over this conversation and cannot see it. \
+Summarize for them in a few short lines: what the user wants, what has been done or decided so far, open questions, \
+and what to do next. Use the same language as the conversation. Output only the note.";
+
+/// 一次交接：目标助手、原因与交接摘要
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandoffRequest {
+    /// 目标助手 id；None 表示由路由按原因与摘要选择
+    #[serde(default)]
+    pub to: Option<String>,
+    pub reason: String,
+    /// 交接摘要（循环结束前生成；工具参数中的 summary 只作为生成失败时的回退）
+    #[serde(default)]
+    pub summary: String,
+}
+
+impl HandoffRequest {
+    /// 从 handoff 工具参数解析（to / reason / summary，空字符串视为未填）
+    pub fn from_args(args: &Value) -> Self {
+        let text = |key: &str| {
+            args.get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            to: text("to"),
+            reason: text("reason").unwrap_or_default(),
+            summary: text("summary").unwrap_or_default(),
+        }
+    }
+
+    /// 供路由选择目标助手的描述
+    pub fn routing_text(&self) -> String {
+        if self.summary.is_empty() {
+            self.reason.clone()
+        } else {
+            format!("{}\n\n{}", self.reason, self.summary)
+        }
+    }
+}
+
+/// 用模型生成交接摘要；失败或为空时回退到工具参数中的 summary，再回退到最近一条用户消息
+pub async fn generate_handoff_summary(planner: &Planner, messages: &[Message], request: &HandoffRequest) -> String {
+    let recent = &messages[messages.len().saturating_sub(SUMMARY_RECENT_MESSAGES)..];
+    let transcript: Vec<String> = recent
+        .iter()
+        .filter(|m| m.role != Role::System)
+        .map(|m| {
+            let text: String = m.transcript_text().chars().take(SUMMARY_MESSAGE_CHARS).collect();
+            format!("{:?}: {}", m.role, text)
+        })
+        .collect();
+    let mut prompt = format!("Reason for handoff: {}\n", request.reason);
+    if !request.summary.is_empty() {
+        prompt.push_str(&format!("Notes from the current assistant: {}\n", request.summary));
+    }
+    prompt.push_str(&format!("\nConversation:\n{}", transcript.join("\n")));
+    match planner.plan_with_system(&[Message::user(prompt)], SUMMARY_SYSTEM).await {
+        Ok(summary) if !summary.trim().is_empty() => summary.trim().to_string(),
+        Ok(_) => fallback_summary(messages, request),
+        Err(e) => {
+            tracing::warn!("handoff summary failed: {}", e);
+            fallback_summary(messages, request)
+        }
+    }
+}
+
+fn fallback_summary(messages: &[Message], request: &HandoffRequest) -> String {
+    if !request.summary.is_empty() {
+        return request.summary.clone();
+    }
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == Role::User)
+        .map(|m| format!("User request: {}", m.content))
+        .unwrap_or_else(|| request.reason.clone())
+}
+
+/// 写入目标助手会话的交接消息：System 说明（不被剪枝，目标助手后续每轮都能看到）与一条用户可见的接手说明
+pub fn handoff_messages(from: &str, request: &HandoffRequest) -> Vec<Message> {
+    vec![
+        Message::system(format!(
+            "This conversation was handed off to you by assistant {}.\nReason: {}\nHandoff summary:\n{}",
+            from, request.reason, request.summary
+        )),
+        Message::assistant(format!(
+            "已接手来自 {} 的会话（{}）。\n\n交接摘要：\n{}",
+            from, request.reason, request.summary
+        )),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::RecoveryEngine;
+    use crate::react::replay::RecordedToolCall;
+    use crate::react::{react_loop_v2, ContextManager, ReactSession, ReplayFixture};
+    use crate::tools::ToolExecutor;
+
+    #[tokio::test]
+    async fn test_handoff_tool_ends_turn_with_summary() {
+        let args = json!({"to": "coder", "reason": "needs a code change"});
+        let fixture = ReplayFixture {
+            llm_responses: vec![
+                json!({"tool": HANDOFF_TOOL, "args": args}).to_string(),
+                "User wants a retry flag added to the fetch command.".into(),
+            ],
+            tool_calls: vec![RecordedToolCall {
+                tool: HANDOFF_TOOL.into(),
+                args: args.clone(),
+                output: Ok("Handoff accepted".into()),
+            }],
+        };
+        let planner = Planner::new(Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        );
+        let mut context = ContextManager::new(10);
+
+        let result = react_loop_v2(&session, &mut context, "add a --retry flag to fetch")
+            .await
+            .unwrap();
+        let handoff = context.take_handoff().expect("pending handoff");
+        assert_eq!(handoff.to.as_deref(), Some("coder"));
+        assert_eq!(handoff.reason, "needs a code change");
+        assert_eq!(handoff.summary, "User wants a retry flag added to the fetch command.");
+        assert!(result.response.contains("coder"));
+        assert!(context.take_handoff().is_none());
+
+        let note = handoff_messages("default", &handoff);
+        assert_eq!(note[0].role, Role::System);
+        assert!(note[0].content.contains("retry flag"));
+        assert_eq!(note[1].role, Role::Assistant);
+
+        // 空的 to 视为未指定，由路由按原因与摘要选择
+        let req = HandoffRequest::from_args(&json!({"to": " ", "reason": "legal question", "summary": "contract"}));
+        assert_eq!(req.to, None);
+        assert_eq!(req.routing_text(), "legal question\n\ncontract");
+    }
+}
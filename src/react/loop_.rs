@@ -1,19 +1,73 @@
 //! ReAct 主循环
 //!
-//! Plan -> Act (Tool) -> Observe -> 可选 Critic -> 下一轮 Plan；支持 RetryWithPrompt、Cancel、最大步数限制。
+//! Plan -> Act (Tool) -> Observe -> 可选 Critic -> 下一轮 Plan；支持 RetryWithPrompt、Cancel、最大步数与总时限（ReactLimits）。
 //! 可选 event_tx：向 Web 等前端推送 Thinking / ToolCall / Observation / MessageChunk / MessageDone。
+//! ContextManager 设置 checkpoint_path 时每步写检查点，可用 resume_react_loop 从中断处继续。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
 
 use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::config::ReactSection;
+use crate::core::{AgentError, ErrorClass, RecoveryAction, RecoveryEngine, TaskScheduler};
+use crate::llm::{estimate_messages_tokens, estimate_tokens, truncate_messages_to_budget};
+use crate::memory::{dedup_observations, importance_score, MemorySegment, Message, Role, TokenBudget};
+use crate::react::ask_user::QuestionBroker;
+use crate::react::checkpoint::{CheckpointLease, PendingToolCall, ReactCheckpoint};
+use crate::react::guardrails::{GuardrailStage, GuardrailVerdict, Guardrails};
+use crate::react::handoff::{generate_handoff_summary, HandoffRequest, HANDOFF_TOOL};
+use crate::react::{
+    condense_observation, parse_llm_output, ContextManager, Critic, CriticResult, Planner, ReactEvent,
+};
+use crate::tools::{ToolError, ToolExecutor, CURRENT_ALLOWED_TOOLS, CURRENT_ASSISTANT_ID};
 
-use crate::core::{AgentError, RecoveryAction, RecoveryEngine, TaskScheduler};
-use crate::memory::Message;
-use crate::react::{parse_llm_output, ContextManager, Critic, CriticResult, Planner, ReactEvent};
-use crate::tools::ToolExecutor;
+/// ReAct 循环上限：[react] 配置，可按助手或单次请求覆盖
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReactLimits {
+    /// 单次请求最大 ReAct 步数，防止死循环
+    pub max_steps: usize,
+    /// 对话条数超过此值时在规划前执行一次 Context Compaction（摘要写入长期记忆并替换为摘要消息）
+    pub compact_threshold: usize,
+    /// 单次请求总时限；到期时结束循环并返回已完成部分的结果，None 表示不限
+    pub max_duration: Option<Duration>,
+    /// 询问用户后等待回答的时长；None 表示不询问，按错误结束
+    pub ask_user_timeout: Option<Duration>,
+}
 
-/// 单次对话内最大 ReAct 步数，防止死循环
-const MAX_REACT_STEPS: usize = 20;
-/// 对话条数超过此值时在规划前执行一次 Context Compaction（摘要写入长期记忆并替换为摘要消息）
-const COMPACT_THRESHOLD: usize = 24;
+impl Default for ReactLimits {
+    fn default() -> Self {
+        (&ReactSection::default()).into()
+    }
+}
+
+impl From<&ReactSection> for ReactLimits {
+    fn from(section: &ReactSection) -> Self {
+        Self {
+            max_steps: section.max_steps.max(1),
+            compact_threshold: section.compact_threshold,
+            max_duration: (section.max_duration_secs > 0).then(|| Duration::from_secs(section.max_duration_secs)),
+            ask_user_timeout: (section.ask_user_timeout_secs > 0)
+                .then(|| Duration::from_secs(section.ask_user_timeout_secs)),
+        }
+    }
+}
+
+impl ReactLimits {
+    /// 用助手或请求级设置覆盖（None 保持原值；max_duration_secs 为 0 表示不限）
+    pub fn with_overrides(mut self, max_steps: Option<usize>, max_duration_secs: Option<u64>) -> Self {
+        if let Some(n) = max_steps {
+            self.max_steps = n.max(1);
+        }
+        if let Some(secs) = max_duration_secs {
+            self.max_duration = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        self
+    }
+}
 
 /// 从用户输入中提取「记住：xxx」类内容，用于写入 preferences
 fn extract_remember_content(input: &str) -> Option<String> {
@@ -66,6 +120,10 @@ pub struct ReactSession<'a> {
     pub system_prompt_override: Option<&'a str>,
     /// 可选：限制可用工具列表
     pub allowed_tools: Option<&'a [String]>,
+    /// 步数与时限（默认取 [react] 默认值）
+    pub limits: ReactLimits,
+    /// 可选：内容护栏（[guardrails]）
+    pub guardrails: Option<&'a Guardrails>,
 }
 
 impl<'a> ReactSession<'a> {
@@ -87,6 +145,8 @@ impl<'a> ReactSession<'a> {
             event_tx: None,
             system_prompt_override: None,
             allowed_tools: None,
+            limits: ReactLimits::default(),
+            guardrails: None,
         }
     }
 
@@ -125,6 +185,18 @@ impl<'a> ReactSession<'a> {
         self.allowed_tools = Some(tools);
         self
     }
+
+    /// 设置步数与时限
+    pub fn with_limits(mut self, limits: ReactLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 设置内容护栏（None 表示不过滤）
+    pub fn with_guardrails(mut self, guardrails: Option<&'a Guardrails>) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
 }
 
 fn send_event(tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>, ev: ReactEvent) {
@@ -133,26 +205,108 @@ fn send_event(tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>, ev:
     }
 }
 
+/// 按 executor 挂载的工具策略放行 / 请求用户审批 / 拒绝；需审批时经 event_tx 发出 ApprovalRequired，无事件消费方视为拒绝
+async fn authorize_tool_call(
+    executor: &ToolExecutor,
+    tool: &str,
+    args: &serde_json::Value,
+    event_tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+) -> Result<(), ToolError> {
+    executor
+        .authorize(tool, args, |req| {
+            event_tx.is_some_and(|tx| {
+                tx.send(ReactEvent::ApprovalRequired {
+                    id: req.id.clone(),
+                    tool: req.tool.clone(),
+                    args: req.args.clone(),
+                    risk: req.risk,
+                })
+                .is_ok()
+            })
+        })
+        .await
+}
+
+/// Context Compaction 结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompactionOutcome {
+    /// 已替换：summarized 条消息被摘要，kept 条消息原样保留（置顶 + 最近轮次）
+    Compacted { summarized: usize, kept: usize },
+    /// 可摘要的消息不足，未做任何改动
+    Skipped,
+    /// Critic 认为摘要丢失了关键内容（或校验调用失败、摘要未经审查），保留原消息不替换
+    Rejected(String),
+}
+
 /// Context Compaction：将当前对话摘要写入长期记忆，并替换为一条摘要型 system 消息，避免 token 溢出。
 /// 可由 ReAct 循环在消息数超过阈值时自动调用，或由 Web API 手动触发。
 pub async fn compact_context(
     planner: &Planner,
     context: &mut ContextManager,
-) -> Result<(), AgentError> {
-    let messages = context.messages().to_vec();
-    if messages.len() < 2 {
-        return Ok(());
+) -> Result<CompactionOutcome, AgentError> {
+    compact_context_with_critic(planner, None, context).await
+}
+
+/// 带质量控制的 Context Compaction：
+/// - 可选先合并重复的工具观察
+/// - 置顶消息、重要性达到 keep_importance 的消息与最近 keep_recent_turns 轮原样保留，只摘要更早的消息
+/// - 若策略要求且提供了 Critic，替换前校验摘要；被拒绝或校验调用失败时保留原消息
+pub async fn compact_context_with_critic(
+    planner: &Planner,
+    critic: Option<&Critic>,
+    context: &mut ContextManager,
+) -> Result<CompactionOutcome, AgentError> {
+    let mut messages = context.messages().to_vec();
+    let policy = context.compaction.clone();
+    if policy.dedup_observations {
+        dedup_observations(&mut messages);
+    }
+    let tail_start = messages.len().saturating_sub(policy.keep_recent_turns * 2);
+    let (head, tail) = messages.split_at(tail_start);
+    // 旧摘要（System）不按重要性保留，随本次一起重新摘要
+    let keep = |m: &Message| {
+        m.pinned
+            || (policy.keep_importance > 0
+                && m.role != Role::System
+                && importance_score(m) >= policy.keep_importance)
+    };
+    let preserved: Vec<Message> = head.iter().filter(|m| keep(m)).cloned().collect();
+    let to_summarize: Vec<Message> = head.iter().filter(|m| !keep(m)).cloned().collect();
+    if to_summarize.len() < 2 {
+        return Ok(CompactionOutcome::Skipped);
     }
-    let summary = planner.summarize(&messages).await?;
+    let summary = planner.summarize(&to_summarize).await?;
     if summary.is_empty() {
-        return Ok(());
+        return Ok(CompactionOutcome::Skipped);
+    }
+    if policy.validate_with_critic {
+        if let Some(c) = critic {
+            match c.validate_summary(&to_summarize, &summary).await {
+                Ok(CriticResult::Correction(reason)) => {
+                    tracing::warn!("compaction summary rejected by critic: {}", reason);
+                    return Ok(CompactionOutcome::Rejected(reason));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("compaction summary check failed, keeping uncompacted history: {}", e);
+                    return Ok(CompactionOutcome::Rejected(format!("critic check failed: {}", e)));
+                }
+            }
+        }
     }
     context.push_to_long_term(&format!("Conversation summary: {}", summary));
-    context.set_messages(vec![Message::system(format!(
+    let kept = preserved.len() + tail.len();
+    let mut new_messages = preserved;
+    new_messages.push(Message::system(format!(
         "Previous conversation summary:\n\n{}",
         summary
-    ))]);
-    Ok(())
+    )));
+    new_messages.extend_from_slice(tail);
+    context.set_messages(new_messages);
+    Ok(CompactionOutcome::Compacted {
+        summarized: to_summarize.len(),
+        kept,
+    })
 }
 
 /// 执行 ReAct 循环（新版本，使用 ReactSession 结构体）
@@ -179,7 +333,7 @@ pub async fn react_loop_v2(
     react_loop_impl(
         planner, executor, recovery, context, user_input,
         stream_tx, event_tx, cancel_token, critic, task_scheduler,
-        system_prompt_override, allowed_tools,
+        system_prompt_override, allowed_tools, session.limits, session.guardrails, None,
     ).await
 }
 
@@ -187,6 +341,7 @@ pub async fn react_loop_v2(
 ///
 /// 若提供 system_prompt_override，则用其替代 planner 的 base_system_prompt（用于多助手场景）。
 /// allowed_tools: 该智能体可用的工具名列表；为 None 或空时使用 executor 全部工具。
+/// 步数与时限使用默认值，需要配置时请用 react_loop_v2 + ReactSession::with_limits。
 #[allow(clippy::too_many_arguments)]
 pub async fn react_loop(
     planner: &Planner,
@@ -205,11 +360,235 @@ pub async fn react_loop(
     react_loop_impl(
         planner, executor, recovery, context, user_input,
         stream_tx, event_tx, cancel_token, critic, task_scheduler,
-        system_prompt_override, allowed_tools,
+        system_prompt_override, allowed_tools, ReactLimits::default(), None, None,
+    ).await
+}
+
+/// 从检查点恢复中断的 ReAct 循环：还原对话与 Working Memory，从记录的步数继续（不重复写入用户输入）
+pub async fn resume_react_loop(
+    session: &ReactSession<'_>,
+    context: &mut ContextManager,
+    checkpoint: ReactCheckpoint,
+) -> Result<ReactResult, AgentError> {
+    let user_input = checkpoint.user_input.clone();
+    react_loop_impl(
+        session.planner, session.executor, session.recovery, context, &user_input,
+        session.stream_tx, session.event_tx, session.cancel_token.clone(), session.critic, session.task_scheduler,
+        session.system_prompt_override, session.allowed_tools, session.limits, session.guardrails, Some(checkpoint),
     ).await
 }
 
-/// ReAct 循环内部实现
+/// 写入检查点；失败只记录日志，不影响本步
+fn save_checkpoint(
+    path: Option<&Path>,
+    context: &ContextManager,
+    user_input: &str,
+    step: usize,
+    pending_tool: Option<PendingToolCall>,
+    pending_question: Option<&str>,
+) {
+    let Some(path) = path else {
+        return;
+    };
+    let checkpoint = ReactCheckpoint::new(
+        user_input,
+        step,
+        context.messages().to_vec(),
+        context.working.clone(),
+        pending_tool,
+    )
+    .with_pending_question(pending_question);
+    if let Err(e) = checkpoint.save(path) {
+        tracing::warn!(path = %path.display(), "failed to save react checkpoint: {}", e);
+    }
+}
+
+/// 向用户提问并等待回答：未开启、无事件消费方、超时或被取消时返回 None
+async fn ask_user(
+    question: &str,
+    timeout: Option<Duration>,
+    event_tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+    cancel_token: &tokio_util::sync::CancellationToken,
+) -> Option<String> {
+    let timeout = timeout?;
+    let tx = (*event_tx)?;
+    let origin = crate::tools::remind::current_origin().filter(|o| o.channel == "web");
+    let broker = QuestionBroker::global();
+    let (pending, rx) = broker.ask(
+        question,
+        origin.as_ref().map(|o| o.target.as_str()),
+        origin.as_ref().and_then(|o| o.assistant_id.as_deref()),
+        origin.as_ref().and_then(|o| o.user_id.as_deref()),
+    );
+    let sent = tx.send(ReactEvent::AskUser {
+        id: pending.id.clone(),
+        question: question.to_string(),
+    });
+    if sent.is_err() {
+        broker.forget(&pending.id);
+        return None;
+    }
+    tracing::info!(id = %pending.id, "waiting for user answer");
+    let answer = tokio::select! {
+        answer = tokio::time::timeout(timeout, rx) => answer.ok().and_then(Result::ok),
+        _ = cancel_token.cancelled() => None,
+    };
+    if answer.is_none() {
+        broker.forget(&pending.id);
+    }
+    answer
+}
+
+/// 在总时限内等待 fut；到期返回 None（未设时限时直接等待）
+async fn within_deadline<F: Future>(deadline: Option<Instant>, fut: F) -> Option<F::Output> {
+    match deadline {
+        Some(d) => tokio::time::timeout_at(d, fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+/// 部分结果：说明停止原因，列出已完成的工具步骤并附最后一次模型输出
+fn partial_response(reason: &str, attempts: &[String], last_llm_output: &str) -> String {
+    let mut out = format!("{}，以下是目前的进展。", reason);
+    if !attempts.is_empty() {
+        out.push_str("\n\n已完成的步骤：");
+        for attempt in attempts {
+            let line: String = attempt.chars().take(OBSERVATION_PREVIEW_CHARS).collect();
+            out.push_str(&format!("\n- {}", line.replace('\n', " ")));
+        }
+    }
+    if !last_llm_output.trim().is_empty() {
+        out.push_str(&format!("\n\n最后输出：\n{}", last_llm_output));
+    }
+    out
+}
+
+/// 步数或时限用尽：以部分结果作为本轮回复结束循环（照常推送给前端并写入对话）
+fn finish_partial(
+    context: &mut ContextManager,
+    user_input: &str,
+    reason: &str,
+    last_llm_output: &str,
+    event_tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+) -> ReactResult {
+    let tools_used = context.working.tool_names_used();
+    context.record_episode(user_input, &tools_used, reason, false);
+    let response = partial_response(reason, &context.working.attempts, last_llm_output);
+    let chars: Vec<char> = response.chars().collect();
+    for chunk in chars.chunks(CHUNK_CHARS) {
+        send_event(event_tx, ReactEvent::MessageChunk {
+            text: chunk.iter().collect(),
+        });
+    }
+    send_event(event_tx, ReactEvent::MessageDone);
+    context.push_message(Message::assistant(response.clone()));
+    ReactResult {
+        response,
+        messages: context.messages().to_vec(),
+    }
+}
+
+/// handoff 工具调用成功：生成交接摘要，以转交说明结束本轮，交接请求留给接入端转交给目标助手
+async fn finish_handoff(
+    planner: &Planner,
+    context: &mut ContextManager,
+    user_input: &str,
+    mut request: HandoffRequest,
+    event_tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+) -> ReactResult {
+    request.summary = generate_handoff_summary(planner, context.messages(), &request).await;
+    send_event(event_tx, ReactEvent::Handoff {
+        to: request.to.clone(),
+        reason: request.reason.clone(),
+        summary: request.summary.clone(),
+    });
+    let response = match &request.to {
+        Some(to) => format!("已转交给 {}：{}", to, request.reason),
+        None => format!("已转交给更合适的助手：{}", request.reason),
+    };
+    let chars: Vec<char> = response.chars().collect();
+    for chunk in chars.chunks(CHUNK_CHARS) {
+        send_event(event_tx, ReactEvent::MessageChunk {
+            text: chunk.iter().collect(),
+        });
+    }
+    send_event(event_tx, ReactEvent::MessageDone);
+    context.push_message(Message::assistant(response.clone()));
+    let tools_used = context.working.tool_names_used();
+    context.record_episode(user_input, &tools_used, &response, true);
+    context.pending_handoff = Some(request);
+    ReactResult {
+        response,
+        messages: context.messages().to_vec(),
+    }
+}
+
+fn deadline_result(
+    context: &mut ContextManager,
+    user_input: &str,
+    limits: &ReactLimits,
+    last_llm_output: &str,
+    event_tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+) -> ReactResult {
+    let secs = limits.max_duration.map(|d| d.as_secs()).unwrap_or_default();
+    send_event(event_tx, ReactEvent::Recovery {
+        action: "Deadline".to_string(),
+        detail: format!("Time limit of {}s reached, returning partial result", secs),
+    });
+    finish_partial(context, user_input, &format!("达到时间上限 ({} 秒)", secs), last_llm_output, event_tx)
+}
+
+/// 拼接动态 system：按模型上下文预算为各记忆段分配 token（对话占用之外的部分），超出时各段按比例截断，
+/// 基础 prompt（含工具 schema）不截断；budget 为 0 时不限制
+fn assemble_system_prompt(sections: Vec<(MemorySegment, String)>, budget: usize, conversation_tokens: usize) -> String {
+    let sections = if budget > 0 {
+        // 对话最多占一半预算，避免长对话把记忆段挤空；剩余溢出由请求前预检压缩 / 截断对话
+        let allocated = TokenBudget::new(budget)
+            .with_conversation_reserve(conversation_tokens.min(budget / 2))
+            .allocate_proportional(&sections);
+        if allocated != sections {
+            let trimmed: Vec<String> = sections
+                .iter()
+                .zip(&allocated)
+                .filter(|(before, after)| before.1 != after.1)
+                .map(|(before, _)| format!("{:?}", before.0))
+                .collect();
+            tracing::info!(budget, conversation_tokens, trimmed = ?trimmed, "system prompt sections trimmed to fit context budget");
+        }
+        allocated
+    } else {
+        sections
+    };
+    let mut parts = sections.into_iter().map(|(_, content)| content);
+    let base = parts.next().unwrap_or_default();
+    let working = parts.next().unwrap_or_default();
+    let rest: String = parts.collect();
+    format!("{}\n\n{}\n\n{}", base, working, rest)
+}
+
+/// 护栏拦截用户输入：不调用模型，直接回复 blocked_message（原输入不写入对话）
+fn input_blocked(
+    context: &mut ContextManager,
+    guardrails: &Guardrails,
+    rule: &str,
+    event_tx: &Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+) -> ReactResult {
+    send_event(event_tx, ReactEvent::Recovery {
+        action: "Guardrail".to_string(),
+        detail: format!("input blocked by rule {}", rule),
+    });
+    let reply = guardrails.blocked_message().to_string();
+    send_event(event_tx, ReactEvent::MessageChunk { text: reply.clone() });
+    send_event(event_tx, ReactEvent::MessageDone);
+    context.push_message(Message::user(format!("(message blocked by guardrail rule {})", rule)));
+    context.push_message(Message::assistant(reply.clone()));
+    ReactResult {
+        response: reply,
+        messages: context.messages().to_vec(),
+    }
+}
+
+/// ReAct 循环内部实现：占用检查点，任务结束（完成、取消、调用越权工具）后删除；其它错误保留检查点以便恢复
 #[allow(clippy::too_many_arguments)]
 async fn react_loop_impl(
     planner: &Planner,
@@ -224,51 +603,166 @@ async fn react_loop_impl(
     task_scheduler: Option<&TaskScheduler>,
     system_prompt_override: Option<&str>,
     allowed_tools: Option<&[String]>,
+    limits: ReactLimits,
+    guardrails: Option<&Guardrails>,
+    resume: Option<ReactCheckpoint>,
 ) -> Result<ReactResult, AgentError> {
-    context.push_message(Message::user(user_input.to_string()));
-    context.working.set_goal(user_input);
+    // 护栏：新任务的用户输入先过滤（恢复时检查点中已是过滤后的输入）
+    let guarded_input;
+    let user_input = match guardrails.filter(|g| resume.is_none() && g.applies_to(GuardrailStage::Input)) {
+        Some(g) => match g.check(GuardrailStage::Input, user_input).await {
+            GuardrailVerdict::Allow(text) => {
+                guarded_input = text;
+                guarded_input.as_str()
+            }
+            GuardrailVerdict::Block { rule } => return Ok(input_blocked(context, g, &rule, &event_tx)),
+        },
+        None => user_input,
+    };
+    // 同一检查点同时只允许一个循环写入
+    let lease = context.checkpoint_path.as_deref().and_then(|path| {
+        let lease = CheckpointLease::acquire(path);
+        if lease.is_none() {
+            tracing::warn!(path = %path.display(), "checkpoint in use by another run, not checkpointing");
+        }
+        lease
+    });
+    let checkpoint_path = lease.as_ref().and(context.checkpoint_path.clone());
+    let result = react_loop_steps(
+        planner, executor, recovery, context, user_input,
+        stream_tx, event_tx, cancel_token, critic, task_scheduler,
+        system_prompt_override, allowed_tools, limits, guardrails, checkpoint_path.as_deref(), resume,
+    ).await;
+    if let Some(ref path) = checkpoint_path {
+        if matches!(result, Ok(_) | Err(AgentError::Cancelled) | Err(AgentError::HallucinatedTool(_))) {
+            ReactCheckpoint::remove(path);
+        }
+    }
+    result
+}
 
-    // 显式用户偏好：若用户说「记住：xxx」，写入 preferences 并同步到长期记忆
-    if let Some(pref) = extract_remember_content(user_input) {
-        context.append_preference(&pref);
-        context.push_to_long_term(&format!("User preference: {}", pref));
+/// 取得恢复决策并累计该类错误的次数；策略要求退避时先等待（取消时提前返回）
+async fn recover(
+    recovery: &RecoveryEngine,
+    err: &AgentError,
+    context: &ContextManager,
+    attempts: &mut HashMap<ErrorClass, u32>,
+    cancel_token: &tokio_util::sync::CancellationToken,
+) -> RecoveryAction {
+    let mut hist = context.conversation.messages().to_vec();
+    let attempt = attempts.entry(ErrorClass::from(err)).or_insert(0);
+    let decision = recovery.decide(err, &mut hist, *attempt);
+    *attempt += 1;
+    if !decision.backoff.is_zero() {
+        tokio::select! {
+            _ = tokio::time::sleep(decision.backoff) => {}
+            _ = cancel_token.cancelled() => {}
+        }
     }
+    decision.action
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn react_loop_steps(
+    planner: &Planner,
+    executor: &ToolExecutor,
+    recovery: &RecoveryEngine,
+    context: &mut ContextManager,
+    user_input: &str,
+    stream_tx: Option<&broadcast::Sender<String>>,
+    event_tx: Option<&tokio::sync::mpsc::UnboundedSender<ReactEvent>>,
+    cancel_token: tokio_util::sync::CancellationToken,
+    critic: Option<&Critic>,
+    task_scheduler: Option<&TaskScheduler>,
+    system_prompt_override: Option<&str>,
+    allowed_tools: Option<&[String]>,
+    limits: ReactLimits,
+    guardrails: Option<&Guardrails>,
+    checkpoint: Option<&Path>,
+    resume: Option<ReactCheckpoint>,
+) -> Result<ReactResult, AgentError> {
+    // 总时限从本次运行开始计（恢复时重新计时）
+    let deadline = limits.max_duration.map(|d| Instant::now() + d);
+    let mut step = match resume {
+        Some(saved) => {
+            let notes = saved.interrupted_messages();
+            context.set_messages(saved.messages);
+            context.working = saved.working;
+            for note in notes {
+                context.push_message(note);
+            }
+            send_event(&event_tx, ReactEvent::Recovery {
+                action: "Resumed".to_string(),
+                detail: format!("Resumed from checkpoint at step {} ({})", saved.step, saved.updated_at),
+            });
+            saved.step
+        }
+        None => {
+            // 会话中的第一条任务说明置顶，Context Compaction 时原样保留；之后的消息照常参与压缩
+            if context.messages().iter().any(|m| m.pinned) {
+                context.push_message(Message::user(user_input.to_string()));
+            } else {
+                context.push_pinned_message(Message::user(user_input.to_string()));
+            }
+            context.working.set_goal(user_input);
+
+            // 显式用户偏好：若用户说「记住：xxx」，写入 preferences 并同步到长期记忆
+            if let Some(pref) = extract_remember_content(user_input) {
+                context.append_preference(&pref);
+                context.push_to_long_term(&format!("User preference: {}", pref));
+            }
+            0
+        }
+    };
 
     // 记录初始 token 数，用于计算本次增量
     let (init_prompt, init_completion, _) = planner.token_usage();
 
-    let mut step = 0;
     let mut last_llm_output = String::new();
+    // 本次运行中各类错误已恢复的次数（[recovery] 策略的重试上限）
+    let mut recovery_attempts: HashMap<ErrorClass, u32> = HashMap::new();
+    // 最终回复评审：仅允许一次自动修订，记录 (初稿, 评审意见)
+    let mut answer_revision: Option<(String, String)> = None;
 
     loop {
-        send_event(&event_tx, ReactEvent::StepUpdate { step, max_steps: MAX_REACT_STEPS });
+        send_event(&event_tx, ReactEvent::StepUpdate { step, max_steps: limits.max_steps });
 
         if cancel_token.is_cancelled() {
             send_event(&event_tx, ReactEvent::Error { text: "Cancelled by user".to_string() });
             return Err(AgentError::Cancelled);
         }
 
-        if step >= MAX_REACT_STEPS {
-            return Ok(ReactResult {
-                response: format!(
-                    "达到最大步数限制 ({})，最后输出：\n{}",
-                    MAX_REACT_STEPS, last_llm_output
-                ),
-                messages: context.messages().to_vec(),
-            });
+        if step >= limits.max_steps {
+            let reason = format!("达到最大步数限制 ({})", limits.max_steps);
+            return Ok(finish_partial(context, user_input, &reason, &last_llm_output, &event_tx));
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Ok(deadline_result(context, user_input, &limits, &last_llm_output, &event_tx));
         }
 
+        save_checkpoint(checkpoint, context, user_input, step, None, None);
+
         // 若当前对话条数过多，先压缩：摘要写入长期记忆并替换为一条摘要消息
-        if context.messages().len() > COMPACT_THRESHOLD {
-            if let Err(e) = compact_context(planner, context).await {
-                send_event(&event_tx, ReactEvent::Error {
-                    text: format!("Compaction failed: {}", e),
-                });
-                // 不中止，继续用当前消息规划
+        if context.messages().len() > limits.compact_threshold {
+            match compact_context_with_critic(planner, critic, context).await {
+                Ok(CompactionOutcome::Rejected(reason)) => {
+                    send_event(&event_tx, ReactEvent::Recovery {
+                        action: "CompactionRejected".to_string(),
+                        detail: reason,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    send_event(&event_tx, ReactEvent::Error {
+                        text: format!("Compaction failed: {}", e),
+                    });
+                    // 不中止，继续用当前消息规划
+                }
             }
         }
 
-        let messages = context.to_llm_messages();
+        let mut messages = context.to_llm_messages();
+        push_revision_request(&mut messages, answer_revision.as_ref());
         let working_section = context.working_memory_section();
         let long_term_block = context.long_term_section(user_input);
         if !long_term_block.is_empty() {
@@ -280,26 +774,66 @@ async fn react_loop_impl(
             };
             send_event(&event_tx, ReactEvent::MemoryRecovery { preview });
         }
-        // 动态 system：基础 prompt（或 override）+ Working Memory + 长期记忆检索 + 行为约束/教训 + 程序记忆 + 用户偏好（自我进化）
+        // 动态 system：基础 prompt（或 override）+ Working Memory + 长期记忆检索 + 知识图谱 + 相似经历 + 行为约束/教训 + 程序记忆 + 用户偏好（自我进化）
         let lessons_block = context.lessons_section();
         let procedural_block = context.procedural_section();
         let preferences_block = context.preferences_section();
+        let episodes_block = context.episodes_section(user_input);
+        let graph_block = context.graph_section(user_input);
         let base_prompt = system_prompt_override.unwrap_or_else(|| planner.base_system_prompt());
-        let system = format!(
-            "{}\n\n{}\n\n{}{}{}{}",
-            base_prompt,
-            working_section,
-            long_term_block,
-            lessons_block,
-            procedural_block,
-            preferences_block
-        );
+        let sections = vec![
+            (MemorySegment::SystemPrompt, base_prompt.to_string()),
+            (MemorySegment::WorkingMemory, working_section),
+            (MemorySegment::LongTerm, long_term_block),
+            (MemorySegment::KnowledgeGraph, graph_block),
+            (MemorySegment::Episodes, episodes_block),
+            (MemorySegment::Lessons, lessons_block),
+            (MemorySegment::Procedural, procedural_block),
+            (MemorySegment::Preferences, preferences_block),
+        ];
+        let system = assemble_system_prompt(sections, planner.context_budget(), estimate_messages_tokens(&messages));
+        // 上下文溢出预检：估算超出模型窗口时先压缩，仍超出则截断本次请求的消息，避免服务端 400
+        let budget = planner.context_budget();
+        if budget > 0 {
+            let system_tokens = estimate_tokens(&system);
+            let estimated = system_tokens + estimate_messages_tokens(&messages);
+            if estimated > budget {
+                send_event(&event_tx, ReactEvent::Recovery {
+                    action: "ContextOverflow".to_string(),
+                    detail: format!("~{} tokens exceeds budget {}, compacting", estimated, budget),
+                });
+                if let Err(e) = compact_context_with_critic(planner, critic, context).await {
+                    tracing::warn!("pre-request compaction failed: {}", e);
+                }
+                messages = context.to_llm_messages();
+                push_revision_request(&mut messages, answer_revision.as_ref());
+                let dropped = truncate_messages_to_budget(
+                    &mut messages,
+                    budget.saturating_sub(system_tokens),
+                );
+                if dropped > 0 {
+                    tracing::warn!(dropped, "context still over budget after compaction, truncated");
+                }
+            }
+        }
+
         send_event(&event_tx, ReactEvent::Thinking);
-        let output = match planner.plan_with_system(&messages, &system).await {
+        // LLM 并发限制：按优先级与助手配额排队，排队时间计入时限
+        let plan = async {
+            let _permit = match task_scheduler {
+                Some(sched) => Some(sched.acquire_llm().await),
+                None => None,
+            };
+            planner.plan_with_system(&messages, &system).await
+        };
+        let planned = match within_deadline(deadline, plan).await {
+            Some(planned) => planned,
+            None => return Ok(deadline_result(context, user_input, &limits, &last_llm_output, &event_tx)),
+        };
+        let output = match planned {
             Ok(o) => o,
             Err(e) => {
-                let mut hist = context.conversation.messages().to_vec();
-                let action = recovery.handle(&e, &mut hist);
+                let action = recover(recovery, &e, context, &mut recovery_attempts, &cancel_token).await;
                 match action {
                     RecoveryAction::RetryWithPrompt(prompt) => {
                         send_event(&event_tx, ReactEvent::Recovery {
@@ -315,6 +849,18 @@ async fn react_loop_impl(
                             action: "AskUser".to_string(),
                             detail: msg.clone(),
                         });
+                        save_checkpoint(checkpoint, context, user_input, step, None, Some(&msg));
+                        if let Some(answer) =
+                            ask_user(&msg, limits.ask_user_timeout, &event_tx, &cancel_token).await
+                        {
+                            context.push_message(Message::assistant(msg));
+                            context.push_message(Message::user(format!("User answer: {}", answer)));
+                            step += 1;
+                            continue;
+                        }
+                        if cancel_token.is_cancelled() {
+                            return Err(AgentError::Cancelled);
+                        }
                         send_event(&event_tx, ReactEvent::Error { text: e.to_string() });
                         return Err(e);
                     }
@@ -323,7 +869,7 @@ async fn react_loop_impl(
                             action: "SummarizeAndPrune".to_string(),
                             detail: "Compacting context and retrying".to_string(),
                         });
-                        if let Err(compact_err) = compact_context(planner, context).await {
+                        if let Err(compact_err) = compact_context_with_critic(planner, critic, context).await {
                             send_event(&event_tx, ReactEvent::Error {
                                 text: format!("Compaction failed: {}", compact_err),
                             });
@@ -353,6 +899,44 @@ async fn react_loop_impl(
             }
         };
 
+        // 护栏：最终回复在展示前、工具参数在执行前过滤（思考预览与流式输出使用过滤后的文本）
+        let mut parsed = parse_llm_output(&output);
+        let mut output = output;
+        let mut output_blocked = false;
+        let mut args_blocked: Option<String> = None;
+        match (guardrails, parsed.as_mut()) {
+            (Some(g), Ok(crate::react::planner::PlannerOutput::Response(resp)))
+                if g.applies_to(GuardrailStage::Output) =>
+            {
+                match g.check(GuardrailStage::Output, resp).await {
+                    GuardrailVerdict::Allow(text) => *resp = text,
+                    GuardrailVerdict::Block { rule } => {
+                        send_event(&event_tx, ReactEvent::Recovery {
+                            action: "Guardrail".to_string(),
+                            detail: format!("response blocked by rule {}", rule),
+                        });
+                        *resp = g.blocked_message().to_string();
+                        output_blocked = true;
+                    }
+                }
+                output = resp.clone();
+            }
+            (Some(g), Ok(crate::react::planner::PlannerOutput::ToolCall(tc)))
+                if g.applies_to(GuardrailStage::ToolArgs) =>
+            {
+                match g.check_args(&tc.tool, &tc.args).await {
+                    GuardrailVerdict::Allow(args) => {
+                        if args != tc.args {
+                            tc.args = args;
+                            output = serde_json::json!({"tool": tc.tool, "args": tc.args}).to_string();
+                        }
+                    }
+                    GuardrailVerdict::Block { rule } => args_blocked = Some(rule),
+                }
+            }
+            _ => {}
+        }
+
         last_llm_output = output.clone();
 
         let thinking_preview: String = output.chars().take(THINKING_PREVIEW_CHARS).collect();
@@ -367,14 +951,36 @@ async fn react_loop_impl(
             let _ = tx.send(output.clone());
         }
 
-        match parse_llm_output(&output) {
+        match parsed {
             Ok(crate::react::planner::PlannerOutput::Response(resp)) => {
+                if answer_revision.is_none() && !output_blocked {
+                    if let Some(c) = critic.filter(|c| c.reviews_final_answer()) {
+                        if let Ok(CriticResult::Correction(critique)) =
+                            c.evaluate_response(user_input, &resp).await
+                        {
+                            send_event(&event_tx, ReactEvent::Recovery {
+                                action: "CriticRevision".to_string(),
+                                detail: critique.clone(),
+                            });
+                            answer_revision = Some((resp, critique));
+                            step += 1;
+                            continue;
+                        }
+                    }
+                }
                 let chars: Vec<char> = resp.chars().collect();
                 for chunk in chars.chunks(CHUNK_CHARS) {
                     send_event(&event_tx, ReactEvent::MessageChunk {
                         text: chunk.iter().collect(),
                     });
                 }
+                if let Some((draft, critique)) = answer_revision.take() {
+                    send_event(&event_tx, ReactEvent::AnswerRevision {
+                        draft,
+                        critique,
+                        revised: resp.clone(),
+                    });
+                }
                 send_event(&event_tx, ReactEvent::MessageDone);
                 context.push_message(Message::assistant(resp.clone()));
                 let cons_preview: String = resp.chars().take(MEMORY_PREVIEW_CHARS).collect();
@@ -397,9 +1003,31 @@ async fn react_loop_impl(
                     cumulative_total: cur_total,
                 });
 
-                // 策略沉淀：将本轮目标与使用的工具写入长期记忆，供后续检索（EVOLUTION §3.5）
+                // 追问建议：仅在有事件消费方且上下文开启时生成，失败不影响回复
+                if context.suggestions && event_tx.is_some() {
+                    match planner.suggest_followups(user_input, &resp).await {
+                        Ok(items) if !items.is_empty() => {
+                            send_event(&event_tx, ReactEvent::Suggestions { items });
+                        }
+                        Ok(_) => {}
+                        Err(e) => tracing::warn!("follow-up suggestions failed: {}", e),
+                    }
+                }
+
+                // 情景沉淀：记录本轮目标、使用的工具与结果，供后续检索相似经历（EVOLUTION §3.5）
                 let tools_used = context.working.tool_names_used();
-                context.push_session_strategy_to_long_term(user_input, &tools_used);
+                context.record_episode(user_input, &tools_used, &resp, true);
+
+                // 知识图谱记忆：抽取本轮实体与关系（失败仅记录日志，不影响回复）
+                if let Some(graph) = context.graph.clone() {
+                    let turn = format!("User: {}\nAssistant: {}", user_input, resp);
+                    match planner.extract_knowledge(&turn).await {
+                        Ok(extracted) => {
+                            graph.merge(extracted);
+                        }
+                        Err(e) => tracing::warn!("knowledge graph extraction failed: {}", e),
+                    }
+                }
 
                 return Ok(ReactResult {
                     response: resp,
@@ -421,6 +1049,14 @@ async fn react_loop_impl(
                     valid_names.iter().any(|n| n == &tc.tool)
                 };
                 if !is_allowed {
+                    // 真实存在但未授权的工具记入用量，供技能建议判断是否应启用
+                    if executor.get_tool(&tc.tool).is_some() {
+                        if let Some(assistant_id) = CURRENT_ASSISTANT_ID.try_with(|a| a.clone()).ok().flatten() {
+                            crate::observability::Metrics::global()
+                                .tools
+                                .record_blocked(&assistant_id, &tc.tool);
+                        }
+                    }
                     let ref_names: Vec<String> = if valid_names.is_empty() {
                         executor.tool_names()
                     } else {
@@ -432,11 +1068,45 @@ async fn react_loop_impl(
                 }
                 // 工具并发限制：从 TaskScheduler 获取许可后再执行
                 let _permit = if let Some(sched) = task_scheduler {
-                    Some(sched.acquire_tool().await)
+                    Some(sched.acquire_tool(&tc.tool).await)
                 } else {
                     None
                 };
-                let result = executor.execute(&tc.tool, tc.args).await;
+                save_checkpoint(
+                    checkpoint,
+                    context,
+                    user_input,
+                    step,
+                    Some(PendingToolCall {
+                        tool: tc.tool.clone(),
+                        args: tc.args.clone(),
+                    }),
+                    None,
+                );
+                // Critic 校验器：执行前记录状态（如目标文件摘要），执行后对比
+                let validation = critic.and_then(|c| c.snapshot(&tc.tool, &tc.args));
+                let arguments = tc.args.to_string();
+                let handoff = (tc.tool == HANDOFF_TOOL).then(|| HandoffRequest::from_args(&tc.args));
+                let call = async {
+                    if let Some(rule) = args_blocked {
+                        return Err(AgentError::ToolFailed(ToolError::PermissionDenied(format!(
+                            "arguments for tool {} were blocked by guardrail rule {}; do not retry with the same content",
+                            tc.tool, rule
+                        ))));
+                    }
+                    match authorize_tool_call(executor, &tc.tool, &tc.args, &event_tx).await {
+                        Ok(()) => {
+                            let allowed = allowed_tools.map(<[String]>::to_vec);
+                            CURRENT_ALLOWED_TOOLS.scope(allowed, executor.execute(&tc.tool, tc.args)).await
+                        }
+                        Err(denied) => Err(AgentError::ToolFailed(denied)),
+                    }
+                };
+                let Some(result) = within_deadline(deadline, call).await else {
+                    context.working.add_failure(format!("{}: stopped at the time limit", tc.tool));
+                    return Ok(deadline_result(context, user_input, &limits, &last_llm_output, &event_tx));
+                };
+                let succeeded = result.is_ok();
                 let observation = match result {
                     Ok(r) => {
                         if context.record_tool_success {
@@ -452,9 +1122,55 @@ async fn react_loop_impl(
                             tool: tc.tool.clone(),
                             reason: e.to_string(),
                         });
-                        format!("Error: {}", e)
+                        let mut observation = format!("Error: {}", e);
+                        // 结构化工具错误：按类别附加修复提示；参数错误时附上参数 schema 供 LLM 修正 args
+                        if let AgentError::ToolFailed(ref tool_err) = e {
+                            let mut hist = context.conversation.messages().to_vec();
+                            if let RecoveryAction::RetryWithPrompt(hint) = recovery.handle(&e, &mut hist) {
+                                observation.push('\n');
+                                observation.push_str(&hint);
+                            }
+                            if matches!(tool_err, ToolError::InvalidArgs(_)) {
+                                if let Some(tool) = executor.get_tool(&tc.tool) {
+                                    observation.push_str(&format!(
+                                        "\nExpected parameters: {}",
+                                        tool.parameters_schema()
+                                    ));
+                                }
+                            }
+                        }
+                        observation
                     }
                 };
+                // 超长输出：完整内容存为 artifact，对话中只保留摘要
+                let observation = match condense_observation(
+                    planner,
+                    user_input,
+                    &tc.tool,
+                    &observation,
+                    context.observation_summary_tokens,
+                    context.artifacts_dir.as_deref(),
+                )
+                .await
+                {
+                    Some(condensed) => {
+                        send_event(&event_tx, ReactEvent::Recovery {
+                            action: "ObservationSummarized".to_string(),
+                            detail: format!(
+                                "{}: ~{} tokens{}",
+                                tc.tool,
+                                condensed.original_tokens,
+                                condensed
+                                    .artifact
+                                    .as_ref()
+                                    .map(|p| format!(" -> {}", p.display()))
+                                    .unwrap_or_default()
+                            ),
+                        });
+                        condensed.text
+                    }
+                    None => observation,
+                };
                 let preview: String = observation.chars().take(OBSERVATION_PREVIEW_CHARS).collect();
                 if observation.len() > OBSERVATION_PREVIEW_CHARS {
                     send_event(&event_tx, ReactEvent::Observation {
@@ -476,9 +1192,15 @@ async fn react_loop_impl(
                     || obs_upper.contains("TIMEOUT");
                 if !is_tool_failure {
                     if let Some(c) = critic {
-                        if let Ok(CriticResult::Correction(suggestion)) =
-                            c.evaluate(user_input, &tc.tool, &observation).await
-                        {
+                        // 校验器未通过时不再调用 LLM 评估
+                        let correction = match validation.as_ref().and_then(|v| c.validate(v, &observation)) {
+                            Some(problem) => Some(problem),
+                            None => match c.evaluate(user_input, &tc.tool, &observation).await {
+                                Ok(CriticResult::Correction(suggestion)) => Some(suggestion),
+                                _ => None,
+                            },
+                        };
+                        if let Some(suggestion) = correction {
                             send_event(&event_tx, ReactEvent::Recovery {
                                 action: "Critic".to_string(),
                                 detail: suggestion.clone(),
@@ -491,20 +1213,17 @@ async fn react_loop_impl(
                         }
                     }
                 }
-                // 将工具调用与结果写回对话，供下一轮 Plan 使用
-                context.push_message(Message::assistant(format!(
-                    "Tool call: {} | Result: {}",
-                    tc.tool, observation
-                )));
-                context.push_message(Message::user(format!(
-                    "Observation from {}: {}",
-                    tc.tool, observation
-                )));
+                // 将工具调用与结果（同一 tool_call_id 的 Assistant / Tool 消息）写回对话，供下一轮 Plan 使用
+                let call_id = format!("call_{}", uuid::Uuid::new_v4().simple());
+                context.push_message(Message::tool_call(&call_id, &tc.tool, arguments));
+                context.push_message(Message::tool_result(&call_id, &tc.tool, observation));
+                if let Some(request) = handoff.filter(|_| succeeded) {
+                    return Ok(finish_handoff(planner, context, user_input, request, &event_tx).await);
+                }
             }
             Err(e) => {
                 // 解析失败（如 JSON 错误），交给 Recovery 决定是否 RetryWithPrompt
-                let mut hist = context.conversation.messages().to_vec();
-                let action = recovery.handle(&e, &mut hist);
+                let action = recover(recovery, &e, context, &mut recovery_attempts, &cancel_token).await;
                 match action {
                     RecoveryAction::RetryWithPrompt(prompt) => {
                         send_event(&event_tx, ReactEvent::Recovery {
@@ -528,3 +1247,164 @@ async fn react_loop_impl(
         step += 1;
     }
 }
+
+/// 最终回复的修订请求：初稿与评审意见只放进发给 LLM 的消息，不写入会话上下文，
+/// 否则它们会随会话历史保存，被当作用户说过的话展示与重放
+fn push_revision_request(messages: &mut Vec<Message>, revision: Option<&(String, String)>) {
+    if let Some((draft, critique)) = revision {
+        messages.push(Message::assistant(draft.clone()));
+        messages.push(Message::user(format!(
+            "Critic 认为上面的回答需要修订：{}\n请直接给出修订后的最终回答。",
+            critique
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::react::replay::RecordedToolCall;
+    use crate::react::ReplayFixture;
+
+    #[tokio::test]
+    async fn test_limits_return_partial_result() {
+        let limits = ReactLimits::from(&ReactSection {
+            max_steps: 5,
+            max_duration_secs: 0,
+            ..Default::default()
+        });
+        assert_eq!(limits.max_duration, None);
+        let limits = limits.with_overrides(Some(1), Some(30)).with_overrides(None, None);
+        assert_eq!((limits.max_steps, limits.max_duration), (1, Some(Duration::from_secs(30))));
+        assert_eq!(limits.with_overrides(Some(0), Some(0)).max_steps, 1);
+        assert_eq!(limits.with_overrides(None, Some(0)).max_duration, None);
+
+        // 步数用尽：返回已完成的步骤，作为回复写入对话
+        let fixture = ReplayFixture {
+            llm_responses: vec![r#"{"tool": "cat", "args": {"path": "notes.md"}}"#.into()],
+            tool_calls: vec![RecordedToolCall {
+                tool: "cat".into(),
+                args: json!({"path": "notes.md"}),
+                output: Ok("two todo items".into()),
+            }],
+        };
+        let planner = Planner::new(std::sync::Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        )
+        .with_limits(limits);
+        let mut context = ContextManager::new(10);
+        let result = react_loop_v2(&session, &mut context, "Summarize my notes").await.unwrap();
+        assert!(result.response.starts_with("达到最大步数限制 (1)"));
+        assert!(result.response.contains("- cat -> two todo items"));
+        assert_eq!(result.messages.last().unwrap().content, result.response);
+
+        // 时限已到：不再调用模型，直接返回部分结果
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        )
+        .with_limits(ReactLimits {
+            max_duration: Some(Duration::ZERO),
+            ..ReactLimits::default()
+        });
+        let result = react_loop_v2(&session, &mut context, "And the calendar?").await.unwrap();
+        assert!(result.response.starts_with("达到时间上限 (0 秒)"));
+    }
+
+    #[tokio::test]
+    async fn test_compaction_kept_when_critic_fails() {
+        let planner_llm = ReplayFixture {
+            llm_responses: vec!["summary of the early turns".into()],
+            tool_calls: vec![],
+        };
+        let planner = Planner::new(std::sync::Arc::new(planner_llm.llm_client()), "test".to_string());
+        // 没有录制回复：校验调用返回错误
+        let critic_llm = ReplayFixture {
+            llm_responses: vec![],
+            tool_calls: vec![],
+        };
+        let critic = Critic::new(std::sync::Arc::new(critic_llm.llm_client()), "check");
+        let mut context = ContextManager::new(20);
+        context.compaction.validate_with_critic = true;
+        for i in 0..6 {
+            context.push_message(Message::user(format!("question {}", i)));
+            context.push_message(Message::assistant(format!("answer {}", i)));
+        }
+        let before = context.messages().to_vec();
+
+        let outcome = compact_context_with_critic(&planner, Some(&critic), &mut context).await.unwrap();
+        match outcome {
+            CompactionOutcome::Rejected(reason) => assert!(reason.starts_with("critic check failed"), "{}", reason),
+            other => panic!("expected rejection, got {:?}", other),
+        }
+        // 未经审查的摘要不替换原消息
+        assert_eq!(context.messages().len(), before.len());
+        assert!(context.messages().iter().all(|m| m.role != Role::System));
+    }
+
+    #[tokio::test]
+    async fn test_critic_revision_stays_out_of_context() {
+        let fixture = ReplayFixture {
+            llm_responses: vec!["Paris is in Italy.".into(), "Paris is in France.".into()],
+            tool_calls: vec![],
+        };
+        let critic_llm = ReplayFixture {
+            llm_responses: vec!["Wrong country.".into()],
+            tool_calls: vec![],
+        };
+        let planner = Planner::new(std::sync::Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let critic = Critic::new(std::sync::Arc::new(critic_llm.llm_client()), "").with_final_answer_review(true);
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        )
+        .with_critic(&critic);
+        let mut context = ContextManager::new(10);
+        let result = react_loop_v2(&session, &mut context, "Where is Paris?").await.unwrap();
+        assert_eq!(result.response, "Paris is in France.");
+        // 初稿与评审意见不进入会话：只有用户问题与修订后的回答
+        let contents: Vec<&str> = context.messages().iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Where is Paris?", "Paris is in France."]);
+    }
+
+    #[tokio::test]
+    async fn test_first_instruction_is_pinned() {
+        let fixture = ReplayFixture {
+            llm_responses: vec!["Bonjour.".into(), "Merci.".into()],
+            tool_calls: vec![],
+        };
+        let planner = Planner::new(std::sync::Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        );
+        let mut context = ContextManager::new(10);
+        react_loop_v2(&session, &mut context, "Task: answer in French").await.unwrap();
+        react_loop_v2(&session, &mut context, "Thanks").await.unwrap();
+        let pinned: Vec<&str> = context
+            .messages()
+            .iter()
+            .filter(|m| m.pinned)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(pinned, vec!["Task: answer in French"]);
+    }
+}
@@ -6,10 +6,112 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
+use crate::config::CompactionSection;
 use crate::memory::{
-    append_lesson, append_preference, append_procedural, load_lessons, load_preferences,
-    load_procedural, ConversationMemory, LongTermMemory, Message, WorkingMemory,
+    append_lesson, append_preference, append_procedural, forget_in_daily_logs, forget_list_lines,
+    list_line_text, load_lessons, load_preferences, load_procedural, long_term_path,
+    remove_list_line, tokenizer, ConversationMemory, FileLongTerm, Episode, EpisodicMemory, GraphMemory, LongTermMemory,
+    MemoryScope, Message, SqlitePersistence, WorkingMemory,
 };
+use crate::react::HandoffRequest;
+
+/// 记忆条目来源（记忆检索 / 审计 API 使用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemorySource {
+    LongTerm,
+    Lesson,
+    Preference,
+}
+
+/// 记忆检索命中：来源 + 原文 + 得分（越大越相关）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryHit {
+    pub source: MemorySource,
+    pub text: String,
+    pub score: f32,
+}
+
+/// forget 结果：各存储中命中（dry_run）或已删除的条目
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ForgetReport {
+    pub dry_run: bool,
+    pub long_term: Vec<String>,
+    pub daily_logs: Vec<String>,
+    pub lessons: Vec<String>,
+    pub preferences: Vec<String>,
+    pub messages: Vec<String>,
+    /// 情景记忆（episodes.jsonl）中命中的情景目标
+    pub episodes: Vec<String>,
+    /// 知识图谱（graph.json）中命中的实体与关系
+    pub graph: Vec<String>,
+}
+
+impl ForgetReport {
+    pub fn total(&self) -> usize {
+        self.long_term.len()
+            + self.daily_logs.len()
+            + self.lessons.len()
+            + self.preferences.len()
+            + self.messages.len()
+            + self.episodes.len()
+            + self.graph.len()
+    }
+}
+
+/// 对 lessons / preferences 文件逐行打分；query 为空时列出全部（得分 0）
+fn score_list_lines(content: &str, query: &str, source: MemorySource) -> Vec<MemoryHit> {
+    let query_tokens = tokenizer::tokenize_to_set(query);
+    content
+        .lines()
+        .map(list_line_text)
+        .filter(|t| !t.is_empty() && !t.starts_with('#'))
+        .filter_map(|t| {
+            let score = if query_tokens.is_empty() {
+                0.0
+            } else {
+                tokenizer::jaccard_similarity(&query_tokens, &tokenizer::tokenize_to_set(t))
+            };
+            (query_tokens.is_empty() || score > 0.0).then(|| MemoryHit {
+                source,
+                text: t.to_string(),
+                score,
+            })
+        })
+        .collect()
+}
+
+/// Context Compaction 策略：保留最近若干轮原文，替换前是否需 Critic 校验摘要
+#[derive(Debug, Clone)]
+pub struct CompactionPolicy {
+    /// 原样保留的最近对话轮数（每轮约 2 条消息）
+    pub keep_recent_turns: usize,
+    /// 是否在替换前由 Critic 校验摘要
+    pub validate_with_critic: bool,
+    /// 重要性评分达到该值的较早消息原样保留（0 表示关闭）
+    pub keep_importance: u32,
+    /// 压缩前是否合并重复的工具观察
+    pub dedup_observations: bool,
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        CompactionSection::default().into()
+    }
+}
+
+impl From<CompactionSection> for CompactionPolicy {
+    fn from(section: CompactionSection) -> Self {
+        Self {
+            keep_recent_turns: section.keep_recent_turns,
+            validate_with_critic: section.validate_with_critic,
+            keep_importance: section.keep_importance,
+            dedup_observations: section.dedup_observations,
+        }
+    }
+}
 
 /// 上下文管理器：整合短期/中期/长期记忆，提供 to_llm_messages、working_memory_section、long_term_section、lessons_section、procedural_section、preferences_section
 #[derive(Clone)]
@@ -27,6 +129,28 @@ pub struct ContextManager {
     pub auto_lesson_on_hallucination: bool,
     /// 是否将工具调用成功也写入 procedural.md（EVOLUTION §3.5 工具统计）
     pub record_tool_success: bool,
+    /// Context Compaction 策略（由 config [memory.compaction] 控制）
+    pub compaction: CompactionPolicy,
+    /// 记忆作用域（user / assistant / namespace），决定长期记忆目录与向量 namespace
+    pub scope: MemoryScope,
+    /// 情景记忆（memory/episodes.jsonl）：每次完成的会话一条摘要，检索相似经历注入 prompt
+    pub episodic: Option<Arc<EpisodicMemory>>,
+    /// 知识图谱记忆（memory/graph.json）：每轮结束抽取实体关系，检索时多跳遍历
+    pub graph: Option<Arc<GraphMemory>>,
+    /// 该作用域的记忆根目录（daily logs / long-term.md 所在），forget 时使用
+    pub memory_root: Option<PathBuf>,
+    /// SQLite 会话持久化数据库（.bee/conversations.db），forget 时一并清理
+    pub persistence_db: Option<PathBuf>,
+    /// 回复结束后是否生成追问建议（ReactEvent::Suggestions），需额外一次 LLM 调用
+    pub suggestions: bool,
+    /// 工具输出估算超过此 tokens 时先摘要再写入对话，0 表示不摘要（由 config [tools.observation_summary] 控制）
+    pub observation_summary_tokens: usize,
+    /// 被摘要的完整工具输出保存目录（workspace/artifacts），None 时不保存
+    pub artifacts_dir: Option<PathBuf>,
+    /// ReAct 检查点文件（每步写入，任务结束删除），None 时不写检查点、不可恢复
+    pub checkpoint_path: Option<PathBuf>,
+    /// 本轮调用 handoff 工具留下的交接请求，由接入端取走并转交给目标助手（不持久化）
+    pub pending_handoff: Option<HandoffRequest>,
 }
 
 impl ContextManager {
@@ -40,6 +164,17 @@ impl ContextManager {
             preferences_path: None,
             auto_lesson_on_hallucination: true,
             record_tool_success: false,
+            compaction: CompactionPolicy::default(),
+            scope: MemoryScope::default(),
+            episodic: None,
+            graph: None,
+            memory_root: None,
+            persistence_db: None,
+            suggestions: false,
+            observation_summary_tokens: 0,
+            artifacts_dir: None,
+            checkpoint_path: None,
+            pending_handoff: None,
         }
     }
 
@@ -121,6 +256,18 @@ impl ContextManager {
         let _ = append_lesson(p, &line);
     }
 
+    /// 会话被看门狗取消时追加一条教训，提醒后续拆分耗时操作
+    pub fn append_stall_lesson(&self, activity: &str, idle_secs: u64) {
+        let Some(ref p) = self.lessons_path else {
+            return;
+        };
+        let line = format!(
+            "会话曾在「{}」后 {} 秒无进展被取消：耗时操作应缩小范围、分步执行或转为后台任务。",
+            activity, idle_secs
+        );
+        let _ = append_lesson(p, &line);
+    }
+
     /// 当发生 HallucinatedTool 时追加一条教训到 lessons.md，减少后续幻觉（受 auto_lesson_on_hallucination 控制）
     pub fn append_hallucination_lesson(&self, hallucinated_tool: &str, valid_tools: &[String]) {
         if !self.auto_lesson_on_hallucination {
@@ -149,6 +296,108 @@ impl ContextManager {
         self
     }
 
+    /// 设置 Context Compaction 策略（与 config [memory.compaction] 一致）
+    pub fn with_compaction_policy(mut self, policy: CompactionPolicy) -> Self {
+        self.compaction = policy;
+        self
+    }
+
+    /// 设置记忆作用域（仅记录；长期记忆与文件路径由 agent::create_context_for_scope 按作用域创建）
+    pub fn with_scope(mut self, scope: MemoryScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// 设置情景记忆存储
+    /// 设置记忆根目录（forget 时清理其中的 logs/ 与 long-term.md）
+    pub fn with_memory_root(mut self, root: PathBuf) -> Self {
+        self.memory_root = Some(root);
+        self
+    }
+
+    /// 设置 SQLite 会话持久化数据库路径（forget 时删除匹配消息）
+    pub fn with_persistence_db(mut self, path: PathBuf) -> Self {
+        self.persistence_db = Some(path);
+        self
+    }
+
+    /// 设置是否在回复后生成追问建议
+    pub fn with_suggestions(mut self, enabled: bool) -> Self {
+        self.suggestions = enabled;
+        self
+    }
+
+    /// 设置超长工具输出摘要阈值（tokens，0 关闭）
+    pub fn with_observation_summary(mut self, threshold_tokens: usize) -> Self {
+        self.observation_summary_tokens = threshold_tokens;
+        self
+    }
+
+    /// 设置完整工具输出（artifact）保存目录
+    pub fn with_artifacts_dir(mut self, dir: PathBuf) -> Self {
+        self.artifacts_dir = Some(dir);
+        self
+    }
+
+    pub fn with_checkpoint_path(mut self, path: PathBuf) -> Self {
+        self.checkpoint_path = Some(path);
+        self
+    }
+
+    /// 取走本轮的交接请求（接入端在循环结束后调用）
+    pub fn take_handoff(&mut self) -> Option<HandoffRequest> {
+        self.pending_handoff.take()
+    }
+
+    pub fn with_episodic(mut self, episodic: Arc<EpisodicMemory>) -> Self {
+        self.episodic = Some(episodic);
+        self
+    }
+
+    /// 相似过往经历段落（按目标相似度检索情景记忆）
+    pub fn episodes_section(&self, query: &str) -> String {
+        let Some(ref ep) = self.episodic else {
+            return String::new();
+        };
+        let hits = ep.similar(query, 3);
+        if hits.is_empty() {
+            return String::new();
+        }
+        let lines: Vec<String> = hits.iter().map(|e| e.to_prompt_line()).collect();
+        format!("\n## Similar Past Episodes（可参考其做法与结果）\n{}\n", lines.join("\n"))
+    }
+
+    /// 记录本轮会话情景；未配置情景记忆时退化为将策略写入长期记忆
+    pub fn record_episode(&self, goal: &str, tool_names: &[String], outcome: &str, success: bool) {
+        match self.episodic {
+            Some(ref ep) => ep.record(Episode::new(goal, tool_names, outcome, success)),
+            None => self.push_session_strategy_to_long_term(goal, tool_names),
+        }
+    }
+
+    /// 设置知识图谱记忆
+    pub fn with_graph(mut self, graph: Arc<GraphMemory>) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// 知识图谱段落：从与问题相关的实体出发两跳内的关系事实
+    pub fn graph_section(&self, query: &str) -> String {
+        let Some(ref g) = self.graph else {
+            return String::new();
+        };
+        let facts = g.walk(query, 2, 10);
+        if facts.is_empty() {
+            return String::new();
+        }
+        format!("\n## Related Facts（知识图谱）\n- {}\n", facts.join("\n- "))
+    }
+
+    /// 追加一条置顶消息：剪枝与 Context Compaction 时始终原样保留
+    pub fn push_pinned_message(&mut self, msg: Message) {
+        self.conversation.push(msg.pinned());
+    }
+
     /// 将本轮会话策略（目标 + 使用的工具）写入长期记忆，供后续检索（EVOLUTION §3.5）
     pub fn push_session_strategy_to_long_term(&self, goal: &str, tool_names: &[String]) {
         if tool_names.is_empty() {
@@ -210,6 +459,142 @@ impl ContextManager {
             lt.add(text);
         }
     }
+
+    /// 检索 Agent「知道」的内容：长期记忆（带得分）、lessons 与 preferences，按得分降序，最多 k 条
+    pub fn search_memory(&self, query: &str, k: usize) -> Vec<MemoryHit> {
+        let mut hits: Vec<MemoryHit> = Vec::new();
+        if let Some(ref lt) = self.long_term {
+            if lt.enabled() {
+                hits.extend(lt.search_scored(query, k).into_iter().map(|(score, text)| MemoryHit {
+                    source: MemorySource::LongTerm,
+                    text,
+                    score,
+                }));
+            }
+        }
+        if let Some(ref p) = self.lessons_path {
+            hits.extend(score_list_lines(&load_lessons(p), query, MemorySource::Lesson));
+        }
+        if let Some(ref p) = self.preferences_path {
+            hits.extend(score_list_lines(&load_preferences(p), query, MemorySource::Preference));
+        }
+        hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        hits.truncate(k);
+        hits
+    }
+
+    /// 遗忘（GDPR 式删除）：从长期记忆（含向量快照 / long-term.md）、每日日志、lessons、preferences、
+    /// 情景记忆、知识图谱与本作用域的 SQLite 会话消息中删除包含 pattern 的条目；dry_run 时只列出将被删除的内容。
+    /// 先收集全部命中再逐个存储提交：文件经临时文件 + rename 原子替换，SQLite 在事务内删除。
+    /// 会话快照（sessions/*.json）不在记忆根目录内，由接入端另行清理。
+    pub fn forget(&self, pattern: &str, dry_run: bool) -> anyhow::Result<ForgetReport> {
+        let mut report = ForgetReport {
+            dry_run,
+            ..Default::default()
+        };
+        if pattern.trim().is_empty() {
+            return Ok(report);
+        }
+
+        let persistence = match self.persistence_db {
+            Some(ref db) if db.exists() => Some(SqlitePersistence::new(db)?),
+            _ => None,
+        };
+        let file_long_term = self
+            .memory_root
+            .as_ref()
+            .map(|root| long_term_path(root))
+            .filter(|p| p.exists())
+            .map(|p| FileLongTerm::new(p, usize::MAX));
+
+        // 1. 收集命中
+        if let Some(ref lt) = self.long_term {
+            report.long_term.extend(lt.find_matching(pattern));
+        }
+        if let Some(ref flt) = file_long_term {
+            report.long_term.extend(flt.find_matching(pattern));
+        }
+        report.long_term.sort();
+        report.long_term.dedup();
+        if let Some(ref root) = self.memory_root {
+            report.daily_logs = forget_in_daily_logs(root, pattern, true)?;
+        }
+        if let Some(ref p) = self.lessons_path {
+            report.lessons = forget_list_lines(p, pattern, true)?;
+        }
+        if let Some(ref p) = self.preferences_path {
+            report.preferences = forget_list_lines(p, pattern, true)?;
+        }
+        let scope = self.persistence_scope();
+        if let Some(ref db) = persistence {
+            report.messages = db.forget_messages(pattern, &scope, true)?;
+        }
+        if let Some(ref episodic) = self.episodic {
+            report.episodes = episodic.forget(pattern, true)?;
+        }
+        if let Some(ref graph) = self.graph {
+            report.graph = graph.forget(pattern, true)?;
+        }
+        if dry_run || report.total() == 0 {
+            return Ok(report);
+        }
+
+        // 2. 提交删除
+        if let Some(ref db) = persistence {
+            db.forget_messages(pattern, &scope, false)?;
+        }
+        if let Some(ref episodic) = self.episodic {
+            episodic.forget(pattern, false)?;
+        }
+        if let Some(ref graph) = self.graph {
+            graph.forget(pattern, false)?;
+        }
+        if let Some(ref root) = self.memory_root {
+            forget_in_daily_logs(root, pattern, false)?;
+        }
+        if let Some(ref p) = self.lessons_path {
+            forget_list_lines(p, pattern, false)?;
+        }
+        if let Some(ref p) = self.preferences_path {
+            forget_list_lines(p, pattern, false)?;
+        }
+        for text in &report.long_term {
+            if let Some(ref lt) = self.long_term {
+                lt.remove(text);
+            }
+            if let Some(ref flt) = file_long_term {
+                flt.remove(text);
+            }
+        }
+        Ok(report)
+    }
+
+    /// SQLite 会话所属的作用域键：全局作用域为空串，其余为 MemoryScope::vector_namespace
+    pub fn persistence_scope(&self) -> String {
+        if self.scope.is_global() {
+            String::new()
+        } else {
+            self.scope.vector_namespace()
+        }
+    }
+
+    /// 删除一条记忆（text 需与 search_memory 返回的原文一致），返回是否有条目被删除
+    pub fn remove_memory_item(&self, source: MemorySource, text: &str) -> bool {
+        let removed = match source {
+            MemorySource::LongTerm => self.long_term.as_ref().map(|lt| lt.remove(text)).unwrap_or(0),
+            MemorySource::Lesson => self
+                .lessons_path
+                .as_ref()
+                .and_then(|p| remove_list_line(p, text).ok())
+                .unwrap_or(0),
+            MemorySource::Preference => self
+                .preferences_path
+                .as_ref()
+                .and_then(|p| remove_list_line(p, text).ok())
+                .unwrap_or(0),
+        };
+        removed > 0
+    }
 }
 
 #[cfg(test)]
@@ -293,6 +678,112 @@ mod tests {
         assert!(!ctx.auto_lesson_on_hallucination);
     }
 
+    #[test]
+    fn test_context_manager_push_pinned_message() {
+        let mut ctx = ContextManager::new(10);
+        ctx.push_pinned_message(Message::user("Always reply in English"));
+        assert!(ctx.messages()[0].pinned);
+        assert_eq!(ctx.compaction.keep_recent_turns, 2);
+    }
+
+    #[test]
+    fn test_context_manager_episodes_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let ep = Arc::new(EpisodicMemory::new(dir.path().join("episodes.jsonl"), 10));
+        let ctx = ContextManager::new(10).with_episodic(ep);
+        assert!(ctx.episodes_section("deploy the site").is_empty());
+        ctx.record_episode("deploy the site", &["shell".to_string()], "Deployed", true);
+        let section = ctx.episodes_section("deploy the docs site");
+        assert!(section.contains("Similar Past Episodes"));
+        assert!(section.contains("tools: shell"));
+    }
+
+    #[test]
+    fn test_context_manager_search_and_remove_memory() {
+        use crate::memory::InMemoryLongTerm;
+        let dir = tempfile::tempdir().unwrap();
+        let lt = Arc::new(InMemoryLongTerm::default());
+        lt.add("The deploy target is the staging cluster");
+        let prefs = dir.path().join("preferences.md");
+        append_preference(&prefs, "Answer deploy questions in Chinese").unwrap();
+        append_preference(&prefs, "Use metric units").unwrap();
+        let ctx = ContextManager::new(10)
+            .with_long_term(lt)
+            .with_preferences_path(prefs.clone());
+
+        let hits = ctx.search_memory("deploy", 10);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().any(|h| h.source == MemorySource::LongTerm));
+        assert!(hits.iter().all(|h| h.score > 0.0));
+        assert_eq!(ctx.search_memory("", 10).len(), 2);
+
+        assert!(ctx.remove_memory_item(MemorySource::Preference, "Use metric units"));
+        assert!(!load_preferences(&prefs).contains("metric"));
+        assert!(ctx.remove_memory_item(
+            MemorySource::LongTerm,
+            "The deploy target is the staging cluster"
+        ));
+        assert!(ctx.search_memory("deploy", 10).iter().all(|h| h.source == MemorySource::Preference));
+    }
+
+    #[test]
+    fn test_context_manager_forget_dry_run_then_purge() {
+        use crate::memory::{append_daily_log, InMemoryLongTerm};
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let lt = Arc::new(InMemoryLongTerm::default());
+        lt.add("Alice's phone number is 555-0100");
+        lt.add("Project deadline is Friday");
+        let prefs = root.join("preferences.md");
+        append_preference(&prefs, "Call alice on weekends").unwrap();
+        append_daily_log(
+            &root,
+            "2026-01-02",
+            "s1",
+            &[Message::user("my friend Alice lives in Paris"), Message::assistant("Noted")],
+        )
+        .unwrap();
+        let db = root.join("conversations.db");
+        let persistence = SqlitePersistence::new(&db).unwrap();
+        persistence.create_session("s1", None).unwrap();
+        persistence.save_message("s1", &Message::user("email alice@example.com")).unwrap();
+        // 其它作用域的会话不受影响
+        persistence.create_session_in_scope("s2", None, "user:bob/default").unwrap();
+        persistence.save_message("s2", &Message::user("alice is bob's sister")).unwrap();
+        drop(persistence);
+        let episodic = Arc::new(EpisodicMemory::new(root.join("episodes.jsonl"), 10));
+        episodic.record(Episode::new("find alice's address", &[], "done", true));
+        episodic.record(Episode::new("book a flight", &[], "done", true));
+
+        let ctx = ContextManager::new(10)
+            .with_long_term(lt)
+            .with_preferences_path(prefs.clone())
+            .with_memory_root(root.clone())
+            .with_persistence_db(db.clone())
+            .with_episodic(Arc::clone(&episodic));
+
+        let preview = ctx.forget("alice", true).unwrap();
+        assert_eq!(preview.long_term.len(), 1);
+        assert_eq!(preview.daily_logs.len(), 1);
+        assert_eq!(preview.preferences.len(), 1);
+        assert_eq!(preview.messages.len(), 1);
+        assert_eq!(preview.episodes.len(), 1);
+        assert_eq!(ctx.forget("alice", true).unwrap().total(), 5);
+
+        let done = ctx.forget("ALICE", false).unwrap();
+        assert_eq!(done.total(), 5);
+        assert_eq!(episodic.len(), 1);
+        let episodes = std::fs::read_to_string(root.join("episodes.jsonl")).unwrap();
+        assert!(!episodes.contains("alice") && episodes.contains("flight"));
+        assert_eq!(ctx.forget("alice", true).unwrap().total(), 0);
+        let log = std::fs::read_to_string(root.join("logs/2026-01-02.md")).unwrap();
+        assert!(log.contains("Noted") && !log.contains("Paris"));
+        assert_eq!(ctx.search_memory("deadline", 5).len(), 1);
+        let persistence = SqlitePersistence::new(&db).unwrap();
+        assert!(persistence.load_messages("s1").unwrap().is_empty());
+        assert_eq!(persistence.load_messages("s2").unwrap().len(), 1);
+    }
+
     #[test]
     fn test_context_manager_record_tool_success_flag() {
         let ctx = ContextManager::new(10).with_record_tool_success(true);
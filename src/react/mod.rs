@@ -1,13 +1,32 @@
 //! 认知层：Planner、Critic、ReAct 主循环、三层记忆协调（ContextManager）
 
+pub mod ask_user;
+pub mod checkpoint;
 pub mod critic;
 pub mod events;
+pub mod guardrails;
+pub mod handoff;
 pub mod loop_;
 pub mod memory;
+pub mod observation;
 pub mod planner;
+pub mod replay;
+pub mod stream;
+pub mod validators;
 
-pub use critic::{Critic, CriticResult};
+pub use ask_user::{PendingQuestion, QuestionBroker};
+pub use checkpoint::{CheckpointLease, PendingToolCall, ReactCheckpoint};
+pub use critic::{Critic, CriticResult, ToolSnapshot};
 pub use events::ReactEvent;
-pub use loop_::{compact_context, react_loop, react_loop_v2, ReactResult, ReactSession};
-pub use memory::ContextManager;
+pub use guardrails::{GuardrailAction, GuardrailStage, GuardrailVerdict, Guardrails};
+pub use handoff::{generate_handoff_summary, handoff_messages, HandoffRequest, HANDOFF_TOOL};
+pub use loop_::{
+    compact_context, compact_context_with_critic, react_loop, react_loop_v2, resume_react_loop,
+    CompactionOutcome, ReactLimits, ReactResult, ReactSession,
+};
+pub use memory::{CompactionPolicy, ContextManager, ForgetReport, MemoryHit, MemorySource};
+pub use observation::{condense_observation, CondensedObservation};
 pub use planner::{parse_llm_output, Planner};
+pub use replay::{ReplayFixture, ReplayRecorder};
+pub use stream::{react_loop_stream, ReactStreamItem};
+pub use validators::ToolValidator;
@@ -0,0 +1,157 @@
+//! 超长工具输出摘要
+//!
+//! 工具输出（如嘈杂的 shell 命令）估算超过阈值时：完整输出写入 workspace/artifacts/，
+//! 对话中只保留摘要与 artifact 路径，避免上下文被撑爆。摘要失败时退化为首尾截取。
+
+use std::path::{Path, PathBuf};
+
+use crate::llm::estimate_tokens;
+use crate::react::Planner;
+
+/// 摘要失败时保留的开头字符数
+const FALLBACK_HEAD_CHARS: usize = 2000;
+/// 摘要失败时保留的结尾字符数（错误与最终状态通常在末尾）
+const FALLBACK_TAIL_CHARS: usize = 1000;
+
+/// 摘要后的工具输出
+#[derive(Debug, Clone)]
+pub struct CondensedObservation {
+    /// 写入对话的内容（摘要 + artifact 说明）
+    pub text: String,
+    /// 完整输出的保存路径（未配置目录或写入失败时为 None）
+    pub artifact: Option<PathBuf>,
+    /// 原始输出估算 tokens
+    pub original_tokens: usize,
+}
+
+/// 将完整输出写入 dir/{时间}-{工具}-{短 id}.txt，返回文件路径
+pub fn save_artifact(dir: &Path, tool: &str, content: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let name: String = tool
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let path = dir.join(format!(
+        "{}-{}-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        name,
+        &id[..8]
+    ));
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// artifact 相对 workspace（artifacts 目录的上级）的路径，便于 cat 等沙箱工具直接读取
+fn workspace_relative<'a>(path: &'a Path, artifacts_dir: Option<&Path>) -> &'a Path {
+    artifacts_dir
+        .and_then(Path::parent)
+        .and_then(|ws| path.strip_prefix(ws).ok())
+        .unwrap_or(path)
+}
+
+/// 保留开头 head 与结尾 tail 个字符，中间以省略标记替代
+pub fn head_tail(text: &str, head: usize, tail: usize) -> String {
+    let total = text.chars().count();
+    if total <= head + tail {
+        return text.to_string();
+    }
+    let start: String = text.chars().take(head).collect();
+    let end: String = text.chars().skip(total - tail).collect();
+    format!("{}\n[... {} chars omitted ...]\n{}", start, total - head - tail, end)
+}
+
+/// 输出估算超过 threshold_tokens 时保存 artifact 并摘要；未超出或 threshold 为 0 时返回 None
+pub async fn condense_observation(
+    planner: &Planner,
+    user_input: &str,
+    tool: &str,
+    observation: &str,
+    threshold_tokens: usize,
+    artifacts_dir: Option<&Path>,
+) -> Option<CondensedObservation> {
+    let original_tokens = estimate_tokens(observation);
+    if threshold_tokens == 0 || original_tokens <= threshold_tokens {
+        return None;
+    }
+    let artifact = artifacts_dir.and_then(|dir| match save_artifact(dir, tool, observation) {
+        Ok(p) => Some(p),
+        Err(e) => {
+            tracing::warn!(tool, "failed to save observation artifact: {}", e);
+            None
+        }
+    });
+    let summary = match planner.summarize_observation(user_input, tool, observation).await {
+        Ok(s) if !s.trim().is_empty() => s.trim().to_string(),
+        Ok(_) => head_tail(observation, FALLBACK_HEAD_CHARS, FALLBACK_TAIL_CHARS),
+        Err(e) => {
+            tracing::warn!(tool, "observation summary failed, truncating: {}", e);
+            head_tail(observation, FALLBACK_HEAD_CHARS, FALLBACK_TAIL_CHARS)
+        }
+    };
+    let note = match artifact {
+        Some(ref p) => format!(
+            "[Output of ~{} tokens summarized; full output saved to {}, read it with cat if details are needed]",
+            original_tokens,
+            workspace_relative(p, artifacts_dir).display()
+        ),
+        None => format!("[Output of ~{} tokens summarized]", original_tokens),
+    };
+    Some(CondensedObservation {
+        text: format!("{}\n{}", note, summary),
+        artifact,
+        original_tokens,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmClient, LlmError};
+    use crate::memory::Message;
+    use std::sync::Arc;
+
+    /// 固定返回一行摘要的测试 LLM
+    struct FixedSummaryLlm;
+
+    #[async_trait::async_trait]
+    impl LlmClient for FixedSummaryLlm {
+        async fn complete(&self, _messages: &[Message]) -> Result<String, LlmError> {
+            Ok("build finished with 200 unused-variable warnings".to_string())
+        }
+
+        async fn complete_stream(
+            &self,
+            messages: &[Message],
+        ) -> Result<
+            std::pin::Pin<Box<dyn futures_util::Stream<Item = Result<String, LlmError>> + Send>>,
+            LlmError,
+        > {
+            let content = self.complete(messages).await?;
+            Ok(Box::pin(futures_util::stream::iter(vec![Ok(content)])))
+        }
+    }
+
+    #[test]
+    fn test_condense_observation_saves_artifact() {
+        assert_eq!(head_tail("abcdef", 2, 2), "ab\n[... 2 chars omitted ...]\nef");
+        assert_eq!(head_tail("abc", 2, 2), "abc");
+
+        let dir = tempfile::tempdir().unwrap();
+        let planner = Planner::new(Arc::new(FixedSummaryLlm), "test");
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let small = rt.block_on(condense_observation(&planner, "build", "shell", "ok", 100, Some(dir.path())));
+        assert!(small.is_none());
+
+        let noisy = "warning: unused variable\n".repeat(200);
+        let condensed = rt
+            .block_on(condense_observation(&planner, "build", "shell", &noisy, 100, Some(dir.path())))
+            .unwrap();
+        let artifact = condensed.artifact.unwrap();
+        assert_eq!(std::fs::read_to_string(&artifact).unwrap(), noisy);
+        let file_name = artifact.file_name().unwrap().to_string_lossy().to_string();
+        assert!(condensed.text.contains(&file_name));
+        assert!(condensed.text.contains("200 unused-variable warnings"));
+        assert!(estimate_tokens(&condensed.text) < condensed.original_tokens);
+    }
+}
@@ -7,7 +7,7 @@ use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 use crate::core::AgentError;
-use crate::llm::LlmClient;
+use crate::llm::{estimate_messages_tokens, estimate_tokens, truncate_messages_to_budget, LlmClient};
 use crate::memory::Message;
 
 /// LLM 返回的 Tool Call（简化 JSON：{"tool": "cat", "args": {"path": "..."}}）
@@ -161,6 +161,10 @@ pub fn validate_tool_name(tool_name: &str, valid_tools: Option<&[String]>) -> bo
 pub struct Planner {
     llm: Arc<dyn LlmClient>,
     system_prompt: String,
+    /// 单次请求允许的输入 tokens（上下文窗口 - 输出预留），0 表示不检查
+    context_budget: usize,
+    /// 摘要超长工具输出用的（廉价）模型，None 时使用 llm
+    summarizer: Option<Arc<dyn LlmClient>>,
 }
 
 impl Planner {
@@ -168,9 +172,31 @@ impl Planner {
         Self {
             llm,
             system_prompt: system_prompt.into(),
+            context_budget: 0,
+            summarizer: None,
         }
     }
 
+    /// 设置摘要工具输出用的模型（通常比主模型便宜）
+    pub fn with_summarizer(mut self, llm: Option<Arc<dyn LlmClient>>) -> Self {
+        self.summarizer = llm;
+        self
+    }
+
+    pub fn summarizer(&self) -> Option<Arc<dyn LlmClient>> {
+        self.summarizer.clone()
+    }
+
+    /// 设置输入 token 预算：ReAct 循环在请求前估算，超出时先压缩/截断
+    pub fn with_context_budget(mut self, budget: usize) -> Self {
+        self.context_budget = budget;
+        self
+    }
+
+    pub fn context_budget(&self) -> usize {
+        self.context_budget
+    }
+
     pub fn base_system_prompt(&self) -> &str {
         &self.system_prompt
     }
@@ -198,24 +224,216 @@ impl Planner {
             .map_err(AgentError::LlmError)
     }
 
-    /// 将对话历史压缩为一段摘要（用于 Context Compaction：写入长期记忆后替换当前消息）
+    /// 从一轮对话中抽取实体与关系（知识图谱记忆）
+    pub async fn extract_knowledge(
+        &self,
+        text: &str,
+    ) -> Result<crate::memory::KnowledgeGraph, AgentError> {
+        crate::memory::graph::extract_graph(self.llm.as_ref(), "conversation", text)
+            .await
+            .map_err(AgentError::ToolExecutionFailed)
+    }
+
+    /// 将对话历史压缩为一段摘要（用于 Context Compaction：写入长期记忆后替换当前消息）。
+    /// 历史本身超出上下文窗口时分块摘要，再合并各块摘要，避免摘要请求自身溢出
     pub async fn summarize(&self, messages: &[Message]) -> Result<String, AgentError> {
         if messages.is_empty() {
             return Ok(String::new());
         }
         let system = "You are a summarizer. Summarize the following conversation in one short paragraph: key facts, decisions, user preferences, and the latest question if any. Use the same language as the conversation. Output only the summary, no preamble.";
+        // 留出约 1/4 窗口给摘要输出
+        let input_budget = if self.context_budget > 0 {
+            (self.context_budget * 3 / 4).saturating_sub(estimate_tokens(system)).max(1)
+        } else {
+            usize::MAX
+        };
+        let mut input = messages.to_vec();
+        for _ in 0..MAX_SUMMARY_ROUNDS {
+            if estimate_messages_tokens(&input) <= input_budget {
+                break;
+            }
+            let mut partials = Vec::new();
+            for chunk in chunk_messages(input, input_budget) {
+                let summary = self.summarize_once(system, chunk, input_budget).await?;
+                partials.push(Message::user(format!(
+                    "Summary of conversation part {}:\n{}",
+                    partials.len() + 1,
+                    summary
+                )));
+            }
+            input = partials;
+        }
+        self.summarize_once(system, input, input_budget).await
+    }
+
+    /// 单次摘要请求；输入仍超出 budget 时截断（单条消息过长的情况）
+    async fn summarize_once(&self, system: &str, mut input: Vec<Message>, budget: usize) -> Result<String, AgentError> {
+        truncate_messages_to_budget(&mut input, budget);
         let mut full = vec![Message::system(system.to_string())];
-        full.extend(messages.to_vec());
+        full.extend(input);
         self.llm
             .complete(&full)
             .await
             .map_err(AgentError::LlmError)
     }
+
+    /// 摘要一次超长的工具输出：保留与任务相关的关键信息（错误、路径、数字、结论）
+    pub async fn summarize_observation(
+        &self,
+        user_input: &str,
+        tool: &str,
+        observation: &str,
+    ) -> Result<String, AgentError> {
+        let system = "You condense tool output for an agent. Summarize the output below in at most 15 lines, keeping what matters for the user's task: errors and warnings, file paths, identifiers, numbers, and the final status. Use the same language as the task. Output only the summary.";
+        let full = vec![
+            Message::system(system.to_string()),
+            Message::user(format!(
+                "Task: {}\nTool: {}\n\nOutput:\n{}",
+                user_input, tool, observation
+            )),
+        ];
+        self.summarizer
+            .as_ref()
+            .unwrap_or(&self.llm)
+            .complete(&full)
+            .await
+            .map_err(AgentError::LlmError)
+    }
+
+    /// 根据本轮问答生成 2~3 条用户可能的追问（供前端渲染快捷回复）
+    pub async fn suggest_followups(
+        &self,
+        user_input: &str,
+        answer: &str,
+    ) -> Result<Vec<String>, AgentError> {
+        let system = "You suggest follow-up prompts. Given the user's question and the assistant's answer, propose 2-3 short follow-up questions the user is likely to ask next, written from the user's point of view in the same language as the conversation. Output only a JSON array of strings.";
+        let full = vec![
+            Message::system(system.to_string()),
+            Message::user(format!("User: {}\n\nAssistant: {}", user_input, answer)),
+        ];
+        let output = self.llm.complete(&full).await.map_err(AgentError::LlmError)?;
+        Ok(parse_suggestions(&output))
+    }
+}
+
+/// 分块摘要最多合并几轮（每轮把各块摘要再当作输入），仍超出时截断
+const MAX_SUMMARY_ROUNDS: usize = 3;
+
+/// 按顺序把消息装入不超过 budget 的块；单条超出 budget 的消息独占一块（摘要时截断）
+fn chunk_messages(messages: Vec<Message>, budget: usize) -> Vec<Vec<Message>> {
+    let mut chunks: Vec<Vec<Message>> = Vec::new();
+    let mut current: Vec<Message> = Vec::new();
+    let mut used = 0;
+    for msg in messages {
+        let tokens = estimate_messages_tokens(std::slice::from_ref(&msg));
+        if !current.is_empty() && used + tokens > budget {
+            chunks.push(std::mem::take(&mut current));
+            used = 0;
+        }
+        used += tokens;
+        current.push(msg);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 追问建议最多条数
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// 解析追问建议：优先取 JSON 字符串数组，否则按行拆分（去掉列表符号与编号），去重并截断
+pub fn parse_suggestions(output: &str) -> Vec<String> {
+    let from_json = output
+        .find('[')
+        .zip(output.rfind(']'))
+        .filter(|(start, end)| start < end)
+        .and_then(|(start, end)| serde_json::from_str::<Vec<String>>(&output[start..=end]).ok());
+    let candidates = from_json.unwrap_or_else(|| {
+        output
+            .lines()
+            .map(|l| {
+                l.trim()
+                    .trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '-' | '*' | '.' | ')' | '、'))
+                    .trim()
+                    .to_string()
+            })
+            .filter(|l| !l.starts_with("```"))
+            .collect()
+    });
+    let mut items: Vec<String> = Vec::new();
+    for item in candidates {
+        let item = item.trim().trim_matches('"').trim().to_string();
+        if !item.is_empty() && !items.contains(&item) {
+            items.push(item);
+        }
+        if items.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+    items
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use futures_util::Stream;
+
+    /// 记录每次请求的估算 token 数，回复固定文本
+    struct SizeRecordingLlm {
+        sizes: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl LlmClient for SizeRecordingLlm {
+        async fn complete(&self, messages: &[Message]) -> Result<String, crate::llm::LlmError> {
+            self.sizes.lock().unwrap().push(estimate_messages_tokens(messages));
+            Ok("short summary".to_string())
+        }
+
+        async fn complete_stream(
+            &self,
+            messages: &[Message],
+        ) -> Result<std::pin::Pin<Box<dyn Stream<Item = Result<String, crate::llm::LlmError>> + Send>>, crate::llm::LlmError>
+        {
+            let content = self.complete(messages).await?;
+            Ok(Box::pin(futures_util::stream::iter(vec![Ok(content)])))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarize_chunks_history_over_budget() {
+        let llm = Arc::new(SizeRecordingLlm { sizes: Mutex::new(Vec::new()) });
+        let planner = Planner::new(llm.clone(), "test".to_string()).with_context_budget(400);
+        let mut history: Vec<Message> = (0..20)
+            .map(|i| Message::user(format!("message {} {}", i, "word ".repeat(40))))
+            .collect();
+        history.push(Message::assistant("x".repeat(4000)));
+        assert!(estimate_messages_tokens(&history) > 400);
+
+        assert_eq!(planner.summarize(&history).await.unwrap(), "short summary");
+        let sizes = llm.sizes.lock().unwrap().clone();
+        // 分块后再合并：每次请求都落在窗口内
+        assert!(sizes.len() > 1);
+        assert!(sizes.iter().all(|s| *s <= 400), "{:?}", sizes);
+    }
+
+    #[test]
+    fn test_parse_suggestions() {
+        let json = "```json\n[\"How do I test it?\", \"Show an example\", \"Show an example\", \"Any pitfalls?\", \"More\"]\n```";
+        assert_eq!(
+            parse_suggestions(json),
+            vec!["How do I test it?", "Show an example", "Any pitfalls?"]
+        );
+        assert_eq!(
+            parse_suggestions("1. 如何部署？\n2) 有哪些限制？\n\n- 能举个例子吗？"),
+            vec!["如何部署？", "有哪些限制？", "能举个例子吗？"]
+        );
+        assert!(parse_suggestions("").is_empty());
+    }
 
     #[test]
     fn test_parse_llm_output_tool_call() {
@@ -0,0 +1,321 @@
+//! 确定性回放（Record / Replay）：供 CI 测试 ReAct 循环
+//!
+//! - 录制：ReplayRecorder 包装真实 LlmClient 与 ToolRegistry，按顺序记录 LLM 回复与工具输出，save() 写成 JSON fixture
+//! - 回放：ReplayFixture::llm_client() / tool_registry() 按录制顺序返回相同内容，无需网络与真实工具
+//!
+//! 回放时若循环行为偏离录制（多调用一次 LLM、调用了未录制的工具、工具参数与录制不同），会返回明确错误，便于测试断言。
+//! 参数不稳定的录制（如含时间戳）可用 ReplayFixture::tool_registry_ignoring_args() 只按顺序回放。
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use futures_util::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::llm::{LlmClient, LlmError};
+use crate::memory::Message;
+use crate::tools::{Tool, ToolError, ToolRegistry};
+
+/// 一次工具调用的录制结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedToolCall {
+    pub tool: String,
+    pub args: Value,
+    pub output: Result<String, ToolError>,
+}
+
+/// 录制好的会话：LLM 回复与工具调用均按发生顺序保存
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFixture {
+    #[serde(default)]
+    pub llm_responses: Vec<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<RecordedToolCall>,
+}
+
+impl ReplayFixture {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 按顺序返回录制回复的 LLM 客户端
+    pub fn llm_client(&self) -> ReplayLlmClient {
+        ReplayLlmClient {
+            responses: Mutex::new(self.llm_responses.iter().cloned().collect()),
+        }
+    }
+
+    /// 为每个录制过的工具名注册一个 ReplayTool，按该工具的调用顺序返回录制输出；参数与录制不同时返回错误
+    pub fn tool_registry(&self) -> ToolRegistry {
+        self.build_registry(true)
+    }
+
+    /// 同 tool_registry()，但不校验参数，只按顺序返回录制输出
+    pub fn tool_registry_ignoring_args(&self) -> ToolRegistry {
+        self.build_registry(false)
+    }
+
+    fn build_registry(&self, check_args: bool) -> ToolRegistry {
+        let mut by_tool: HashMap<String, VecDeque<RecordedToolCall>> = HashMap::new();
+        for call in &self.tool_calls {
+            by_tool.entry(call.tool.clone()).or_default().push_back(call.clone());
+        }
+        let mut registry = ToolRegistry::new();
+        for (name, calls) in by_tool {
+            registry.register(ReplayTool {
+                name,
+                calls: Mutex::new(calls),
+                check_args,
+            });
+        }
+        registry
+    }
+}
+
+/// 回放 LLM：依次弹出录制的回复，耗尽后返回 ApiError
+pub struct ReplayLlmClient {
+    responses: Mutex<VecDeque<String>>,
+}
+
+impl ReplayLlmClient {
+    /// 尚未被消费的回复数（测试可断言为 0，确认循环与录制一致）
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl LlmClient for ReplayLlmClient {
+    async fn complete(&self, _messages: &[Message]) -> Result<String, LlmError> {
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| LlmError::ApiError("replay: no more recorded LLM responses".to_string()))
+    }
+
+    async fn complete_stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError> {
+        let content = self.complete(messages).await?;
+        Ok(Box::pin(stream::iter(vec![Ok(content)])))
+    }
+}
+
+/// 回放工具：按顺序返回录制输出，不执行任何真实操作；check_args 时参数须与录制一致
+pub struct ReplayTool {
+    name: String,
+    calls: Mutex<VecDeque<RecordedToolCall>>,
+    check_args: bool,
+}
+
+#[async_trait]
+impl Tool for ReplayTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "Replayed tool (returns recorded outputs)"
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        match self.calls.lock().unwrap().pop_front() {
+            Some(call) if self.check_args && call.args != args => Err(ToolError::Failed(format!(
+                "replay: tool {} called with args {} but recorded {}",
+                self.name, args, call.args
+            ))),
+            Some(call) => call.output,
+            None => Err(ToolError::Failed(format!(
+                "replay: no more recorded outputs for tool {}",
+                self.name
+            ))),
+        }
+    }
+}
+
+/// 录制器：包装真实 LLM 与工具，记录到共享的 ReplayFixture
+#[derive(Clone, Default)]
+pub struct ReplayRecorder {
+    fixture: Arc<Mutex<ReplayFixture>>,
+}
+
+impl ReplayRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 包装 LLM 客户端：每次成功的 complete 都会被记录
+    pub fn wrap_llm(&self, inner: Arc<dyn LlmClient>) -> RecordingLlmClient {
+        RecordingLlmClient {
+            inner,
+            fixture: Arc::clone(&self.fixture),
+        }
+    }
+
+    /// 包装注册表中的所有工具：每次 execute 的参数与结果都会被记录
+    pub fn wrap_registry(&self, registry: &ToolRegistry) -> ToolRegistry {
+        let mut wrapped = ToolRegistry::new();
+        for name in registry.tool_names() {
+            if let Some(inner) = registry.get(&name) {
+                wrapped.register(RecordingTool {
+                    inner,
+                    fixture: Arc::clone(&self.fixture),
+                });
+            }
+        }
+        wrapped
+    }
+
+    /// 当前已录制内容的快照
+    pub fn fixture(&self) -> ReplayFixture {
+        self.fixture.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.fixture().save(path)
+    }
+}
+
+/// 录制中的 LLM 客户端
+pub struct RecordingLlmClient {
+    inner: Arc<dyn LlmClient>,
+    fixture: Arc<Mutex<ReplayFixture>>,
+}
+
+#[async_trait]
+impl LlmClient for RecordingLlmClient {
+    async fn complete(&self, messages: &[Message]) -> Result<String, LlmError> {
+        let output = self.inner.complete(messages).await?;
+        self.fixture.lock().unwrap().llm_responses.push(output.clone());
+        Ok(output)
+    }
+
+    async fn complete_stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>, LlmError> {
+        // 录制时合并为一次完整回复，保证回放顺序与非流式一致
+        let content = self.complete(messages).await?;
+        Ok(Box::pin(stream::iter(vec![Ok(content)])))
+    }
+
+    fn token_usage(&self) -> (u64, u64, u64) {
+        self.inner.token_usage()
+    }
+}
+
+/// 录制中的工具
+pub struct RecordingTool {
+    inner: Arc<dyn Tool>,
+    fixture: Arc<Mutex<ReplayFixture>>,
+}
+
+#[async_trait]
+impl Tool for RecordingTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn short_description(&self) -> &str {
+        self.inner.short_description()
+    }
+
+    fn long_description(&self) -> &str {
+        self.inner.long_description()
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let output = self.inner.execute(args.clone()).await;
+        self.fixture.lock().unwrap().tool_calls.push(RecordedToolCall {
+            tool: self.inner.name().to_string(),
+            args,
+            output: output.clone(),
+        });
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockLlmClient;
+    use crate::tools::EchoTool;
+
+    #[test]
+    fn test_record_then_replay_roundtrip() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let recorder = ReplayRecorder::new();
+            let llm = recorder.wrap_llm(Arc::new(MockLlmClient));
+            let mut registry = ToolRegistry::new();
+            registry.register(EchoTool);
+            let tools = recorder.wrap_registry(&registry);
+
+            let first = llm.complete(&[Message::user("hi")]).await.unwrap();
+            let echo = tools.get("echo").unwrap();
+            let out = echo.execute(serde_json::json!({"text": "hi"})).await;
+
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("fixture.json");
+            recorder.save(&path).unwrap();
+            let fixture = ReplayFixture::load(&path).unwrap();
+            assert_eq!(fixture.llm_responses, vec![first.clone()]);
+            assert_eq!(fixture.tool_calls.len(), 1);
+
+            let replay_llm = fixture.llm_client();
+            assert_eq!(replay_llm.complete(&[]).await.unwrap(), first);
+            assert!(replay_llm.complete(&[]).await.is_err());
+            let replay_tools = fixture.tool_registry();
+            let replayed = replay_tools
+                .get("echo")
+                .unwrap()
+                .execute(serde_json::json!({"text": "hi"}))
+                .await;
+            assert_eq!(replayed, out);
+        });
+    }
+
+    #[test]
+    fn test_replay_rejects_mismatched_args() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let fixture = ReplayFixture {
+                tool_calls: vec![RecordedToolCall {
+                    tool: "cat".to_string(),
+                    args: serde_json::json!({"path": "a.txt"}),
+                    output: Ok("hello".to_string()),
+                }],
+                ..Default::default()
+            };
+            let cat = fixture.tool_registry().get("cat").unwrap();
+            let err = cat.execute(serde_json::json!({"path": "b.txt"})).await.unwrap_err();
+            assert!(matches!(err, ToolError::Failed(msg) if msg.contains("b.txt")));
+
+            let cat = fixture.tool_registry_ignoring_args().get("cat").unwrap();
+            assert_eq!(cat.execute(serde_json::json!({"path": "b.txt"})).await.unwrap(), "hello");
+        });
+    }
+}
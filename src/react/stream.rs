@@ -0,0 +1,139 @@
+//! ReAct 事件流：把 react_loop_v2 包装为 `Stream`，库调用方无需自行创建与消费 event_tx 通道
+//!
+//! 流依次产出循环的过程事件，最后产出一次 `ReactStreamItem::Finished`（最终结果或错误）后结束。
+//! 循环在流被轮询时推进；中途丢弃流即中止循环（等同取消，不写入最终回复）。
+//!
+//! ```ignore
+//! let mut events = Box::pin(react_loop_stream(session, &mut context, "Summarize my notes"));
+//! while let Some(item) = events.next().await {
+//!     match item {
+//!         ReactStreamItem::Event(ReactEvent::MessageChunk { text }) => print!("{}", text),
+//!         ReactStreamItem::Finished(result) => println!("\n{:?}", result.map(|r| r.response)),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+
+use futures_util::Stream;
+use tokio::sync::mpsc;
+
+use crate::core::AgentError;
+use crate::react::{react_loop_v2, ContextManager, ReactEvent, ReactResult, ReactSession};
+
+/// 事件流的单项
+#[derive(Debug)]
+pub enum ReactStreamItem {
+    /// 循环过程事件（与 event_tx 收到的相同）
+    Event(ReactEvent),
+    /// 循环结束：最终结果或错误，之后流结束
+    Finished(Result<ReactResult, AgentError>),
+}
+
+type LoopFuture<'a> = Pin<Box<dyn Future<Output = Result<ReactResult, AgentError>> + Send + 'a>>;
+
+struct StreamState<'a> {
+    run: Option<LoopFuture<'a>>,
+    rx: mpsc::UnboundedReceiver<ReactEvent>,
+    result: Option<Result<ReactResult, AgentError>>,
+}
+
+/// 以事件流的形式运行 ReAct 循环；session 已设置的 event_tx 会被替换为流内部的通道
+pub fn react_loop_stream<'a>(
+    session: ReactSession<'a>,
+    context: &'a mut ContextManager,
+    user_input: &'a str,
+) -> impl Stream<Item = ReactStreamItem> + Send + 'a {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let run = async move {
+        let session = session.with_event_tx(&tx);
+        react_loop_v2(&session, context, user_input).await
+    };
+    let state = StreamState {
+        run: Some(Box::pin(run)),
+        rx,
+        result: None,
+    };
+    futures_util::stream::unfold(state, |mut state| async move {
+        if let Some(run) = state.run.as_mut() {
+            // 先交付已产生的事件，再检查循环是否结束
+            tokio::select! {
+                biased;
+                Some(ev) = state.rx.recv() => return Some((ReactStreamItem::Event(ev), state)),
+                result = run => {
+                    state.run = None;
+                    state.result = Some(result);
+                }
+            }
+        }
+        // 循环已结束：交付剩余事件，最后交付结果
+        if let Ok(ev) = state.rx.try_recv() {
+            return Some((ReactStreamItem::Event(ev), state));
+        }
+        let result = state.result.take()?;
+        Some((ReactStreamItem::Finished(result), state))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::RecoveryEngine;
+    use crate::react::replay::RecordedToolCall;
+    use crate::react::{Planner, ReplayFixture};
+    use crate::tools::ToolExecutor;
+
+    #[tokio::test]
+    async fn test_react_loop_stream_yields_events_then_result() {
+        let fixture = ReplayFixture {
+            llm_responses: vec![
+                r#"{"tool": "cat", "args": {"path": "notes.md"}}"#.into(),
+                "Two todo items.".into(),
+            ],
+            tool_calls: vec![RecordedToolCall {
+                tool: "cat".into(),
+                args: json!({"path": "notes.md"}),
+                output: Ok("- buy milk\n- call Bob".into()),
+            }],
+        };
+        let planner = Planner::new(Arc::new(fixture.llm_client()), "test".to_string());
+        let executor = ToolExecutor::new(fixture.tool_registry(), 30);
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        );
+        let mut context = ContextManager::new(10).with_suggestions(false);
+        let items: Vec<ReactStreamItem> = react_loop_stream(session, &mut context, "Summarize notes.md")
+            .collect()
+            .await;
+
+        let tools: Vec<&str> = items
+            .iter()
+            .filter_map(|item| match item {
+                ReactStreamItem::Event(ReactEvent::ToolCall { tool, .. }) => Some(tool.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tools, vec!["cat"]);
+        let done = items
+            .iter()
+            .position(|item| matches!(item, ReactStreamItem::Event(ReactEvent::MessageDone)))
+            .unwrap();
+        match items.last() {
+            Some(ReactStreamItem::Finished(Ok(result))) => assert_eq!(result.response, "Two todo items."),
+            other => panic!("unexpected last item: {:?}", other),
+        }
+        assert!(done < items.len() - 1);
+        assert_eq!(context.messages().last().unwrap().content, "Two todo items.");
+    }
+}
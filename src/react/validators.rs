@@ -0,0 +1,163 @@
+//! Critic 工具校验器：不调用 LLM 的确定性检查，如「code_edit 是否真的改动了文件」
+//!
+//! 校验器在工具执行前记录状态（snapshot），执行后结合参数与 Observation 判断是否达到预期；
+//! 未通过时与 LLM Critic 的修正建议一样写回对话。按 [critic.validators] 为工具配置内置校验器，
+//! 也可实现 `ToolValidator` 后用 `Critic::with_validator` 挂载。
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// 工具校验器
+pub trait ToolValidator: Send + Sync {
+    /// 名称（日志与修正建议中使用）
+    fn name(&self) -> &str;
+
+    /// 执行前记录需要对比的状态；默认不记录
+    fn snapshot(&self, _args: &Value) -> Option<String> {
+        None
+    }
+
+    /// 执行后校验：Err 为未达预期的原因
+    fn validate(&self, args: &Value, observation: &str, snapshot: Option<&str>) -> Result<(), String>;
+}
+
+/// 按名称创建内置校验器：file_changed / file_exists / non_empty
+pub fn builtin_validator(name: &str, workspace: &Path) -> Option<Arc<dyn ToolValidator>> {
+    match name {
+        "file_changed" => Some(Arc::new(FileChangedValidator::new(workspace))),
+        "file_exists" => Some(Arc::new(FileExistsValidator::new(workspace))),
+        "non_empty" => Some(Arc::new(NonEmptyValidator)),
+        _ => None,
+    }
+}
+
+/// 参数中的目标文件（file_path 或 path），相对路径按工作区解析
+fn target_file(workspace: &Path, args: &Value) -> Option<PathBuf> {
+    let path = args
+        .get("file_path")
+        .or_else(|| args.get("path"))
+        .and_then(|v| v.as_str())?;
+    let path = Path::new(path);
+    Some(if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        workspace.join(path)
+    })
+}
+
+fn file_digest(path: &Path) -> String {
+    match std::fs::read(path) {
+        Ok(bytes) => format!("{:x}", Sha256::digest(&bytes)),
+        Err(_) => "missing".to_string(),
+    }
+}
+
+/// 目标文件内容在执行后发生了变化（code_edit / code_write）
+pub struct FileChangedValidator {
+    workspace: PathBuf,
+}
+
+impl FileChangedValidator {
+    pub fn new(workspace: &Path) -> Self {
+        Self {
+            workspace: workspace.to_path_buf(),
+        }
+    }
+}
+
+impl ToolValidator for FileChangedValidator {
+    fn name(&self) -> &str {
+        "file_changed"
+    }
+
+    fn snapshot(&self, args: &Value) -> Option<String> {
+        target_file(&self.workspace, args).map(|p| file_digest(&p))
+    }
+
+    fn validate(&self, args: &Value, _observation: &str, snapshot: Option<&str>) -> Result<(), String> {
+        let (Some(path), Some(before)) = (target_file(&self.workspace, args), snapshot) else {
+            return Ok(());
+        };
+        if file_digest(&path) == before {
+            return Err(format!(
+                "{} was not changed although the tool reported success; re-read it and check the edit",
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 目标文件在执行后存在
+pub struct FileExistsValidator {
+    workspace: PathBuf,
+}
+
+impl FileExistsValidator {
+    pub fn new(workspace: &Path) -> Self {
+        Self {
+            workspace: workspace.to_path_buf(),
+        }
+    }
+}
+
+impl ToolValidator for FileExistsValidator {
+    fn name(&self) -> &str {
+        "file_exists"
+    }
+
+    fn validate(&self, args: &Value, _observation: &str, _snapshot: Option<&str>) -> Result<(), String> {
+        match target_file(&self.workspace, args) {
+            Some(path) if !path.exists() => Err(format!("{} does not exist after the call", path.display())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Observation 非空
+pub struct NonEmptyValidator;
+
+impl ToolValidator for NonEmptyValidator {
+    fn name(&self) -> &str {
+        "non_empty"
+    }
+
+    fn validate(&self, _args: &Value, observation: &str, _snapshot: Option<&str>) -> Result<(), String> {
+        if observation.trim().is_empty() {
+            Err("the tool returned no output".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_builtin_validators() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}").unwrap();
+        let args = json!({"file_path": "a.rs"});
+
+        let changed = builtin_validator("file_changed", dir.path()).unwrap();
+        let before = changed.snapshot(&args);
+        assert!(changed.validate(&args, "ok", before.as_deref()).is_err());
+        std::fs::write(dir.path().join("a.rs"), "fn b() {}").unwrap();
+        assert!(changed.validate(&args, "ok", before.as_deref()).is_ok());
+
+        let exists = builtin_validator("file_exists", dir.path()).unwrap();
+        assert!(exists.validate(&args, "", None).is_ok());
+        assert!(exists.validate(&json!({"path": "b.rs"}), "", None).is_err());
+
+        let non_empty = builtin_validator("non_empty", dir.path()).unwrap();
+        assert!(non_empty.validate(&args, "  \n", None).is_err());
+        assert!(builtin_validator("unknown", dir.path()).is_none());
+    }
+}
@@ -9,6 +9,8 @@ use std::sync::Arc;
 use serde::Deserialize;
 use tokio::sync::RwLock;
 
+use crate::tools::ReportLanguage;
+
 /// 技能元数据（skill.toml）
 #[derive(Debug, Clone, Deserialize)]
 pub struct SkillMeta {
@@ -21,6 +23,9 @@ pub struct SkillMeta {
     pub script: Option<String>,
     #[serde(default)]
     pub script_type: Option<String>,
+    /// 选中该技能时 generate_report 使用的报告语言（zh / en / bilingual）
+    #[serde(default)]
+    pub report_language: Option<ReportLanguage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -193,6 +198,7 @@ mod tests {
                 tags: vec![],
                 script: None,
                 script_type: None,
+                report_language: None,
             },
             capability: "# 能力\n测试能力描述".to_string(),
             template: None,
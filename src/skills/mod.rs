@@ -16,6 +16,8 @@
 
 mod loader;
 mod selector;
+mod usage;
 
 pub use loader::{Skill, SkillCache, SkillLoader};
 pub use selector::SkillSelector;
+pub use usage::{suggest_skill_changes, SkillSuggestion, SkillSuggestionAction};
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use crate::llm::LlmClient;
 use crate::memory::Message;
+use crate::tools::ReportLanguage;
 
 use super::{Skill, SkillCache};
 
@@ -135,6 +136,13 @@ impl SkillSelector {
             if let Some(template) = &skill.template {
                 parts.push(format!("#### 模板\n{}\n\n", template));
             }
+
+            if let Some(lang) = skill.meta.report_language.filter(|l| *l != ReportLanguage::Auto) {
+                parts.push(format!(
+                    "生成报告时调用 generate_report 并传入 \"language\": \"{}\"\n",
+                    lang.as_str()
+                ));
+            }
         }
 
         parts.join("\n")
@@ -158,6 +166,7 @@ mod tests {
                 tags: vec![],
                 script: None,
                 script_type: None,
+                report_language: Some(ReportLanguage::Bilingual),
             },
             capability: "能力描述".to_string(),
             template: Some("模板内容".to_string()),
@@ -169,5 +178,6 @@ mod tests {
         assert!(prompt.contains("测试"));
         assert!(prompt.contains("能力描述"));
         assert!(prompt.contains("模板内容"));
+        assert!(prompt.contains("\"language\": \"bilingual\""));
     }
 }
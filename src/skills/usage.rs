@@ -0,0 +1,100 @@
+//! 基于用量的技能建议
+//!
+//! 根据助手的实际工具用量（ToolMetrics 按助手统计）建议调整 assistant_skills：
+//! 多次被拦截的技能范围外工具建议启用，调用量足够多却从未使用的已启用工具建议停用。
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::observability::ToolUsage;
+
+/// 被拦截达到该次数的工具建议启用
+const MIN_BLOCKED_TO_ENABLE: u64 = 2;
+/// 助手累计工具调用达到该次数后，才对从未使用的工具建议停用
+const MIN_CALLS_TO_DISABLE: u64 = 20;
+/// 始终保留、不建议停用的工具
+const ALWAYS_KEEP: &[&str] = &["tool_help"];
+
+/// 建议动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillSuggestionAction {
+    Enable,
+    Disable,
+}
+
+/// 单条技能建议
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkillSuggestion {
+    pub tool: String,
+    pub action: SkillSuggestionAction,
+    pub reason: String,
+}
+
+/// 按用量生成建议：enabled 为当前已启用工具，available 为全部已注册工具
+pub fn suggest_skill_changes(
+    enabled: &[String],
+    available: &[String],
+    usage: &HashMap<String, ToolUsage>,
+) -> Vec<SkillSuggestion> {
+    let total_calls: u64 = usage.values().map(|u| u.calls).sum();
+    let mut suggestions = Vec::new();
+
+    for tool in available {
+        let u = usage.get(tool).cloned().unwrap_or_default();
+        let is_enabled = enabled.contains(tool);
+        if !is_enabled && u.blocked >= MIN_BLOCKED_TO_ENABLE {
+            suggestions.push(SkillSuggestion {
+                tool: tool.clone(),
+                action: SkillSuggestionAction::Enable,
+                reason: format!("blocked {} times because it is not enabled", u.blocked),
+            });
+        } else if is_enabled
+            && u.calls == 0
+            && total_calls >= MIN_CALLS_TO_DISABLE
+            && !ALWAYS_KEEP.contains(&tool.as_str())
+        {
+            suggestions.push(SkillSuggestion {
+                tool: tool.clone(),
+                action: SkillSuggestionAction::Disable,
+                reason: format!("never used in {} tool calls", total_calls),
+            });
+        }
+    }
+    // 启用建议在前，同类按工具名排序
+    suggestions.sort_by(|a, b| {
+        (a.action != SkillSuggestionAction::Enable, &a.tool)
+            .cmp(&(b.action != SkillSuggestionAction::Enable, &b.tool))
+    });
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_skill_changes() {
+        let names = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let enabled = names(&["cat", "ls", "tool_help"]);
+        let available = names(&["cat", "ls", "tool_help", "shell", "search"]);
+        let mut usage = HashMap::new();
+        usage.insert("shell".to_string(), ToolUsage { calls: 0, failures: 0, blocked: 3, ..Default::default() });
+        usage.insert("search".to_string(), ToolUsage { calls: 0, failures: 0, blocked: 1, ..Default::default() });
+        usage.insert("cat".to_string(), ToolUsage { calls: 5, failures: 0, blocked: 0, ..Default::default() });
+
+        // 调用量不足时不建议停用
+        let s = suggest_skill_changes(&enabled, &available, &usage);
+        assert_eq!(s.len(), 1);
+        assert_eq!((s[0].tool.as_str(), s[0].action), ("shell", SkillSuggestionAction::Enable));
+
+        usage.get_mut("cat").unwrap().calls = 30;
+        let s = suggest_skill_changes(&enabled, &available, &usage);
+        let actions: Vec<_> = s.iter().map(|x| (x.tool.as_str(), x.action)).collect();
+        assert_eq!(
+            actions,
+            vec![("shell", SkillSuggestionAction::Enable), ("ls", SkillSuggestionAction::Disable)]
+        );
+    }
+}
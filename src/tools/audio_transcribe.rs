@@ -0,0 +1,284 @@
+//! 语音转写工具
+//!
+//! audio_transcribe 把 workspace 内的音频（如 WhatsApp 语音消息）转为文字：优先调用 OpenAI 兼容的
+//! /audio/transcriptions（multipart 上传），无 Key 或调用失败时回退到 [tools.audio_transcribe] local_command 配置的本机命令
+//! （如 whisper.cpp）。
+
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::config::AudioTranscribeSection;
+use crate::tools::filesystem::{fs_error, SafeFs};
+use crate::tools::{Tool, ToolError};
+
+const DEFAULT_TRANSCRIBE_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// 实际使用的后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Api,
+    Local,
+}
+
+impl Backend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Api => "api",
+            Backend::Local => "local",
+        }
+    }
+}
+
+/// 按扩展名得到音频 MIME 类型；不支持的格式返回 None
+fn audio_media_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "ogg" | "oga" | "opus" => Some("audio/ogg"),
+        "mp3" | "mpga" | "mpeg" => Some("audio/mpeg"),
+        "m4a" | "mp4" => Some("audio/mp4"),
+        "aac" => Some("audio/aac"),
+        "wav" => Some("audio/wav"),
+        "webm" => Some("audio/webm"),
+        "flac" => Some("audio/flac"),
+        "amr" => Some("audio/amr"),
+        _ => None,
+    }
+}
+
+/// local_command 的参数：{path} 替换为音频文件路径
+fn local_args(command: &[String], path: &Path) -> Vec<String> {
+    let path = path.to_string_lossy();
+    command.iter().skip(1).map(|arg| arg.replace("{path}", &path)).collect()
+}
+
+/// audio_transcribe 工具
+pub struct AudioTranscribeTool {
+    fs: SafeFs,
+    client: Client,
+    config: AudioTranscribeSection,
+}
+
+impl AudioTranscribeTool {
+    pub fn new(root_dir: impl AsRef<Path>, config: &AudioTranscribeSection) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self {
+            fs: SafeFs::new(root_dir),
+            client,
+            config: config.clone(),
+        }
+    }
+
+    fn api_key(&self) -> Option<String> {
+        std::env::var(&self.config.api_key_env)
+            .ok()
+            .filter(|k| !k.trim().is_empty())
+    }
+
+    /// 按配置与环境选择后端（auto 时有 Key 用转写 API，配置了 local_command 时作为回退）
+    fn backends(&self) -> Result<Vec<Backend>, ToolError> {
+        let has_key = self.api_key().is_some();
+        let has_local = !self.config.local_command.is_empty();
+        let backends = match self.config.backend.to_ascii_lowercase().as_str() {
+            "api" if has_key => vec![Backend::Api],
+            "api" => {
+                return Err(ToolError::Failed(format!(
+                    "audio_transcribe backend is api but {} is not set",
+                    self.config.api_key_env
+                )))
+            }
+            "local" => vec![Backend::Local],
+            _ => [(has_key, Backend::Api), (has_local, Backend::Local)]
+                .into_iter()
+                .filter_map(|(enabled, backend)| enabled.then_some(backend))
+                .collect(),
+        };
+        if backends.contains(&Backend::Local) && !has_local {
+            return Err(ToolError::Failed(
+                "audio_transcribe backend is local but [tools.audio_transcribe] local_command is empty".to_string(),
+            ));
+        }
+        if backends.is_empty() {
+            return Err(ToolError::Failed(format!(
+                "audio_transcribe needs {} or [tools.audio_transcribe] local_command",
+                self.config.api_key_env
+            )));
+        }
+        Ok(backends)
+    }
+
+    async fn transcribe_with_api(&self, file_name: &str, mime: &str, bytes: Vec<u8>) -> Result<String, String> {
+        let key = self.api_key().ok_or("no API key")?;
+        let base = self
+            .config
+            .base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_TRANSCRIBE_BASE_URL)
+            .trim_end_matches('/');
+        let file = Part::bytes(bytes)
+            .file_name(file_name.to_string())
+            .mime_str(mime)
+            .map_err(|e| e.to_string())?;
+        let mut form = Form::new()
+            .text("model", self.config.model.clone())
+            .text("response_format", "json")
+            .part("file", file);
+        if let Some(language) = self.config.language.as_deref().filter(|l| !l.trim().is_empty()) {
+            form = form.text("language", language.to_string());
+        }
+        let resp = self
+            .client
+            .post(format!("{}/audio/transcriptions", base))
+            .bearer_auth(key)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = resp.status();
+        let value: Value = resp.json().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            let msg = value
+                .pointer("/error/message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string());
+            return Err(format!("HTTP {}: {}", status.as_u16(), msg));
+        }
+        value
+            .get("text")
+            .and_then(|t| t.as_str())
+            .map(|t| t.trim().to_string())
+            .ok_or_else(|| "transcription response has no text".to_string())
+    }
+
+    async fn transcribe_locally(&self, path: &Path) -> Result<String, String> {
+        let program = &self.config.local_command[0];
+        let output = tokio::time::timeout(
+            Duration::from_secs(self.config.timeout_secs.max(1)),
+            tokio::process::Command::new(program)
+                .args(local_args(&self.config.local_command, path))
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await
+        .map_err(|_| format!("{} timed out after {}s", program, self.config.timeout_secs))?
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("{} not found", program),
+            _ => e.to_string(),
+        })?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+#[async_trait]
+impl Tool for AudioTranscribeTool {
+    fn name(&self) -> &str {
+        "audio_transcribe"
+    }
+
+    fn description(&self) -> &str {
+        "Transcribe an audio file in the workspace (voice note, recording) to text. Args: {\"path\": \"audio path relative to workspace (ogg/opus/mp3/m4a/aac/wav/webm/flac/amr)\"}"
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if path.is_empty() {
+            return Err(ToolError::InvalidArgs("Missing path".to_string()));
+        }
+        let resolved = self.fs.resolve(path).map_err(fs_error)?;
+        let mime = audio_media_type(&resolved)
+            .ok_or_else(|| ToolError::InvalidArgs(format!("Unsupported audio type: {}", path)))?;
+        let size = std::fs::metadata(&resolved).map(|m| m.len()).unwrap_or(0);
+        if size > self.config.max_audio_bytes {
+            return Err(ToolError::InvalidArgs(format!(
+                "{} is {} bytes, larger than the {} byte limit",
+                path, size, self.config.max_audio_bytes
+            )));
+        }
+        tracing::info!(path = %path, "audio_transcribe tool execute");
+
+        let mut errors = Vec::new();
+        for backend in self.backends()? {
+            let result = match backend {
+                Backend::Api => {
+                    let bytes = tokio::fs::read(&resolved)
+                        .await
+                        .map_err(|e| ToolError::Failed(format!("Read failed: {}", e)))?;
+                    let file_name = resolved.file_name().and_then(|n| n.to_str()).unwrap_or("audio");
+                    self.transcribe_with_api(file_name, mime, bytes)
+                        .await
+                        .map(|text| (self.config.model.as_str(), text))
+                }
+                Backend::Local => self.transcribe_locally(&resolved).await.map(|text| ("local", text)),
+            };
+            match result {
+                Ok((source, text)) => {
+                    let text = if text.is_empty() { "[no speech]" } else { text.as_str() };
+                    return Ok(format!("[{} · {}]\n{}", path, source, text));
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path, ?backend, error = %e, "audio_transcribe failed");
+                    errors.push(format!("{}: {}", backend.as_str(), e));
+                }
+            }
+        }
+        Err(ToolError::Failed(format!(
+            "audio_transcribe failed: {}",
+            errors.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_types_and_backend_selection() {
+        assert_eq!(audio_media_type(Path::new("inbox/whatsapp-1.OGG")), Some("audio/ogg"));
+        assert_eq!(audio_media_type(Path::new("a.m4a")), Some("audio/mp4"));
+        assert_eq!(audio_media_type(Path::new("a.png")), None);
+
+        let command = vec![
+            "whisper-cli".to_string(),
+            "-nt".to_string(),
+            "-f".to_string(),
+            "{path}".to_string(),
+        ];
+        assert_eq!(
+            local_args(&command, Path::new("/w/a.ogg")),
+            vec!["-nt", "-f", "/w/a.ogg"]
+        );
+
+        let config = AudioTranscribeSection {
+            api_key_env: "BEE_TEST_AUDIO_TRANSCRIBE_KEY_UNSET".to_string(),
+            ..Default::default()
+        };
+        assert!(AudioTranscribeTool::new(".", &config).backends().is_err());
+        let local = AudioTranscribeTool::new(
+            ".",
+            &AudioTranscribeSection {
+                local_command: command,
+                ..config.clone()
+            },
+        );
+        assert_eq!(local.backends().unwrap(), vec![Backend::Local]);
+        let api_only = AudioTranscribeTool::new(
+            ".",
+            &AudioTranscribeSection {
+                backend: "api".into(),
+                ..config
+            },
+        );
+        assert!(api_only.backends().is_err());
+    }
+}
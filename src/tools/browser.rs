@@ -2,6 +2,7 @@
 //!
 //! 需启用 feature "browser" 且系统已安装 Chrome/Chromium。
 //! 访问 URL、执行 JS 渲染后提取可读文本（适用于 Search 无法处理的动态页面）。
+//! 挂载 PolitePolicy 后导航前按域名限速、遵守 robots.txt，并覆盖标签页 User-Agent。
 //!
 //! ## 语义快照（Semantic Snapshot）
 //!
@@ -9,16 +10,50 @@
 //! - 降低 Token 开销（相比完整 HTML）
 //! - AI 能够精准定位并点击特定的 DOM 节点
 //! - 每个可交互元素都有唯一的引用 ID（如 [1], [2]）
+//! - 按父子关系缩进；filter 只保留可交互元素或指定角色，skip_boilerplate 跳过导航栏与页脚
+//!
+//! ## 多标签页与下载
+//!
+//! open / switch / close / tabs 管理命名标签页，各标签页独立保存元素映射，多页面调研互不覆盖；
+//! wait 等待选择器出现或导航完成；download 把文件保存到 workspace/downloads（按 URL 下载时带上标签页 Cookie）。
+//!
+//! ## 按助手隔离的配置目录
+//!
+//! 每个助手使用独立的浏览器实例与标签页；[tools.browser] profile / persist_cookies 开启后登录状态跨重启保留
+//! （见 browser_profile），login_state 检查某个白名单域名是否已登录。
+//! 实例由进程内共享的浏览器池管理：限制实例数、关闭空闲实例、崩溃后重新启动（见 browser_pool）。
 
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use headless_chrome::{Browser, Tab};
+use headless_chrome::browser::tab::point::Point;
+use headless_chrome::protocol::cdp::{Input, Network, Page, Runtime, DOM};
+use headless_chrome::Tab;
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::config::BrowserSection;
+use crate::tools::browser_pool::{BrowserPool, BrowserProfile};
+use crate::tools::browser_profile::{login_state_report, profile_key, CookieJar, StoredCookie};
+use crate::tools::{scoped_root, PolitePolicy, Tool, ToolError, CURRENT_ASSISTANT_ID, CURRENT_WORKSPACE};
+
+/// 同时打开的标签页上限
+const MAX_TABS: usize = 8;
+/// 单个下载文件的大小上限
+const MAX_DOWNLOAD_BYTES: u64 = 50 * 1024 * 1024;
+/// 下载（请求或点击后等待落盘）的最长时间
+const DOWNLOAD_WAIT: Duration = Duration::from_secs(60);
+/// wait 动作的默认与最大超时（毫秒）
+const DEFAULT_WAIT_MS: u64 = 10_000;
+const MAX_WAIT_MS: u64 = 60_000;
+/// headless_chrome 标签页的默认超时
+const TAB_DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+const NO_SESSION: &str = "No active browser session. Use navigate first.";
+const BROWSER_CRASHED: &str = "Browser process crashed and its tabs were closed. Use navigate to reopen the page.";
 
 /// 语义快照中的元素
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +84,79 @@ pub struct BrowserSession {
     pub current_url: String,
 }
 
+/// 命名标签页集合；navigate / click 等动作默认作用于活动标签页
+#[derive(Default)]
+pub struct BrowserTabs {
+    tabs: BTreeMap<String, BrowserSession>,
+    active: Option<String>,
+}
+
+impl BrowserTabs {
+    fn get(&self, name: Option<&str>) -> Option<&BrowserSession> {
+        self.tabs.get(name.or(self.active.as_deref())?)
+    }
+
+    fn get_mut(&mut self, name: Option<&str>) -> Result<&mut BrowserSession, String> {
+        let name = name
+            .or(self.active.as_deref())
+            .ok_or_else(|| NO_SESSION.to_string())?
+            .to_string();
+        self.tabs
+            .get_mut(&name)
+            .ok_or_else(|| format!("No tab named '{}'. Use tabs to list open tabs.", name))
+    }
+
+    /// 未使用的默认标签名 tab1、tab2…
+    fn next_name(&self) -> String {
+        (1..)
+            .map(|i| format!("tab{}", i))
+            .find(|n| !self.tabs.contains_key(n))
+            .expect("unbounded tab name search")
+    }
+
+    /// 加入（或替换）标签页并设为活动
+    fn insert(&mut self, name: String, session: BrowserSession) {
+        self.tabs.insert(name.clone(), session);
+        self.active = Some(name);
+    }
+
+    /// 移除标签页；关闭的是活动标签页时改为激活剩余的最后一个
+    fn close(&mut self, name: Option<&str>) -> Result<(String, BrowserSession), String> {
+        let name = name
+            .or(self.active.as_deref())
+            .ok_or_else(|| NO_SESSION.to_string())?
+            .to_string();
+        let session = self
+            .tabs
+            .remove(&name)
+            .ok_or_else(|| format!("No tab named '{}'", name))?;
+        if self.active.as_deref() == Some(name.as_str()) {
+            self.active = self.tabs.keys().next_back().cloned();
+        }
+        Ok((name, session))
+    }
+
+    /// 任一打开的标签页（用于读取 Cookie）
+    pub(crate) fn any_tab(&self) -> Option<Arc<Tab>> {
+        self.tabs.values().next().map(|s| Arc::clone(&s.tab))
+    }
+
+    fn describe(&self) -> String {
+        if self.tabs.is_empty() {
+            return "No tabs open".to_string();
+        }
+        let lines: Vec<String> = self
+            .tabs
+            .iter()
+            .map(|(name, s)| {
+                let marker = if self.active.as_deref() == Some(name.as_str()) { "*" } else { " " };
+                format!("{} {} — {}", marker, name, s.tab.get_url())
+            })
+            .collect();
+        format!("{} tab(s):\n{}", self.tabs.len(), lines.join("\n"))
+    }
+}
+
 /// 从 URL 提取域名（小写）
 fn extract_domain(url: &str) -> Option<String> {
     let url = url.trim();
@@ -114,16 +222,332 @@ fn build_text_representation(elements: &[SemanticElement]) -> String {
     lines.join("\n")
 }
 
+
+/// 不占层级的结构性角色：自身不输出，子节点上移一层
+const STRUCTURAL_ROLES: &[&str] = &["none", "unknown", "generic", "InlineTextBox", "LineBreak"];
+/// skip_boilerplate 时整棵跳过的样板区域（导航栏、页眉、页脚）
+const BOILERPLATE_ROLES: &[&str] = &["navigation", "banner", "contentinfo"];
+
+/// 语义快照过滤条件（snapshot / navigate / open 的 filter 与 skip_boilerplate 参数）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotFilter {
+    /// 只保留可交互元素
+    pub interactive_only: bool,
+    /// 只保留这些角色（小写）；为空时不按角色过滤
+    pub roles: HashSet<String>,
+    /// 跳过导航栏、页眉页脚等样板区域
+    pub skip_boilerplate: bool,
+}
+
+impl SnapshotFilter {
+    /// filter: "all"（默认）| "interactive" | 角色列表（数组或逗号分隔，如 "heading,link"）
+    pub fn from_args(args: &Value) -> Result<Self, ToolError> {
+        let mut filter = SnapshotFilter {
+            skip_boilerplate: args
+                .get("skip_boilerplate")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            ..Default::default()
+        };
+        let roles: Vec<String> = match args.get("filter") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(s)) => s.split(',').map(|r| r.trim().to_lowercase()).collect(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|r| r.trim().to_lowercase())
+                .collect(),
+            Some(_) => {
+                return Err(ToolError::InvalidArgs(
+                    "filter must be \"all\", \"interactive\" or a list of roles".to_string(),
+                ))
+            }
+        };
+        for role in roles.into_iter().filter(|r| !r.is_empty()) {
+            match role.as_str() {
+                "all" => {}
+                "interactive" => filter.interactive_only = true,
+                _ => {
+                    filter.roles.insert(role);
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    fn keeps(&self, role: &str, is_interactive: bool) -> bool {
+        (!self.interactive_only || is_interactive)
+            && (self.roles.is_empty() || self.roles.contains(&role.to_lowercase()))
+    }
+
+    fn is_active(&self) -> bool {
+        self.interactive_only || !self.roles.is_empty() || self.skip_boilerplate
+    }
+}
+
+/// 无障碍树节点（从 CDP AXNode 提取的字段）
+struct AxNode {
+    id: String,
+    parent: Option<String>,
+    children: Vec<String>,
+    ignored: bool,
+    role: String,
+    name: String,
+    value: Option<String>,
+    description: Option<String>,
+    backend_node_id: Option<i64>,
+}
+
+/// 按父子关系深度优先展开无障碍树
+///
+/// 深度为已输出祖先的数量：结构性节点、被忽略或被过滤掉的节点不占层级；
+/// 与父元素同名的 StaticText 视为重复文本省略。可交互元素按输出顺序编号。
+fn flatten_ax_tree(nodes: &[AxNode], filter: &SnapshotFilter) -> Vec<SemanticElement> {
+    let index: HashMap<&str, &AxNode> = nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let roots: Vec<&AxNode> = nodes
+        .iter()
+        .filter(|n| n.parent.as_deref().is_none_or(|p| !index.contains_key(p)))
+        .collect();
+
+    let mut elements = Vec::new();
+    let mut visited = HashSet::new();
+    let mut ref_id = 1usize;
+    // (节点, 深度, 最近输出祖先的名称)
+    let mut stack: Vec<(&AxNode, usize, String)> = roots.into_iter().rev().map(|n| (n, 0, String::new())).collect();
+    while let Some((node, depth, parent_name)) = stack.pop() {
+        if !visited.insert(node.id.as_str()) {
+            continue;
+        }
+        if filter.skip_boilerplate && BOILERPLATE_ROLES.contains(&node.role.as_str()) {
+            continue;
+        }
+        let is_interactive = is_interactive_role(&node.role);
+        let structural = node.ignored || STRUCTURAL_ROLES.contains(&node.role.as_str());
+        let duplicate_text = node.role == "StaticText" && node.name == parent_name;
+        let emitted = !structural && !duplicate_text && filter.keeps(&node.role, is_interactive);
+        if emitted {
+            elements.push(SemanticElement {
+                ref_id: if is_interactive { ref_id } else { 0 },
+                role: node.role.clone(),
+                name: node.name.clone(),
+                value: node.value.clone(),
+                description: node.description.clone(),
+                backend_node_id: node.backend_node_id,
+                is_interactive,
+                depth,
+            });
+            if is_interactive {
+                ref_id += 1;
+            }
+        }
+        let (child_depth, child_parent_name) = if emitted {
+            (depth + 1, node.name.clone())
+        } else {
+            (depth, parent_name)
+        };
+        for child in node.children.iter().rev().filter_map(|c| index.get(c.as_str())) {
+            stack.push((child, child_depth, child_parent_name.clone()));
+        }
+    }
+    elements
+}
+
+/// 解析下载链接：绝对地址直接使用，相对地址按标签页当前地址拼接
+fn resolve_url(base: Option<&str>, href: &str) -> Option<String> {
+    let href = href.trim();
+    match reqwest::Url::parse(href) {
+        Ok(url) => Some(url.to_string()),
+        Err(_) => reqwest::Url::parse(base?).ok()?.join(href).ok().map(|u| u.to_string()),
+    }
+}
+
+/// 只保留文件名中的安全字符，避免路径穿越
+fn sanitize_file_name(name: &str) -> String {
+    let name = std::path::Path::new(name.trim())
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') { c } else { '_' })
+        .collect();
+    cleaned.trim().trim_start_matches('.').to_string()
+}
+
+/// 下载文件名：优先用调用方指定的名字，其次 Content-Disposition，最后取 URL 末段
+fn download_file_name(requested: Option<&str>, content_disposition: Option<&str>, url: &str) -> String {
+    let from_header = content_disposition.and_then(|h| {
+        h.split(';')
+            .filter_map(|part| part.trim().strip_prefix("filename="))
+            .map(|v| v.trim_matches('"').to_string())
+            .next()
+    });
+    let from_url = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.path_segments()?.rfind(|s| !s.is_empty()).map(str::to_string));
+    [requested.map(str::to_string), from_header, from_url]
+        .into_iter()
+        .flatten()
+        .map(|n| sanitize_file_name(&n))
+        .find(|n| !n.is_empty())
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// 目标目录中不与已有文件重名的路径（report.pdf → report-1.pdf …）
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let path = Path::new(name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|i| dir.join(format!("{}-{}{}", stem, i, ext)))
+        .find(|p| !p.exists())
+        .expect("unbounded suffix search")
+}
+
+fn truncate_output(output: String, max_chars: usize) -> String {
+    if output.chars().count() > max_chars {
+        output.chars().take(max_chars).collect::<String>() + "\n...[truncated]"
+    } else {
+        output
+    }
+}
+
+/// 四边形（x1,y1..x4,y4）的中心点；面积为 0 时返回 None
+fn quad_center(quad: &[f64]) -> Option<Point> {
+    if quad.len() != 8 {
+        return None;
+    }
+    let xs = [quad[0], quad[2], quad[4], quad[6]];
+    let ys = [quad[1], quad[3], quad[5], quad[7]];
+    let span = |v: &[f64; 4]| v.iter().cloned().fold(f64::MIN, f64::max) - v.iter().cloned().fold(f64::MAX, f64::min);
+    if span(&xs) < 1.0 || span(&ys) < 1.0 {
+        return None;
+    }
+    Some(Point {
+        x: xs.iter().sum::<f64>() / 4.0,
+        y: ys.iter().sum::<f64>() / 4.0,
+    })
+}
+
+/// 快照引用对应的 DOM 节点（backend_node_id 在页面导航前保持稳定）
+struct NodeRef {
+    ref_id: usize,
+    backend_node_id: DOM::BackendNodeId,
+    object_id: Runtime::RemoteObjectId,
+}
+
+impl NodeRef {
+    fn resolve(tab: &Tab, ref_id: usize, element_map: &HashMap<usize, i64>) -> Result<Self, String> {
+        let backend_node_id = element_map
+            .get(&ref_id)
+            .and_then(|id| DOM::BackendNodeId::try_from(*id).ok())
+            .ok_or_else(|| format!("Element ref [{}] not found, take a new snapshot", ref_id))?;
+        let object_id = tab
+            .call_method(DOM::ResolveNode {
+                node_id: None,
+                backend_node_id: Some(backend_node_id),
+                object_group: None,
+                execution_context_id: None,
+            })
+            .ok()
+            .and_then(|r| r.object.object_id)
+            .ok_or_else(|| {
+                format!(
+                    "Element ref [{}] is no longer in the page (page changed?), take a new snapshot",
+                    ref_id
+                )
+            })?;
+        Ok(Self {
+            ref_id,
+            backend_node_id,
+            object_id,
+        })
+    }
+
+    /// 以节点为 this 调用 JS 函数，返回按值序列化的结果
+    fn call(&self, tab: &Tab, function: &str) -> Result<Option<Value>, String> {
+        let result = tab
+            .call_method(Runtime::CallFunctionOn {
+                function_declaration: function.to_string(),
+                object_id: Some(self.object_id.clone()),
+                arguments: None,
+                silent: Some(true),
+                return_by_value: Some(true),
+                generate_preview: None,
+                user_gesture: Some(true),
+                await_promise: None,
+                execution_context_id: None,
+                object_group: None,
+                throw_on_side_effect: None,
+                unique_context_id: None,
+                serialization_options: None,
+            })
+            .map_err(|e| format!("Element [{}] call failed: {}", self.ref_id, e))?;
+        if let Some(details) = result.exception_details {
+            return Err(format!("Element [{}] script error: {}", self.ref_id, details.text));
+        }
+        Ok(result.result.value)
+    }
+
+    /// 元素的简短描述（文本 / 值 / aria-label / 标签名）
+    fn label(&self, tab: &Tab) -> Option<String> {
+        self.call(
+            tab,
+            r#"function() {
+                const text = (this.innerText || this.value || this.getAttribute('aria-label') || this.tagName || '');
+                return String(text).trim().substring(0, 50);
+            }"#,
+        )
+        .ok()
+        .flatten()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .filter(|s| !s.is_empty())
+    }
+
+    fn scroll_into_view(&self, tab: &Tab) {
+        let _ = tab.call_method(DOM::ScrollIntoViewIfNeeded {
+            node_id: None,
+            backend_node_id: Some(self.backend_node_id),
+            object_id: None,
+            rect: None,
+        });
+    }
+
+    /// 元素内容盒（无内容盒时用边框盒）的视口中心点
+    fn center(&self, tab: &Tab) -> Option<Point> {
+        let model = tab
+            .call_method(DOM::GetBoxModel {
+                node_id: None,
+                backend_node_id: Some(self.backend_node_id),
+                object_id: None,
+            })
+            .ok()?
+            .model;
+        quad_center(&model.content).or_else(|| quad_center(&model.border))
+    }
+}
+
 /// Browser 工具：Headless Chrome 访问 URL、提取页面可读文本
 ///
 /// 支持两种模式：
 /// - 传统模式：提取页面文本内容
 /// - 语义快照模式：获取无障碍树，返回结构化语义文本
 pub struct BrowserTool {
-    allowed_domains: HashSet<String>,
+    allowed_domains: Arc<HashSet<String>>,
     max_result_chars: usize,
-    session: Arc<RwLock<Option<BrowserSession>>>,
-    browser: Arc<RwLock<Option<Browser>>>,
+    /// 助手 id -> 该助手的浏览器；with_profiles 后使用进程内共享的池
+    pool: Arc<BrowserPool>,
+    /// 配置目录根（workspace/browser_profiles）；未设置时不持久化
+    profiles_root: Option<PathBuf>,
+    settings: BrowserSection,
+    polite: Option<Arc<PolitePolicy>>,
+    /// 下载目录（workspace/downloads，隔离用户为其工作区下的同名目录）；未设置时 download 不可用
+    download_dir: Option<PathBuf>,
+    client: Client,
 }
 
 impl BrowserTool {
@@ -132,95 +556,446 @@ impl BrowserTool {
             .into_iter()
             .map(|s| s.to_lowercase())
             .collect();
+        let settings = BrowserSection::default();
         Self {
-            allowed_domains,
+            allowed_domains: Arc::new(allowed_domains),
             max_result_chars,
-            session: Arc::new(RwLock::new(None)),
-            browser: Arc::new(RwLock::new(None)),
+            pool: Arc::new(BrowserPool::new(
+                settings.max_instances,
+                Duration::from_secs(settings.idle_timeout_secs),
+            )),
+            profiles_root: None,
+            settings,
+            polite: None,
+            download_dir: None,
+            client: Client::builder()
+                .timeout(DOWNLOAD_WAIT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 挂载礼貌抓取策略（可与其它联网工具共享）
+    pub fn with_politeness(mut self, polite: Arc<PolitePolicy>) -> Self {
+        self.polite = Some(polite);
+        self
+    }
+
+    /// 设置下载目录，启用 download 动作
+    pub fn with_download_dir(mut self, dir: PathBuf) -> Self {
+        self.download_dir = Some(dir);
+        self
+    }
+
+    /// 启用按助手持久化的配置目录（root 下每个助手一个子目录），并改用进程内共享的浏览器池
+    pub fn with_profiles(mut self, root: PathBuf, settings: &BrowserSection) -> Self {
+        self.profiles_root = Some(root);
+        self.settings = settings.clone();
+        self.pool = BrowserPool::shared(settings);
+        self
+    }
+
+    /// 本次调用的下载目录：请求限定于用户工作区时为该工作区下的同名目录
+    fn download_dir(&self) -> Option<PathBuf> {
+        let dir = self.download_dir.as_ref()?;
+        Some(match (dir.parent(), dir.file_name()) {
+            (Some(root), Some(name)) => scoped_root(root).join(name),
+            _ => dir.clone(),
+        })
+    }
+
+    /// 当前用户与助手的浏览器（不在助手上下文中时为 default）；隔离用户取 CURRENT_WORKSPACE
+    /// （workspace/users/<user_id>/）的目录名区分
+    fn profile(&self) -> Arc<BrowserProfile> {
+        let assistant = CURRENT_ASSISTANT_ID
+            .try_with(|a| a.clone())
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "default".to_string());
+        let user = CURRENT_WORKSPACE
+            .try_with(|w| w.as_ref().and_then(|w| w.file_name()).map(|n| n.to_string_lossy().into_owned()))
+            .ok()
+            .flatten();
+        let key = profile_key(user.as_deref(), &assistant);
+        let dir = self.profiles_root.as_ref().map(|root| root.join(&key));
+        let pool_key = dir.as_ref().map(|d| d.display().to_string()).unwrap_or(key);
+        self.pool.profile(&pool_key, || {
+            BrowserProfile::new(
+                dir.as_ref().filter(|_| self.settings.profile).map(|d| d.join("chrome")),
+                dir.filter(|_| self.settings.persist_cookies)
+                    .map(|d| CookieJar::new(d.join("cookies.json"), Arc::clone(&self.allowed_domains))),
+            )
+        })
+    }
+
+    /// 导航前检查：robots.txt 与按域名限速；返回需覆盖的 User-Agent
+    async fn before_navigate(&self, url: &str) -> Result<Option<String>, ToolError> {
+        match self.polite {
+            Some(ref polite) => {
+                polite.acquire(url).await?;
+                Ok(Some(polite.user_agent().to_string()))
+            }
+            None => Ok(None),
         }
     }
 
-    fn is_allowed(&self, url: &str) -> Result<(), String> {
+    fn is_allowed(&self, url: &str) -> Result<(), ToolError> {
         let domain = extract_domain(url)
-            .ok_or_else(|| "Invalid or missing URL".to_string())?;
+            .ok_or_else(|| ToolError::InvalidArgs("Invalid or missing URL".to_string()))?;
         if self.allowed_domains.contains(&domain) {
             return Ok(());
         }
-        Err(format!("Domain not in allowlist: {}", domain))
+        Err(ToolError::PermissionDenied(format!("Domain not in allowlist: {}", domain)))
     }
 
-    /// 获取语义快照
-    pub fn get_semantic_snapshot(tab: &Arc<Tab>) -> Result<SemanticSnapshot, String> {
-        let url = tab.get_url();
-        let title = tab
-            .get_title()
-            .map_err(|e| format!("Get title failed: {}", e))?;
-
-        let ax_tree = tab
-            .call_method(headless_chrome::protocol::cdp::Accessibility::GetFullAXTree {
-                depth: Some(10),
-                frame_id: None,
-            })
-            .map_err(|e| format!("Get accessibility tree failed: {}", e))?;
+    /// 在阻塞线程中操作指定（缺省为活动）标签页；save_cookies 为 true 时操作成功后保存 Cookie
+    async fn with_tab<R, F>(&self, tab: Option<String>, save_cookies: bool, f: F) -> Result<R, ToolError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut BrowserSession) -> Result<R, String> + Send + 'static,
+    {
+        let profile = self.profile();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut guard = profile.tabs.write().map_err(|e| e.to_string())?;
+            if profile.reset_if_crashed(&mut guard) {
+                return Err(BROWSER_CRASHED.to_string());
+            }
+            let session = guard.get_mut(tab.as_deref())?;
+            let result = f(session)?;
+            if save_cookies {
+                profile.save_cookies(&session.tab);
+            }
+            Ok::<_, String>(result)
+        })
+        .await
+        .map_err(|e| format!("Task join: {}", e))??;
+        Ok(result)
+    }
 
-        let mut elements = Vec::new();
-        let mut element_map = HashMap::new();
-        let mut ref_id = 1usize;
+    /// navigate / open：在已有标签页导航，或新开命名标签页
+    async fn open_or_navigate(&self, args: &Value, open_new: bool) -> Result<String, ToolError> {
+        let url = args
+            .get("url")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        let tab_name = tab_arg(args);
+        if !open_new && url.is_none() {
+            return Err(ToolError::missing("url"));
+        }
+        let user_agent = match url {
+            Some(ref url) => {
+                self.is_allowed(url)?;
+                self.before_navigate(url).await?
+            }
+            None => None,
+        };
 
-        for node in &ax_tree.nodes {
-                let role = node.role.as_ref()
-                    .and_then(|r| r.value.as_ref())
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("unknown")
-                    .to_string();
+        let filter = SnapshotFilter::from_args(args)?;
+        let profile = self.profile();
+        let pool = Arc::clone(&self.pool);
+        let max_chars = self.max_result_chars;
+
+        tracing::info!(url = ?url, tab = ?tab_name, open_new, "browser navigate with semantic snapshot");
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut browser_guard = profile.browser.write().map_err(|e| e.to_string())?;
+            let mut tabs = profile.tabs.write().map_err(|e| e.to_string())?;
+            let crashed = BrowserProfile::discard_if_crashed(&mut browser_guard, &mut tabs);
+
+            let existing = if open_new {
+                None
+            } else {
+                tab_name
+                    .as_deref()
+                    .or(tabs.active.as_deref())
+                    .and_then(|name| tabs.tabs.get(name))
+                    .map(|s| Arc::clone(&s.tab))
+            };
+            let name = match (&tab_name, &existing) {
+                (Some(name), _) => name.clone(),
+                (None, Some(_)) => tabs.active.clone().unwrap_or_default(),
+                (None, None) => tabs.next_name(),
+            };
+            let tab = match existing {
+                Some(tab) => tab,
+                None => {
+                    if tabs.tabs.contains_key(&name) {
+                        return Err(format!("Tab '{}' already exists; use switch or navigate", name));
+                    }
+                    if tabs.tabs.len() >= MAX_TABS {
+                        return Err(format!("Too many open tabs (max {}); close some first", MAX_TABS));
+                    }
+                    profile.new_tab(&pool, &mut browser_guard)?
+                }
+            };
 
-                if role == "none" || role == "unknown" || role == "generic" {
-                    continue;
+            let (output, element_map) = match url {
+                Some(ref url) => {
+                    if let Some(ref ua) = user_agent {
+                        tab.set_user_agent(ua, None, None)
+                            .map_err(|e| format!("Set user agent failed: {}", e))?;
+                    }
+                    tab.navigate_to(url)
+                        .map_err(|e| format!("Navigate failed: {}", e))?;
+                    tab.wait_for_element("body")
+                        .map_err(|e| format!("Page load failed: {}", e))?;
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+                    profile.save_cookies(&tab);
+                    Self::snapshot_output(&tab, max_chars, &filter)?
                 }
+                None => ("(blank page)".to_string(), HashMap::new()),
+            };
+            tabs.insert(
+                name.clone(),
+                BrowserSession {
+                    tab,
+                    element_map,
+                    current_url: url.unwrap_or_default(),
+                },
+            );
+            let notice = if crashed {
+                "(browser had crashed and was restarted; previous tabs were closed)\n"
+            } else {
+                ""
+            };
+            Ok::<_, String>(format!("{}[tab: {}]\n{}", notice, name, output))
+        })
+        .await
+        .map_err(|e| format!("Task join: {}", e))??;
 
-                let name = node.name.as_ref()
-                    .and_then(|n| n.value.as_ref())
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
+        Ok(result)
+    }
 
-                let value = node.value.as_ref()
-                    .and_then(|v| v.value.as_ref())
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
+    /// 生成语义快照文本与可交互元素映射
+    fn snapshot_output(
+        tab: &Arc<Tab>,
+        max_chars: usize,
+        filter: &SnapshotFilter,
+    ) -> Result<(String, HashMap<usize, i64>), String> {
+        let snapshot = Self::get_semantic_snapshot(tab, filter)?;
 
-                let description = node.description.as_ref()
-                    .and_then(|d| d.value.as_ref())
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
+        let mut element_map = HashMap::new();
+        for elem in &snapshot.elements {
+            if elem.is_interactive {
+                if let Some(id) = elem.backend_node_id {
+                    element_map.insert(elem.ref_id, id);
+                }
+            }
+        }
+
+        let heading = if filter.is_active() {
+            format!("## Semantic Snapshot (filtered, {} elements)", snapshot.elements.len())
+        } else {
+            "## Semantic Snapshot".to_string()
+        };
+        let output = format!(
+            "# {}\nURL: {}\n\n{}\n{}",
+            snapshot.title, snapshot.url, heading, snapshot.text_representation
+        );
+        Ok((truncate_output(output, max_chars), element_map))
+    }
 
-                let backend_node_id = node.backend_dom_node_id.map(|id| id as i64);
-                let is_interactive = is_interactive_role(&role);
+    /// 直接请求下载链接（带上标签页的 Cookie，支持登录后的下载）
+    async fn download_url(
+        &self,
+        tab: Option<String>,
+        href: &str,
+        dir: PathBuf,
+        filename: Option<String>,
+    ) -> Result<String, ToolError> {
+        let current = self
+            .profile()
+            .tabs
+            .read()
+            .ok()
+            .and_then(|t| t.get(tab.as_deref()).map(|s| Arc::clone(&s.tab)));
+        let base = current.as_ref().map(|t| t.get_url());
+        let url = resolve_url(base.as_deref(), href)
+            .ok_or_else(|| ToolError::InvalidArgs(format!("Invalid download URL: {}", href)))?;
+        self.is_allowed(&url)?;
+        let user_agent = self.before_navigate(&url).await?;
+
+        let cookie_header = match current {
+            Some(tab) => {
+                let cookie_url = url.clone();
+                tokio::task::spawn_blocking(move || {
+                    tab.call_method(Network::GetCookies {
+                        urls: Some(vec![cookie_url]),
+                    })
+                    .map(|r| {
+                        r.cookies
+                            .iter()
+                            .map(|c| format!("{}={}", c.name, c.value))
+                            .collect::<Vec<_>>()
+                            .join("; ")
+                    })
+                    .unwrap_or_default()
+                })
+                .await
+                .map_err(|e| format!("Task join: {}", e))?
+            }
+            None => String::new(),
+        };
 
-                let depth = 0;
+        tracing::info!(url = %url, "browser download");
+        let mut req = self.client.get(&url);
+        if !cookie_header.is_empty() {
+            req = req.header(reqwest::header::COOKIE, cookie_header);
+        }
+        if let Some(ua) = user_agent {
+            req = req.header(reqwest::header::USER_AGENT, ua);
+        }
+        let mut resp = req
+            .send()
+            .await
+            .map_err(|e| ToolError::Transient(format!("Download failed: {}", e)))?;
+        let status = resp.status();
+        if status.is_server_error() || status.as_u16() == 429 {
+            return Err(ToolError::Transient(format!("HTTP {}", status)));
+        }
+        if status.as_u16() == 404 {
+            return Err(ToolError::NotFound(format!("HTTP {}", status)));
+        }
+        if !status.is_success() {
+            return Err(ToolError::Failed(format!("HTTP {}", status)));
+        }
+        if resp.content_length().is_some_and(|n| n > MAX_DOWNLOAD_BYTES) {
+            return Err(ToolError::Failed(format!(
+                "File too large (limit {} MB)",
+                MAX_DOWNLOAD_BYTES / 1024 / 1024
+            )));
+        }
+        let disposition = resp
+            .headers()
+            .get(reqwest::header::CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let name = download_file_name(filename.as_deref(), disposition.as_deref(), &url);
+
+        let mut body = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| ToolError::Transient(format!("Download failed: {}", e)))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() as u64 > MAX_DOWNLOAD_BYTES {
+                return Err(ToolError::Failed(format!(
+                    "File too large (limit {} MB)",
+                    MAX_DOWNLOAD_BYTES / 1024 / 1024
+                )));
+            }
+        }
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = unique_path(&dir, &name);
+        tokio::fs::write(&path, &body).await?;
+        Ok(format!("✓ Downloaded {} bytes to {}", body.len(), display_download(&dir, &path)))
+    }
 
-                if is_interactive {
-                    if let Some(id) = backend_node_id {
-                        element_map.insert(ref_id, id);
+    /// 点击页面元素触发下载（适用于由脚本生成的下载），等待文件写入下载目录
+    async fn download_by_click(
+        &self,
+        tab: Option<String>,
+        ref_id: usize,
+        dir: PathBuf,
+        filename: Option<String>,
+    ) -> Result<String, ToolError> {
+        self.with_tab(tab, false, move |session| {
+            std::fs::create_dir_all(&dir).map_err(|e| format!("Create download dir failed: {}", e))?;
+            session
+                .tab
+                .call_method(Page::SetDownloadBehavior {
+                    behavior: Page::SetDownloadBehaviorBehaviorOption::Allow,
+                    download_path: Some(dir.to_string_lossy().to_string()),
+                })
+                .map_err(|e| format!("Enable downloads failed: {}", e))?;
+            let list = || -> HashSet<String> {
+                std::fs::read_dir(&dir)
+                    .map(|entries| {
+                        entries
+                            .filter_map(|e| e.ok())
+                            .map(|e| e.file_name().to_string_lossy().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            let before = list();
+            Self::click_by_ref(&session.tab, ref_id, &session.element_map)?;
+
+            // 新文件出现且已无 .crdownload 临时文件即视为下载完成
+            let deadline = Instant::now() + DOWNLOAD_WAIT;
+            let downloaded = loop {
+                let now = list();
+                let pending = now.iter().any(|n| n.ends_with(".crdownload"));
+                if let Some(name) = now.difference(&before).find(|n| !n.ends_with(".crdownload")) {
+                    if !pending {
+                        break name.clone();
                     }
                 }
-
-                elements.push(SemanticElement {
-                    ref_id: if is_interactive { ref_id } else { 0 },
-                    role,
-                    name,
-                    value,
-                    description,
-                    backend_node_id,
-                    is_interactive,
-                    depth,
-                });
-
-                if is_interactive {
-                    ref_id += 1;
+                if Instant::now() >= deadline {
+                    return Err(format!(
+                        "No download finished within {}s after clicking [{}]",
+                        DOWNLOAD_WAIT.as_secs(),
+                        ref_id
+                    ));
                 }
-        }
+                std::thread::sleep(Duration::from_millis(500));
+            };
+
+            let mut path = dir.join(&downloaded);
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if size > MAX_DOWNLOAD_BYTES {
+                let _ = std::fs::remove_file(&path);
+                return Err(format!("File too large (limit {} MB)", MAX_DOWNLOAD_BYTES / 1024 / 1024));
+            }
+            if let Some(name) = filename.as_deref().map(sanitize_file_name).filter(|n| !n.is_empty()) {
+                let target = unique_path(&dir, &name);
+                std::fs::rename(&path, &target).map_err(|e| format!("Rename download failed: {}", e))?;
+                path = target;
+            }
+            Ok(format!("✓ Downloaded {} bytes to {}", size, display_download(&dir, &path)))
+        })
+        .await
+    }
+
+    /// 获取语义快照（按 filter 过滤）
+    pub fn get_semantic_snapshot(tab: &Arc<Tab>, filter: &SnapshotFilter) -> Result<SemanticSnapshot, String> {
+        let url = tab.get_url();
+        let title = tab
+            .get_title()
+            .map_err(|e| format!("Get title failed: {}", e))?;
 
+        let ax_tree = tab
+            .call_method(headless_chrome::protocol::cdp::Accessibility::GetFullAXTree {
+                depth: None,
+                frame_id: None,
+            })
+            .map_err(|e| format!("Get accessibility tree failed: {}", e))?;
+
+        let ax_value = |v: Option<&headless_chrome::protocol::cdp::Accessibility::AXValue>| {
+            v.and_then(|v| v.value.as_ref())
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+        let nodes: Vec<AxNode> = ax_tree
+            .nodes
+            .iter()
+            .map(|node| AxNode {
+                id: node.node_id.clone(),
+                parent: node.parent_id.clone(),
+                children: node.child_ids.clone().unwrap_or_default(),
+                ignored: node.ignored,
+                role: ax_value(node.role.as_ref()).unwrap_or_else(|| "unknown".to_string()),
+                name: ax_value(node.name.as_ref()).unwrap_or_default(),
+                value: ax_value(node.value.as_ref()),
+                description: ax_value(node.description.as_ref()),
+                backend_node_id: node.backend_dom_node_id.map(|id| id as i64),
+            })
+            .collect();
+
+        let elements = flatten_ax_tree(&nodes, filter);
         let text_representation = build_text_representation(&elements);
 
         Ok(SemanticSnapshot {
@@ -231,101 +1006,164 @@ impl BrowserTool {
         })
     }
 
-    /// 通过引用 ID 点击元素（使用 JavaScript 执行）
+    /// 通过引用 ID 点击元素：按快照中的 backend_node_id 定位，滚动到可见后在元素中心派发真实鼠标事件
     pub fn click_by_ref(tab: &Arc<Tab>, ref_id: usize, element_map: &HashMap<usize, i64>) -> Result<String, String> {
-        let _backend_node_id = element_map
-            .get(&ref_id)
-            .ok_or_else(|| format!("Element ref [{}] not found", ref_id))?;
-
-        let js = format!(
-            r#"
-            (function() {{
-                // 通过 CDP 获取 DOM 节点
-                return new Promise((resolve, reject) => {{
-                    // 使用 TreeWalker 遍历所有可交互元素，找到第 {} 个
-                    const interactiveRoles = ['button', 'link', 'textbox', 'checkbox', 'radio', 'combobox', 
-                        'listbox', 'menuitem', 'option', 'searchbox', 'slider', 'spinbutton', 'switch', 'tab', 'treeitem'];
-                    
-                    const allElements = document.querySelectorAll('button, a, input, select, textarea, [role]');
-                    let count = 0;
-                    for (const el of allElements) {{
-                        const role = el.getAttribute('role') || el.tagName.toLowerCase();
-                        const isInteractive = interactiveRoles.includes(role) || 
-                            ['button', 'a', 'input', 'select', 'textarea'].includes(el.tagName.toLowerCase());
-                        if (isInteractive) {{
-                            count++;
-                            if (count === {}) {{
-                                el.scrollIntoView({{ behavior: 'instant', block: 'center' }});
-                                el.click();
-                                resolve('clicked: ' + (el.textContent || el.value || el.tagName).substring(0, 50));
-                                return;
-                            }}
-                        }}
-                    }}
-                    reject('Element not found');
-                }});
-            }})()
-            "#,
-            ref_id, ref_id
-        );
-
-        let result = tab
-            .evaluate(&js, true)
-            .map_err(|e| format!("Click failed: {}", e))?;
-
-        if let Some(val) = result.value {
-            Ok(format!("Clicked element [{}]: {}", ref_id, val))
-        } else {
-            Ok(format!("Clicked element [{}]", ref_id))
+        let node = NodeRef::resolve(tab, ref_id, element_map)?;
+        let label = node.label(tab);
+        node.scroll_into_view(tab);
+
+        // 无布局盒（display:contents、零尺寸等）时退回元素自身的 click()
+        match node.center(tab) {
+            Some(point) => {
+                tab.click_point(point)
+                    .map_err(|e| format!("Click failed: {}", e))?;
+            }
+            None => {
+                node.call(tab, "function() { this.click(); }")?;
+            }
         }
+
+        Ok(match label {
+            Some(label) => format!("Clicked element [{}]: {}", ref_id, label),
+            None => format!("Clicked element [{}]", ref_id),
+        })
     }
 
-    /// 在元素中输入文本（使用 JavaScript 执行）
+    /// 在元素中输入文本：按 backend_node_id 聚焦并清空原内容，再通过 Input.insertText 输入
     pub fn type_text_by_ref(
         tab: &Arc<Tab>,
         ref_id: usize,
         text: &str,
         element_map: &HashMap<usize, i64>,
     ) -> Result<String, String> {
-        let _backend_node_id = element_map
-            .get(&ref_id)
-            .ok_or_else(|| format!("Element ref [{}] not found", ref_id))?;
-
-        let escaped_text = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
-
-        let js = format!(
-            r#"
-            (function() {{
-                const interactiveRoles = ['textbox', 'searchbox', 'combobox', 'spinbutton'];
-                const allElements = document.querySelectorAll('input, textarea, [role="textbox"], [role="searchbox"], [contenteditable="true"]');
-                let count = 0;
-                for (const el of allElements) {{
-                    count++;
-                    if (count === {}) {{
-                        el.scrollIntoView({{ behavior: 'instant', block: 'center' }});
-                        el.focus();
-                        if (el.tagName === 'INPUT' || el.tagName === 'TEXTAREA') {{
-                            el.value = "{}";
-                            el.dispatchEvent(new Event('input', {{ bubbles: true }}));
-                            el.dispatchEvent(new Event('change', {{ bubbles: true }}));
-                        }} else {{
-                            el.textContent = "{}";
-                            el.dispatchEvent(new Event('input', {{ bubbles: true }}));
-                        }}
-                        return 'typed';
-                    }}
-                }}
-                return 'element not found';
-            }})()
-            "#,
-            ref_id, escaped_text, escaped_text
-        );
+        let node = NodeRef::resolve(tab, ref_id, element_map)?;
+        node.scroll_into_view(tab);
+        tab.call_method(DOM::Focus {
+            node_id: None,
+            backend_node_id: Some(node.backend_node_id),
+            object_id: None,
+        })
+        .map_err(|e| format!("Focus element [{}] failed: {}", ref_id, e))?;
+
+        let editable = node.call(
+            tab,
+            r#"function() {
+                if (this.isContentEditable) {
+                    this.textContent = '';
+                } else if ('value' in this && !this.readOnly && !this.disabled) {
+                    this.value = '';
+                } else {
+                    return false;
+                }
+                this.dispatchEvent(new Event('input', { bubbles: true }));
+                return true;
+            }"#,
+        )?;
+        if editable != Some(Value::Bool(true)) {
+            return Err(format!("Element [{}] is not editable", ref_id));
+        }
 
-        tab.evaluate(&js, false)
+        tab.call_method(Input::InsertText { text: text.to_string() })
             .map_err(|e| format!("Type failed: {}", e))?;
+        node.call(
+            tab,
+            "function() { this.dispatchEvent(new Event('change', { bubbles: true })); }",
+        )?;
 
         Ok(format!("Typed \"{}\" into element [{}]", text, ref_id))
     }
+
+    /// 按登录类 Cookie（及可选的页面选择器）判断当前助手的浏览器是否已登录某个白名单域名
+    async fn login_state(
+        &self,
+        tab: Option<String>,
+        domain: Option<String>,
+        selector: Option<String>,
+    ) -> Result<String, ToolError> {
+        let allowed = Arc::clone(&self.allowed_domains);
+        self.with_tab(tab, false, move |session| {
+            let current = extract_domain(&session.tab.get_url());
+            let domain = domain
+                .or_else(|| current.clone())
+                .ok_or_else(|| "Missing domain (current page has none)".to_string())?;
+            if !allowed.contains(&domain) {
+                return Err(format!("Domain not in allowlist: {}", domain));
+            }
+            let cookies: Vec<StoredCookie> = session
+                .tab
+                .call_method(Network::GetCookies {
+                    urls: Some(vec![format!("https://{}/", domain)]),
+                })
+                .map_err(|e| format!("Read cookies failed: {}", e))?
+                .cookies
+                .iter()
+                .map(StoredCookie::from)
+                .collect();
+            let on_domain = current.as_deref() == Some(domain.as_str());
+            let found = selector
+                .as_deref()
+                .filter(|_| on_domain)
+                .map(|sel| (sel, session.tab.find_element(sel).is_ok()));
+            let mut report = login_state_report(&domain, &cookies, found);
+            if selector.is_some() && !on_domain {
+                report.push_str(&format!(
+                    "\n- selector not checked: current page is not on {} (navigate there first)",
+                    domain
+                ));
+            }
+            Ok(report)
+        })
+        .await
+    }
+
+    /// 切换活动标签页
+    fn switch_tab(&self, name: &str) -> Result<String, ToolError> {
+        let profile = self.profile();
+        let mut tabs = profile.tabs.write().map_err(|e| e.to_string())?;
+        if !tabs.tabs.contains_key(name) {
+            return Err(ToolError::NotFound(format!("No tab named '{}'\n{}", name, tabs.describe())));
+        }
+        tabs.active = Some(name.to_string());
+        let session = &tabs.tabs[name];
+        let title = session.tab.get_title().unwrap_or_default();
+        Ok(format!("Switched to tab '{}': {} ({})", name, title, session.tab.get_url()))
+    }
+
+    /// 关闭标签页（缺省为活动标签页）
+    async fn close_tab(&self, name: Option<String>) -> Result<String, ToolError> {
+        let profile = self.profile();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut tabs = profile.tabs.write().map_err(|e| e.to_string())?;
+            let (name, session) = tabs.close(name.as_deref())?;
+            profile.save_cookies(&session.tab);
+            let _ = session.tab.close(false);
+            Ok::<_, String>(match tabs.active {
+                Some(ref active) => format!("Closed tab '{}'; active tab is now '{}'", name, active),
+                None => format!("Closed tab '{}'; no tabs open", name),
+            })
+        })
+        .await
+        .map_err(|e| format!("Task join: {}", e))??;
+        Ok(result)
+    }
+}
+
+/// 可选的 tab 参数（标签页名）
+fn tab_arg(args: &Value) -> Option<String> {
+    args.get("tab")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// 下载文件相对 workspace 的展示路径（如 downloads/report.pdf）
+fn display_download(dir: &Path, path: &Path) -> String {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    match dir.file_name() {
+        Some(d) => format!("{}/{}", d.to_string_lossy(), name),
+        None => name,
+    }
 }
 
 #[async_trait]
@@ -338,12 +1176,26 @@ impl Tool for BrowserTool {
         r#"Headless browser with semantic snapshots for precise interaction.
 
 Actions:
-- navigate: Visit URL and get semantic snapshot
-  Args: {"action": "navigate", "url": "https://..."}
+- navigate: Visit URL in the active tab (or the tab named by "tab") and get semantic snapshot
+  Args: {"action": "navigate", "url": "https://...", "tab": "optional name"}
   Returns: Structured accessibility tree with interactive elements marked as [1], [2], etc.
 
+- open: Open a new named tab (becomes active), optionally visiting a URL
+  Args: {"action": "open", "tab": "docs", "url": "https://..."}
+
+- tabs: List open tabs (* marks the active tab)
+  Args: {"action": "tabs"}
+
+- switch: Make another tab active
+  Args: {"action": "switch", "tab": "docs"}
+
+- close: Close a tab (default: active tab)
+  Args: {"action": "close", "tab": "docs"}
+
 - snapshot: Get current page semantic snapshot (refresh element refs)
-  Args: {"action": "snapshot"}
+  Args: {"action": "snapshot", "filter": "interactive", "skip_boilerplate": true}
+  filter: "all" (default), "interactive", or roles like ["heading", "link"]; skip_boilerplate drops nav/header/footer.
+  navigate and open accept the same filter / skip_boilerplate args.
 
 - click: Click element by reference ID
   Args: {"action": "click", "ref": 1}
@@ -354,9 +1206,22 @@ Actions:
 - scroll: Scroll page
   Args: {"action": "scroll", "direction": "down"} (or "up")
 
+- wait: Wait for a CSS selector to appear and/or for navigation to finish
+  Args: {"action": "wait", "selector": ".results", "navigation": true, "timeout_ms": 10000}
+
+- download: Save a file into the workspace downloads/ folder, by URL (uses the tab's cookies) or by clicking a download link
+  Args: {"action": "download", "url": "https://.../report.pdf", "filename": "optional.pdf"}
+        {"action": "download", "ref": 3}
+
+- login_state: Check whether this assistant's browser is logged in to an allowlisted domain (default: current page's domain), by auth cookies and optionally a CSS selector only shown when logged in
+  Args: {"action": "login_state", "domain": "github.com", "selector": "optional CSS"}
+
 - content: Get page text content (legacy mode)
   Args: {"action": "content", "url": "...", "selector": "optional CSS"}
 
+snapshot / click / type / scroll / wait / download / login_state accept "tab" to target a non-active tab.
+Each assistant has its own browser; with [tools.browser] profile / persist_cookies enabled, logins survive restarts.
+
 The semantic snapshot shows interactive elements like:
   [1] button: "Submit"
   [2] textbox: "Search"
@@ -364,116 +1229,41 @@ The semantic snapshot shows interactive elements like:
 Use ref IDs to interact with elements precisely."#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let action = args
             .get("action")
             .and_then(|v| v.as_str())
             .unwrap_or("content");
+        let tab_name = tab_arg(&args);
 
         match action {
-            "navigate" => {
-                let url = args
-                    .get("url")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .trim();
-                if url.is_empty() {
-                    return Err("Missing url".to_string());
-                }
-                self.is_allowed(url)?;
-
-                let url = url.to_string();
-                let session_arc = Arc::clone(&self.session);
-                let browser_arc = Arc::clone(&self.browser);
-                let max_chars = self.max_result_chars;
-
-                tracing::info!(url = %url, "browser navigate with semantic snapshot");
-
-                let result = tokio::task::spawn_blocking(move || {
-                    let mut browser_guard = browser_arc.write().map_err(|e| e.to_string())?;
-                    if browser_guard.is_none() {
-                        let browser = Browser::default()
-                            .map_err(|e| format!("Chrome launch failed: {}", e))?;
-                        *browser_guard = Some(browser);
-                    }
-                    let browser = browser_guard.as_ref().unwrap();
-
-                    let tab = browser
-                        .new_tab()
-                        .map_err(|e| format!("Browser tab failed: {}", e))?;
-                    tab.navigate_to(&url)
-                        .map_err(|e| format!("Navigate failed: {}", e))?;
-                    tab.wait_for_element("body")
-                        .map_err(|e| format!("Page load failed: {}", e))?;
-
-                    std::thread::sleep(std::time::Duration::from_millis(500));
+            "navigate" => self.open_or_navigate(&args, false).await,
 
-                    let snapshot = Self::get_semantic_snapshot(&tab)?;
+            "open" => self.open_or_navigate(&args, true).await,
 
-                    let mut element_map = HashMap::new();
-                    for elem in &snapshot.elements {
-                        if elem.is_interactive {
-                            if let Some(id) = elem.backend_node_id {
-                                element_map.insert(elem.ref_id, id);
-                            }
-                        }
-                    }
-
-                    let mut session_guard = session_arc.write().map_err(|e| e.to_string())?;
-                    *session_guard = Some(BrowserSession {
-                        tab,
-                        element_map,
-                        current_url: url,
-                    });
-
-                    let output = format!(
-                        "# {}\nURL: {}\n\n## Semantic Snapshot\n{}",
-                        snapshot.title,
-                        snapshot.url,
-                        snapshot.text_representation
-                    );
-
-                    if output.len() > max_chars {
-                        Ok::<_, String>(output.chars().take(max_chars).collect::<String>() + "\n...[truncated]")
-                    } else {
-                        Ok(output)
-                    }
-                })
-                .await
-                .map_err(|e| format!("Task join: {}", e))??;
+            "tabs" => {
+                let profile = self.profile();
+                let tabs = profile.tabs.read().map_err(|e| e.to_string())?;
+                Ok(tabs.describe())
+            }
 
-                Ok(result)
+            "switch" => {
+                let name = tab_name.ok_or_else(|| ToolError::missing("tab"))?;
+                self.switch_tab(&name)
             }
 
+            "close" => self.close_tab(tab_name).await,
+
             "snapshot" => {
-                let session_arc = Arc::clone(&self.session);
                 let max_chars = self.max_result_chars;
-
-                let result = tokio::task::spawn_blocking(move || {
-                    let session_guard = session_arc.read().map_err(|e| e.to_string())?;
-                    let session = session_guard
-                        .as_ref()
-                        .ok_or_else(|| "No active browser session. Use navigate first.".to_string())?;
-
-                    let snapshot = Self::get_semantic_snapshot(&session.tab)?;
-
-                    let output = format!(
-                        "# {}\nURL: {}\n\n## Semantic Snapshot\n{}",
-                        snapshot.title,
-                        snapshot.url,
-                        snapshot.text_representation
-                    );
-
-                    if output.len() > max_chars {
-                        Ok::<_, String>(output.chars().take(max_chars).collect::<String>() + "\n...[truncated]")
-                    } else {
-                        Ok(output)
-                    }
+                let filter = SnapshotFilter::from_args(&args)?;
+                self.with_tab(tab_name, false, move |session| {
+                    let (output, element_map) = Self::snapshot_output(&session.tab, max_chars, &filter)?;
+                    session.element_map = element_map;
+                    session.current_url = session.tab.get_url();
+                    Ok(output)
                 })
                 .await
-                .map_err(|e| format!("Task join: {}", e))??;
-
-                Ok(result)
             }
 
             "click" => {
@@ -483,20 +1273,10 @@ Use ref IDs to interact with elements precisely."#
                     .ok_or_else(|| "Missing ref (element reference ID)".to_string())?
                     as usize;
 
-                let session_arc = Arc::clone(&self.session);
-
-                let result = tokio::task::spawn_blocking(move || {
-                    let session_guard = session_arc.read().map_err(|e| e.to_string())?;
-                    let session = session_guard
-                        .as_ref()
-                        .ok_or_else(|| "No active browser session. Use navigate first.".to_string())?;
-
+                self.with_tab(tab_name, true, move |session| {
                     Self::click_by_ref(&session.tab, ref_id, &session.element_map)
                 })
                 .await
-                .map_err(|e| format!("Task join: {}", e))??;
-
-                Ok(result)
             }
 
             "type" => {
@@ -508,52 +1288,112 @@ Use ref IDs to interact with elements precisely."#
                 let text = args
                     .get("text")
                     .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                let session_arc = Arc::clone(&self.session);
-                let text = text.to_string();
-
-                let result = tokio::task::spawn_blocking(move || {
-                    let session_guard = session_arc.read().map_err(|e| e.to_string())?;
-                    let session = session_guard
-                        .as_ref()
-                        .ok_or_else(|| "No active browser session. Use navigate first.".to_string())?;
+                    .unwrap_or("")
+                    .to_string();
 
+                self.with_tab(tab_name, false, move |session| {
                     Self::type_text_by_ref(&session.tab, ref_id, &text, &session.element_map)
                 })
                 .await
-                .map_err(|e| format!("Task join: {}", e))??;
-
-                Ok(result)
             }
 
             "scroll" => {
                 let direction = args
                     .get("direction")
                     .and_then(|v| v.as_str())
-                    .unwrap_or("down");
-
-                let session_arc = Arc::clone(&self.session);
-                let direction = direction.to_string();
-
-                let result = tokio::task::spawn_blocking(move || {
-                    let session_guard = session_arc.read().map_err(|e| e.to_string())?;
-                    let session = session_guard
-                        .as_ref()
-                        .ok_or_else(|| "No active browser session. Use navigate first.".to_string())?;
+                    .unwrap_or("down")
+                    .to_string();
 
+                self.with_tab(tab_name, false, move |session| {
                     let scroll_amount = if direction == "up" { -500 } else { 500 };
                     let js = format!("window.scrollBy(0, {})", scroll_amount);
                     session.tab
                         .evaluate(&js, false)
                         .map_err(|e| format!("Scroll failed: {}", e))?;
 
-                    Ok::<_, String>(format!("Scrolled {}", direction))
+                    Ok(format!("Scrolled {}", direction))
                 })
                 .await
-                .map_err(|e| format!("Task join: {}", e))??;
+            }
+
+            "wait" => {
+                let selector = args
+                    .get("selector")
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string);
+                let navigation = args.get("navigation").and_then(|v| v.as_bool()).unwrap_or(false);
+                if selector.is_none() && !navigation {
+                    return Err(ToolError::InvalidArgs(
+                        "wait needs a selector and/or \"navigation\": true".to_string(),
+                    ));
+                }
+                let timeout = Duration::from_millis(
+                    args.get("timeout_ms")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(DEFAULT_WAIT_MS)
+                        .min(MAX_WAIT_MS),
+                );
+
+                self.with_tab(tab_name, true, move |session| {
+                    if navigation {
+                        session.tab.set_default_timeout(timeout);
+                        let navigated = session.tab.wait_until_navigated().map(|_| ());
+                        session.tab.set_default_timeout(TAB_DEFAULT_TIMEOUT);
+                        navigated.map_err(|e| format!("Navigation did not finish: {}", e))?;
+                        session.current_url = session.tab.get_url();
+                    }
+                    if let Some(ref selector) = selector {
+                        session
+                            .tab
+                            .wait_for_element_with_custom_timeout(selector, timeout)
+                            .map_err(|e| format!("Timed out waiting for '{}': {}", selector, e))?;
+                    }
+                    Ok(format!(
+                        "Ready: {} (run snapshot to refresh element refs)",
+                        session.tab.get_url()
+                    ))
+                })
+                .await
+            }
+
+            "login_state" => {
+                let domain = args
+                    .get("domain")
+                    .and_then(|v| v.as_str())
+                    .map(|d| d.trim().to_lowercase())
+                    .filter(|d| !d.is_empty());
+                if let Some(ref domain) = domain {
+                    self.is_allowed(&format!("https://{}/", domain))?;
+                }
+                let selector = args
+                    .get("selector")
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string);
+                self.login_state(tab_name, domain, selector).await
+            }
 
-                Ok(result)
+            "download" => {
+                let dir = self
+                    .download_dir()
+                    .ok_or_else(|| ToolError::Failed("Downloads are not available (no workspace)".to_string()))?;
+                let filename = args
+                    .get("filename")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string);
+                if let Some(ref_id) = args.get("ref").and_then(|v| v.as_u64()) {
+                    return self.download_by_click(tab_name, ref_id as usize, dir, filename).await;
+                }
+                let url = args
+                    .get("url")
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .ok_or_else(|| ToolError::missing("url or ref"))?;
+                self.download_url(tab_name, url, dir, filename).await
             }
 
             "content" | _ => {
@@ -563,9 +1403,10 @@ Use ref IDs to interact with elements precisely."#
                     .unwrap_or("")
                     .trim();
                 if url.is_empty() {
-                    return Err("Missing url".to_string());
+                    return Err(ToolError::missing("url"));
                 }
                 self.is_allowed(url)?;
+                let user_agent = self.before_navigate(url).await?;
 
                 let selector = args.get("selector").and_then(|v| v.as_str()).map(|s| s.to_string());
                 let max_chars = self.max_result_chars;
@@ -573,36 +1414,43 @@ Use ref IDs to interact with elements precisely."#
 
                 tracing::info!(url = %url, selector = ?selector, "browser tool fetch content");
 
+                let profile = self.profile();
+                let pool = Arc::clone(&self.pool);
                 let text = tokio::task::spawn_blocking(move || {
-                    let browser = Browser::default()
-                        .map_err(|e| format!("Chrome launch failed: {}. Install Chrome/Chromium.", e))?;
-                    let tab = browser
-                        .new_tab()
-                        .map_err(|e| format!("Browser tab failed: {}", e))?;
-                    tab.navigate_to(&url)
-                        .map_err(|e| format!("Navigate failed: {}", e))?;
-                    tab.wait_for_element("body")
-                        .map_err(|e| format!("Page load failed: {}", e))?;
-
-                    let text = if let Some(sel) = selector {
-                        let el = tab
-                            .wait_for_element(&sel)
-                            .map_err(|e| format!("Element not found: {}", e))?;
-                        el.get_inner_text()
-                            .map_err(|e| format!("Get text failed: {}", e))?
-                    } else {
-                        let content = tab
-                            .get_content()
-                            .map_err(|e| format!("Get content failed: {}", e))?;
-                        html2text::from_read(content.as_bytes(), 120).unwrap_or_else(|_| content)
+                    // 在助手的浏览器中开临时标签页，读取后关闭（不占命名标签页）
+                    let tab = {
+                        let mut browser = profile.browser.write().map_err(|e| e.to_string())?;
+                        let mut tabs = profile.tabs.write().map_err(|e| e.to_string())?;
+                        BrowserProfile::discard_if_crashed(&mut browser, &mut tabs);
+                        profile.new_tab(&pool, &mut browser)?
                     };
-
-                    let len = text.chars().count();
-                    if len > max_chars {
-                        Ok::<_, String>(text.chars().take(max_chars).collect::<String>() + "\n...[truncated]")
-                    } else {
-                        Ok(text)
-                    }
+                    let text = (|| {
+                        if let Some(ref ua) = user_agent {
+                            tab.set_user_agent(ua, None, None)
+                                .map_err(|e| format!("Set user agent failed: {}", e))?;
+                        }
+                        tab.navigate_to(&url)
+                            .map_err(|e| format!("Navigate failed: {}", e))?;
+                        tab.wait_for_element("body")
+                            .map_err(|e| format!("Page load failed: {}", e))?;
+
+                        let text = if let Some(sel) = selector {
+                            let el = tab
+                                .wait_for_element(&sel)
+                                .map_err(|e| format!("Element not found: {}", e))?;
+                            el.get_inner_text()
+                                .map_err(|e| format!("Get text failed: {}", e))?
+                        } else {
+                            let content = tab
+                                .get_content()
+                                .map_err(|e| format!("Get content failed: {}", e))?;
+                            html2text::from_read(content.as_bytes(), 120).unwrap_or(content)
+                        };
+
+                        Ok::<_, String>(truncate_output(text, max_chars))
+                    })();
+                    let _ = tab.close(false);
+                    text
                 })
                 .await
                 .map_err(|e| format!("Task join: {}", e))??;
@@ -612,3 +1460,93 @@ Use ref IDs to interact with elements precisely."#
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_naming() {
+        assert_eq!(
+            resolve_url(Some("https://docs.rs/crate/bee/latest"), "../files/report.pdf").as_deref(),
+            Some("https://docs.rs/crate/files/report.pdf")
+        );
+        assert_eq!(resolve_url(None, "report.pdf"), None);
+
+        let url = "https://arxiv.org/pdf/2401.00001v2";
+        assert_eq!(download_file_name(None, None, url), "2401.00001v2");
+        assert_eq!(
+            download_file_name(None, Some("attachment; filename=\"paper final.pdf\""), url),
+            "paper final.pdf"
+        );
+        assert_eq!(download_file_name(Some("../../etc/passwd"), None, url), "passwd");
+        assert_eq!(download_file_name(Some(".."), None, "https://x.org/"), "download");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.pdf"), b"x").unwrap();
+        assert_eq!(unique_path(dir.path(), "a.pdf"), dir.path().join("a-1.pdf"));
+        assert_eq!(unique_path(dir.path(), "b.pdf"), dir.path().join("b.pdf"));
+    }
+
+    #[test]
+    fn test_quad_center() {
+        let point = quad_center(&[10.0, 20.0, 110.0, 20.0, 110.0, 60.0, 10.0, 60.0]).unwrap();
+        assert_eq!((point.x, point.y), (60.0, 40.0));
+        assert!(quad_center(&[5.0, 5.0, 5.0, 5.0, 5.0, 9.0, 5.0, 9.0]).is_none());
+        assert!(quad_center(&[1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn test_ax_tree_depth_and_filters() {
+        let node = |id: &str, parent: Option<&str>, children: &[&str], role: &str, name: &str| AxNode {
+            id: id.into(),
+            parent: parent.map(str::to_string),
+            children: children.iter().map(|c| c.to_string()).collect(),
+            ignored: false,
+            role: role.into(),
+            name: name.into(),
+            value: None,
+            description: None,
+            backend_node_id: Some(id.len() as i64),
+        };
+        let nodes = vec![
+            node("1", None, &["2", "4", "9"], "RootWebArea", "Page"),
+            node("2", Some("1"), &["3"], "navigation", ""),
+            node("3", Some("2"), &[], "link", "Home"),
+            node("4", Some("1"), &["5"], "main", ""),
+            node("5", Some("4"), &["6", "8"], "generic", ""),
+            node("6", Some("5"), &["7"], "heading", "Title"),
+            node("7", Some("6"), &[], "StaticText", "Title"),
+            node("8", Some("5"), &[], "button", "Go"),
+            node("9", Some("1"), &["10"], "contentinfo", ""),
+            node("10", Some("9"), &[], "link", "Privacy"),
+        ];
+        let summary = |filter: &SnapshotFilter| -> Vec<(String, usize, usize)> {
+            flatten_ax_tree(&nodes, filter)
+                .into_iter()
+                .map(|e| (e.role, e.depth, e.ref_id))
+                .collect()
+        };
+        let s = |role: &str, depth, ref_id| (role.to_string(), depth, ref_id);
+        assert_eq!(
+            summary(&SnapshotFilter::default()),
+            vec![
+                s("RootWebArea", 0, 0),
+                s("navigation", 1, 0),
+                s("link", 2, 1),
+                s("main", 1, 0),
+                s("heading", 2, 0),
+                s("button", 2, 2),
+                s("contentinfo", 1, 0),
+                s("link", 2, 3),
+            ]
+        );
+
+        let filter = SnapshotFilter::from_args(&serde_json::json!({"filter": "interactive", "skip_boilerplate": true}))
+            .unwrap();
+        assert_eq!(summary(&filter), vec![s("button", 0, 1)]);
+        let filter = SnapshotFilter::from_args(&serde_json::json!({"filter": ["Heading", "link"]})).unwrap();
+        assert_eq!(summary(&filter), vec![s("link", 0, 1), s("heading", 0, 0), s("link", 0, 2)]);
+        assert!(SnapshotFilter::from_args(&serde_json::json!({"filter": 3})).is_err());
+    }
+}
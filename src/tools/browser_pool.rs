@@ -0,0 +1,302 @@
+//! Browser 工具的浏览器池：按助手复用 Chrome 实例并管理其生命周期
+//!
+//! 池在进程内共享（网关多会话、配置重载重建工具时复用同一批实例），长时间运行时内存有上界：
+//! - 同时运行的实例数受 [tools.browser] max_instances 限制，启动新实例前关闭最久未用的空闲实例；
+//! - 空闲超过 idle_timeout_secs 的实例由后台线程关闭（Cookie 先落盘，下次使用时重新启动）；
+//! - 使用前检查 DevTools 连接，Chrome 崩溃时丢弃其标签页，下次打开页面时重新启动。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once, OnceLock, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+use headless_chrome::{Browser, LaunchOptions, Tab};
+
+use crate::config::BrowserSection;
+use crate::tools::browser::BrowserTabs;
+use crate::tools::browser_profile::CookieJar;
+
+static SHARED: OnceLock<Arc<BrowserPool>> = OnceLock::new();
+
+/// 单个助手的浏览器实例与标签页
+pub(crate) struct BrowserProfile {
+    pub(crate) browser: RwLock<Option<Browser>>,
+    pub(crate) tabs: RwLock<BrowserTabs>,
+    /// 持久的 user-data-dir；None 时每次启动使用临时目录
+    user_data_dir: Option<PathBuf>,
+    pub(crate) cookies: Option<CookieJar>,
+    last_used: Mutex<Instant>,
+}
+
+impl BrowserProfile {
+    pub(crate) fn new(user_data_dir: Option<PathBuf>, cookies: Option<CookieJar>) -> Self {
+        Self {
+            browser: RwLock::new(None),
+            tabs: RwLock::new(BrowserTabs::default()),
+            user_data_dir,
+            cookies,
+            last_used: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub(crate) fn launch(&self) -> Result<Browser, String> {
+        let browser = match self.user_data_dir {
+            Some(ref dir) => {
+                std::fs::create_dir_all(dir).map_err(|e| format!("Create browser profile failed: {}", e))?;
+                let options = LaunchOptions::default_builder()
+                    .user_data_dir(Some(dir.clone()))
+                    .build()
+                    .map_err(|e| format!("Chrome launch options: {}", e))?;
+                Browser::new(options)
+            }
+            None => Browser::default(),
+        };
+        browser.map_err(|e| format!("Chrome launch failed: {}. Install Chrome/Chromium.", e))
+    }
+
+    /// 在 Chrome 中新开标签页；未运行时先向池申请名额再启动，并恢复保存的登录 Cookie
+    pub(crate) fn new_tab(
+        self: &Arc<Self>,
+        pool: &BrowserPool,
+        browser: &mut Option<Browser>,
+    ) -> Result<Arc<Tab>, String> {
+        let launched = browser.is_none();
+        if launched {
+            pool.reserve_slot(self)?;
+            *browser = Some(self.launch()?);
+        }
+        let tab = browser
+            .as_ref()
+            .expect("browser launched above")
+            .new_tab()
+            .map_err(|e| format!("Browser tab failed: {}", e))?;
+        if let Some(jar) = self.cookies.as_ref().filter(|_| launched) {
+            match jar.restore(&tab) {
+                Ok(n) => tracing::debug!(restored = n, "browser cookies restored"),
+                Err(e) => tracing::warn!(error = %e, "browser cookie restore failed"),
+            }
+        }
+        Ok(tab)
+    }
+
+    /// 保存 Cookie；失败只记录日志，不影响当前操作
+    pub(crate) fn save_cookies(&self, tab: &Tab) {
+        if let Some(ref jar) = self.cookies {
+            if let Err(e) = jar.save(tab) {
+                tracing::warn!(error = %e, "browser cookie persistence failed");
+            }
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used.lock().unwrap_or_else(|e| e.into_inner()).elapsed()
+    }
+
+    /// 是否有运行中的 Chrome（正被其它操作占用时按运行中计）
+    fn is_running(&self) -> bool {
+        self.browser.try_read().map(|b| b.is_some()).unwrap_or(true)
+    }
+
+    /// Chrome 已退出或 DevTools 连接断开时丢弃实例与全部标签页，返回是否发生过崩溃
+    pub(crate) fn discard_if_crashed(browser: &mut Option<Browser>, tabs: &mut BrowserTabs) -> bool {
+        let crashed = browser.as_ref().is_some_and(|b| b.get_version().is_err());
+        if crashed {
+            tracing::warn!("browser process crashed or disconnected, discarding its tabs");
+            *tabs = BrowserTabs::default();
+            *browser = None;
+        }
+        crashed
+    }
+
+    /// 已持有标签页锁时的崩溃检查（实例正被占用时跳过）
+    pub(crate) fn reset_if_crashed(&self, tabs: &mut BrowserTabs) -> bool {
+        match self.browser.try_write() {
+            Ok(mut browser) => Self::discard_if_crashed(&mut browser, tabs),
+            Err(_) => false,
+        }
+    }
+
+    /// 关闭空闲的 Chrome（先保存 Cookie）；实例正被占用或未运行时返回 false
+    fn shut_down(&self) -> bool {
+        let (Ok(mut browser), Ok(mut tabs)) = (self.browser.try_write(), self.tabs.try_write()) else {
+            return false;
+        };
+        if browser.is_none() {
+            return false;
+        }
+        if let Some(tab) = tabs.any_tab() {
+            self.save_cookies(&tab);
+        }
+        *tabs = BrowserTabs::default();
+        *browser = None;
+        true
+    }
+}
+
+/// 按助手复用的 Chrome 实例池
+pub struct BrowserPool {
+    profiles: Mutex<HashMap<String, Arc<BrowserProfile>>>,
+    max_instances: AtomicUsize,
+    idle_timeout_secs: AtomicU64,
+    reaper: Once,
+}
+
+impl BrowserPool {
+    pub fn new(max_instances: usize, idle_timeout: Duration) -> Self {
+        Self {
+            profiles: Mutex::new(HashMap::new()),
+            max_instances: AtomicUsize::new(max_instances.max(1)),
+            idle_timeout_secs: AtomicU64::new(idle_timeout.as_secs().max(1)),
+            reaper: Once::new(),
+        }
+    }
+
+    /// 进程内共享的池；已创建时按新配置更新上限与空闲超时
+    pub fn shared(settings: &BrowserSection) -> Arc<Self> {
+        let pool = SHARED.get_or_init(|| {
+            Arc::new(Self::new(
+                settings.max_instances,
+                Duration::from_secs(settings.idle_timeout_secs),
+            ))
+        });
+        pool.configure(settings);
+        Arc::clone(pool)
+    }
+
+    pub fn configure(&self, settings: &BrowserSection) {
+        self.max_instances
+            .store(settings.max_instances.max(1), Ordering::Relaxed);
+        self.idle_timeout_secs
+            .store(settings.idle_timeout_secs.max(1), Ordering::Relaxed);
+    }
+
+    fn max_instances(&self) -> usize {
+        self.max_instances.load(Ordering::Relaxed)
+    }
+
+    fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs.load(Ordering::Relaxed))
+    }
+
+    /// 取（或创建）某个助手的浏览器并标记为刚使用；首次调用时启动空闲回收线程
+    pub(crate) fn profile(self: &Arc<Self>, key: &str, make: impl FnOnce() -> BrowserProfile) -> Arc<BrowserProfile> {
+        self.reaper.call_once(|| self.spawn_reaper());
+        let mut profiles = self.profiles.lock().unwrap_or_else(|e| e.into_inner());
+        let profile = Arc::clone(profiles.entry(key.to_string()).or_insert_with(|| Arc::new(make())));
+        profile.touch();
+        profile
+    }
+
+    fn snapshot(&self) -> Vec<(String, Arc<BrowserProfile>)> {
+        let profiles = self.profiles.lock().unwrap_or_else(|e| e.into_inner());
+        profiles.iter().map(|(k, p)| (k.clone(), Arc::clone(p))).collect()
+    }
+
+    /// 运行中的 Chrome 实例数
+    pub fn running(&self) -> usize {
+        self.snapshot().iter().filter(|(_, p)| p.is_running()).count()
+    }
+
+    /// 为 owner 启动新实例腾出名额：已满时关闭最久未用的空闲实例，全部占用时返回错误
+    pub(crate) fn reserve_slot(&self, owner: &Arc<BrowserProfile>) -> Result<(), String> {
+        let max = self.max_instances();
+        let mut others: Vec<(String, Arc<BrowserProfile>)> = self
+            .snapshot()
+            .into_iter()
+            .filter(|(_, p)| !Arc::ptr_eq(p, owner) && p.is_running())
+            .collect();
+        if others.len() < max {
+            return Ok(());
+        }
+        others.sort_by_key(|(_, p)| std::cmp::Reverse(p.idle_for()));
+        for (key, profile) in others {
+            if profile.shut_down() {
+                tracing::info!(profile = %key, "browser pool full, closed least recently used browser");
+                return Ok(());
+            }
+        }
+        Err(format!(
+            "Browser pool is full ({} instances in use); try again later",
+            max
+        ))
+    }
+
+    /// 关闭空闲超时的实例并清理已崩溃的实例，返回关闭的数量
+    pub fn reap(&self) -> usize {
+        let idle_timeout = self.idle_timeout();
+        let mut closed = 0;
+        for (key, profile) in self.snapshot() {
+            if profile.idle_for() >= idle_timeout {
+                if profile.shut_down() {
+                    tracing::info!(profile = %key, "browser closed after idle timeout");
+                    closed += 1;
+                }
+            } else if let (Ok(mut browser), Ok(mut tabs)) = (profile.browser.try_write(), profile.tabs.try_write()) {
+                if BrowserProfile::discard_if_crashed(&mut browser, &mut tabs) {
+                    closed += 1;
+                }
+            }
+        }
+        closed
+    }
+
+    /// 后台回收线程；池被释放后自动退出
+    fn spawn_reaper(self: &Arc<Self>) {
+        let pool: Weak<Self> = Arc::downgrade(self);
+        let spawned = std::thread::Builder::new()
+            .name("browser-pool-reaper".into())
+            .spawn(move || loop {
+                let interval = match pool.upgrade() {
+                    Some(pool) => (pool.idle_timeout() / 4).clamp(Duration::from_secs(1), Duration::from_secs(60)),
+                    None => break,
+                };
+                std::thread::sleep(interval);
+                match pool.upgrade() {
+                    Some(pool) => {
+                        pool.reap();
+                    }
+                    None => break,
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!(error = %e, "browser pool reaper thread failed to start");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_profiles_and_limits() {
+        let pool = Arc::new(BrowserPool::new(0, Duration::from_secs(0)));
+        assert_eq!(pool.max_instances(), 1);
+        assert_eq!(pool.idle_timeout(), Duration::from_secs(1));
+
+        let coder = pool.profile("coder", || BrowserProfile::new(None, None));
+        let again = pool.profile("coder", || unreachable!("profile is reused"));
+        assert!(Arc::ptr_eq(&coder, &again));
+        let writer = pool.profile("writer", || BrowserProfile::new(None, None));
+        assert!(!Arc::ptr_eq(&coder, &writer));
+
+        // 尚未启动 Chrome：不占名额，也没有可回收的实例
+        assert_eq!(pool.running(), 0);
+        assert!(pool.reserve_slot(&coder).is_ok());
+        assert_eq!(pool.reap(), 0);
+        assert!(!coder.shut_down());
+
+        pool.configure(&BrowserSection {
+            max_instances: 3,
+            idle_timeout_secs: 120,
+            ..Default::default()
+        });
+        assert_eq!(pool.max_instances(), 3);
+        assert_eq!(pool.idle_timeout(), Duration::from_secs(120));
+    }
+}
@@ -0,0 +1,269 @@
+//! Browser 工具的按助手配置目录与 Cookie 存储
+//!
+//! 每个助手使用独立的浏览器实例；开启 [tools.browser] profile 时使用持久的 user-data-dir
+//! （workspace/browser_profiles/<助手>/chrome），开启 persist_cookies 时把白名单域名的 Cookie
+//! （含会话 Cookie）写入 cookies.json，下次启动浏览器时恢复，登录状态跨重启保留。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use headless_chrome::protocol::cdp::Network;
+use headless_chrome::Tab;
+use serde::{Deserialize, Serialize};
+
+use crate::memory::scope::sanitize_segment;
+
+/// 名称中含这些片段的 Cookie 视为登录凭据
+const AUTH_COOKIE_HINTS: &[&str] = &[
+    "session",
+    "sess",
+    "sid",
+    "auth",
+    "token",
+    "login",
+    "logged_in",
+    "jwt",
+    "remember",
+];
+
+/// 持久化的 Cookie（与 CDP 类型解耦，便于序列化）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    /// 过期时间（Unix 秒）；None 为会话 Cookie
+    pub expires: Option<f64>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl From<&Network::Cookie> for StoredCookie {
+    fn from(c: &Network::Cookie) -> Self {
+        Self {
+            name: c.name.clone(),
+            value: c.value.clone(),
+            domain: c.domain.clone(),
+            path: c.path.clone(),
+            expires: (!c.session && c.expires > 0.0).then_some(c.expires),
+            secure: c.secure,
+            http_only: c.http_only,
+        }
+    }
+}
+
+impl StoredCookie {
+    fn to_param(&self) -> Network::CookieParam {
+        Network::CookieParam {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            url: None,
+            domain: Some(self.domain.clone()),
+            path: Some(self.path.clone()),
+            secure: Some(self.secure),
+            http_only: Some(self.http_only),
+            same_site: None,
+            expires: self.expires,
+            priority: None,
+            same_party: None,
+            source_scheme: None,
+            source_port: None,
+            partition_key: None,
+        }
+    }
+
+    fn is_expired(&self, now: f64) -> bool {
+        self.expires.is_some_and(|e| e <= now)
+    }
+
+    fn looks_like_auth(&self) -> bool {
+        let name = self.name.to_lowercase();
+        !self.value.is_empty() && AUTH_COOKIE_HINTS.iter().any(|h| name.contains(h))
+    }
+}
+
+/// Cookie 域（如 .google.com）是否覆盖白名单中的某个主机
+pub fn cookie_domain_allowed(cookie_domain: &str, allowed: &HashSet<String>) -> bool {
+    let domain = cookie_domain.trim_start_matches('.').to_lowercase();
+    allowed
+        .iter()
+        .any(|host| *host == domain || host.ends_with(&format!(".{}", domain)))
+}
+
+/// 助手 id 转为安全的目录名（与记忆目录同样清洗，不同 id 不共用目录）；空 id 为 default。
+/// 隔离用户（user 为 Some）的配置加上 `<user>+` 前缀，同一助手的不同用户不共用 Cookie 与登录态
+pub fn profile_key(user: Option<&str>, assistant_id: &str) -> String {
+    let assistant = sanitize_segment(assistant_id).unwrap_or_else(|| "default".to_string());
+    match user.and_then(sanitize_segment) {
+        Some(user) => format!("{}+{}", user, assistant),
+        None => assistant,
+    }
+}
+
+fn now_secs() -> f64 {
+    chrono::Utc::now().timestamp() as f64
+}
+
+/// 某个助手的 Cookie 文件（只保存白名单域名的 Cookie）
+#[derive(Clone)]
+pub struct CookieJar {
+    path: PathBuf,
+    allowed: Arc<HashSet<String>>,
+}
+
+impl CookieJar {
+    pub fn new(path: PathBuf, allowed: Arc<HashSet<String>>) -> Self {
+        Self { path, allowed }
+    }
+
+    fn load(&self) -> Vec<StoredCookie> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// 把保存的未过期 Cookie 写回浏览器，返回恢复的数量
+    pub fn restore(&self, tab: &Tab) -> Result<usize, String> {
+        let now = now_secs();
+        let cookies: Vec<Network::CookieParam> = self
+            .load()
+            .iter()
+            .filter(|c| !c.is_expired(now) && cookie_domain_allowed(&c.domain, &self.allowed))
+            .map(StoredCookie::to_param)
+            .collect();
+        let count = cookies.len();
+        if count > 0 {
+            tab.call_method(Network::SetCookies { cookies })
+                .map_err(|e| format!("Restore cookies failed: {}", e))?;
+        }
+        Ok(count)
+    }
+
+    /// 保存浏览器中白名单域名的全部 Cookie
+    pub fn save(&self, tab: &Tab) -> Result<usize, String> {
+        let cookies: Vec<StoredCookie> = tab
+            .call_method(Network::GetAllCookies(None))
+            .map_err(|e| format!("Read cookies failed: {}", e))?
+            .cookies
+            .iter()
+            .filter(|c| cookie_domain_allowed(&c.domain, &self.allowed))
+            .map(StoredCookie::from)
+            .collect();
+        write_private(
+            &self.path,
+            &serde_json::to_string_pretty(&cookies).map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| format!("Save cookies failed: {}", e))?;
+        Ok(cookies.len())
+    }
+}
+
+/// 写入仅当前用户可读的文件（Cookie 等同登录凭据）
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// login_state 的报告：按登录类 Cookie 与可选的页面选择器判断是否已登录
+pub fn login_state_report(domain: &str, cookies: &[StoredCookie], selector: Option<(&str, bool)>) -> String {
+    let now = now_secs();
+    let live: Vec<&StoredCookie> = cookies.iter().filter(|c| !c.is_expired(now)).collect();
+    let auth: Vec<&StoredCookie> = live.iter().copied().filter(|c| c.looks_like_auth()).collect();
+    let verdict = match selector {
+        Some((_, true)) => "logged in",
+        Some((_, false)) => "not logged in",
+        None if !auth.is_empty() => "likely logged in",
+        None => "not logged in (no auth cookies)",
+    };
+    let mut out = format!("Login state for {}: {}", domain, verdict);
+    let sessions = live.iter().filter(|c| c.expires.is_none()).count();
+    out.push_str(&format!("\n- {} cookie(s), {} session-only", live.len(), sessions));
+    if !auth.is_empty() {
+        let names: Vec<String> = auth
+            .iter()
+            .map(
+                |c| match c.expires.and_then(|e| chrono::DateTime::from_timestamp(e as i64, 0)) {
+                    Some(t) => format!("{} (expires {})", c.name, t.format("%Y-%m-%d")),
+                    None => format!("{} (session)", c.name),
+                },
+            )
+            .collect();
+        out.push_str(&format!("\n- auth cookies: {}", names.join(", ")));
+    }
+    if let Some((sel, found)) = selector {
+        out.push_str(&format!(
+            "\n- selector '{}' {} on the current page",
+            sel,
+            if found { "found" } else { "not found" }
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(name: &str, domain: &str, expires: Option<f64>) -> StoredCookie {
+        StoredCookie {
+            name: name.into(),
+            value: "v".into(),
+            domain: domain.into(),
+            path: "/".into(),
+            expires,
+            secure: true,
+            http_only: true,
+        }
+    }
+
+    #[test]
+    fn test_cookie_allowlist_and_login_state() {
+        let allowed: HashSet<String> = ["github.com", "www.google.com"].iter().map(|s| s.to_string()).collect();
+        assert!(cookie_domain_allowed(".github.com", &allowed));
+        assert!(cookie_domain_allowed(".google.com", &allowed));
+        assert!(!cookie_domain_allowed("evil.com", &allowed));
+        assert!(!cookie_domain_allowed("api.github.com", &allowed));
+        assert_eq!(profile_key(None, "coder"), "coder");
+        assert_eq!(profile_key(None, ""), "default");
+        assert!(profile_key(None, "coder/../x").starts_with("coder____x~"));
+        assert_ne!(profile_key(None, "a/b"), profile_key(None, "a_b"));
+        // 隔离用户各自一份配置，且不与默认用户或其他用户重合
+        assert!(profile_key(Some("key.alice"), "coder").ends_with("+coder"));
+        assert_ne!(profile_key(Some("key.alice"), "coder"), profile_key(Some("key.bob"), "coder"));
+        assert_ne!(profile_key(Some("key.alice"), "coder"), profile_key(None, "coder"));
+
+        let future = now_secs() + 86_400.0;
+        let report = login_state_report(
+            "github.com",
+            &[
+                cookie("user_session", "github.com", Some(future)),
+                cookie("_octo", ".github.com", None),
+                cookie("old_token", "github.com", Some(1.0)),
+            ],
+            None,
+        );
+        assert!(report.starts_with("Login state for github.com: likely logged in"));
+        assert!(report.contains("2 cookie(s), 1 session-only"));
+        assert!(report.contains("user_session (expires"));
+        assert!(!report.contains("old_token"));
+
+        let report = login_state_report(
+            "github.com",
+            &[cookie("_octo", "github.com", None)],
+            Some((".avatar", false)),
+        );
+        assert!(report.starts_with("Login state for github.com: not logged in"));
+        assert!(report.contains("selector '.avatar' not found"));
+    }
+}
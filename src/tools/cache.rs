@@ -0,0 +1,222 @@
+//! 工具结果缓存
+//!
+//! 以「工作区 + 工具名 + 参数」为键缓存成功的工具输出（多用户隔离时各用户的工作区不同，互不命中），每个工具单独配置 TTL（[tools.cache]），未配置的工具不缓存。
+//! cat / ls / code_read 额外记录目标路径的 mtime 与大小，文件变化后缓存自动失效；
+//! 非只读工具（写文件、执行命令等）成功执行后清空全部缓存，避免读到过期内容。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::ToolCacheSection;
+use crate::tools::scoped_root;
+
+/// 需要按文件 mtime 校验的工具及其路径参数名
+const MTIME_AWARE_TOOLS: &[(&str, &str)] = &[("cat", "path"), ("ls", "path"), ("code_read", "file_path")];
+
+/// 路径指纹：(mtime, 大小)；路径不存在时为 None
+type FileStamp = Option<(SystemTime, u64)>;
+
+struct CacheEntry {
+    output: String,
+    stored_at: Instant,
+    ttl: Duration,
+    stamp: FileStamp,
+}
+
+/// 工具结果缓存（由 ToolExecutor 持有）
+pub struct ToolCache {
+    ttl: HashMap<String, Duration>,
+    max_entries: usize,
+    /// 相对路径的解析根（与文件类工具的 workspace 一致，请求限定于用户工作区时改用该工作区）
+    root: PathBuf,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl ToolCache {
+    pub fn new(section: &ToolCacheSection, root: impl AsRef<Path>) -> Self {
+        Self {
+            ttl: section
+                .ttl_secs
+                .iter()
+                .filter(|(_, secs)| **secs > 0)
+                .map(|(tool, secs)| (tool.clone(), Duration::from_secs(*secs)))
+                .collect(),
+            max_entries: section.max_entries.max(1),
+            root: root.as_ref().to_path_buf(),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 该次调用是否可缓存：工具配置了 TTL，且 http_fetch 仅缓存 GET
+    pub fn is_cacheable(&self, tool: &str, args: &serde_json::Value) -> bool {
+        if !self.ttl.contains_key(tool) {
+            return false;
+        }
+        if tool == "http_fetch" {
+            let method = args.get("method").and_then(|v| v.as_str()).unwrap_or("GET");
+            return method.eq_ignore_ascii_case("GET");
+        }
+        true
+    }
+
+    /// 查找未过期且文件未变化的缓存
+    pub fn get(&self, tool: &str, args: &serde_json::Value) -> Option<String> {
+        if !self.is_cacheable(tool, args) {
+            return None;
+        }
+        let root = scoped_root(&self.root);
+        let key = cache_key(&root, tool, args);
+        let mut entries = self.lock();
+        let entry = entries.get(&key)?;
+        if entry.stored_at.elapsed() >= entry.ttl || entry.stamp != stamp(&root, tool, args) {
+            entries.remove(&key);
+            return None;
+        }
+        Some(entry.output.clone())
+    }
+
+    /// 写入一次成功的输出；满时先清过期项，仍满则淘汰最旧的一项
+    pub fn put(&self, tool: &str, args: &serde_json::Value, output: &str) {
+        let Some(ttl) = self.ttl.get(tool).copied() else {
+            return;
+        };
+        if !self.is_cacheable(tool, args) {
+            return;
+        }
+        let root = scoped_root(&self.root);
+        let stamp = stamp(&root, tool, args);
+        let mut entries = self.lock();
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, e| e.stored_at.elapsed() < e.ttl);
+        }
+        if entries.len() >= self.max_entries {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.stored_at)
+                .map(|(k, _)| k.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            cache_key(&root, tool, args),
+            CacheEntry {
+                output: output.to_string(),
+                stored_at: Instant::now(),
+                ttl,
+                stamp,
+            },
+        );
+    }
+
+    /// 清空全部缓存（修改类工具执行后调用）
+    pub fn invalidate_all(&self) {
+        let mut entries = self.lock();
+        if !entries.is_empty() {
+            tracing::debug!(count = entries.len(), "tool cache invalidated");
+            entries.clear();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CacheEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 文件类工具的目标路径指纹（相对路径按本次调用的根目录解析）；其他工具恒为 None
+fn stamp(root: &Path, tool: &str, args: &serde_json::Value) -> FileStamp {
+    let (_, key) = MTIME_AWARE_TOOLS.iter().find(|(name, _)| *name == tool)?;
+    let raw = args.get(*key).and_then(|v| v.as_str()).unwrap_or(".");
+    let path = Path::new(raw);
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    };
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// 缓存键：工作区 + 工具名 + 参数 JSON（对象键有序，参数顺序不同视为同一调用）
+fn cache_key(root: &Path, tool: &str, args: &serde_json::Value) -> String {
+    format!("{}\u{0}{}\u{0}{}", root.display(), tool, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_cache_ttl_mtime_and_invalidation() {
+        let dir = std::path::PathBuf::from("./target/test_tool_cache");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "one").unwrap();
+
+        let mut section = ToolCacheSection::default();
+        section.ttl_secs.insert("echo".to_string(), 0);
+        let cache = ToolCache::new(&section, &dir);
+
+        // 未配置 TTL 或 TTL 为 0 的工具不缓存；http_fetch 只缓存 GET
+        let echo = serde_json::json!({"text": "hi"});
+        cache.put("echo", &echo, "hi");
+        assert!(cache.get("echo", &echo).is_none());
+        assert!(!cache.is_cacheable("http_fetch", &serde_json::json!({"url": "https://a.com", "method": "POST"})));
+        assert!(cache.is_cacheable("http_fetch", &serde_json::json!({"url": "https://a.com"})));
+
+        let args = serde_json::json!({"path": "a.txt"});
+        cache.put("cat", &args, "one");
+        assert_eq!(cache.get("cat", &args).as_deref(), Some("one"));
+
+        // 文件内容变化（大小不同）后失效
+        std::fs::write(dir.join("a.txt"), "changed").unwrap();
+        assert!(cache.get("cat", &args).is_none());
+
+        let query = serde_json::json!({"query": "rust"});
+        cache.put("search", &query, "results");
+        cache.put("cat", &args, "changed");
+        assert_eq!(cache.len(), 2);
+        cache.invalidate_all();
+        assert!(cache.is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_tool_cache_separates_user_workspaces() {
+        use crate::tools::CURRENT_WORKSPACE;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (alice, bob) = (dir.path().join("users/key.alice"), dir.path().join("users/key.bob"));
+        std::fs::create_dir_all(&alice).unwrap();
+        std::fs::create_dir_all(&bob).unwrap();
+        std::fs::write(alice.join("notes.txt"), "alice").unwrap();
+        std::fs::write(bob.join("notes.txt"), "bob!!").unwrap();
+
+        let mut section = ToolCacheSection::default();
+        section.ttl_secs.insert("cat".to_string(), 60);
+        let cache = ToolCache::new(&section, dir.path());
+        let args = serde_json::json!({"path": "notes.txt"});
+
+        CURRENT_WORKSPACE
+            .scope(Some(alice.clone()), async { cache.put("cat", &args, "alice") })
+            .await;
+        let as_alice = CURRENT_WORKSPACE
+            .scope(Some(alice), async { cache.get("cat", &args) })
+            .await;
+        assert_eq!(as_alice.as_deref(), Some("alice"));
+        // 同一相对路径，另一用户不会命中前一用户的输出
+        let as_bob = CURRENT_WORKSPACE.scope(Some(bob), async { cache.get("cat", &args) }).await;
+        assert!(as_bob.is_none());
+        assert!(cache.get("cat", &args).is_none());
+    }
+}
@@ -0,0 +1,1237 @@
+//! 日历工具
+//!
+//! calendar 支持 list / create / update 日程，后端为 Google Calendar（REST + OAuth 刷新令牌）或 CalDAV
+//! （REPORT 查询、PUT 写入 iCalendar）。时间参数接受 RFC 3339、本地时间 "2026-10-18T15:00" 或日期（全天）；
+//! update 只给 start 时保持原时长，便于「把明天下午 3 点的会挪到 4 点」；CalDAV 事件改写时保留 RRULE 与时间的 TZID，
+//! 只接受日历服务器本身的事件 href。
+//! Google 令牌由 `bee calendar auth` 通过本地回环授权写入 [tools.calendar.google] token_file。
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use reqwest::{Client, Method, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::config::{CalDavSection, CalendarSection, GoogleCalendarSection};
+use crate::tools::{Tool, ToolError};
+
+const GOOGLE_API_BASE: &str = "https://www.googleapis.com/calendar/v3/calendars/";
+const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_SCOPE: &str = "https://www.googleapis.com/auth/calendar.events";
+/// 未给出结束时间时的默认时长（分钟）
+const DEFAULT_DURATION_MINUTES: i64 = 60;
+
+/// 日程时间：具体时刻或全天日期
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTime {
+    At(DateTime<Utc>),
+    AllDay(NaiveDate),
+}
+
+impl EventTime {
+    /// 解析 RFC 3339、本地时间（"2026-10-18T15:00" / "2026-10-18 15:00"）或日期（全天）
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Some(Self::At(dt.with_timezone(&Utc)));
+        }
+        for fmt in [
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%d %H:%M",
+        ] {
+            if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) {
+                return local_to_utc(naive).map(Self::At);
+            }
+        }
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().map(Self::AllDay)
+    }
+
+    fn is_all_day(&self) -> bool {
+        matches!(self, Self::AllDay(_))
+    }
+
+    /// 用于区间计算的时刻（全天按本地零点）
+    fn instant(&self) -> DateTime<Utc> {
+        match self {
+            Self::At(t) => *t,
+            Self::AllDay(d) => local_to_utc(d.and_hms_opt(0, 0, 0).expect("midnight")).unwrap_or_else(Utc::now),
+        }
+    }
+
+    fn shifted(&self, by: Duration) -> Self {
+        match self {
+            Self::At(t) => Self::At(*t + by),
+            Self::AllDay(d) => Self::AllDay(*d + Duration::days(by.num_days())),
+        }
+    }
+}
+
+fn local_to_utc(naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// 日程
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+    /// 后端内的标识（Google 为事件 id，CalDAV 为资源 href）
+    pub id: String,
+    pub title: String,
+    pub start: EventTime,
+    pub end: EventTime,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+impl CalendarEvent {
+    /// 单行展示（本地时间）
+    fn line(&self) -> String {
+        let when = match (self.start, self.end) {
+            (EventTime::AllDay(s), EventTime::AllDay(e)) if e <= s + Duration::days(1) => format!("{} (all day)", s),
+            (EventTime::AllDay(s), EventTime::AllDay(e)) => format!("{} – {} (all day)", s, e - Duration::days(1)),
+            (start, end) => {
+                let (s, e) = (
+                    start.instant().with_timezone(&Local),
+                    end.instant().with_timezone(&Local),
+                );
+                if s.date_naive() == e.date_naive() {
+                    format!("{}–{}", s.format("%Y-%m-%d %H:%M"), e.format("%H:%M"))
+                } else {
+                    format!("{} – {}", s.format("%Y-%m-%d %H:%M"), e.format("%Y-%m-%d %H:%M"))
+                }
+            }
+        };
+        let mut line = format!("- {} {} [id: {}]", when, self.title, self.id);
+        if let Some(loc) = self.location.as_deref().filter(|l| !l.is_empty()) {
+            line.push_str(&format!(" @ {}", loc));
+        }
+        line
+    }
+}
+
+/// 日历后端
+#[async_trait]
+pub trait CalendarBackend: Send + Sync {
+    /// 区间 [from, to) 内的日程（按开始时间排序，重复日程展开为单次）
+    async fn list(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CalendarEvent>, ToolError>;
+    async fn get(&self, id: &str) -> Result<CalendarEvent, ToolError>;
+    /// 新建日程（忽略 event.id），返回带 id 的日程
+    async fn create(&self, event: &CalendarEvent) -> Result<CalendarEvent, ToolError>;
+    /// 按 event.id 更新标题、时间、地点与描述，其它字段（参与者、提醒等）保持不变
+    async fn update(&self, event: &CalendarEvent) -> Result<CalendarEvent, ToolError>;
+}
+
+fn http_err(e: reqwest::Error) -> ToolError {
+    ToolError::Failed(format!("Calendar request failed: {}", e))
+}
+
+/// 非 2xx 响应转为错误（带响应体摘要）
+async fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, ToolError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    let body: String = body.chars().take(500).collect();
+    Err(ToolError::Failed(format!(
+        "Calendar server returned {}: {}",
+        status.as_u16(),
+        body
+    )))
+}
+
+// ---------- Google Calendar ----------
+
+/// 持久化的 OAuth 令牌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GoogleToken {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    /// 过期时间（Unix 秒）
+    #[serde(default)]
+    expires_at: i64,
+}
+
+impl GoogleToken {
+    fn from_response(v: &Value, previous_refresh: Option<String>) -> Option<Self> {
+        Some(Self {
+            access_token: v.get("access_token")?.as_str()?.to_string(),
+            refresh_token: v
+                .get("refresh_token")
+                .and_then(|r| r.as_str())
+                .map(str::to_string)
+                .or(previous_refresh),
+            expires_at: Utc::now().timestamp() + v.get("expires_in").and_then(|e| e.as_i64()).unwrap_or(3600),
+        })
+    }
+
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)
+    }
+}
+
+fn google_time(t: &EventTime) -> Value {
+    match t {
+        EventTime::At(dt) => json!({ "dateTime": dt.to_rfc3339() }),
+        EventTime::AllDay(d) => json!({ "date": d.format("%Y-%m-%d").to_string() }),
+    }
+}
+
+fn google_event_json(event: &CalendarEvent) -> Value {
+    json!({
+        "summary": event.title,
+        "location": event.location.clone().unwrap_or_default(),
+        "description": event.description.clone().unwrap_or_default(),
+        "start": google_time(&event.start),
+        "end": google_time(&event.end),
+    })
+}
+
+fn parse_google_event(v: &Value) -> Option<CalendarEvent> {
+    let time = |t: &Value| -> Option<EventTime> {
+        match (
+            t.get("dateTime").and_then(|x| x.as_str()),
+            t.get("date").and_then(|x| x.as_str()),
+        ) {
+            (Some(dt), _) => EventTime::parse(dt),
+            (None, Some(d)) => NaiveDate::parse_from_str(d, "%Y-%m-%d").ok().map(EventTime::AllDay),
+            _ => None,
+        }
+    };
+    let text = |key: &str| {
+        v.get(key)
+            .and_then(|x| x.as_str())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    Some(CalendarEvent {
+        id: v.get("id")?.as_str()?.to_string(),
+        title: text("summary").unwrap_or_else(|| "(no title)".to_string()),
+        start: time(v.get("start")?)?,
+        end: time(v.get("end")?)?,
+        location: text("location"),
+        description: text("description"),
+    })
+}
+
+/// Google Calendar 后端
+pub struct GoogleCalendar {
+    client: Client,
+    config: GoogleCalendarSection,
+    token: Mutex<Option<GoogleToken>>,
+}
+
+impl GoogleCalendar {
+    pub fn new(config: &GoogleCalendarSection) -> Self {
+        Self {
+            client: Client::new(),
+            config: config.clone(),
+            token: Mutex::new(None),
+        }
+    }
+
+    fn client_credentials(config: &GoogleCalendarSection) -> Result<(String, String), ToolError> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| ToolError::Failed(format!("Google OAuth env {} is not set", name)))
+        };
+        Ok((var(&config.client_id_env)?, var(&config.client_secret_env)?))
+    }
+
+    /// 有效的 access token：首次从 token_file 读取，临近过期时用 refresh_token 刷新并写回
+    async fn access_token(&self) -> Result<String, ToolError> {
+        let mut guard = self.token.lock().await;
+        if guard.is_none() {
+            let raw = std::fs::read_to_string(&self.config.token_file).map_err(|_| {
+                ToolError::Failed(format!(
+                    "No Google Calendar token at {}: run `bee calendar auth` first",
+                    self.config.token_file.display()
+                ))
+            })?;
+            *guard =
+                Some(serde_json::from_str(&raw).map_err(|e| ToolError::Failed(format!("Invalid token file: {}", e)))?);
+        }
+        let token = guard.as_ref().expect("token loaded");
+        if token.expires_at - 60 > Utc::now().timestamp() {
+            return Ok(token.access_token.clone());
+        }
+        let refresh = token.refresh_token.clone().ok_or_else(|| {
+            ToolError::Failed("Google token expired and has no refresh_token: run `bee calendar auth`".to_string())
+        })?;
+        let (client_id, client_secret) = Self::client_credentials(&self.config)?;
+        let resp = self
+            .client
+            .post(GOOGLE_TOKEN_URL)
+            .form(&[
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh.as_str()),
+                ("grant_type", "refresh_token"),
+            ])
+            .send()
+            .await
+            .map_err(http_err)?;
+        let body: Value = check_status(resp).await?.json().await.map_err(http_err)?;
+        let refreshed = GoogleToken::from_response(&body, Some(refresh))
+            .ok_or_else(|| ToolError::Failed("Token refresh response has no access_token".to_string()))?;
+        if let Err(e) = refreshed.save(&self.config.token_file) {
+            tracing::warn!("failed to save refreshed calendar token: {}", e);
+        }
+        let access = refreshed.access_token.clone();
+        *guard = Some(refreshed);
+        Ok(access)
+    }
+
+    fn events_url(&self, event_id: Option<&str>) -> Result<Url, ToolError> {
+        let mut url = Url::parse(GOOGLE_API_BASE).map_err(|e| ToolError::Failed(e.to_string()))?;
+        {
+            let mut segs = url
+                .path_segments_mut()
+                .map_err(|_| ToolError::Failed("invalid calendar API url".to_string()))?;
+            segs.pop_if_empty().push(&self.config.calendar_id).push("events");
+            if let Some(id) = event_id {
+                segs.push(id);
+            }
+        }
+        Ok(url)
+    }
+
+    async fn send(&self, method: Method, url: Url, body: Option<Value>) -> Result<Value, ToolError> {
+        let mut req = self.client.request(method, url).bearer_auth(self.access_token().await?);
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let resp = check_status(req.send().await.map_err(http_err)?).await?;
+        resp.json().await.map_err(http_err)
+    }
+
+    fn parse(v: &Value) -> Result<CalendarEvent, ToolError> {
+        parse_google_event(v).ok_or_else(|| ToolError::Failed("Unexpected event format from Google".to_string()))
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for GoogleCalendar {
+    async fn list(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CalendarEvent>, ToolError> {
+        let mut url = self.events_url(None)?;
+        url.query_pairs_mut()
+            .append_pair("timeMin", &from.to_rfc3339())
+            .append_pair("timeMax", &to.to_rfc3339())
+            .append_pair("singleEvents", "true")
+            .append_pair("orderBy", "startTime")
+            .append_pair("maxResults", "100");
+        let body = self.send(Method::GET, url, None).await?;
+        Ok(body
+            .get("items")
+            .and_then(|i| i.as_array())
+            .map(|items| items.iter().filter_map(parse_google_event).collect())
+            .unwrap_or_default())
+    }
+
+    async fn get(&self, id: &str) -> Result<CalendarEvent, ToolError> {
+        Self::parse(&self.send(Method::GET, self.events_url(Some(id))?, None).await?)
+    }
+
+    async fn create(&self, event: &CalendarEvent) -> Result<CalendarEvent, ToolError> {
+        Self::parse(
+            &self
+                .send(Method::POST, self.events_url(None)?, Some(google_event_json(event)))
+                .await?,
+        )
+    }
+
+    async fn update(&self, event: &CalendarEvent) -> Result<CalendarEvent, ToolError> {
+        let url = self.events_url(Some(&event.id))?;
+        Self::parse(&self.send(Method::PATCH, url, Some(google_event_json(event))).await?)
+    }
+}
+
+/// `bee calendar auth`：本地回环 OAuth 授权，令牌写入 token_file
+pub async fn google_authorize(config: &GoogleCalendarSection) -> anyhow::Result<PathBuf> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (client_id, client_secret) =
+        GoogleCalendar::client_credentials(config).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let redirect_uri = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+    let auth_url = Url::parse_with_params(
+        GOOGLE_AUTH_URL,
+        &[
+            ("client_id", client_id.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("response_type", "code"),
+            ("scope", GOOGLE_SCOPE),
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+        ],
+    )?;
+    println!(
+        "在浏览器中打开以下地址并授权 Bee 访问日历：\n\n{}\n\n等待授权回调 {} ...",
+        auth_url, redirect_uri
+    );
+
+    let (mut stream, _) = listener.accept().await?;
+    let mut buf = vec![0u8; 8192];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let callback = Url::parse(&format!("http://127.0.0.1{}", path))?;
+    let param = |key: &str| {
+        callback
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    };
+    let page = if param("code").is_some() {
+        "授权成功，可以关闭此页面。"
+    } else {
+        "授权失败，请回到终端查看。"
+    };
+    let page = format!("<html><meta charset=\"utf-8\"><body>{}</body></html>", page);
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        page.len(),
+        page
+    );
+    stream.write_all(response.as_bytes()).await?;
+    let code = match (param("code"), param("error")) {
+        (Some(code), _) => code,
+        (None, err) => anyhow::bail!(
+            "authorization failed: {}",
+            err.unwrap_or_else(|| "no code in callback".into())
+        ),
+    };
+
+    let resp = Client::new()
+        .post(GOOGLE_TOKEN_URL)
+        .form(&[
+            ("code", code.as_str()),
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("grant_type", "authorization_code"),
+        ])
+        .send()
+        .await?;
+    let status = resp.status();
+    let body: Value = resp.json().await?;
+    if !status.is_success() {
+        anyhow::bail!("token exchange failed ({}): {}", status, body);
+    }
+    let token =
+        GoogleToken::from_response(&body, None).ok_or_else(|| anyhow::anyhow!("no access_token in response"))?;
+    token.save(&config.token_file)?;
+    Ok(config.token_file.clone())
+}
+
+// ---------- CalDAV ----------
+
+/// iCalendar 属性行：名称、参数（原样）、值
+struct IcsProp<'a> {
+    name: String,
+    params: &'a str,
+    value: &'a str,
+}
+
+/// 展开折行（CRLF 后接空格 / 制表符为续行）
+fn ics_unfold(raw: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in raw.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match line.strip_prefix([' ', '\t']) {
+            Some(cont) if !lines.is_empty() => lines.last_mut().expect("non-empty").push_str(cont),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines.retain(|l| !l.is_empty());
+    lines
+}
+
+fn ics_prop(line: &str) -> Option<IcsProp<'_>> {
+    // 参数值可能带引号（内含 ':' 或 ';'），取引号外的第一个 ':'
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let (name, params) = head.split_once(';').unwrap_or((head, ""));
+    Some(IcsProp {
+        name: name.to_ascii_uppercase(),
+        params,
+        value,
+    })
+}
+
+fn ics_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => {}
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn ics_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// 解析 DTSTART / DTEND：VALUE=DATE 为全天；以 Z 结尾为 UTC；其余（含 TZID）按本地时间处理
+fn ics_time(params: &str, value: &str) -> Option<EventTime> {
+    let value = value.trim();
+    if params.to_ascii_uppercase().contains("VALUE=DATE") && !params.to_ascii_uppercase().contains("VALUE=DATE-TIME")
+        || value.len() == 8
+    {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(EventTime::AllDay);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(EventTime::At(Utc.from_utc_datetime(&naive)));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    local_to_utc(naive).map(EventTime::At)
+}
+
+/// 解析 DURATION（如 PT1H30M、P1D、P1W）
+fn ics_duration(value: &str) -> Option<Duration> {
+    let re = Regex::new(r"^([+-])?P(?:(\d+)W)?(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?)?$").ok()?;
+    let caps = re.captures(value.trim())?;
+    let num = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<i64>().ok()).unwrap_or(0);
+    let d = Duration::weeks(num(2))
+        + Duration::days(num(3))
+        + Duration::hours(num(4))
+        + Duration::minutes(num(5))
+        + Duration::seconds(num(6));
+    Some(if caps.get(1).is_some_and(|m| m.as_str() == "-") {
+        -d
+    } else {
+        d
+    })
+}
+
+fn ics_format_time(name: &str, t: &EventTime) -> String {
+    match t {
+        EventTime::At(dt) => format!("{}:{}", name, dt.format("%Y%m%dT%H%M%SZ")),
+        EventTime::AllDay(d) => format!("{};VALUE=DATE:{}", name, d.format("%Y%m%d")),
+    }
+}
+
+/// 按原属性的 TZID 写回时间：与 ics_time 对称，TZID 时间按本地时间书写；原属性无 TZID 时同 ics_format_time
+fn ics_format_time_in(name: &str, t: &EventTime, tzid: Option<&str>) -> String {
+    match (t, tzid) {
+        (EventTime::At(dt), Some(tzid)) => format!(
+            "{};TZID={}:{}",
+            name,
+            tzid,
+            dt.with_timezone(&Local).naive_local().format("%Y%m%dT%H%M%S")
+        ),
+        _ => ics_format_time(name, t),
+    }
+}
+
+/// 属性参数中的 TZID（可带引号）
+fn ics_tzid(params: &str) -> Option<&str> {
+    params.split(';').find_map(|p| {
+        let (k, v) = p.split_once('=')?;
+        k.trim().eq_ignore_ascii_case("TZID").then_some(v.trim())
+    })
+}
+
+/// 按 RFC 5545 折行（每行不超过 75 字节）
+fn ics_fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out
+}
+
+/// 解析日历数据中的第一个 VEVENT（嵌套的 VALARM 忽略）
+fn parse_ics_event(id: &str, raw: &str) -> Option<CalendarEvent> {
+    let mut depth = 0usize;
+    let mut in_event = false;
+    let (mut title, mut start, mut end, mut duration, mut location, mut description) =
+        (None, None, None, None, None, None);
+    for line in ics_unfold(raw) {
+        let Some(prop) = ics_prop(&line) else { continue };
+        match (prop.name.as_str(), prop.value.to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") if !in_event => {
+                in_event = true;
+                depth = 0;
+                continue;
+            }
+            ("BEGIN", _) if in_event => depth += 1,
+            ("END", "VEVENT") if in_event && depth == 0 => break,
+            ("END", _) if in_event => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if !in_event || depth > 0 {
+            continue;
+        }
+        match prop.name.as_str() {
+            "SUMMARY" => title = Some(ics_unescape(prop.value)),
+            "DTSTART" => start = ics_time(prop.params, prop.value),
+            "DTEND" => end = ics_time(prop.params, prop.value),
+            "DURATION" => duration = ics_duration(prop.value),
+            "LOCATION" => location = Some(ics_unescape(prop.value)).filter(|s| !s.is_empty()),
+            "DESCRIPTION" => description = Some(ics_unescape(prop.value)).filter(|s| !s.is_empty()),
+            _ => {}
+        }
+    }
+    let start: EventTime = start?;
+    let end = end.unwrap_or_else(|| match (duration, start) {
+        (Some(d), s) => s.shifted(d),
+        (None, EventTime::AllDay(d)) => EventTime::AllDay(d + Duration::days(1)),
+        (None, s) => s,
+    });
+    Some(CalendarEvent {
+        id: id.to_string(),
+        title: title.unwrap_or_else(|| "(no title)".to_string()),
+        start,
+        end,
+        location,
+        description,
+    })
+}
+
+/// 本工具维护的 VEVENT 属性（update 时整体替换）
+const MANAGED_PROPS: &[&str] = &["SUMMARY", "DTSTART", "DTEND", "DURATION", "LOCATION", "DESCRIPTION"];
+
+fn ics_event_lines(event: &CalendarEvent) -> Vec<String> {
+    let mut lines = vec![
+        format!("SUMMARY:{}", ics_escape(&event.title)),
+        ics_format_time("DTSTART", &event.start),
+        ics_format_time("DTEND", &event.end),
+    ];
+    if let Some(loc) = &event.location {
+        lines.push(format!("LOCATION:{}", ics_escape(loc)));
+    }
+    if let Some(desc) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", ics_escape(desc)));
+    }
+    lines
+}
+
+fn ics_join(lines: impl IntoIterator<Item = String>) -> String {
+    let mut out: String = lines.into_iter().map(|l| ics_fold(&l) + "\r\n").collect();
+    if out.is_empty() {
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// 新建日程的 iCalendar 文本
+fn ics_new(uid: &str, event: &CalendarEvent) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//bee//calendar//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+    ];
+    lines.extend(ics_event_lines(event));
+    lines.extend(["END:VEVENT".to_string(), "END:VCALENDAR".to_string()]);
+    ics_join(lines)
+}
+
+/// 在原 iCalendar 文本中替换第一个 VEVENT 的标题、地点与描述，其余内容（含 RRULE）保留。
+/// 开始 / 结束时间未改动时原样保留 DTSTART / DTEND / DURATION；改动后按原属性的 TZID 写回
+fn ics_apply(raw: &str, event: &CalendarEvent) -> String {
+    let original = parse_ics_event(&event.id, raw);
+    let (mut start_tzid, mut end_tzid) = (None, None);
+    let mut depth = 0usize;
+    let mut state = 0u8; // 0 = 未进入，1 = 第一个 VEVENT 内，2 = 已处理
+    for line in ics_unfold(raw) {
+        let Some(prop) = ics_prop(&line) else { continue };
+        match state {
+            0 if prop.name == "BEGIN" && prop.value.eq_ignore_ascii_case("VEVENT") => state = 1,
+            1 if prop.name == "BEGIN" => depth += 1,
+            1 if prop.name == "END" && depth == 0 => break,
+            1 if prop.name == "END" => depth -= 1,
+            1 if depth == 0 && prop.name == "DTSTART" => start_tzid = ics_tzid(prop.params).map(str::to_string),
+            1 if depth == 0 && prop.name == "DTEND" => end_tzid = ics_tzid(prop.params).map(str::to_string),
+            _ => {}
+        }
+    }
+    let keep_start = original.as_ref().is_some_and(|o| o.start == event.start);
+    // DURATION 相对于 DTSTART：开始时间改了就改写为 DTEND
+    let keep_end = keep_start && original.as_ref().is_some_and(|o| o.end == event.end);
+    let start_tzid = start_tzid.as_deref();
+    let end_tzid = end_tzid.as_deref().or(start_tzid);
+
+    let mut replaced = vec![format!("SUMMARY:{}", ics_escape(&event.title))];
+    if !keep_start {
+        replaced.push(ics_format_time_in("DTSTART", &event.start, start_tzid));
+    }
+    if !keep_end {
+        replaced.push(ics_format_time_in("DTEND", &event.end, end_tzid));
+    }
+    if let Some(loc) = &event.location {
+        replaced.push(format!("LOCATION:{}", ics_escape(loc)));
+    }
+    if let Some(desc) = &event.description {
+        replaced.push(format!("DESCRIPTION:{}", ics_escape(desc)));
+    }
+    let managed = |name: &str| match name {
+        "DTSTART" => !keep_start,
+        "DTEND" | "DURATION" => !keep_end,
+        _ => MANAGED_PROPS.contains(&name),
+    };
+
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut state = 0u8;
+    let mut replaced = Some(replaced);
+    for line in ics_unfold(raw) {
+        let (name, value) = ics_prop(&line)
+            .map(|p| (p.name, p.value.to_ascii_uppercase()))
+            .unwrap_or_default();
+        match state {
+            0 if name == "BEGIN" && value == "VEVENT" => state = 1,
+            1 if name == "BEGIN" => depth += 1,
+            1 if name == "END" && value == "VEVENT" && depth == 0 => {
+                out.extend(replaced.take().unwrap_or_default());
+                state = 2;
+            }
+            1 if name == "END" => depth = depth.saturating_sub(1),
+            1 if depth == 0 && managed(&name) => continue,
+            _ => {}
+        }
+        out.push(line);
+    }
+    ics_join(out)
+}
+
+/// 取 XML 中指定本地名（忽略命名空间前缀）的元素内容
+fn xml_elements(xml: &str, local: &str) -> Vec<String> {
+    let re = Regex::new(&format!(
+        r"(?s)<(?:[A-Za-z0-9_-]+:)?{0}(?:\s[^>]*)?>(.*?)</(?:[A-Za-z0-9_-]+:)?{0}\s*>",
+        regex::escape(local)
+    ))
+    .expect("valid regex");
+    re.captures_iter(xml).map(|c| xml_text(&c[1])).collect()
+}
+
+fn xml_text(s: &str) -> String {
+    let s = s.trim();
+    if let Some(cdata) = s.strip_prefix("<![CDATA[").and_then(|r| r.strip_suffix("]]>")) {
+        return cdata.to_string();
+    }
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// 解析 REPORT 返回的 multistatus：每个 response 的 href 与 calendar-data
+fn parse_multistatus(xml: &str) -> Vec<CalendarEvent> {
+    let response_re =
+        Regex::new(r"(?s)<(?:[A-Za-z0-9_-]+:)?response(?:\s[^>]*)?>(.*?)</(?:[A-Za-z0-9_-]+:)?response\s*>")
+            .expect("valid regex");
+    response_re
+        .captures_iter(xml)
+        .filter_map(|c| {
+            let block = &c[1];
+            let href = xml_elements(block, "href").into_iter().next()?;
+            let data = xml_elements(block, "calendar-data").into_iter().next()?;
+            parse_ics_event(&href, &data)
+        })
+        .collect()
+}
+
+/// CalDAV 后端
+pub struct CalDavCalendar {
+    client: Client,
+    url: Url,
+    username: String,
+    password_env: String,
+}
+
+impl CalDavCalendar {
+    pub fn new(config: &CalDavSection) -> Result<Self, ToolError> {
+        let mut raw = config.url.trim().to_string();
+        if !raw.ends_with('/') {
+            raw.push('/');
+        }
+        let url =
+            Url::parse(&raw).map_err(|e| ToolError::Failed(format!("Invalid CalDAV url '{}': {}", config.url, e)))?;
+        Ok(Self {
+            client: Client::new(),
+            url,
+            username: config.username.clone(),
+            password_env: config.password_env.clone(),
+        })
+    }
+
+    fn request(&self, method: Method, url: Url) -> Result<reqwest::RequestBuilder, ToolError> {
+        let password = std::env::var(&self.password_env)
+            .map_err(|_| ToolError::Failed(format!("CalDAV password env {} is not set", self.password_env)))?;
+        Ok(self
+            .client
+            .request(method, url)
+            .basic_auth(&self.username, Some(password)))
+    }
+
+    /// 事件 href 相对日历地址解析；指向其他源（协议、主机或端口不同）的 href 被拒绝，避免把凭据发往别处
+    fn resource_url(&self, href: &str) -> Result<Url, ToolError> {
+        let url = self
+            .url
+            .join(href)
+            .map_err(|e| ToolError::InvalidArgs(format!("Invalid event id '{}': {}", href, e)))?;
+        if url.origin() != self.url.origin() {
+            return Err(ToolError::InvalidArgs(format!(
+                "Invalid event id '{}': not on the calendar server",
+                href
+            )));
+        }
+        Ok(url)
+    }
+
+    /// 读取资源原文与 ETag
+    async fn fetch(&self, href: &str) -> Result<(String, Option<String>), ToolError> {
+        let resp = self
+            .request(Method::GET, self.resource_url(href)?)?
+            .send()
+            .await
+            .map_err(http_err)?;
+        let resp = check_status(resp).await?;
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        Ok((resp.text().await.map_err(http_err)?, etag))
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for CalDavCalendar {
+    async fn list(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CalendarEvent>, ToolError> {
+        let (start, end) = (from.format("%Y%m%dT%H%M%SZ"), to.format("%Y%m%dT%H%M%SZ"));
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data><c:expand start="{start}" end="{end}"/></c:calendar-data>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT"><c:time-range start="{start}" end="{end}"/></c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#
+        );
+        let method = Method::from_bytes(b"REPORT").expect("valid method");
+        let resp = self
+            .request(method, self.url.clone())?
+            .header("Depth", "1")
+            .header(reqwest::header::CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await
+            .map_err(http_err)?;
+        let xml = check_status(resp).await?.text().await.map_err(http_err)?;
+        let mut events = parse_multistatus(&xml);
+        events.sort_by_key(|e| e.start.instant());
+        Ok(events)
+    }
+
+    async fn get(&self, id: &str) -> Result<CalendarEvent, ToolError> {
+        let (raw, _) = self.fetch(id).await?;
+        parse_ics_event(id, &raw).ok_or_else(|| ToolError::Failed(format!("No VEVENT in {}", id)))
+    }
+
+    async fn create(&self, event: &CalendarEvent) -> Result<CalendarEvent, ToolError> {
+        let uid = uuid::Uuid::new_v4().to_string();
+        let url = self.resource_url(&format!("{}.ics", uid))?;
+        let href = url.path().to_string();
+        let resp = self
+            .request(Method::PUT, url)?
+            .header(reqwest::header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .header(reqwest::header::IF_NONE_MATCH, "*")
+            .body(ics_new(&uid, event))
+            .send()
+            .await
+            .map_err(http_err)?;
+        check_status(resp).await?;
+        Ok(CalendarEvent {
+            id: href,
+            ..event.clone()
+        })
+    }
+
+    async fn update(&self, event: &CalendarEvent) -> Result<CalendarEvent, ToolError> {
+        let (raw, etag) = self.fetch(&event.id).await?;
+        let mut req = self
+            .request(Method::PUT, self.resource_url(&event.id)?)?
+            .header(reqwest::header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(ics_apply(&raw, event));
+        // 带 If-Match 防止覆盖他人同时做的修改
+        if let Some(etag) = etag {
+            req = req.header(reqwest::header::IF_MATCH, etag);
+        }
+        check_status(req.send().await.map_err(http_err)?).await?;
+        Ok(event.clone())
+    }
+}
+
+// ---------- 工具 ----------
+
+/// list 的日期参数：today / tomorrow / yesterday 或 YYYY-MM-DD
+fn parse_day(s: &str) -> Option<NaiveDate> {
+    let today = Local::now().date_naive();
+    match s.trim().to_ascii_lowercase().as_str() {
+        "today" | "今天" => Some(today),
+        "tomorrow" | "明天" => Some(today + Duration::days(1)),
+        "yesterday" | "昨天" => Some(today - Duration::days(1)),
+        other => NaiveDate::parse_from_str(other, "%Y-%m-%d").ok(),
+    }
+}
+
+/// 查询区间：day 为单日；from / to 为起止（日期型 to 含当天）；默认今天起 1 天
+fn list_range(args: &Value) -> Result<(DateTime<Utc>, DateTime<Utc>), ToolError> {
+    let str_arg = |k: &str| args.get(k).and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty());
+    let bound = |k: &str, end: bool| -> Result<Option<DateTime<Utc>>, ToolError> {
+        let Some(s) = str_arg(k) else { return Ok(None) };
+        if let Some(day) = parse_day(s) {
+            let day = if end { day + Duration::days(1) } else { day };
+            return Ok(Some(EventTime::AllDay(day).instant()));
+        }
+        EventTime::parse(s)
+            .map(|t| Some(t.instant()))
+            .ok_or_else(|| ToolError::InvalidArgs(format!("Cannot parse {} '{}'", k, s)))
+    };
+    if let Some(day) = str_arg("day") {
+        let day = parse_day(day).ok_or_else(|| ToolError::InvalidArgs(format!("Cannot parse day '{}'", day)))?;
+        return Ok((
+            EventTime::AllDay(day).instant(),
+            EventTime::AllDay(day + Duration::days(1)).instant(),
+        ));
+    }
+    let from = bound("from", false)?.unwrap_or_else(|| EventTime::AllDay(Local::now().date_naive()).instant());
+    let to = bound("to", true)?.unwrap_or(from + Duration::days(1));
+    if to <= from {
+        return Err(ToolError::InvalidArgs("to must be after from".to_string()));
+    }
+    Ok((from, to))
+}
+
+fn time_arg(args: &Value, key: &str) -> Result<Option<EventTime>, ToolError> {
+    match args.get(key).and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty()) {
+        Some(s) => EventTime::parse(s).map(Some).ok_or_else(|| {
+            ToolError::InvalidArgs(format!(
+                "Cannot parse {} '{}': use RFC 3339, YYYY-MM-DDTHH:MM or YYYY-MM-DD",
+                key, s
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+fn text_arg(args: &Value, key: &str) -> Option<String> {
+    args.get(key).and_then(|v| v.as_str()).map(|s| s.trim().to_string())
+}
+
+/// 合并 update 参数：只改 start 时保持原时长；全天与具体时刻不能混用
+fn apply_update(mut event: CalendarEvent, args: &Value) -> Result<CalendarEvent, ToolError> {
+    let duration = event.end.instant() - event.start.instant();
+    match (time_arg(args, "start")?, time_arg(args, "end")?) {
+        (Some(start), Some(end)) => {
+            event.start = start;
+            event.end = end;
+        }
+        (Some(start), None) => {
+            event.end = match start {
+                EventTime::AllDay(_) => start.shifted(Duration::days(duration.num_days().max(1))),
+                EventTime::At(_) => start.shifted(duration),
+            };
+            event.start = start;
+        }
+        (None, Some(end)) => event.end = end,
+        (None, None) => {}
+    }
+    if event.start.is_all_day() != event.end.is_all_day() || event.end.instant() < event.start.instant() {
+        return Err(ToolError::InvalidArgs(
+            "end must be after start and of the same kind (date or time)".to_string(),
+        ));
+    }
+    if let Some(title) = text_arg(args, "title").filter(|t| !t.is_empty()) {
+        event.title = title;
+    }
+    if let Some(loc) = text_arg(args, "location") {
+        event.location = Some(loc).filter(|l| !l.is_empty());
+    }
+    if let Some(desc) = text_arg(args, "description") {
+        event.description = Some(desc).filter(|d| !d.is_empty());
+    }
+    Ok(event)
+}
+
+/// calendar 工具
+pub struct CalendarTool {
+    backend: Box<dyn CalendarBackend>,
+}
+
+impl CalendarTool {
+    pub fn new(backend: Box<dyn CalendarBackend>) -> Self {
+        Self { backend }
+    }
+
+    /// 按 [tools.calendar] 构建；backend 未设置或无效时返回 None
+    pub fn from_config(config: &CalendarSection) -> Option<Self> {
+        let backend: Box<dyn CalendarBackend> = match config.backend.as_deref()?.to_ascii_lowercase().as_str() {
+            "google" => Box::new(GoogleCalendar::new(&config.google)),
+            "caldav" => match CalDavCalendar::new(&config.caldav) {
+                Ok(c) => Box::new(c),
+                Err(e) => {
+                    tracing::warn!("calendar tool disabled: {}", e);
+                    return None;
+                }
+            },
+            other => {
+                tracing::warn!("calendar tool disabled: unknown backend '{}'", other);
+                return None;
+            }
+        };
+        Some(Self::new(backend))
+    }
+}
+
+#[async_trait]
+impl Tool for CalendarTool {
+    fn name(&self) -> &str {
+        "calendar"
+    }
+
+    fn description(&self) -> &str {
+        "Manage the user's calendar. Args: {\"action\": \"list|create|update\"}. list: {\"day\": \"today|tomorrow|YYYY-MM-DD\"} or {\"from\", \"to\"} (dates or times; default today). create: {\"title\", \"start\", \"end\" or \"duration_minutes\" (default 60), \"location\", \"description\"}. update: {\"id\" (from list), and any of \"start\", \"end\", \"title\", \"location\", \"description\"}; changing only start keeps the duration. Times: RFC 3339 or local YYYY-MM-DDTHH:MM; a bare YYYY-MM-DD means all day."
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("list");
+        tracing::info!(action, "calendar tool execute");
+        match action {
+            "list" => {
+                let (from, to) = list_range(&args)?;
+                let events = self.backend.list(from, to).await?;
+                let range = format!(
+                    "{} – {}",
+                    from.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                    to.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                );
+                if events.is_empty() {
+                    return Ok(format!("No events between {}", range));
+                }
+                let lines: Vec<String> = events.iter().map(CalendarEvent::line).collect();
+                Ok(format!(
+                    "{} event(s) between {}:\n{}",
+                    events.len(),
+                    range,
+                    lines.join("\n")
+                ))
+            }
+            "create" => {
+                let title = text_arg(&args, "title")
+                    .filter(|t| !t.is_empty())
+                    .ok_or_else(|| ToolError::InvalidArgs("Missing title".to_string()))?;
+                let start =
+                    time_arg(&args, "start")?.ok_or_else(|| ToolError::InvalidArgs("Missing start".to_string()))?;
+                let end = match time_arg(&args, "end")? {
+                    Some(end) => end,
+                    None if start.is_all_day() => start.shifted(Duration::days(1)),
+                    None => {
+                        let minutes = args
+                            .get("duration_minutes")
+                            .and_then(|v| v.as_i64())
+                            .unwrap_or(DEFAULT_DURATION_MINUTES);
+                        start.shifted(Duration::minutes(minutes.max(1)))
+                    }
+                };
+                let event = CalendarEvent {
+                    id: String::new(),
+                    title,
+                    start,
+                    end,
+                    location: text_arg(&args, "location").filter(|s| !s.is_empty()),
+                    description: text_arg(&args, "description").filter(|s| !s.is_empty()),
+                };
+                let event = apply_update(event, &json!({}))?;
+                let created = self.backend.create(&event).await?;
+                Ok(format!("Created:\n{}", created.line()))
+            }
+            "update" => {
+                let id = text_arg(&args, "id")
+                    .filter(|s| !s.is_empty())
+                    .ok_or_else(|| ToolError::InvalidArgs("Missing id (use list to find it)".to_string()))?;
+                let current = self.backend.get(&id).await?;
+                let before = current.line();
+                let updated = self.backend.update(&apply_update(current, &args)?).await?;
+                Ok(format!(
+                    "Updated:\n{}\n(was {})",
+                    updated.line(),
+                    before.trim_start_matches("- ")
+                ))
+            }
+            other => Err(ToolError::InvalidArgs(format!(
+                "Unknown action '{}': use list, create or update",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar_ics_google_and_update() {
+        let utc = |s: &str| EventTime::At(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc));
+        assert_eq!(
+            EventTime::parse("2026-10-18T15:00:00+08:00"),
+            Some(utc("2026-10-18T07:00:00Z"))
+        );
+        assert_eq!(
+            EventTime::parse("2026-10-18"),
+            Some(EventTime::AllDay(NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()))
+        );
+        assert_eq!(ics_duration("PT1H30M"), Some(Duration::minutes(90)));
+
+        let multistatus = "<d:multistatus xmlns:d=\"DAV:\" xmlns:cal=\"urn:ietf:params:xml:ns:caldav\"><d:response>\
+            <d:href>/cal/me/a.ics</d:href><d:propstat><d:prop><d:getetag>\"1\"</d:getetag>\
+            <cal:calendar-data>BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:a\r\nSUMMARY:Design review\\, v2\r\n\
+            DTSTART:20261018T070000Z\r\nDURATION:PT1H\r\nLOCATION:Room &amp; 3\r\nBEGIN:VALARM\r\nSUMMARY:ignored\r\n\
+            END:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n</cal:calendar-data></d:prop></d:propstat></d:response></d:multistatus>";
+        let events = parse_multistatus(multistatus);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(
+            (event.id.as_str(), event.title.as_str()),
+            ("/cal/me/a.ics", "Design review, v2")
+        );
+        assert_eq!(
+            (event.start, event.end),
+            (utc("2026-10-18T07:00:00Z"), utc("2026-10-18T08:00:00Z"))
+        );
+        assert_eq!(event.location.as_deref(), Some("Room & 3"));
+
+        // 只改开始时间：保持时长；ics_apply 保留 UID 与 VALARM
+        let moved = apply_update(event.clone(), &json!({"start": "2026-10-18T08:00:00Z"})).unwrap();
+        assert_eq!(moved.end, utc("2026-10-18T09:00:00Z"));
+        let raw = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:a\r\nSUMMARY:Design review\r\nDTSTART:20261018T070000Z\r\nDURATION:PT1H\r\nBEGIN:VALARM\r\nSUMMARY:ignored\r\nEND:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let applied = ics_apply(raw, &moved);
+        assert!(applied.contains("UID:a") && applied.contains("SUMMARY:ignored"));
+        assert!(
+            applied.contains("DTSTART:20261018T080000Z\r\nDTEND:20261018T090000Z") && !applied.contains("DURATION")
+        );
+        assert_eq!(parse_ics_event("x", &applied).unwrap().end, moved.end);
+        assert!(apply_update(event.clone(), &json!({"end": "2026-10-17"})).is_err());
+
+        // 时间未改动时 DTSTART / DTEND 原样保留（含 TZID），RRULE 始终保留；改动后沿用原 TZID
+        let zoned = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:z\r\nSUMMARY:Standup\r\n\
+            DTSTART;TZID=Europe/Berlin:20261019T093000\r\nDTEND;TZID=Europe/Berlin:20261019T094500\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let standup = parse_ics_event("z", zoned).unwrap();
+        let renamed = apply_update(standup.clone(), &json!({"title": "Daily"})).unwrap();
+        let applied = ics_apply(zoned, &renamed);
+        assert!(applied.contains("SUMMARY:Daily"));
+        assert!(applied.contains("DTSTART;TZID=Europe/Berlin:20261019T093000\r\n"));
+        assert!(applied.contains("DTEND;TZID=Europe/Berlin:20261019T094500\r\n"));
+        assert!(applied.contains("RRULE:FREQ=WEEKLY;BYDAY=MO"));
+        let later = apply_update(standup, &json!({"start": "2026-10-19T10:00"})).unwrap();
+        let applied = ics_apply(zoned, &later);
+        assert!(applied.contains("DTSTART;TZID=Europe/Berlin:20261019T100000\r\n"));
+        assert!(applied.contains("DTEND;TZID=Europe/Berlin:20261019T101500\r\n"));
+        assert!(applied.contains("RRULE:FREQ=WEEKLY;BYDAY=MO"));
+
+        // 事件 href 只能指向日历服务器本身
+        let caldav = CalDavCalendar::new(&CalDavSection {
+            url: "https://dav.example.com/cal/me".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(caldav.resource_url("/cal/me/a.ics").unwrap().as_str(), "https://dav.example.com/cal/me/a.ics");
+        assert!(caldav.resource_url("b.ics").is_ok());
+        assert!(caldav.resource_url("https://evil.example.com/a.ics").is_err());
+        assert!(caldav.resource_url("//evil.example.com/a.ics").is_err());
+        assert!(caldav.resource_url("http://dav.example.com/cal/me/a.ics").is_err());
+
+        let google =
+            json!({"id": "g1", "summary": "Lunch", "start": {"date": "2026-10-19"}, "end": {"date": "2026-10-20"}});
+        let lunch = parse_google_event(&google).unwrap();
+        assert!(lunch.line().contains("2026-10-19 (all day) Lunch [id: g1]"));
+        assert_eq!(google_event_json(&lunch)["start"], json!({"date": "2026-10-19"}));
+    }
+
+    #[tokio::test]
+    async fn test_caldav_ignores_off_origin_hrefs() {
+        // 服务器返回指向其他源的绝对 href：事件 id 原样保留，不被改写成本服务器上的路径
+        let multistatus = "<d:multistatus xmlns:d=\"DAV:\"><d:response>\
+            <d:href>https://evil.example.com/steal.ics</d:href><d:propstat><d:prop>\
+            <c:calendar-data>BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:x\r\nSUMMARY:Phish\r\n\
+            DTSTART:20261018T070000Z\r\nDURATION:PT1H\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n</c:calendar-data>\
+            </d:prop></d:propstat></d:response></d:multistatus>";
+        let events = parse_multistatus(multistatus);
+        assert_eq!(events[0].id, "https://evil.example.com/steal.ics");
+
+        // 跟随该 id 读取或改写时在发出请求（带凭据）之前就被拒绝
+        let caldav = CalDavCalendar::new(&CalDavSection {
+            url: "https://dav.example.com/cal/me".to_string(),
+            password_env: "BEE_TEST_CALDAV_PASSWORD_UNSET".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        for href in ["https://evil.example.com/steal.ics", "//evil.example.com/steal.ics"] {
+            assert!(matches!(caldav.get(href).await, Err(ToolError::InvalidArgs(_))));
+            let event = CalendarEvent {
+                id: href.to_string(),
+                ..events[0].clone()
+            };
+            assert!(matches!(caldav.update(&event).await, Err(ToolError::InvalidArgs(_))));
+        }
+        // 同源的绝对 href 可以使用
+        assert_eq!(
+            caldav.resource_url("https://dav.example.com/cal/me/a.ics").unwrap().as_str(),
+            "https://dav.example.com/cal/me/a.ics"
+        );
+    }
+}
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{resolve_in_root, Tool, ToolError};
 
 /// 代码编辑工具
 pub struct CodeEditTool {
@@ -42,34 +42,6 @@ impl CodeEditTool {
         self
     }
 
-    fn validate_path(&self, file_path: &str) -> Result<PathBuf, String> {
-        let path = Path::new(file_path);
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.allowed_root.join(path)
-        };
-
-        let canonical_path = match absolute_path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => absolute_path,
-        };
-
-        let allowed_canonical = match self.allowed_root.canonicalize() {
-            Ok(p) => p,
-            Err(_) => self.allowed_root.clone(),
-        };
-
-        if !canonical_path.starts_with(&allowed_canonical) {
-            return Err(format!(
-                "Access denied: path '{}' is outside allowed root",
-                file_path
-            ));
-        }
-
-        Ok(canonical_path)
-    }
-
     fn create_backup(&self, file_path: &Path) -> Result<PathBuf, String> {
         if !self.backup_enabled {
             return Ok(file_path.to_path_buf());
@@ -267,16 +239,19 @@ impl Tool for CodeEditTool {
 {"file_path": "src/main.rs", "old_string": "fn old() {}", "new_string": "fn new() {}"}"#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let file_path = args
             .get("file_path")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: file_path")?;
+            .ok_or_else(|| ToolError::missing("file_path"))?;
 
-        let validated_path = self.validate_path(file_path)?;
+        let validated_path = resolve_in_root(&self.allowed_root, file_path)?;
 
         if !validated_path.exists() {
-            return Err(format!("File not found: {}", validated_path.display()));
+            return Err(ToolError::NotFound(format!(
+                "File not found: {}",
+                validated_path.display()
+            )));
         }
 
         // 检查是否有批量编辑
@@ -312,7 +287,7 @@ impl Tool for CodeEditTool {
             }
 
             if fail_count > 0 {
-                return Err(output);
+                return Err(ToolError::Failed(output));
             }
             return Ok(output);
         }
@@ -321,12 +296,12 @@ impl Tool for CodeEditTool {
         let old_string = args
             .get("old_string")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: old_string")?;
+            .ok_or_else(|| ToolError::missing("old_string"))?;
 
         let new_string = args
             .get("new_string")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: new_string")?;
+            .ok_or_else(|| ToolError::missing("new_string"))?;
 
         let result = self.perform_edit(&validated_path, old_string, new_string)?;
         
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{resolve_in_root, Tool, ToolError};
 
 /// 代码搜索工具
 pub struct CodeGrepTool {
@@ -31,34 +31,6 @@ impl CodeGrepTool {
         self
     }
 
-    fn validate_path(&self, file_path: &str) -> Result<PathBuf, String> {
-        let path = Path::new(file_path);
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.allowed_root.join(path)
-        };
-
-        let canonical_path = match absolute_path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => absolute_path,
-        };
-
-        let allowed_canonical = match self.allowed_root.canonicalize() {
-            Ok(p) => p,
-            Err(_) => self.allowed_root.clone(),
-        };
-
-        if !canonical_path.starts_with(&allowed_canonical) {
-            return Err(format!(
-                "Access denied: path '{}' is outside allowed root",
-                file_path
-            ));
-        }
-
-        Ok(canonical_path)
-    }
-
     fn search_in_file(
         &self,
         file_path: &Path,
@@ -183,11 +155,11 @@ impl Tool for CodeGrepTool {
 {"pattern": "fn main", "include": "*.rs", "use_regex": false}"#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let pattern = args
             .get("pattern")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: pattern")?;
+            .ok_or_else(|| ToolError::missing("pattern"))?;
 
         let path = args
             .get("path")
@@ -201,7 +173,7 @@ impl Tool for CodeGrepTool {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
-        let search_path = self.validate_path(path)?;
+        let search_path = resolve_in_root(&self.allowed_root, path)?;
 
         let results = if search_path.is_file() {
             // 搜索单个文件
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{resolve_in_root, Tool, ToolError};
 
 /// 代码读取工具
 pub struct CodeReadTool {
@@ -34,43 +34,6 @@ impl CodeReadTool {
         self
     }
 
-    /// 验证路径是否在允许范围内
-    fn validate_path(&self, file_path: &str) -> Result<PathBuf, String> {
-        let path = Path::new(file_path);
-        
-        // 解析为绝对路径
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.allowed_root.join(path)
-        };
-
-        // 规范化路径
-        let canonical_path = match absolute_path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => {
-                // 文件可能不存在，使用绝对路径继续
-                absolute_path
-            }
-        };
-
-        // 安全检查：确保在允许目录内
-        let allowed_canonical = match self.allowed_root.canonicalize() {
-            Ok(p) => p,
-            Err(_) => self.allowed_root.clone(),
-        };
-
-        if !canonical_path.starts_with(&allowed_canonical) {
-            return Err(format!(
-                "Access denied: path '{}' is outside allowed root '{}'",
-                file_path,
-                self.allowed_root.display()
-            ));
-        }
-
-        Ok(canonical_path)
-    }
-
     /// 读取文件内容（带行号）
     fn read_file_with_lines(
         &self,
@@ -162,11 +125,11 @@ impl Tool for CodeReadTool {
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let file_path = args
             .get("file_path")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: file_path")?;
+            .ok_or_else(|| ToolError::missing("file_path"))?;
 
         let offset = args
             .get("offset")
@@ -181,17 +144,24 @@ impl Tool for CodeReadTool {
             .map(|v| v as usize)
             .or(Some(200));
 
-        let validated_path = self.validate_path(file_path)?;
+        let validated_path = resolve_in_root(&self.allowed_root, file_path)?;
         
         if !validated_path.exists() {
-            return Err(format!("File not found: {}", validated_path.display()));
+            return Err(ToolError::NotFound(format!(
+                "File not found: {}",
+                validated_path.display()
+            )));
         }
 
         if !validated_path.is_file() {
-            return Err(format!("Path is not a file: {}", validated_path.display()));
+            return Err(ToolError::InvalidArgs(format!(
+                "Path is not a file: {}",
+                validated_path.display()
+            )));
         }
 
         self.read_file_with_lines(&validated_path, offset, limit)
+            .map_err(ToolError::Failed)
     }
 }
 
@@ -210,12 +180,12 @@ mod tests {
         let tool = CodeReadTool::new(&test_dir);
         
         // 正常路径
-        assert!(tool.validate_path("src/main.rs").is_ok());
-        assert!(tool.validate_path("Cargo.toml").is_ok());
+        assert!(resolve_in_root(&tool.allowed_root, "src/main.rs").is_ok());
+        assert!(resolve_in_root(&tool.allowed_root, "Cargo.toml").is_ok());
         
         // 路径穿越攻击应该被阻止
-        assert!(tool.validate_path("../../../etc/passwd").is_err());
-        assert!(tool.validate_path("src/../../../etc/passwd").is_err());
+        assert!(resolve_in_root(&tool.allowed_root, "../../../etc/passwd").is_err());
+        assert!(resolve_in_root(&tool.allowed_root, "src/../../../etc/passwd").is_err());
         
         std::fs::remove_dir_all(&test_dir).ok();
     }
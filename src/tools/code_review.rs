@@ -6,7 +6,7 @@ use regex::Regex;
 use serde_json::Value;
 use walkdir::WalkDir;
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
 
 pub struct CodeReviewTool {
     allowed_extensions: Vec<String>,
@@ -413,13 +413,15 @@ impl Tool for CodeReviewTool {
         "Review code files for common issues. Args: {\"path\": \"file or dir\", \"focus\": \"all|security|performance|style|documentation\"}"
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
-        let path = args["path"].as_str().ok_or("Missing 'path'")?;
-        let path = self.validate_path(path).map_err(|e| e.to_string())?;
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let path = args["path"].as_str().ok_or_else(|| ToolError::missing("path"))?;
+        let path = self
+            .validate_path(path)
+            .map_err(|e| ToolError::PermissionDenied(e.to_string()))?;
         let _focus = args["focus"].as_str().unwrap_or("all");
         
         if !path.exists() {
-            return Err(format!("Path does not exist: {}", path.display()));
+            return Err(ToolError::NotFound(format!("Path does not exist: {}", path.display())));
         }
         
         let mut results = Vec::new();
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{resolve_in_root, Tool, ToolError};
 
 /// 代码写入工具
 pub struct CodeWriteTool {
@@ -23,34 +23,6 @@ impl CodeWriteTool {
         }
     }
 
-    fn validate_path(&self, file_path: &str) -> Result<PathBuf, String> {
-        let path = Path::new(file_path);
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.allowed_root.join(path)
-        };
-
-        let canonical_path = match absolute_path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => absolute_path,
-        };
-
-        let allowed_canonical = match self.allowed_root.canonicalize() {
-            Ok(p) => p,
-            Err(_) => self.allowed_root.clone(),
-        };
-
-        if !canonical_path.starts_with(&allowed_canonical) {
-            return Err(format!(
-                "Access denied: path '{}' is outside allowed root",
-                file_path
-            ));
-        }
-
-        Ok(canonical_path)
-    }
-
     fn ensure_parent_dir(&self, file_path: &Path) -> Result<(), String> {
         if let Some(parent) = file_path.parent() {
             std::fs::create_dir_all(parent)
@@ -82,16 +54,16 @@ impl Tool for CodeWriteTool {
 {"file_path": "src/new_module.rs", "content": "pub fn hello() {}", "overwrite": false}"#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let file_path = args
             .get("file_path")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: file_path")?;
+            .ok_or_else(|| ToolError::missing("file_path"))?;
 
         let content = args
             .get("content")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: content")?;
+            .ok_or_else(|| ToolError::missing("content"))?;
 
         let overwrite = args
             .get("overwrite")
@@ -99,21 +71,21 @@ impl Tool for CodeWriteTool {
             .unwrap_or(false);
 
         if content.len() > self.max_file_size {
-            return Err(format!(
+            return Err(ToolError::InvalidArgs(format!(
                 "Content too large: {} bytes (max: {})",
                 content.len(),
                 self.max_file_size
-            ));
+            )));
         }
 
-        let validated_path = self.validate_path(file_path)?;
+        let validated_path = resolve_in_root(&self.allowed_root, file_path)?;
 
         // 检查文件是否已存在
         if validated_path.exists() && !overwrite {
-            return Err(format!(
+            return Err(ToolError::InvalidArgs(format!(
                 "File already exists: {}. Use overwrite=true to overwrite.",
                 validated_path.display()
-            ));
+            )));
         }
 
         // 确保父目录存在
@@ -0,0 +1,298 @@
+//! 组合工具：由配置 [[tools.composites]] 注册，把多步工具调用合成一个工具
+//!
+//! 每步调用一个已注册的工具，args 中的字符串模板可引用：{{参数名}}（组合工具收到的 args）、
+//! {{prev}}（上一步输出）、{{步骤名}}（带 name 的步骤输出），并可加过滤器 trim / first_line / first_path
+//! （如 `{{prev|first_path}}` 取 code_grep 首个匹配的文件路径）。整串恰为一个占位符时保留原 JSON 类型，
+//! 引用未传入的可选参数时该字段省略。这样 grep→read 之类的常用多步操作只需一次 LLM 往返。
+//! 步骤直接调用工具，权限在组合工具层面检查：ToolPolicy 取各步骤中最高的风险等级，
+//! 动作与各步骤的动作取最严格者（某步被助手禁用时整个组合工具被拒绝，见 `ToolPolicy::with_composites`）。
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::{CompositeEntry, CompositeOutput, CompositeStep};
+use crate::tools::schema::{describe_violations, validate_args};
+use crate::tools::{Tool, ToolError, ToolRegistry};
+
+static PLACEHOLDER_RE: OnceLock<Regex> = OnceLock::new();
+
+fn placeholder_re() -> &'static Regex {
+    PLACEHOLDER_RE.get_or_init(|| {
+        Regex::new(r"\{\{\s*([A-Za-z0-9_.-]+)\s*(?:\|\s*([a-z_]+)\s*)?\}\}").expect("valid placeholder regex")
+    })
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn apply_filter(text: String, filter: Option<&str>) -> Result<String, ToolError> {
+    Ok(match filter {
+        None => text,
+        Some("trim") => text.trim().to_string(),
+        Some("first_line") => text
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or("")
+            .to_string(),
+        // 首个形如 `路径:` 的行中的路径（兼容 code_grep 与 grep -n 的输出）
+        Some("first_path") => text
+            .lines()
+            .filter_map(|l| l.trim().split_once(':').map(|(head, _)| head))
+            .find(|head| !head.is_empty() && !head.contains(char::is_whitespace) && head.contains(['/', '.']))
+            .unwrap_or("")
+            .to_string(),
+        Some(other) => {
+            return Err(ToolError::InvalidArgs(format!(
+                "Unknown template filter '{}': use trim, first_line or first_path",
+                other
+            )))
+        }
+    })
+}
+
+/// 渲染模板；返回 None 表示该值整体引用了未传入的变量，调用方应省略该字段
+fn render(template: &Value, vars: &HashMap<String, Value>) -> Result<Option<Value>, ToolError> {
+    match template {
+        Value::String(s) => {
+            let re = placeholder_re();
+            if let Some(caps) = re
+                .captures(s)
+                .filter(|c| c.get(0).map(|m| m.as_str().len()) == Some(s.len()))
+            {
+                let Some(value) = vars.get(&caps[1]) else {
+                    return Ok(None);
+                };
+                return Ok(Some(match caps.get(2) {
+                    None => value.clone(),
+                    Some(f) => Value::String(apply_filter(value_text(value), Some(f.as_str()))?),
+                }));
+            }
+            let mut out = String::with_capacity(s.len());
+            let mut last = 0;
+            for caps in re.captures_iter(s) {
+                let m = caps.get(0).expect("whole match");
+                out.push_str(&s[last..m.start()]);
+                if let Some(value) = vars.get(&caps[1]) {
+                    out.push_str(&apply_filter(value_text(value), caps.get(2).map(|f| f.as_str()))?);
+                }
+                last = m.end();
+            }
+            out.push_str(&s[last..]);
+            Ok(Some(Value::String(out)))
+        }
+        Value::Array(items) => Ok(Some(Value::Array(
+            items
+                .iter()
+                .map(|v| render(v, vars))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+        ))),
+        Value::Object(map) => {
+            let mut out = serde_json::Map::new();
+            for (k, v) in map {
+                if let Some(v) = render(v, vars)? {
+                    out.insert(k.clone(), v);
+                }
+            }
+            Ok(Some(Value::Object(out)))
+        }
+        other => Ok(Some(other.clone())),
+    }
+}
+
+/// 给步骤错误加上步骤信息，保留错误类别
+fn step_error(index: usize, tool: &str, e: ToolError) -> ToolError {
+    let msg = format!("step {} ({}) failed: {}", index + 1, tool, e.message());
+    match e {
+        ToolError::InvalidArgs(_) => ToolError::InvalidArgs(msg),
+        ToolError::PermissionDenied(_) => ToolError::PermissionDenied(msg),
+        ToolError::NotFound(_) => ToolError::NotFound(msg),
+        ToolError::Transient(_) => ToolError::Transient(msg),
+        ToolError::Failed(_) => ToolError::Failed(msg),
+    }
+}
+
+/// 从配置项构建的组合工具
+pub struct CompositeTool {
+    name: String,
+    description: String,
+    params: Vec<String>,
+    steps: Vec<(CompositeStep, Arc<dyn Tool>)>,
+    output: CompositeOutput,
+}
+
+impl CompositeTool {
+    /// 从已注册的工具中解析各步骤；引用了不存在的工具时返回错误（组合工具不注册）
+    pub fn new(entry: &CompositeEntry, registry: &ToolRegistry) -> Result<Self, String> {
+        if entry.steps.is_empty() {
+            return Err(format!("composite tool {} has no steps", entry.name));
+        }
+        let steps = entry
+            .steps
+            .iter()
+            .map(|step| {
+                registry
+                    .get(&step.tool)
+                    .map(|tool| (step.clone(), tool))
+                    .ok_or_else(|| format!("composite tool {} uses unknown tool {}", entry.name, step.tool))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            name: entry.name.clone(),
+            description: entry.description.clone(),
+            params: entry.params.clone(),
+            steps,
+            output: entry.output,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for CompositeTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        let properties: serde_json::Map<String, Value> =
+            self.params.iter().map(|p| (p.clone(), serde_json::json!({}))).collect();
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": self.params,
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let mut vars: HashMap<String, Value> = args
+            .as_object()
+            .map(|o| o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+        let mut sections = Vec::with_capacity(self.steps.len());
+        let mut last = String::new();
+        for (i, (step, tool)) in self.steps.iter().enumerate() {
+            let step_args = render(&Value::Object(step.args.clone()), &vars)?.unwrap_or(Value::Null);
+            let violations = validate_args(&tool.parameters_schema(), &step_args);
+            let result = if violations.is_empty() {
+                tool.execute(step_args).await
+            } else {
+                Err(ToolError::InvalidArgs(describe_violations(&step.tool, &violations)))
+            };
+            let output = match result {
+                Ok(output) => output,
+                Err(e) if step.continue_on_error => format!("(failed: {})", e),
+                Err(e) => return Err(step_error(i, &step.tool, e)),
+            };
+            tracing::debug!(composite = %self.name, step = i + 1, tool = %step.tool, "composite step done");
+            sections.push(format!("## {}. {}\n{}", i + 1, step.tool, output));
+            if let Some(name) = &step.name {
+                vars.insert(name.clone(), Value::String(output.clone()));
+            }
+            vars.insert("prev".to_string(), Value::String(output.clone()));
+            last = output;
+        }
+        Ok(match self.output {
+            CompositeOutput::All => sections.join("\n\n"),
+            CompositeOutput::Last => last,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::tools::EchoTool;
+
+    /// 返回 args 的 JSON 文本，便于检查模板渲染结果
+    struct ArgsTool;
+
+    #[async_trait]
+    impl Tool for ArgsTool {
+        fn name(&self) -> &str {
+            "args"
+        }
+
+        fn description(&self) -> &str {
+            "echo args"
+        }
+
+        async fn execute(&self, args: Value) -> Result<String, ToolError> {
+            Ok(args.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_composite_pipes_outputs_between_steps() {
+        let mut registry = ToolRegistry::new();
+        registry.register(EchoTool);
+        registry.register(ArgsTool);
+        let entry: CompositeEntry = toml::from_str(
+            r#"
+            name = "find_and_read"
+            description = "grep then read"
+            params = ["pattern"]
+            output = "last"
+
+            [[steps]]
+            tool = "echo"
+            name = "hits"
+            args = { text = "Found 2 matches for 'x':\nsrc/lib.rs:12: fn {{pattern}}\nsrc/main.rs:3: {{pattern}}()" }
+
+            [[steps]]
+            tool = "args"
+            args = { file = "{{hits|first_path}}", limit = "{{limit}}", note = "line {{prev|first_line}}", missing = "{{nope}}" }
+            "#,
+        )
+        .unwrap();
+        let tool = CompositeTool::new(&entry, &registry).unwrap();
+        assert_eq!(tool.parameters_schema()["required"], json!(["pattern"]));
+
+        let out = tool.execute(json!({"pattern": "run", "limit": 20})).await.unwrap();
+        let rendered: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            rendered,
+            json!({"file": "src/lib.rs", "limit": 20, "note": "line Found 2 matches for 'x':"})
+        );
+
+        // 步骤参数不符合该步工具的 schema 时报告是第几步
+        let bad: CompositeEntry = toml::from_str(
+            r#"
+            name = "bad"
+            description = "x"
+            steps = [{ tool = "echo", args = { text = "{{count}}" } }]
+            "#,
+        )
+        .unwrap();
+        let err = CompositeTool::new(&bad, &registry)
+            .unwrap()
+            .execute(json!({"count": 3}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArgs(ref m) if m.starts_with("step 1 (echo) failed")));
+        assert!(CompositeTool::new(
+            &CompositeEntry {
+                steps: vec![],
+                ..bad.clone()
+            },
+            &registry
+        )
+        .is_err());
+    }
+}
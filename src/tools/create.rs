@@ -8,7 +8,9 @@ use async_trait::async_trait;
 use serde_json::Value;
 
 use super::send::CURRENT_ASSISTANT_ID;
-use crate::tools::Tool;
+use crate::core::mailbox::p2p_group_id;
+use crate::core::workspace_store::{GroupInfo, GroupRepository, SqliteWorkspaceStore};
+use crate::tools::{Tool, ToolError};
 
 /// 动态 agent 持久化结构
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -21,12 +23,6 @@ pub struct DynamicAgent {
 }
 
 const AGENTS_FILE: &str = "agents.json";
-const P2P_PREFIX: &str = "p2p_";
-
-fn p2p_group_id(a: &str, b: &str) -> String {
-    let (x, y) = if a <= b { (a, b) } else { (b, a) };
-    format!("{}{}_{}", P2P_PREFIX, x, y)
-}
 
 /// create 工具：创建 sub-agent
 pub struct CreateTool {
@@ -44,10 +40,6 @@ impl CreateTool {
         self.workspace.join(AGENTS_FILE)
     }
 
-    fn groups_path(&self) -> std::path::PathBuf {
-        self.workspace.join("groups.json")
-    }
-
     fn sessions_dir(&self) -> std::path::PathBuf {
         self.workspace.join("sessions")
     }
@@ -67,18 +59,17 @@ impl CreateTool {
         }
     }
 
-    fn load_groups(&self) -> std::collections::HashMap<String, GroupInfo> {
-        let data = match std::fs::read_to_string(self.groups_path()) {
-            Ok(d) => d,
-            Err(_) => return std::collections::HashMap::new(),
-        };
-        serde_json::from_str(&data).unwrap_or_default()
-    }
-
-    fn save_groups(&self, groups: &std::collections::HashMap<String, GroupInfo>) {
-        if let Ok(json) = serde_json::to_string_pretty(groups) {
-            let _ = std::fs::write(self.groups_path(), json);
-        }
+    /// 与 creator 建立 P2P 群（已存在则跳过）
+    fn ensure_p2p_group(&self, parent_id: &str, id: &str) -> Result<(), String> {
+        let group = GroupInfo::new(
+            p2p_group_id(parent_id, id),
+            Some(format!("P2P {} ↔ {}", parent_id, id)),
+            vec![parent_id.to_string(), id.to_string()],
+        );
+        SqliteWorkspaceStore::open(&self.workspace)
+            .and_then(|store| store.insert_group_if_absent(&group))
+            .map(|_| ())
+            .map_err(|e| format!("failed to save P2P group: {}", e))
     }
 
     /// 直接创建 agent（供 API 等非 Tool 场景使用，显式指定 parent_id）
@@ -108,32 +99,11 @@ impl CreateTool {
         let mut agents = self.load_agents();
         agents.push(agent.clone());
         self.save_agents(&agents);
-        let group_id = p2p_group_id(parent_id, &id);
-        let mut groups = self.load_groups();
-        if !groups.contains_key(&group_id) {
-            groups.insert(
-                group_id.clone(),
-                GroupInfo {
-                    id: group_id.clone(),
-                    name: Some(format!("P2P {} ↔ {}", parent_id, id)),
-                    member_ids: vec![parent_id.to_string(), id.clone()],
-                    created_at: chrono::Utc::now().to_rfc3339(),
-                },
-            );
-            self.save_groups(&groups);
-        }
+        self.ensure_p2p_group(parent_id, &id)?;
         Ok(agent)
     }
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-struct GroupInfo {
-    id: String,
-    name: Option<String>,
-    member_ids: Vec<String>,
-    created_at: String,
-}
-
 #[async_trait]
 impl Tool for CreateTool {
     fn name(&self) -> &str {
@@ -161,7 +131,7 @@ impl Tool for CreateTool {
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let role = args
             .get("role")
             .and_then(|v| v.as_str())
@@ -175,7 +145,7 @@ impl Tool for CreateTool {
             .filter(|s| !s.is_empty());
 
         if role.is_empty() {
-            return Err("create: 'role' is required".to_string());
+            return Err(ToolError::InvalidArgs("create: 'role' is required".to_string()));
         }
 
         let parent_id = CURRENT_ASSISTANT_ID
@@ -198,20 +168,7 @@ impl Tool for CreateTool {
         agents.push(agent.clone());
         self.save_agents(&agents);
 
-        let group_id = p2p_group_id(&parent_id, &id);
-        let mut groups = self.load_groups();
-        if !groups.contains_key(&group_id) {
-            groups.insert(
-                group_id.clone(),
-                GroupInfo {
-                    id: group_id.clone(),
-                    name: Some(format!("P2P {} ↔ {}", parent_id, id)),
-                    member_ids: vec![parent_id.clone(), id.clone()],
-                    created_at: chrono::Utc::now().to_rfc3339(),
-                },
-            );
-            self.save_groups(&groups);
-        }
+        self.ensure_p2p_group(&parent_id, &id).map_err(ToolError::Failed)?;
 
         Ok(format!(
             "Sub-agent created: id={}, role={}. Use send tool to message it: send({{to: \"{}\", content: \"...\"}}).",
@@ -5,40 +5,18 @@ use std::path::Path;
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
-
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-struct GroupInfo {
-    id: String,
-    name: Option<String>,
-    member_ids: Vec<String>,
-    created_at: String,
-}
+use crate::core::workspace_store::{GroupInfo, GroupRepository, SqliteWorkspaceStore};
+use crate::tools::{Tool, ToolError};
 
 /// create_group 工具：创建群聊（≥2 人）
 pub struct CreateGroupTool {
-    groups_path: std::path::PathBuf,
+    workspace: std::path::PathBuf,
 }
 
 impl CreateGroupTool {
     pub fn new(workspace: &Path) -> Self {
         Self {
-            groups_path: workspace.join("groups.json"),
-        }
-    }
-
-    fn load_groups(&self) -> std::collections::HashMap<String, GroupInfo> {
-        let data = match std::fs::read_to_string(&self.groups_path) {
-            Ok(d) => d,
-            Err(_) => return std::collections::HashMap::new(),
-        };
-        serde_json::from_str(&data).unwrap_or_default()
-    }
-
-    fn save_groups(&self, groups: &std::collections::HashMap<String, GroupInfo>) {
-        std::fs::create_dir_all(self.groups_path.parent().unwrap()).ok();
-        if let Ok(json) = serde_json::to_string_pretty(groups) {
-            let _ = std::fs::write(&self.groups_path, json);
+            workspace: workspace.to_path_buf(),
         }
     }
 }
@@ -71,7 +49,7 @@ impl Tool for CreateGroupTool {
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let member_ids: Vec<String> = args
             .get("member_ids")
             .and_then(|v| v.as_array())
@@ -89,7 +67,7 @@ impl Tool for CreateGroupTool {
             .filter(|s| !s.is_empty());
 
         if member_ids.len() < 2 {
-            return Err("create_group: member_ids must have at least 2 agents".to_string());
+            return Err(ToolError::InvalidArgs("create_group: member_ids must have at least 2 agents".to_string()));
         }
 
         let dedup: Vec<String> = member_ids
@@ -103,20 +81,18 @@ impl Tool for CreateGroupTool {
             .1;
 
         if dedup.len() < 2 {
-            return Err("create_group: need at least 2 distinct agent ids".to_string());
+            return Err(ToolError::InvalidArgs("create_group: need at least 2 distinct agent ids".to_string()));
         }
 
         let id = uuid::Uuid::new_v4().to_string();
-        let group = GroupInfo {
-            id: id.clone(),
-            name: name.or_else(|| Some(format!("群聊 {}", &id[..8]))),
-            member_ids: dedup.clone(),
-            created_at: chrono::Utc::now().to_rfc3339(),
-        };
-
-        let mut groups = self.load_groups();
-        groups.insert(id.clone(), group);
-        self.save_groups(&groups);
+        let group = GroupInfo::new(
+            id.clone(),
+            name.or_else(|| Some(format!("群聊 {}", &id[..8]))),
+            dedup.clone(),
+        );
+        SqliteWorkspaceStore::open(&self.workspace)
+            .and_then(|store| store.upsert_group(&group))
+            .map_err(|e| ToolError::Failed(format!("create_group: failed to save group: {}", e)))?;
 
         Ok(format!(
             "Group created: id={}, members=[{}]. Use send to message agents, or users can chat in this group via the UI.",
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use serde_json::{json, Value};
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
 use crate::llm::LlmClient;
 use crate::memory::Message;
 
@@ -182,7 +182,7 @@ impl Tool for DeepSearchTool {
         "Conduct deep research on a complex topic through multiple rounds of autonomous search. Automatically decomposes query, performs iterative searches, and synthesizes findings. Args: {\"topic\": \"research question\", \"max_rounds\": 3 (optional)}"
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let topic = args
             .get("topic")
             .and_then(|v| v.as_str())
@@ -190,7 +190,7 @@ impl Tool for DeepSearchTool {
             .trim();
 
         if topic.is_empty() {
-            return Err("Missing topic".to_string());
+            return Err(ToolError::InvalidArgs("Missing topic".to_string()));
         }
 
         let max_rounds = args
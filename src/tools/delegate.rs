@@ -0,0 +1,253 @@
+//! delegate 工具：在 ReAct 循环内把子任务交给子 Agent
+//!
+//! 子 Agent 是一个独立的 ReactSession：全新的上下文（不读写父会话的对话与长期记忆）、受限的工具集与步数预算，
+//! 其最终回答作为 Observation 返回给父 Agent。子 Agent 可用的工具不超过父 Agent 的技能范围，
+//! 工具调用同样经过 [tools.policy]（需审批的调用在子任务中无人审批，视为拒绝）；子任务中不能再委派。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::config::AppConfig;
+use crate::core::RecoveryEngine;
+use crate::llm::LlmClient;
+use crate::react::{react_loop_v2, ContextManager, Guardrails, Planner, ReactLimits, ReactSession};
+use crate::tools::{Tool, ToolError, ToolExecutor, ToolPolicy, ToolRegistry};
+
+tokio::task_local! {
+    /// 当前 ReAct 循环允许的工具（None 或空表示全部），由循环在执行工具时设置，delegate 据此限制子 Agent
+    pub static CURRENT_ALLOWED_TOOLS: Option<Vec<String>>;
+}
+
+/// 子 Agent 的对话轮数上限
+const CHILD_MAX_TURNS: usize = 20;
+
+/// delegate：持有注册时其它工具的快照（不含自身）与共享的 LLM
+pub struct DelegateTool {
+    llm: Arc<dyn LlmClient>,
+    tools: ToolRegistry,
+    config: AppConfig,
+    /// 子 Agent 同样经过 [guardrails] 规则（不含 LLM 分类器）
+    guardrails: Option<Guardrails>,
+}
+
+impl DelegateTool {
+    /// 从已注册的工具生成快照（应在其它工具注册完成后、tool_help 之前调用）
+    pub fn from_registry(registry: &ToolRegistry, llm: Arc<dyn LlmClient>, config: &AppConfig) -> Self {
+        Self {
+            llm,
+            tools: registry.subset(&registry.tool_names()),
+            config: config.clone(),
+            guardrails: config.guardrails.enabled.then(|| Guardrails::new(&config.guardrails)),
+        }
+    }
+
+    /// 子 Agent 的工具：请求的工具与父 Agent 技能范围的交集（未指定时继承父 Agent 的全部工具）
+    fn child_tools(&self, requested: Option<Vec<String>>) -> Result<Vec<String>, ToolError> {
+        let parent: Vec<String> = CURRENT_ALLOWED_TOOLS
+            .try_with(|a| a.clone())
+            .ok()
+            .flatten()
+            .filter(|a| !a.is_empty())
+            .unwrap_or_else(|| self.tools.tool_names());
+        let mut tools: Vec<String> = match requested {
+            Some(requested) => {
+                let denied: Vec<&String> = requested.iter().filter(|t| !parent.contains(t)).collect();
+                if !denied.is_empty() {
+                    return Err(ToolError::InvalidArgs(format!(
+                        "Tools not available to this agent: {}",
+                        denied.iter().map(|t| t.as_str()).collect::<Vec<_>>().join(", ")
+                    )));
+                }
+                requested
+            }
+            None => parent,
+        };
+        tools.retain(|t| t != self.name() && self.tools.get(t).is_some());
+        tools.sort();
+        tools.dedup();
+        Ok(tools)
+    }
+
+    fn child_prompt(role: Option<&str>, tools: &ToolRegistry) -> String {
+        let role = role.map(|r| format!(" acting as: {}", r)).unwrap_or_default();
+        format!(
+            "You are a sub-agent{} that another agent delegated one task to.\n\
+             Work on the task with the tools below. To call a tool, output only JSON: {{\"tool\": \"<name>\", \"args\": {{...}}}}.\n\
+             When done, reply with the final answer in plain text. It is returned verbatim to the delegating agent, \
+             which cannot see your tool calls, so include every fact it needs.\n\n\
+             ## Tool call JSON Schema\n```json\n{}\n```",
+            role,
+            tools.to_schema_json()
+        )
+    }
+}
+
+#[async_trait]
+impl Tool for DelegateTool {
+    fn name(&self) -> &str {
+        "delegate"
+    }
+
+    fn description(&self) -> &str {
+        "Delegate a self-contained subtask to a specialist sub-agent and get its final answer. Args: task (string), role?, context?, tools? (array), max_steps?.\n\n\
+         The sub-agent starts with an empty conversation: put everything it needs in task / context. \
+         tools limits what it may use (default: all of your tools); max_steps is its step budget. \
+         It cannot delegate further. Use it to fan out independent research or review work."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "task": {
+                    "type": "string",
+                    "description": "The subtask, stated completely"
+                },
+                "role": {
+                    "type": "string",
+                    "description": "Specialist role, e.g. \"security reviewer\""
+                },
+                "context": {
+                    "type": "string",
+                    "description": "Background the sub-agent needs (it cannot see this conversation)"
+                },
+                "tools": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Tools the sub-agent may use"
+                },
+                "max_steps": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Step budget"
+                }
+            },
+            "required": ["task"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let task = args
+            .get("task")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| ToolError::missing("task"))?;
+        let role = args
+            .get("role")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty());
+        let background = args
+            .get("context")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty());
+        let requested = match args.get("tools") {
+            None | Some(Value::Null) => None,
+            Some(Value::Array(items)) => Some(
+                items
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or_else(|| ToolError::InvalidArgs("tools must be an array of tool names".into()))?,
+            ),
+            Some(_) => return Err(ToolError::InvalidArgs("tools must be an array of tool names".into())),
+        };
+        let section = &self.config.tools.delegate;
+        let max_steps = args
+            .get("max_steps")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(section.max_steps)
+            .clamp(1, section.max_steps_limit.max(1));
+
+        let tool_names = self.child_tools(requested)?;
+        let registry = self.tools.subset(&tool_names);
+        let planner = Planner::new(Arc::clone(&self.llm), Self::child_prompt(role, &registry));
+        let executor = ToolExecutor::new(registry, self.config.tools.tool_timeout_secs)
+            .with_limits(&self.config.tools.limits)
+            .with_policy(
+                self.config
+                    .tools
+                    .policy
+                    .enabled
+                    .then(|| ToolPolicy::from(self.config.tools.policy.clone()).with_composites(&self.config.tools.composites)),
+            );
+        let recovery = RecoveryEngine::new();
+        let session = ReactSession::new(
+            &planner,
+            &executor,
+            &recovery,
+            tokio_util::sync::CancellationToken::new(),
+        )
+        .with_allowed_tools(&tool_names)
+        .with_limits(ReactLimits::default().with_overrides(Some(max_steps), None))
+        .with_guardrails(self.guardrails.as_ref());
+        let mut context = ContextManager::new(CHILD_MAX_TURNS).with_suggestions(false);
+        let input = match background {
+            Some(background) => format!("{}\n\nContext:\n{}", task, background),
+            None => task.to_string(),
+        };
+        tracing::info!(role = role.unwrap_or("-"), tools = ?tool_names, max_steps, "delegating subtask");
+        let result = react_loop_v2(&session, &mut context, &input)
+            .await
+            .map_err(|e| ToolError::Failed(format!("Sub-agent failed: {}", e)))?;
+        Ok(format!(
+            "Sub-agent{} answer:\n{}",
+            role.map(|r| format!(" ({})", r)).unwrap_or_default(),
+            result.response
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::react::replay::RecordedToolCall;
+    use crate::react::ReplayFixture;
+
+    #[tokio::test]
+    async fn test_delegate_runs_scoped_child() {
+        let fixture = ReplayFixture {
+            llm_responses: vec![
+                r#"{"tool": "cat", "args": {"path": "notes.md"}}"#.into(),
+                "The notes list two todo items.".into(),
+            ],
+            tool_calls: vec![RecordedToolCall {
+                tool: "cat".into(),
+                args: json!({"path": "notes.md"}),
+                output: Ok("- buy milk\n- call Bob".into()),
+            }],
+        };
+        let mut registry = fixture.tool_registry();
+        registry.register(crate::tools::EchoTool);
+        let delegate = DelegateTool::from_registry(&registry, Arc::new(fixture.llm_client()), &AppConfig::default());
+
+        // 父 Agent 只允许 cat：申请 echo 被拒绝，未指定时只继承 cat
+        let parent = Some(vec!["cat".to_string(), "delegate".to_string()]);
+        let err = CURRENT_ALLOWED_TOOLS
+            .scope(
+                parent.clone(),
+                delegate.execute(json!({"task": "x", "tools": ["echo"]})),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("echo"));
+
+        let out = CURRENT_ALLOWED_TOOLS
+            .scope(
+                parent,
+                delegate.execute(json!({"task": "Summarize notes.md", "role": "reader", "max_steps": 3})),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out, "Sub-agent (reader) answer:\nThe notes list two todo items.");
+
+        let prompt = DelegateTool::child_prompt(Some("reader"), &registry.subset(&["cat"]));
+        assert!(prompt.contains("acting as: reader"));
+        assert!(prompt.contains("\"cat\"") && !prompt.contains("\"echo\""));
+    }
+}
@@ -0,0 +1,368 @@
+//! 桌面工具（desktop feature）：剪贴板读写与截图
+//!
+//! clipboard 通过 arboard 读写系统剪贴板文本；screenshot 调用系统截图命令（macOS screencapture，
+//! Linux grim / scrot / ImageMagick import / gnome-screenshot，Windows PowerShell）截取整个屏幕、
+//! 当前窗口或指定区域，保存到 workspace/screenshots/（隔离用户为其工作区下的 screenshots/），可再交给 image_read 识别。
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::tools::{scoped_root, Tool, ToolError};
+
+/// 截图保存目录（相对 workspace）
+pub const SCREENSHOT_DIR: &str = "screenshots";
+/// 读取剪贴板时返回的最大字符数
+const MAX_CLIPBOARD_CHARS: usize = 20000;
+/// 截图命令超时
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// clipboard 工具
+pub struct ClipboardTool {
+    /// Linux 上剪贴板内容归写入进程所有，保留同一实例以免设置后内容随实例释放而丢失
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl ClipboardTool {
+    pub fn new() -> Self {
+        Self {
+            clipboard: Mutex::new(None),
+        }
+    }
+
+    fn with_clipboard<T>(
+        &self,
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, ToolError> {
+        let mut guard = self.clipboard.lock().unwrap_or_else(|e| e.into_inner());
+        if guard.is_none() {
+            *guard = Some(
+                arboard::Clipboard::new().map_err(|e| ToolError::Failed(format!("Clipboard unavailable: {}", e)))?,
+            );
+        }
+        f(guard.as_mut().expect("clipboard initialized"))
+            .map_err(|e| ToolError::Failed(format!("Clipboard error: {}", e)))
+    }
+}
+
+impl Default for ClipboardTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for ClipboardTool {
+    fn name(&self) -> &str {
+        "clipboard"
+    }
+
+    fn description(&self) -> &str {
+        r#"读写系统剪贴板文本。
+
+参数:
+- action: get | set（必需）
+- text: set 时写入的文本
+
+示例:
+{"action": "get"}
+{"action": "set", "text": "已整理好的会议纪要"}"#
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let action = args
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("action"))?;
+        match action {
+            "get" => {
+                let text = match self.with_clipboard(|c| c.get_text()) {
+                    Ok(text) => text,
+                    Err(ToolError::Failed(msg)) if msg.contains("empty") || msg.contains("not available") => {
+                        return Ok("Clipboard has no text".to_string())
+                    }
+                    Err(e) => return Err(e),
+                };
+                let total = text.chars().count();
+                if total > MAX_CLIPBOARD_CHARS {
+                    let head: String = text.chars().take(MAX_CLIPBOARD_CHARS).collect();
+                    return Ok(format!(
+                        "{}\n... (truncated, {} of {} chars)",
+                        head, MAX_CLIPBOARD_CHARS, total
+                    ));
+                }
+                Ok(text)
+            }
+            "set" => {
+                let text = args
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ToolError::missing("text"))?
+                    .to_string();
+                let chars = text.chars().count();
+                self.with_clipboard(|c| c.set_text(text))?;
+                Ok(format!("✓ Copied {} chars to clipboard", chars))
+            }
+            other => Err(ToolError::InvalidArgs(format!(
+                "Unknown action '{}': use get or set",
+                other
+            ))),
+        }
+    }
+}
+
+/// 截图范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Screen,
+    /// 当前（前台）窗口
+    Window,
+    Region {
+        x: i64,
+        y: i64,
+        width: i64,
+        height: i64,
+    },
+}
+
+fn parse_target(args: &Value) -> Result<Target, ToolError> {
+    match args.get("target").and_then(|v| v.as_str()).unwrap_or("screen") {
+        "screen" => Ok(Target::Screen),
+        "window" => Ok(Target::Window),
+        "region" => {
+            let num = |k: &str| {
+                args.get(k)
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ToolError::InvalidArgs(format!("region requires integer {}", k)))
+            };
+            let (width, height) = (num("width")?, num("height")?);
+            if width <= 0 || height <= 0 {
+                return Err(ToolError::InvalidArgs("width and height must be positive".to_string()));
+            }
+            Ok(Target::Region {
+                x: num("x")?,
+                y: num("y")?,
+                width,
+                height,
+            })
+        }
+        other => Err(ToolError::InvalidArgs(format!(
+            "Unknown target '{}': use screen, window or region",
+            other
+        ))),
+    }
+}
+
+fn cmd(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|s| s.to_string()).collect()
+}
+
+/// 各平台可用的截图命令（按优先级，依次尝试未安装的跳过）；macOS 的 window 先换算成区域
+fn screenshot_commands(os: &str, wayland: bool, target: Target, out: &str) -> Vec<Vec<String>> {
+    match (os, target) {
+        ("macos", Target::Region { x, y, width, height }) => {
+            vec![cmd(&[
+                "screencapture",
+                "-x",
+                "-R",
+                &format!("{},{},{},{}", x, y, width, height),
+                out,
+            ])]
+        }
+        ("macos", _) => vec![cmd(&["screencapture", "-x", out])],
+        ("windows", Target::Window) => Vec::new(),
+        ("windows", target) => {
+            let bounds = match target {
+                Target::Region { x, y, width, height } => {
+                    format!("New-Object Drawing.Rectangle({}, {}, {}, {})", x, y, width, height)
+                }
+                _ => "[Windows.Forms.SystemInformation]::VirtualScreen".to_string(),
+            };
+            let script = format!(
+                "Add-Type -AssemblyName System.Windows.Forms,System.Drawing; $b = {}; \
+                 $bmp = New-Object Drawing.Bitmap($b.Width, $b.Height); $g = [Drawing.Graphics]::FromImage($bmp); \
+                 $g.CopyFromScreen($b.X, $b.Y, 0, 0, $bmp.Size); $bmp.Save('{}', [Drawing.Imaging.ImageFormat]::Png)",
+                bounds,
+                out.replace('\'', "''")
+            );
+            vec![cmd(&["powershell", "-NoProfile", "-Command", &script])]
+        }
+        (_, Target::Screen) if wayland => vec![cmd(&["grim", out]), cmd(&["gnome-screenshot", "-f", out])],
+        (_, Target::Screen) => vec![
+            cmd(&["scrot", "-o", out]),
+            cmd(&["import", "-window", "root", out]),
+            cmd(&["gnome-screenshot", "-f", out]),
+        ],
+        (_, Target::Window) if wayland => vec![cmd(&["gnome-screenshot", "-w", "-f", out])],
+        (_, Target::Window) => vec![
+            cmd(&["scrot", "-o", "-u", out]),
+            cmd(&["gnome-screenshot", "-w", "-f", out]),
+        ],
+        (_, Target::Region { x, y, width, height }) if wayland => {
+            vec![cmd(&["grim", "-g", &format!("{},{} {}x{}", x, y, width, height), out])]
+        }
+        (_, Target::Region { x, y, width, height }) => vec![
+            cmd(&["scrot", "-o", "-a", &format!("{},{},{},{}", x, y, width, height), out]),
+            cmd(&[
+                "import",
+                "-window",
+                "root",
+                "-crop",
+                &format!("{}x{}+{}+{}", width, height, x, y),
+                out,
+            ]),
+        ],
+    }
+}
+
+async fn run(command: &[String]) -> std::io::Result<std::process::Output> {
+    tokio::time::timeout(
+        SCREENSHOT_TIMEOUT,
+        tokio::process::Command::new(&command[0])
+            .args(&command[1..])
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"))?
+}
+
+/// macOS 前台窗口的位置与大小（需「辅助功能」权限）
+async fn macos_front_window() -> Result<Target, ToolError> {
+    let script = "tell application \"System Events\" to tell (first process whose frontmost is true) \
+                  to get {position, size} of front window";
+    let output = run(&cmd(&["osascript", "-e", script]))
+        .await
+        .map_err(|e| ToolError::Failed(format!("osascript failed: {}", e)))?;
+    let nums: Vec<i64> = String::from_utf8_lossy(&output.stdout)
+        .split(',')
+        .filter_map(|s| s.trim().parse().ok())
+        .collect();
+    match nums[..] {
+        [x, y, width, height] if output.status.success() => Ok(Target::Region { x, y, width, height }),
+        _ => Err(ToolError::Failed(format!(
+            "Cannot get the front window bounds: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+/// screenshot 工具
+pub struct ScreenshotTool {
+    workspace: PathBuf,
+}
+
+impl ScreenshotTool {
+    pub fn new(workspace: impl AsRef<Path>) -> Self {
+        Self {
+            workspace: workspace.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ScreenshotTool {
+    fn name(&self) -> &str {
+        "screenshot"
+    }
+
+    fn description(&self) -> &str {
+        r#"截取屏幕并保存到 workspace/screenshots/，返回图片路径（可再用 image_read 识别内容）。
+
+参数:
+- target: screen（默认，整个屏幕）| window（当前窗口）| region（指定区域）
+- x, y, width, height: target 为 region 时的区域（像素）
+
+示例:
+{"target": "window"}
+{"target": "region", "x": 0, "y": 0, "width": 800, "height": 600}"#
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let mut target = parse_target(&args)?;
+        let os = std::env::consts::OS;
+        if os == "macos" && target == Target::Window {
+            target = macos_front_window().await?;
+        }
+        let dir = scoped_root(&self.workspace).join(SCREENSHOT_DIR);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| ToolError::Failed(format!("Cannot create {}: {}", dir.display(), e)))?;
+        let name = format!("screenshot-{}.png", chrono::Local::now().format("%Y%m%d-%H%M%S-%3f"));
+        let path = dir.join(&name);
+        let out = path.to_string_lossy().to_string();
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let commands = screenshot_commands(os, wayland, target, &out);
+        if commands.is_empty() {
+            return Err(ToolError::Failed(format!(
+                "target {:?} is not supported on {}",
+                target, os
+            )));
+        }
+        let mut errors = Vec::new();
+        for command in &commands {
+            match run(command).await {
+                Ok(output) if output.status.success() && path.exists() => {
+                    tracing::info!(tool = %command[0], path = %path.display(), "screenshot saved");
+                    return Ok(format!(
+                        "✓ Screenshot saved to {}/{} (use image_read to read it)",
+                        SCREENSHOT_DIR, name
+                    ));
+                }
+                Ok(output) => errors.push(format!(
+                    "{}: {}",
+                    command[0],
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    errors.push(format!("{}: not installed", command[0]))
+                }
+                Err(e) => errors.push(format!("{}: {}", command[0], e)),
+            }
+        }
+        Err(ToolError::Failed(format!("Screenshot failed ({})", errors.join("; "))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screenshot_targets_and_commands() {
+        let region =
+            parse_target(&serde_json::json!({"target": "region", "x": 10, "y": 20, "width": 300, "height": 200}))
+                .unwrap();
+        assert_eq!(
+            region,
+            Target::Region {
+                x: 10,
+                y: 20,
+                width: 300,
+                height: 200
+            }
+        );
+        assert!(
+            parse_target(&serde_json::json!({"target": "region", "x": 0, "y": 0, "width": 0, "height": 5})).is_err()
+        );
+        assert_eq!(parse_target(&serde_json::json!({})).unwrap(), Target::Screen);
+
+        assert_eq!(
+            screenshot_commands("macos", false, region, "/w/a.png"),
+            vec![cmd(&["screencapture", "-x", "-R", "10,20,300,200", "/w/a.png"])]
+        );
+        assert_eq!(
+            screenshot_commands("linux", true, region, "/w/a.png"),
+            vec![cmd(&["grim", "-g", "10,20 300x200", "/w/a.png"])]
+        );
+        let x11 = screenshot_commands("linux", false, region, "/w/a.png");
+        assert_eq!(
+            x11[1],
+            cmd(&["import", "-window", "root", "-crop", "300x200+10+20", "/w/a.png"])
+        );
+        assert!(screenshot_commands("windows", false, Target::Window, "a.png").is_empty());
+        assert!(screenshot_commands("windows", false, Target::Screen, "C:\\w\\a.png")[0][3].contains("VirtualScreen"));
+    }
+}
@@ -0,0 +1,534 @@
+//! 文档读取工具
+//!
+//! doc_read 从 workspace 内的 PDF / DOCX / EPUB 抽取纯文本，按页（PDF）、标题（DOCX）或章节（EPUB）
+//! 插入 `--- page 3 ---` 形式的位置标记，长文档分块返回（chunk 参数翻页）；
+//! rag 为 true 时把全文分块写入当前会话的长期记忆，便于之后按问题检索。
+
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::memory::{Chunker, ChunkingConfig, LongTermMemory};
+use crate::tools::filesystem::{fs_error, SafeFs};
+use crate::tools::{Tool, ToolError};
+
+tokio::task_local! {
+    /// 当前会话的长期记忆，由 process_message_stream 设置（doc_read 的 rag 选项写入）
+    pub static CURRENT_LONG_TERM: Option<Arc<dyn LongTermMemory>>;
+}
+
+/// 每块默认字符数
+const DEFAULT_CHUNK_CHARS: usize = 8000;
+/// 单个文件最大字节数
+const MAX_FILE_BYTES: u64 = 50 * 1024 * 1024;
+/// 压缩包（DOCX / EPUB）读取的各条目解压后合计的最大字节数，防止 zip 炸弹耗尽内存
+const MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+/// rag 单次最多写入的记忆条数
+const MAX_RAG_CHUNKS: usize = 200;
+
+/// 文档中带位置标记的一段（页 / 小节 / 章）
+#[derive(Debug, Clone, PartialEq)]
+struct DocSection {
+    marker: String,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocFormat {
+    Pdf,
+    Docx,
+    Epub,
+}
+
+impl DocFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "epub" => Some(Self::Epub),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Pdf => "PDF",
+            Self::Docx => "DOCX",
+            Self::Epub => "EPUB",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            Self::Pdf => "pages",
+            Self::Docx => "sections",
+            Self::Epub => "chapters",
+        }
+    }
+}
+
+/// 文档读取工具：PDF / DOCX / EPUB 转文本
+pub struct DocReadTool {
+    fs: SafeFs,
+    chunk_chars: usize,
+}
+
+impl DocReadTool {
+    pub fn new(root_dir: impl AsRef<Path>) -> Self {
+        Self {
+            fs: SafeFs::new(root_dir),
+            chunk_chars: DEFAULT_CHUNK_CHARS,
+        }
+    }
+
+    pub fn with_chunk_chars(mut self, chars: usize) -> Self {
+        self.chunk_chars = chars.max(500);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for DocReadTool {
+    fn name(&self) -> &str {
+        "doc_read"
+    }
+
+    fn description(&self) -> &str {
+        "Extract text from PDF, DOCX or EPUB files in the workspace, with page/section/chapter markers. Long documents are returned in chunks. Args: {\"path\": \"file path relative to workspace\", \"chunk\": 1 (optional, 1-based), \"rag\": false (optional, also index the whole document into long-term memory for later retrieval)}"
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if path.is_empty() {
+            return Err(ToolError::InvalidArgs("Missing path".to_string()));
+        }
+        let chunk = args.get("chunk").and_then(|v| v.as_u64()).unwrap_or(1).max(1) as usize;
+        let rag = args.get("rag").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let resolved = self.fs.resolve(path).map_err(fs_error)?;
+        let format = DocFormat::from_path(&resolved).ok_or_else(|| {
+            ToolError::InvalidArgs(format!("Unsupported document type: {} (use cat for text files)", path))
+        })?;
+        let size = std::fs::metadata(&resolved).map(|m| m.len()).unwrap_or(0);
+        if size > MAX_FILE_BYTES {
+            return Err(ToolError::InvalidArgs(format!(
+                "{} is {} MB, larger than the {} MB limit",
+                path,
+                size / 1024 / 1024,
+                MAX_FILE_BYTES / 1024 / 1024
+            )));
+        }
+        tracing::info!(path = %path, format = format.label(), chunk, rag, "doc_read tool execute");
+
+        let bytes = tokio::fs::read(&resolved)
+            .await
+            .map_err(|e| ToolError::Failed(format!("Read failed: {}", e)))?;
+        let sections = tokio::task::spawn_blocking(move || extract_sections(format, &bytes))
+            .await
+            .map_err(|e| ToolError::Failed(e.to_string()))?
+            .map_err(|e| ToolError::Failed(format!("Cannot parse {} as {}: {}", path, format.label(), e)))?;
+
+        if sections.iter().all(|s| s.text.trim().is_empty()) {
+            let hint = if format == DocFormat::Pdf {
+                " (the PDF is probably scanned images; OCR is needed)"
+            } else {
+                ""
+            };
+            return Ok(format!("[{}] {} contains no extractable text{}", path, format.label(), hint));
+        }
+
+        let chunks = split_chunks(&render_sections(&sections), self.chunk_chars);
+        let Some(body) = chunks.get(chunk - 1) else {
+            return Err(ToolError::InvalidArgs(format!(
+                "chunk {} out of range: the document has {} chunk(s)",
+                chunk,
+                chunks.len()
+            )));
+        };
+        let mut out = format!(
+            "[{}] {}, {} {}, chunk {}/{}\n\n{}",
+            path,
+            format.label(),
+            sections.len(),
+            format.unit(),
+            chunk,
+            chunks.len(),
+            body
+        );
+        if chunk < chunks.len() {
+            out.push_str(&format!(
+                "\n\n[{} more chunk(s): call doc_read with \"chunk\": {}]",
+                chunks.len() - chunk,
+                chunk + 1
+            ));
+        }
+        if rag {
+            out.push_str("\n\n");
+            out.push_str(&index_into_long_term(path, &sections));
+        }
+        Ok(out)
+    }
+}
+
+/// 将各段分块写入当前会话的长期记忆，返回说明文字
+fn index_into_long_term(path: &str, sections: &[DocSection]) -> String {
+    let Some(long_term) = CURRENT_LONG_TERM.try_with(|lt| lt.clone()).ok().flatten() else {
+        return "[rag: no long-term memory in this session, skipped]".to_string();
+    };
+    let chunker = Chunker::new(ChunkingConfig::default());
+    let mut added = 0;
+    'outer: for section in sections {
+        for c in chunker.chunk(path, &section.text) {
+            if added >= MAX_RAG_CHUNKS {
+                break 'outer;
+            }
+            long_term.add(&format!("[{} · {}] {}", path, section.marker, c.text.trim()));
+            added += 1;
+        }
+    }
+    let truncated = if added >= MAX_RAG_CHUNKS { " (limit reached, rest skipped)" } else { "" };
+    format!("[rag: indexed {} chunk(s) into long-term memory{}]", added, truncated)
+}
+
+fn render_sections(sections: &[DocSection]) -> String {
+    sections
+        .iter()
+        .filter(|s| !s.text.trim().is_empty())
+        .map(|s| format!("--- {} ---\n{}", s.marker, s.text.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 按行切块，每块不超过 max_chars 个字符（超长的单行硬切）
+fn split_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for line in text.split_inclusive('\n') {
+        let mut line = line;
+        loop {
+            let len = line.chars().count();
+            if current_len + len <= max_chars {
+                current.push_str(line);
+                current_len += len;
+                break;
+            }
+            if current_len > 0 {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+                continue;
+            }
+            let split = line.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(line.len());
+            chunks.push(line[..split].to_string());
+            line = &line[split..];
+        }
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn extract_sections(format: DocFormat, bytes: &[u8]) -> Result<Vec<DocSection>, String> {
+    match format {
+        DocFormat::Pdf => extract_pdf(bytes),
+        DocFormat::Docx => extract_docx(bytes),
+        DocFormat::Epub => extract_epub(bytes),
+    }
+}
+
+fn extract_pdf(bytes: &[u8]) -> Result<Vec<DocSection>, String> {
+    let doc = lopdf::Document::load_mem(bytes).map_err(|e| e.to_string())?;
+    Ok(doc
+        .get_pages()
+        .keys()
+        .map(|&page| DocSection {
+            marker: format!("page {}", page),
+            text: doc
+                .extract_text(&[page])
+                .unwrap_or_else(|e| format!("[text extraction failed: {}]", e)),
+        })
+        .collect())
+}
+
+/// 读取条目文本并从 budget 中扣除解压后的大小；超出剩余预算时报错（不信任条目头中声明的大小），
+/// 多个条目合计超出同样报错
+fn read_zip_entry<R: Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+    budget: &mut u64,
+) -> Result<String, String> {
+    let entry = archive.by_name(name).map_err(|e| format!("{}: {}", name, e))?;
+    let mut s = String::new();
+    entry
+        .take(*budget + 1)
+        .read_to_string(&mut s)
+        .map_err(|e| format!("{}: {}", name, e))?;
+    *budget = budget
+        .checked_sub(s.len() as u64)
+        .ok_or_else(|| format!("{}: archive decompressed size exceeds the limit", name))?;
+    Ok(s)
+}
+
+/// DOCX：word/document.xml 中每个 w:p 为一段，Heading / Title 样式的段落开启新小节
+fn extract_docx(bytes: &[u8]) -> Result<Vec<DocSection>, String> {
+    extract_docx_limited(bytes, MAX_DECOMPRESSED_BYTES)
+}
+
+fn extract_docx_limited(bytes: &[u8], mut budget: u64) -> Result<Vec<DocSection>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let xml = read_zip_entry(&mut archive, "word/document.xml", &mut budget)?;
+
+    let mut sections = vec![DocSection {
+        marker: "section 1".to_string(),
+        text: String::new(),
+    }];
+    let mut paragraph = String::new();
+    let mut heading = false;
+    let mut in_text = false;
+    let mut rest = xml.as_str();
+    while let Some(start) = rest.find('<') {
+        if in_text {
+            paragraph.push_str(&unescape_xml(&rest[..start]));
+        }
+        let Some(end) = rest[start..].find('>') else { break };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let (closing, body) = match tag.strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, tag),
+        };
+        let name = body.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+        match (closing, name) {
+            (false, "w:p") if !tag.ends_with('/') => {
+                paragraph.clear();
+                heading = false;
+            }
+            (false, "w:pStyle") => {
+                let style = xml_attr(tag, "w:val").unwrap_or_default().to_ascii_lowercase();
+                heading = style.starts_with("heading") || style == "title";
+            }
+            (false, "w:t") => in_text = !tag.ends_with('/'),
+            (true, "w:t") => in_text = false,
+            (false, "w:tab") => paragraph.push('\t'),
+            (false, "w:br" | "w:cr") => paragraph.push('\n'),
+            (true, "w:p") => {
+                let text = paragraph.trim();
+                if heading && !text.is_empty() {
+                    sections.push(DocSection {
+                        marker: format!("section: {}", text),
+                        text: String::new(),
+                    });
+                } else if let Some(current) = sections.last_mut() {
+                    current.text.push_str(text);
+                    current.text.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+    sections.retain(|s| !s.text.trim().is_empty() || s.marker != "section 1");
+    Ok(sections)
+}
+
+/// EPUB：container.xml → OPF 的 spine 顺序读取各章 XHTML 并转为文本
+fn extract_epub(bytes: &[u8]) -> Result<Vec<DocSection>, String> {
+    extract_epub_limited(bytes, MAX_DECOMPRESSED_BYTES)
+}
+
+fn extract_epub_limited(bytes: &[u8], mut budget: u64) -> Result<Vec<DocSection>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let container = read_zip_entry(&mut archive, "META-INF/container.xml", &mut budget)?;
+    let opf_path = xml_tags(&container, "rootfile")
+        .find_map(|t| xml_attr(t, "full-path"))
+        .ok_or("container.xml has no rootfile")?;
+    let opf = read_zip_entry(&mut archive, &opf_path, &mut budget)?;
+    let opf_dir = opf_path.rsplit_once('/').map(|(d, _)| format!("{}/", d)).unwrap_or_default();
+
+    let manifest: std::collections::HashMap<String, String> = xml_tags(&opf, "item")
+        .filter_map(|t| Some((xml_attr(t, "id")?, xml_attr(t, "href")?)))
+        .collect();
+    let mut sections = Vec::new();
+    for idref in xml_tags(&opf, "itemref").filter_map(|t| xml_attr(t, "idref")) {
+        let Some(href) = manifest.get(&idref) else { continue };
+        let href = href.split('#').next().unwrap_or(href).replace("%20", " ");
+        // 缺失的章节跳过；解压超出预算则整体失败
+        let path = format!("{}{}", opf_dir, href);
+        if archive.index_for_name(&path).is_none() {
+            continue;
+        }
+        let html = read_zip_entry(&mut archive, &path, &mut budget)?;
+        let text = html2text::from_read(html.as_bytes(), 100).unwrap_or_default();
+        if text.trim().is_empty() {
+            continue;
+        }
+        let n = sections.len() + 1;
+        let marker = match html_title(&html) {
+            Some(title) => format!("chapter {}: {}", n, title),
+            None => format!("chapter {}", n),
+        };
+        sections.push(DocSection { marker, text });
+    }
+    Ok(sections)
+}
+
+/// 章节标题：首个 h1/h2，其次 <title>
+fn html_title(html: &str) -> Option<String> {
+    ["h1", "h2", "title"].iter().find_map(|tag| {
+        let open = html.find(&format!("<{}", tag))?;
+        let body_start = open + html[open..].find('>')? + 1;
+        let body_end = body_start + html[body_start..].find(&format!("</{}", tag))?;
+        let text = strip_tags(&html[body_start..body_end]);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then(|| text.chars().take(80).collect())
+    })
+}
+
+fn strip_tags(s: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    unescape_xml(&out)
+}
+
+/// 指定名称（忽略命名空间前缀）的开始标签内容
+fn xml_tags<'a>(xml: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    xml.split('<').skip(1).filter_map(move |part| {
+        let tag = &part[..part.find('>')?];
+        let tag_name = tag.split(|c: char| c.is_whitespace() || c == '/').next()?;
+        let local = tag_name.rsplit(':').next()?;
+        (local == name).then_some(tag)
+    })
+}
+
+fn xml_attr(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    loop {
+        let idx = rest.find(name)?;
+        let preceded_ok = idx == 0 || rest[..idx].ends_with(char::is_whitespace);
+        let after = rest[idx + name.len()..].trim_start();
+        if preceded_ok {
+            if let Some(after_eq) = after.strip_prefix('=') {
+                let after_eq = after_eq.trim_start();
+                let quote = after_eq.chars().next()?;
+                if quote == '"' || quote == '\'' {
+                    let value = &after_eq[1..];
+                    return Some(unescape_xml(&value[..value.find(quote)?]));
+                }
+            }
+        }
+        rest = &rest[idx + name.len()..];
+    }
+}
+
+fn unescape_xml(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn zip_bytes(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_doc_read_docx_epub_and_chunks() {
+        let docx = zip_bytes(&[(
+            "word/document.xml",
+            r#"<w:document><w:body>
+                <w:p><w:r><w:t>Preface &amp; intro</w:t></w:r></w:p>
+                <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Results</w:t></w:r></w:p>
+                <w:p><w:r><w:t xml:space="preserve">Growth was </w:t></w:r><w:r><w:t>12%</w:t></w:r></w:p>
+            </w:body></w:document>"#,
+        )]);
+        let sections = extract_docx(&docx).unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].text.trim(), "Preface & intro");
+        assert_eq!(sections[1].marker, "section: Results");
+        assert_eq!(sections[1].text.trim(), "Growth was 12%");
+
+        let epub = zip_bytes(&[
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package><manifest><item id="c1" href="ch1.xhtml"/><item id="c2" href="ch2.xhtml"/></manifest>
+                   <spine><itemref idref="c2"/><itemref idref="c1"/></spine></package>"#,
+            ),
+            ("OEBPS/ch1.xhtml", "<html><body><h1>Second</h1><p>Two</p></body></html>"),
+            ("OEBPS/ch2.xhtml", "<html><body><h1>First</h1><p>One</p></body></html>"),
+        ]);
+        let sections = extract_epub(&epub).unwrap();
+        let markers: Vec<_> = sections.iter().map(|s| s.marker.as_str()).collect();
+        assert_eq!(markers, vec!["chapter 1: First", "chapter 2: Second"]);
+        assert!(render_sections(&sections).contains("--- chapter 1: First ---"));
+
+        // 解压后超过上限的条目直接报错，不整体读入内存
+        let bomb = zip_bytes(&[("word/document.xml", &"x".repeat(4096))]);
+        let mut archive = zip::ZipArchive::new(Cursor::new(bomb)).unwrap();
+        assert!(read_zip_entry(&mut archive, "word/document.xml", &mut 1024).is_err());
+        let mut budget = 4096;
+        assert_eq!(read_zip_entry(&mut archive, "word/document.xml", &mut budget).unwrap().len(), 4096);
+        assert_eq!(budget, 0);
+
+        let chunks = split_chunks(&"line\n".repeat(300), 1000);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 1000));
+        assert_eq!(split_chunks(&"x".repeat(2500), 1000).len(), 3);
+    }
+
+    #[test]
+    fn test_zip_total_decompressed_size_is_capped() {
+        // 每个条目都小于上限，但合计超出：整体拒绝而不是逐章读完
+        let chapter = format!("<html><body><p>{}</p></body></html>", "x".repeat(3000));
+        let epub = zip_bytes(&[
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="content.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "content.opf",
+                r#"<package><manifest><item id="a" href="a.xhtml"/><item id="b" href="b.xhtml"/>
+                   <item id="c" href="c.xhtml"/></manifest>
+                   <spine><itemref idref="a"/><itemref idref="b"/><itemref idref="c"/></spine></package>"#,
+            ),
+            ("a.xhtml", &chapter),
+            ("b.xhtml", &chapter),
+            ("c.xhtml", &chapter),
+        ]);
+        let err = extract_epub_limited(&epub, 8 * 1024).unwrap_err();
+        assert!(err.contains("exceeds the limit"), "{}", err);
+        assert_eq!(extract_epub_limited(&epub, 16 * 1024).unwrap().len(), 3);
+
+        let docx = zip_bytes(&[("word/document.xml", &format!("<w:document>{}</w:document>", "x".repeat(4096)))]);
+        assert!(extract_docx_limited(&docx, 4096).is_err());
+    }
+}
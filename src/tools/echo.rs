@@ -3,7 +3,7 @@
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
 
 /// Echo 工具：回显文本
 pub struct EchoTool;
@@ -31,7 +31,7 @@ impl Tool for EchoTool {
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let text = args
             .get("text")
             .and_then(|v| v.as_str())
@@ -0,0 +1,514 @@
+//! 邮件工具（email feature）
+//!
+//! 按 [tools.email.accounts] 配置的账户：IMAP 读取未读 / 搜索 / 读取单封（只读打开，不改变已读状态，
+//! 除非显式 mark_read），SMTP 发送或保存草稿。发送仅限账户的 allowed_recipients 白名单；
+//! 草稿写入 workspace/email/drafts/*.eml（隔离用户为其工作区下的同名目录），供心跳等后台任务起草回复、由人确认后再发。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_imap::Session;
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, MessageBuilder};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use mail_parser::MessageParser;
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::config::{EmailAccountSection, EmailSection};
+use crate::tools::{scoped_root, Tool, ToolError};
+
+/// 草稿目录（相对 workspace）
+const DRAFTS_DIR: &str = "email/drafts";
+/// 列表中每封邮件的正文预览字符数
+const PREVIEW_CHARS: usize = 300;
+
+pub(crate) type ImapSession = Session<TlsStream<TcpStream>>;
+
+/// 解析后的邮件（邮件接入端 `integrations::email` 复用）
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ParsedEmail {
+    pub(crate) uid: u32,
+    pub(crate) from: String,
+    /// 发件人地址（不含显示名）
+    pub(crate) from_address: Option<String>,
+    pub(crate) to: String,
+    pub(crate) subject: String,
+    pub(crate) date: String,
+    pub(crate) message_id: Option<String>,
+    /// References 与 In-Reply-To 中的 Message-ID（按线程从早到晚）
+    pub(crate) references: Vec<String>,
+    pub(crate) body: String,
+}
+
+impl ParsedEmail {
+    pub(crate) fn parse(uid: u32, raw: &[u8]) -> Option<Self> {
+        let msg = MessageParser::default().parse(raw)?;
+        let addrs = |a: Option<&mail_parser::Address<'_>>| {
+            a.map(|a| {
+                a.iter()
+                    .map(|addr| match (addr.name(), addr.address()) {
+                        (Some(name), Some(email)) => format!("{} <{}>", name, email),
+                        (None, Some(email)) => email.to_string(),
+                        (Some(name), None) => name.to_string(),
+                        (None, None) => String::new(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+        };
+        let mut references: Vec<String> = msg
+            .references()
+            .as_text_list()
+            .map(|ids| ids.iter().map(|id| id.to_string()).collect())
+            .unwrap_or_default();
+        if let Some(parent) = msg.in_reply_to().as_text() {
+            if !references.iter().any(|id| id == parent) {
+                references.push(parent.to_string());
+            }
+        }
+        Some(Self {
+            uid,
+            from: addrs(msg.from()),
+            from_address: msg.from().and_then(|a| a.first()).and_then(|a| a.address()).map(str::to_string),
+            to: addrs(msg.to()),
+            subject: msg.subject().unwrap_or("(no subject)").to_string(),
+            date: msg.date().map(|d| d.to_rfc3339()).unwrap_or_default(),
+            message_id: msg.message_id().map(str::to_string),
+            references,
+            body: msg.body_text(0).map(|b| b.trim().to_string()).unwrap_or_default(),
+        })
+    }
+
+    /// 列表格式：头部 + 正文预览
+    fn summary(&self) -> String {
+        format!(
+            "[uid {}] {}\nFrom: {}\nSubject: {}\n{}",
+            self.uid,
+            self.date,
+            self.from,
+            self.subject,
+            truncate(&self.body.split_whitespace().collect::<Vec<_>>().join(" "), PREVIEW_CHARS)
+        )
+    }
+
+    fn full(&self, max_chars: usize) -> String {
+        format!(
+            "[uid {}] {}\nFrom: {}\nTo: {}\nSubject: {}\n\n{}",
+            self.uid,
+            self.date,
+            self.from,
+            self.to,
+            self.subject,
+            truncate(&self.body, max_chars)
+        )
+    }
+}
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &s[..i]),
+        None => s.to_string(),
+    }
+}
+
+/// 收件人是否在白名单内：完整地址精确匹配（忽略大小写），"@域名" 匹配该域名
+pub(crate) fn recipient_allowed(allowlist: &[String], recipient: &str) -> bool {
+    let recipient = recipient.trim().to_ascii_lowercase();
+    allowlist.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        if entry.starts_with('@') {
+            recipient.ends_with(&entry)
+        } else {
+            recipient == entry
+        }
+    })
+}
+
+/// IMAP 字符串字面量（转义引号与反斜杠）
+fn imap_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 由参数构造 IMAP SEARCH 条件：from / to / subject / text / since（YYYY-MM-DD）/ unread
+fn build_search_query(args: &Value) -> Result<String, ToolError> {
+    let mut parts = Vec::new();
+    for (key, criterion) in [("from", "FROM"), ("to", "TO"), ("subject", "SUBJECT"), ("text", "TEXT")] {
+        if let Some(v) = args.get(key).and_then(|v| v.as_str()).filter(|v| !v.trim().is_empty()) {
+            parts.push(format!("{} {}", criterion, imap_quote(v.trim())));
+        }
+    }
+    if let Some(since) = args.get("since").and_then(|v| v.as_str()) {
+        let date = chrono::NaiveDate::parse_from_str(since.trim(), "%Y-%m-%d")
+            .map_err(|_| ToolError::InvalidArgs(format!("since must be YYYY-MM-DD, got '{}'", since)))?;
+        parts.push(format!("SINCE {}", date.format("%d-%b-%Y")));
+    }
+    if args.get("unread").and_then(|v| v.as_bool()).unwrap_or(false) {
+        parts.push("UNSEEN".to_string());
+    }
+    if parts.is_empty() {
+        return Err(ToolError::InvalidArgs(
+            "search needs at least one of from, to, subject, text, since, unread".to_string(),
+        ));
+    }
+    Ok(parts.join(" "))
+}
+
+/// to / cc 参数：字符串（逗号分隔）或字符串数组
+fn recipients(args: &Value, key: &str) -> Vec<String> {
+    match args.get(key) {
+        Some(Value::String(s)) => s.split(',').map(|x| x.trim().to_string()).filter(|x| !x.is_empty()).collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// email 工具
+pub struct EmailTool {
+    accounts: Arc<HashMap<String, EmailAccountSection>>,
+    max_messages: usize,
+    max_body_chars: usize,
+    /// 草稿目录的根（workspace，隔离用户为其工作区）
+    workspace: PathBuf,
+}
+
+impl EmailTool {
+    pub fn new(config: &EmailSection, workspace: impl AsRef<Path>) -> Self {
+        Self {
+            accounts: Arc::new(config.accounts.clone()),
+            max_messages: config.max_messages.max(1),
+            max_body_chars: config.max_body_chars.max(200),
+            workspace: workspace.as_ref().to_path_buf(),
+        }
+    }
+
+    /// 选择账户：只有一个账户时可省略 account
+    fn account(&self, args: &Value) -> Result<(&str, &EmailAccountSection), ToolError> {
+        match args.get("account").and_then(|v| v.as_str()) {
+            Some(name) => self
+                .accounts
+                .get_key_value(name)
+                .map(|(k, v)| (k.as_str(), v))
+                .ok_or_else(|| ToolError::InvalidArgs(format!("Unknown email account: {}", name))),
+            None if self.accounts.len() == 1 => {
+                let (k, v) = self.accounts.iter().next().expect("one account");
+                Ok((k.as_str(), v))
+            }
+            None if self.accounts.is_empty() => Err(ToolError::Failed(
+                "No email accounts configured ([tools.email.accounts] in config)".to_string(),
+            )),
+            None => {
+                let mut names: Vec<_> = self.accounts.keys().cloned().collect();
+                names.sort();
+                Err(ToolError::InvalidArgs(format!("Specify account: one of {}", names.join(", "))))
+            }
+        }
+    }
+
+    fn password(account: &EmailAccountSection) -> Result<String, ToolError> {
+        std::env::var(&account.password_env)
+            .map_err(|_| ToolError::Failed(format!("Email password env {} is not set", account.password_env)))
+    }
+
+    pub(crate) async fn imap_session(account: &EmailAccountSection) -> Result<ImapSession, ToolError> {
+        let imap_err = |e: &dyn std::fmt::Display| ToolError::Failed(format!("IMAP {}: {}", account.imap_host, e));
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = ClientConfig::builder_with_provider(Arc::new(tokio_rustls::rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| imap_err(&e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name = ServerName::try_from(account.imap_host.clone()).map_err(|e| imap_err(&e))?;
+        let tcp = TcpStream::connect((account.imap_host.as_str(), account.imap_port))
+            .await
+            .map_err(|e| imap_err(&e))?;
+        let tls = TlsConnector::from(Arc::new(config))
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| imap_err(&e))?;
+        let mut client = async_imap::Client::new(tls);
+        client
+            .read_response()
+            .await
+            .ok_or_else(|| imap_err(&"connection closed before greeting"))?
+            .map_err(|e| imap_err(&e))?;
+        let username = account.username.as_deref().unwrap_or(&account.address);
+        client
+            .login(username, Self::password(account)?)
+            .await
+            .map_err(|(e, _)| imap_err(&e))
+    }
+
+    /// 取指定 UID 的完整邮件（BODY.PEEK 不改变已读状态）
+    pub(crate) async fn fetch(session: &mut ImapSession, uids: &[u32]) -> Result<Vec<ParsedEmail>, ToolError> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let set = uids.iter().map(|u| u.to_string()).collect::<Vec<_>>().join(",");
+        let fetches: Vec<_> = session
+            .uid_fetch(&set, "(UID BODY.PEEK[])")
+            .await
+            .map_err(|e| ToolError::Failed(format!("IMAP fetch: {}", e)))?
+            .try_collect()
+            .await
+            .map_err(|e| ToolError::Failed(format!("IMAP fetch: {}", e)))?;
+        let mut emails: Vec<ParsedEmail> = fetches
+            .iter()
+            .filter_map(|f| ParsedEmail::parse(f.uid?, f.body()?))
+            .collect();
+        // 新邮件在前
+        emails.sort_by_key(|e| std::cmp::Reverse(e.uid));
+        Ok(emails)
+    }
+
+    async fn list(&self, account: &EmailAccountSection, query: &str, limit: usize) -> Result<String, ToolError> {
+        let mut session = Self::imap_session(account).await?;
+        let result = async {
+            session
+                .examine(&account.mailbox)
+                .await
+                .map_err(|e| ToolError::Failed(format!("IMAP examine {}: {}", account.mailbox, e)))?;
+            let mut uids: Vec<u32> = session
+                .uid_search(query)
+                .await
+                .map_err(|e| ToolError::Failed(format!("IMAP search: {}", e)))?
+                .into_iter()
+                .collect();
+            uids.sort_unstable_by(|a, b| b.cmp(a));
+            let total = uids.len();
+            uids.truncate(limit);
+            let emails = Self::fetch(&mut session, &uids).await?;
+            Ok::<_, ToolError>((total, emails))
+        }
+        .await;
+        let _ = session.logout().await;
+        let (total, emails) = result?;
+        if emails.is_empty() {
+            return Ok(format!("No messages in {} match {}", account.mailbox, query));
+        }
+        let mut out = format!("{} message(s) match {}, showing {}:\n\n", total, query, emails.len());
+        out.push_str(&emails.iter().map(ParsedEmail::summary).collect::<Vec<_>>().join("\n\n"));
+        Ok(out)
+    }
+
+    async fn read(&self, account: &EmailAccountSection, uid: u32, mark_read: bool) -> Result<ParsedEmail, ToolError> {
+        let mut session = Self::imap_session(account).await?;
+        let result = async {
+            // mark_read 需要可写打开（SELECT），否则只读（EXAMINE）
+            if mark_read {
+                session.select(&account.mailbox).await
+            } else {
+                session.examine(&account.mailbox).await
+            }
+            .map_err(|e| ToolError::Failed(format!("IMAP open {}: {}", account.mailbox, e)))?;
+            let email = Self::fetch(&mut session, &[uid])
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| ToolError::Failed(format!("No message with uid {}", uid)))?;
+            if mark_read {
+                let _: Vec<_> = session
+                    .uid_store(uid.to_string(), "+FLAGS (\\Seen)")
+                    .await
+                    .map_err(|e| ToolError::Failed(format!("IMAP store: {}", e)))?
+                    .try_collect()
+                    .await
+                    .map_err(|e| ToolError::Failed(format!("IMAP store: {}", e)))?;
+            }
+            Ok(email)
+        }
+        .await;
+        let _ = session.logout().await;
+        result
+    }
+
+    /// 由参数构造邮件；reply_to_uid 时引用原邮件主题与 Message-ID
+    async fn compose(&self, account: &EmailAccountSection, args: &Value, to: &[String]) -> Result<lettre::Message, ToolError> {
+        let mailbox = |s: &str| {
+            s.parse::<Mailbox>()
+                .map_err(|e| ToolError::InvalidArgs(format!("Invalid address '{}': {}", s, e)))
+        };
+        let from = match &account.display_name {
+            Some(name) => format!("{} <{}>", name, account.address),
+            None => account.address.clone(),
+        };
+        let mut builder: MessageBuilder = lettre::Message::builder().from(mailbox(&from)?);
+        for addr in to {
+            builder = builder.to(mailbox(addr)?);
+        }
+        for addr in recipients(args, "cc") {
+            builder = builder.cc(mailbox(&addr)?);
+        }
+        let mut subject = args.get("subject").and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+        if let Some(uid) = args.get("reply_to_uid").and_then(|v| v.as_u64()) {
+            let original = self.read(account, uid as u32, false).await?;
+            if subject.is_empty() {
+                subject = if original.subject.to_ascii_lowercase().starts_with("re:") {
+                    original.subject.clone()
+                } else {
+                    format!("Re: {}", original.subject)
+                };
+            }
+            if let Some(id) = original.message_id {
+                builder = builder.in_reply_to(format!("<{}>", id)).references(format!("<{}>", id));
+            }
+        }
+        if subject.is_empty() {
+            return Err(ToolError::InvalidArgs("Missing subject".to_string()));
+        }
+        let body = args.get("body").and_then(|v| v.as_str()).unwrap_or("");
+        if body.trim().is_empty() {
+            return Err(ToolError::InvalidArgs("Missing body".to_string()));
+        }
+        builder
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())
+            .map_err(|e| ToolError::InvalidArgs(format!("Cannot build message: {}", e)))
+    }
+
+    pub(crate) async fn send(account: &EmailAccountSection, message: lettre::Message) -> Result<(), ToolError> {
+        let smtp_err = |e: lettre::transport::smtp::Error| ToolError::Failed(format!("SMTP {}: {}", account.smtp_host, e));
+        let builder = if account.smtp_port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&account.smtp_host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&account.smtp_host)
+        }
+        .map_err(smtp_err)?;
+        let username = account.username.clone().unwrap_or_else(|| account.address.clone());
+        let transport = builder
+            .port(account.smtp_port)
+            .credentials(Credentials::new(username, Self::password(account)?))
+            .build();
+        transport.send(message).await.map_err(smtp_err)?;
+        Ok(())
+    }
+
+    fn save_draft(&self, account_name: &str, message: &lettre::Message) -> Result<PathBuf, ToolError> {
+        let drafts_dir = scoped_root(&self.workspace).join(DRAFTS_DIR);
+        std::fs::create_dir_all(&drafts_dir).map_err(|e| ToolError::Failed(format!("Create drafts dir: {}", e)))?;
+        let name = format!("{}-{}.eml", chrono::Local::now().format("%Y%m%d-%H%M%S%3f"), account_name);
+        let path = drafts_dir.join(name);
+        std::fs::write(&path, message.formatted()).map_err(|e| ToolError::Failed(format!("Write draft: {}", e)))?;
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl Tool for EmailTool {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn description(&self) -> &str {
+        "Read and send email for configured accounts. Args: {\"action\": \"unread|search|read|send|draft\", \"account\": \"account name (optional when only one)\", \"limit\": 10 (unread/search), search filters \"from\", \"to\", \"subject\", \"text\", \"since\": \"YYYY-MM-DD\", \"unread\": bool; read: \"uid\", \"mark_read\": bool; send/draft: \"to\" (string or array), \"cc\", \"subject\", \"body\", \"reply_to_uid\" (optional, replies to that message)}. send only reaches allowlisted recipients; draft saves an .eml under email/drafts for the user to review."
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let action = args.get("action").and_then(|v| v.as_str()).unwrap_or("unread");
+        let (account_name, account) = self.account(&args)?;
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, self.max_messages))
+            .unwrap_or(self.max_messages.min(10));
+        tracing::info!(account = account_name, action, "email tool execute");
+
+        match action {
+            "unread" => self.list(account, "UNSEEN", limit).await,
+            "search" => {
+                let query = build_search_query(&args)?;
+                self.list(account, &query, limit).await
+            }
+            "read" => {
+                let uid = args
+                    .get("uid")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| ToolError::InvalidArgs("read needs uid".to_string()))?;
+                let mark_read = args.get("mark_read").and_then(|v| v.as_bool()).unwrap_or(false);
+                let email = self.read(account, uid as u32, mark_read).await?;
+                Ok(email.full(self.max_body_chars))
+            }
+            "send" | "draft" => {
+                let to = recipients(&args, "to");
+                if to.is_empty() {
+                    return Err(ToolError::InvalidArgs("Missing to".to_string()));
+                }
+                if action == "send" {
+                    if account.allowed_recipients.is_empty() {
+                        return Err(ToolError::Failed(format!(
+                            "Sending is disabled for account {} (empty allowed_recipients); use action \"draft\" instead",
+                            account_name
+                        )));
+                    }
+                    let blocked: Vec<_> = to
+                        .iter()
+                        .chain(recipients(&args, "cc").iter())
+                        .map(|r| r.parse::<Mailbox>().map(|m| m.email.to_string()).unwrap_or_else(|_| r.clone()))
+                        .filter(|r| !recipient_allowed(&account.allowed_recipients, r))
+                        .collect();
+                    if !blocked.is_empty() {
+                        return Err(ToolError::Failed(format!(
+                            "Recipients not in allowed_recipients of account {}: {} (use action \"draft\" instead)",
+                            account_name,
+                            blocked.join(", ")
+                        )));
+                    }
+                }
+                let message = self.compose(account, &args, &to).await?;
+                if action == "draft" {
+                    let path = self.save_draft(account_name, &message)?;
+                    return Ok(format!("Draft saved to {}", path.display()));
+                }
+                Self::send(account, message).await?;
+                Ok(format!("Sent from {} to {}", account.address, to.join(", ")))
+            }
+            other => Err(ToolError::InvalidArgs(format!(
+                "Unknown action '{}': use unread, search, read, send or draft",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_email_allowlist_search_and_parse() {
+        let allow = vec!["boss@example.com".to_string(), "@team.example.org".to_string()];
+        assert!(recipient_allowed(&allow, "Boss@Example.com"));
+        assert!(recipient_allowed(&allow, "dev@team.example.org"));
+        assert!(!recipient_allowed(&allow, "dev@evil-team.example.org.cn"));
+        assert!(!recipient_allowed(&allow, "someone@example.com"));
+
+        let query = build_search_query(&json!({"from": "a\"b", "since": "2026-10-07", "unread": true})).unwrap();
+        assert_eq!(query, "FROM \"a\\\"b\" SINCE 07-Oct-2026 UNSEEN");
+        assert!(build_search_query(&json!({})).is_err());
+        assert_eq!(recipients(&json!({"to": "a@x.com, b@y.com"}), "to"), vec!["a@x.com", "b@y.com"]);
+
+        let raw = b"From: Alice <alice@example.com>\r\nTo: me@example.com\r\nSubject: Invoice\r\nMessage-ID: <m1@example.com>\r\nDate: Fri, 16 Oct 2026 09:00:00 +0000\r\n\r\nPlease pay\r\nby Friday.\r\n";
+        let email = ParsedEmail::parse(42, raw).unwrap();
+        assert_eq!(email.from, "Alice <alice@example.com>");
+        assert_eq!(email.message_id.as_deref(), Some("m1@example.com"));
+        assert!(email.summary().contains("[uid 42]") && email.summary().contains("Please pay by Friday."));
+    }
+}
@@ -0,0 +1,111 @@
+//! 工具错误类型
+//!
+//! Tool::execute 返回结构化的 ToolError，区分参数错误、权限拒绝、资源不存在、临时故障与一般失败：
+//! ToolExecutor 对可重试（Transient）错误自动重试，RecoveryEngine 与参数修复逻辑按类型给出不同提示。
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// 工具执行错误（按可恢复性分类）
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ToolError {
+    /// 参数缺失或不合法（LLM 可据 schema 修正后重试）
+    #[error("Invalid arguments: {0}")]
+    InvalidArgs(String),
+
+    /// 权限不足 / 超出允许范围（路径逃逸、命令或域名不在白名单等），重试无意义
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// 目标资源不存在（文件、元素、会话等）
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// 临时故障（网络、超时、IO 抖动），可原样重试
+    #[error("Transient failure: {0}")]
+    Transient(String),
+
+    /// 其它失败
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl ToolError {
+    /// 是否值得原样重试（仅临时故障）
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ToolError::Transient(_))
+    }
+
+    /// 错误类别名（审计日志与事件使用）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ToolError::InvalidArgs(_) => "invalid_args",
+            ToolError::PermissionDenied(_) => "permission_denied",
+            ToolError::NotFound(_) => "not_found",
+            ToolError::Transient(_) => "transient",
+            ToolError::Failed(_) => "failed",
+        }
+    }
+
+    /// 不含类别前缀的原始信息
+    pub fn message(&self) -> &str {
+        match self {
+            ToolError::InvalidArgs(m)
+            | ToolError::PermissionDenied(m)
+            | ToolError::NotFound(m)
+            | ToolError::Transient(m)
+            | ToolError::Failed(m) => m,
+        }
+    }
+
+    /// 缺少必填参数
+    pub fn missing(param: &str) -> Self {
+        ToolError::InvalidArgs(format!("Missing required parameter: {}", param))
+    }
+}
+
+impl From<String> for ToolError {
+    fn from(msg: String) -> Self {
+        ToolError::Failed(msg)
+    }
+}
+
+impl From<&str> for ToolError {
+    fn from(msg: &str) -> Self {
+        ToolError::Failed(msg.to_string())
+    }
+}
+
+impl From<std::io::Error> for ToolError {
+    fn from(e: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match e.kind() {
+            ErrorKind::NotFound => ToolError::NotFound(e.to_string()),
+            ErrorKind::PermissionDenied => ToolError::PermissionDenied(e.to_string()),
+            ErrorKind::TimedOut | ErrorKind::Interrupted | ErrorKind::WouldBlock => {
+                ToolError::Transient(e.to_string())
+            }
+            _ => ToolError::Failed(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_error_classification() {
+        assert!(ToolError::Transient("reset".into()).is_retryable());
+        assert!(!ToolError::InvalidArgs("x".into()).is_retryable());
+        assert_eq!(ToolError::missing("path").kind(), "invalid_args");
+        assert_eq!(ToolError::from("boom").to_string(), "boom");
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert!(matches!(ToolError::from(io), ToolError::NotFound(_)));
+
+        let json = serde_json::to_string(&ToolError::NotFound("a.txt".into())).unwrap();
+        assert_eq!(json, r#"{"kind":"not_found","message":"a.txt"}"#);
+    }
+}
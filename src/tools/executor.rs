@@ -1,20 +1,51 @@
 //! 工具执行器
 //!
 //! 持有 ToolRegistry 与全局超时，execute(tool_name, args) 在超时内调用 registry.execute，
-//! 超时或失败时转为 AgentError（ToolTimeout / ToolExecutionFailed）；每次调用输出结构化审计日志（JSON）。
+//! 只读调用的可重试 ToolError（Transient）按指数退避自动重试，超时或失败时转为 AgentError（ToolTimeout / ToolFailed）；
+//! 超时、重试次数、退避与并发上限可按工具覆盖（[tools.limits]）；
+//! 每次调用输出结构化审计日志（JSON）。可挂载 ToolPolicy，由 ReAct 循环在执行前做权限与审批检查；
+//! 可挂载 ToolCache，命中时直接返回缓存结果，非只读工具成功执行后清空缓存。
+//! 执行前按工具的 parameters_schema 校验参数，不符时直接返回 InvalidArgs（列出缺失 / 不合法字段）。
 
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use tokio::sync::Semaphore;
 use tokio::time::timeout;
 
+use crate::config::ToolLimitSection;
 use crate::core::AgentError;
-use crate::observability::Metrics;
-use crate::tools::ToolRegistry;
+use crate::observability::{Metrics, UNSCOPED_ASSISTANT};
+use crate::tools::schema::{describe_violations, validate_args};
+use crate::tools::{
+    builtin_risk, ApprovalRequest, RiskLevel, ToolCache, ToolError, ToolPolicy, ToolRegistry, CURRENT_ASSISTANT_ID,
+};
+
+/// 临时故障（ToolError::Transient）的默认自动重试次数
+const DEFAULT_TRANSIENT_RETRIES: u32 = 1;
+/// 首次重试前的默认等待，之后每次翻倍
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+/// 单次重试等待上限
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 单个工具的执行限制（未设置的项沿用执行器全局值）
+#[derive(Default)]
+struct ToolLimits {
+    timeout: Option<Duration>,
+    retries: Option<u32>,
+    backoff: Option<Duration>,
+    permits: Option<Arc<Semaphore>>,
+}
 
 /// 工具执行器：对每次调用施加超时，并将结果映射为 AgentError
 pub struct ToolExecutor {
     registry: ToolRegistry,
     timeout: Duration,
+    transient_retries: u32,
+    limits: HashMap<String, ToolLimits>,
+    policy: Option<ToolPolicy>,
+    cache: Option<ToolCache>,
 }
 
 impl ToolExecutor {
@@ -22,38 +53,191 @@ impl ToolExecutor {
         Self {
             registry,
             timeout: Duration::from_secs(timeout_secs),
+            transient_retries: DEFAULT_TRANSIENT_RETRIES,
+            limits: HashMap::new(),
+            policy: None,
+            cache: None,
         }
     }
 
-    /// 执行指定工具；超时返回 ToolTimeout，工具返回 Err 则转为 ToolExecutionFailed；输出 JSON 审计日志
+    /// 设置临时故障的自动重试次数（0 为不重试）
+    pub fn with_transient_retries(mut self, retries: u32) -> Self {
+        self.transient_retries = retries;
+        self
+    }
+
+    /// 设置按工具的超时、重试与并发上限（[tools.limits]），整体替换已有设置
+    pub fn with_limits(mut self, limits: &HashMap<String, ToolLimitSection>) -> Self {
+        self.limits = limits
+            .iter()
+            .map(|(tool, l)| {
+                let limits = ToolLimits {
+                    timeout: l.timeout_secs.filter(|s| *s > 0).map(Duration::from_secs),
+                    retries: l.max_retries,
+                    backoff: l.retry_backoff_ms.map(Duration::from_millis),
+                    permits: l.max_concurrency.filter(|n| *n > 0).map(|n| Arc::new(Semaphore::new(n))),
+                };
+                (tool.clone(), limits)
+            })
+            .collect();
+        self
+    }
+
+    /// 工具的生效超时
+    pub fn timeout_for(&self, tool_name: &str) -> Duration {
+        self.limits
+            .get(tool_name)
+            .and_then(|l| l.timeout)
+            .unwrap_or(self.timeout)
+    }
+
+    /// 挂载工具权限策略（None 表示不检查）
+    pub fn with_policy(mut self, policy: Option<ToolPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> Option<&ToolPolicy> {
+        self.policy.as_ref()
+    }
+
+    /// 按挂载的工具策略放行 / 请求审批 / 拒绝（未挂载策略时放行），助手取自 CURRENT_ASSISTANT_ID；
+    /// notify 负责把审批请求送达用户，返回 false 表示当前调用方无审批通道，需审批的工具直接拒绝
+    pub async fn authorize(
+        &self,
+        tool_name: &str,
+        args: &serde_json::Value,
+        notify: impl FnOnce(&ApprovalRequest) -> bool,
+    ) -> Result<(), ToolError> {
+        let Some(policy) = self.policy.as_ref() else {
+            return Ok(());
+        };
+        let assistant_id = CURRENT_ASSISTANT_ID.try_with(|a| a.clone()).ok().flatten();
+        policy.authorize(assistant_id.as_deref(), tool_name, args, notify).await
+    }
+
+    /// 挂载工具结果缓存（None 表示不缓存）
+    pub fn with_cache(mut self, cache: Option<ToolCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// 本次调用的风险等级：有策略时沿用策略（含配置覆盖），否则用内置判定
+    fn risk(&self, tool_name: &str, args: &serde_json::Value) -> RiskLevel {
+        self.policy
+            .as_ref()
+            .map(|p| p.risk(tool_name, args))
+            .unwrap_or_else(|| builtin_risk(tool_name, args))
+    }
+
+    /// 执行指定工具；超时返回 ToolTimeout，工具返回 Err 则转为 ToolFailed（Transient 先自动重试）；输出 JSON 审计日志
     pub async fn execute(&self, tool_name: &str, args: serde_json::Value) -> Result<String, AgentError> {
         let start = Instant::now();
         let args_preview = args_preview(&args);
         let metrics = Metrics::global();
-        
-        let result = timeout(
-            self.timeout,
-            self.registry.execute(tool_name, args),
-        )
-        .await;
+
+        // 参数先按工具 schema 校验：不符时不执行，返回逐条问题供 Planner 修正，并记为一次工具误用
+        if let Some(tool) = self.registry.get(tool_name) {
+            let violations = validate_args(&tool.parameters_schema(), &args);
+            if !violations.is_empty() {
+                metrics.behavior.record_tool_misuse();
+                let audit = serde_json::json!({
+                    "event": "tool_audit",
+                    "tool": tool_name,
+                    "ok": false,
+                    "outcome": "schema_violation",
+                    "violations": violations.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                    "duration_ms": start.elapsed().as_millis() as u64,
+                    "attempts": 0,
+                    "args_preview": args_preview,
+                });
+                tracing::info!(audit = %audit.to_string(), "tool");
+                return Err(AgentError::ToolFailed(ToolError::InvalidArgs(describe_violations(tool_name, &violations))));
+            }
+        }
+
+        if let Some(output) = self.cache.as_ref().and_then(|c| c.get(tool_name, &args)) {
+            let audit = serde_json::json!({
+                "event": "tool_audit",
+                "tool": tool_name,
+                "ok": true,
+                "outcome": "cached",
+                "duration_ms": start.elapsed().as_millis() as u64,
+                "attempts": 0,
+                "args_preview": args_preview,
+            });
+            tracing::info!(audit = %audit.to_string(), "tool");
+            metrics.tools.record_usage(&current_assistant_label(), tool_name, true, start.elapsed());
+            return Ok(output);
+        }
+
+        let limits = self.limits.get(tool_name);
+        let tool_timeout = self.timeout_for(tool_name);
+        // 只自动重试只读调用：修改类调用只审批过一次，且失败时可能已在外部生效
+        let retries = if self.risk(tool_name, &args) == RiskLevel::ReadOnly {
+            limits.and_then(|l| l.retries).unwrap_or(self.transient_retries)
+        } else {
+            0
+        };
+        let backoff = limits.and_then(|l| l.backoff).unwrap_or(DEFAULT_RETRY_BACKOFF);
+
+        // 并发上限：排队时间不计入超时，许可在全部重试结束后释放
+        let _permit = match limits.and_then(|l| l.permits.as_ref()) {
+            Some(permits) => {
+                let queued = Instant::now();
+                let permit = permits.acquire().await.ok();
+                if queued.elapsed() >= Duration::from_millis(1) {
+                    tracing::debug!(tool = tool_name, queued_ms = queued.elapsed().as_millis() as u64, "tool call queued");
+                }
+                permit
+            }
+            None => None,
+        };
+
+        let mut attempts = 0u32;
+        let result = loop {
+            attempts += 1;
+            let result = timeout(tool_timeout, self.registry.execute(tool_name, args.clone())).await;
+            match result {
+                Ok(Err(ref e)) if e.is_retryable() && attempts <= retries => {
+                    let delay = backoff.saturating_mul(1 << (attempts - 1).min(16)).min(MAX_RETRY_BACKOFF);
+                    tracing::warn!(
+                        tool = tool_name,
+                        attempt = attempts,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %e,
+                        "transient tool error, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                other => break other,
+            }
+        };
 
         let (ok, outcome, success): (bool, &str, bool) = match &result {
             Ok(Ok(_)) => (true, "ok", true),
             Ok(Err(_)) => (false, "error", false),
             Err(_) => (false, "timeout", false),
         };
+        let error_kind = match &result {
+            Ok(Err(e)) => Some(e.kind()),
+            _ => None,
+        };
         let duration = start.elapsed();
         let duration_ms = duration.as_millis() as u64;
         
-        // 记录工具执行 metrics
+        // 记录工具执行 metrics，并按工具 / 助手记入用量与耗时
         metrics.tools.record_execution(success, duration);
+        metrics.tools.record_usage(&current_assistant_label(), tool_name, success, duration);
         
         let audit = serde_json::json!({
             "event": "tool_audit",
             "tool": tool_name,
             "ok": ok,
             "outcome": outcome,
+            "error_kind": error_kind,
             "duration_ms": duration_ms,
+            "attempts": attempts,
             "args_preview": args_preview,
         });
         tracing::info!(audit = %audit.to_string(), "tool");
@@ -65,9 +249,20 @@ impl ToolExecutor {
             "tool_execution"
         );
 
+        // 修改类工具即使失败也可能已改动部分状态，一律清空
+        if let Some(cache) = &self.cache {
+            if cache.is_cacheable(tool_name, &args) {
+                if let Ok(Ok(content)) = &result {
+                    cache.put(tool_name, &args, content);
+                }
+            } else if self.risk(tool_name, &args) != RiskLevel::ReadOnly {
+                cache.invalidate_all();
+            }
+        }
+
         match result {
             Ok(Ok(content)) => Ok(content),
-            Ok(Err(e)) => Err(AgentError::ToolExecutionFailed(e)),
+            Ok(Err(e)) => Err(AgentError::ToolFailed(e)),
             Err(_) => Err(AgentError::ToolTimeout(tool_name.to_string())),
         }
     }
@@ -86,6 +281,15 @@ impl ToolExecutor {
     }
 }
 
+/// 当前助手 id；不在助手上下文中时记入 UNSCOPED_ASSISTANT
+fn current_assistant_label() -> String {
+    CURRENT_ASSISTANT_ID
+        .try_with(|a| a.clone())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| UNSCOPED_ASSISTANT.to_string())
+}
+
 fn args_preview(args: &serde_json::Value) -> String {
     let s = args.to_string();
     if s.len() > 200 {
@@ -94,3 +298,175 @@ fn args_preview(args: &serde_json::Value) -> String {
         s
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Tool, ToolError};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 睡眠 args.ms 毫秒，记录同时运行的最大调用数；args.fail 为 true 时返回临时故障
+    struct SlowTool {
+        running: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for SlowTool {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn description(&self) -> &str {
+            "sleep"
+        }
+
+        async fn execute(&self, args: serde_json::Value) -> Result<String, ToolError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            let ms = args.get("ms").and_then(|v| v.as_u64()).unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            if args.get("fail").and_then(|v| v.as_bool()).unwrap_or(false) {
+                return Err(ToolError::Transient("flaky".to_string()));
+            }
+            Ok("done".to_string())
+        }
+    }
+
+    #[test]
+    fn test_per_tool_timeout_retry_and_concurrency() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let peak = Arc::new(AtomicUsize::new(0));
+            let calls = Arc::new(AtomicUsize::new(0));
+            let mut registry = ToolRegistry::new();
+            registry.register(SlowTool {
+                running: Arc::new(AtomicUsize::new(0)),
+                peak: peak.clone(),
+                calls: calls.clone(),
+            });
+            let mut limits = HashMap::new();
+            limits.insert(
+                "slow".to_string(),
+                ToolLimitSection {
+                    timeout_secs: Some(1),
+                    max_retries: Some(2),
+                    retry_backoff_ms: Some(5),
+                    max_concurrency: Some(1),
+                },
+            );
+            let mut section = crate::config::ToolPolicySection::default();
+            section.risk.insert("slow".to_string(), RiskLevel::ReadOnly);
+            let executor = ToolExecutor::new(registry, 30)
+                .with_limits(&limits)
+                .with_policy(Some(ToolPolicy::from(section)));
+            assert_eq!(executor.timeout_for("slow"), Duration::from_secs(1));
+            assert_eq!(executor.timeout_for("echo"), Duration::from_secs(30));
+
+            // 并发上限为 1：两次调用串行执行
+            let args = serde_json::json!({"ms": 30});
+            let (a, b) = tokio::join!(executor.execute("slow", args.clone()), executor.execute("slow", args));
+            assert!(a.is_ok() && b.is_ok());
+            assert_eq!(peak.load(Ordering::SeqCst), 1);
+
+            // 覆盖的超时生效
+            let err = executor.execute("slow", serde_json::json!({"ms": 1500})).await.unwrap_err();
+            assert!(matches!(err, AgentError::ToolTimeout(_)));
+
+            // 临时故障按 max_retries 重试：共 3 次调用
+            calls.store(0, Ordering::SeqCst);
+            let err = executor.execute("slow", serde_json::json!({"fail": true})).await.unwrap_err();
+            assert!(matches!(err, AgentError::ToolFailed(_)));
+            assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+            // 非只读工具即使是临时故障也不自动重试
+            let mut registry = ToolRegistry::new();
+            registry.register(SlowTool {
+                running: Arc::new(AtomicUsize::new(0)),
+                peak: peak.clone(),
+                calls: calls.clone(),
+            });
+            let executor = ToolExecutor::new(registry, 30).with_limits(&limits);
+            calls.store(0, Ordering::SeqCst);
+            let err = executor.execute("slow", serde_json::json!({"fail": true})).await.unwrap_err();
+            assert!(matches!(err, AgentError::ToolFailed(_)));
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_schema_violation_rejected_before_execution() {
+        let mut registry = ToolRegistry::new();
+        registry.register(crate::tools::EchoTool);
+        let executor = ToolExecutor::new(registry, 30);
+        let misuses = || Metrics::global().behavior.tool_misuses.load(Ordering::Relaxed);
+        let before = misuses();
+
+        let err = executor.execute("echo", serde_json::json!({"text": 42})).await.unwrap_err();
+        match err {
+            AgentError::ToolFailed(ToolError::InvalidArgs(msg)) => {
+                assert!(msg.contains("`text`: expected string, got integer 42"), "{}", msg);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(misuses() > before);
+        assert_eq!(executor.execute("echo", serde_json::json!({"text": "hi"})).await.unwrap(), "hi");
+    }
+
+    #[tokio::test]
+    async fn test_http_fetch_non_get_needs_approval() {
+        let executor = ToolExecutor::new(ToolRegistry::new(), 30);
+        let get = serde_json::json!({"url": "https://api.example.com/items"});
+        assert_eq!(executor.risk("http_fetch", &get), RiskLevel::ReadOnly);
+        for method in ["POST", "put", "DELETE", "patch"] {
+            let args = serde_json::json!({"url": "https://api.example.com/items", "method": method});
+            assert_eq!(executor.risk("http_fetch", &args), RiskLevel::Mutating, "{}", method);
+        }
+
+        // 默认策略（mutating = ask）：GET 直接放行，POST 需审批，无审批通道时拒绝
+        let executor = executor.with_policy(Some(ToolPolicy::from(crate::config::ToolPolicySection::default())));
+        assert!(executor.authorize("http_fetch", &get, |_| false).await.is_ok());
+        let post = serde_json::json!({"url": "https://api.example.com/items", "method": "POST", "json": {}});
+        let mut asked = None;
+        let err = executor
+            .authorize("http_fetch", &post, |req| {
+                asked = Some(req.risk);
+                false
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::PermissionDenied(_)));
+        assert_eq!(asked, Some(RiskLevel::Mutating));
+    }
+
+    #[tokio::test]
+    async fn test_usage_recorded_per_assistant_with_latency() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut registry = ToolRegistry::new();
+        registry.register(SlowTool {
+            running: Arc::new(AtomicUsize::new(0)),
+            peak: Arc::new(AtomicUsize::new(0)),
+            calls: calls.clone(),
+        });
+        let executor = ToolExecutor::new(registry, 30);
+        let assistant = "usage-probe-assistant";
+        CURRENT_ASSISTANT_ID
+            .scope(Some(assistant.to_string()), async {
+                executor.execute("slow", serde_json::json!({"ms": 20})).await.unwrap();
+                executor.execute("slow", serde_json::json!({"fail": true})).await.unwrap_err();
+            })
+            .await;
+
+        let usage = &Metrics::global().tools.usage_for(assistant)["slow"];
+        assert_eq!((usage.calls, usage.failures, usage.blocked), (2, 1, 0));
+        assert_eq!(usage.latency.count, 2);
+        assert!(usage.latency.max_ms >= 20);
+        // 不在助手上下文中的调用记入 UNSCOPED_ASSISTANT
+        executor.execute("slow", serde_json::json!({})).await.unwrap();
+        assert!(Metrics::global().tools.usage_for(UNSCOPED_ASSISTANT)["slow"].calls >= 1);
+    }
+}
@@ -2,14 +2,65 @@
 //!
 //! SafeFs 绑定 root_dir，所有路径经 resolve 校验必须在 root 下（禁止 ../ 逃逸）；
 //! CatTool / LsTool 基于 SafeFs 提供 cat / ls 能力。
+//!
+//! 多用户：bee-web 为非默认用户设置 [`CURRENT_WORKSPACE`]，SafeFs 与 code_* 等文件类工具改以该用户的
+//! workspace/users/<user_id>/ 为根（[`scoped_root`]），看不到其他用户与默认用户的会话、记忆。
 
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use async_trait::async_trait;
 use serde_json::Value;
 
 use crate::core::AgentError;
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
+
+tokio::task_local! {
+    /// 当前请求所属用户的工作区，由 bee-web 为非默认用户设置；文件类工具以它代替构造时的根目录
+    pub static CURRENT_WORKSPACE: Option<PathBuf>;
+}
+
+/// 文件类工具本次调用的根目录：设置了 CURRENT_WORKSPACE 时为该用户的工作区，否则为构造时的 root
+pub fn scoped_root(root: &Path) -> PathBuf {
+    CURRENT_WORKSPACE
+        .try_with(|w| w.clone())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| root.to_path_buf())
+}
+
+/// code_* 工具的路径校验：相对路径按本次调用的根目录（见 [`scoped_root`]）解析，规范化后须在根内；
+/// 尚不存在的路径（如待写入的新文件）无法规范化，含 `..` 时无法确定其位置，直接拒绝
+pub(crate) fn resolve_in_root(root: &Path, file_path: &str) -> Result<PathBuf, ToolError> {
+    let root = scoped_root(root);
+    let denied = || ToolError::PermissionDenied(format!("Access denied: path '{}' is outside allowed root", file_path));
+    let path = Path::new(file_path);
+    let absolute_path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    };
+    let canonical_path = match absolute_path.canonicalize() {
+        Ok(p) => p,
+        Err(_) if absolute_path.components().any(|c| c == Component::ParentDir) => return Err(denied()),
+        Err(_) => absolute_path,
+    };
+    let root_canonical = root.canonicalize().unwrap_or(root);
+    if !canonical_path.starts_with(&root_canonical) {
+        return Err(denied());
+    }
+    Ok(canonical_path)
+}
+
+/// 会执行任意程序的工具（shell、cargo、git）无法按路径约束，在限定于用户工作区的请求中拒绝执行
+pub(crate) fn ensure_unscoped(tool: &str) -> Result<(), ToolError> {
+    if CURRENT_WORKSPACE.try_with(|w| w.is_some()).unwrap_or(false) {
+        return Err(ToolError::PermissionDenied(format!(
+            "{} is not available in a per-user workspace",
+            tool
+        )));
+    }
+    Ok(())
+}
 
 /// 沙箱文件系统：绑定根目录，resolve 校验路径在根下，防止路径逃逸
 #[derive(Debug, Clone)]
@@ -24,17 +75,20 @@ impl SafeFs {
         Self { root_dir }
     }
 
+    /// 本次调用的根目录（见 [`scoped_root`]）
+    fn root(&self) -> PathBuf {
+        scoped_root(&self.root_dir)
+    }
+
     /// 检查路径是否在沙箱内
     pub fn resolve(&self, path: &str) -> Result<PathBuf, AgentError> {
         let path = path.trim_start_matches("./");
-        let full = self.root_dir.join(path);
+        let root = self.root();
+        let full = root.join(path);
         let canonical = full
             .canonicalize()
             .map_err(|_| AgentError::ToolExecutionFailed(format!("Path not found: {}", path)))?;
-        let root_canon = self
-            .root_dir
-            .canonicalize()
-            .unwrap_or_else(|_| self.root_dir.clone());
+        let root_canon = root.canonicalize().unwrap_or(root);
         if canonical.starts_with(root_canon) {
             Ok(canonical)
         } else {
@@ -51,7 +105,7 @@ impl SafeFs {
 
     pub fn list_dir(&self, path: &str) -> Result<Vec<String>, AgentError> {
         let base = if path.is_empty() || path == "." {
-            self.root_dir.clone()
+            self.root()
         } else {
             self.resolve(path)?
         };
@@ -98,13 +152,24 @@ impl Tool for CatTool {
         "Read file contents. Args: {\"path\": \"file path relative to workspace\"}"
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let path = args
             .get("path")
             .and_then(|v| v.as_str())
             .unwrap_or("");
         tracing::info!(path = %path, "cat tool execute");
-        self.fs.read_file(path).map_err(|e| e.to_string())
+        self.fs.read_file(path).map_err(fs_error)
+    }
+}
+
+/// SafeFs 错误转为 ToolError：路径逃逸为权限拒绝，路径不存在为 NotFound
+pub(crate) fn fs_error(e: AgentError) -> ToolError {
+    match e {
+        AgentError::PathEscape(p) => ToolError::PermissionDenied(format!("Path escape attempt: {}", p)),
+        AgentError::ToolExecutionFailed(msg) if msg.starts_with("Path not found") => {
+            ToolError::NotFound(msg)
+        }
+        other => ToolError::Failed(other.to_string()),
     }
 }
 
@@ -131,13 +196,100 @@ impl Tool for LsTool {
         "List directory. Args: {\"path\": \"directory path, default '.'\"}"
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let path = args
             .get("path")
             .and_then(|v| v.as_str())
             .unwrap_or(".");
         tracing::info!(path = %path, "ls tool execute");
-        let entries = self.fs.list_dir(path).map_err(|e| e.to_string())?;
+        let entries = self.fs.list_dir(path).map_err(fs_error)?;
         Ok(entries.join("\n"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::UserId;
+    use crate::tools::{CodeReadTool, CodeWriteTool, ShellTool};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_user_workspace_cannot_reach_other_users() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let alice = UserId::new("key.alice").workspace(root);
+        let bob = UserId::new("key.bob").workspace(root);
+        std::fs::create_dir_all(alice.join("sessions")).unwrap();
+        std::fs::create_dir_all(bob.join("sessions")).unwrap();
+        std::fs::write(alice.join("sessions/s1.json"), "alice secret").unwrap();
+        std::fs::write(bob.join("sessions/s2.json"), "bob notes").unwrap();
+        std::fs::write(root.join("MEMORY.md"), "default memory").unwrap();
+
+        let cat = CatTool::new(root);
+        let ls = LsTool::new(root);
+        let code_read = CodeReadTool::new(root);
+        let code_write = CodeWriteTool::new(root);
+        let shell = ShellTool::new(vec!["cat".to_string()], 5);
+        let alice_session = alice.join("sessions/s1.json").display().to_string();
+
+        CURRENT_WORKSPACE
+            .scope(Some(bob.clone()), async {
+                assert_eq!(cat.execute(json!({"path": "sessions/s2.json"})).await.unwrap(), "bob notes");
+                assert_eq!(ls.execute(json!({"path": "."})).await.unwrap(), "sessions/");
+                // 其他用户与默认用户的文件：相对路径越界被拒绝，按全局布局写的路径不存在
+                assert!(matches!(
+                    cat.execute(json!({"path": "../key.alice/sessions/s1.json"})).await,
+                    Err(ToolError::PermissionDenied(_))
+                ));
+                assert!(matches!(
+                    cat.execute(json!({"path": "../../MEMORY.md"})).await,
+                    Err(ToolError::PermissionDenied(_))
+                ));
+                assert!(matches!(
+                    cat.execute(json!({"path": "users/key.alice/sessions/s1.json"})).await,
+                    Err(ToolError::NotFound(_))
+                ));
+                assert!(matches!(
+                    code_read.execute(json!({"file_path": alice_session})).await,
+                    Err(ToolError::PermissionDenied(_))
+                ));
+                assert!(matches!(
+                    code_write
+                        .execute(json!({"file_path": "../key.alice/sessions/new.json", "content": "x"}))
+                        .await,
+                    Err(ToolError::PermissionDenied(_))
+                ));
+                assert!(matches!(
+                    shell.execute(json!({"command": format!("cat {}", alice_session)})).await,
+                    Err(ToolError::PermissionDenied(_))
+                ));
+            })
+            .await;
+        assert!(!alice.join("sessions/new.json").exists());
+
+        // 未设置 CURRENT_WORKSPACE（默认用户）时仍以全局工作区为根
+        assert_eq!(cat.execute(json!({"path": "MEMORY.md"})).await.unwrap(), "default memory");
+    }
+
+    #[test]
+    fn test_resolve_in_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "").unwrap();
+        let canonical = root.canonicalize().unwrap();
+
+        assert_eq!(resolve_in_root(root, "src/lib.rs").unwrap(), canonical.join("src/lib.rs"));
+        // 尚不存在的新文件可以写入
+        assert_eq!(resolve_in_root(root, "src/new.rs").unwrap(), root.join("src/new.rs"));
+        // 已存在路径规范化后越界、不存在路径含 ..、根外绝对路径均被拒绝
+        for path in ["../outside.txt", "src/../../outside.txt", "src/../new.rs", "/etc/passwd"] {
+            assert!(
+                matches!(resolve_in_root(root, path), Err(ToolError::PermissionDenied(_))),
+                "{}",
+                path
+            );
+        }
+    }
+}
@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use serde_json::Value;
 use tokio::process::Command;
 
-use crate::tools::Tool;
+use crate::tools::{ensure_unscoped, Tool, ToolError};
 
 pub struct GitCommitTool {
     project_root: PathBuf,
@@ -37,11 +37,12 @@ impl Tool for GitCommitTool {
 {"message": "Fix bug in parser", "files": ["src/parser.rs"]}"#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        ensure_unscoped("git_commit")?;
         let message = args
             .get("message")
             .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: message")?;
+            .ok_or_else(|| ToolError::missing("message"))?;
 
         let files = args.get("files").and_then(|v| v.as_array());
 
@@ -67,7 +68,7 @@ impl Tool for GitCommitTool {
 
         if !add_output.status.success() {
             let stderr = String::from_utf8_lossy(&add_output.stderr);
-            return Err(format!("git add failed: {}", stderr));
+            return Err(ToolError::Failed(format!("git add failed: {}", stderr)));
         }
 
         // Git commit
@@ -88,7 +89,7 @@ impl Tool for GitCommitTool {
         if commit_output.status.success() {
             Ok(format!("✓ Committed: {}\n{}", message, stdout))
         } else {
-            Err(format!("git commit failed: {}", stderr))
+            Err(ToolError::Failed(format!("git commit failed: {}", stderr)))
         }
     }
 }
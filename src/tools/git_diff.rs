@@ -3,7 +3,7 @@ use serde_json::Value;
 use std::path::Path;
 use std::process::Command;
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
 
 pub struct GitDiffTool;
 
@@ -51,17 +51,17 @@ impl Tool for GitDiffTool {
         "Show git diff. Args: {\"path\": \"repo path\", \"mode\": \"unstaged|staged|commit|branch\", \"target\": \"commit/branch\", \"base\": \"HEAD\", \"file\": \"specific file\", \"stat\": false}"
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let path_str = args["path"].as_str().unwrap_or(".");
         let path = Path::new(path_str);
         let mode = args["mode"].as_str().unwrap_or("unstaged");
         let stat = args["stat"].as_bool().unwrap_or(false);
         
         if !self.is_git_repo(path) {
-            return Err(format!(
+            return Err(ToolError::InvalidArgs(format!(
                 "Not a git repository: {}. Run 'git init' first.",
                 path.display()
-            ));
+            )));
         }
         
         let mut git_args = vec!["diff"];
@@ -76,22 +76,22 @@ impl Tool for GitDiffTool {
                 git_args.push("--cached");
             }
             "commit" => {
-                let target = args["target"].as_str().ok_or({
-                    "'target' is required for commit mode"
+                let target = args["target"].as_str().ok_or_else(|| {
+                    ToolError::InvalidArgs("'target' is required for commit mode".to_string())
                 })?;
                 let base = args["base"].as_str().unwrap_or("HEAD");
                 git_args.push(base);
                 git_args.push(target);
             }
             "branch" => {
-                let target = args["target"].as_str().ok_or({
-                    "'target' is required for branch mode"
+                let target = args["target"].as_str().ok_or_else(|| {
+                    ToolError::InvalidArgs("'target' is required for branch mode".to_string())
                 })?;
                 let base = args["base"].as_str().unwrap_or("HEAD");
                 git_args.push(base);
                 git_args.push(target);
             }
-            _ => return Err(format!("Invalid mode: {}", mode)),
+            _ => return Err(ToolError::InvalidArgs(format!("Invalid mode: {}", mode))),
         }
         
         if let Some(file) = args["file"].as_str() {
@@ -101,7 +101,7 @@ impl Tool for GitDiffTool {
         
         let diff_output = match self.run_git_command(&git_args, Some(path)) {
             Ok(output) => output,
-            Err(e) => return Err(format!("Git command failed: {}", e)),
+            Err(e) => return Err(ToolError::Failed(format!("Git command failed: {}", e))),
         };
         
         if diff_output.is_empty() {
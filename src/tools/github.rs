@@ -0,0 +1,572 @@
+//! GitHub / GitLab 工具
+//!
+//! github 支持列出 Issue 与 PR（GitLab 为 MR）、读取 PR 详情与 diff、发表评论，以及从分支创建 PR
+//! （可先 `git push -u origin <branch>`），让自主进化与编码助手走完「修改 → 审查 → PR」闭环。
+//! 令牌从 token_env 读取；只能访问 repo / allowed_repos 中的仓库，写操作需 allow_write = true。
+//! create_pr 会在 project_root 运行 git，因此限定于用户工作区的请求（多用户隔离）不可用本工具。
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use reqwest::{Client, Method, Url};
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+use crate::config::GithubSection;
+use crate::tools::{ensure_unscoped, Tool, ToolError};
+
+const GITHUB_API: &str = "https://api.github.com";
+const GITLAB_API: &str = "https://gitlab.com/api/v4";
+const DEFAULT_LIMIT: u64 = 20;
+const MAX_LIMIT: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Provider {
+    GitHub,
+    GitLab,
+}
+
+/// Issue / PR 列表项（两种平台字段名不同）
+fn item_line(provider: Provider, v: &Value) -> String {
+    let text = |path: &[&str]| {
+        path.iter()
+            .try_fold(v, |cur, key| cur.get(*key))
+            .and_then(|x| x.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    let (number, author, url) = match provider {
+        Provider::GitHub => (v.get("number"), text(&["user", "login"]), text(&["html_url"])),
+        Provider::GitLab => (v.get("iid"), text(&["author", "username"]), text(&["web_url"])),
+    };
+    let labels: Vec<&str> = v
+        .get("labels")
+        .and_then(|l| l.as_array())
+        .map(|l| {
+            l.iter()
+                .filter_map(|x| x.get("name").or(Some(x)).and_then(|n| n.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut line = format!(
+        "- #{} [{}] {} (@{})",
+        number.and_then(|n| n.as_u64()).unwrap_or(0),
+        text(&["state"]),
+        text(&["title"]),
+        author
+    );
+    if !labels.is_empty() {
+        line.push_str(&format!(" {{{}}}", labels.join(", ")));
+    }
+    if !url.is_empty() {
+        line.push_str(&format!(" {}", url));
+    }
+    line
+}
+
+/// GitLab MR changes 转为统一 diff 文本
+fn gitlab_changes_to_diff(v: &Value) -> String {
+    let mut out = String::new();
+    for change in v.get("changes").and_then(|c| c.as_array()).into_iter().flatten() {
+        let path = |k: &str| change.get(k).and_then(|p| p.as_str()).unwrap_or("");
+        let old = if change.get("new_file").and_then(|b| b.as_bool()) == Some(true) {
+            "/dev/null".to_string()
+        } else {
+            format!("a/{}", path("old_path"))
+        };
+        let new = if change.get("deleted_file").and_then(|b| b.as_bool()) == Some(true) {
+            "/dev/null".to_string()
+        } else {
+            format!("b/{}", path("new_path"))
+        };
+        out.push_str(&format!(
+            "diff --git a/{} b/{}\n--- {}\n+++ {}\n",
+            path("old_path"),
+            path("new_path"),
+            old,
+            new
+        ));
+        let diff = path("diff");
+        out.push_str(diff);
+        if !diff.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    let total = s.chars().count();
+    if total <= max {
+        return s.to_string();
+    }
+    let head: String = s.chars().take(max).collect();
+    format!("{}\n... (truncated, {} of {} chars shown)", head, max, total)
+}
+
+/// 仓库名只允许 "owner/name" 形式（GitLab 可多级 group）
+fn valid_repo(repo: &str) -> bool {
+    let parts: Vec<&str> = repo.split('/').collect();
+    parts.len() >= 2
+        && parts.iter().all(|p| {
+            !p.is_empty()
+                && *p != "."
+                && *p != ".."
+                && p.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        })
+}
+
+/// github 工具
+pub struct GithubTool {
+    client: Client,
+    provider: Provider,
+    api_base: String,
+    token_env: String,
+    default_repo: Option<String>,
+    allowed_repos: Vec<String>,
+    allow_write: bool,
+    max_diff_chars: usize,
+    project_root: PathBuf,
+}
+
+impl GithubTool {
+    /// project_root 为 create_pr 推送分支时运行 git 的目录
+    pub fn new(config: &GithubSection, project_root: impl AsRef<Path>) -> Self {
+        let provider = if config.provider.eq_ignore_ascii_case("gitlab") {
+            Provider::GitLab
+        } else {
+            Provider::GitHub
+        };
+        let default_api = match provider {
+            Provider::GitHub => GITHUB_API,
+            Provider::GitLab => GITLAB_API,
+        };
+        let mut allowed_repos = config.allowed_repos.clone();
+        if allowed_repos.is_empty() {
+            allowed_repos.extend(config.repo.clone());
+        }
+        Self {
+            client: Client::new(),
+            provider,
+            api_base: config.api_base.clone().unwrap_or_else(|| default_api.to_string()),
+            token_env: config.token_env.clone(),
+            default_repo: config.repo.clone(),
+            allowed_repos,
+            allow_write: config.allow_write,
+            max_diff_chars: config.max_diff_chars,
+            project_root: project_root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn repo(&self, args: &Value) -> Result<String, ToolError> {
+        let repo = args
+            .get("repo")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .or_else(|| self.default_repo.clone())
+            .ok_or_else(|| ToolError::missing("repo"))?;
+        if !valid_repo(&repo) {
+            return Err(ToolError::InvalidArgs(format!(
+                "Invalid repo '{}': expected owner/name",
+                repo
+            )));
+        }
+        if !self.allowed_repos.iter().any(|r| r.eq_ignore_ascii_case(&repo)) {
+            return Err(ToolError::Failed(format!(
+                "Repo '{}' is not in [tools.github] allowed_repos ({})",
+                repo,
+                self.allowed_repos.join(", ")
+            )));
+        }
+        Ok(repo)
+    }
+
+    fn number(args: &Value) -> Result<u64, ToolError> {
+        args.get("number")
+            .and_then(|v| {
+                v.as_u64()
+                    .or_else(|| v.as_str().and_then(|s| s.trim_start_matches('#').parse().ok()))
+            })
+            .ok_or_else(|| ToolError::missing("number"))
+    }
+
+    fn require_write(&self, action: &str) -> Result<(), ToolError> {
+        if self.allow_write {
+            Ok(())
+        } else {
+            Err(ToolError::Failed(format!(
+                "{} is a write action; set [tools.github] allow_write = true to enable it",
+                action
+            )))
+        }
+    }
+
+    /// 仓库下的 API 地址：GitHub 为 /repos/{owner}/{name}/...，GitLab 为 /projects/{编码后的路径}/...
+    fn url(&self, repo: &str, segments: &[&str]) -> Result<Url, ToolError> {
+        let mut url = Url::parse(&self.api_base)
+            .map_err(|e| ToolError::Failed(format!("Invalid api_base '{}': {}", self.api_base, e)))?;
+        {
+            let mut segs = url
+                .path_segments_mut()
+                .map_err(|_| ToolError::Failed(format!("Invalid api_base '{}'", self.api_base)))?;
+            segs.pop_if_empty();
+            match self.provider {
+                Provider::GitHub => {
+                    segs.push("repos").extend(repo.split('/'));
+                }
+                Provider::GitLab => {
+                    segs.push("projects").push(repo);
+                }
+            }
+            segs.extend(segments);
+        }
+        Ok(url)
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<Value>,
+        accept: Option<&str>,
+    ) -> Result<reqwest::Response, ToolError> {
+        let token = std::env::var(&self.token_env)
+            .map_err(|_| ToolError::Failed(format!("Token env {} is not set", self.token_env)))?;
+        let mut req = self
+            .client
+            .request(method, url)
+            .header(reqwest::header::USER_AGENT, "bee-agent");
+        req = match self.provider {
+            Provider::GitHub => req
+                .bearer_auth(token)
+                .header(reqwest::header::ACCEPT, accept.unwrap_or("application/vnd.github+json"))
+                .header("X-GitHub-Api-Version", "2022-11-28"),
+            Provider::GitLab => req.header("PRIVATE-TOKEN", token),
+        };
+        if let Some(body) = body {
+            req = req.json(&body);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ToolError::Failed(format!("API request failed: {}", e)))?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let text: String = resp.text().await.unwrap_or_default().chars().take(500).collect();
+        Err(ToolError::Failed(format!("API returned {}: {}", status.as_u16(), text)))
+    }
+
+    async fn json(&self, method: Method, url: Url, body: Option<Value>) -> Result<Value, ToolError> {
+        self.request(method, url, body, None)
+            .await?
+            .json()
+            .await
+            .map_err(|e| ToolError::Failed(format!("Invalid API response: {}", e)))
+    }
+
+    fn pr_segment(&self) -> &'static str {
+        match self.provider {
+            Provider::GitHub => "pulls",
+            Provider::GitLab => "merge_requests",
+        }
+    }
+
+    async fn list(&self, args: &Value, prs: bool) -> Result<String, ToolError> {
+        let repo = self.repo(args)?;
+        let state = args.get("state").and_then(|v| v.as_str()).unwrap_or("open");
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_LIMIT)
+            .clamp(1, MAX_LIMIT);
+        let kind = if prs { self.pr_segment() } else { "issues" };
+        let mut url = self.url(&repo, &[kind])?;
+        {
+            let mut q = url.query_pairs_mut();
+            match self.provider {
+                Provider::GitHub => q.append_pair("state", state),
+                // GitLab 的状态为 opened / closed / merged / all
+                Provider::GitLab => q.append_pair("state", if state == "open" { "opened" } else { state }),
+            };
+            q.append_pair("per_page", &limit.to_string());
+        }
+        let items = self.json(Method::GET, url, None).await?;
+        let lines: Vec<String> = items
+            .as_array()
+            .into_iter()
+            .flatten()
+            // GitHub 的 issues 列表包含 PR，这里排除
+            .filter(|v| prs || v.get("pull_request").is_none())
+            .map(|v| item_line(self.provider, v))
+            .collect();
+        let label = if prs { "pull request(s)" } else { "issue(s)" };
+        if lines.is_empty() {
+            return Ok(format!("No {} {} in {}", state, label, repo));
+        }
+        Ok(format!(
+            "{} {} {} in {}:\n{}",
+            lines.len(),
+            state,
+            label,
+            repo,
+            lines.join("\n")
+        ))
+    }
+
+    async fn get_pr(&self, args: &Value) -> Result<String, ToolError> {
+        let repo = self.repo(args)?;
+        let number = Self::number(args)?;
+        let v = self
+            .json(
+                Method::GET,
+                self.url(&repo, &[self.pr_segment(), &number.to_string()])?,
+                None,
+            )
+            .await?;
+        let text = |k: &str| v.get(k).and_then(|x| x.as_str()).unwrap_or("").to_string();
+        let (head, base, body) = match self.provider {
+            Provider::GitHub => (
+                v.pointer("/head/ref")
+                    .and_then(|x| x.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                v.pointer("/base/ref")
+                    .and_then(|x| x.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                text("body"),
+            ),
+            Provider::GitLab => (text("source_branch"), text("target_branch"), text("description")),
+        };
+        Ok(format!(
+            "{}\nBranches: {} -> {}\n\n{}",
+            item_line(self.provider, &v),
+            head,
+            base,
+            truncate_chars(&body, self.max_diff_chars)
+        ))
+    }
+
+    async fn diff(&self, args: &Value) -> Result<String, ToolError> {
+        let repo = self.repo(args)?;
+        let number = Self::number(args)?.to_string();
+        let diff = match self.provider {
+            Provider::GitHub => {
+                let url = self.url(&repo, &["pulls", &number])?;
+                self.request(Method::GET, url, None, Some("application/vnd.github.diff"))
+                    .await?
+                    .text()
+                    .await
+                    .map_err(|e| ToolError::Failed(format!("Invalid API response: {}", e)))?
+            }
+            Provider::GitLab => {
+                let url = self.url(&repo, &["merge_requests", &number, "changes"])?;
+                gitlab_changes_to_diff(&self.json(Method::GET, url, None).await?)
+            }
+        };
+        if diff.trim().is_empty() {
+            return Ok(format!("#{} has no changes", number));
+        }
+        Ok(truncate_chars(&diff, self.max_diff_chars))
+    }
+
+    async fn comment(&self, args: &Value) -> Result<String, ToolError> {
+        self.require_write("comment")?;
+        let repo = self.repo(args)?;
+        let number = Self::number(args)?.to_string();
+        let body = args
+            .get("body")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| ToolError::missing("body"))?;
+        let (url, payload) = match self.provider {
+            // GitHub 的 PR 评论与 Issue 评论共用 issues 接口
+            Provider::GitHub => (
+                self.url(&repo, &["issues", &number, "comments"])?,
+                json!({ "body": body }),
+            ),
+            Provider::GitLab => {
+                let kind = if args.get("on").and_then(|v| v.as_str()) == Some("issue") {
+                    "issues"
+                } else {
+                    "merge_requests"
+                };
+                (self.url(&repo, &[kind, &number, "notes"])?, json!({ "body": body }))
+            }
+        };
+        let v = self.json(Method::POST, url, Some(payload)).await?;
+        let link = v.get("html_url").and_then(|x| x.as_str()).unwrap_or("");
+        Ok(format!("✓ Commented on {}#{} {}", repo, number, link)
+            .trim_end()
+            .to_string())
+    }
+
+    async fn git(&self, args: &[&str]) -> Result<String, ToolError> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.project_root)
+            .output()
+            .await
+            .map_err(|e| ToolError::Failed(format!("Failed to run git: {}", e)))?;
+        if !output.status.success() {
+            return Err(ToolError::Failed(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    async fn create_pr(&self, args: &Value) -> Result<String, ToolError> {
+        self.require_write("create_pr")?;
+        let repo = self.repo(args)?;
+        let title = args
+            .get("title")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| ToolError::missing("title"))?;
+        let body = args.get("body").and_then(|v| v.as_str()).unwrap_or("");
+        let head = match args.get("head").and_then(|v| v.as_str()) {
+            Some(h) => h.to_string(),
+            None => self.git(&["rev-parse", "--abbrev-ref", "HEAD"]).await?,
+        };
+        let base = match args.get("base").and_then(|v| v.as_str()) {
+            Some(b) => b.to_string(),
+            None => {
+                let info = self.json(Method::GET, self.url(&repo, &[])?, None).await?;
+                info.get("default_branch")
+                    .and_then(|b| b.as_str())
+                    .ok_or_else(|| ToolError::Failed("Cannot determine default branch; pass base".to_string()))?
+                    .to_string()
+            }
+        };
+        if head == base || head == "HEAD" {
+            return Err(ToolError::InvalidArgs(format!(
+                "head branch '{}' must be a branch other than base '{}'",
+                head, base
+            )));
+        }
+        if args.get("push").and_then(|v| v.as_bool()).unwrap_or(false) {
+            self.git(&["push", "-u", "origin", &head]).await?;
+        }
+        let draft = args.get("draft").and_then(|v| v.as_bool()).unwrap_or(false);
+        let (url, payload) = match self.provider {
+            Provider::GitHub => (
+                self.url(&repo, &["pulls"])?,
+                json!({ "title": title, "head": head, "base": base, "body": body, "draft": draft }),
+            ),
+            Provider::GitLab => (
+                self.url(&repo, &["merge_requests"])?,
+                json!({
+                    "title": if draft { format!("Draft: {}", title) } else { title.to_string() },
+                    "source_branch": head,
+                    "target_branch": base,
+                    "description": body,
+                }),
+            ),
+        };
+        let v = self.json(Method::POST, url, Some(payload)).await?;
+        Ok(format!(
+            "✓ Created {}",
+            item_line(self.provider, &v).trim_start_matches("- ")
+        ))
+    }
+}
+
+#[async_trait]
+impl Tool for GithubTool {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn description(&self) -> &str {
+        r#"GitHub / GitLab 仓库操作（Issue、PR/MR、diff、评论）。
+
+参数:
+- action: list_issues | list_prs | get_pr | diff | comment | create_pr（必需）
+- repo: "owner/name"（可选，默认配置的 repo）
+- list_*: state（open/closed/all，默认 open）、limit（默认 20）
+- get_pr / diff: number
+- comment: number、body（GitLab 评论 Issue 时加 "on": "issue"）
+- create_pr: title、body、head（默认当前分支）、base（默认仓库默认分支）、push（先 git push，默认 false）、draft
+
+示例:
+{"action": "diff", "number": 42}
+{"action": "create_pr", "title": "Fix parser panic", "body": "...", "push": true}"#
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        ensure_unscoped(self.name())?;
+        let action = args
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("action"))?;
+        tracing::info!(action, "github tool execute");
+        match action {
+            "list_issues" => self.list(&args, false).await,
+            "list_prs" => self.list(&args, true).await,
+            "get_pr" => self.get_pr(&args).await,
+            "diff" => self.diff(&args).await,
+            "comment" => self.comment(&args).await,
+            "create_pr" => self.create_pr(&args).await,
+            other => Err(ToolError::InvalidArgs(format!(
+                "Unknown action '{}': use list_issues, list_prs, get_pr, diff, comment or create_pr",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_github_scoping_urls_and_formatting() {
+        let config = GithubSection {
+            repo: Some("acme/bee".to_string()),
+            ..GithubSection::default()
+        };
+        let tool = GithubTool::new(&config, ".");
+        assert_eq!(tool.repo(&json!({})).unwrap(), "acme/bee");
+        assert!(tool.repo(&json!({"repo": "acme/other"})).is_err());
+        assert!(tool.repo(&json!({"repo": "../etc"})).is_err());
+        // 默认只读：写操作在发请求前被拒绝
+        let err = tool
+            .execute(json!({"action": "comment", "number": 1, "body": "hi"}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("allow_write"));
+        assert_eq!(
+            tool.url("acme/bee", &["pulls", "7"]).unwrap().as_str(),
+            "https://api.github.com/repos/acme/bee/pulls/7"
+        );
+
+        let gitlab = GithubTool::new(
+            &GithubSection {
+                provider: "gitlab".to_string(),
+                ..config
+            },
+            ".",
+        );
+        assert_eq!(
+            gitlab.url("acme/bee", &["merge_requests"]).unwrap().as_str(),
+            "https://gitlab.com/api/v4/projects/acme%2Fbee/merge_requests"
+        );
+
+        let pr = json!({"number": 7, "state": "open", "title": "Fix", "user": {"login": "jo"},
+            "labels": [{"name": "bug"}], "html_url": "https://github.com/acme/bee/pull/7"});
+        assert_eq!(
+            item_line(Provider::GitHub, &pr),
+            "- #7 [open] Fix (@jo) {bug} https://github.com/acme/bee/pull/7"
+        );
+        let changes = json!({"changes": [{"old_path": "a.rs", "new_path": "a.rs", "diff": "@@ -1 +1 @@\n-x\n+y"}]});
+        assert_eq!(
+            gitlab_changes_to_diff(&changes),
+            "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n@@ -1 +1 @@\n-x\n+y\n"
+        );
+    }
+}
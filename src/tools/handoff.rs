@@ -0,0 +1,83 @@
+//! handoff 工具：把当前会话转交给另一个助手
+//!
+//! 工具本身只校验参数；调用成功后由 ReAct 循环生成交接摘要并结束本轮，接入端把会话转交给目标助手
+//! （见 `react::handoff`）。目标助手使用自己的技能范围，不继承当前助手的工具权限。
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::react::{HandoffRequest, HANDOFF_TOOL};
+use crate::tools::{Tool, ToolError, CURRENT_ASSISTANT_ID};
+
+/// handoff：转交会话给指定助手，或留空由路由按原因选择
+pub struct HandoffTool;
+
+#[async_trait]
+impl Tool for HandoffTool {
+    fn name(&self) -> &str {
+        HANDOFF_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Hand the conversation off to another assistant that is better suited to the user's request.\n\
+         Args: to (assistant id, optional; omit to let the router choose), reason (required: why the other assistant should take over), \
+         summary (optional: notes for the next assistant). \
+         Your turn ends after a successful handoff; the next assistant receives a summary of this conversation and continues with its own tools."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "to": { "type": "string", "description": "Target assistant id; omit to let the router choose" },
+                "reason": { "type": "string", "description": "Why the other assistant should take over" },
+                "summary": { "type": "string", "description": "Optional notes for the next assistant" }
+            },
+            "required": ["reason"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let request = HandoffRequest::from_args(&args);
+        if request.reason.is_empty() {
+            return Err(ToolError::InvalidArgs("reason is required".to_string()));
+        }
+        let current = CURRENT_ASSISTANT_ID.try_with(|id| id.clone()).ok().flatten();
+        if request.to.is_some() && request.to == current {
+            return Err(ToolError::InvalidArgs(
+                "cannot hand off to yourself; answer the user directly or pick another assistant".to_string(),
+            ));
+        }
+        Ok(match &request.to {
+            Some(to) => format!("Handoff to {} accepted: {}", to, request.reason),
+            None => format!(
+                "Handoff accepted, the router will choose the assistant: {}",
+                request.reason
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handoff_validates_args() {
+        let tool = HandoffTool;
+        let ok = tool
+            .execute(serde_json::json!({"to": "coder", "reason": "code change"}))
+            .await
+            .unwrap();
+        assert!(ok.contains("coder"));
+        assert!(matches!(
+            tool.execute(serde_json::json!({"to": "coder"})).await,
+            Err(ToolError::InvalidArgs(_))
+        ));
+        let to_self = CURRENT_ASSISTANT_ID.scope(
+            Some("coder".to_string()),
+            tool.execute(serde_json::json!({"to": "coder", "reason": "x"})),
+        );
+        assert!(matches!(to_self.await, Err(ToolError::InvalidArgs(_))));
+    }
+}
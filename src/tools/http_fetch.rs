@@ -0,0 +1,342 @@
+//! HTTP 请求工具：调用 REST API 或读取网页，无需 browser feature
+//!
+//! 支持 GET/POST、自定义请求头与 JSON 请求体；仅允许白名单域名及其子域名（重定向同样校验）。
+//! 响应最多读取 max_response_bytes 字节；HTML 自动转为 Markdown 风格文本，JSON 格式化后返回，
+//! 结果超过 max_result_chars 时截断。挂载 PolitePolicy 后按域名限速并遵守 robots.txt。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, Method, Url};
+use serde_json::Value;
+
+use crate::config::HttpFetchSection;
+use crate::tools::search::{looks_like_html, strip_html_tags};
+use crate::tools::{PolitePolicy, Tool, ToolError};
+
+/// HTML 转 Markdown 时的行宽
+const HTML_WIDTH: usize = 100;
+
+/// http_fetch 工具
+pub struct HttpFetchTool {
+    client: Client,
+    allowed_domains: Arc<Vec<String>>,
+    max_response_bytes: usize,
+    max_result_chars: usize,
+    polite: Option<Arc<PolitePolicy>>,
+}
+
+/// host 是否为白名单域名或其子域名；完全限定写法的结尾点（github.com.）视为同一主机
+fn domain_allowed(allowed: &[String], host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    allowed
+        .iter()
+        .any(|d| host == d || host.strip_suffix(d.as_str()).is_some_and(|p| p.ends_with('.')))
+}
+
+/// 网络层 / 服务端错误：GET、HEAD 可安全重放，记为 Transient；其它方法可能已在远端生效，记为 Failed
+fn retryable_error(method: &Method, msg: String) -> ToolError {
+    if *method == Method::GET || *method == Method::HEAD {
+        ToolError::Transient(msg)
+    } else {
+        ToolError::Failed(msg)
+    }
+}
+
+/// 将 args.headers（对象）转为 HeaderMap；Host 与 Content-Length 由客户端决定，不允许覆盖
+fn parse_headers(value: Option<&Value>) -> Result<HeaderMap, ToolError> {
+    let mut headers = HeaderMap::new();
+    let Some(value) = value else {
+        return Ok(headers);
+    };
+    let obj = value
+        .as_object()
+        .ok_or_else(|| ToolError::InvalidArgs("headers must be an object".to_string()))?;
+    for (k, v) in obj {
+        let name = HeaderName::from_bytes(k.as_bytes())
+            .map_err(|_| ToolError::InvalidArgs(format!("Invalid header name: {}", k)))?;
+        if name == reqwest::header::HOST || name == reqwest::header::CONTENT_LENGTH {
+            continue;
+        }
+        let text = match v {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let value = HeaderValue::from_str(&text)
+            .map_err(|_| ToolError::InvalidArgs(format!("Invalid value for header {}", k)))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// 按内容类型整理响应体：JSON 格式化，HTML 转 Markdown，其它原样；再按字符数截断
+fn render_body(body: &str, content_type: &str, max_chars: usize) -> String {
+    let body = body.trim_start_matches('\u{FEFF}');
+    let rendered = if content_type.contains("json") {
+        serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|v| serde_json::to_string_pretty(&v).ok())
+            .unwrap_or_else(|| body.to_string())
+    } else if content_type.contains("html") || looks_like_html(body) {
+        match html2text::from_read(body.as_bytes(), HTML_WIDTH) {
+            Ok(md) if !md.trim().is_empty() => md,
+            _ => strip_html_tags(body),
+        }
+    } else {
+        body.to_string()
+    };
+    if rendered.chars().count() > max_chars {
+        rendered.chars().take(max_chars).collect::<String>() + "\n...[truncated]"
+    } else {
+        rendered
+    }
+}
+
+impl HttpFetchTool {
+    pub fn new(config: &HttpFetchSection, fallback_domains: &[String]) -> Self {
+        let domains = if config.allowed_domains.is_empty() {
+            fallback_domains
+        } else {
+            &config.allowed_domains
+        };
+        let allowed_domains: Arc<Vec<String>> =
+            Arc::new(domains.iter().map(|d| d.trim().trim_end_matches('.').to_lowercase()).collect());
+        // 重定向目标同样必须在白名单内
+        let redirect_allowed = Arc::clone(&allowed_domains);
+        let redirect = reqwest::redirect::Policy::custom(move |attempt| {
+            let allowed = attempt
+                .url()
+                .host_str()
+                .is_some_and(|h| domain_allowed(&redirect_allowed, &h.to_lowercase()));
+            if attempt.previous().len() >= 5 || !allowed {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        });
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(config.timeout_secs))
+            .user_agent(concat!("bee-agent/", env!("CARGO_PKG_VERSION")))
+            .redirect(redirect)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            allowed_domains,
+            max_response_bytes: config.max_response_bytes,
+            max_result_chars: config.max_result_chars,
+            polite: None,
+        }
+    }
+
+    /// 挂载礼貌抓取策略（与 search / browser 共享）
+    pub fn with_politeness(mut self, polite: Arc<PolitePolicy>) -> Self {
+        self.polite = Some(polite);
+        self
+    }
+
+    /// 解析并校验 URL：仅 http(s)、不带 userinfo，host 须在白名单内；返回解析结果供请求直接使用，
+    /// 避免校验与实际连接对 host 的理解不一致
+    fn check_url(&self, url: &str) -> Result<Url, ToolError> {
+        let invalid = || ToolError::InvalidArgs("Invalid or missing URL (http/https only)".to_string());
+        let parsed = Url::parse(url).map_err(|_| invalid())?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(invalid());
+        }
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err(ToolError::InvalidArgs("URL must not contain credentials".to_string()));
+        }
+        let host = parsed.host_str().ok_or_else(invalid)?.to_lowercase();
+        if domain_allowed(&self.allowed_domains, &host) {
+            Ok(parsed)
+        } else {
+            Err(ToolError::PermissionDenied(format!("Domain not in allowlist: {}", host)))
+        }
+    }
+
+    /// 逐块读取响应体，超过 max_response_bytes 时停止，返回 (内容, 是否截断)
+    async fn read_limited(&self, mut resp: reqwest::Response, method: &Method) -> Result<(String, bool), ToolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| retryable_error(method, format!("Read body: {}", e)))?
+        {
+            let room = self.max_response_bytes.saturating_sub(buf.len());
+            if chunk.len() > room {
+                buf.extend_from_slice(&chunk[..room]);
+                truncated = true;
+                break;
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        Ok((String::from_utf8_lossy(&buf).into_owned(), truncated))
+    }
+}
+
+#[async_trait]
+impl Tool for HttpFetchTool {
+    fn name(&self) -> &str {
+        "http_fetch"
+    }
+
+    fn description(&self) -> &str {
+        "HTTP request to an allowlisted domain (REST APIs or web pages; HTML is converted to Markdown). Args: {\"url\": \"https://...\", \"method\": \"GET|POST\", \"headers\": {...}, \"json\": {...}}.\n\
+         - method defaults to GET; POST sends `json` (object/array) as application/json, or `body` (string) as-is.\n\
+         - Responses are size-limited and truncated; JSON is pretty-printed.\n\
+         - Redirects to domains outside the allowlist are not followed."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "http(s) URL on an allowlisted domain" },
+                "method": { "type": "string", "enum": ["GET", "POST"], "description": "HTTP method (default GET)" },
+                "headers": { "type": "object", "description": "Extra request headers, e.g. {\"Accept\": \"application/json\"}" },
+                "json": { "description": "JSON request body (POST)" },
+                "body": { "type": "string", "description": "Raw request body (POST, used when json is absent)" }
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if url.is_empty() {
+            return Err(ToolError::missing("url"));
+        }
+        let url = self.check_url(url)?;
+        let method = match args
+            .get("method")
+            .and_then(|v| v.as_str())
+            .unwrap_or("GET")
+            .to_uppercase()
+            .as_str()
+        {
+            "GET" => Method::GET,
+            "POST" => Method::POST,
+            other => {
+                return Err(ToolError::InvalidArgs(format!(
+                    "Unsupported method {} (GET or POST)",
+                    other
+                )))
+            }
+        };
+        let headers = parse_headers(args.get("headers"))?;
+
+        let mut req = self.client.request(method.clone(), url.clone()).headers(headers);
+        if method == Method::POST {
+            if let Some(json) = args.get("json") {
+                req = req.json(json);
+            } else if let Some(body) = args.get("body").and_then(|v| v.as_str()) {
+                req = req.body(body.to_string());
+            }
+        }
+        if let Some(ref polite) = self.polite {
+            polite.acquire(url.as_str()).await?;
+            req = req.header(reqwest::header::USER_AGENT, polite.user_agent());
+        }
+        tracing::info!(url = %url, method = %method, "http_fetch");
+
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| retryable_error(&method, format!("Request failed: {}", e)))?;
+        let status = resp.status();
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+        let (body, truncated) = self.read_limited(resp, &method).await?;
+        let mut rendered = render_body(&body, &content_type, self.max_result_chars);
+        if truncated {
+            rendered.push_str(&format!(
+                "\n...[response exceeded {} bytes, rest discarded]",
+                self.max_response_bytes
+            ));
+        }
+
+        if status.is_server_error() || status.as_u16() == 429 {
+            return Err(retryable_error(&method, format!("HTTP {}", status)));
+        }
+        if status.as_u16() == 404 {
+            return Err(ToolError::NotFound(format!("HTTP {}", status)));
+        }
+        if !status.is_success() && !status.is_redirection() {
+            // 4xx 保留响应体，REST API 的错误信息通常在其中
+            return Err(ToolError::Failed(format!("HTTP {}\n{}", status, rendered)));
+        }
+        Ok(format!("HTTP {} ({})\n\n{}", status, content_type, rendered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_fetch_allowlist_headers_and_render() {
+        let config = HttpFetchSection {
+            allowed_domains: vec!["GitHub.com".to_string()],
+            ..Default::default()
+        };
+        let tool = HttpFetchTool::new(&config, &["docs.rs".to_string()]);
+        assert!(tool.check_url("https://api.github.com/repos/x/y").is_ok());
+        assert!(tool.check_url("https://github.com/x").is_ok());
+        assert!(matches!(
+            tool.check_url("https://evilgithub.com/"),
+            Err(ToolError::PermissionDenied(_))
+        ));
+        assert!(matches!(tool.check_url("https://docs.rs/"), Err(ToolError::PermissionDenied(_))));
+        assert!(matches!(tool.check_url("ftp://github.com/"), Err(ToolError::InvalidArgs(_))));
+        // 查询串 / 片段里伪装的白名单后缀不能绕过校验
+        assert!(matches!(
+            tool.check_url("https://evil.com?.github.com/"),
+            Err(ToolError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            tool.check_url("https://evil.com#.github.com/"),
+            Err(ToolError::PermissionDenied(_))
+        ));
+        assert_eq!(tool.check_url("https://api.github.com?x=1").unwrap().host_str(), Some("api.github.com"));
+        // 带 userinfo 的 URL 一律拒绝
+        assert!(matches!(tool.check_url("https://github.com@evil.com/"), Err(ToolError::InvalidArgs(_))));
+        assert!(matches!(tool.check_url("https://user:pw@github.com/"), Err(ToolError::InvalidArgs(_))));
+        assert!(matches!(tool.check_url("http://github.com@evil.com/"), Err(ToolError::InvalidArgs(_))));
+        // host 大小写与结尾点不影响判断，也不能借此绕过
+        assert_eq!(tool.check_url("HTTPS://API.GitHub.COM/x").unwrap().host_str(), Some("api.github.com"));
+        assert!(tool.check_url("https://github.com./x").is_ok());
+        assert!(matches!(tool.check_url("https://EVIL.com./"), Err(ToolError::PermissionDenied(_))));
+        assert!(matches!(
+            tool.check_url("https://github.com.evil.com./"),
+            Err(ToolError::PermissionDenied(_))
+        ));
+
+        let headers =
+            parse_headers(Some(&serde_json::json!({"Accept": "application/json", "X-Page": 2, "Host": "x"})))
+                .unwrap();
+        assert_eq!(headers.get("x-page").unwrap(), "2");
+        assert!(headers.get("host").is_none());
+        assert!(parse_headers(Some(&serde_json::json!(["bad"]))).is_err());
+
+        assert_eq!(render_body(r#"{"a":1}"#, "application/json", 100), "{\n  \"a\": 1\n}");
+        let md = render_body(
+            "<html><body><h1>Title</h1><p>Hello <a href=\"https://x.org\">link</a></p></body></html>",
+            "text/html; charset=utf-8",
+            1000,
+        );
+        assert!(md.contains("# Title"));
+        assert!(!md.contains("<p>"));
+        assert!(render_body(&"x".repeat(50), "text/plain", 10).ends_with("...[truncated]"));
+
+        // 只有 GET / HEAD 的失败可以自动重试
+        assert!(retryable_error(&Method::GET, "HTTP 503".into()).is_retryable());
+        assert!(retryable_error(&Method::HEAD, "HTTP 429".into()).is_retryable());
+        assert!(matches!(retryable_error(&Method::POST, "HTTP 503".into()), ToolError::Failed(_)));
+    }
+}
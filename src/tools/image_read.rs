@@ -0,0 +1,303 @@
+//! 图片识别工具
+//!
+//! image_read 对 workspace 内的图片做 OCR 与内容描述：优先调用 OpenAI 兼容的视觉模型（image_url 传 base64），
+//! 无 Key 或调用失败时回退到本机 tesseract（仅 OCR）。用于截图驱动的流程，如 WhatsApp / 飞书用户发来的截图。
+
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::config::ImageReadSection;
+use crate::tools::filesystem::{fs_error, SafeFs};
+use crate::tools::{Tool, ToolError};
+
+const DEFAULT_VISION_BASE_URL: &str = "https://api.openai.com/v1";
+/// 视觉模型单次回复上限
+const VISION_MAX_TOKENS: u32 = 1500;
+
+/// 识别方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageMode {
+    /// 仅抽取文字
+    Ocr,
+    /// 仅描述画面
+    Describe,
+    /// 文字 + 描述
+    Both,
+}
+
+impl ImageMode {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ocr" | "text" => Some(Self::Ocr),
+            "describe" | "caption" => Some(Self::Describe),
+            "" | "both" | "auto" => Some(Self::Both),
+            _ => None,
+        }
+    }
+}
+
+/// 实际使用的后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Vision,
+    Tesseract,
+}
+
+/// 按扩展名得到图片 MIME 类型；不支持的格式返回 None
+fn media_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "bmp" => Some("image/bmp"),
+        "tif" | "tiff" => Some("image/tiff"),
+        _ => None,
+    }
+}
+
+/// 视觉模型的指令文本
+fn vision_prompt(mode: ImageMode, question: Option<&str>) -> String {
+    let task = match mode {
+        ImageMode::Ocr => "Transcribe all visible text in this image verbatim, preserving line breaks, table rows and reading order. Output only the text; write [no text] if there is none.",
+        ImageMode::Describe => "Describe this image concisely: what it shows, its layout, and any notable UI elements, charts or people. Mention key text briefly.",
+        ImageMode::Both => "First output a section \"Text:\" transcribing all visible text verbatim (preserving line breaks and reading order, or [no text]). Then output a section \"Description:\" describing what the image shows in a few sentences.",
+    };
+    match question.map(str::trim).filter(|q| !q.is_empty()) {
+        Some(q) => format!("{}\n\nAlso answer this question about the image: {}", task, q),
+        None => task.to_string(),
+    }
+}
+
+/// OpenAI 兼容 chat/completions 请求体（图片以 data URL 内联）
+fn vision_request(model: &str, prompt: &str, mime: &str, bytes: &[u8]) -> Value {
+    let data_url = format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    );
+    json!({
+        "model": model,
+        "max_tokens": VISION_MAX_TOKENS,
+        "messages": [{
+            "role": "user",
+            "content": [
+                { "type": "text", "text": prompt },
+                { "type": "image_url", "image_url": { "url": data_url } }
+            ]
+        }]
+    })
+}
+
+fn vision_content(resp: &Value) -> Option<String> {
+    let content = resp.pointer("/choices/0/message/content")?;
+    match content {
+        Value::String(s) => Some(s.clone()),
+        // 部分兼容实现返回分段数组
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join(""),
+        ),
+        _ => None,
+    }
+}
+
+/// image_read 工具
+pub struct ImageReadTool {
+    fs: SafeFs,
+    client: Client,
+    config: ImageReadSection,
+}
+
+impl ImageReadTool {
+    pub fn new(root_dir: impl AsRef<Path>, config: &ImageReadSection) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+        Self {
+            fs: SafeFs::new(root_dir),
+            client,
+            config: config.clone(),
+        }
+    }
+
+    fn api_key(&self) -> Option<String> {
+        std::env::var(&self.config.api_key_env).ok().filter(|k| !k.trim().is_empty())
+    }
+
+    /// 按配置与环境选择后端（auto 时有 Key 用视觉模型，否则 tesseract）
+    fn backends(&self) -> Result<Vec<Backend>, ToolError> {
+        let has_key = self.api_key().is_some();
+        match self.config.backend.to_ascii_lowercase().as_str() {
+            "vision" if has_key => Ok(vec![Backend::Vision]),
+            "vision" => Err(ToolError::Failed(format!(
+                "image_read backend is vision but {} is not set",
+                self.config.api_key_env
+            ))),
+            "tesseract" => Ok(vec![Backend::Tesseract]),
+            _ if has_key => Ok(vec![Backend::Vision, Backend::Tesseract]),
+            _ => Ok(vec![Backend::Tesseract]),
+        }
+    }
+
+    async fn read_with_vision(&self, mode: ImageMode, question: Option<&str>, mime: &str, bytes: &[u8]) -> Result<String, String> {
+        let key = self.api_key().ok_or("no API key")?;
+        let base = self
+            .config
+            .vision_base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_VISION_BASE_URL)
+            .trim_end_matches('/');
+        let body = vision_request(&self.config.vision_model, &vision_prompt(mode, question), mime, bytes);
+        let resp = self
+            .client
+            .post(format!("{}/chat/completions", base))
+            .bearer_auth(key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = resp.status();
+        let value: Value = resp.json().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            let msg = value
+                .pointer("/error/message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string());
+            return Err(format!("HTTP {}: {}", status.as_u16(), msg));
+        }
+        vision_content(&value).ok_or_else(|| "vision response has no content".to_string())
+    }
+
+    async fn read_with_tesseract(&self, path: &Path) -> Result<String, String> {
+        let output = tokio::time::timeout(
+            Duration::from_secs(self.config.timeout_secs.max(1)),
+            tokio::process::Command::new("tesseract")
+                .arg(path)
+                .arg("stdout")
+                .arg("-l")
+                .arg(&self.config.tesseract_lang)
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await
+        .map_err(|_| format!("tesseract timed out after {}s", self.config.timeout_secs))?
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "tesseract not found: install tesseract-ocr or set a vision model API key".to_string()
+            }
+            _ => e.to_string(),
+        })?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+#[async_trait]
+impl Tool for ImageReadTool {
+    fn name(&self) -> &str {
+        "image_read"
+    }
+
+    fn description(&self) -> &str {
+        "Read an image in the workspace (screenshot, photo, scanned page): OCR its text and/or describe it. Args: {\"path\": \"image path relative to workspace (png/jpg/gif/webp/bmp/tiff)\", \"mode\": \"ocr|describe|both\" (optional, default both), \"question\": \"optional question about the image\"}"
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if path.is_empty() {
+            return Err(ToolError::InvalidArgs("Missing path".to_string()));
+        }
+        let mode = match args.get("mode").and_then(|v| v.as_str()) {
+            Some(s) => ImageMode::parse(s)
+                .ok_or_else(|| ToolError::InvalidArgs(format!("Unknown mode '{}': use ocr, describe or both", s)))?,
+            None => ImageMode::Both,
+        };
+        let question = args.get("question").and_then(|v| v.as_str());
+
+        let resolved = self.fs.resolve(path).map_err(fs_error)?;
+        let mime = media_type(&resolved)
+            .ok_or_else(|| ToolError::InvalidArgs(format!("Unsupported image type: {}", path)))?;
+        let size = std::fs::metadata(&resolved).map(|m| m.len()).unwrap_or(0);
+        if size > self.config.max_image_bytes {
+            return Err(ToolError::InvalidArgs(format!(
+                "{} is {} bytes, larger than the {} byte limit",
+                path, size, self.config.max_image_bytes
+            )));
+        }
+        let bytes = tokio::fs::read(&resolved)
+            .await
+            .map_err(|e| ToolError::Failed(format!("Read failed: {}", e)))?;
+        tracing::info!(path = %path, ?mode, "image_read tool execute");
+
+        let mut errors = Vec::new();
+        for backend in self.backends()? {
+            match backend {
+                Backend::Vision => match self.read_with_vision(mode, question, mime, &bytes).await {
+                    Ok(text) => return Ok(format!("[{} · {}]\n{}", path, self.config.vision_model, text.trim())),
+                    Err(e) => {
+                        tracing::warn!(path = %path, error = %e, "image_read vision call failed");
+                        errors.push(format!("vision: {}", e));
+                    }
+                },
+                Backend::Tesseract => match self.read_with_tesseract(&resolved).await {
+                    Ok(text) => {
+                        let text = if text.is_empty() { "[no text]" } else { text.as_str() };
+                        let note = if mode == ImageMode::Ocr {
+                            ""
+                        } else {
+                            "\n\n[OCR only: image description and questions need a vision model API key]"
+                        };
+                        return Ok(format!("[{} · tesseract]\n{}{}", path, text, note));
+                    }
+                    Err(e) => errors.push(format!("tesseract: {}", e)),
+                },
+            }
+        }
+        Err(ToolError::Failed(format!("image_read failed: {}", errors.join("; "))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_read_request_and_backend_selection() {
+        assert_eq!(media_type(Path::new("shots/a.PNG")), Some("image/png"));
+        assert_eq!(media_type(Path::new("a.jpeg")), Some("image/jpeg"));
+        assert_eq!(media_type(Path::new("a.pdf")), None);
+        assert_eq!(ImageMode::parse("caption"), Some(ImageMode::Describe));
+        assert_eq!(ImageMode::parse("x"), None);
+
+        let prompt = vision_prompt(ImageMode::Ocr, Some("What is the total?"));
+        assert!(prompt.contains("verbatim") && prompt.ends_with("What is the total?"));
+        let body = vision_request("gpt-4o-mini", &prompt, "image/png", b"abc");
+        assert_eq!(body["messages"][0]["content"][1]["image_url"]["url"], "data:image/png;base64,YWJj");
+        assert_eq!(
+            vision_content(&json!({"choices": [{"message": {"content": "hello"}}]})).as_deref(),
+            Some("hello")
+        );
+
+        let config = ImageReadSection {
+            api_key_env: "BEE_TEST_IMAGE_READ_KEY_UNSET".to_string(),
+            ..Default::default()
+        };
+        let tool = ImageReadTool::new(".", &config);
+        assert_eq!(tool.backends().unwrap(), vec![Backend::Tesseract]);
+        let vision_only = ImageReadTool::new(".", &ImageReadSection { backend: "vision".into(), ..config });
+        assert!(vision_only.backends().is_err());
+    }
+}
@@ -5,7 +5,7 @@ use std::path::Path;
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct DynamicAgent {
@@ -56,7 +56,7 @@ impl Tool for ListAgentsTool {
         })
     }
 
-    async fn execute(&self, _args: Value) -> Result<String, String> {
+    async fn execute(&self, _args: Value) -> Result<String, ToolError> {
         let agents = self.load_agents();
         if agents.is_empty() {
             return Ok("No dynamic agents yet. Use create tool to add specialized agents. Config assistants (default, etc.) are always available.".to_string());
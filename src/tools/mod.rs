@@ -1,11 +1,24 @@
+pub mod error;
 pub mod executor;
+pub mod cache;
+pub mod composite;
 pub mod filesystem;
+pub mod doc_read;
 pub mod echo;
 pub mod plugin;
+pub mod polite;
+pub mod policy;
 pub mod registry;
+pub mod remind;
+pub mod send_file;
 pub mod schema;
 pub mod shell;
 pub mod search;
+pub mod search_provider;
+pub mod http_fetch;
+pub mod image_read;
+pub mod audio_transcribe;
+pub mod calendar;
 pub mod code_read;
 pub mod code_grep;
 pub mod code_edit;
@@ -14,32 +27,64 @@ pub mod code_review;
 pub mod test_run;
 pub mod test_check;
 pub mod git_commit;
+pub mod github;
 pub mod git_diff;
 pub mod deep_search;
+pub mod delegate;
 pub mod source_validator;
 pub mod report_generator;
 pub mod knowledge_graph;
+pub mod tool_help;
+pub mod watch;
 
 #[cfg(feature = "web")]
 pub mod create;
 #[cfg(feature = "web")]
 pub mod create_group;
 #[cfg(feature = "web")]
+pub mod handoff;
+#[cfg(feature = "web")]
 pub mod list_agents;
 #[cfg(feature = "web")]
 pub mod send;
 
 #[cfg(feature = "browser")]
 pub mod browser;
+#[cfg(feature = "browser")]
+pub mod browser_pool;
+#[cfg(feature = "browser")]
+pub mod browser_profile;
+
+#[cfg(feature = "email")]
+pub mod email;
 
+#[cfg(feature = "desktop")]
+pub mod desktop;
+
+pub use error::ToolError;
 pub use executor::ToolExecutor;
 pub use echo::EchoTool;
-pub use filesystem::{CatTool, LsTool, SafeFs};
+pub use cache::ToolCache;
+pub use composite::CompositeTool;
+pub(crate) use filesystem::{ensure_unscoped, resolve_in_root};
+pub use filesystem::{scoped_root, CatTool, LsTool, SafeFs, CURRENT_WORKSPACE};
+pub use doc_read::{DocReadTool, CURRENT_LONG_TERM};
 pub use plugin::PluginTool;
-pub use registry::{Tool, ToolRegistry};
-pub use schema::tool_call_schema_json;
+pub use polite::PolitePolicy;
+pub use policy::{
+    builtin_risk, ApprovalBroker, ApprovalRequest, PolicyAction, RiskLevel, ToolPolicy, CURRENT_ASSISTANT_ID,
+};
+pub use registry::{Tool, ToolRegistry, SAFE_MODE_TOOLS};
+pub use remind::{RemindTool, CURRENT_ORIGIN};
+pub use send_file::{AttachmentQueue, OutboundFile, SendFileTool, CURRENT_ATTACHMENTS};
+pub use schema::{tool_call_schema_json, validate_args, SchemaViolation};
 pub use shell::ShellTool;
 pub use search::SearchTool;
+pub use search_provider::{providers_from_config, SearchHit, SearchProvider};
+pub use http_fetch::HttpFetchTool;
+pub use image_read::ImageReadTool;
+pub use audio_transcribe::AudioTranscribeTool;
+pub use calendar::{google_authorize, CalendarTool};
 pub use code_read::CodeReadTool;
 pub use code_grep::CodeGrepTool;
 pub use code_edit::CodeEditTool;
@@ -48,20 +93,31 @@ pub use code_review::CodeReviewTool;
 pub use test_run::TestRunTool;
 pub use test_check::TestCheckTool;
 pub use git_commit::GitCommitTool;
+pub use github::GithubTool;
 pub use git_diff::GitDiffTool;
 pub use deep_search::DeepSearchTool;
+pub use delegate::{DelegateTool, CURRENT_ALLOWED_TOOLS};
 pub use source_validator::SourceValidatorTool;
-pub use report_generator::ReportGeneratorTool;
+pub use report_generator::{set_assistant_report_languages, ReportGeneratorTool, ReportLanguage};
 pub use knowledge_graph::KnowledgeGraphBuilder;
+pub use tool_help::ToolHelpTool;
+pub use watch::WatchTool;
 
 #[cfg(feature = "web")]
 pub use create::{CreateTool, DynamicAgent};
 #[cfg(feature = "web")]
 pub use create_group::CreateGroupTool;
 #[cfg(feature = "web")]
+pub use handoff::HandoffTool;
+#[cfg(feature = "web")]
 pub use list_agents::ListAgentsTool;
 #[cfg(feature = "web")]
-pub use send::{SendTool, CURRENT_ASSISTANT_ID};
+pub use send::SendTool;
 
 #[cfg(feature = "browser")]
 pub use browser::BrowserTool;
+
+#[cfg(feature = "email")]
+pub use email::EmailTool;
+#[cfg(feature = "desktop")]
+pub use desktop::{ClipboardTool, ScreenshotTool};
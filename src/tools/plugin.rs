@@ -2,6 +2,7 @@
 //!
 //! 参数模板中 {{workspace}} 替换为沙箱根路径，{{key}} 从 LLM 传入的 args 中取 key；
 //! 执行时无 shell，直接 exec program + substituted args，带超时与审计日志。
+//! 插件程序可访问任意路径，限定于用户工作区的请求（多用户隔离）不可用。
 
 use std::path::Path;
 
@@ -10,7 +11,7 @@ use serde_json::Value;
 use tokio::process::Command;
 
 use crate::config::PluginEntry;
-use crate::tools::Tool;
+use crate::tools::{ensure_unscoped, Tool, ToolError};
 
 /// 从配置项构建的插件工具
 pub struct PluginTool {
@@ -89,7 +90,8 @@ impl Tool for PluginTool {
         &self.description
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        ensure_unscoped(self.name())?;
         let args_vec = self.substitute(&args);
         let program = self.program.clone();
         tracing::info!(tool = %self.name, program = %program, "plugin tool invoke");
@@ -103,7 +105,7 @@ impl Tool for PluginTool {
         let timeout = std::time::Duration::from_secs(self.timeout_secs);
         let output = tokio::time::timeout(timeout, child.wait_with_output())
             .await
-            .map_err(|_| format!("plugin timeout after {}s", self.timeout_secs))?
+            .map_err(|_| ToolError::Transient(format!("plugin timeout after {}s", self.timeout_secs)))?
             .map_err(|e| format!("plugin wait failed: {}", e))?;
         let stdout = String::from_utf8_lossy(&output.stdout);
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -127,7 +129,7 @@ impl Tool for PluginTool {
                     }
                 )
             };
-            return Err(err);
+            return Err(ToolError::Failed(err));
         }
         Ok(stdout.trim().to_string())
     }
@@ -0,0 +1,430 @@
+//! 工具权限与审批
+//!
+//! 每个工具有风险等级（只读 / 修改 / 破坏性，可在 [tools.policy.risk] 覆盖内置判定），
+//! 策略按等级决定放行、请求用户审批或拒绝，并可按助手覆盖。需审批时循环发出
+//! `ReactEvent::ApprovalRequired` 并阻塞等待：TUI 按 y/n，Web 调用 `POST /api/approvals/:id`，飞书点击审批卡片按钮；
+//! 超时或无人可审批时视为拒绝。
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::config::{CompositeEntry, ToolPolicySection};
+use crate::tools::{ToolError, CURRENT_ORIGIN};
+
+tokio::task_local! {
+    /// 当前执行 ReAct 的 assistant_id，由 process_message_stream 设置（send / create 工具与按助手策略使用）
+    pub static CURRENT_ASSISTANT_ID: Option<String>;
+}
+
+/// 工具风险等级（按声明顺序由低到高）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    /// 只读：不改变工作区与外部状态
+    ReadOnly,
+    /// 修改：写文件、创建 agent / 群组、发消息等
+    Mutating,
+    /// 破坏性：执行命令、提交代码等难以撤销的操作
+    Destructive,
+}
+
+/// 策略动作（按声明顺序由宽到严）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    Allow,
+    Ask,
+    Deny,
+}
+
+/// 内置工具的风险等级（按本次调用参数）；未知工具（插件等）按修改处理
+pub fn builtin_risk(tool: &str, args: &serde_json::Value) -> RiskLevel {
+    match tool {
+        // GET / HEAD 只读取，其它方法（POST 等）可能改动远端状态
+        "http_fetch" => {
+            let method = args.get("method").and_then(|v| v.as_str()).unwrap_or("GET");
+            if method.eq_ignore_ascii_case("GET") || method.eq_ignore_ascii_case("HEAD") {
+                RiskLevel::ReadOnly
+            } else {
+                RiskLevel::Mutating
+            }
+        }
+        "cat" | "ls" | "echo" | "search" | "code_read" | "code_grep" | "code_review"
+        | "git_diff" | "deep_search" | "validate_source" | "knowledge_graph" | "tool_help"
+        | "list_agents" | "test_check" | "doc_read" | "image_read" | "audio_transcribe" => RiskLevel::ReadOnly,
+        // 只把工作区文件发给当前会话的用户
+        "send_file" => RiskLevel::ReadOnly,
+        // 子 Agent 的每次工具调用各自经过策略检查
+        "delegate" => RiskLevel::ReadOnly,
+        // 交接只改变会话归属，目标助手按自己的技能与策略执行
+        "handoff" => RiskLevel::ReadOnly,
+        "shell" | "git_commit" => RiskLevel::Destructive,
+        _ => RiskLevel::Mutating,
+    }
+}
+
+/// 组合工具嵌套展开的最大深度（步骤只能引用先注册的工具，正常配置不会触及）
+const MAX_COMPOSITE_DEPTH: usize = 8;
+
+/// 工具调用策略（由 [tools.policy] 构建）
+#[derive(Debug, Clone)]
+pub struct ToolPolicy {
+    section: ToolPolicySection,
+    /// 组合工具名 -> 各步骤的 (工具名, 参数模板)，按步骤判定风险与动作
+    composites: HashMap<String, Vec<(String, serde_json::Value)>>,
+}
+
+impl From<ToolPolicySection> for ToolPolicy {
+    fn from(section: ToolPolicySection) -> Self {
+        Self {
+            section,
+            composites: HashMap::new(),
+        }
+    }
+}
+
+impl ToolPolicy {
+    /// 登记组合工具（[[tools.composites]]）：其步骤直接调用工具，策略需按步骤判定
+    pub fn with_composites(mut self, entries: &[CompositeEntry]) -> Self {
+        self.composites = entries
+            .iter()
+            .map(|e| {
+                let steps = e
+                    .steps
+                    .iter()
+                    .map(|s| (s.tool.clone(), serde_json::Value::Object(s.args.clone())))
+                    .collect();
+                (e.name.clone(), steps)
+            })
+            .collect();
+        self
+    }
+
+    /// 本次工具调用的风险等级：配置覆盖优先，组合工具取各步骤中最高的等级，否则用内置判定
+    pub fn risk(&self, tool: &str, args: &serde_json::Value) -> RiskLevel {
+        self.risk_at(tool, args, 0)
+    }
+
+    fn risk_at(&self, tool: &str, args: &serde_json::Value, depth: usize) -> RiskLevel {
+        if let Some(risk) = self.section.risk.get(tool) {
+            return *risk;
+        }
+        match self.composites.get(tool) {
+            Some(steps) if depth < MAX_COMPOSITE_DEPTH => steps
+                .iter()
+                .map(|(t, a)| self.risk_at(t, a, depth + 1))
+                .max()
+                .unwrap_or(RiskLevel::ReadOnly),
+            Some(_) => RiskLevel::Destructive,
+            None => builtin_risk(tool, args),
+        }
+    }
+
+    /// 指定助手调用工具时的动作：助手的按工具覆盖 > 助手的按等级覆盖 > 全局按等级默认；
+    /// 组合工具再与各步骤的动作取最严格者，步骤被拒绝时整个组合工具被拒绝
+    pub fn decide(&self, assistant_id: Option<&str>, tool: &str, args: &serde_json::Value) -> (RiskLevel, PolicyAction) {
+        self.decide_at(assistant_id, tool, args, 0)
+    }
+
+    fn decide_at(
+        &self,
+        assistant_id: Option<&str>,
+        tool: &str,
+        args: &serde_json::Value,
+        depth: usize,
+    ) -> (RiskLevel, PolicyAction) {
+        let (risk, action) = self.decide_own(assistant_id, tool, args, depth);
+        let Some(steps) = self.composites.get(tool) else {
+            return (risk, action);
+        };
+        if depth >= MAX_COMPOSITE_DEPTH {
+            return (risk, PolicyAction::Deny);
+        }
+        let action = steps
+            .iter()
+            .map(|(t, a)| self.decide_at(assistant_id, t, a, depth + 1).1)
+            .fold(action, PolicyAction::max);
+        (risk, action)
+    }
+
+    /// 只看工具自身（不展开组合工具步骤）的动作
+    fn decide_own(
+        &self,
+        assistant_id: Option<&str>,
+        tool: &str,
+        args: &serde_json::Value,
+        depth: usize,
+    ) -> (RiskLevel, PolicyAction) {
+        let risk = self.risk_at(tool, args, depth);
+        let assistant = assistant_id.and_then(|id| self.section.assistants.get(id));
+        if let Some(action) = assistant.and_then(|a| a.tools.get(tool)) {
+            return (risk, *action);
+        }
+        let action = match risk {
+            RiskLevel::ReadOnly => assistant.and_then(|a| a.read_only).unwrap_or(self.section.read_only),
+            RiskLevel::Mutating => assistant.and_then(|a| a.mutating).unwrap_or(self.section.mutating),
+            RiskLevel::Destructive => assistant
+                .and_then(|a| a.destructive)
+                .unwrap_or(self.section.destructive),
+        };
+        (risk, action)
+    }
+
+    /// 等待审批的超时
+    pub fn approval_timeout(&self) -> Duration {
+        Duration::from_secs(self.section.approval_timeout_secs)
+    }
+
+    /// 按策略放行、请求审批或拒绝。notify 负责把审批请求送达用户，返回 false 表示无人可审批（视为拒绝）
+    pub async fn authorize(
+        &self,
+        assistant_id: Option<&str>,
+        tool: &str,
+        args: &serde_json::Value,
+        notify: impl FnOnce(&ApprovalRequest) -> bool,
+    ) -> Result<(), ToolError> {
+        let (risk, action) = self.decide(assistant_id, tool, args);
+        match action {
+            PolicyAction::Allow => Ok(()),
+            PolicyAction::Deny => Err(ToolError::PermissionDenied(format!(
+                "tool {} ({:?}) is not allowed by policy",
+                tool, risk
+            ))),
+            PolicyAction::Ask => {
+                let broker = ApprovalBroker::global();
+                let user_id = CURRENT_ORIGIN.try_with(|o| o.as_ref().and_then(|o| o.user_id.clone())).ok().flatten();
+                let (request, rx) = broker.request(tool, args.clone(), risk, assistant_id, user_id.as_deref());
+                if !notify(&request) {
+                    broker.forget(&request.id);
+                    return Err(ToolError::PermissionDenied(format!(
+                        "tool {} requires user approval, but no interactive session is available",
+                        tool
+                    )));
+                }
+                tracing::info!(id = %request.id, tool, ?risk, "waiting for tool approval");
+                let approved = match tokio::time::timeout(self.approval_timeout(), rx).await {
+                    Ok(Ok(approved)) => approved,
+                    _ => {
+                        broker.forget(&request.id);
+                        false
+                    }
+                };
+                if approved {
+                    Ok(())
+                } else {
+                    Err(ToolError::PermissionDenied(format!(
+                        "user did not approve tool {}; choose another approach or ask the user",
+                        tool
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// 待审批的工具调用
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub tool: String,
+    pub args: serde_json::Value,
+    pub risk: RiskLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assistant_id: Option<String>,
+    /// 发起调用的会话所属用户（取自 CURRENT_ORIGIN；默认用户与 TUI 为 None），Web 只向该用户展示与放行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// 审批中转：登记待审批请求，TUI / Web 按 id 送回结果
+#[derive(Default)]
+pub struct ApprovalBroker {
+    pending: Mutex<HashMap<String, (ApprovalRequest, oneshot::Sender<bool>)>>,
+}
+
+static APPROVALS: OnceLock<ApprovalBroker> = OnceLock::new();
+
+impl ApprovalBroker {
+    /// 进程内共享的审批中转
+    pub fn global() -> &'static ApprovalBroker {
+        APPROVALS.get_or_init(ApprovalBroker::default)
+    }
+
+    /// 登记一个待审批请求，返回请求与结果接收端
+    pub fn request(
+        &self,
+        tool: &str,
+        args: serde_json::Value,
+        risk: RiskLevel,
+        assistant_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> (ApprovalRequest, oneshot::Receiver<bool>) {
+        let request = ApprovalRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            tool: tool.to_string(),
+            args,
+            risk,
+            assistant_id: assistant_id.map(str::to_string),
+            user_id: user_id.map(str::to_string),
+        };
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(request.id.clone(), (request.clone(), tx));
+        (request, rx)
+    }
+
+    /// 送回审批结果；id 不存在（已超时或已处理）时返回 false
+    pub fn resolve(&self, id: &str, approved: bool) -> bool {
+        match self.lock().remove(id) {
+            Some((_, tx)) => tx.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 只在请求属于 user_id 时送回审批结果；不属于该用户的请求与不存在的一样返回 false
+    pub fn resolve_for(&self, id: &str, user_id: Option<&str>, approved: bool) -> bool {
+        let mut pending = self.lock();
+        if pending.get(id).is_none_or(|(r, _)| r.user_id.as_deref() != user_id) {
+            return false;
+        }
+        match pending.remove(id) {
+            Some((_, tx)) => tx.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 当前待审批的请求
+    pub fn pending(&self) -> Vec<ApprovalRequest> {
+        self.lock().values().map(|(r, _)| r.clone()).collect()
+    }
+
+    /// 属于 user_id 的待审批请求
+    pub fn pending_for(&self, user_id: Option<&str>) -> Vec<ApprovalRequest> {
+        self.lock()
+            .values()
+            .filter(|(r, _)| r.user_id.as_deref() == user_id)
+            .map(|(r, _)| r.clone())
+            .collect()
+    }
+
+    fn forget(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (ApprovalRequest, oneshot::Sender<bool>)>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AssistantPolicySection;
+
+    #[test]
+    fn test_tool_policy_decide_and_approval() {
+        let mut section = ToolPolicySection {
+            enabled: true,
+            ..Default::default()
+        };
+        section.risk.insert("report".to_string(), RiskLevel::ReadOnly);
+        section.assistants.insert(
+            "coder".to_string(),
+            AssistantPolicySection {
+                mutating: Some(PolicyAction::Allow),
+                tools: HashMap::from([("git_commit".to_string(), PolicyAction::Deny)]),
+                ..Default::default()
+            },
+        );
+        let policy = ToolPolicy::from(section);
+        let none = serde_json::json!({});
+        assert_eq!(policy.decide(None, "cat", &none), (RiskLevel::ReadOnly, PolicyAction::Allow));
+        assert_eq!(policy.decide(None, "report", &none), (RiskLevel::ReadOnly, PolicyAction::Allow));
+        assert_eq!(policy.decide(None, "code_write", &none), (RiskLevel::Mutating, PolicyAction::Ask));
+        assert_eq!(policy.decide(Some("coder"), "code_write", &none).1, PolicyAction::Allow);
+        assert_eq!(policy.decide(Some("coder"), "shell", &none).1, PolicyAction::Ask);
+        assert_eq!(policy.decide(Some("coder"), "git_commit", &none).1, PolicyAction::Deny);
+        // http_fetch 按方法判定
+        assert_eq!(policy.decide(None, "http_fetch", &none), (RiskLevel::ReadOnly, PolicyAction::Allow));
+        let post = serde_json::json!({"url": "https://a.com", "method": "POST"});
+        assert_eq!(policy.decide(None, "http_fetch", &post), (RiskLevel::Mutating, PolicyAction::Ask));
+        assert_eq!(builtin_risk("http_fetch", &serde_json::json!({"method": "head"})), RiskLevel::ReadOnly);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let args = serde_json::json!({"path": "a.txt"});
+        // 无人可审批时拒绝
+        let denied = rt.block_on(policy.authorize(None, "code_write", &args, |_| false));
+        assert!(matches!(denied, Err(ToolError::PermissionDenied(_))));
+
+        // 审批通过 / 拒绝
+        for approved in [true, false] {
+            let result = rt.block_on(policy.authorize(None, "code_write", &args, |req| {
+                let id = req.id.clone();
+                assert!(ApprovalBroker::global().pending().iter().any(|r| r.id == id));
+                std::thread::spawn(move || assert!(ApprovalBroker::global().resolve(&id, approved)));
+                true
+            }));
+            assert_eq!(result.is_ok(), approved);
+        }
+        assert!(!ApprovalBroker::global().resolve("missing", true));
+
+        // 只有发起会话所属的用户能看到并处理审批
+        let broker = ApprovalBroker::default();
+        let (request, _rx) = broker.request("shell", serde_json::json!({}), RiskLevel::Destructive, None, Some("key.bob"));
+        assert!(broker.pending_for(None).is_empty());
+        assert_eq!(broker.pending_for(Some("key.bob")).len(), 1);
+        assert!(!broker.resolve_for(&request.id, Some("key.eve"), true));
+        assert!(!broker.resolve_for(&request.id, None, true));
+        assert!(broker.resolve_for(&request.id, Some("key.bob"), true));
+    }
+
+    #[test]
+    fn test_composite_policy_follows_steps() {
+        let mut section = ToolPolicySection {
+            enabled: true,
+            ..Default::default()
+        };
+        section.assistants.insert(
+            "coder".to_string(),
+            AssistantPolicySection {
+                destructive: Some(PolicyAction::Allow),
+                tools: HashMap::from([("shell".to_string(), PolicyAction::Deny)]),
+                ..Default::default()
+            },
+        );
+        let entries: Vec<CompositeEntry> = [
+            r#"
+            name = "grep_read"
+            description = "read only"
+            steps = [{ tool = "code_grep" }, { tool = "code_read" }]
+            "#,
+            r#"
+            name = "build"
+            description = "runs shell"
+            steps = [{ tool = "cat" }, { tool = "shell", args = { command = "cargo build" } }]
+            "#,
+            r#"
+            name = "nested"
+            description = "wraps build"
+            steps = [{ tool = "build" }]
+            "#,
+        ]
+        .iter()
+        .map(|t| toml::from_str(t).unwrap())
+        .collect();
+        let policy = ToolPolicy::from(section).with_composites(&entries);
+        let none = serde_json::json!({});
+
+        // 风险取步骤中最高的等级（含嵌套组合工具）
+        assert_eq!(policy.decide(None, "grep_read", &none), (RiskLevel::ReadOnly, PolicyAction::Allow));
+        assert_eq!(policy.decide(None, "build", &none), (RiskLevel::Destructive, PolicyAction::Ask));
+        assert_eq!(policy.risk("nested", &none), RiskLevel::Destructive);
+        // 助手禁用了某一步时，整个组合工具被拒绝
+        assert_eq!(policy.decide(Some("coder"), "build", &none).1, PolicyAction::Deny);
+        assert_eq!(policy.decide(Some("coder"), "nested", &none).1, PolicyAction::Deny);
+        assert_eq!(policy.decide(Some("coder"), "git_commit", &none).1, PolicyAction::Allow);
+    }
+}
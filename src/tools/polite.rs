@@ -0,0 +1,318 @@
+//! 礼貌抓取策略：按域名限速、遵守 robots.txt、使用可识别的 User-Agent
+//!
+//! Search / Browser 等联网工具共享同一个 PolitePolicy：同一域名两次请求至少间隔 min_interval_ms，
+//! 首次访问某域名时拉取并缓存其 robots.txt，命中 Disallow 的路径返回 PermissionDenied。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use tokio::sync::Mutex;
+
+use crate::config::PoliteSection;
+use crate::tools::ToolError;
+
+/// robots.txt 中适用于本 Agent 的规则（Allow / Disallow 前缀）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsRules {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+}
+
+impl RobotsRules {
+    /// 解析 robots.txt：优先使用 User-agent 与 agent 产品名（`/` 之前的部分）相同的分组（不区分大小写），
+    /// 否则使用 `*` 分组
+    pub fn parse(body: &str, agent: &str) -> Self {
+        let agent = agent.split(['/', ' ']).next().unwrap_or("").trim().to_lowercase();
+        let mut specific: Option<RobotsRules> = None;
+        let mut wildcard: Option<RobotsRules> = None;
+
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut group = RobotsRules::default();
+        let mut in_rules = false;
+
+        let mut finish = |agents: &[String], rules: &RobotsRules| {
+            for a in agents {
+                if a == "*" {
+                    wildcard.get_or_insert_with(RobotsRules::default).merge(rules);
+                } else if !agent.is_empty() && *a == agent {
+                    specific.get_or_insert_with(RobotsRules::default).merge(rules);
+                }
+            }
+        };
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        finish(&group_agents, &group);
+                        group_agents.clear();
+                        group = RobotsRules::default();
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // 空 Disallow 表示全部允许
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if key == "allow" {
+                        group.allow.push(value.to_string());
+                    } else {
+                        group.disallow.push(value.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        finish(&group_agents, &group);
+
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    fn merge(&mut self, other: &RobotsRules) {
+        self.allow.extend(other.allow.iter().cloned());
+        self.disallow.extend(other.disallow.iter().cloned());
+    }
+
+    /// 路径是否允许访问：最长匹配优先，长度相同时 Allow 优先
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let longest = |rules: &[String]| {
+            rules
+                .iter()
+                .filter(|p| robots_match(p, path))
+                .map(|p| p.len())
+                .max()
+        };
+        match (longest(&self.allow), longest(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(d)) => a >= d,
+        }
+    }
+}
+
+/// robots 路径模式匹配：前缀匹配，支持 `*` 通配与结尾 `$` 锚定
+fn robots_match(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('$') {
+        Some(p) => glob_match(p.as_bytes(), path.as_bytes()),
+        None => glob_match(format!("{}*", pattern).as_bytes(), path.as_bytes()),
+    }
+}
+
+/// `*` 通配匹配：双指针，失配时只回溯到最近的 `*`，最坏 O(模式长度 × 路径长度)；robots.txt 来自远端，不能用指数级的递归匹配
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // 最近一个 `*` 的位置及其当前吞下到的 s 位置
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == s[i] {
+            p += 1;
+            i += 1;
+        } else if let Some((sp, si)) = star {
+            p = sp + 1;
+            i = si + 1;
+            star = Some((sp, si + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// 拆分 URL 为 (scheme://host[:port], path?query)
+fn split_url(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let scheme_end = url.find("://")?;
+    let after = &url[scheme_end + 3..];
+    let (authority, path) = match after.find(['/', '?', '#']) {
+        Some(i) => (&after[..i], &after[i..]),
+        None => (after, "/"),
+    };
+    if authority.is_empty() {
+        return None;
+    }
+    let path = path.split('#').next().unwrap_or("/");
+    let path = if path.starts_with('/') { path.to_string() } else { format!("/{}", path) };
+    Some((format!("{}{}", &url[..scheme_end + 3], authority.to_lowercase()), path))
+}
+
+/// 礼貌抓取策略（可在多个工具间共享）
+pub struct PolitePolicy {
+    config: PoliteSection,
+    client: Client,
+    /// 域名 -> 下次允许请求的时间点
+    next_slot: Mutex<HashMap<String, Instant>>,
+    /// 域名 -> 已缓存的 robots 规则
+    robots: Mutex<HashMap<String, Arc<RobotsRules>>>,
+}
+
+impl PolitePolicy {
+    pub fn new(config: PoliteSection) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.robots_timeout_secs))
+            .user_agent(config.user_agent.clone())
+            .build()
+            .unwrap_or_default();
+        Self {
+            config,
+            client,
+            next_slot: Mutex::new(HashMap::new()),
+            robots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 请求时使用的 User-Agent
+    pub fn user_agent(&self) -> &str {
+        &self.config.user_agent
+    }
+
+    /// 请求前调用：检查 robots.txt 并按域名排队等待，返回前保证与上次请求间隔足够
+    pub async fn acquire(&self, url: &str) -> Result<(), ToolError> {
+        let (origin, path) = split_url(url)
+            .ok_or_else(|| ToolError::InvalidArgs("Invalid or missing URL".to_string()))?;
+
+        if self.config.respect_robots_txt {
+            let rules = self.robots_for(&origin).await;
+            if !rules.is_allowed(&path) {
+                return Err(ToolError::PermissionDenied(format!(
+                    "Disallowed by robots.txt: {}",
+                    url
+                )));
+            }
+        }
+
+        let wait = self.reserve_slot(&origin).await;
+        if !wait.is_zero() {
+            tracing::debug!(origin = %origin, wait_ms = wait.as_millis() as u64, "polite pacing");
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    /// 预占该域名的下一个请求时间片，返回需等待的时长（并发请求依次排队）
+    async fn reserve_slot(&self, origin: &str) -> Duration {
+        let interval = Duration::from_millis(self.config.min_interval_ms);
+        let now = Instant::now();
+        let mut slots = self.next_slot.lock().await;
+        let start = slots.get(origin).copied().filter(|t| *t > now).unwrap_or(now);
+        slots.insert(origin.to_string(), start + interval);
+        start - now
+    }
+
+    /// 取（必要时拉取）域名的 robots 规则；拉取失败或不存在时视为全部允许
+    async fn robots_for(&self, origin: &str) -> Arc<RobotsRules> {
+        if let Some(rules) = self.robots.lock().await.get(origin) {
+            return Arc::clone(rules);
+        }
+        let robots_url = format!("{}/robots.txt", origin);
+        let body = match self.client.get(&robots_url).send().await {
+            Ok(resp) if resp.status().is_success() => resp.text().await.unwrap_or_default(),
+            Ok(_) => String::new(),
+            Err(e) => {
+                tracing::debug!(url = %robots_url, error = %e, "robots.txt fetch failed, allowing");
+                String::new()
+            }
+        };
+        let agent = self.config.user_agent.split('/').next().unwrap_or("");
+        let rules = Arc::new(RobotsRules::parse(&body, agent));
+        self.robots
+            .lock()
+            .await
+            .insert(origin.to_string(), Arc::clone(&rules));
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_rules_parse_and_match() {
+        let body = "\
+User-agent: *
+Disallow: /private/
+Allow: /private/open
+Disallow: /*.pdf$
+
+User-agent: bee-agent
+Disallow: /bee-only/
+";
+        let wildcard = RobotsRules::parse(body, "other-bot");
+        assert!(!wildcard.is_allowed("/private/x"));
+        assert!(wildcard.is_allowed("/private/open/page"));
+        assert!(!wildcard.is_allowed("/docs/a.pdf"));
+        assert!(wildcard.is_allowed("/docs/a.pdf.html"));
+        assert!(wildcard.is_allowed("/bee-only/"));
+
+        let specific = RobotsRules::parse(body, "bee-agent");
+        assert!(!specific.is_allowed("/bee-only/x"));
+        assert!(specific.is_allowed("/private/x"));
+
+        assert!(RobotsRules::parse("", "bee-agent").is_allowed("/anything"));
+
+        // 按产品名整体匹配（不区分大小写），不是子串匹配
+        let body = "User-agent: b\nDisallow: /\n\nUser-agent: BEE-Agent\nDisallow: /x/\n";
+        let rules = RobotsRules::parse(body, "bee-agent/0.1 (+https://example.com)");
+        assert!(rules.is_allowed("/y"));
+        assert!(!rules.is_allowed("/x/1"));
+    }
+
+    #[test]
+    fn test_glob_match_many_wildcards() {
+        assert!(glob_match(b"/a*b*c", b"/a-b-c"));
+        assert!(glob_match(b"*", b""));
+        assert!(!glob_match(b"/a*b", b"/a-c"));
+        assert!(robots_match("/*.pdf$", "/docs/a.pdf"));
+        assert!(!robots_match("/*.pdf$", "/docs/a.pdf.html"));
+        // 大量 `*` 的恶意模式不会卡住
+        let pattern = format!("/{}b", "*a".repeat(30));
+        let start = Instant::now();
+        assert!(!robots_match(&pattern, &format!("/{}", "a".repeat(5000))));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_split_url() {
+        assert_eq!(
+            split_url("https://Docs.rs/serde?x=1#top"),
+            Some(("https://docs.rs".to_string(), "/serde?x=1".to_string()))
+        );
+        assert_eq!(
+            split_url("http://example.com:8080"),
+            Some(("http://example.com:8080".to_string(), "/".to_string()))
+        );
+        assert_eq!(split_url("not a url"), None);
+    }
+
+    #[test]
+    fn test_polite_policy_paces_same_domain() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let policy = PolitePolicy::new(PoliteSection {
+                respect_robots_txt: false,
+                min_interval_ms: 50,
+                ..PoliteSection::default()
+            });
+            let start = Instant::now();
+            policy.acquire("https://a.example/1").await.unwrap();
+            policy.acquire("https://b.example/1").await.unwrap();
+            assert!(start.elapsed() < Duration::from_millis(50));
+            policy.acquire("https://a.example/2").await.unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(50));
+        });
+    }
+}
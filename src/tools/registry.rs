@@ -2,6 +2,7 @@
 //!
 //! 所有工具实现 Tool trait（name / description / execute），由 ToolRegistry 按名注册与查找，
 //! ToolExecutor 在调用时加超时并统一转 AgentError。
+//! 注入 prompt 时默认使用 short_description 节省 token，完整说明由 tool_help 工具按需返回。
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -9,6 +10,11 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use serde_json::Value;
 
+use crate::tools::ToolError;
+
+/// 安全模式下允许的只读工具（tool_help 只返回这些工具的说明）
+pub const SAFE_MODE_TOOLS: &[&str] = &["cat", "ls", "search", "code_read", "echo", "tool_help"];
+
 /// 工具 trait：名称、描述（供 LLM 理解）、参数 schema、异步执行（args 为 JSON）
 /// 解决问题 6.2：添加 parameters_schema 方法
 #[async_trait]
@@ -19,6 +25,20 @@ pub trait Tool: Send + Sync {
     /// 工具描述（供 LLM 理解功能）
     fn description(&self) -> &str;
 
+    /// 简短描述（注入每次 system prompt，默认取 description 的首个非空行）
+    fn short_description(&self) -> &str {
+        self.description()
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or("")
+    }
+
+    /// 完整描述（由 tool_help 按需返回，默认与 description 相同）
+    fn long_description(&self) -> &str {
+        self.description()
+    }
+
     /// 参数 JSON Schema（供 LLM 生成正确的参数格式）
     /// 默认返回空对象，表示无参数或参数格式不限
     fn parameters_schema(&self) -> Value {
@@ -30,7 +50,7 @@ pub trait Tool: Send + Sync {
     }
 
     /// 执行工具
-    async fn execute(&self, args: Value) -> Result<String, String>;
+    async fn execute(&self, args: Value) -> Result<String, ToolError>;
 }
 
 /// 工具注册表：按名称存储 Arc<dyn Tool>，支持 register / get / execute / tool_names
@@ -53,23 +73,63 @@ impl ToolRegistry {
         self.tools.get(name).cloned()
     }
 
-    pub async fn execute(&self, name: &str, args: Value) -> Result<String, String> {
-        let tool = self.tools.get(name).ok_or_else(|| format!("Unknown tool: {name}"))?;
+    pub async fn execute(&self, name: &str, args: Value) -> Result<String, ToolError> {
+        let tool = self
+            .tools
+            .get(name)
+            .ok_or_else(|| ToolError::NotFound(format!("Unknown tool: {name}")))?;
         tool.execute(args).await
     }
 
+    /// 仅保留指定名称的工具（安全模式等），返回被移除的工具名
+    pub fn retain_only(&mut self, names: &[&str]) -> Vec<String> {
+        let mut removed: Vec<String> = self
+            .tools
+            .keys()
+            .filter(|n| !names.contains(&n.as_str()))
+            .cloned()
+            .collect();
+        removed.sort();
+        for name in &removed {
+            self.tools.remove(name);
+        }
+        removed
+    }
+
+    /// 复制指定名称的工具到新注册表（共享同一实例，未注册的名称忽略）
+    pub fn subset<S: AsRef<str>>(&self, names: &[S]) -> ToolRegistry {
+        let tools = names
+            .iter()
+            .filter_map(|n| self.tools.get_key_value(n.as_ref()))
+            .map(|(name, tool)| (name.clone(), Arc::clone(tool)))
+            .collect();
+        ToolRegistry { tools }
+    }
+
     pub fn tool_names(&self) -> Vec<String> {
         self.tools.keys().cloned().collect()
     }
 
-    /// 返回 (name, description) 列表，用于生成 prompt 中的 Available tools 段落
+    /// 返回 (name, short_description) 列表，用于生成 prompt 中的 Available tools 段落
     pub fn tool_descriptions(&self) -> Vec<(String, String)> {
         self.tools
             .iter()
-            .map(|(name, tool)| (name.clone(), tool.description().to_string()))
+            .map(|(name, tool)| (name.clone(), tool.short_description().to_string()))
             .collect()
     }
 
+    /// 单个工具的完整说明（long_description + 参数 schema），供 tool_help 使用
+    pub fn tool_help(&self, name: &str) -> Option<String> {
+        let tool = self.tools.get(name)?;
+        let schema = serde_json::to_string_pretty(&tool.parameters_schema()).unwrap_or_default();
+        Some(format!(
+            "{}\n\n{}\n\nParameters:\n{}",
+            name,
+            tool.long_description(),
+            schema
+        ))
+    }
+
     /// 动态生成工具 schema JSON（解决问题 6.1：Schema 与实际注册工具匹配）
     /// 包含参数 schema（解决问题 6.2）
     pub fn to_schema_json(&self) -> String {
@@ -79,7 +139,7 @@ impl ToolRegistry {
             .map(|(name, tool)| {
                 serde_json::json!({
                     "name": name,
-                    "description": tool.description(),
+                    "description": tool.short_description(),
                     "parameters": tool.parameters_schema()
                 })
             })
@@ -87,3 +147,52 @@ impl ToolRegistry {
         serde_json::to_string_pretty(&tools).unwrap_or_else(|_| "[]".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct VerboseTool;
+
+    #[async_trait]
+    impl Tool for VerboseTool {
+        fn name(&self) -> &str {
+            "verbose"
+        }
+
+        fn description(&self) -> &str {
+            "\nDo verbose things.\n\nActions:\n- a: first\n- b: second"
+        }
+
+        async fn execute(&self, _args: Value) -> Result<String, ToolError> {
+            Ok(String::new())
+        }
+    }
+
+    #[test]
+    fn test_short_description_first_line() {
+        assert_eq!(VerboseTool.short_description(), "Do verbose things.");
+        assert!(VerboseTool.long_description().contains("Actions:"));
+    }
+
+    #[test]
+    fn test_schema_uses_short_description() {
+        let mut registry = ToolRegistry::new();
+        registry.register(VerboseTool);
+        let schema = registry.to_schema_json();
+        assert!(schema.contains("Do verbose things."));
+        assert!(!schema.contains("Actions:"));
+        let help = registry.tool_help("verbose").unwrap();
+        assert!(help.contains("- b: second"));
+        assert!(registry.tool_help("missing").is_none());
+    }
+
+    #[test]
+    fn test_retain_only_safe_mode_tools() {
+        let mut registry = ToolRegistry::new();
+        registry.register(VerboseTool);
+        registry.register(crate::tools::EchoTool);
+        assert_eq!(registry.retain_only(SAFE_MODE_TOOLS), vec!["verbose".to_string()]);
+        assert_eq!(registry.tool_names(), vec!["echo".to_string()]);
+    }
+}
@@ -0,0 +1,266 @@
+//! 定时提醒工具
+//!
+//! remind 支持 create / list / cancel：按时间（at）、相对分钟（in_minutes）或 5 段 cron 表达式创建提醒，
+//! 存入 workspace.db 后由各接入端的提醒调度器（TaskScheduler::spawn_reminders）到期推送回当前会话。
+//! 当前会话由接入端通过 CURRENT_ORIGIN 设置；TUI 等无法推送的场景下不能创建提醒。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, Local, TimeZone, Utc};
+use serde_json::Value;
+
+use crate::core::{CronSchedule, Reminder, ReminderOrigin, ReminderStore};
+use crate::tools::calendar::EventTime;
+use crate::tools::{Tool, ToolError, CURRENT_ASSISTANT_ID};
+
+tokio::task_local! {
+    /// 当前消息的来源会话，由 bee-web / bee-whatsapp / bee-lark 处理消息时设置（remind 工具据此回推）
+    pub static CURRENT_ORIGIN: Option<ReminderOrigin>;
+}
+
+/// 只给日期时的默认提醒时刻
+const DEFAULT_HOUR: u32 = 9;
+
+/// 当前消息的来源会话（assistant_id 缺省时取 CURRENT_ASSISTANT_ID）
+pub(crate) fn current_origin() -> Option<ReminderOrigin> {
+    let mut origin = CURRENT_ORIGIN.try_with(|o| o.clone()).ok().flatten()?;
+    if origin.assistant_id.is_none() {
+        origin.assistant_id = CURRENT_ASSISTANT_ID.try_with(|a| a.clone()).ok().flatten();
+    }
+    Some(origin)
+}
+
+fn store_err(e: impl std::fmt::Display) -> ToolError {
+    ToolError::Failed(format!("Reminder store error: {}", e))
+}
+
+fn reminder_line(r: &Reminder) -> String {
+    let schedule = match &r.cron {
+        Some(cron) => format!(
+            "cron \"{}\", next {}",
+            cron,
+            r.next_fire_local().format("%Y-%m-%d %H:%M")
+        ),
+        None => r.next_fire_local().format("%Y-%m-%d %H:%M").to_string(),
+    };
+    format!("- [{}] {} — {}", r.id, schedule, r.text)
+}
+
+/// remind 工具
+pub struct RemindTool {
+    store: Arc<ReminderStore>,
+    max_active: usize,
+}
+
+impl RemindTool {
+    pub fn new(store: Arc<ReminderStore>, max_active: usize) -> Self {
+        Self { store, max_active }
+    }
+
+    /// 解析触发方式：恰好一个 at / in_minutes / cron，返回 (下次触发 Unix 秒, cron)
+    fn schedule(args: &Value) -> Result<(i64, Option<String>), ToolError> {
+        let at = args.get("at").and_then(|v| v.as_str()).filter(|s| !s.trim().is_empty());
+        let in_minutes = args.get("in_minutes").and_then(|v| v.as_i64());
+        let cron = args
+            .get("cron")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty());
+        let now = Utc::now();
+        match (at, in_minutes, cron) {
+            (Some(at), None, None) => {
+                let when = match EventTime::parse(at) {
+                    Some(EventTime::At(t)) => t,
+                    Some(EventTime::AllDay(d)) => Local
+                        .from_local_datetime(&d.and_hms_opt(DEFAULT_HOUR, 0, 0).expect("valid hour"))
+                        .earliest()
+                        .ok_or_else(|| ToolError::InvalidArgs(format!("Invalid local time '{}'", at)))?
+                        .with_timezone(&Utc),
+                    None => {
+                        return Err(ToolError::InvalidArgs(format!(
+                            "Cannot parse at '{}': use RFC 3339 or local YYYY-MM-DDTHH:MM",
+                            at
+                        )))
+                    }
+                };
+                if when <= now {
+                    return Err(ToolError::InvalidArgs(format!("at '{}' is in the past", at)));
+                }
+                Ok((when.timestamp(), None))
+            }
+            (None, Some(minutes), None) if minutes > 0 => Ok(((now + Duration::minutes(minutes)).timestamp(), None)),
+            (None, None, Some(cron)) => {
+                let schedule = CronSchedule::parse(cron)
+                    .map_err(|e| ToolError::InvalidArgs(format!("Invalid cron '{}': {}", cron, e)))?;
+                let next = schedule
+                    .next_after(now.with_timezone(&Local))
+                    .ok_or_else(|| ToolError::InvalidArgs(format!("Cron '{}' never fires", cron)))?;
+                Ok((next.timestamp(), Some(cron.trim().to_string())))
+            }
+            _ => Err(ToolError::InvalidArgs(
+                "Give exactly one of at, in_minutes (> 0) or cron".to_string(),
+            )),
+        }
+    }
+
+    fn create(&self, args: &Value) -> Result<String, ToolError> {
+        let text = args
+            .get("text")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| ToolError::missing("text"))?;
+        let origin = current_origin().ok_or_else(|| {
+            ToolError::Failed("Reminders can only be created from a chat channel (Web, WhatsApp, Lark)".to_string())
+        })?;
+        let (next_fire, cron) = Self::schedule(args)?;
+        if self.store.list(Some(&origin)).map_err(store_err)?.len() >= self.max_active {
+            return Err(ToolError::Failed(format!(
+                "This conversation already has {} reminders; cancel some first",
+                self.max_active
+            )));
+        }
+        let id: String = uuid::Uuid::new_v4().simple().to_string().chars().take(8).collect();
+        let reminder = Reminder {
+            id,
+            text: text.to_string(),
+            origin,
+            cron,
+            next_fire,
+            created_at: Utc::now().timestamp(),
+            failures: 0,
+        };
+        self.store.upsert(&reminder).map_err(store_err)?;
+        Ok(format!("✓ Reminder set:\n{}", reminder_line(&reminder)))
+    }
+
+    /// 当前会话的提醒；无来源会话（如 TUI）时列出全部
+    fn list(&self) -> Result<String, ToolError> {
+        let origin = current_origin();
+        let reminders = self.store.list(origin.as_ref()).map_err(store_err)?;
+        if reminders.is_empty() {
+            return Ok("No reminders".to_string());
+        }
+        let lines: Vec<String> = reminders.iter().map(reminder_line).collect();
+        Ok(format!("{} reminder(s):\n{}", reminders.len(), lines.join("\n")))
+    }
+
+    fn cancel(&self, args: &Value) -> Result<String, ToolError> {
+        let id = args
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("id"))?;
+        // 只能取消当前会话的提醒
+        let owned = self.store.list(current_origin().as_ref()).map_err(store_err)?;
+        if !owned.iter().any(|r| r.id == id) || !self.store.remove(id).map_err(store_err)? {
+            return Err(ToolError::Failed(format!(
+                "No reminder with id '{}' in this conversation",
+                id
+            )));
+        }
+        Ok(format!("✓ Cancelled reminder {}", id))
+    }
+}
+
+#[async_trait]
+impl Tool for RemindTool {
+    fn name(&self) -> &str {
+        "remind"
+    }
+
+    fn description(&self) -> &str {
+        r#"定时提醒：到时把提醒推送回当前会话（Web / WhatsApp / 飞书），进程重启后仍有效。
+
+参数:
+- action: create | list | cancel（必需）
+- create: text（提醒内容），以及三选一：at（"2026-10-18T15:00" 本地时间或 RFC 3339；只给日期为当天 9 点）、
+  in_minutes（几分钟后）、cron（5 段 cron，本地时区，如 "0 9 * * 1-5" 工作日 9 点）
+- cancel: id（list 返回的 id）
+
+示例:
+{"action": "create", "text": "给妈妈打电话", "at": "2026-10-18T19:30"}
+{"action": "create", "text": "喝水", "cron": "0 10-18/2 * * *"}"#
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let action = args
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("action"))?;
+        match action {
+            "create" => self.create(&args),
+            "list" => self.list(),
+            "cancel" => self.cancel(&args),
+            other => Err(ToolError::InvalidArgs(format!(
+                "Unknown action '{}': use create, list or cancel",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remind_scoped_to_origin() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = RemindTool::new(Arc::new(ReminderStore::open(dir.path()).unwrap()), 2);
+        let err = tool
+            .execute(json!({"action": "create", "text": "x", "in_minutes": 5}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("chat channel"));
+
+        let origin = ReminderOrigin {
+            channel: "web".into(),
+            target: "s1".into(),
+            assistant_id: None,
+            user_id: None,
+        };
+        let created = CURRENT_ORIGIN
+            .scope(Some(origin.clone()), async {
+                assert!(tool
+                    .execute(json!({"action": "create", "text": "x", "at": "2000-01-01T09:00"}))
+                    .await
+                    .is_err());
+                assert!(tool
+                    .execute(json!({"action": "create", "text": "x", "in_minutes": 5, "cron": "@daily"}))
+                    .await
+                    .is_err());
+                tool.execute(json!({"action": "create", "text": "weekly sync", "cron": "30 10 * * 1"}))
+                    .await
+                    .unwrap()
+            })
+            .await;
+        assert!(created.contains("cron \"30 10 * * 1\"") && created.contains("weekly sync"));
+        let id = created
+            .split('[')
+            .nth(1)
+            .unwrap()
+            .split(']')
+            .next()
+            .unwrap()
+            .to_string();
+
+        // 其它会话看不到也取消不了
+        let other = ReminderOrigin {
+            target: "s2".into(),
+            ..origin.clone()
+        };
+        CURRENT_ORIGIN
+            .scope(Some(other), async {
+                assert_eq!(tool.execute(json!({"action": "list"})).await.unwrap(), "No reminders");
+                assert!(tool.execute(json!({"action": "cancel", "id": id})).await.is_err());
+            })
+            .await;
+        CURRENT_ORIGIN
+            .scope(Some(origin), async {
+                assert!(tool.execute(json!({"action": "cancel", "id": id})).await.is_ok());
+            })
+            .await;
+    }
+}
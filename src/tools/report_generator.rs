@@ -1,57 +1,160 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError, CURRENT_ASSISTANT_ID};
 use crate::llm::LlmClient;
 use crate::memory::Message;
 
-pub struct ReportGeneratorTool {
-    llm: Arc<dyn LlmClient>,
+/// 报告语言：auto 由模型按材料决定；bilingual 为中英双语
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ReportLanguage {
+    #[default]
+    Auto,
+    Zh,
+    En,
+    Bilingual,
 }
 
-impl ReportGeneratorTool {
-    pub fn new(llm: Arc<dyn LlmClient>) -> Self {
-        Self { llm }
+impl ReportLanguage {
+    /// 解析语言名，接受常见别名（zh-CN / chinese / 中文、english / 英文、双语 / zh-en 等）
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => Some(Self::Auto),
+            "zh" | "zh-cn" | "zh_cn" | "cn" | "chinese" | "中文" => Some(Self::Zh),
+            "en" | "en-us" | "english" | "英文" => Some(Self::En),
+            "bilingual" | "zh-en" | "zh+en" | "en-zh" | "双语" | "中英" | "中英双语" => Some(Self::Bilingual),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Zh => "zh",
+            Self::En => "en",
+            Self::Bilingual => "bilingual",
+        }
     }
 }
 
-#[async_trait]
-impl Tool for ReportGeneratorTool {
-    fn name(&self) -> &str {
-        "generate_report"
+impl TryFrom<String> for ReportLanguage {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s).ok_or_else(|| format!("unknown report language: {}", s))
     }
+}
 
-    fn description(&self) -> &str {
-        "Generate a structured research report from research findings. Supports Markdown and JSON formats. Args: {\"topic\": \"research topic\", \"findings\": \"research data\", \"format\": \"markdown|json\" (optional)}"
+impl From<ReportLanguage> for String {
+    fn from(lang: ReportLanguage) -> Self {
+        lang.as_str().to_string()
     }
+}
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
-        let topic = args
-            .get("topic")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .trim();
+/// 双语报告的排版：逐节中英对照，或先完整中文再完整英文
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BilingualLayout {
+    #[default]
+    Interleaved,
+    Separate,
+}
 
-        let findings = args
-            .get("findings")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .trim();
+impl BilingualLayout {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "interleaved" | "parallel" | "对照" => Some(Self::Interleaved),
+            "separate" | "two" | "两份" => Some(Self::Separate),
+            _ => None,
+        }
+    }
+}
 
-        let format = args
-            .get("format")
-            .and_then(|v| v.as_str())
-            .unwrap_or("markdown");
+/// 按助手的默认报告语言（assistants.toml / skills 中的 report_language），由 bee-web 加载助手后设置
+static ASSISTANT_REPORT_LANGUAGES: OnceLock<RwLock<HashMap<String, ReportLanguage>>> = OnceLock::new();
 
-        if topic.is_empty() || findings.is_empty() {
-            return Err("Missing topic or findings".to_string());
+/// 设置各助手的默认报告语言（整体替换）
+pub fn set_assistant_report_languages(languages: HashMap<String, ReportLanguage>) {
+    let lock = ASSISTANT_REPORT_LANGUAGES.get_or_init(Default::default);
+    *lock.write().unwrap_or_else(|e| e.into_inner()) = languages;
+}
+
+fn assistant_report_language(assistant_id: &str) -> Option<ReportLanguage> {
+    ASSISTANT_REPORT_LANGUAGES
+        .get()?
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(assistant_id)
+        .copied()
+}
+
+pub struct ReportGeneratorTool {
+    llm: Arc<dyn LlmClient>,
+    /// 未指定 language 且当前助手未配置时使用（[tools] report_language）
+    default_language: ReportLanguage,
+}
+
+impl ReportGeneratorTool {
+    pub fn new(llm: Arc<dyn LlmClient>) -> Self {
+        Self {
+            llm,
+            default_language: ReportLanguage::Auto,
+        }
+    }
+
+    pub fn with_default_language(mut self, language: ReportLanguage) -> Self {
+        self.default_language = language;
+        self
+    }
+
+    /// 生效语言：参数 > 当前助手配置 > 全局默认
+    fn resolve_language(&self, arg: Option<&str>) -> Result<ReportLanguage, ToolError> {
+        if let Some(s) = arg {
+            return ReportLanguage::parse(s).ok_or_else(|| {
+                ToolError::InvalidArgs(format!("Unknown language '{}': use zh, en, bilingual or auto", s))
+            });
         }
+        let assistant = CURRENT_ASSISTANT_ID.try_with(|a| a.clone()).ok().flatten();
+        Ok(assistant
+            .and_then(|id| assistant_report_language(&id))
+            .unwrap_or(self.default_language))
+    }
+}
 
-        let prompt = if format == "json" {
-            format!(
-                r#"Generate a structured research report in JSON format.
+/// 语言要求，附加在报告 prompt 末尾
+fn language_instruction(format: &str, language: ReportLanguage, layout: BilingualLayout) -> &'static str {
+    let json = format == "json";
+    match (language, layout) {
+        (ReportLanguage::Auto, _) => "",
+        (ReportLanguage::Zh, _) => {
+            "\n\nLanguage: write the whole report in Simplified Chinese (简体中文). Translate English findings faithfully; keep proper nouns, product names and source titles in their original form."
+        }
+        (ReportLanguage::En, _) => {
+            "\n\nLanguage: write the whole report in English. Translate non-English findings faithfully; keep proper nouns and source titles in their original form."
+        }
+        (ReportLanguage::Bilingual, BilingualLayout::Interleaved) if json => {
+            "\n\nLanguage: bilingual. Every text value (including list items) must be an object {\"zh\": \"Simplified Chinese\", \"en\": \"English\"} with the same meaning in both languages."
+        }
+        (ReportLanguage::Bilingual, BilingualLayout::Interleaved) => {
+            "\n\nLanguage: bilingual, section by section. Use bilingual headings such as \"## 执行摘要 / Executive Summary\"; under each heading write the Simplified Chinese text first, then the English text with the same content."
+        }
+        (ReportLanguage::Bilingual, BilingualLayout::Separate) if json => {
+            "\n\nLanguage: bilingual. Output one JSON object {\"zh\": <full report in Simplified Chinese>, \"en\": <full report in English>}, each following the structure above."
+        }
+        (ReportLanguage::Bilingual, BilingualLayout::Separate) => {
+            "\n\nLanguage: bilingual, two complete versions. First output the full report in Simplified Chinese, then a line containing only ---, then the full report in English with the same structure."
+        }
+    }
+}
+
+fn build_prompt(topic: &str, findings: &str, format: &str, language: ReportLanguage, layout: BilingualLayout) -> String {
+    let body = if format == "json" {
+        format!(
+            r#"Generate a structured research report in JSON format.
 
 Topic: {}
 
@@ -68,11 +171,11 @@ Output JSON structure:
     "recommendations": ["recommendation 1", "recommendation 2"],
     "references": ["source 1", "source 2"]
 }}"#,
-                topic, findings
-            )
-        } else {
-            format!(
-                r#"Generate a comprehensive research report in Markdown format.
+            topic, findings
+        )
+    } else {
+        format!(
+            r#"Generate a comprehensive research report in Markdown format.
 
 Topic: {}
 
@@ -100,13 +203,55 @@ Format:
 
 ## References
 - Source 1
-- Source 2
+- Source 2"#,
+            topic, findings
+        )
+    };
+    let suffix = if format == "json" { "" } else { "\n\nReport:" };
+    format!("{}{}{}", body, language_instruction(format, language, layout), suffix)
+}
 
-Report:"#,
-                topic, findings
-            )
+#[async_trait]
+impl Tool for ReportGeneratorTool {
+    fn name(&self) -> &str {
+        "generate_report"
+    }
+
+    fn description(&self) -> &str {
+        "Generate a structured research report from research findings. Supports Markdown and JSON formats, in Chinese, English or bilingual. Args: {\"topic\": \"research topic\", \"findings\": \"research data\", \"format\": \"markdown|json\" (optional), \"language\": \"zh|en|bilingual|auto\" (optional, defaults to the assistant's report_language), \"layout\": \"interleaved|separate\" (optional, bilingual only: section-by-section or two full versions)}"
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let topic = args
+            .get("topic")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim();
+
+        let findings = args
+            .get("findings")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim();
+
+        let format = args
+            .get("format")
+            .and_then(|v| v.as_str())
+            .unwrap_or("markdown");
+
+        if topic.is_empty() || findings.is_empty() {
+            return Err(ToolError::InvalidArgs("Missing topic or findings".to_string()));
+        }
+
+        let language = self.resolve_language(args.get("language").and_then(|v| v.as_str()))?;
+        let layout = match args.get("layout").and_then(|v| v.as_str()) {
+            Some(s) => BilingualLayout::parse(s).ok_or_else(|| {
+                ToolError::InvalidArgs(format!("Unknown layout '{}': use interleaved or separate", s))
+            })?,
+            None => BilingualLayout::default(),
         };
 
+        let prompt = build_prompt(topic, findings, format, language, layout);
         let messages = vec![Message::user(&prompt)];
         let response = self.llm.complete(&messages).await
             .map_err(|e| format!("LLM error: {}", e))?;
@@ -114,3 +259,27 @@ Report:"#,
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_language_prompt() {
+        assert_eq!(ReportLanguage::parse("中文"), Some(ReportLanguage::Zh));
+        assert_eq!(ReportLanguage::parse("zh-en"), Some(ReportLanguage::Bilingual));
+        assert_eq!(ReportLanguage::parse("fr"), None);
+
+        let auto = build_prompt("t", "f", "markdown", ReportLanguage::Auto, BilingualLayout::Interleaved);
+        assert!(!auto.contains("Language:"));
+        assert!(auto.ends_with("Report:"));
+
+        let zh = build_prompt("t", "f", "markdown", ReportLanguage::Zh, BilingualLayout::Interleaved);
+        assert!(zh.contains("Simplified Chinese"));
+
+        let interleaved = build_prompt("t", "f", "markdown", ReportLanguage::Bilingual, BilingualLayout::Interleaved);
+        assert!(interleaved.contains("执行摘要 / Executive Summary"));
+        let separate = build_prompt("t", "f", "json", ReportLanguage::Bilingual, BilingualLayout::Separate);
+        assert!(separate.contains("{\"zh\": <full report"));
+    }
+}
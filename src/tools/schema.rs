@@ -1,8 +1,10 @@
 //! 工具调用 JSON Schema 生成（白皮书 §4 BOM：schemars 自动生成工具 Schema）
 //!
-//! 用于将「合法 tool call」的 JSON 结构注入 system prompt，减少 LLM 输出格式错误。
+//! 用于将「合法 tool call」的 JSON 结构注入 system prompt，减少 LLM 输出格式错误；
+//! 并在执行前按各工具的 parameters_schema 校验参数（[validate_args]），不符时返回可供 Planner 修正的错误。
 
 use schemars::{schema_for, JsonSchema};
+use serde_json::Value;
 use std::collections::HashMap;
 
 /// 工具调用请求格式：与 ReAct 解析的 `{"tool": "...", "args": {...}}` 一致（仅用于 Schema 生成）
@@ -20,3 +22,198 @@ pub fn tool_call_schema_json() -> String {
     let schema = schema_for!(ToolCallFormat);
     serde_json::to_string_pretty(&schema).unwrap_or_else(|_| String::new())
 }
+
+/// 参数与工具 JSON Schema 不符的一处问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaViolation {
+    /// 缺少必填字段（值为字段路径）
+    Missing(String),
+    /// 字段类型或取值不合法
+    Invalid { field: String, reason: String },
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaViolation::Missing(field) => write!(f, "missing required field `{}`", field),
+            SchemaViolation::Invalid { field, reason } => write!(f, "`{}`: {}", field, reason),
+        }
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match (expected, value) {
+        ("integer", Value::Number(n)) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0),
+        ("number", Value::Number(_)) => true,
+        _ => expected == json_type(value),
+    }
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn short(value: &Value) -> String {
+    let s = value.to_string();
+    if s.chars().count() > 40 {
+        format!("{}...", s.chars().take(40).collect::<String>())
+    } else {
+        s
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, out: &mut Vec<SchemaViolation>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let field = if path.is_empty() { "args".to_string() } else { path.to_string() };
+    let expected: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if !expected.is_empty() && !expected.iter().any(|t| type_matches(t, value)) {
+        out.push(SchemaViolation::Invalid {
+            field,
+            reason: format!("expected {}, got {} {}", expected.join(" or "), json_type(value), short(value)),
+        });
+        return;
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            out.push(SchemaViolation::Invalid {
+                field: field.clone(),
+                reason: format!("must be one of {}, got {}", allowed.join(", "), short(value)),
+            });
+        }
+    }
+    if let Some(n) = value.as_f64() {
+        let min = schema.get("minimum").and_then(|v| v.as_f64());
+        let max = schema.get("maximum").and_then(|v| v.as_f64());
+        if min.is_some_and(|m| n < m) || max.is_some_and(|m| n > m) {
+            let bounds = match (min, max) {
+                (Some(lo), Some(hi)) => format!("between {} and {}", lo, hi),
+                (Some(lo), None) => format!(">= {}", lo),
+                (_, hi) => format!("<= {}", hi.unwrap_or_default()),
+            };
+            out.push(SchemaViolation::Invalid {
+                field: field.clone(),
+                reason: format!("must be {}, got {}", bounds, n),
+            });
+        }
+    }
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if map.get(key).is_none_or(Value::is_null) {
+                        out.push(SchemaViolation::Missing(child(path, key)));
+                    }
+                }
+            }
+            for (key, v) in map {
+                match properties.and_then(|p| p.get(key)) {
+                    // 可选字段显式传 null 视为未传
+                    Some(_) if v.is_null() => {}
+                    Some(sub) => validate_at(sub, v, &child(path, key), out),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        out.push(SchemaViolation::Invalid {
+                            field: child(path, key),
+                            reason: "unknown field".to_string(),
+                        });
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{}[{}]", field, i), out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 按工具的 parameters_schema 校验参数，返回全部问题（空表示通过）
+///
+/// 支持 type / enum / required / properties / items / additionalProperties(false) / minimum / maximum；
+/// 顶层 args 为 null 时按空对象处理，以便报告缺少的必填字段。
+pub fn validate_args(schema: &Value, args: &Value) -> Vec<SchemaViolation> {
+    let empty = Value::Object(Default::default());
+    let args = if args.is_null() { &empty } else { args };
+    let mut out = Vec::new();
+    validate_at(schema, args, "", &mut out);
+    out
+}
+
+/// 供 Planner 自行修正的错误信息：逐条列出问题（ReAct 循环会在 Observation 中再附上参数 schema）
+pub fn describe_violations(tool: &str, violations: &[SchemaViolation]) -> String {
+    let lines: Vec<String> = violations.iter().map(|v| format!("- {}", v)).collect();
+    format!("arguments for `{}` do not match its schema:\n{}", tool, lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_args_against_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "file_path": { "type": "string" },
+                "limit": { "type": "integer", "minimum": 1 },
+                "method": { "type": "string", "enum": ["GET", "POST"] },
+                "member_ids": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["file_path"]
+        });
+        assert!(validate_args(&schema, &json!({"file_path": "a.rs", "limit": 20.0, "extra": 1})).is_empty());
+        assert_eq!(validate_args(&schema, &Value::Null), vec![SchemaViolation::Missing("file_path".into())]);
+
+        let violations = validate_args(
+            &schema,
+            &json!({"limit": "50", "method": "PUT", "member_ids": ["a", 2], "file_path": null}),
+        );
+        let text: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "missing required field `file_path`",
+                "`limit`: expected integer, got string \"50\"",
+                "`member_ids[1]`: expected string, got integer 2",
+                "`method`: must be one of \"GET\", \"POST\", got \"PUT\"",
+            ]
+        );
+        assert!(validate_args(&schema, &json!({"file_path": "a", "limit": 0}))[0]
+            .to_string()
+            .contains("must be >= 1"));
+        assert!(describe_violations("code_read", &violations).starts_with("arguments for `code_read`"));
+        assert!(validate_args(&json!({"type": "object", "additionalProperties": false}), &json!({"x": 1}))[0]
+            .to_string()
+            .contains("unknown field"));
+    }
+}
@@ -3,25 +3,32 @@
 //! 仅允许配置中的域名（如 wikipedia、docs.rs）；GET 请求带超时与 User-Agent；
 //! 响应超过 max_result_chars 时截断并追加 ...[truncated]。
 //! 对 HTML 响应使用 html2text 提取可读文本，去除标签与脚本。
+//! 挂载 PolitePolicy 后按域名限速、遵守 robots.txt，并改用可识别的 User-Agent。
+//! 配置了 [[tools.search.providers]] 时还支持关键词搜索（query），按顺序尝试各后端并自动回退。
 
 use std::collections::HashSet;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use html2text::from_read;
 use reqwest::Client;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::search_provider::search_with_fallback;
+use crate::tools::{PolitePolicy, SearchProvider, Tool, ToolError};
 
 /// Search 工具：抓取 URL 内容，仅允许白名单域名；超时与最大字符数由配置决定
 pub struct SearchTool {
     client: Client,
     allowed_domains: HashSet<String>,
     max_result_chars: usize,
+    polite: Option<Arc<PolitePolicy>>,
+    providers: Vec<Arc<dyn SearchProvider>>,
+    max_results: usize,
 }
 
 /// 简易去除 HTML 标签（html2text 失败时的回退）
-fn strip_html_tags(html: &str) -> String {
+pub(crate) fn strip_html_tags(html: &str) -> String {
     let mut out = String::with_capacity(html.len());
     let mut in_tag = false;
     let mut prev_whitespace = false;
@@ -44,14 +51,14 @@ fn strip_html_tags(html: &str) -> String {
 }
 
 /// 判断内容是否像 HTML（需提取可读文本）
-fn looks_like_html(s: &str) -> bool {
+pub(crate) fn looks_like_html(s: &str) -> bool {
     let s = s.trim_start();
     s.starts_with("<!") || s.starts_with("<html") || s.starts_with("<HTML")
         || (s.len() > 20 && s.contains('<') && (s.contains("</") || s.contains("<meta") || s.contains("<head") || s.contains("<title")))
 }
 
 /// 从 URL 中提取 host（不含端口后的路径）
-fn extract_domain(url: &str) -> Option<String> {
+pub(crate) fn extract_domain(url: &str) -> Option<String> {
     let url = url.trim();
     let url = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
     let host = url.split('/').next()?;
@@ -87,16 +94,37 @@ impl SearchTool {
             client,
             allowed_domains,
             max_result_chars,
+            polite: None,
+            providers: Vec::new(),
+            max_results: 5,
         }
     }
 
-    fn is_allowed(&self, url: &str) -> Result<(), String> {
+    /// 供搜索后端复用的 HTTP 客户端（共享超时与连接池）
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// 挂载关键词搜索后端（按顺序回退），max_results 为默认结果数
+    pub fn with_providers(mut self, providers: Vec<Arc<dyn SearchProvider>>, max_results: usize) -> Self {
+        self.providers = providers;
+        self.max_results = max_results.max(1);
+        self
+    }
+
+    /// 挂载礼貌抓取策略（可与其它联网工具共享）
+    pub fn with_politeness(mut self, polite: Arc<PolitePolicy>) -> Self {
+        self.polite = Some(polite);
+        self
+    }
+
+    fn is_allowed(&self, url: &str) -> Result<(), ToolError> {
         let domain = extract_domain(url)
-            .ok_or_else(|| "Invalid or missing URL".to_string())?;
+            .ok_or_else(|| ToolError::InvalidArgs("Invalid or missing URL".to_string()))?;
         if self.allowed_domains.contains(&domain) {
             return Ok(());
         }
-        Err(format!("Domain not in allowlist: {}", domain))
+        Err(ToolError::PermissionDenied(format!("Domain not in allowlist: {}", domain)))
     }
 
     /// 将 HTML 转为可读文本（去除 script/style 等）
@@ -107,21 +135,31 @@ impl SearchTool {
         }
     }
 
-    async fn fetch(&self, url: &str) -> Result<String, String> {
+    async fn fetch(&self, url: &str) -> Result<String, ToolError> {
         self.is_allowed(url)?;
-        let resp = self
-            .client
-            .get(url)
+        let mut req = self.client.get(url);
+        if let Some(ref polite) = self.polite {
+            polite.acquire(url).await?;
+            req = req.header(reqwest::header::USER_AGENT, polite.user_agent());
+        }
+        let resp = req
             .send()
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
-        if !resp.status().is_success() {
-            return Err(format!("HTTP {}", resp.status()));
+            .map_err(|e| ToolError::Transient(format!("Request failed: {}", e)))?;
+        let status = resp.status();
+        if status.is_server_error() || status.as_u16() == 429 {
+            return Err(ToolError::Transient(format!("HTTP {}", status)));
+        }
+        if status.as_u16() == 404 {
+            return Err(ToolError::NotFound(format!("HTTP {}", status)));
+        }
+        if !status.is_success() {
+            return Err(ToolError::Failed(format!("HTTP {}", status)));
         }
         let mut body = resp
             .text()
             .await
-            .map_err(|e| format!("Read body: {}", e))?;
+            .map_err(|e| ToolError::Transient(format!("Read body: {}", e)))?;
 
         // 去除 BOM，避免 HTML 检测失败
         if body.starts_with('\u{FEFF}') {
@@ -143,6 +181,29 @@ impl SearchTool {
             Ok(body)
         }
     }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<String, ToolError> {
+        if self.providers.is_empty() {
+            return Err(ToolError::Failed(
+                "No search providers configured ([[tools.search.providers]]); pass a url instead".to_string(),
+            ));
+        }
+        let (provider, hits) = search_with_fallback(&self.providers, query, limit).await?;
+        if hits.is_empty() {
+            return Ok(format!("No results for \"{}\"", query));
+        }
+        let mut out = format!("Results for \"{}\" (via {}):", query, provider);
+        for (i, hit) in hits.iter().enumerate() {
+            out.push_str(&format!("\n{}. {}\n   {}", i + 1, hit.title, hit.url));
+            if !hit.snippet.is_empty() {
+                out.push_str(&format!("\n   {}", hit.snippet));
+            }
+        }
+        if out.chars().count() > self.max_result_chars {
+            out = out.chars().take(self.max_result_chars).collect::<String>() + "\n...[truncated]";
+        }
+        Ok(out)
+    }
 }
 
 #[async_trait]
@@ -152,17 +213,30 @@ impl Tool for SearchTool {
     }
 
     fn description(&self) -> &str {
-        "Fetch URL content (domain allowlist: Wikipedia, Baidu, JD, Zhihu, GitHub, StackOverflow, docs.rs, MDN, arxiv, etc). Args: {\"url\": \"https://...\"}."
+        "Fetch URL content (domain allowlist: Wikipedia, Baidu, JD, Zhihu, GitHub, StackOverflow, docs.rs, MDN, arxiv, etc). Args: {\"url\": \"https://...\"}. When search providers are configured, also web search by keywords: {\"query\": \"...\", \"limit\": 5}."
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        if let Some(query) = args
+            .get("query")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+        {
+            let limit = args
+                .get("limit")
+                .and_then(|v| v.as_u64())
+                .map_or(self.max_results, |n| n.clamp(1, 20) as usize);
+            tracing::info!(query = %query, "search tool query");
+            return self.search(query, limit).await;
+        }
         let url = args
             .get("url")
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .trim();
         if url.is_empty() {
-            return Err("Missing url".to_string());
+            return Err(ToolError::missing(if self.providers.is_empty() { "url" } else { "url or query" }));
         }
         tracing::info!(url = %url, "search tool fetch");
         self.fetch(url).await
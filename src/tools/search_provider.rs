@@ -0,0 +1,248 @@
+//! 关键词搜索后端：SearxNG / Brave Search API / Tavily
+//!
+//! 由 [[tools.search.providers]] 按顺序配置；search 工具收到 query 时依次尝试，
+//! 某个后端出错（未配置密钥、限流、网络错误）或无结果时自动换下一个。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::config::SearchProviderEntry;
+use crate::tools::ToolError;
+
+const BRAVE_API_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+const TAVILY_API_URL: &str = "https://api.tavily.com/search";
+
+/// 单条搜索结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// 搜索后端
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    /// 后端名，用于日志与结果标注
+    fn name(&self) -> &str;
+
+    /// 搜索关键词，最多返回 limit 条
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, ToolError>;
+}
+
+fn api_key(env: &str) -> Result<String, ToolError> {
+    std::env::var(env)
+        .ok()
+        .filter(|k| !k.trim().is_empty())
+        .ok_or_else(|| ToolError::Failed(format!("API key env {} is not set", env)))
+}
+
+async fn json_response(req: reqwest::RequestBuilder) -> Result<Value, ToolError> {
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| ToolError::Transient(format!("Request failed: {}", e)))?;
+    let status = resp.status();
+    if status.is_server_error() || status.as_u16() == 429 {
+        return Err(ToolError::Transient(format!("HTTP {}", status)));
+    }
+    if !status.is_success() {
+        return Err(ToolError::Failed(format!("HTTP {}", status)));
+    }
+    resp.json()
+        .await
+        .map_err(|e| ToolError::Failed(format!("Invalid search response: {}", e)))
+}
+
+/// 从结果数组中取 title / url / 摘要字段
+fn parse_hits(results: Option<&Value>, snippet_field: &str, limit: usize) -> Vec<SearchHit> {
+    let field = |r: &Value, key: &str| r.get(key).and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+    results
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .map(|r| SearchHit {
+                    title: field(r, "title"),
+                    url: field(r, "url"),
+                    snippet: field(r, snippet_field),
+                })
+                .filter(|h| !h.url.is_empty())
+                .take(limit)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 自建 SearxNG 实例（需在 settings.yml 中启用 json 格式）
+pub struct SearxngProvider {
+    client: Client,
+    base_url: String,
+}
+
+#[async_trait]
+impl SearchProvider for SearxngProvider {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, ToolError> {
+        let url = format!("{}/search", self.base_url.trim_end_matches('/'));
+        let body = json_response(self.client.get(url).query(&[("q", query), ("format", "json")])).await?;
+        Ok(parse_hits(body.get("results"), "content", limit))
+    }
+}
+
+/// Brave Search API
+pub struct BraveProvider {
+    client: Client,
+    api_key_env: String,
+}
+
+#[async_trait]
+impl SearchProvider for BraveProvider {
+    fn name(&self) -> &str {
+        "brave"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, ToolError> {
+        let key = api_key(&self.api_key_env)?;
+        let req = self
+            .client
+            .get(BRAVE_API_URL)
+            .header("X-Subscription-Token", key)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .query(&[("q", query), ("count", &limit.min(20).to_string())]);
+        let body = json_response(req).await?;
+        Ok(parse_hits(body.pointer("/web/results"), "description", limit))
+    }
+}
+
+/// Tavily Search API
+pub struct TavilyProvider {
+    client: Client,
+    api_key_env: String,
+}
+
+#[async_trait]
+impl SearchProvider for TavilyProvider {
+    fn name(&self) -> &str {
+        "tavily"
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, ToolError> {
+        let key = api_key(&self.api_key_env)?;
+        let req = self
+            .client
+            .post(TAVILY_API_URL)
+            .bearer_auth(key)
+            .json(&serde_json::json!({ "query": query, "max_results": limit }));
+        let body = json_response(req).await?;
+        Ok(parse_hits(body.get("results"), "content", limit))
+    }
+}
+
+/// 按配置顺序构建搜索后端
+pub fn providers_from_config(entries: &[SearchProviderEntry], client: &Client) -> Vec<Arc<dyn SearchProvider>> {
+    entries
+        .iter()
+        .map(|entry| -> Arc<dyn SearchProvider> {
+            match entry {
+                SearchProviderEntry::Searxng { url } => Arc::new(SearxngProvider {
+                    client: client.clone(),
+                    base_url: url.clone(),
+                }),
+                SearchProviderEntry::Brave { api_key_env } => Arc::new(BraveProvider {
+                    client: client.clone(),
+                    api_key_env: api_key_env.clone(),
+                }),
+                SearchProviderEntry::Tavily { api_key_env } => Arc::new(TavilyProvider {
+                    client: client.clone(),
+                    api_key_env: api_key_env.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// 依次尝试各后端，返回首个有结果的后端名与结果；全部失败时返回最后一个错误
+pub async fn search_with_fallback(
+    providers: &[Arc<dyn SearchProvider>],
+    query: &str,
+    limit: usize,
+) -> Result<(String, Vec<SearchHit>), ToolError> {
+    let mut last_err = None;
+    for provider in providers {
+        match provider.search(query, limit).await {
+            Ok(hits) if !hits.is_empty() => return Ok((provider.name().to_string(), hits)),
+            Ok(_) => tracing::info!(provider = provider.name(), "search provider returned no results"),
+            Err(e) => {
+                tracing::warn!(provider = provider.name(), error = %e, "search provider failed, trying next");
+                last_err = Some(e);
+            }
+        }
+    }
+    match last_err {
+        Some(e) => Err(e),
+        None => Ok((String::new(), Vec::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    struct StubProvider {
+        name: &'static str,
+        result: Result<Vec<SearchHit>, ToolError>,
+    }
+
+    #[async_trait]
+    impl SearchProvider for StubProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn search(&self, _query: &str, _limit: usize) -> Result<Vec<SearchHit>, ToolError> {
+            self.result.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_fallback_and_parsing() {
+        let brave = json!({"web": {"results": [
+            {"title": "Rust", "url": "https://www.rust-lang.org", "description": "A language"},
+            {"title": "no url"},
+            {"title": "Book", "url": "https://doc.rust-lang.org/book", "description": "The book"}
+        ]}});
+        let hits = parse_hits(brave.pointer("/web/results"), "description", 5);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].snippet, "A language");
+        assert_eq!(parse_hits(brave.pointer("/web/results"), "description", 1).len(), 1);
+
+        let providers: Vec<Arc<dyn SearchProvider>> = vec![
+            Arc::new(StubProvider {
+                name: "searxng",
+                result: Err(ToolError::Transient("HTTP 503".into())),
+            }),
+            Arc::new(StubProvider {
+                name: "brave",
+                result: Ok(vec![]),
+            }),
+            Arc::new(StubProvider {
+                name: "tavily",
+                result: Ok(hits.clone()),
+            }),
+        ];
+        let (used, found) = search_with_fallback(&providers, "rust", 5).await.unwrap();
+        assert_eq!((used.as_str(), found), ("tavily", hits));
+
+        let err = search_with_fallback(&providers[..2], "rust", 5).await.unwrap_err();
+        assert!(matches!(err, ToolError::Transient(_)));
+    }
+}
@@ -1,56 +1,34 @@
 //! send 工具：assistant 向另一个 assistant 发送消息（Phase 2）
 //!
-//! 创建/复用 P2P 群，写入消息。发送方来自 task_local（process_message_stream 设置）。
+//! 信件投递到工作区信箱（[Mailbox](crate::core::mailbox::Mailbox)），由收件人的信箱处理循环自动处理；
+//! 同时创建/复用 P2P 群并写入会话记录供页面查看。发送方来自 task_local（process_message_stream 设置），
+//! 在处理来信期间发出的信挂到同一会话链（[CURRENT_MAIL]）。
 
 use std::path::Path;
 
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::core::mailbox::{p2p_group_id, MailKind, MailMessage, Mailbox, CURRENT_MAIL};
+use crate::core::workspace_store::{GroupInfo, GroupRepository, SqliteWorkspaceStore};
+use crate::tools::{Tool, ToolError};
 
-tokio::task_local! {
-    /// 当前执行 ReAct 的 assistant_id，由 process_message_stream 设置
-    pub static CURRENT_ASSISTANT_ID: Option<String>;
-}
-
-/// P2P 群 ID 前缀
-const P2P_PREFIX: &str = "p2p_";
-
-/// 生成 P2P 群 id：按字母序排列 (a, b) 保证唯一
-fn p2p_group_id(a: &str, b: &str) -> String {
-    let (x, y) = if a <= b { (a, b) } else { (b, a) };
-    format!("{}{}_{}", P2P_PREFIX, x, y)
-}
+pub use crate::tools::policy::CURRENT_ASSISTANT_ID;
 
 /// send 工具：向另一 assistant 发私信
 pub struct SendTool {
-    groups_path: std::path::PathBuf,
+    workspace: std::path::PathBuf,
     sessions_dir: std::path::PathBuf,
 }
 
 impl SendTool {
     pub fn new(workspace: &Path) -> Self {
         Self {
-            groups_path: workspace.join("groups.json"),
+            workspace: workspace.to_path_buf(),
             sessions_dir: workspace.join("sessions"),
         }
     }
 
-    fn load_groups(&self) -> std::collections::HashMap<String, GroupInfo> {
-        let data = match std::fs::read_to_string(&self.groups_path) {
-            Ok(d) => d,
-            Err(_) => return std::collections::HashMap::new(),
-        };
-        serde_json::from_str(&data).unwrap_or_default()
-    }
-
-    fn save_groups(&self, groups: &std::collections::HashMap<String, GroupInfo>) {
-        if let Ok(json) = serde_json::to_string_pretty(groups) {
-            let _ = std::fs::write(&self.groups_path, json);
-        }
-    }
-
     fn load_group_messages(&self, group_id: &str) -> Vec<GroupMessage> {
         let path = self.group_session_path(group_id);
         let data = match std::fs::read_to_string(&path) {
@@ -90,14 +68,6 @@ impl SendTool {
     }
 }
 
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
-struct GroupInfo {
-    id: String,
-    name: Option<String>,
-    member_ids: Vec<String>,
-    created_at: String,
-}
-
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct GroupMessage {
     role: String,
@@ -123,7 +93,7 @@ impl Tool for SendTool {
     }
 
     fn description(&self) -> &str {
-        "Send a direct message to another assistant. Use when you need to delegate, ask for help, or hand off a task. Args: to (assistant_id), content (string)."
+        "Send a direct message to another assistant. Use when you need to delegate, ask for help, or hand off a task. Args: to (assistant_id), content (string), kind (request = expects a reply, delivered back to you automatically; notice = no reply)."
     }
 
     fn parameters_schema(&self) -> Value {
@@ -137,13 +107,18 @@ impl Tool for SendTool {
                 "content": {
                     "type": "string",
                     "description": "Message content to send"
+                },
+                "kind": {
+                    "type": "string",
+                    "enum": ["request", "notice"],
+                    "description": "request (default): the recipient's answer is sent back to you; notice: informational, no reply"
                 }
             },
             "required": ["to", "content"]
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let to = args
             .get("to")
             .and_then(|v| v.as_str())
@@ -157,11 +132,24 @@ impl Tool for SendTool {
             .trim()
             .to_string();
 
+        let kind = match args.get("kind").and_then(|v| v.as_str()) {
+            None => MailKind::Request,
+            Some(k) => match MailKind::parse(k) {
+                Some(MailKind::Reply) | None => {
+                    return Err(ToolError::InvalidArgs(format!(
+                        "send: invalid kind '{}', expected request or notice",
+                        k
+                    )))
+                }
+                Some(kind) => kind,
+            },
+        };
+
         if to.is_empty() {
-            return Err("send: 'to' is required".to_string());
+            return Err(ToolError::InvalidArgs("send: 'to' is required".to_string()));
         }
         if content.is_empty() {
-            return Err("send: 'content' is required".to_string());
+            return Err(ToolError::InvalidArgs("send: 'content' is required".to_string()));
         }
 
         let from = CURRENT_ASSISTANT_ID
@@ -170,24 +158,30 @@ impl Tool for SendTool {
             .unwrap_or_else(|| "default".to_string());
 
         if from == to {
-            return Err("send: cannot send message to yourself".to_string());
+            return Err(ToolError::InvalidArgs("send: cannot send message to yourself".to_string()));
         }
 
+        // 处理来信期间发出的信延续该会话链
+        let mail = CURRENT_MAIL
+            .try_with(|m| m.follow_up(&to, kind, &content))
+            .ok()
+            .filter(|m| m.from == from)
+            .unwrap_or_else(|| MailMessage::new(&from, &to, kind, &content));
+        Mailbox::open(&self.workspace)
+            .map_err(|e| ToolError::Failed(format!("send: failed to open mailbox: {}", e)))?
+            .send(&mail)
+            .map_err(|e| ToolError::Failed(format!("send: {}", e)))?;
+
         let group_id = p2p_group_id(&from, &to);
 
-        let mut groups = self.load_groups();
-        if !groups.contains_key(&group_id) {
-            groups.insert(
-                group_id.clone(),
-                GroupInfo {
-                    id: group_id.clone(),
-                    name: Some(format!("P2P {} ↔ {}", from, to)),
-                    member_ids: vec![from.clone(), to.clone()],
-                    created_at: chrono::Utc::now().to_rfc3339(),
-                },
-            );
-            self.save_groups(&groups);
-        }
+        let group = GroupInfo::new(
+            group_id.clone(),
+            Some(format!("P2P {} ↔ {}", from, to)),
+            vec![from.clone(), to.clone()],
+        );
+        SqliteWorkspaceStore::open(&self.workspace)
+            .and_then(|store| store.insert_group_if_absent(&group))
+            .map_err(|e| ToolError::Failed(format!("send: failed to save P2P group: {}", e)))?;
 
         let mut messages = self.load_group_messages(&group_id);
         messages.push(GroupMessage {
@@ -197,9 +191,13 @@ impl Tool for SendTool {
         });
         self.save_group_messages(&group_id, &messages);
 
+        let follow = match kind {
+            MailKind::Request => "Their reply will be delivered to you automatically.",
+            _ => "No reply is expected.",
+        };
         Ok(format!(
-            "Message sent to {} in P2P group {}. The recipient may process it when their inbox is checked.",
-            to, group_id
+            "Message {} sent to {} (P2P group {}). {}",
+            mail.id, to, group_id, follow
         ))
     }
 }
@@ -0,0 +1,144 @@
+//! 发送文件工具
+//!
+//! send_file 把 workspace 内的文件作为附件随本次回复发回当前会话（如 WhatsApp 的图片、文档、音频）。
+//! 支持附件的接入端处理消息时设置 CURRENT_ATTACHMENTS，回复发出后依次发送收集到的文件；未设置时调用报错。
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::tools::filesystem::{fs_error, SafeFs};
+use crate::tools::{Tool, ToolError};
+
+/// 本次回复待发送的附件
+pub type AttachmentQueue = Arc<Mutex<Vec<OutboundFile>>>;
+
+tokio::task_local! {
+    /// 本次回复的附件队列，由支持发送文件的接入端（bee-whatsapp）处理消息时设置
+    pub static CURRENT_ATTACHMENTS: Option<AttachmentQueue>;
+}
+
+/// 单个附件最大字节数
+const MAX_ATTACHMENT_BYTES: u64 = 100 * 1024 * 1024;
+/// 单次回复最多附件数
+const MAX_ATTACHMENTS: usize = 10;
+
+/// 一个待发送的附件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundFile {
+    /// 文件绝对路径
+    pub path: PathBuf,
+    /// 发送时显示的文件名
+    pub file_name: String,
+    pub caption: Option<String>,
+}
+
+/// send_file 工具
+pub struct SendFileTool {
+    fs: SafeFs,
+}
+
+impl SendFileTool {
+    pub fn new(root_dir: impl AsRef<Path>) -> Self {
+        Self {
+            fs: SafeFs::new(root_dir),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for SendFileTool {
+    fn name(&self) -> &str {
+        "send_file"
+    }
+
+    fn description(&self) -> &str {
+        "Send a workspace file (image, PDF, document, audio) to the user as an attachment of this reply. Only available on channels that support files (e.g. WhatsApp). Args: {\"path\": \"file path relative to workspace\", \"caption\": \"optional caption\"}"
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let queue = CURRENT_ATTACHMENTS
+            .try_with(|q| q.clone())
+            .ok()
+            .flatten()
+            .ok_or_else(|| ToolError::Failed("The current channel cannot send files".to_string()))?;
+        let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if path.is_empty() {
+            return Err(ToolError::InvalidArgs("Missing path".to_string()));
+        }
+        let resolved = self.fs.resolve(path).map_err(fs_error)?;
+        let metadata =
+            std::fs::metadata(&resolved).map_err(|e| ToolError::Failed(format!("Cannot read {}: {}", path, e)))?;
+        if !metadata.is_file() {
+            return Err(ToolError::InvalidArgs(format!("{} is not a file", path)));
+        }
+        if metadata.len() > MAX_ATTACHMENT_BYTES {
+            return Err(ToolError::InvalidArgs(format!(
+                "{} is {} bytes, larger than the {} byte limit",
+                path,
+                metadata.len(),
+                MAX_ATTACHMENT_BYTES
+            )));
+        }
+        let caption = args
+            .get("caption")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::to_string);
+        let file_name = resolved
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".to_string());
+
+        let mut files = queue.lock().unwrap_or_else(|e| e.into_inner());
+        if files.len() >= MAX_ATTACHMENTS {
+            return Err(ToolError::Failed(format!(
+                "At most {} files can be sent with one reply",
+                MAX_ATTACHMENTS
+            )));
+        }
+        if files.iter().all(|f| f.path != resolved) {
+            files.push(OutboundFile {
+                path: resolved,
+                file_name,
+                caption,
+            });
+        }
+        Ok(format!("{} will be sent to the user with this reply", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_file_queues_attachment() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("report.pdf"), b"%PDF").unwrap();
+        let tool = SendFileTool::new(dir.path());
+        let args = serde_json::json!({"path": "report.pdf", "caption": "周报"});
+
+        assert!(tool.execute(args.clone()).await.is_err());
+
+        let queue: AttachmentQueue = Arc::default();
+        CURRENT_ATTACHMENTS
+            .scope(Some(Arc::clone(&queue)), async {
+                tool.execute(args.clone()).await.unwrap();
+                tool.execute(args).await.unwrap();
+                assert!(tool.execute(serde_json::json!({"path": "missing.pdf"})).await.is_err());
+                assert!(tool
+                    .execute(serde_json::json!({"path": "../etc/passwd"}))
+                    .await
+                    .is_err());
+            })
+            .await;
+        let files = queue.lock().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_name, "report.pdf");
+        assert_eq!(files[0].caption.as_deref(), Some("周报"));
+    }
+}
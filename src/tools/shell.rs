@@ -1,7 +1,7 @@
 //! Shell 执行器：白名单命令，禁止危险操作
 //!
 //! 仅允许配置中的命令名（首词，如 ls、grep、cargo）；禁止 rm -rf、wget、chmod 777 等子串；
-//! 执行通过 sh -c / cmd /C，带超时与 tracing 审计。
+//! 执行通过 sh -c / cmd /C，带超时与 tracing 审计。命令可访问任意路径，限定于用户工作区的请求（多用户隔离）不可用。
 
 use std::collections::HashSet;
 
@@ -9,7 +9,7 @@ use async_trait::async_trait;
 use serde_json::Value;
 use tokio::process::Command;
 
-use crate::tools::Tool;
+use crate::tools::{ensure_unscoped, Tool, ToolError};
 
 /// 禁止的命令/子串（即使白名单中有同名，也不允许带这些参数）
 const FORBIDDEN_SUBSTR: &[&str] = &[
@@ -49,21 +49,21 @@ impl ShellTool {
         raw.split_whitespace().next().unwrap_or("")
     }
 
-    fn is_allowed(&self, raw: &str) -> Result<(), String> {
+    fn is_allowed(&self, raw: &str) -> Result<(), ToolError> {
         let raw_lower = raw.to_lowercase();
         for forbidden in FORBIDDEN_SUBSTR {
             if raw_lower.contains(forbidden) {
-                return Err(format!("Forbidden pattern: {}", forbidden));
+                return Err(ToolError::PermissionDenied(format!("Forbidden pattern: {}", forbidden)));
             }
         }
         let name = self.command_name(&raw_lower);
         if name.is_empty() {
-            return Err("Empty command".to_string());
+            return Err(ToolError::InvalidArgs("Empty command".to_string()));
         }
         if self.allowed_commands.contains(name) {
             return Ok(());
         }
-        Err(format!("Command '{}' not in allowlist", name))
+        Err(ToolError::PermissionDenied(format!("Command '{}' not in allowlist", name)))
     }
 }
 
@@ -90,12 +90,13 @@ impl Tool for ShellTool {
         })
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let command = args
             .get("command")
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .trim();
+        ensure_unscoped("shell")?;
         self.is_allowed(command)?;
 
         tracing::info!(command = %command, "shell tool execute");
@@ -109,19 +110,25 @@ impl Tool for ShellTool {
             c.args(["-c", command]);
             c
         };
+        // 超时后丢弃的 future 连同子进程一起结束，避免命令在后台继续运行
+        cmd.kill_on_drop(true);
 
         let output = tokio::time::timeout(
             std::time::Duration::from_secs(self.timeout_secs),
             cmd.output(),
         )
         .await
-        .map_err(|_| format!("Command timed out after {}s", self.timeout_secs))?
+        .map_err(|_| ToolError::Failed(format!("Command timed out after {}s", self.timeout_secs)))?
         .map_err(|e| format!("Execution failed: {}", e))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
         let stderr = String::from_utf8_lossy(&output.stderr).to_string();
         if !output.status.success() {
-            return Err(format!("Exit {:?}\nstderr: {}", output.status, stderr.trim()));
+            return Err(ToolError::Failed(format!(
+                "Exit {:?}\nstderr: {}",
+                output.status,
+                stderr.trim()
+            )));
         }
         Ok(if stderr.is_empty() {
             stdout
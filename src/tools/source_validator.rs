@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{Tool, ToolError};
 
 pub struct SourceValidatorTool {
     trusted_domains: Vec<String>,
@@ -52,7 +52,7 @@ impl Tool for SourceValidatorTool {
         "Validate the credibility of a web source. Returns trust score (0-1) and credibility analysis. Args: {\"url\": \"https://...\", \"content\": \"optional content snippet\"}"
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
         let url = args
             .get("url")
             .and_then(|v| v.as_str())
@@ -60,7 +60,7 @@ impl Tool for SourceValidatorTool {
             .trim();
 
         if url.is_empty() {
-            return Err("Missing url".to_string());
+            return Err(ToolError::InvalidArgs("Missing url".to_string()));
         }
 
         let trust_score = self.calculate_trust_score(url);
@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use serde_json::Value;
 use tokio::process::Command;
 
-use crate::tools::Tool;
+use crate::tools::{ensure_unscoped, Tool, ToolError};
 
 pub struct TestCheckTool {
     project_root: PathBuf,
@@ -44,7 +44,8 @@ impl Tool for TestCheckTool {
 {"features": "web", "all_targets": true}"#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        ensure_unscoped("test_check")?;
         let features = args.get("features").and_then(|v| v.as_str());
         let all_targets = args.get("all_targets").and_then(|v| v.as_bool()).unwrap_or(true);
 
@@ -67,7 +68,7 @@ impl Tool for TestCheckTool {
             cmd.output(),
         )
         .await
-        .map_err(|_| "Check timed out")?
+        .map_err(|_| ToolError::Transient("Check timed out".to_string()))?
         .map_err(|e| format!("Failed to run check: {}", e))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -91,7 +92,7 @@ impl Tool for TestCheckTool {
         if success {
             Ok(result)
         } else {
-            Err(result)
+            Err(ToolError::Failed(result))
         }
     }
 }
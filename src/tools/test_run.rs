@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use serde_json::Value;
 use tokio::process::Command;
 
-use crate::tools::Tool;
+use crate::tools::{ensure_unscoped, Tool, ToolError};
 
 pub struct TestRunTool {
     project_root: PathBuf,
@@ -45,7 +45,8 @@ impl Tool for TestRunTool {
 {"package": "bee", "test_name": "test_agent", "features": ""}"#
     }
 
-    async fn execute(&self, args: Value) -> Result<String, String> {
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        ensure_unscoped("test_run")?;
         let package = args.get("package").and_then(|v| v.as_str());
         let test_name = args.get("test_name").and_then(|v| v.as_str());
         let features = args.get("features").and_then(|v| v.as_str());
@@ -76,7 +77,7 @@ impl Tool for TestRunTool {
             cmd.output(),
         )
         .await
-        .map_err(|_| "Test execution timed out")?
+        .map_err(|_| ToolError::Transient("Test execution timed out".to_string()))?
         .map_err(|e| format!("Failed to run tests: {}", e))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -105,7 +106,7 @@ impl Tool for TestRunTool {
         if success {
             Ok(result)
         } else {
-            Err(result)
+            Err(ToolError::Failed(result))
         }
     }
 }
@@ -0,0 +1,86 @@
+//! tool_help 工具：按需返回某个工具的完整说明与参数 schema
+//!
+//! system prompt 中仅注入各工具的 short_description，LLM 需要详细用法时调用 tool_help。
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::tools::{Tool, ToolError, ToolRegistry};
+
+/// tool_help：持有注册时各工具完整说明的快照
+pub struct ToolHelpTool {
+    help: HashMap<String, String>,
+}
+
+impl ToolHelpTool {
+    /// 从已注册的工具生成说明快照（应在其它工具注册完成后调用）
+    pub fn from_registry(registry: &ToolRegistry) -> Self {
+        let help = registry
+            .tool_names()
+            .into_iter()
+            .filter_map(|name| registry.tool_help(&name).map(|h| (name, h)))
+            .collect();
+        Self { help }
+    }
+}
+
+#[async_trait]
+impl Tool for ToolHelpTool {
+    fn name(&self) -> &str {
+        "tool_help"
+    }
+
+    fn description(&self) -> &str {
+        "Show the full description and parameter schema of a tool. Args: name (string)."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Tool name to describe"
+                }
+            },
+            "required": ["name"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let name = args
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("name"))?;
+        self.help.get(name).cloned().ok_or_else(|| {
+            let mut names: Vec<&str> = self.help.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            ToolError::NotFound(format!("Unknown tool: {}. Available: {}", name, names.join(", ")))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::EchoTool;
+
+    #[test]
+    fn test_tool_help_lookup() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut registry = ToolRegistry::new();
+            registry.register(EchoTool);
+            let help = ToolHelpTool::from_registry(&registry);
+
+            let out = help.execute(serde_json::json!({"name": "echo"})).await.unwrap();
+            assert!(out.contains("Echo text"));
+            assert!(out.contains("\"text\""));
+
+            let err = help.execute(serde_json::json!({"name": "nope"})).await.unwrap_err();
+            assert!(err.to_string().contains("echo"));
+        });
+    }
+}
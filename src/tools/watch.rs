@@ -0,0 +1,214 @@
+//! 文件监听工具
+//!
+//! watch 支持 add / list / remove：登记相对 workspace 的 glob 规则（可附 task），存入 workspace.db 后由
+//! bee-web / bee-gateway 的文件监听器（TaskScheduler::spawn_file_watcher）轮询，文件新增 / 修改 / 删除时
+//! 推送 file_changed 事件；带 task 的规则让 Agent 处理该文件，结果写回创建规则的 Web 会话（无来源会话时由网关以后台任务执行）。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::core::{normalize_watch_pattern, WatchRule, WatchStore};
+use crate::tools::remind::current_origin;
+use crate::tools::{Tool, ToolError};
+
+/// 支持文件监听的接入端（这些接入端会运行监听器并把结果写回会话）
+const WATCH_CHANNELS: &[&str] = &["web"];
+
+fn store_err(e: impl std::fmt::Display) -> ToolError {
+    ToolError::Failed(format!("Watch store error: {}", e))
+}
+
+fn rule_line(r: &WatchRule) -> String {
+    match &r.task {
+        Some(task) => format!("- [{}] {} → {}", r.id, r.pattern, task),
+        None => format!("- [{}] {} (events only)", r.id, r.pattern),
+    }
+}
+
+/// watch 工具
+pub struct WatchTool {
+    store: Arc<WatchStore>,
+    max_watches: usize,
+}
+
+impl WatchTool {
+    pub fn new(store: Arc<WatchStore>, max_watches: usize) -> Self {
+        Self { store, max_watches }
+    }
+
+    fn add(&self, args: &Value) -> Result<String, ToolError> {
+        let pattern = args
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("pattern"))?;
+        let pattern = normalize_watch_pattern(pattern).map_err(ToolError::InvalidArgs)?;
+        let task = args
+            .get("task")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        let origin = current_origin();
+        if let Some(o) = origin
+            .as_ref()
+            .filter(|o| !WATCH_CHANNELS.contains(&o.channel.as_str()))
+        {
+            return Err(ToolError::Failed(format!(
+                "File watches are not supported from {}; use the Web UI or the gateway",
+                o.channel
+            )));
+        }
+        let existing = self.store.list(None).map_err(store_err)?;
+        if existing.len() >= self.max_watches {
+            return Err(ToolError::Failed(format!(
+                "Already {} file watches; remove some first",
+                self.max_watches
+            )));
+        }
+        if let Some(dup) = existing.iter().find(|r| r.pattern == pattern && r.origin == origin) {
+            return Err(ToolError::Failed(format!(
+                "Already watching {} as [{}]",
+                pattern, dup.id
+            )));
+        }
+        let rule = WatchRule {
+            id: uuid::Uuid::new_v4().simple().to_string().chars().take(8).collect(),
+            pattern,
+            task,
+            origin,
+            created_at: Utc::now().timestamp(),
+        };
+        self.store.upsert(&rule).map_err(store_err)?;
+        Ok(format!(
+            "✓ Watching (existing files are not reported, only later changes):\n{}",
+            rule_line(&rule)
+        ))
+    }
+
+    /// 当前会话的规则；无来源会话（如 TUI）时列出全部
+    fn list(&self) -> Result<String, ToolError> {
+        let rules = self.store.list(current_origin().as_ref()).map_err(store_err)?;
+        if rules.is_empty() {
+            return Ok("No file watches".to_string());
+        }
+        let lines: Vec<String> = rules.iter().map(rule_line).collect();
+        Ok(format!("{} file watch(es):\n{}", rules.len(), lines.join("\n")))
+    }
+
+    fn remove(&self, args: &Value) -> Result<String, ToolError> {
+        let id = args
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("id"))?;
+        // 只能移除当前会话的规则
+        let owned = self.store.list(current_origin().as_ref()).map_err(store_err)?;
+        if !owned.iter().any(|r| r.id == id) || !self.store.remove(id).map_err(store_err)? {
+            return Err(ToolError::Failed(format!(
+                "No file watch with id '{}' in this conversation",
+                id
+            )));
+        }
+        Ok(format!("✓ Removed file watch {}", id))
+    }
+}
+
+#[async_trait]
+impl Tool for WatchTool {
+    fn name(&self) -> &str {
+        "watch"
+    }
+
+    fn description(&self) -> &str {
+        r#"监听工作区文件变化：匹配的文件新增 / 修改 / 删除时推送事件；给出 task 时由你自动处理该文件，结果发回当前会话。
+
+参数:
+- action: add | list | remove（必需）
+- add: pattern（相对 workspace 的 glob，如 "inbox/*.csv"、"reports/**/*.md"），task（可选，文件变化时要做的事）
+- remove: id（list 返回的 id）
+
+示例:
+{"action": "add", "pattern": "inbox/*.csv", "task": "读取新文件并总结主要数据"}
+{"action": "remove", "id": "a1b2c3d4"}"#
+    }
+
+    async fn execute(&self, args: Value) -> Result<String, ToolError> {
+        let action = args
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::missing("action"))?;
+        match action {
+            "add" => self.add(&args),
+            "list" => self.list(),
+            "remove" => self.remove(&args),
+            other => Err(ToolError::InvalidArgs(format!(
+                "Unknown action '{}': use add, list or remove",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::ReminderOrigin;
+    use crate::tools::CURRENT_ORIGIN;
+
+    #[tokio::test]
+    async fn test_watch_add_list_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = WatchTool::new(Arc::new(WatchStore::open(dir.path()).unwrap()), 2);
+        assert!(tool
+            .execute(json!({"action": "add", "pattern": "../outside/*"}))
+            .await
+            .is_err());
+
+        let origin = |channel: &str| ReminderOrigin {
+            channel: channel.into(),
+            target: "s1".into(),
+            assistant_id: None,
+            user_id: None,
+        };
+        CURRENT_ORIGIN
+            .scope(Some(origin("whatsapp")), async {
+                let err = tool
+                    .execute(json!({"action": "add", "pattern": "inbox/*.csv"}))
+                    .await
+                    .unwrap_err();
+                assert!(err.to_string().contains("not supported"));
+            })
+            .await;
+        let id = CURRENT_ORIGIN
+            .scope(Some(origin("web")), async {
+                let added = tool
+                    .execute(json!({"action": "add", "pattern": "./inbox/*.csv", "task": "summarize it"}))
+                    .await
+                    .unwrap();
+                assert!(added.contains("inbox/*.csv → summarize it"));
+                assert!(tool
+                    .execute(json!({"action": "add", "pattern": "inbox/*.csv"}))
+                    .await
+                    .is_err());
+                added.split('[').nth(1).unwrap().split(']').next().unwrap().to_string()
+            })
+            .await;
+
+        // 无来源会话（TUI）可见全部规则
+        assert!(tool.execute(json!({"action": "list"})).await.unwrap().contains(&id));
+        tool.execute(json!({"action": "add", "pattern": "drop/*"}))
+            .await
+            .unwrap();
+        assert!(tool
+            .execute(json!({"action": "add", "pattern": "more/*"}))
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("Already 2"));
+        assert!(tool.execute(json!({"action": "remove", "id": id})).await.is_ok());
+    }
+}
@@ -57,6 +57,14 @@ pub async fn run_app(
                         break;
                     }
                 }
+                super::event::AppEvent::Key(key) if state.pending_approval.is_some() => {
+                    let id = state.pending_approval.as_ref().map(|r| r.id.clone()).unwrap_or_default();
+                    match key.code {
+                        KeyCode::Char('y') | KeyCode::Char('Y') => event_handler.send_approval(id, true),
+                        KeyCode::Char('n') | KeyCode::Char('N') => event_handler.send_approval(id, false),
+                        _ => {}
+                    }
+                }
                 super::event::AppEvent::Key(key) if !state.input_locked => {
                     match key.code {
                         KeyCode::Enter => {
@@ -69,7 +77,10 @@ pub async fn run_app(
                                     if matches!(input.to_lowercase().as_str(), "/exit" | "exit" | "/quit" | "quit") {
                                         break;
                                     }
-                                    event_handler.send_submit(input);
+                                    match state.pending_question {
+                                        Some(ref q) => event_handler.send_answer(q.id.clone(), input),
+                                        None => event_handler.send_submit(input),
+                                    }
                                 }
                             }
                         }
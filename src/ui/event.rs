@@ -1,7 +1,7 @@
 //! 事件处理
 //!
 //! 轮询 crossterm 键盘事件，将 Ctrl+C/Ctrl+L/Esc/Ctrl+Q 转为 Command（Cancel/Clear/Quit），
-//! 其余按键交给 run_app 拼 input_buffer，Enter 时 send_submit。
+//! 其余按键交给 run_app 拼 input_buffer，Enter 时 send_submit；有待审批工具调用时 y/n 经 send_approval 回复。
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
 use tokio::sync::mpsc;
@@ -58,4 +58,14 @@ impl EventHandler {
     pub fn send_submit(&self, input: String) {
         let _ = self.cmd_tx.send(Command::Submit(input));
     }
+
+    /// 回复待审批的工具调用（y 批准 / n 拒绝）
+    pub fn send_approval(&self, id: String, approved: bool) {
+        let _ = self.cmd_tx.send(Command::Approve { id, approved });
+    }
+
+    /// 回答循环中等待的问题（AskUser）
+    pub fn send_answer(&self, id: String, text: String) {
+        let _ = self.cmd_tx.send(Command::Answer { id, text });
+    }
 }
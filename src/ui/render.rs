@@ -2,7 +2,7 @@
 //!
 //! 根据 UiState（phase、history、error）与 input_buffer 绘制：标题栏显示 phase，
 //! 主体为对话历史（按角色着色、工具结果折叠、按宽度换行），底部为现代化输入框（占位符、圆角、
-//! 智能体/模型选择器、发送按钮）。
+//! 智能体/模型选择器、发送按钮）；有待审批的工具调用时输入区改为显示审批提示。
 
 use ratatui::{
     layout::{Constraint, Direction, Layout},
@@ -141,7 +141,7 @@ pub fn draw(
             Role::System => ("Sys ", Color::Gray),
             Role::Tool => ("🔧  ", Color::Yellow),
         };
-        let display_text = truncate_for_display(&m.content);
+        let display_text = truncate_for_display(&m.transcript_text());
         let wrapped = wrap_text(&display_text, content_width.max(40));
         for (i, line) in wrapped.into_iter().enumerate() {
             let pref = if i == 0 { prefix } else { "    " };
@@ -176,13 +176,21 @@ pub fn draw(
 
     let input_area = chunks[1];
 
-    let border_color = if state.error_message.is_some() {
+    let border_color = if state.pending_approval.is_some() || state.pending_question.is_some() {
+        Color::Yellow
+    } else if state.error_message.is_some() {
         Color::Red
     } else {
         Color::Rgb(100, 116, 139) // 浅灰
     };
 
-    let hint = " Enter 发送 │ Tab 切换 │ ↑↓ 选择 │ Ctrl+Q 退出 ";
+    let hint = if state.pending_approval.is_some() {
+        " y 批准 │ n 拒绝 │ Ctrl+Q 退出 "
+    } else if state.pending_question.is_some() {
+        " Enter 回答 │ Ctrl+Q 退出 "
+    } else {
+        " Enter 发送 │ Tab 切换 │ ↑↓ 选择 │ Ctrl+Q 退出 "
+    };
     let input_block = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
@@ -198,15 +206,33 @@ pub fn draw(
         (chunks[0], chunks[1])
     };
 
-    let display_text = if input_buffer.is_empty() && !state.input_locked {
-        Span::styled("随便问点什么...", Style::default().fg(Color::DarkGray))
+    // 待审批的工具调用占用输入区，显示工具、风险等级与参数
+    let display_text = if let Some(ref req) = state.pending_approval {
+        Text::from(vec![
+            Line::from(Span::styled(
+                format!("⚠ 工具 {} ({:?}) 需要批准", req.tool, req.risk),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::raw(req.args.to_string())),
+        ])
+    } else if let Some(ref q) = state.pending_question {
+        // 等待回答：问题显示在输入内容上方
+        Text::from(vec![
+            Line::from(Span::styled(
+                format!("? {}", q.question),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::raw(input_buffer)),
+        ])
+    } else if input_buffer.is_empty() && !state.input_locked {
+        Text::from(Span::styled("随便问点什么...", Style::default().fg(Color::DarkGray)))
     } else {
-        Span::raw(input_buffer)
+        Text::from(Span::raw(input_buffer))
     };
 
-    let input = Paragraph::new(Text::from(display_text))
+    let input = Paragraph::new(display_text)
         .wrap(Wrap { trim: false })
-        .style(if state.input_locked {
+        .style(if state.input_locked && state.pending_approval.is_none() {
             Style::default().fg(Color::DarkGray)
         } else {
             Style::default()
@@ -5,6 +5,10 @@
 use std::collections::HashMap;
 #[cfg(feature = "gateway")]
 use crate::gateway::BackgroundTask;
+use crate::workflow::definition::INPUT_NODE_ID;
+use crate::workflow::expr::Expression;
+#[cfg(feature = "gateway")]
+use crate::workflow::templates;
 use crate::workflow::types::*;
 
 /// 工作流构建器
@@ -14,6 +18,8 @@ pub struct WorkflowBuilder {
     description: Option<String>,
     user_id: String,
     session_id: Option<String>,
+    definition_id: Option<String>,
+    input: Option<serde_json::Value>,
     tasks: HashMap<TaskId, WorkflowTask>,
 }
 
@@ -26,10 +32,21 @@ impl WorkflowBuilder {
             description: None,
             user_id: String::new(),
             session_id: None,
+            definition_id: None,
+            input: None,
             tasks: HashMap::new(),
         }
     }
 
+    /// 由内置模板创建构建器：`params` 按模板参数补全默认值后作为运行输入
+    #[cfg(feature = "gateway")]
+    pub fn from_template(template_id: &str, user_id: &str, params: serde_json::Value) -> Result<Self, WorkflowError> {
+        let def = templates::get(template_id)
+            .ok_or_else(|| WorkflowError::InvalidConfiguration(format!("unknown template '{}'", template_id)))?;
+        let input = def.resolve_input(params)?;
+        Ok(def.to_builder(user_id)?.input(input))
+    }
+
     /// 设置描述
     pub fn description(mut self, desc: impl Into<String>) -> Self {
         self.description = Some(desc.into());
@@ -48,6 +65,18 @@ impl WorkflowBuilder {
         self
     }
 
+    /// 设置来源定义 ID
+    pub fn definition_id(mut self, definition_id: impl Into<String>) -> Self {
+        self.definition_id = Some(definition_id.into());
+        self
+    }
+
+    /// 设置运行输入（表达式与占位符中的 `$.input`）
+    pub fn input(mut self, input: serde_json::Value) -> Self {
+        self.input = Some(input);
+        self
+    }
+
     /// 添加任务
     #[cfg(feature = "gateway")]
     pub fn task(mut self, id: impl Into<TaskId>, task: BackgroundTask) -> Self {
@@ -57,7 +86,50 @@ impl WorkflowBuilder {
             definition: TaskDefinition::Simple(Box::new(task)),
             dependencies: TaskDependencies::None,
             fallback: None,
+            compensation: None,
+            state: TaskState::Waiting,
+            retry: RetryPolicy::default(),
+            idempotency_key: None,
+        });
+        self
+    }
+
+    /// 添加并行任务组：分支并发执行，按 `join` 汇合，节点输出为各分支结果
+    #[cfg(feature = "gateway")]
+    pub fn parallel(mut self, id: impl Into<TaskId>, tasks: Vec<BackgroundTask>, join: JoinStrategy) -> Self {
+        let id = id.into();
+        self.tasks.insert(id.clone(), WorkflowTask {
+            id,
+            definition: TaskDefinition::Parallel {
+                tasks: tasks.into_iter().map(Box::new).collect(),
+                join,
+            },
+            dependencies: TaskDependencies::None,
+            fallback: None,
+            compensation: None,
+            state: TaskState::Waiting,
+            retry: RetryPolicy::default(),
+            idempotency_key: None,
+        });
+        self
+    }
+
+    /// 添加子工作流节点：执行时以新的子运行执行 `workflow`，`input`（支持占位符）作为其 `$.input`，
+    /// 节点输出为子工作流各任务输出组成的对象
+    pub fn sub_workflow(mut self, id: impl Into<TaskId>, workflow: Workflow, input: serde_json::Value) -> Self {
+        let id = id.into();
+        self.tasks.insert(id.clone(), WorkflowTask {
+            id,
+            definition: TaskDefinition::SubWorkflow {
+                workflow: Box::new(workflow),
+                input,
+            },
+            dependencies: TaskDependencies::None,
+            fallback: None,
+            compensation: None,
             state: TaskState::Waiting,
+            retry: RetryPolicy::default(),
+            idempotency_key: None,
         });
         self
     }
@@ -89,6 +161,77 @@ impl WorkflowBuilder {
         self
     }
 
+    /// 设置汇合依赖：扇出的多个分支按策略汇合到 `task_id`
+    ///
+    /// `WaitAll` 等同 [`depends_on_all`](Self::depends_on_all)，`FirstSuccess` 等同
+    /// [`depends_on_any`](Self::depends_on_any)；汇合成功后，未计入的失败分支不会使工作流失败。
+    pub fn join(mut self, task_id: impl Into<TaskId>, deps: Vec<TaskId>, strategy: JoinStrategy) -> Self {
+        let id = task_id.into();
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.dependencies = match strategy {
+                JoinStrategy::WaitAll => TaskDependencies::All(deps),
+                JoinStrategy::FirstSuccess => TaskDependencies::Any(deps),
+                JoinStrategy::Quorum(min) => TaskDependencies::Quorum { task_ids: deps, min },
+            };
+        }
+        self
+    }
+
+    /// 设置条件依赖：`source` 结束且 `predicate` 满足时执行，否则跳过
+    pub fn condition(
+        mut self,
+        task_id: impl Into<TaskId>,
+        source: impl Into<TaskId>,
+        predicate: ConditionPredicate,
+    ) -> Self {
+        let id = task_id.into();
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.dependencies = TaskDependencies::Condition { task_id: source.into(), predicate };
+        }
+        self
+    }
+
+    /// 条件分支：`source` 完成后对表达式求值，为真执行 `then_task`，否则执行 `else_task`；
+    /// 未走到的分支及其下游（顺序 / AND 依赖）被跳过
+    pub fn branch(
+        self,
+        source: impl Into<TaskId>,
+        expression: impl Into<String>,
+        then_task: impl Into<TaskId>,
+        else_task: impl Into<TaskId>,
+    ) -> Self {
+        let source = source.into();
+        let expression = expression.into();
+        let negated = format!("!({})", expression);
+        self.condition(then_task, source.clone(), ConditionPredicate::Expression(expression))
+            .condition(else_task, source, ConditionPredicate::Expression(negated))
+    }
+
+    /// 设置重试与超时
+    pub fn with_retry(mut self, task_id: impl Into<TaskId>, retry: RetryPolicy) -> Self {
+        let id = task_id.into();
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.retry = retry;
+        }
+        self
+    }
+
+    /// 设置幂等键（支持 `{{$.task.field}}` 占位符）
+    pub fn with_idempotency_key(mut self, task_id: impl Into<TaskId>, key: impl Into<String>) -> Self {
+        let id = task_id.into();
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.idempotency_key = Some(key.into());
+        }
+        self
+    }
+
+    /// 标记任务有副作用：以 `工作流 ID:任务 ID` 为幂等键，恢复运行时不重复执行
+    pub fn side_effecting(self, task_id: impl Into<TaskId>) -> Self {
+        let id = task_id.into();
+        let key = format!("{}:{}", self.id, id);
+        self.with_idempotency_key(id, key)
+    }
+
     /// 设置失败备用任务
     pub fn with_fallback(mut self, task_id: impl Into<TaskId>, fallback_id: TaskId) -> Self {
         let id = task_id.into();
@@ -98,11 +241,27 @@ impl WorkflowBuilder {
         self
     }
 
+    /// 设置补偿任务：`task_id` 成功后若工作流最终失败，各补偿任务按完成的逆序依次执行
+    pub fn with_compensation(mut self, task_id: impl Into<TaskId>, compensation_id: TaskId) -> Self {
+        let id = task_id.into();
+        if let Some(task) = self.tasks.get_mut(&id) {
+            task.compensation = Some(compensation_id);
+        }
+        self
+    }
+
     /// 构建工作流
     pub fn build(self) -> Result<Workflow, WorkflowError> {
         if self.user_id.is_empty() {
             return Err(WorkflowError::InvalidConfiguration("user_id is required".to_string()));
         }
+        self.validate_joins()?;
+        self.validate_expressions()?;
+
+        let mut outputs = HashMap::new();
+        if let Some(input) = self.input {
+            outputs.insert(INPUT_NODE_ID.to_string(), input);
+        }
 
         Ok(Workflow {
             id: self.id,
@@ -111,12 +270,51 @@ impl WorkflowBuilder {
             user_id: self.user_id,
             session_id: self.session_id,
             tasks: self.tasks,
+            outputs,
+            errors: HashMap::new(),
+            definition_id: self.definition_id,
+            parent: None,
+            children: HashMap::new(),
+            completed_order: Vec::new(),
+            compensations: Vec::new(),
             status: WorkflowStatus::Created,
             created_at: chrono::Utc::now().timestamp_millis(),
             started_at: None,
             completed_at: None,
         })
     }
+
+    /// 校验汇合策略：法定数不超过分支数，并行任务组非空
+    fn validate_joins(&self) -> Result<(), WorkflowError> {
+        for (task_id, task) in &self.tasks {
+            let result = match (&task.dependencies, &task.definition) {
+                (TaskDependencies::Quorum { task_ids, min }, _) => {
+                    JoinStrategy::Quorum(*min).validate(task_ids.len())
+                }
+                #[cfg(feature = "gateway")]
+                (_, TaskDefinition::Parallel { tasks, join }) => join.validate(tasks.len()),
+                _ => Ok(()),
+            };
+            result.map_err(|e| WorkflowError::InvalidConfiguration(format!("task {}: {}", task_id, e)))?;
+        }
+        Ok(())
+    }
+
+    /// 校验条件表达式：语法正确，且只引用工作流中的任务
+    fn validate_expressions(&self) -> Result<(), WorkflowError> {
+        for (task_id, task) in &self.tasks {
+            let TaskDependencies::Condition { predicate: ConditionPredicate::Expression(source), .. } = &task.dependencies
+            else {
+                continue;
+            };
+            let invalid = |message: String| WorkflowError::InvalidExpression { task_id: task_id.clone(), message };
+            let expr = Expression::parse(source).map_err(|e| invalid(e.to_string()))?;
+            if let Some(unknown) = expr.task_refs().into_iter().find(|id| !self.tasks.contains_key(id)) {
+                return Err(invalid(format!("unknown task '{}'", unknown)));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -142,6 +340,55 @@ mod tests {
         assert!(matches!(workflow.tasks.get("task2").unwrap().dependencies, TaskDependencies::Sequential(_)));
     }
 
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_branch_validates_expression() {
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+        let build = |expr: &str| {
+            WorkflowBuilder::new("Branch")
+                .user_id("user1".to_string())
+                .task("check", task("Check"))
+                .task("fix", task("Fix"))
+                .task("ship", task("Ship"))
+                .branch("check", expr, "fix", "ship")
+                .build()
+        };
+
+        let workflow = build("$.check.issues > 0").unwrap();
+        assert!(matches!(
+            &workflow.tasks["ship"].dependencies,
+            TaskDependencies::Condition { predicate: ConditionPredicate::Expression(e), .. } if e == "!($.check.issues > 0)"
+        ));
+        assert!(matches!(build("$.check.issues >"), Err(WorkflowError::InvalidExpression { .. })));
+        assert!(matches!(build("$.other.ok"), Err(WorkflowError::InvalidExpression { .. })));
+    }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_join_strategies() {
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+        let build = |strategy: JoinStrategy| {
+            WorkflowBuilder::new("Join")
+                .user_id("user1".to_string())
+                .task("a", task("A"))
+                .task("b", task("B"))
+                .task("merge", task("Merge"))
+                .join("merge", vec!["a".to_string(), "b".to_string()], strategy)
+                .build()
+        };
+
+        let workflow = build(JoinStrategy::Quorum(2)).unwrap();
+        assert!(matches!(workflow.tasks["merge"].dependencies, TaskDependencies::Quorum { min: 2, .. }));
+        assert!(matches!(build(JoinStrategy::FirstSuccess).unwrap().tasks["merge"].dependencies, TaskDependencies::Any(_)));
+        assert!(matches!(build(JoinStrategy::Quorum(3)), Err(WorkflowError::InvalidConfiguration(_))));
+
+        let empty = WorkflowBuilder::new("Empty")
+            .user_id("user1".to_string())
+            .parallel("fan", Vec::new(), JoinStrategy::WaitAll)
+            .build();
+        assert!(empty.is_err());
+    }
+
     #[test]
     fn test_build_without_user_id_fails() {
         let result = WorkflowBuilder::new("Test")
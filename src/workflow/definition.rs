@@ -0,0 +1,696 @@
+//! 声明式工作流定义
+//!
+//! 从 TOML / YAML / JSON 文件读取节点与边，转为 [`WorkflowBuilder`]，供不写 Rust 的用户编排自动化：
+//!
+//! ```toml
+//! name = "每日简报"
+//! assistant_id = "default"
+//!
+//! [[nodes]]
+//! id = "fetch"
+//! type = "tool"
+//! tool = "http_fetch"
+//! args = { url = "https://example.com/feed.json" }
+//! retries = 2
+//! timeout_secs = 30
+//!
+//! [[nodes]]
+//! id = "summary"
+//! type = "llm"
+//! prompt = "总结：{{$.fetch}}"
+//!
+//! [[edges]]
+//! from = "fetch"
+//! to = "summary"
+//! when = "len($.fetch) > 0"
+//! ```
+//!
+//! 节点类型：`tool`（直接调用工具）、`llm`（单次模型调用）、`agent`（助手完整对话轮）、
+//! `workflow`（以子运行执行另一个定义或内置模板，`input` 映射为其 `$.input`）。
+//! `[[params]]` 声明运行输入的参数：有 `default` 的可省略，否则运行时必须提供。
+//! 多条入边按节点的 `join`（`"wait_all"` / `"first_success"` / `{ quorum = 2 }`）汇合；
+//! 带 `when` 的边为条件边，必须是该节点唯一的入边。运行时输入可用 `$.input` 引用。
+//! 有副作用的节点设置 `side_effects = true`（或自定义 `idempotency_key`），进程重启恢复运行时不会重复执行。
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "gateway")]
+use crate::gateway::BackgroundTask;
+#[cfg(feature = "gateway")]
+use crate::workflow::builder::WorkflowBuilder;
+use crate::workflow::expr::Expression;
+use crate::workflow::types::*;
+
+/// 任务元数据中保存节点定义的键
+pub const NODE_METADATA_KEY: &str = "workflow_node";
+
+/// 运行时输入在表达式中的任务 ID（`$.input`），不可作为节点 ID
+pub const INPUT_NODE_ID: &str = "input";
+
+/// 支持的定义文件扩展名
+pub const DEFINITION_EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "json"];
+
+/// 工作流定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowDefinition {
+    /// 定义 ID，从文件加载时缺省为文件名
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// llm / agent 节点缺省使用的助手
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assistant_id: Option<String>,
+    /// 运行输入参数（`$.input.<name>`）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<ParamDefinition>,
+    pub nodes: Vec<NodeDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<EdgeDefinition>,
+}
+
+/// 运行输入参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 缺省值；未设置时为必填参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+}
+
+/// 节点定义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDefinition {
+    pub id: TaskId,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+    /// 失败后的重试次数
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    /// 单次执行超时（秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// 多条入边的汇合策略，缺省 wait_all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub join: Option<JoinStrategy>,
+    /// 失败时执行的备用节点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<TaskId>,
+    /// 补偿节点：本节点成功后若运行最终失败，按完成的逆序执行（如删除已建分支、撤销文件修改）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensate: Option<TaskId>,
+    /// 有副作用（发消息、写外部系统等）：以 `运行 ID:节点 ID` 为幂等键，恢复运行时不重复执行
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub side_effects: bool,
+    /// 自定义幂等键（支持占位符，如 `"invoice-{{$.input.order_id}}"`），设置即视为有副作用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// 节点类型；字符串字段支持 `{{$.node.field}}` 占位符
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeKind {
+    /// 直接调用工具
+    Tool {
+        tool: String,
+        #[serde(default)]
+        args: serde_json::Value,
+    },
+    /// 单次模型调用（不带工具）
+    Llm {
+        prompt: String,
+        /// [[llm.models]] 中的模型 id，缺省使用主模型
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+    },
+    /// 以助手身份执行一轮完整对话（可调用该助手的工具）
+    Agent {
+        instruction: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        assistant_id: Option<String>,
+    },
+    /// 子工作流：执行另一个工作流定义或内置模板，`input` 中的字符串支持占位符，作为其 `$.input`
+    Workflow {
+        workflow: String,
+        #[serde(default)]
+        input: serde_json::Value,
+    },
+}
+
+impl NodeKind {
+    /// 类型名（tool / llm / agent）
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            NodeKind::Tool { .. } => "tool",
+            NodeKind::Llm { .. } => "llm",
+            NodeKind::Agent { .. } => "agent",
+            NodeKind::Workflow { .. } => "workflow",
+        }
+    }
+
+    /// 任务指令文本（工具节点为工具名）
+    fn instruction(&self) -> &str {
+        match self {
+            NodeKind::Tool { tool, .. } => tool,
+            NodeKind::Llm { prompt, .. } => prompt,
+            NodeKind::Agent { instruction, .. } => instruction,
+            NodeKind::Workflow { workflow, .. } => workflow,
+        }
+    }
+}
+
+/// 边定义：`from` 结束后执行 `to`；`when` 为条件表达式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeDefinition {
+    pub from: TaskId,
+    pub to: TaskId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+}
+
+impl WorkflowDefinition {
+    /// 按扩展名（toml / yaml / yml / json）解析定义文件；`id` 缺省为文件名
+    pub fn load(path: &Path) -> Result<Self, WorkflowError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let mut def = Self::parse(&content, ext)?;
+        if def.id.is_empty() {
+            def.id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+        }
+        Ok(def)
+    }
+
+    /// 解析定义文本，`format` 为 toml / yaml / yml / json
+    pub fn parse(content: &str, format: &str) -> Result<Self, WorkflowError> {
+        match format {
+            "toml" => toml::from_str(content).map_err(|e| invalid(e.to_string())),
+            "json" => serde_json::from_str(content).map_err(|e| invalid(e.to_string())),
+            "yaml" | "yml" => config::Config::builder()
+                .add_source(config::File::from_str(content, config::FileFormat::Yaml))
+                .build()
+                .and_then(|c| c.try_deserialize())
+                .map_err(|e| invalid(e.to_string())),
+            other => Err(invalid(format!("unsupported workflow format '{}'", other))),
+        }
+    }
+
+    /// 序列化为 TOML（API 保存时使用）
+    pub fn to_toml(&self) -> Result<String, WorkflowError> {
+        toml::to_string_pretty(self).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// 校验：节点 ID 唯一、边与备用节点引用存在、无环、条件边与汇合策略合法
+    pub fn validate(&self) -> Result<(), WorkflowError> {
+        if self.name.trim().is_empty() {
+            return Err(invalid("name is required"));
+        }
+        if self.nodes.is_empty() {
+            return Err(invalid("workflow has no nodes"));
+        }
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if node.id.is_empty() || !node.id.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                return Err(invalid(format!("invalid node id '{}'", node.id)));
+            }
+            if node.id == INPUT_NODE_ID {
+                return Err(invalid(format!("'{}' is reserved for the run input", INPUT_NODE_ID)));
+            }
+            if !ids.insert(node.id.as_str()) {
+                return Err(invalid(format!("duplicate node id '{}'", node.id)));
+            }
+            match &node.kind {
+                NodeKind::Tool { tool, .. } if tool.trim().is_empty() => {
+                    return Err(invalid(format!("node {}: tool is required", node.id)));
+                }
+                NodeKind::Workflow { workflow, input } => {
+                    if workflow.trim().is_empty() {
+                        return Err(invalid(format!("node {}: workflow is required", node.id)));
+                    }
+                    if !(input.is_null() || input.is_object()) {
+                        return Err(invalid(format!("node {}: input must be a table", node.id)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut params = HashSet::new();
+        for param in &self.params {
+            if param.name.is_empty() || !param.name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(invalid(format!("invalid parameter name '{}'", param.name)));
+            }
+            if !params.insert(param.name.as_str()) {
+                return Err(invalid(format!("duplicate parameter '{}'", param.name)));
+            }
+        }
+        for node in &self.nodes {
+            if let Some(fallback) = &node.fallback {
+                if !ids.contains(fallback.as_str()) || fallback == &node.id {
+                    return Err(invalid(format!("node {}: unknown fallback '{}'", node.id, fallback)));
+                }
+            }
+            if let Some(compensate) = &node.compensate {
+                if !ids.contains(compensate.as_str()) || compensate == &node.id {
+                    return Err(invalid(format!("node {}: unknown compensation '{}'", node.id, compensate)));
+                }
+                // 补偿节点只由补偿阶段调度，不能再参与依赖边
+                if self.edges.iter().any(|e| &e.from == compensate || &e.to == compensate) {
+                    return Err(invalid(format!("node {}: compensation node cannot have edges", compensate)));
+                }
+            }
+        }
+
+        let incoming = self.incoming();
+        for edge in &self.edges {
+            for end in [&edge.from, &edge.to] {
+                if !ids.contains(end.as_str()) {
+                    return Err(invalid(format!(
+                        "edge {} -> {}: unknown node '{}'",
+                        edge.from, edge.to, end
+                    )));
+                }
+            }
+            if let Some(when) = &edge.when {
+                if incoming[edge.to.as_str()].len() > 1 {
+                    return Err(invalid(format!(
+                        "node {}: a conditional edge must be its only incoming edge",
+                        edge.to
+                    )));
+                }
+                let expr = Expression::parse(when).map_err(|e| WorkflowError::InvalidExpression {
+                    task_id: edge.to.clone(),
+                    message: e.to_string(),
+                })?;
+                if let Some(unknown) = expr
+                    .task_refs()
+                    .into_iter()
+                    .find(|id| id != INPUT_NODE_ID && !ids.contains(id.as_str()))
+                {
+                    return Err(WorkflowError::InvalidExpression {
+                        task_id: edge.to.clone(),
+                        message: format!("unknown task '{}'", unknown),
+                    });
+                }
+            }
+        }
+        for node in &self.nodes {
+            if let Some(join) = &node.join {
+                join.validate(incoming[node.id.as_str()].len())
+                    .map_err(|e| invalid(format!("node {}: {}", node.id, e)))?;
+            }
+        }
+        self.check_acyclic(&incoming)
+    }
+
+    /// 按参数声明补全运行输入：缺省的参数取默认值，必填参数缺失时报错；未声明参数时原样返回
+    pub fn resolve_input(&self, input: serde_json::Value) -> Result<serde_json::Value, WorkflowError> {
+        if self.params.is_empty() {
+            return Ok(input);
+        }
+        let mut input = match input {
+            serde_json::Value::Null => serde_json::Map::new(),
+            serde_json::Value::Object(map) => map,
+            _ => return Err(invalid("input must be an object")),
+        };
+        for param in &self.params {
+            if input.contains_key(&param.name) {
+                continue;
+            }
+            match &param.default {
+                Some(default) => {
+                    input.insert(param.name.clone(), default.clone());
+                }
+                None => return Err(invalid(format!("missing parameter '{}'", param.name))),
+            }
+        }
+        Ok(serde_json::Value::Object(input))
+    }
+
+    /// 节点 ID -> 入边
+    fn incoming(&self) -> HashMap<&str, Vec<&EdgeDefinition>> {
+        let mut incoming: HashMap<&str, Vec<&EdgeDefinition>> =
+            self.nodes.iter().map(|n| (n.id.as_str(), Vec::new())).collect();
+        for edge in &self.edges {
+            incoming.entry(edge.to.as_str()).or_default().push(edge);
+        }
+        incoming
+    }
+
+    fn check_acyclic(&self, incoming: &HashMap<&str, Vec<&EdgeDefinition>>) -> Result<(), WorkflowError> {
+        let mut degree: HashMap<&str, usize> = incoming.iter().map(|(id, edges)| (*id, edges.len())).collect();
+        let mut queue: VecDeque<&str> = degree.iter().filter(|(_, d)| **d == 0).map(|(id, _)| *id).collect();
+        let mut visited = 0;
+        while let Some(id) = queue.pop_front() {
+            visited += 1;
+            for edge in self.edges.iter().filter(|e| e.from == id) {
+                let d = degree.get_mut(edge.to.as_str()).expect("validated edge");
+                *d -= 1;
+                if *d == 0 {
+                    queue.push_back(edge.to.as_str());
+                }
+            }
+        }
+        if visited == degree.len() {
+            Ok(())
+        } else {
+            Err(WorkflowError::CyclicDependency)
+        }
+    }
+
+    /// 转为工作流构建器：每个节点是一个任务，节点定义存于任务元数据 [`NODE_METADATA_KEY`]；
+    /// `workflow` 节点只能引用内置模板，引用其它定义用 [`to_builder_with`](Self::to_builder_with)
+    #[cfg(feature = "gateway")]
+    pub fn to_builder(&self, user_id: &str) -> Result<WorkflowBuilder, WorkflowError> {
+        self.to_builder_with(user_id, &crate::workflow::templates::get)
+    }
+
+    /// 同 [`to_builder`](Self::to_builder)，`workflow` 节点引用的定义由 `resolve` 按 ID 查找
+    #[cfg(feature = "gateway")]
+    pub fn to_builder_with(
+        &self,
+        user_id: &str,
+        resolve: &dyn Fn(&str) -> Option<WorkflowDefinition>,
+    ) -> Result<WorkflowBuilder, WorkflowError> {
+        self.build_nested(user_id, resolve, &mut vec![self.id.clone()])
+    }
+
+    /// `stack` 为正在构建的定义链，用于发现相互引用
+    #[cfg(feature = "gateway")]
+    fn build_nested(
+        &self,
+        user_id: &str,
+        resolve: &dyn Fn(&str) -> Option<WorkflowDefinition>,
+        stack: &mut Vec<String>,
+    ) -> Result<WorkflowBuilder, WorkflowError> {
+        self.validate()?;
+        let incoming = self.incoming();
+        let mut builder = WorkflowBuilder::new(self.name.clone())
+            .user_id(user_id.to_string())
+            .definition_id(self.id.clone());
+        if let Some(description) = &self.description {
+            builder = builder.description(description.clone());
+        }
+
+        for node in &self.nodes {
+            if let NodeKind::Workflow { workflow, input } = &node.kind {
+                if stack.contains(workflow) {
+                    return Err(invalid(format!(
+                        "node {}: workflow '{}' references itself ({} -> {})",
+                        node.id,
+                        workflow,
+                        stack.join(" -> "),
+                        workflow
+                    )));
+                }
+                let mut child_def =
+                    resolve(workflow).ok_or_else(|| invalid(format!("node {}: unknown workflow '{}'", node.id, workflow)))?;
+                if child_def.id.is_empty() {
+                    child_def.id = workflow.clone();
+                }
+                // 缺省参数在构建时补全，必填参数缺失即报错
+                let input = child_def
+                    .resolve_input(input.clone())
+                    .map_err(|e| match e {
+                        WorkflowError::InvalidConfiguration(message) => invalid(format!("node {}: {}", node.id, message)),
+                        other => other,
+                    })?;
+                stack.push(workflow.clone());
+                let child = child_def.build_nested(user_id, resolve, stack)?.build()?;
+                stack.pop();
+                builder = builder.sub_workflow(node.id.clone(), child, input);
+                if let Some(fallback) = &node.fallback {
+                    builder = builder.with_fallback(node.id.clone(), fallback.clone());
+                }
+                if let Some(compensate) = &node.compensate {
+                    builder = builder.with_compensation(node.id.clone(), compensate.clone());
+                }
+                continue;
+            }
+            let mut task = BackgroundTask::new(user_id.to_string(), node.kind.instruction().to_string());
+            let mut metadata = serde_json::json!({ NODE_METADATA_KEY: node.kind });
+            let assistant_id = match &node.kind {
+                NodeKind::Agent {
+                    assistant_id: Some(id), ..
+                } => Some(id),
+                _ => self.assistant_id.as_ref(),
+            };
+            if let Some(assistant_id) = assistant_id {
+                metadata["assistant_id"] = serde_json::json!(assistant_id);
+            }
+            task.metadata = Some(metadata);
+            builder = builder.task(node.id.clone(), task).with_retry(
+                node.id.clone(),
+                RetryPolicy {
+                    retries: node.retries,
+                    timeout_secs: node.timeout_secs,
+                },
+            );
+            if let Some(fallback) = &node.fallback {
+                builder = builder.with_fallback(node.id.clone(), fallback.clone());
+            }
+            if let Some(compensate) = &node.compensate {
+                builder = builder.with_compensation(node.id.clone(), compensate.clone());
+            }
+            if let Some(key) = &node.idempotency_key {
+                builder = builder.with_idempotency_key(node.id.clone(), key.clone());
+            } else if node.side_effects {
+                builder = builder.side_effecting(node.id.clone());
+            }
+        }
+
+        for node in &self.nodes {
+            let edges = &incoming[node.id.as_str()];
+            builder = match edges.as_slice() {
+                [] => builder,
+                [edge] => match &edge.when {
+                    Some(when) => builder.condition(
+                        node.id.clone(),
+                        edge.from.clone(),
+                        ConditionPredicate::Expression(when.clone()),
+                    ),
+                    None => builder.sequential(edge.from.clone(), node.id.clone()),
+                },
+                many => builder.join(
+                    node.id.clone(),
+                    many.iter().map(|e| e.from.clone()).collect(),
+                    node.join.unwrap_or(JoinStrategy::WaitAll),
+                ),
+            };
+        }
+        Ok(builder)
+    }
+}
+
+/// 任务元数据中的节点定义（由 [`WorkflowDefinition::to_builder`] 构建的任务才有）
+#[cfg(feature = "gateway")]
+pub fn node_kind_of(task: &BackgroundTask) -> Option<NodeKind> {
+    let value = task.metadata.as_ref()?.get(NODE_METADATA_KEY)?;
+    serde_json::from_value(value.clone()).ok()
+}
+
+fn invalid(message: impl Into<String>) -> WorkflowError {
+    WorkflowError::InvalidConfiguration(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAILY: &str = r#"
+name = "Daily brief"
+assistant_id = "default"
+
+[[nodes]]
+id = "fetch"
+type = "tool"
+tool = "http_fetch"
+args = { url = "https://example.com/feed.json" }
+retries = 2
+timeout_secs = 30
+
+[[nodes]]
+id = "summary"
+type = "llm"
+prompt = "Summarize: {{$.fetch}}"
+
+[[nodes]]
+id = "empty"
+type = "agent"
+instruction = "Tell the user there is nothing new"
+assistant_id = "media"
+
+[[nodes]]
+id = "notify"
+type = "agent"
+instruction = "Send the brief"
+join = "first_success"
+side_effects = true
+
+[[edges]]
+from = "fetch"
+to = "summary"
+when = "len($.fetch.items) > 0"
+
+[[edges]]
+from = "fetch"
+to = "empty"
+when = "len($.fetch.items) == 0"
+
+[[edges]]
+from = "summary"
+to = "notify"
+
+[[edges]]
+from = "empty"
+to = "notify"
+"#;
+
+    #[test]
+    fn test_parse_and_validate_definition() {
+        let def = WorkflowDefinition::parse(DAILY, "toml").unwrap();
+        def.validate().unwrap();
+        assert_eq!(def.nodes.len(), 4);
+        assert_eq!(def.nodes[0].retries, 2);
+        assert!(
+            matches!(&def.nodes[0].kind, NodeKind::Tool { tool, args } if tool == "http_fetch" && args["url"].is_string())
+        );
+        assert_eq!(def.nodes[3].join, Some(JoinStrategy::FirstSuccess));
+
+        // TOML 往返
+        let again = WorkflowDefinition::parse(&def.to_toml().unwrap(), "toml").unwrap();
+        assert_eq!(again.nodes, def.nodes);
+        assert_eq!(again.edges, def.edges);
+
+        let yaml = "name: Y\nnodes:\n  - id: a\n    type: llm\n    prompt: hi\n  - id: b\n    type: tool\n    tool: echo\n    args:\n      text: \"{{$.a}}\"\n    join:\n      quorum: 1\nedges:\n  - from: a\n    to: b\n";
+        let def = WorkflowDefinition::parse(yaml, "yaml").unwrap();
+        def.validate().unwrap();
+        assert_eq!(def.nodes[1].join, Some(JoinStrategy::Quorum(1)));
+        assert!(matches!(&def.nodes[1].kind, NodeKind::Tool { args, .. } if args["text"] == "{{$.a}}"));
+
+        let mut cyclic = def.clone();
+        cyclic.edges.push(EdgeDefinition {
+            from: "b".into(),
+            to: "a".into(),
+            when: None,
+        });
+        assert!(matches!(cyclic.validate(), Err(WorkflowError::CyclicDependency)));
+
+        let mut bad = def.clone();
+        bad.edges[0].when = Some("$.missing > 1".into());
+        assert!(matches!(bad.validate(), Err(WorkflowError::InvalidExpression { .. })));
+
+        // 补偿节点须存在且不参与依赖边
+        let mut compensated = def.clone();
+        compensated.nodes[0].compensate = Some("b".into());
+        assert!(compensated.validate().is_err());
+        let mut undo = compensated.nodes[0].clone();
+        undo.id = "undo".into();
+        undo.compensate = None;
+        compensated.nodes.push(undo);
+        compensated.nodes[0].compensate = Some("undo".into());
+        compensated.validate().unwrap();
+
+        let mut bad = def;
+        bad.nodes[1].join = Some(JoinStrategy::Quorum(2));
+        assert!(bad.validate().is_err());
+
+        // 仓库自带的示例
+        let example = WorkflowDefinition::load(Path::new("config/workflows/research-brief.toml")).unwrap();
+        assert_eq!(example.id, "research-brief");
+        example.validate().unwrap();
+    }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_definition_to_builder() {
+        let def = WorkflowDefinition::parse(DAILY, "toml").unwrap();
+        let workflow = def.to_builder("user1").unwrap().build().unwrap();
+        assert_eq!(workflow.definition_id.as_deref(), Some(""));
+        assert_eq!(
+            workflow.tasks["fetch"].retry,
+            RetryPolicy {
+                retries: 2,
+                timeout_secs: Some(30)
+            }
+        );
+        assert!(matches!(
+            workflow.tasks["summary"].dependencies,
+            TaskDependencies::Condition { .. }
+        ));
+        assert!(matches!(
+            workflow.tasks["notify"].dependencies,
+            TaskDependencies::Any(_)
+        ));
+
+        let TaskDefinition::Simple(task) = &workflow.tasks["empty"].definition else {
+            panic!("expected simple task");
+        };
+        assert_eq!(task.metadata.as_ref().unwrap()["assistant_id"], "media");
+        assert!(matches!(node_kind_of(task), Some(NodeKind::Agent { .. })));
+        assert_eq!(
+            workflow.tasks["notify"].idempotency_key,
+            Some(format!("{}:notify", workflow.id))
+        );
+        assert_eq!(workflow.tasks["summary"].idempotency_key, None);
+    }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_workflow_node_resolves_definitions() {
+        let parent = r#"
+name = "Weekly"
+
+[[nodes]]
+id = "report"
+type = "workflow"
+workflow = "research-report"
+input = { topic = "{{$.input.topic}}" }
+
+[[nodes]]
+id = "nested"
+type = "workflow"
+workflow = "inner"
+"#;
+        let inner = "name = \"Inner\"\n[[nodes]]\nid = \"loop\"\ntype = \"workflow\"\nworkflow = \"weekly\"\n";
+        let mut def = WorkflowDefinition::parse(parent, "toml").unwrap();
+        def.id = "weekly".to_string();
+        def.validate().unwrap();
+
+        // 内置模板缺省参数在构建时补全
+        let resolve = |id: &str| match id {
+            "inner" => Some(WorkflowDefinition::parse("name = \"Inner\"\n[[nodes]]\nid = \"a\"\ntype = \"llm\"\nprompt = \"hi\"\n", "toml").unwrap()),
+            other => crate::workflow::templates::get(other),
+        };
+        let workflow = def.to_builder_with("user1", &resolve).unwrap().build().unwrap();
+        let TaskDefinition::SubWorkflow { workflow: child, input } = &workflow.tasks["report"].definition else {
+            panic!("expected sub-workflow");
+        };
+        assert_eq!(child.definition_id.as_deref(), Some("research-report"));
+        assert_eq!(input["audience"], "技术团队");
+        assert_eq!(input["topic"], "{{$.input.topic}}");
+
+        // 相互引用与未知定义
+        let cyclic = |id: &str| (id == "inner").then(|| WorkflowDefinition::parse(inner, "toml").unwrap());
+        assert!(def.to_builder_with("user1", &cyclic).is_err());
+        assert!(def.to_builder("user1").is_err());
+    }
+}
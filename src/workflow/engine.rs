@@ -3,14 +3,27 @@
 //! 核心执行引擎，管理工作流生命周期和任务调度
 
 use std::collections::HashMap;
+#[cfg(feature = "gateway")]
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+#[cfg(feature = "gateway")]
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
 use async_trait::async_trait;
+#[cfg(feature = "gateway")]
+use futures_util::FutureExt;
+#[cfg(feature = "gateway")]
+use std::panic::AssertUnwindSafe;
 
 #[cfg(feature = "gateway")]
 use crate::gateway::{BackgroundTask, TaskQueue};
 use crate::workflow::types::*;
+#[cfg(feature = "gateway")]
+use crate::workflow::expr::{interpolate, parse_output};
+#[cfg(feature = "gateway")]
+use crate::workflow::definition::INPUT_NODE_ID;
 use crate::workflow::graph::WorkflowGraph;
+use crate::workflow::store::WorkflowStore;
 
 /// 工作流任务执行器 trait
 #[async_trait]
@@ -19,12 +32,25 @@ pub trait WorkflowTaskExecutor: Send + Sync {
     async fn execute(&self, task: &BackgroundTask) -> Result<String, String>;
 }
 
+/// 任务元数据中的幂等键（已代入占位符），执行器可转给外部系统（如 HTTP `Idempotency-Key` 头）
+pub const IDEMPOTENCY_METADATA_KEY: &str = "idempotency_key";
+
+/// 任务执行结束的回报：(工作流 ID, 任务 ID, 结果)
+type TaskCompletion = (WorkflowId, TaskId, Result<String, String>);
+
 /// 工作流引擎
 pub struct WorkflowEngine {
     #[cfg(feature = "gateway")]
     task_queue: Arc<TaskQueue>,
     workflows: RwLock<HashMap<WorkflowId, Workflow>>,
+    /// 运行中工作流的依赖图（入度随任务结束递减，结束后移除）
+    graphs: RwLock<HashMap<WorkflowId, WorkflowGraph>>,
     executor: Arc<dyn WorkflowTaskExecutor>,
+    /// 任务结束后回报结果；[`start`](Self::start) 取走接收端并自动推进工作流
+    completions: mpsc::UnboundedSender<TaskCompletion>,
+    completion_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<TaskCompletion>>>,
+    /// 运行状态与幂等日志持久化；未设置时只在内存中运行
+    store: Option<Arc<WorkflowStore>>,
 }
 
 #[cfg(feature = "gateway")]
@@ -34,11 +60,131 @@ impl WorkflowEngine {
         task_queue: Arc<TaskQueue>,
         executor: Arc<dyn WorkflowTaskExecutor>,
     ) -> Self {
+        let (completions, completion_rx) = mpsc::unbounded_channel();
         Self {
             task_queue,
             workflows: RwLock::new(HashMap::new()),
+            graphs: RwLock::new(HashMap::new()),
             executor,
+            completions,
+            completion_rx: std::sync::Mutex::new(Some(completion_rx)),
+            store: None,
+        }
+    }
+
+    /// 持久化运行状态：每次状态变化后保存，重启后可用 [`resume`](Self::resume) 继续
+    pub fn with_store(mut self, store: Arc<WorkflowStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    fn persist(&self, workflow: &Workflow) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(workflow) {
+                tracing::warn!(workflow = %workflow.id, "failed to persist workflow run: {}", e);
+            }
+        }
+    }
+
+    /// 恢复存储中未结束的运行（进程重启后调用），返回恢复的工作流 ID
+    ///
+    /// 已结束任务的状态与输出保持不变；中断时已提交、未回报结果的任务重新提交执行，
+    /// 带幂等键且已成功过的任务直接复用幂等日志中的结果。
+    pub async fn resume(&self) -> Result<Vec<WorkflowId>, WorkflowError> {
+        let Some(store) = &self.store else {
+            return Ok(Vec::new());
+        };
+        let runs = store.unfinished().map_err(|e| WorkflowError::Storage(e.to_string()))?;
+        let unfinished: HashSet<WorkflowId> = runs.iter().map(|w| w.id.clone()).collect();
+        let mut resumed = Vec::new();
+        for mut workflow in runs {
+            let workflow_id = workflow.id.clone();
+            if self.workflows.read().await.contains_key(&workflow_id) {
+                continue;
+            }
+            for task in workflow.tasks.values_mut() {
+                if !matches!(task.state, TaskState::Ready | TaskState::Pending | TaskState::Running) {
+                    continue;
+                }
+                // 子运行仍未结束的随子运行一起恢复，结束时回报；已结束的直接回报其结果
+                match workflow.children.get(&task.id) {
+                    Some(child_id) if task.state == TaskState::Running && unfinished.contains(child_id) => {}
+                    Some(child_id) if task.state == TaskState::Running => {
+                        match store.get(child_id).ok().flatten().filter(|c| c.status.is_finished()) {
+                            Some(child) => {
+                                let _ = self.completions.send((workflow_id.clone(), task.id.clone(), sub_workflow_result(&child)));
+                            }
+                            None => task.state = TaskState::Waiting,
+                        }
+                    }
+                    _ => task.state = TaskState::Waiting,
+                }
+            }
+            if workflow.status == WorkflowStatus::Compensating {
+                // 补偿阶段中断：从当前补偿任务继续（子运行未结束的等其回报）
+                let waiting_child = workflow.compensations.first()
+                    .and_then(|id| workflow.tasks.get(id))
+                    .is_some_and(|t| t.state == TaskState::Running);
+                tracing::info!(workflow = %workflow_id, pending = workflow.compensations.len(), "resuming workflow compensation");
+                self.workflows.write().await.insert(workflow_id.clone(), workflow);
+                if !waiting_child {
+                    self.run_compensation(&workflow_id).await;
+                }
+                resumed.push(workflow_id);
+                continue;
+            }
+            workflow.status = WorkflowStatus::Running;
+            workflow.started_at.get_or_insert_with(|| chrono::Utc::now().timestamp_millis());
+
+            // 按已结束任务重放依赖图
+            let mut graph = WorkflowGraph::new(&workflow.tasks);
+            let finished: Vec<(TaskId, TaskState)> = workflow.tasks.values()
+                .filter(|task| matches!(task.state, TaskState::Completed | TaskState::Failed | TaskState::Skipped))
+                .map(|task| (task.id.clone(), task.state))
+                .collect();
+            let mut to_submit = advance(&mut graph, &mut workflow, finished);
+            let states: HashMap<_, _> = workflow.tasks.iter()
+                .map(|(k, v)| (k.clone(), v.state))
+                .collect();
+            to_submit.extend(without_standby(&workflow, graph.get_ready_tasks(&states)));
+            // 失败任务的备用任务在中断前未执行完的，重新执行
+            let pending_fallbacks: Vec<TaskId> = workflow.tasks.values()
+                .filter(|task| task.state == TaskState::Failed)
+                .filter_map(|task| task.fallback.clone())
+                .filter(|id| workflow.tasks.get(id).is_some_and(|t| t.state == TaskState::Waiting))
+                .collect();
+            to_submit.extend(pending_fallbacks);
+
+            tracing::info!(workflow = %workflow_id, pending = to_submit.len(), "resuming workflow run");
+            self.persist(&workflow);
+            self.workflows.write().await.insert(workflow_id.clone(), workflow);
+            self.graphs.write().await.insert(workflow_id.clone(), graph);
+            for task_id in to_submit {
+                self.submit_task(&workflow_id, &task_id).await?;
+            }
+            self.check_completion(&workflow_id).await;
+            resumed.push(workflow_id);
         }
+        Ok(resumed)
+    }
+
+    /// 启动自动推进：任务执行结束后自动调用 [`on_task_completed`](Self::on_task_completed)。
+    /// 未启动时由调用方自行回报任务结果；重复调用无效
+    pub fn start(self: &Arc<Self>) {
+        let Some(mut rx) = self.completion_rx.lock().unwrap().take() else {
+            return;
+        };
+        let engine = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while let Some((workflow_id, task_id, result)) = rx.recv().await {
+                let Some(engine) = engine.upgrade() else {
+                    break;
+                };
+                if let Err(e) = engine.on_task_completed(&workflow_id, &task_id, result).await {
+                    tracing::warn!(workflow = %workflow_id, task = %task_id, "workflow completion failed: {}", e);
+                }
+            }
+        });
     }
 
     /// 提交工作流
@@ -66,7 +212,9 @@ impl WorkflowEngine {
             .map(|(k, v)| (k.clone(), v.state))
             .collect();
         
-        let ready_tasks = graph.get_ready_tasks(&states);
+        let ready_tasks = without_standby(workflow, graph.get_ready_tasks(&states));
+        self.graphs.write().await.insert(workflow_id.clone(), graph);
+        self.persist(workflow);
         
         drop(workflows);
         
@@ -86,18 +234,28 @@ impl WorkflowEngine {
         let mut workflows = self.workflows.write().await;
         let workflow = workflows.get_mut(workflow_id)
             .ok_or(WorkflowError::WorkflowNotFound)?;
+        let user_id = workflow.user_id.clone();
         
         let task = workflow.tasks.get_mut(task_id)
             .ok_or(WorkflowError::TaskNotFound)?;
         
         task.state = TaskState::Running;
+        let retry = task.retry;
+        let completions = self.completions.clone();
+        let (workflow_id, task_id) = (workflow_id.clone(), task_id.clone());
         
         if let TaskDefinition::Simple(bg_task) = &task.definition {
-            let bg_task = bg_task.clone();
-            let _workflow_id = workflow_id.clone();
-            let _task_id = task_id.clone();
+            let mut bg_task = interpolate_task(bg_task, &workflow.outputs);
+            let idempotency_key = task.idempotency_key.as_ref().map(|key| interpolate(key, &workflow.outputs));
+            if let Some(key) = &idempotency_key {
+                let metadata = bg_task.metadata.get_or_insert_with(|| serde_json::json!({}));
+                if let Some(metadata) = metadata.as_object_mut() {
+                    metadata.insert(IDEMPOTENCY_METADATA_KEY.to_string(), serde_json::json!(key));
+                }
+            }
             let queue = Arc::clone(&self.task_queue);
             let executor = Arc::clone(&self.executor);
+            let store = self.store.clone();
             
             tokio::spawn(async move {
                 let wrapper = BackgroundTask::new(
@@ -106,16 +264,88 @@ impl WorkflowEngine {
                 );
                 let submitted_id = queue.submit(wrapper).await;
                 
-                match executor.execute(&bg_task).await {
-                    Ok(result) => {
-                        queue.set_result(&submitted_id, result).await;
+                let journal = store.as_deref().zip(idempotency_key.as_deref());
+                let cached = journal.and_then(|(store, key)| {
+                    store.idempotent_result(key).unwrap_or_else(|e| {
+                        tracing::warn!(key, "idempotency lookup failed: {}", e);
+                        None
+                    })
+                });
+                let result = match cached {
+                    Some(output) => {
+                        tracing::debug!(workflow = %workflow_id, task = %task_id, "reusing idempotent result");
+                        Ok(output)
+                    }
+                    None => {
+                        let result = execute_with_retry(executor.as_ref(), &bg_task, retry).await;
+                        if let (Ok(output), Some((store, key))) = (&result, journal) {
+                            if let Err(e) = store.record_idempotent(key, &workflow_id, &task_id, output) {
+                                tracing::warn!(key, "failed to record idempotent result: {}", e);
+                            }
+                        }
+                        result
+                    }
+                };
+                match &result {
+                    Ok(output) => {
+                        queue.set_result(&submitted_id, output.clone()).await;
                     }
                     Err(error) => {
-                        queue.set_error(&submitted_id, error).await;
+                        queue.set_error(&submitted_id, error.clone()).await;
                     }
                 }
+                // 包装任务只在节点执行期间占位，结果已随 completions 交给引擎
+                queue.remove(&submitted_id).await;
+                let _ = completions.send((workflow_id, task_id, result));
             });
+        } else if let TaskDefinition::Parallel { tasks, join } = &task.definition {
+            let branches: Vec<BackgroundTask> =
+                tasks.iter().map(|t| interpolate_task(t, &workflow.outputs)).collect();
+            let join = *join;
+            let wrapper = BackgroundTask::new(
+                user_id,
+                format!("parallel {}: {} branches", task_id, branches.len()),
+            );
+            let queue = Arc::clone(&self.task_queue);
+            let executor = Arc::clone(&self.executor);
+            
+            tokio::spawn(async move {
+                let submitted_id = queue.submit(wrapper).await;
+                let result = run_parallel(executor, branches, join, retry).await;
+                match &result {
+                    Ok(output) => queue.set_result(&submitted_id, output.clone()).await,
+                    Err(error) => queue.set_error(&submitted_id, error.clone()).await,
+                }
+                queue.remove(&submitted_id).await;
+                let _ = completions.send((workflow_id, task_id, result));
+            });
+        } else if let TaskDefinition::SubWorkflow { workflow: template, input } = &task.definition {
+            // 每次执行都是新的子运行；幂等键中的模板 ID 换成子运行 ID，避免多次调用共用幂等结果
+            let mut child = (**template).clone();
+            child.id = format!("wf_{}", uuid::Uuid::new_v4());
+            for child_task in child.tasks.values_mut() {
+                if let Some(key) = child_task.idempotency_key.as_mut() {
+                    *key = key.replace(&template.id, &child.id);
+                }
+            }
+            child.user_id = user_id;
+            child.session_id = child.session_id.or_else(|| workflow.session_id.clone());
+            child.parent = Some((workflow_id.clone(), task_id.clone()));
+            let mut input = input.clone();
+            interpolate_value(&mut input, &workflow.outputs);
+            child.outputs.insert(INPUT_NODE_ID.to_string(), input);
+            workflow.children.insert(task_id.clone(), child.id.clone());
+            self.persist(workflow);
+            drop(workflows);
+            
+            tracing::debug!(workflow = %workflow_id, task = %task_id, child = %child.id, "starting sub-workflow");
+            self.workflows.write().await.insert(child.id.clone(), child.clone());
+            if let Err(e) = Box::pin(self.start_workflow(&child.id)).await {
+                let _ = self.completions.send((workflow_id, task_id, Err(format!("sub-workflow failed to start: {}", e))));
+            }
+            return Ok(());
         }
+        self.persist(workflow);
         
         Ok(())
     }
@@ -127,6 +357,47 @@ impl WorkflowEngine {
             .map(|w| w.status)
     }
 
+    /// 获取工作流运行快照（状态、各任务状态与输出）
+    pub async fn snapshot(&self, workflow_id: &WorkflowId) -> Option<WorkflowSnapshot> {
+        self.workflows.read().await
+            .get(workflow_id)
+            .map(WorkflowSnapshot::from)
+    }
+
+    /// 所有工作流的快照，新创建的在前
+    pub async fn list(&self) -> Vec<WorkflowSnapshot> {
+        let mut list: Vec<WorkflowSnapshot> = self.workflows.read().await
+            .values()
+            .map(WorkflowSnapshot::from)
+            .collect();
+        list.sort_by_key(|w| std::cmp::Reverse(w.created_at));
+        list
+    }
+
+    /// 只保留最近 `keep` 个已结束的工作流（存储中同样清理），返回内存中清理的数量
+    pub async fn prune_finished(&self, keep: usize) -> usize {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.prune_finished(keep) {
+                tracing::warn!("failed to prune stored workflow runs: {}", e);
+            }
+        }
+        let mut workflows = self.workflows.write().await;
+        let mut finished: Vec<(i64, WorkflowId)> = workflows
+            .values()
+            .filter(|w| w.status.is_finished())
+            .map(|w| (w.completed_at.unwrap_or(w.created_at), w.id.clone()))
+            .collect();
+        if finished.len() <= keep {
+            return 0;
+        }
+        finished.sort();
+        let remove = finished.len() - keep;
+        for (_, id) in finished.into_iter().take(remove) {
+            workflows.remove(&id);
+        }
+        remove
+    }
+
     /// 处理任务完成回调
     pub async fn on_task_completed(
         &self,
@@ -138,55 +409,72 @@ impl WorkflowEngine {
         let workflow = workflows.get_mut(workflow_id)
             .ok_or(WorkflowError::WorkflowNotFound)?;
         
+        // 补偿阶段只接受当前补偿任务的结果
+        let compensating = workflow.status == WorkflowStatus::Compensating;
+        if compensating && workflow.compensations.first() != Some(task_id) {
+            return Ok(());
+        }
         let task = workflow.tasks.get_mut(task_id)
             .ok_or(WorkflowError::TaskNotFound)?;
         
-        match result {
-            Ok(_) => {
+        let fallback_id = task.fallback.clone();
+        let task_state = match result {
+            Ok(output) => {
                 task.state = TaskState::Completed;
+                workflow.outputs.insert(task_id.clone(), parse_output(&output));
+                workflow.completed_order.push(task_id.clone());
+                TaskState::Completed
             }
-            Err(_) => {
+            Err(error) => {
                 task.state = TaskState::Failed;
-                
-                if let Some(fallback_id) = task.fallback.clone() {
-                    drop(workflows);
-                    self.submit_task(workflow_id, &fallback_id).await?;
-                    return Ok(());
-                }
+                workflow.errors.insert(task_id.clone(), error);
+                TaskState::Failed
             }
-        }
-        
-        let task_state = {
-            let workflows = self.workflows.read().await;
-            let workflow = workflows.get(workflow_id)
-                .ok_or(WorkflowError::WorkflowNotFound)?;
-            let task = workflow.tasks.get(task_id)
-                .ok_or(WorkflowError::TaskNotFound)?;
-            task.state
         };
         
-        {
-            let mut workflows = self.workflows.write().await;
-            let workflow = workflows.get_mut(workflow_id).unwrap();
-            let mut graph = WorkflowGraph::new(&workflow.tasks);
-            let ready_tasks = graph.mark_completed(task_id, &workflow.tasks, task_state);
-            
+        // 补偿失败只记录，继续执行后续补偿；全部结束后工作流记为失败
+        if compensating {
+            if task_state == TaskState::Failed {
+                tracing::warn!(workflow = %workflow_id, task = %task_id, "compensation step failed");
+            }
+            workflow.compensations.remove(0);
+            self.persist(workflow);
             drop(workflows);
-            
-            for (ready_task_id, condition_met) in ready_tasks {
-                if condition_met {
-                    self.submit_task(workflow_id, &ready_task_id).await?;
+            self.run_compensation(workflow_id).await;
+            return Ok(());
+        }
+        
+        // 备用任务：失败时执行，成功时跳过（及其下游）
+        let mut finished = vec![(task_id.clone(), task_state)];
+        let mut fallback = None;
+        if let Some(fallback_task) = fallback_id.as_ref().and_then(|id| workflow.tasks.get_mut(id)) {
+            if fallback_task.state == TaskState::Waiting {
+                if task_state == TaskState::Failed {
+                    fallback_task.state = TaskState::Ready;
+                    fallback = fallback_id;
                 } else {
-                    let mut workflows = self.workflows.write().await;
-                    if let Some(workflow) = workflows.get_mut(workflow_id) {
-                        if let Some(task) = workflow.tasks.get_mut(&ready_task_id) {
-                            task.state = TaskState::Skipped;
-                        }
-                    }
+                    fallback_task.state = TaskState::Skipped;
+                    finished.push((fallback_task.id.clone(), TaskState::Skipped));
                 }
             }
         }
         
+        let to_submit = {
+            let mut graphs = self.graphs.write().await;
+            let graph = graphs
+                .entry(workflow_id.clone())
+                .or_insert_with(|| WorkflowGraph::new(&workflow.tasks));
+            let mut to_submit = advance(graph, workflow, finished);
+            to_submit.extend(fallback);
+            to_submit
+        };
+        self.persist(workflow);
+        drop(workflows);
+        
+        for ready_task_id in to_submit {
+            self.submit_task(workflow_id, &ready_task_id).await?;
+        }
+        
         self.check_completion(workflow_id).await;
         
         Ok(())
@@ -194,27 +482,294 @@ impl WorkflowEngine {
 
     async fn check_completion(&self, workflow_id: &WorkflowId) {
         let mut workflows = self.workflows.write().await;
-        if let Some(workflow) = workflows.get_mut(workflow_id) {
-            let all_finished = workflow.tasks.values().all(|task| {
-                matches!(task.state, TaskState::Completed | TaskState::Failed | TaskState::Skipped)
-            });
-            
-            if all_finished {
-                let all_success = workflow.tasks.values().all(|task| {
-                    matches!(task.state, TaskState::Completed)
-                });
-                
-                workflow.status = if all_success {
-                    WorkflowStatus::Completed
-                } else {
-                    WorkflowStatus::Failed
+        let Some(workflow) = workflows
+            .get_mut(workflow_id)
+            .filter(|w| !w.status.is_finished() && w.status != WorkflowStatus::Compensating)
+        else {
+            return;
+        };
+        // 补偿任务只在工作流失败时执行，未执行的不影响是否结束
+        let compensation_ids: HashSet<TaskId> = workflow.tasks.values()
+            .filter_map(|task| task.compensation.clone())
+            .collect();
+        let all_finished = workflow.tasks.values().all(|task| {
+            matches!(task.state, TaskState::Completed | TaskState::Failed | TaskState::Skipped)
+                || (task.state == TaskState::Waiting && compensation_ids.contains(&task.id))
+        });
+        if !all_finished {
+            return;
+        }
+        
+        // 未走到的分支（Skipped）不算失败；已成功汇合的 OR / 法定数依赖隔离其失败分支
+        let isolated = |failed_id: &TaskId| {
+            workflow.tasks.values().any(|task| {
+                task.state == TaskState::Completed
+                    && match &task.dependencies {
+                        TaskDependencies::Any(ids) | TaskDependencies::Quorum { task_ids: ids, .. } => {
+                            ids.contains(failed_id)
+                        }
+                        _ => false,
+                    }
+            })
+        };
+        // 备用任务成功的失败任务同样不计
+        let recovered = |task: &WorkflowTask| {
+            task.fallback.as_ref().and_then(|id| workflow.tasks.get(id))
+                .is_some_and(|fallback| fallback.state == TaskState::Completed)
+        };
+        let all_success = !workflow.tasks.iter().any(|(id, task)| {
+            task.state == TaskState::Failed && !isolated(id) && !recovered(task)
+        });
+        
+        // 失败时按完成的逆序执行已成功任务的补偿，用不到的补偿任务标记为跳过
+        let mut seen = HashSet::new();
+        let pending: Vec<TaskId> = if all_success {
+            Vec::new()
+        } else {
+            workflow.completed_order.iter().rev()
+                .filter_map(|id| workflow.tasks.get(id))
+                .filter(|task| task.state == TaskState::Completed)
+                .filter_map(|task| task.compensation.clone())
+                .filter(|id| workflow.tasks.get(id).is_some_and(|t| t.state == TaskState::Waiting))
+                .filter(|id| seen.insert(id.clone()))
+                .collect()
+        };
+        for id in &compensation_ids {
+            if let Some(task) = workflow.tasks.get_mut(id) {
+                if task.state == TaskState::Waiting && !pending.contains(id) {
+                    task.state = TaskState::Skipped;
+                }
+            }
+        }
+        
+        if pending.is_empty() {
+            let status = if all_success { WorkflowStatus::Completed } else { WorkflowStatus::Failed };
+            self.finish(workflow, status).await;
+            return;
+        }
+        tracing::info!(workflow = %workflow_id, steps = pending.len(), "workflow failed, running compensation");
+        workflow.status = WorkflowStatus::Compensating;
+        workflow.compensations = pending;
+        self.graphs.write().await.remove(workflow_id);
+        self.persist(workflow);
+        drop(workflows);
+        self.run_compensation(workflow_id).await;
+    }
+
+    /// 提交当前补偿任务；提交失败的记为失败并继续下一个，全部结束后工作流记为失败
+    async fn run_compensation(&self, workflow_id: &WorkflowId) {
+        loop {
+            let next = {
+                let mut workflows = self.workflows.write().await;
+                let Some(workflow) = workflows.get_mut(workflow_id) else {
+                    return;
                 };
-                workflow.completed_at = Some(chrono::Utc::now().timestamp_millis());
+                match workflow.compensations.first().cloned() {
+                    Some(next) => next,
+                    None => {
+                        self.finish(workflow, WorkflowStatus::Failed).await;
+                        return;
+                    }
+                }
+            };
+            let Err(e) = self.submit_task(workflow_id, &next).await else {
+                return;
+            };
+            tracing::warn!(workflow = %workflow_id, task = %next, "failed to start compensation: {}", e);
+            let mut workflows = self.workflows.write().await;
+            let Some(workflow) = workflows.get_mut(workflow_id) else {
+                return;
+            };
+            if let Some(task) = workflow.tasks.get_mut(&next) {
+                task.state = TaskState::Failed;
             }
+            workflow.errors.insert(next, format!("compensation failed to start: {}", e));
+            workflow.compensations.remove(0);
+            self.persist(workflow);
+        }
+    }
+
+    /// 结束工作流：记录状态与完成时间、移除依赖图，子运行向父节点回报结果
+    async fn finish(&self, workflow: &mut Workflow, status: WorkflowStatus) {
+        workflow.status = status;
+        workflow.completed_at = Some(chrono::Utc::now().timestamp_millis());
+        self.graphs.write().await.remove(&workflow.id);
+        self.persist(workflow);
+        if let Some((parent_id, parent_task_id)) = workflow.parent.clone() {
+            let _ = self.completions.send((parent_id, parent_task_id, sub_workflow_result(workflow)));
         }
     }
 }
 
+/// 子工作流结束后回报给父节点的结果：成功时为各任务输出组成的 JSON 对象（不含 `input`），失败时汇总各任务错误
+#[cfg(feature = "gateway")]
+fn sub_workflow_result(workflow: &Workflow) -> Result<String, String> {
+    if workflow.status == WorkflowStatus::Completed {
+        let outputs: serde_json::Map<String, serde_json::Value> = workflow.outputs.iter()
+            .filter(|(id, _)| id.as_str() != INPUT_NODE_ID)
+            .map(|(id, output)| (id.clone(), output.clone()))
+            .collect();
+        Ok(serde_json::Value::Object(outputs).to_string())
+    } else {
+        let mut errors: Vec<String> = workflow.errors.iter()
+            .map(|(id, error)| format!("{}: {}", id, error))
+            .collect();
+        errors.sort();
+        Err(format!("sub-workflow {} failed: {}", workflow.id, errors.join("; ")))
+    }
+}
+
+/// 去掉作为其它任务备用或补偿的任务：它们只在对应任务失败、工作流失败时执行
+#[cfg(feature = "gateway")]
+fn without_standby(workflow: &Workflow, ready: Vec<TaskId>) -> Vec<TaskId> {
+    ready
+        .into_iter()
+        .filter(|id| {
+            !workflow.tasks.values().any(|task| {
+                task.fallback.as_ref() == Some(id) || task.compensation.as_ref() == Some(id)
+            })
+        })
+        .collect()
+}
+
+/// 推进依赖图：条件不满足的任务标记为跳过，并继续向下游传播；返回应提交执行的任务
+#[cfg(feature = "gateway")]
+fn advance(graph: &mut WorkflowGraph, workflow: &mut Workflow, mut finished: Vec<(TaskId, TaskState)>) -> Vec<TaskId> {
+    let mut to_submit = Vec::new();
+    while let Some((finished_id, state)) = finished.pop() {
+        let ready_tasks = graph.mark_completed(&finished_id, &workflow.tasks, state, &workflow.outputs);
+        for (ready_task_id, condition_met) in ready_tasks {
+            let Some(task) = workflow.tasks.get_mut(&ready_task_id) else {
+                continue;
+            };
+            if condition_met {
+                task.state = TaskState::Ready;
+                to_submit.push(ready_task_id);
+            } else {
+                task.state = TaskState::Skipped;
+                finished.push((ready_task_id, TaskState::Skipped));
+            }
+        }
+    }
+    to_submit
+}
+
+/// 代入 JSON 中各字符串的 `{{$.task.field}}` 占位符
+#[cfg(feature = "gateway")]
+fn interpolate_value(value: &mut serde_json::Value, outputs: &HashMap<TaskId, serde_json::Value>) {
+    match value {
+        serde_json::Value::String(s) => *s = interpolate(s, outputs),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| interpolate_value(v, outputs)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|v| interpolate_value(v, outputs)),
+        _ => {}
+    }
+}
+
+/// 代入任务指令与元数据字符串中的 `{{$.task.field}}` 占位符
+#[cfg(feature = "gateway")]
+fn interpolate_task(task: &BackgroundTask, outputs: &HashMap<TaskId, serde_json::Value>) -> BackgroundTask {
+    let mut task = task.clone();
+    task.instruction = interpolate(&task.instruction, outputs);
+    if let Some(metadata) = task.metadata.as_mut() {
+        interpolate_value(metadata, outputs);
+    }
+    task
+}
+
+/// 按重试策略执行任务：超时计为失败，失败后按 1s、2s、4s…（上限 30s）退避重试
+#[cfg(feature = "gateway")]
+pub async fn execute_with_retry(
+    executor: &dyn WorkflowTaskExecutor,
+    task: &BackgroundTask,
+    retry: RetryPolicy,
+) -> Result<String, String> {
+    let mut attempt = 0;
+    loop {
+        let run = executor.execute(task);
+        let result = match retry.timeout_secs {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), run)
+                .await
+                .unwrap_or_else(|_| Err(format!("timed out after {}s", secs))),
+            None => run.await,
+        };
+        match result {
+            Err(error) if attempt < retry.retries => {
+                attempt += 1;
+                tracing::debug!(task = %task.id, attempt, "workflow task failed, retrying: {}", error);
+                tokio::time::sleep(Duration::from_secs((1u64 << (attempt - 1).min(5)).min(30))).await;
+            }
+            other => return other,
+        }
+    }
+}
+
+/// 并发执行并行任务组的各分支并按策略汇合
+///
+/// 分支之间错误隔离（失败或 panic 只记入该分支结果）。`WaitAll` 等待全部分支结束且全部成功才算成功；
+/// `FirstSuccess` / `Quorum` 在成功数达到要求或已不可能达到时立即返回，其余分支被取消。
+/// 输出为 JSON：`{ "join", "succeeded", "failed", "results": [{ "ok", "output" | "error" } | null] }`，
+/// 未结束（已取消）的分支为 `null`；汇合失败时同样的 JSON 作为错误返回。
+#[cfg(feature = "gateway")]
+pub async fn run_parallel(
+    executor: Arc<dyn WorkflowTaskExecutor>,
+    branches: Vec<BackgroundTask>,
+    join: JoinStrategy,
+    retry: RetryPolicy,
+) -> Result<String, String> {
+    let total = branches.len();
+    join.validate(total)?;
+    let required = join.required(total);
+
+    let mut set = tokio::task::JoinSet::new();
+    for (index, branch) in branches.into_iter().enumerate() {
+        let executor = Arc::clone(&executor);
+        set.spawn(async move {
+            let result = AssertUnwindSafe(execute_with_retry(executor.as_ref(), &branch, retry))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|_| Err("branch panicked".to_string()));
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<Option<serde_json::Value>> = vec![None; total];
+    let (mut succeeded, mut failed) = (0usize, 0usize);
+    while let Some(joined) = set.join_next().await {
+        let Ok((index, result)) = joined else {
+            continue;
+        };
+        results[index] = Some(match result {
+            Ok(output) => {
+                succeeded += 1;
+                serde_json::json!({ "ok": true, "output": parse_output(&output) })
+            }
+            Err(error) => {
+                failed += 1;
+                serde_json::json!({ "ok": false, "error": error })
+            }
+        });
+        let remaining = total - succeeded - failed;
+        let decided = succeeded >= required || succeeded + remaining < required;
+        if decided && join != JoinStrategy::WaitAll {
+            break;
+        }
+    }
+    set.abort_all();
+
+    let summary = serde_json::json!({
+        "join": join,
+        "succeeded": succeeded,
+        "failed": failed,
+        "results": results,
+    })
+    .to_string();
+    if succeeded >= required {
+        Ok(summary)
+    } else {
+        Err(summary)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,4 +828,495 @@ mod tests {
         let status = engine.get_status(&workflow_id).await;
         assert!(matches!(status, Some(WorkflowStatus::Running)));
     }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_expression_branch_completes() {
+        let (queue, _, _) = TaskQueue::new();
+        let engine = WorkflowEngine::new(Arc::new(queue), Arc::new(MockExecutor));
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+
+        let workflow = WorkflowBuilder::new("Branch Test")
+            .user_id("user1".to_string())
+            .task("check", task("Check"))
+            .task("fix", task("Fix"))
+            .task("ship", task("Ship"))
+            .branch("check", "$.check.issues > 0", "fix", "ship")
+            .build()
+            .unwrap();
+        let workflow_id = engine.submit_workflow(workflow).await.unwrap();
+
+        engine
+            .on_task_completed(&workflow_id, &"check".to_string(), Ok(r#"{"issues": 0}"#.to_string()))
+            .await
+            .unwrap();
+        {
+            let workflows = engine.workflows.read().await;
+            let workflow = &workflows[&workflow_id];
+            assert_eq!(workflow.tasks["fix"].state, TaskState::Skipped);
+            assert_eq!(workflow.tasks["ship"].state, TaskState::Running);
+        }
+
+        engine
+            .on_task_completed(&workflow_id, &"ship".to_string(), Ok("shipped".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(engine.get_status(&workflow_id).await, Some(WorkflowStatus::Completed));
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_run_parallel_isolates_branch_errors() {
+        struct FlakyExecutor;
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for FlakyExecutor {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                if task.instruction.starts_with("fail") {
+                    Err(format!("{} failed", task.instruction))
+                } else {
+                    Ok(format!(r#"{{"from": "{}"}}"#, task.instruction))
+                }
+            }
+        }
+
+        let branches = |names: &[&str]| -> Vec<BackgroundTask> {
+            names.iter().map(|n| BackgroundTask::new("user1".to_string(), n.to_string())).collect()
+        };
+        let executor: Arc<dyn WorkflowTaskExecutor> = Arc::new(FlakyExecutor);
+
+        let none = RetryPolicy::default();
+        let out = run_parallel(executor.clone(), branches(&["a", "fail-b", "c"]), JoinStrategy::Quorum(2), none)
+            .await
+            .unwrap();
+        let out: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert!(out["succeeded"].as_u64().unwrap() >= 2);
+
+        let err = run_parallel(executor.clone(), branches(&["a", "fail-b"]), JoinStrategy::WaitAll, none)
+            .await
+            .unwrap_err();
+        let err: serde_json::Value = serde_json::from_str(&err).unwrap();
+        assert_eq!(err["succeeded"], 1);
+        assert_eq!(err["results"][1]["error"], "fail-b failed");
+        assert_eq!(err["results"][0]["output"]["from"], "a");
+
+        assert!(run_parallel(executor.clone(), branches(&["fail-a", "ok"]), JoinStrategy::FirstSuccess, none).await.is_ok());
+        assert!(run_parallel(executor, branches(&["fail-a"]), JoinStrategy::FirstSuccess, none).await.is_err());
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_first_success_join_isolates_failure() {
+        let (queue, _, _) = TaskQueue::new();
+        let engine = WorkflowEngine::new(Arc::new(queue), Arc::new(MockExecutor));
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+
+        let workflow = WorkflowBuilder::new("Fan-out Test")
+            .user_id("user1".to_string())
+            .task("mirror1", task("Fetch mirror 1"))
+            .task("mirror2", task("Fetch mirror 2"))
+            .task("merge", task("Merge"))
+            .join("merge", vec!["mirror1".to_string(), "mirror2".to_string()], JoinStrategy::FirstSuccess)
+            .build()
+            .unwrap();
+        let workflow_id = engine.submit_workflow(workflow).await.unwrap();
+
+        engine.on_task_completed(&workflow_id, &"mirror1".to_string(), Err("timeout".to_string())).await.unwrap();
+        engine.on_task_completed(&workflow_id, &"mirror2".to_string(), Ok("data".to_string())).await.unwrap();
+        engine.on_task_completed(&workflow_id, &"merge".to_string(), Ok("merged".to_string())).await.unwrap();
+
+        assert_eq!(engine.get_status(&workflow_id).await, Some(WorkflowStatus::Completed));
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_start_drives_workflow_with_retry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        /// 第一次执行 "flaky" 失败，其余返回 JSON，并记录收到的指令
+        struct RecordingExecutor {
+            flaky_calls: AtomicUsize,
+            seen: Mutex<Vec<String>>,
+        }
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for RecordingExecutor {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                self.seen.lock().unwrap().push(task.instruction.clone());
+                if task.instruction == "flaky" && self.flaky_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err("transient".to_string());
+                }
+                Ok(r#"{"count": 7}"#.to_string())
+            }
+        }
+
+        let (queue, _, _) = TaskQueue::new();
+        let queue = Arc::new(queue);
+        let executor = Arc::new(RecordingExecutor { flaky_calls: AtomicUsize::new(0), seen: Mutex::new(Vec::new()) });
+        let engine = Arc::new(WorkflowEngine::new(Arc::clone(&queue), executor.clone()));
+        engine.start();
+
+        let workflow = WorkflowBuilder::new("Driven")
+            .user_id("user1".to_string())
+            .task("a", BackgroundTask::new("user1".to_string(), "flaky".to_string()))
+            .task("b", BackgroundTask::new("user1".to_string(), "got {{$.a.count}}".to_string()))
+            .sequential("a", "b")
+            .with_retry("a", RetryPolicy { retries: 1, timeout_secs: Some(5) })
+            .build()
+            .unwrap();
+        let workflow_id = engine.submit_workflow(workflow).await.unwrap();
+
+        for _ in 0..50 {
+            if engine.get_status(&workflow_id).await == Some(WorkflowStatus::Completed) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let snapshot = engine.snapshot(&workflow_id).await.unwrap();
+        assert_eq!(snapshot.status, WorkflowStatus::Completed);
+        assert_eq!(snapshot.tasks[1].output, Some(serde_json::json!({ "count": 7 })));
+        assert_eq!(*executor.seen.lock().unwrap(), vec!["flaky", "flaky", "got 7"]);
+        // 节点结束后包装任务从队列移除
+        assert!(queue.get_user_tasks("user1").await.is_empty());
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_sub_workflow_and_fallback() {
+        use std::sync::Mutex;
+
+        /// 指令以 "fail" 开头时失败，否则原样返回指令
+        #[derive(Default)]
+        struct Echo(Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for Echo {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                self.0.lock().unwrap().push(task.instruction.clone());
+                if task.instruction.starts_with("fail") {
+                    return Err("boom".to_string());
+                }
+                Ok(task.instruction.clone())
+            }
+        }
+
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+        let child = WorkflowBuilder::new("Child")
+            .user_id("user1".to_string())
+            .task("greet", task("hello {{$.input.name}}"))
+            .task("flaky", task("fail once"))
+            .task("recover", task("recovered"))
+            .sequential("greet", "flaky")
+            .with_fallback("flaky", "recover".to_string())
+            .build()
+            .unwrap();
+        let parent = WorkflowBuilder::new("Parent")
+            .user_id("user1".to_string())
+            .input(serde_json::json!({ "who": "bee" }))
+            .task("prep", task("prep"))
+            .sub_workflow("sub", child, serde_json::json!({ "name": "{{$.input.who}}" }))
+            .task("done", task("got {{$.sub.greet}}"))
+            .sequential("prep", "sub")
+            .sequential("sub", "done")
+            .build()
+            .unwrap();
+
+        let (queue, _, _) = TaskQueue::new();
+        let executor = Arc::new(Echo::default());
+        let engine = Arc::new(WorkflowEngine::new(Arc::new(queue), executor.clone()));
+        engine.start();
+        let parent_id = engine.submit_workflow(parent).await.unwrap();
+
+        for _ in 0..50 {
+            if engine.get_status(&parent_id).await == Some(WorkflowStatus::Completed) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let snapshot = engine.snapshot(&parent_id).await.unwrap();
+        assert_eq!(snapshot.status, WorkflowStatus::Completed);
+        let seen = executor.0.lock().unwrap().clone();
+        assert_eq!(seen, vec!["prep", "hello bee", "fail once", "recovered", "got hello bee"]);
+
+        let runs = engine.list().await;
+        let child = runs.iter().find(|r| r.parent_id.as_deref() == Some(parent_id.as_str())).unwrap();
+        assert_eq!(child.status, WorkflowStatus::Completed);
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_fallback_runs_only_on_failure() {
+        use std::sync::Mutex;
+
+        /// 指令以 "fail" 开头时失败，否则原样返回指令
+        #[derive(Default)]
+        struct Echo(Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for Echo {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                self.0.lock().unwrap().push(task.instruction.clone());
+                if task.instruction.starts_with("fail") {
+                    return Err("boom".to_string());
+                }
+                Ok(task.instruction.clone())
+            }
+        }
+
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+        let flow = |primary: &str, recover: &str| {
+            WorkflowBuilder::new("Fallback")
+                .user_id("user1".to_string())
+                .task("primary", task(primary))
+                .task("next", task("next"))
+                .task("recover", task(recover))
+                .task("after_recover", task("after recover"))
+                .sequential("primary", "next")
+                .sequential("recover", "after_recover")
+                .with_fallback("primary", "recover".to_string())
+                .build()
+                .unwrap()
+        };
+        let run = |workflow: Workflow| async move {
+            let (queue, _, _) = TaskQueue::new();
+            let executor = Arc::new(Echo::default());
+            let engine = Arc::new(WorkflowEngine::new(Arc::new(queue), executor.clone()));
+            engine.start();
+            let id = engine.submit_workflow(workflow).await.unwrap();
+            for _ in 0..50 {
+                if engine.get_status(&id).await.is_some_and(|s| s.is_finished()) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            let seen = executor.0.lock().unwrap().clone();
+            (engine.snapshot(&id).await.unwrap(), seen)
+        };
+        let state = |snapshot: &WorkflowSnapshot, id: &str| snapshot.tasks.iter().find(|t| t.id == id).unwrap().state;
+
+        // 主节点成功：备用节点（没有入边也）不执行，与其下游一起跳过
+        let (snapshot, seen) = run(flow("ok", "recover")).await;
+        assert_eq!(snapshot.status, WorkflowStatus::Completed);
+        assert_eq!(seen, vec!["ok", "next"]);
+        assert_eq!(state(&snapshot, "recover"), TaskState::Skipped);
+        assert_eq!(state(&snapshot, "after_recover"), TaskState::Skipped);
+
+        // 主节点失败、备用成功：失败已恢复，工作流成功；主节点的下游照常执行
+        let (snapshot, mut seen) = run(flow("fail primary", "recover")).await;
+        seen.sort();
+        assert_eq!(snapshot.status, WorkflowStatus::Completed);
+        assert_eq!(seen, vec!["after recover", "fail primary", "next", "recover"]);
+        assert_eq!(state(&snapshot, "primary"), TaskState::Failed);
+
+        // 备用也失败：工作流失败
+        let (snapshot, _) = run(flow("fail primary", "fail recover")).await;
+        assert_eq!(snapshot.status, WorkflowStatus::Failed);
+        assert_eq!(state(&snapshot, "after_recover"), TaskState::Completed);
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_compensation_runs_in_reverse_order() {
+        use std::sync::Mutex;
+
+        /// 指令以 "fail" 开头时失败，否则原样返回指令
+        #[derive(Default)]
+        struct Echo(Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for Echo {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                self.0.lock().unwrap().push(task.instruction.clone());
+                if task.instruction.starts_with("fail") {
+                    return Err("boom".to_string());
+                }
+                Ok(task.instruction.clone())
+            }
+        }
+
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+        let workflow = WorkflowBuilder::new("Saga")
+            .user_id("user1".to_string())
+            .task("branch", task("create branch"))
+            .task("edit", task("edit files"))
+            .task("push", task("fail push"))
+            .task("delete_branch", task("delete {{$.branch}}"))
+            .task("revert", task("revert edits"))
+            .task("unpush", task("unpush"))
+            .sequential("branch", "edit")
+            .sequential("edit", "push")
+            .with_compensation("branch", "delete_branch".to_string())
+            .with_compensation("edit", "revert".to_string())
+            .with_compensation("push", "unpush".to_string())
+            .build()
+            .unwrap();
+
+        let (queue, _, _) = TaskQueue::new();
+        let executor = Arc::new(Echo::default());
+        let engine = Arc::new(WorkflowEngine::new(Arc::new(queue), executor.clone()));
+        engine.start();
+        let workflow_id = engine.submit_workflow(workflow).await.unwrap();
+
+        for _ in 0..50 {
+            if engine.get_status(&workflow_id).await == Some(WorkflowStatus::Failed) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let snapshot = engine.snapshot(&workflow_id).await.unwrap();
+        assert_eq!(snapshot.status, WorkflowStatus::Failed);
+        let seen = executor.0.lock().unwrap().clone();
+        assert_eq!(seen, vec!["create branch", "edit files", "fail push", "revert edits", "delete create branch"]);
+        let state = |id: &str| snapshot.tasks.iter().find(|t| t.id == id).unwrap().state;
+        assert_eq!(state("delete_branch"), TaskState::Completed);
+        assert_eq!(state("unpush"), TaskState::Skipped);
+
+        // 成功的运行不执行补偿
+        let workflow = WorkflowBuilder::new("Saga ok")
+            .user_id("user1".to_string())
+            .task("branch", task("create branch"))
+            .task("delete_branch", task("delete branch"))
+            .with_compensation("branch", "delete_branch".to_string())
+            .build()
+            .unwrap();
+        let workflow_id = engine.submit_workflow(workflow).await.unwrap();
+        for _ in 0..50 {
+            if engine.get_status(&workflow_id).await == Some(WorkflowStatus::Completed) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let snapshot = engine.snapshot(&workflow_id).await.unwrap();
+        assert_eq!(snapshot.status, WorkflowStatus::Completed);
+        assert_eq!(snapshot.tasks[1].state, TaskState::Skipped);
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_resume_skips_completed_side_effects() {
+        use std::sync::Mutex;
+
+        /// 记录收到的指令，返回 "done: 指令"
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for Recorder {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                self.0.lock().unwrap().push(task.instruction.clone());
+                Ok(format!("done: {}", task.instruction))
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(WorkflowStore::open(dir.path()).unwrap());
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+
+        // 模拟中断：fetch 已完成，charge 已成功但结果未回报，notify 未开始
+        let mut workflow = WorkflowBuilder::new("Resumable")
+            .user_id("user1".to_string())
+            .task("fetch", task("Fetch"))
+            .task("charge", task("Charge {{$.fetch}}"))
+            .task("notify", task("Notify"))
+            .sequential("fetch", "charge")
+            .sequential("charge", "notify")
+            .side_effecting("charge")
+            .build()
+            .unwrap();
+        workflow.status = WorkflowStatus::Running;
+        workflow.tasks.get_mut("fetch").unwrap().state = TaskState::Completed;
+        workflow.outputs.insert("fetch".to_string(), serde_json::json!("order-1"));
+        workflow.tasks.get_mut("charge").unwrap().state = TaskState::Running;
+        let key = workflow.tasks["charge"].idempotency_key.clone().unwrap();
+        store.record_idempotent(&key, &workflow.id, &"charge".to_string(), "charged once").unwrap();
+        store.save(&workflow).unwrap();
+
+        let (queue, _, _) = TaskQueue::new();
+        let executor = Arc::new(Recorder::default());
+        let engine = Arc::new(
+            WorkflowEngine::new(Arc::new(queue), executor.clone()).with_store(Arc::clone(&store)),
+        );
+        engine.start();
+        assert_eq!(engine.resume().await.unwrap(), vec![workflow.id.clone()]);
+
+        for _ in 0..50 {
+            if engine.get_status(&workflow.id).await == Some(WorkflowStatus::Completed) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(*executor.0.lock().unwrap(), vec!["Notify"]);
+        let stored = store.get(&workflow.id).unwrap().unwrap();
+        assert_eq!(stored.status, WorkflowStatus::Completed);
+        assert_eq!(stored.outputs["charge"], "charged once");
+        assert!(store.unfinished().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "gateway")]
+    #[tokio::test]
+    async fn test_resume_interrupted_compensation() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl WorkflowTaskExecutor for Recorder {
+            async fn execute(&self, task: &BackgroundTask) -> Result<String, String> {
+                self.0.lock().unwrap().push(task.instruction.clone());
+                Ok(task.instruction.clone())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(WorkflowStore::open(dir.path()).unwrap());
+        let task = |text: &str| BackgroundTask::new("user1".to_string(), text.to_string());
+
+        // 模拟中断：push 失败后开始补偿，revert 已提交但未回报；队首是已不存在的补偿任务（提交失败）
+        let mut workflow = WorkflowBuilder::new("Saga")
+            .user_id("user1".to_string())
+            .task("branch", task("create branch"))
+            .task("edit", task("edit files"))
+            .task("push", task("push"))
+            .task("delete_branch", task("delete branch"))
+            .task("revert", task("revert edits"))
+            .sequential("branch", "edit")
+            .sequential("edit", "push")
+            .with_compensation("branch", "delete_branch".to_string())
+            .with_compensation("edit", "revert".to_string())
+            .build()
+            .unwrap();
+        workflow.status = WorkflowStatus::Compensating;
+        for id in ["branch", "edit"] {
+            workflow.tasks.get_mut(id).unwrap().state = TaskState::Completed;
+            workflow.completed_order.push(id.to_string());
+        }
+        workflow.tasks.get_mut("push").unwrap().state = TaskState::Failed;
+        workflow.tasks.get_mut("revert").unwrap().state = TaskState::Running;
+        workflow.compensations = vec!["gone".to_string(), "revert".to_string(), "delete_branch".to_string()];
+        store.save(&workflow).unwrap();
+
+        let (queue, _, _) = TaskQueue::new();
+        let executor = Arc::new(Recorder::default());
+        let engine = Arc::new(
+            WorkflowEngine::new(Arc::new(queue), executor.clone()).with_store(Arc::clone(&store)),
+        );
+        engine.start();
+        assert_eq!(engine.resume().await.unwrap(), vec![workflow.id.clone()]);
+
+        for _ in 0..50 {
+            if engine.get_status(&workflow.id).await == Some(WorkflowStatus::Failed) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(*executor.0.lock().unwrap(), vec!["revert edits", "delete branch"]);
+        let stored = store.get(&workflow.id).unwrap().unwrap();
+        assert_eq!(stored.status, WorkflowStatus::Failed);
+        assert!(stored.compensations.is_empty());
+        assert!(stored.errors["gone"].contains("compensation failed to start"));
+        assert_eq!(stored.tasks["delete_branch"].state, TaskState::Completed);
+        assert!(store.unfinished().unwrap().is_empty());
+    }
 }
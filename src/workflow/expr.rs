@@ -0,0 +1,696 @@
+//! 条件表达式
+//!
+//! 对前置任务的输出求值，供条件依赖（`ConditionPredicate::Expression`）决定分支走向。
+//! 语法为 JSONPath 风格的路径加比较与逻辑运算：
+//!
+//! - 路径：`$.check.status`、`$.search.items[0].title`、`$["task-id"].count`，首段为任务 ID；
+//!   任务输出为 JSON 时按字段取值，否则为整段文本；不存在的路径取值为 `null`
+//! - 字面量：`"text"` / `'text'`、数字、`true` / `false` / `null`
+//! - 运算：`==` `!=` `<` `<=` `>` `>=`、`&&` `||` `!`、括号
+//! - 函数：`contains(a, b)`（子串 / 数组元素 / 对象键）、`starts_with(a, b)`、`len(a)`
+//!
+//! 单独的路径或函数按真值判断：`null`、`false`、`0`、空串、空数组与空对象为假。
+//! `!`、括号与函数调用最多嵌套 64 层。
+//!
+//! 任务指令中的 `{{$.task.field}}` 占位符在执行前用 [`interpolate`] 代入前置任务的输出。
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::workflow::types::TaskId;
+
+/// `!`、括号与函数调用的最大嵌套层数，避免恶意表达式耗尽栈
+const MAX_DEPTH: usize = 64;
+
+/// 表达式解析错误
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{message} (at {pos})")]
+pub struct ExprError {
+    /// 出错位置（字符偏移）
+    pub pos: usize,
+    pub message: String,
+}
+
+impl ExprError {
+    fn new(pos: usize, message: impl Into<String>) -> Self {
+        Self {
+            pos,
+            message: message.into(),
+        }
+    }
+}
+
+/// 路径片段
+#[derive(Debug, Clone, PartialEq)]
+enum PathSeg {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Contains,
+    StartsWith,
+    Len,
+}
+
+impl Func {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "contains" => Some(Self::Contains),
+            "starts_with" => Some(Self::StartsWith),
+            "len" => Some(Self::Len),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Self::Contains | Self::StartsWith => 2,
+            Self::Len => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(Value),
+    Path(Vec<PathSeg>),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Compare(CmpOp, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+/// 已解析的条件表达式
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    /// 解析表达式
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: source.chars().count(),
+            depth: 0,
+        };
+        let root = parser.parse_or()?;
+        if let Some((pos, tok)) = parser.tokens.get(parser.pos) {
+            return Err(ExprError::new(*pos, format!("unexpected {}", tok.describe())));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    /// 原始表达式文本
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// 表达式引用的任务 ID（去重，按出现顺序）
+    pub fn task_refs(&self) -> Vec<TaskId> {
+        let mut refs = Vec::new();
+        collect_refs(&self.root, &mut refs);
+        refs
+    }
+
+    /// 以任务输出为上下文求值
+    pub fn evaluate(&self, outputs: &HashMap<TaskId, Value>) -> Value {
+        eval(&self.root, outputs)
+    }
+
+    /// 求值并按真值判断
+    pub fn is_met(&self, outputs: &HashMap<TaskId, Value>) -> bool {
+        is_truthy(&self.evaluate(outputs))
+    }
+}
+
+/// 把任务的文本结果转为表达式上下文：能解析为 JSON 时按 JSON，否则为字符串
+pub fn parse_output(output: &str) -> Value {
+    let trimmed = output.trim();
+    match serde_json::from_str::<Value>(trimmed) {
+        Ok(value) => value,
+        Err(_) => Value::String(output.to_string()),
+    }
+}
+
+/// 代入文本中的 `{{ 表达式 }}` 占位符（仅处理以 `$` 开头或函数调用的表达式，其它 `{{…}}` 原样保留）：
+/// 字符串按原文代入，`null` 为空串，其它值为 JSON
+pub fn interpolate(text: &str, outputs: &HashMap<TaskId, Value>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let inner = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        let parsed = (inner.starts_with('$') || inner.contains('('))
+            .then(|| Expression::parse(inner).ok())
+            .flatten();
+        match parsed {
+            Some(expr) => match expr.evaluate(outputs) {
+                Value::String(s) => out.push_str(&s),
+                Value::Null => {}
+                other => out.push_str(&other.to_string()),
+            },
+            None => out.push_str(&rest[start..start + 2 + len + 2]),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 真值判断
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|f| f != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn collect_refs(node: &Node, refs: &mut Vec<TaskId>) {
+    match node {
+        Node::Literal(_) => {}
+        Node::Path(segs) => {
+            if let Some(PathSeg::Key(task_id)) = segs.first() {
+                if !refs.contains(task_id) {
+                    refs.push(task_id.clone());
+                }
+            }
+        }
+        Node::Not(inner) => collect_refs(inner, refs),
+        Node::And(a, b) | Node::Or(a, b) | Node::Compare(_, a, b) => {
+            collect_refs(a, refs);
+            collect_refs(b, refs);
+        }
+        Node::Call(_, args) => args.iter().for_each(|arg| collect_refs(arg, refs)),
+    }
+}
+
+fn eval(node: &Node, outputs: &HashMap<TaskId, Value>) -> Value {
+    match node {
+        Node::Literal(v) => v.clone(),
+        Node::Path(segs) => resolve_path(segs, outputs).cloned().unwrap_or(Value::Null),
+        Node::Not(inner) => Value::Bool(!is_truthy(&eval(inner, outputs))),
+        Node::And(a, b) => Value::Bool(is_truthy(&eval(a, outputs)) && is_truthy(&eval(b, outputs))),
+        Node::Or(a, b) => Value::Bool(is_truthy(&eval(a, outputs)) || is_truthy(&eval(b, outputs))),
+        Node::Compare(op, a, b) => Value::Bool(compare(*op, &eval(a, outputs), &eval(b, outputs))),
+        Node::Call(func, args) => {
+            let args: Vec<Value> = args.iter().map(|arg| eval(arg, outputs)).collect();
+            call(*func, &args)
+        }
+    }
+}
+
+fn resolve_path<'a>(segs: &[PathSeg], outputs: &'a HashMap<TaskId, Value>) -> Option<&'a Value> {
+    let (first, rest) = segs.split_first()?;
+    let PathSeg::Key(task_id) = first else {
+        return None;
+    };
+    let mut current = outputs.get(task_id)?;
+    for seg in rest {
+        current = match (seg, current) {
+            (PathSeg::Key(key), Value::Object(map)) => map.get(key)?,
+            (PathSeg::Index(i), Value::Array(items)) => items.get(*i)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+fn compare(op: CmpOp, a: &Value, b: &Value) -> bool {
+    match op {
+        CmpOp::Eq => return values_equal(a, b),
+        CmpOp::Ne => return !values_equal(a, b),
+        _ => {}
+    }
+    let ordering = match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64().zip(y.as_f64()).and_then(|(x, y)| x.partial_cmp(&y)),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        CmpOp::Lt => ordering.is_lt(),
+        CmpOp::Le => ordering.is_le(),
+        CmpOp::Gt => ordering.is_gt(),
+        CmpOp::Ge => ordering.is_ge(),
+        CmpOp::Eq | CmpOp::Ne => unreachable!(),
+    }
+}
+
+fn call(func: Func, args: &[Value]) -> Value {
+    match func {
+        Func::Contains => Value::Bool(match (&args[0], &args[1]) {
+            (Value::String(s), Value::String(needle)) => s.contains(needle.as_str()),
+            (Value::Array(items), needle) => items.iter().any(|item| values_equal(item, needle)),
+            (Value::Object(map), Value::String(key)) => map.contains_key(key),
+            _ => false,
+        }),
+        Func::StartsWith => Value::Bool(match (&args[0], &args[1]) {
+            (Value::String(s), Value::String(prefix)) => s.starts_with(prefix.as_str()),
+            _ => false,
+        }),
+        Func::Len => match &args[0] {
+            Value::String(s) => Value::from(s.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(map) => Value::from(map.len()),
+            Value::Null => Value::from(0),
+            _ => Value::Null,
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Comma,
+    Not,
+    And,
+    Or,
+    Cmp(CmpOp),
+    Literal(Value),
+    Path(Vec<PathSeg>),
+    Ident(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Not => "'!'".to_string(),
+            Token::And => "'&&'".to_string(),
+            Token::Or => "'||'".to_string(),
+            Token::Cmp(_) => "comparison operator".to_string(),
+            Token::Literal(v) => format!("literal {}", v),
+            Token::Path(_) => "path".to_string(),
+            Token::Ident(name) => format!("identifier '{}'", name),
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '&' if next == Some('&') => {
+                i += 1;
+                Token::And
+            }
+            '|' if next == Some('|') => {
+                i += 1;
+                Token::Or
+            }
+            '=' if next == Some('=') => {
+                i += 1;
+                Token::Cmp(CmpOp::Eq)
+            }
+            '!' if next == Some('=') => {
+                i += 1;
+                Token::Cmp(CmpOp::Ne)
+            }
+            '!' => Token::Not,
+            '<' | '>' => {
+                let eq = next == Some('=');
+                if eq {
+                    i += 1;
+                }
+                Token::Cmp(match (c, eq) {
+                    ('<', false) => CmpOp::Lt,
+                    ('<', true) => CmpOp::Le,
+                    ('>', false) => CmpOp::Gt,
+                    _ => CmpOp::Ge,
+                })
+            }
+            '"' | '\'' => {
+                let (s, end) = read_string(&chars, i)?;
+                i = end;
+                tokens.push((start, Token::Literal(Value::String(s))));
+                continue;
+            }
+            '$' => {
+                let (segs, end) = read_path(&chars, i + 1)?;
+                i = end;
+                tokens.push((start, Token::Path(segs)));
+                continue;
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+                    end += 1;
+                }
+                let text: String = chars[i..end].iter().collect();
+                let number: f64 = text
+                    .parse()
+                    .map_err(|_| ExprError::new(start, format!("invalid number '{}'", text)))?;
+                let value = if number.fract() == 0.0 && !text.contains('.') {
+                    Value::from(number as i64)
+                } else {
+                    Value::from(number)
+                };
+                i = end;
+                tokens.push((start, Token::Literal(value)));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+                    end += 1;
+                }
+                let word: String = chars[i..end].iter().collect();
+                let token = match word.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    _ => Token::Ident(word),
+                };
+                i = end;
+                tokens.push((start, token));
+                continue;
+            }
+            other => return Err(ExprError::new(start, format!("unexpected character '{}'", other))),
+        };
+        tokens.push((start, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+/// 读取引号字符串，返回内容与结束位置（闭合引号之后）
+fn read_string(chars: &[char], start: usize) -> Result<(String, usize), ExprError> {
+    let quote = chars[start];
+    let mut out = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                out.push(match chars[i + 1] {
+                    'n' => '\n',
+                    't' => '\t',
+                    other => other,
+                });
+                i += 2;
+            }
+            c if c == quote => return Ok((out, i + 1)),
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(ExprError::new(start, "unterminated string"))
+}
+
+/// 读取 `$` 之后的路径片段
+fn read_path(chars: &[char], mut i: usize) -> Result<(Vec<PathSeg>, usize), ExprError> {
+    let mut segs = Vec::new();
+    loop {
+        match chars.get(i) {
+            Some('.') => {
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && is_ident_char(chars[end]) {
+                    end += 1;
+                }
+                if end == start {
+                    return Err(ExprError::new(i, "expected field name after '.'"));
+                }
+                segs.push(PathSeg::Key(chars[start..end].iter().collect()));
+                i = end;
+            }
+            Some('[') => {
+                match chars.get(i + 1) {
+                    Some('"') | Some('\'') => {
+                        let (key, end) = read_string(chars, i + 1)?;
+                        segs.push(PathSeg::Key(key));
+                        i = end;
+                    }
+                    _ => {
+                        let start = i + 1;
+                        let mut end = start;
+                        while end < chars.len() && chars[end].is_ascii_digit() {
+                            end += 1;
+                        }
+                        let index = chars[start..end]
+                            .iter()
+                            .collect::<String>()
+                            .parse::<usize>()
+                            .map_err(|_| ExprError::new(i, "expected index or quoted key in '[...]'"))?;
+                        segs.push(PathSeg::Index(index));
+                        i = end;
+                    }
+                }
+                if chars.get(i) != Some(&']') {
+                    return Err(ExprError::new(i, "expected ']'"));
+                }
+                i += 1;
+            }
+            _ => break,
+        }
+    }
+    match segs.first() {
+        Some(PathSeg::Key(_)) => Ok((segs, i)),
+        _ => Err(ExprError::new(i, "path must start with a task id, e.g. $.task_id")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// 表达式末尾位置（用于报错）
+    end: usize,
+    /// 当前嵌套层数
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), ExprError> {
+        let item = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| ExprError::new(self.end, "unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(item)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExprError> {
+        let (pos, tok) = self.next()?;
+        if tok == expected {
+            Ok(())
+        } else {
+            Err(ExprError::new(
+                pos,
+                format!("expected {}, found {}", expected.describe(), tok.describe()),
+            ))
+        }
+    }
+
+    /// 进入一层嵌套解析，超过 [MAX_DEPTH] 时报错
+    fn nested<T>(&mut self, pos: usize, parse: impl FnOnce(&mut Self) -> Result<T, ExprError>) -> Result<T, ExprError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(ExprError::new(pos, format!("expression is nested too deeply (max {})", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_or(&mut self) -> Result<Node, ExprError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Node::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, ExprError> {
+        let mut left = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.parse_not()?;
+            left = Node::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Node, ExprError> {
+        if let Some((pos, Token::Not)) = self.tokens.get(self.pos) {
+            let pos = *pos;
+            self.pos += 1;
+            return Ok(Node::Not(Box::new(self.nested(pos, Self::parse_not)?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Node, ExprError> {
+        let left = self.parse_primary()?;
+        if let Some(Token::Cmp(op)) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            return Ok(Node::Compare(op, Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, ExprError> {
+        let (pos, tok) = self.next()?;
+        match tok {
+            Token::Literal(v) => Ok(Node::Literal(v)),
+            Token::Path(segs) => Ok(Node::Path(segs)),
+            Token::LParen => {
+                let inner = self.nested(pos, Self::parse_or)?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Token::Ident(name) => {
+                let func =
+                    Func::parse(&name).ok_or_else(|| ExprError::new(pos, format!("unknown function '{}'", name)))?;
+                self.expect(Token::LParen)?;
+                let args = self.nested(pos, |parser| {
+                    let mut args = Vec::new();
+                    if parser.peek() != Some(&Token::RParen) {
+                        args.push(parser.parse_or()?);
+                        while parser.peek() == Some(&Token::Comma) {
+                            parser.pos += 1;
+                            args.push(parser.parse_or()?);
+                        }
+                    }
+                    Ok(args)
+                })?;
+                self.expect(Token::RParen)?;
+                if args.len() != func.arity() {
+                    return Err(ExprError::new(
+                        pos,
+                        format!("{}() takes {} argument(s), got {}", name, func.arity(), args.len()),
+                    ));
+                }
+                Ok(Node::Call(func, args))
+            }
+            other => Err(ExprError::new(pos, format!("unexpected {}", other.describe()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn outputs() -> HashMap<TaskId, Value> {
+        let mut outputs = HashMap::new();
+        outputs.insert(
+            "search".to_string(),
+            json!({ "status": "ok", "count": 3, "items": [{ "title": "Rust" }], "tags": ["a", "b"] }),
+        );
+        outputs.insert("check-1".to_string(), parse_output("needs review: 2 issues"));
+        outputs.insert("score".to_string(), parse_output(" 42\n"));
+        outputs
+    }
+
+    fn eval_str(src: &str) -> bool {
+        Expression::parse(src).unwrap().is_met(&outputs())
+    }
+
+    #[test]
+    fn test_expression_evaluation() {
+        assert!(eval_str(r#"$.search.status == "ok" && $.search.count > 2"#));
+        assert!(eval_str("$.search.items[0].title == 'Rust'"));
+        assert!(eval_str(r#"$["check-1"] != null && contains($.check-1, "review")"#));
+        assert!(eval_str("$.score >= 42 && !($.score < 10)"));
+        assert!(eval_str("len($.search.items) == 1 && contains($.search.tags, 'b')"));
+        assert!(eval_str("$.missing.field == null || false"));
+        assert!(!eval_str("$.search.items[5]"));
+        assert!(!eval_str("$.search.status > 3"));
+        assert!(eval_str("starts_with($.check-1, 'needs')"));
+
+        let expr = Expression::parse("$.a.x == $.b || contains($.a.y, 1)").unwrap();
+        assert_eq!(expr.task_refs(), vec!["a".to_string(), "b".to_string()]);
+
+        assert_eq!(
+            interpolate("Top: {{ $.search.items[0].title }}, n={{$.search.count}}{{$.nope}} {{name}}", &outputs()),
+            "Top: Rust, n=3 {{name}}"
+        );
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        assert!(Expression::parse("$.a ==").is_err());
+        assert!(Expression::parse("$.a == 'x").is_err());
+        assert!(Expression::parse("$[0] == 1").is_err());
+        assert!(Expression::parse("unknown($.a)").is_err());
+        assert!(Expression::parse("len($.a, $.b)").is_err());
+        assert!(Expression::parse("($.a").is_err());
+        assert!(Expression::parse("$.a $.b").is_err());
+
+        // 嵌套过深时报错而不是栈溢出
+        let deep = format!("{}$.a{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert!(Expression::parse(&deep).unwrap_err().message.contains("nested too deeply"));
+        assert!(Expression::parse(&format!("{}$.a", "!".repeat(10_000))).is_err());
+        assert!(Expression::parse(&format!("{}$.a{}", "len(".repeat(10_000), ")".repeat(10_000))).is_err());
+        let ok = format!("{}$.a{}", "(".repeat(MAX_DEPTH - 1), ")".repeat(MAX_DEPTH - 1));
+        assert!(Expression::parse(&ok).is_ok());
+    }
+}
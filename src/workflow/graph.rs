@@ -1,8 +1,9 @@
 //! 工作流依赖图
 //!
-//! 使用邻接表和入度表实现 DAG 拓扑排序
+//! 使用邻接表和入度表实现 DAG 拓扑排序；可导出为 Mermaid / Graphviz DOT 图（按任务状态着色）
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use crate::workflow::types::*;
 
 /// 工作流依赖图
@@ -11,6 +12,16 @@ pub struct WorkflowGraph {
     pub adjacency: HashMap<TaskId, Vec<TaskId>>,
     /// 入度表：任务 ID -> 未完成的依赖数
     pub in_degree: HashMap<TaskId, usize>,
+    /// 因前置任务被跳过而随之跳过的任务（未走到的分支）
+    pub blocked: HashSet<TaskId>,
+    /// OR / 法定数依赖：任务 ID -> 已成功的前置任务数
+    pub successes: HashMap<TaskId, usize>,
+    /// 边标签（条件表达式、汇合策略）：(前置任务, 任务) -> 标签
+    pub labels: HashMap<(TaskId, TaskId), String>,
+    /// 失败备用：任务 ID -> 备用任务 ID
+    pub fallbacks: HashMap<TaskId, TaskId>,
+    /// 失败补偿：任务 ID -> 补偿任务 ID
+    pub compensations: HashMap<TaskId, TaskId>,
 }
 
 impl WorkflowGraph {
@@ -18,6 +29,9 @@ impl WorkflowGraph {
     pub fn new(tasks: &HashMap<TaskId, WorkflowTask>) -> Self {
         let mut adjacency: HashMap<TaskId, Vec<TaskId>> = HashMap::new();
         let mut in_degree: HashMap<TaskId, usize> = HashMap::new();
+        let mut labels = HashMap::new();
+        let mut fallbacks = HashMap::new();
+        let mut compensations = HashMap::new();
 
         for task_id in tasks.keys() {
             in_degree.insert(task_id.clone(), 0);
@@ -25,32 +39,176 @@ impl WorkflowGraph {
         }
 
         for (task_id, task) in tasks {
+            if let Some(fallback) = &task.fallback {
+                fallbacks.insert(task_id.clone(), fallback.clone());
+            }
+            if let Some(compensation) = &task.compensation {
+                compensations.insert(task_id.clone(), compensation.clone());
+            }
+            let join_label = match &task.dependencies {
+                TaskDependencies::Any(_) => Some("first_success".to_string()),
+                TaskDependencies::Quorum { min, .. } => Some(format!("quorum {}", min)),
+                _ => None,
+            };
             match &task.dependencies {
                 TaskDependencies::None => {}
                 TaskDependencies::Sequential(dep_id) => {
                     adjacency.entry(dep_id.clone()).or_default().push(task_id.clone());
                     *in_degree.entry(task_id.clone()).or_insert(0) += 1;
                 }
-                TaskDependencies::All(dep_ids) => {
+                TaskDependencies::All(dep_ids)
+                | TaskDependencies::Any(dep_ids)
+                | TaskDependencies::Quorum { task_ids: dep_ids, .. } => {
                     for dep_id in dep_ids {
                         adjacency.entry(dep_id.clone()).or_default().push(task_id.clone());
                         *in_degree.entry(task_id.clone()).or_insert(0) += 1;
+                        if let Some(label) = &join_label {
+                            labels.insert((dep_id.clone(), task_id.clone()), label.clone());
+                        }
                     }
                 }
-                TaskDependencies::Any(dep_ids) => {
-                    for dep_id in dep_ids {
-                        adjacency.entry(dep_id.clone()).or_default().push(task_id.clone());
-                    }
-                    in_degree.insert(task_id.clone(), 1);
-                }
-                TaskDependencies::Condition { task_id: dep_id, .. } => {
+                TaskDependencies::Condition { task_id: dep_id, predicate } => {
                     adjacency.entry(dep_id.clone()).or_default().push(task_id.clone());
                     *in_degree.entry(task_id.clone()).or_insert(0) += 1;
+                    let label = match predicate {
+                        ConditionPredicate::Success => "success".to_string(),
+                        ConditionPredicate::ResultContains(text) => format!("contains \"{}\"", text),
+                        ConditionPredicate::Expression(source) => source.clone(),
+                    };
+                    labels.insert((dep_id.clone(), task_id.clone()), label);
+                }
+            }
+        }
+
+        Self {
+            adjacency,
+            in_degree,
+            blocked: HashSet::new(),
+            successes: HashMap::new(),
+            labels,
+            fallbacks,
+            compensations,
+        }
+    }
+
+    /// 所有依赖边 (前置任务, 任务, 标签)，按 ID 排序
+    fn edges(&self) -> Vec<(&TaskId, &TaskId, Option<&String>)> {
+        let mut edges: Vec<_> = self
+            .adjacency
+            .iter()
+            .flat_map(|(from, dependents)| dependents.iter().map(move |to| (from, to)))
+            .map(|(from, to)| (from, to, self.labels.get(&(from.clone(), to.clone()))))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// 按 ID 排序的任务
+    fn sorted_ids(&self) -> Vec<&TaskId> {
+        let mut ids: Vec<&TaskId> = self.in_degree.keys().collect();
+        ids.sort();
+        ids
+    }
+
+    /// 备用与补偿边 (任务, 目标任务, 标签)，按 ID 排序
+    fn standby_edges(&self) -> Vec<(&TaskId, &TaskId, &'static str)> {
+        let mut edges: Vec<_> = self
+            .fallbacks
+            .iter()
+            .map(|(task, target)| (task, target, "fallback"))
+            .chain(self.compensations.iter().map(|(task, target)| (task, target, "compensate")))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    /// 导出 Mermaid 流程图：依赖边带条件 / 汇合标签，备用与补偿边为虚线；`states` 中有状态的任务按状态着色
+    pub fn to_mermaid(&self, states: &HashMap<TaskId, TaskState>) -> String {
+        let ids = self.sorted_ids();
+        // Mermaid 节点 ID 用序号，任务 ID 作为标签，避免特殊字符
+        let node: HashMap<&TaskId, String> = ids.iter().enumerate().map(|(i, id)| (*id, format!("n{}", i))).collect();
+        let escape = |text: &str| text.replace('"', "#quot;");
+
+        let mut out = String::from("flowchart TD\n");
+        for id in &ids {
+            let _ = writeln!(out, "    {}[\"{}\"]", node[id], escape(id));
+        }
+        for (from, to, label) in self.edges() {
+            match label {
+                Some(label) => {
+                    let _ = writeln!(out, "    {} -->|\"{}\"| {}", node[from], escape(label), node[to]);
+                }
+                None => {
+                    let _ = writeln!(out, "    {} --> {}", node[from], node[to]);
                 }
             }
         }
+        for (task, target, label) in self.standby_edges() {
+            if let (Some(from), Some(to)) = (node.get(task), node.get(target)) {
+                let _ = writeln!(out, "    {} -.->|{}| {}", from, label, to);
+            }
+        }
+        for state in STATE_STYLES {
+            let members: Vec<&str> = ids
+                .iter()
+                .filter(|id| states.get(**id) == Some(&state.state))
+                .map(|id| node[id].as_str())
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            let dash = if state.dashed { ",stroke-dasharray:4 4" } else { "" };
+            let _ = writeln!(out, "    classDef {} fill:{},stroke:{},color:#0f172a{}", state.name, state.fill, state.stroke, dash);
+            let _ = writeln!(out, "    class {} {}", members.join(","), state.name);
+        }
+        out
+    }
 
-        Self { adjacency, in_degree }
+    /// 导出 Graphviz DOT 图，内容与 [`to_mermaid`](Self::to_mermaid) 相同
+    pub fn to_dot(&self, states: &HashMap<TaskId, TaskState>) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+
+        let mut out = String::from("digraph workflow {\n    rankdir=TB;\n");
+        out.push_str("    node [shape=box, style=\"rounded,filled\", fillcolor=\"#ffffff\", color=\"#94a3b8\"];\n");
+        for id in self.sorted_ids() {
+            match states.get(id).and_then(|s| STATE_STYLES.iter().find(|style| style.state == *s)) {
+                Some(style) => {
+                    let dashed = if style.dashed { ", style=\"rounded,filled,dashed\"" } else { "" };
+                    let _ = writeln!(
+                        out,
+                        "    \"{}\" [fillcolor=\"{}\", color=\"{}\"{}];",
+                        escape(id),
+                        style.fill,
+                        style.stroke,
+                        dashed
+                    );
+                }
+                None => {
+                    let _ = writeln!(out, "    \"{}\";", escape(id));
+                }
+            }
+        }
+        for (from, to, label) in self.edges() {
+            match label {
+                Some(label) => {
+                    let _ = writeln!(out, "    \"{}\" -> \"{}\" [label=\"{}\"];", escape(from), escape(to), escape(label));
+                }
+                None => {
+                    let _ = writeln!(out, "    \"{}\" -> \"{}\";", escape(from), escape(to));
+                }
+            }
+        }
+        for (task, target, label) in self.standby_edges() {
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\", style=dashed];",
+                escape(task),
+                escape(target),
+                label
+            );
+        }
+        out.push_str("}\n");
+        out
     }
 
     /// 获取可执行的任务（入度为 0 且未执行）
@@ -64,41 +222,71 @@ impl WorkflowGraph {
             .collect()
     }
 
-    /// 更新任务完成状态，返回新变为可执行的任务
+    /// 更新任务结束状态，返回新变为可执行的任务及其是否应执行（false 表示跳过）
+    ///
+    /// - 条件依赖按谓词对 `outputs` 求值，不满足时跳过
+    /// - 顺序 / AND 依赖中任一前置任务被跳过，则随之跳过（未走到的分支整体跳过）
+    /// - OR / 法定数依赖在成功数达到要求时立即执行（其余分支继续运行但不再影响），
+    ///   剩余分支不足以达到要求时跳过
     pub fn mark_completed(
         &mut self,
         completed_task_id: &TaskId,
-        _tasks: &HashMap<TaskId, WorkflowTask>,
+        tasks: &HashMap<TaskId, WorkflowTask>,
         completed_task_state: TaskState,
+        outputs: &HashMap<TaskId, serde_json::Value>,
     ) -> Vec<(TaskId, bool)> {
         let mut newly_ready = Vec::new();
 
-        if let Some(dependents) = self.adjacency.get(completed_task_id) {
-            for dependent_id in dependents {
-                if let Some(degree) = self.in_degree.get_mut(dependent_id) {
-                    if let Some(task) = _tasks.get(dependent_id) {
-                        match &task.dependencies {
-                            TaskDependencies::Any(_) => {
-                                if completed_task_state == TaskState::Completed {
-                                    *degree = 0;
-                                }
-                            }
-                            TaskDependencies::Condition { .. } => {
-                                let condition_met = completed_task_state == TaskState::Completed;
-                                *degree -= 1;
-                                if *degree == 0 {
-                                    newly_ready.push((dependent_id.clone(), condition_met));
-                                    continue;
-                                }
-                            }
-                            _ => {
-                                *degree -= 1;
-                            }
-                        }
+        let Some(dependents) = self.adjacency.get(completed_task_id) else {
+            return newly_ready;
+        };
+        for dependent_id in dependents {
+            let Some(task) = tasks.get(dependent_id) else {
+                continue;
+            };
+            // 已触发或已跳过（如 OR 依赖的其它前置任务先成功）
+            if task.state != TaskState::Waiting {
+                continue;
+            }
+            let Some(degree) = self.in_degree.get_mut(dependent_id) else {
+                continue;
+            };
+            if *degree == 0 {
+                continue;
+            }
+            match &task.dependencies {
+                TaskDependencies::Any(_) | TaskDependencies::Quorum { .. } => {
+                    let min = match &task.dependencies {
+                        TaskDependencies::Quorum { min, .. } => *min,
+                        _ => 1,
+                    };
+                    let successes = self.successes.entry(dependent_id.clone()).or_insert(0);
+                    if completed_task_state == TaskState::Completed {
+                        *successes += 1;
                     }
-
-                    if *degree == 0 {
+                    *degree -= 1;
+                    if *successes >= min {
+                        *degree = 0;
                         newly_ready.push((dependent_id.clone(), true));
+                    } else if *successes + *degree < min {
+                        *degree = 0;
+                        newly_ready.push((dependent_id.clone(), false));
+                    }
+                }
+                TaskDependencies::Condition { predicate, .. } => {
+                    let condition_met = predicate.is_met(completed_task_id, completed_task_state, outputs);
+                    *degree -= 1;
+                    if *degree == 0 {
+                        newly_ready.push((dependent_id.clone(), condition_met));
+                    }
+                }
+                _ => {
+                    if completed_task_state == TaskState::Skipped {
+                        self.blocked.insert(dependent_id.clone());
+                    }
+                    *degree -= 1;
+                    if *degree == 0 {
+                        newly_ready.push((dependent_id.clone(), !self.blocked.contains(dependent_id)));
                     }
                 }
             }
@@ -108,6 +296,25 @@ impl WorkflowGraph {
     }
 }
 
+/// 导出图中任务状态的样式
+struct StateStyle {
+    state: TaskState,
+    name: &'static str,
+    fill: &'static str,
+    stroke: &'static str,
+    dashed: bool,
+}
+
+const STATE_STYLES: &[StateStyle] = &[
+    StateStyle { state: TaskState::Waiting, name: "waiting", fill: "#f8fafc", stroke: "#94a3b8", dashed: false },
+    StateStyle { state: TaskState::Ready, name: "ready", fill: "#fef9c3", stroke: "#ca8a04", dashed: false },
+    StateStyle { state: TaskState::Pending, name: "pending", fill: "#fef9c3", stroke: "#ca8a04", dashed: false },
+    StateStyle { state: TaskState::Running, name: "running", fill: "#dbeafe", stroke: "#2563eb", dashed: false },
+    StateStyle { state: TaskState::Completed, name: "completed", fill: "#dcfce7", stroke: "#16a34a", dashed: false },
+    StateStyle { state: TaskState::Failed, name: "failed", fill: "#fee2e2", stroke: "#dc2626", dashed: false },
+    StateStyle { state: TaskState::Skipped, name: "skipped", fill: "#f1f5f9", stroke: "#cbd5e1", dashed: true },
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,7 +331,10 @@ mod tests {
             ))),
             dependencies: deps,
             fallback: None,
+            compensation: None,
             state: TaskState::Waiting,
+            retry: RetryPolicy::default(),
+            idempotency_key: None,
         }
     }
 
@@ -157,4 +367,104 @@ mod tests {
         assert_eq!(ready.len(), 1);
         assert_eq!(ready[0], "task1");
     }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_expression_branch() {
+        let condition = |expr: &str| TaskDependencies::Condition {
+            task_id: "check".to_string(),
+            predicate: ConditionPredicate::Expression(expr.to_string()),
+        };
+        let mut tasks = HashMap::new();
+        tasks.insert("check".to_string(), create_test_task("check", TaskDependencies::None));
+        tasks.insert("fix".to_string(), create_test_task("fix", condition("$.check.issues > 0")));
+        tasks.insert("ship".to_string(), create_test_task("ship", condition("!($.check.issues > 0)")));
+        tasks.insert("notify".to_string(), create_test_task("notify", TaskDependencies::Sequential("ship".to_string())));
+
+        let mut graph = WorkflowGraph::new(&tasks);
+        let mut outputs = HashMap::new();
+        outputs.insert("check".to_string(), serde_json::json!({ "issues": 2 }));
+        tasks.get_mut("check").unwrap().state = TaskState::Completed;
+
+        let mut ready = graph.mark_completed(&"check".to_string(), &tasks, TaskState::Completed, &outputs);
+        ready.sort();
+        assert_eq!(ready, vec![("fix".to_string(), true), ("ship".to_string(), false)]);
+
+        // 未走到的分支：下游随之跳过
+        tasks.get_mut("ship").unwrap().state = TaskState::Skipped;
+        let ready = graph.mark_completed(&"ship".to_string(), &tasks, TaskState::Skipped, &outputs);
+        assert_eq!(ready, vec![("notify".to_string(), false)]);
+    }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_quorum_join() {
+        let branches: Vec<TaskId> = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut tasks = HashMap::new();
+        for id in &branches {
+            tasks.insert(id.clone(), create_test_task(id, TaskDependencies::None));
+        }
+        tasks.insert(
+            "merge".to_string(),
+            create_test_task("merge", TaskDependencies::Quorum { task_ids: branches.clone(), min: 2 }),
+        );
+        let outputs = HashMap::new();
+
+        // 1 失败 + 1 成功：尚未达到法定数
+        let mut graph = WorkflowGraph::new(&tasks);
+        assert!(graph.mark_completed(&"a".to_string(), &tasks, TaskState::Failed, &outputs).is_empty());
+        assert!(graph.mark_completed(&"b".to_string(), &tasks, TaskState::Completed, &outputs).is_empty());
+        let ready = graph.mark_completed(&"c".to_string(), &tasks, TaskState::Completed, &outputs);
+        assert_eq!(ready, vec![("merge".to_string(), true)]);
+
+        // 2 失败：剩余分支已不可能达到法定数，提前跳过
+        let mut graph = WorkflowGraph::new(&tasks);
+        assert!(graph.mark_completed(&"a".to_string(), &tasks, TaskState::Failed, &outputs).is_empty());
+        let ready = graph.mark_completed(&"b".to_string(), &tasks, TaskState::Failed, &outputs);
+        assert_eq!(ready, vec![("merge".to_string(), false)]);
+    }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn test_export_mermaid_and_dot() {
+        let mut tasks = HashMap::new();
+        tasks.insert("check".to_string(), create_test_task("check", TaskDependencies::None));
+        tasks.insert(
+            "fix".to_string(),
+            create_test_task(
+                "fix",
+                TaskDependencies::Condition {
+                    task_id: "check".to_string(),
+                    predicate: ConditionPredicate::Expression("$.check.msg == \"bad\"".to_string()),
+                },
+            ),
+        );
+        tasks.insert("report".to_string(), create_test_task("report", TaskDependencies::None));
+        tasks.insert("undo".to_string(), create_test_task("undo", TaskDependencies::None));
+        tasks.get_mut("fix").unwrap().fallback = Some("report".to_string());
+        tasks.get_mut("check").unwrap().compensation = Some("undo".to_string());
+
+        let graph = WorkflowGraph::new(&tasks);
+        let mut states = HashMap::new();
+        states.insert("check".to_string(), TaskState::Completed);
+        states.insert("fix".to_string(), TaskState::Running);
+
+        let mermaid = graph.to_mermaid(&states);
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("    n0[\"check\"]\n"));
+        assert!(mermaid.contains("    n0 -->|\"$.check.msg == #quot;bad#quot;\"| n1\n"));
+        assert!(mermaid.contains("    n1 -.->|fallback| n2\n"));
+        assert!(mermaid.contains("    n0 -.->|compensate| n3\n"));
+        assert!(mermaid.contains("    class n0 completed\n"));
+        assert!(mermaid.contains("    class n1 running\n"));
+        assert!(!mermaid.contains("class n2"));
+
+        let dot = graph.to_dot(&states);
+        assert!(dot.starts_with("digraph workflow {\n"));
+        assert!(dot.contains(r#"    "check" -> "fix" [label="$.check.msg == \"bad\""];"#));
+        assert!(dot.contains(r#"    "fix" -> "report" [label="fallback", style=dashed];"#));
+        assert!(dot.contains(r#"    "check" -> "undo" [label="compensate", style=dashed];"#));
+        assert!(dot.contains(r##"    "check" [fillcolor="#dcfce7", color="#16a34a"];"##));
+        assert!(dot.trim_end().ends_with('}'));
+    }
 }
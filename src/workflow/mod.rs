@@ -1,9 +1,16 @@
 pub mod types;
 pub mod graph;
 pub mod builder;
+pub mod definition;
 pub mod engine;
+pub mod expr;
+pub mod store;
+pub mod templates;
 
 pub use types::*;
 pub use graph::WorkflowGraph;
 pub use builder::WorkflowBuilder;
+pub use definition::{EdgeDefinition, NodeDefinition, NodeKind, ParamDefinition, WorkflowDefinition};
 pub use engine::{WorkflowEngine, WorkflowTaskExecutor};
+pub use expr::{ExprError, Expression};
+pub use store::WorkflowStore;
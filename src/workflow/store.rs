@@ -0,0 +1,174 @@
+//! 工作流运行持久化：运行状态与幂等日志存于 workspace.db
+//!
+//! - `workflow_runs`：每次状态变化后整体保存工作流（各任务状态、已完成任务输出、错误），
+//!   进程重启后 [`WorkflowEngine::resume`](crate::workflow::WorkflowEngine::resume) 从未结束的运行继续
+//! - `workflow_idempotency`：带幂等键的任务成功后记录结果；恢复后重新提交的同键任务直接复用结果，
+//!   不再重复执行副作用。调度与副作用都是至少一次的：执行成功但结果记入日志前中断的任务仍会重新执行，
+//!   需要严格去重的执行器应把幂等键（任务元数据 `idempotency_key`）转给外部系统
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::core::workspace_store::{StoreError, WORKSPACE_DB_FILE};
+use crate::workflow::types::*;
+
+/// 幂等日志保留时长（毫秒），超过后同键任务会重新执行
+pub const IDEMPOTENCY_RETENTION_MS: i64 = 7 * 24 * 3600 * 1000;
+
+/// 工作流运行存储
+pub struct WorkflowStore {
+    conn: Mutex<Connection>,
+}
+
+impl WorkflowStore {
+    pub fn open(workspace: &Path) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(workspace)?;
+        Self::open_at(&workspace.join(WORKSPACE_DB_FILE))
+    }
+
+    pub fn open_at(db_path: &Path) -> Result<Self, StoreError> {
+        let conn = Connection::open(db_path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS workflow_runs (
+                 id TEXT PRIMARY KEY,
+                 user_id TEXT NOT NULL,
+                 finished INTEGER NOT NULL,
+                 updated_at INTEGER NOT NULL,
+                 data TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS workflow_runs_finished ON workflow_runs (finished, updated_at);
+             CREATE TABLE IF NOT EXISTS workflow_idempotency (
+                 key TEXT PRIMARY KEY,
+                 workflow_id TEXT NOT NULL,
+                 task_id TEXT NOT NULL,
+                 result TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 保存（覆盖）工作流当前状态
+    pub fn save(&self, workflow: &Workflow) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO workflow_runs (id, user_id, finished, updated_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                workflow.id,
+                workflow.user_id,
+                workflow.status.is_finished(),
+                chrono::Utc::now().timestamp_millis(),
+                serde_json::to_string(workflow)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Workflow>, StoreError> {
+        let data: Option<String> = self
+            .conn()
+            .query_row("SELECT data FROM workflow_runs WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+    }
+
+    /// 未结束的运行（重启后待恢复），按更新时间先后
+    pub fn unfinished(&self) -> Result<Vec<Workflow>, StoreError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM workflow_runs WHERE finished = 0 ORDER BY updated_at")?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows.iter().filter_map(|d| serde_json::from_str(d).ok()).collect())
+    }
+
+    /// 只保留最近 `keep` 个已结束的运行，并清理过期的幂等日志；返回删除的运行数
+    pub fn prune_finished(&self, keep: usize) -> Result<usize, StoreError> {
+        let conn = self.conn();
+        let removed = conn.execute(
+            "DELETE FROM workflow_runs WHERE finished = 1 AND id NOT IN (
+                 SELECT id FROM workflow_runs WHERE finished = 1 ORDER BY updated_at DESC LIMIT ?1
+             )",
+            [keep as i64],
+        )?;
+        conn.execute(
+            "DELETE FROM workflow_idempotency WHERE created_at < ?1",
+            [chrono::Utc::now().timestamp_millis() - IDEMPOTENCY_RETENTION_MS],
+        )?;
+        Ok(removed)
+    }
+
+    /// 幂等键已记录的成功结果
+    pub fn idempotent_result(&self, key: &str) -> Result<Option<String>, StoreError> {
+        Ok(self
+            .conn()
+            .query_row("SELECT result FROM workflow_idempotency WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    /// 记录幂等键的成功结果；同键已有记录时保留先前的结果
+    pub fn record_idempotent(
+        &self,
+        key: &str,
+        workflow_id: &WorkflowId,
+        task_id: &TaskId,
+        result: &str,
+    ) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO workflow_idempotency (key, workflow_id, task_id, result, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![key, workflow_id, task_id, result, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::WorkflowBuilder;
+
+    #[test]
+    fn test_store_roundtrip_and_idempotency() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = WorkflowStore::open(dir.path()).unwrap();
+
+        let mut workflow = WorkflowBuilder::new("Persisted")
+            .user_id("user1".to_string())
+            .build()
+            .unwrap();
+        workflow.status = WorkflowStatus::Running;
+        workflow.outputs.insert("a".to_string(), serde_json::json!({ "n": 1 }));
+        store.save(&workflow).unwrap();
+
+        let loaded = store.get(&workflow.id).unwrap().unwrap();
+        assert_eq!(loaded.outputs["a"]["n"], 1);
+        assert_eq!(store.unfinished().unwrap().len(), 1);
+
+        workflow.status = WorkflowStatus::Completed;
+        store.save(&workflow).unwrap();
+        assert!(store.unfinished().unwrap().is_empty());
+        assert_eq!(store.prune_finished(0).unwrap(), 1);
+        assert!(store.get(&workflow.id).unwrap().is_none());
+
+        assert!(store.idempotent_result("charge-1").unwrap().is_none());
+        store
+            .record_idempotent("charge-1", &workflow.id, &"pay".to_string(), "ok")
+            .unwrap();
+        store
+            .record_idempotent("charge-1", &workflow.id, &"pay".to_string(), "again")
+            .unwrap();
+        assert_eq!(store.idempotent_result("charge-1").unwrap().as_deref(), Some("ok"));
+    }
+}
@@ -0,0 +1,81 @@
+//! 内置工作流模板
+//!
+//! 模板即随程序发布的 [`WorkflowDefinition`]（`src/workflow/templates/*.toml`），用 `[[params]]` 声明输入参数：
+//!
+//! - `research-report`：多轮深度搜索 → 提纲 → 撰写调研报告（`topic`，`audience?`）
+//! - `code-fix-pr`：修改代码 → 运行测试 → 通过则创建 PR，否则汇报原因（`issue`，`title?`）
+//! - `daily-digest`：抓取订阅源 → 摘要 → 发送（`feed_url`，`focus?`）
+//!
+//! 直接运行见 [`WorkflowBuilder::from_template`](crate::workflow::WorkflowBuilder::from_template)；
+//! [`instantiate`] 生成以参数为默认值的新定义，可保存后按需修改；其它定义也可用 `workflow` 节点引用模板 ID。
+
+use crate::workflow::definition::WorkflowDefinition;
+use crate::workflow::types::WorkflowError;
+
+/// 模板 ID 与定义文本
+const BUILTIN: &[(&str, &str)] = &[
+    ("research-report", include_str!("templates/research-report.toml")),
+    ("code-fix-pr", include_str!("templates/code-fix-pr.toml")),
+    ("daily-digest", include_str!("templates/daily-digest.toml")),
+];
+
+/// 所有内置模板
+pub fn list() -> Vec<WorkflowDefinition> {
+    BUILTIN.iter().filter_map(|(id, _)| get(id)).collect()
+}
+
+/// 按 ID 取内置模板
+pub fn get(id: &str) -> Option<WorkflowDefinition> {
+    let (id, content) = BUILTIN.iter().find(|(template_id, _)| *template_id == id)?;
+    let mut def = WorkflowDefinition::parse(content, "toml").ok()?;
+    def.id = id.to_string();
+    Some(def)
+}
+
+/// 由模板生成新定义 `id`：`params` 中的值成为对应参数的默认值（未声明的参数报错）
+pub fn instantiate(
+    template_id: &str,
+    id: &str,
+    params: &serde_json::Map<String, serde_json::Value>,
+) -> Result<WorkflowDefinition, WorkflowError> {
+    let mut def = get(template_id)
+        .ok_or_else(|| WorkflowError::InvalidConfiguration(format!("unknown template '{}'", template_id)))?;
+    for (name, value) in params {
+        let param = def.params.iter_mut().find(|p| &p.name == name).ok_or_else(|| {
+            WorkflowError::InvalidConfiguration(format!("template {} has no parameter '{}'", template_id, name))
+        })?;
+        param.default = Some(value.clone());
+    }
+    def.id = id.to_string();
+    def.validate()?;
+    Ok(def)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates() {
+        let templates = list();
+        assert_eq!(templates.len(), BUILTIN.len());
+        for def in &templates {
+            def.validate().unwrap();
+            assert!(!def.params.is_empty(), "{} declares no params", def.id);
+        }
+
+        let report = get("research-report").unwrap();
+        assert!(report.resolve_input(serde_json::json!({})).is_err());
+        let input = report.resolve_input(serde_json::json!({ "topic": "WASI" })).unwrap();
+        assert_eq!(input["audience"], "技术团队");
+
+        let params = serde_json::json!({ "topic": "WASI" });
+        let def = instantiate("research-report", "wasi-report", params.as_object().unwrap()).unwrap();
+        assert_eq!(def.id, "wasi-report");
+        assert_eq!(def.resolve_input(serde_json::Value::Null).unwrap()["topic"], "WASI");
+
+        let unknown = serde_json::json!({ "nope": 1 });
+        assert!(instantiate("research-report", "x", unknown.as_object().unwrap()).is_err());
+        assert!(instantiate("missing", "x", &serde_json::Map::new()).is_err());
+    }
+}
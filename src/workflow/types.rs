@@ -6,6 +6,8 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::workflow::expr::Expression;
+
 #[cfg(feature = "gateway")]
 use crate::gateway::BackgroundTask;
 
@@ -27,6 +29,15 @@ pub enum WorkflowStatus {
     Cancelled,
     /// 已暂停
     Paused,
+    /// 执行失败，正在逆序运行已完成任务的补偿步骤（结束后为 `Failed`）
+    Compensating,
+}
+
+impl WorkflowStatus {
+    /// 是否已结束（完成、失败或取消）
+    pub fn is_finished(&self) -> bool {
+        matches!(self, WorkflowStatus::Completed | WorkflowStatus::Failed | WorkflowStatus::Cancelled)
+    }
 }
 
 /// 任务状态
@@ -49,6 +60,7 @@ pub enum TaskState {
 }
 
 /// 工作流定义
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Workflow {
     /// 工作流唯一标识
     pub id: WorkflowId,
@@ -62,6 +74,24 @@ pub struct Workflow {
     pub session_id: Option<String>,
     /// 工作流中的所有任务
     pub tasks: HashMap<TaskId, WorkflowTask>,
+    /// 已完成任务的输出（能解析为 JSON 时按 JSON，否则为字符串），供条件表达式求值
+    pub outputs: HashMap<TaskId, serde_json::Value>,
+    /// 失败任务的错误信息
+    pub errors: HashMap<TaskId, String>,
+    /// 来源定义 ID（由工作流定义文件构建时）
+    pub definition_id: Option<String>,
+    /// 作为子工作流运行时的父运行与节点：(父工作流 ID, 任务 ID)
+    #[serde(default)]
+    pub parent: Option<(WorkflowId, TaskId)>,
+    /// 子工作流节点已启动的子运行：任务 ID -> 子工作流 ID
+    #[serde(default)]
+    pub children: HashMap<TaskId, WorkflowId>,
+    /// 成功完成的任务，按完成先后排列（失败时据此逆序补偿）
+    #[serde(default)]
+    pub completed_order: Vec<TaskId>,
+    /// 补偿阶段待执行的补偿任务，首个为正在执行的任务
+    #[serde(default)]
+    pub compensations: Vec<TaskId>,
     /// 当前状态
     pub status: WorkflowStatus,
     /// 创建时间
@@ -73,6 +103,7 @@ pub struct Workflow {
 }
 
 /// 工作流中的任务节点
+#[derive(Clone, Serialize, Deserialize)]
 pub struct WorkflowTask {
     /// 任务ID
     pub id: TaskId,
@@ -82,28 +113,120 @@ pub struct WorkflowTask {
     pub dependencies: TaskDependencies,
     /// 失败时的备用任务ID
     pub fallback: Option<TaskId>,
+    /// 补偿任务ID：本任务成功后若工作流最终失败，按完成的逆序执行以撤销其副作用
+    #[serde(default)]
+    pub compensation: Option<TaskId>,
     /// 执行状态
     pub state: TaskState,
+    /// 重试与超时
+    pub retry: RetryPolicy,
+    /// 幂等键（支持 `{{$.task.field}}` 占位符）：有副作用的任务设置后，
+    /// 成功结果记入幂等日志，恢复运行时同键任务不再重复执行
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// 任务执行策略：失败后重试次数与单次执行超时
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// 失败后的重试次数（0 表示不重试）
+    #[serde(default)]
+    pub retries: u32,
+    /// 单次执行超时（秒），超时计为失败
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// 工作流运行快照（供 API 查询）
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowSnapshot {
+    pub id: WorkflowId,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definition_id: Option<String>,
+    /// 作为子工作流运行时的父运行 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<WorkflowId>,
+    pub user_id: String,
+    pub status: WorkflowStatus,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub completed_at: Option<i64>,
+    /// 按任务 ID 排序
+    pub tasks: Vec<TaskSnapshot>,
+}
+
+/// 任务运行快照
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskSnapshot {
+    pub id: TaskId,
+    pub state: TaskState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<&Workflow> for WorkflowSnapshot {
+    fn from(workflow: &Workflow) -> Self {
+        let mut tasks: Vec<TaskSnapshot> = workflow
+            .tasks
+            .values()
+            .map(|task| TaskSnapshot {
+                id: task.id.clone(),
+                state: task.state,
+                output: workflow.outputs.get(&task.id).cloned(),
+                error: workflow.errors.get(&task.id).cloned(),
+            })
+            .collect();
+        tasks.sort_by(|a, b| a.id.cmp(&b.id));
+        Self {
+            id: workflow.id.clone(),
+            name: workflow.name.clone(),
+            definition_id: workflow.definition_id.clone(),
+            parent_id: workflow.parent.as_ref().map(|(id, _)| id.clone()),
+            user_id: workflow.user_id.clone(),
+            status: workflow.status,
+            created_at: workflow.created_at,
+            started_at: workflow.started_at,
+            completed_at: workflow.completed_at,
+            tasks,
+        }
+    }
 }
 
 /// 任务定义
 #[cfg(feature = "gateway")]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum TaskDefinition {
     /// 简单任务：复用现有的BackgroundTask
     Simple(Box<BackgroundTask>),
-    /// 子工作流：嵌套另一个工作流
-    SubWorkflow(Box<Workflow>),
-    /// 并行任务组：Map模式
-    Parallel(Vec<Box<BackgroundTask>>),
+    /// 子工作流：每次执行以新的子运行执行该工作流，`input` 代入占位符后作为子工作流的 `$.input`；
+    /// 节点输出为子工作流各任务输出
+    SubWorkflow {
+        workflow: Box<Workflow>,
+        input: serde_json::Value,
+    },
+    /// 并行任务组：分支并发执行，按汇合策略决定节点成败
+    Parallel {
+        tasks: Vec<Box<BackgroundTask>>,
+        join: JoinStrategy,
+    },
 }
 
 #[cfg(not(feature = "gateway"))]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum TaskDefinition {
-    /// 子工作流：嵌套另一个工作流
-    SubWorkflow(Box<Workflow>),
+    /// 子工作流：每次执行以新的子运行执行该工作流，`input` 代入占位符后作为子工作流的 `$.input`；
+    /// 节点输出为子工作流各任务输出
+    SubWorkflow {
+        workflow: Box<Workflow>,
+        input: serde_json::Value,
+    },
 }
 
 /// 任务依赖类型
+#[derive(Clone, Serialize, Deserialize)]
 pub enum TaskDependencies {
     /// 无依赖，可立即执行
     None,
@@ -113,6 +236,11 @@ pub enum TaskDependencies {
     All(Vec<TaskId>),
     /// OR依赖：任一指定任务完成
     Any(Vec<TaskId>),
+    /// 法定数依赖：至少 `min` 个指定任务成功
+    Quorum {
+        task_ids: Vec<TaskId>,
+        min: usize,
+    },
     /// 条件依赖：前置任务满足条件
     Condition {
         task_id: TaskId,
@@ -120,6 +248,44 @@ pub enum TaskDependencies {
     },
 }
 
+/// 汇合策略：并行分支在何种情况下视为汇合成功
+///
+/// 分支之间错误隔离：单个分支失败不会中断其它分支，只影响汇合结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinStrategy {
+    /// 等待全部分支结束
+    WaitAll,
+    /// 任一分支成功即汇合
+    FirstSuccess,
+    /// 至少 N 个分支成功即汇合
+    Quorum(usize),
+}
+
+impl JoinStrategy {
+    /// 共 `total` 个分支时汇合所需的成功数
+    pub fn required(&self, total: usize) -> usize {
+        match self {
+            JoinStrategy::WaitAll => total,
+            JoinStrategy::FirstSuccess => 1,
+            JoinStrategy::Quorum(n) => *n,
+        }
+    }
+
+    /// 校验策略对 `total` 个分支是否可满足
+    pub fn validate(&self, total: usize) -> Result<(), String> {
+        if total == 0 {
+            return Err("join requires at least one branch".to_string());
+        }
+        match self {
+            JoinStrategy::Quorum(n) if *n == 0 || *n > total => {
+                Err(format!("quorum must be between 1 and {}, got {}", total, n))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 /// 条件谓词（可序列化的条件定义）
 #[derive(Clone, Serialize, Deserialize)]
 pub enum ConditionPredicate {
@@ -127,6 +293,31 @@ pub enum ConditionPredicate {
     Success,
     /// 任务返回结果包含指定文本
     ResultContains(String),
+    /// 表达式为真（对前置任务输出求值，语法见 `workflow::expr`）
+    Expression(String),
+}
+
+impl ConditionPredicate {
+    /// 前置任务 `task_id` 结束后判断条件是否满足；前置任务未成功时一律不满足
+    pub fn is_met(&self, task_id: &TaskId, state: TaskState, outputs: &HashMap<TaskId, serde_json::Value>) -> bool {
+        if state != TaskState::Completed {
+            return false;
+        }
+        match self {
+            ConditionPredicate::Success => true,
+            ConditionPredicate::ResultContains(text) => outputs.get(task_id).is_some_and(|output| match output {
+                serde_json::Value::String(s) => s.contains(text.as_str()),
+                other => other.to_string().contains(text.as_str()),
+            }),
+            ConditionPredicate::Expression(source) => match Expression::parse(source) {
+                Ok(expr) => expr.is_met(outputs),
+                Err(e) => {
+                    tracing::warn!("workflow condition '{}' is invalid: {}", source, e);
+                    false
+                }
+            },
+        }
+    }
 }
 
 /// 工作流错误类型
@@ -140,4 +331,8 @@ pub enum WorkflowError {
     CyclicDependency,
     #[error("Invalid workflow configuration: {0}")]
     InvalidConfiguration(String),
+    #[error("Invalid condition expression for task {task_id}: {message}")]
+    InvalidExpression { task_id: TaskId, message: String },
+    #[error("Workflow storage error: {0}")]
+    Storage(String),
 }